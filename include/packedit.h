@@ -0,0 +1,87 @@
+#ifndef PACKEDIT_H
+#define PACKEDIT_H
+
+/* Generated with cbindgen from src/ffi.rs, regenerate with `cbindgen --config cbindgen.toml --output include/packedit.h` */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Opaque handle of parsed Ethernet frame
+// IP packet and TCP or UDP segment are parsed when frame carries them, their payloads are kept only in the innermost layer
+typedef struct PackeditFrame PackeditFrame;
+
+// Parses Ethernet frame of `len` bytes
+// Returns null if `data` is not a valid frame, returned handle must be freed with `packedit_frame_free()`
+PackeditFrame *packedit_frame_parse(const uint8_t *data, size_t len);
+
+// Frees handle returned by `packedit_frame_parse()`, null is ignored
+void packedit_frame_free(PackeditFrame *frame);
+
+// Copies source MAC address into `out`, which must hold `6 bytes`
+bool packedit_frame_source_mac(const PackeditFrame *frame, uint8_t *out);
+
+// Copies destination MAC address into `out`, which must hold `6 bytes`
+bool packedit_frame_destination_mac(const PackeditFrame *frame, uint8_t *out);
+
+// Returns EtherType of frame, or 0 for null handle
+uint16_t packedit_frame_ether_type(const PackeditFrame *frame);
+
+// Returns IP version of carried packet, or 0 if frame doesn't carry IP packet
+uint8_t packedit_frame_ip_version(const PackeditFrame *frame);
+
+// Returns upper-layer protocol of carried IP packet, or 0 if frame doesn't carry IP packet
+uint8_t packedit_frame_ip_protocol(const PackeditFrame *frame);
+
+// Returns TTL of carried IPv4 packet or hop limit of IPv6 packet, or 0 if frame doesn't carry IP packet
+uint8_t packedit_frame_hop_limit(const PackeditFrame *frame);
+
+// Copies source IP address into `out` of `out_len` bytes
+// Returns address length, i.e. 4 or 16, or 0 if frame doesn't carry IP packet or `out` is too short
+size_t packedit_frame_ip_source(const PackeditFrame *frame, uint8_t *out, size_t out_len);
+
+// Copies destination IP address into `out` of `out_len` bytes
+// Returns address length, i.e. 4 or 16, or 0 if frame doesn't carry IP packet or `out` is too short
+size_t packedit_frame_ip_destination(const PackeditFrame *frame, uint8_t *out, size_t out_len);
+
+// Returns source port of carried TCP or UDP segment, or 0 if there is none
+uint16_t packedit_frame_source_port(const PackeditFrame *frame);
+
+// Returns destination port of carried TCP or UDP segment, or 0 if there is none
+uint16_t packedit_frame_destination_port(const PackeditFrame *frame);
+
+// Returns pointer to payload of the innermost parsed layer and writes its length into `len`
+// Pointer is valid until frame is edited or freed, returns null for null handle
+const uint8_t *packedit_frame_payload(PackeditFrame *frame, size_t *len);
+
+// Sets source MAC address from `6 bytes` of `mac`
+bool packedit_frame_set_source_mac(PackeditFrame *frame, const uint8_t *mac);
+
+// Sets destination MAC address from `6 bytes` of `mac`
+bool packedit_frame_set_destination_mac(PackeditFrame *frame, const uint8_t *mac);
+
+// Sets source IP address from `len` bytes of `address`, which must match IP version of carried packet
+bool packedit_frame_set_ip_source(PackeditFrame *frame, const uint8_t *address, size_t len);
+
+// Sets destination IP address from `len` bytes of `address`, which must match IP version of carried packet
+bool packedit_frame_set_ip_destination(PackeditFrame *frame, const uint8_t *address, size_t len);
+
+// Sets TTL of carried IPv4 packet or hop limit of IPv6 packet
+bool packedit_frame_set_hop_limit(PackeditFrame *frame, uint8_t hop_limit);
+
+// Sets source and destination ports of carried TCP or UDP segment
+bool packedit_frame_set_ports(PackeditFrame *frame, uint16_t source, uint16_t destination);
+
+// Replaces payload of the innermost parsed layer with `len` bytes of `data`
+bool packedit_frame_set_payload(PackeditFrame *frame, const uint8_t *data, size_t len);
+
+// Serializes frame into new buffer and writes its length into `len`
+// TCP, UDP and IPv4 header checksums are recalculated, returned buffer must be freed with `packedit_buffer_free()`
+uint8_t *packedit_frame_build(const PackeditFrame *frame, size_t *len);
+
+// Frees buffer of `len` bytes returned by `packedit_frame_build()`, null is ignored
+void packedit_buffer_free(uint8_t *buffer, size_t len);
+
+#endif  /* PACKEDIT_H */
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::pcap::{Direction, PacketEnvelope};
+
+/// Protocol of packet socket, which receives frames of all protocols
+const ETH_P_ALL: u16 = 0x0003;
+
+/// Linux `AF_PACKET` raw socket, which captures Ethernet frames of one or all interfaces
+/// Frames are received as `PacketEnvelope` with receive time, interface, direction and original length, using `recv()` or `Iterator` implementation
+/// Note that opening socket requires `CAP_NET_RAW` capability
+#[derive(Debug)]
+pub struct AfPacketSocket {
+    fd: OwnedFd,
+    buffer: Vec<u8>,
+    /// Names of interfaces by index, resolved on first frame from each of them
+    interface_names: HashMap<u32, Option<String>>
+}
+impl AfPacketSocket {
+    /// Opens socket capturing on `interface`, or on all interfaces if it is `None`
+    /// Frames longer than `snaplen` are truncated, their original length is kept in envelope
+    pub fn open(interface: Option<&str>, snaplen: usize) -> io::Result<Self> {
+        let fd = unsafe {libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, ETH_P_ALL.to_be() as i32)};
+        if fd < 0 {return Err(io::Error::last_os_error());}
+        let fd = unsafe {OwnedFd::from_raw_fd(fd)};
+        if let Some(interface) = interface {
+            let name = CString::new(interface).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "interface name contains zero byte"))?;
+            let index = unsafe {libc::if_nametoindex(name.as_ptr())};
+            if index == 0 {return Err(io::Error::last_os_error());}
+            let mut address: libc::sockaddr_ll = unsafe {core::mem::zeroed()};
+            address.sll_family = libc::AF_PACKET as u16;
+            address.sll_protocol = ETH_P_ALL.to_be();
+            address.sll_ifindex = index as i32;
+            let result = unsafe {
+                libc::bind(fd.as_raw_fd(), &address as *const libc::sockaddr_ll as *const libc::sockaddr, size_of::<libc::sockaddr_ll>() as u32)
+            };
+            if result < 0 {return Err(io::Error::last_os_error());}
+        }
+        Ok(Self {
+            fd,
+            buffer: vec![0u8; snaplen],
+            interface_names: HashMap::new()
+        })
+    }
+    /// Maximal length of captured frames
+    pub fn snaplen(&self) -> usize {
+        self.buffer.len()
+    }
+    /// Waits for the next frame and returns it with capture metadata
+    /// Timestamp is the time frame was received by this call, not by the kernel
+    pub fn recv(&mut self) -> io::Result<PacketEnvelope<Vec<u8>>> {
+        let mut address: libc::sockaddr_ll = unsafe {core::mem::zeroed()};
+        let mut address_len = size_of::<libc::sockaddr_ll>() as u32;
+        let length = unsafe {
+            libc::recvfrom(
+                self.fd.as_raw_fd(),
+                self.buffer.as_mut_ptr() as *mut libc::c_void,
+                self.buffer.len(),
+                libc::MSG_TRUNC,
+                &mut address as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                &mut address_len
+            )
+        };
+        if length < 0 {return Err(io::Error::last_os_error());}
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let original_length = length as usize;
+        let captured_length = original_length.min(self.buffer.len());
+        let interface_index = address.sll_ifindex as u32;
+        Ok(PacketEnvelope {
+            timestamp,
+            interface_index: Some(interface_index),
+            interface_name: self.interface_name(interface_index),
+            direction: Direction::from_packet_type(address.sll_pkttype as u16),
+            original_length: original_length as u32,
+            captured_length: captured_length as u32,
+            frame: self.buffer[..captured_length].to_vec()
+        })
+    }
+    fn interface_name(&mut self, index: u32) -> Option<String> {
+        self.interface_names.entry(index).or_insert_with(|| {
+            let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+            let result = unsafe {libc::if_indextoname(index, name.as_mut_ptr())};
+            if result.is_null() {return None;}
+            Some(unsafe {CStr::from_ptr(name.as_ptr())}.to_string_lossy().into_owned())
+        }).clone()
+    }
+}
+impl Iterator for AfPacketSocket {
+    type Item = io::Result<PacketEnvelope<Vec<u8>>>;
+    /// Receives frames with `recv()` forever
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.recv())
+    }
+}
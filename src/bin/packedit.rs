@@ -0,0 +1,202 @@
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+use packedit::description::{Description, DescriptionFormat};
+use packedit::l2::ethernet;
+use packedit::l2::sll::{LinuxSll2Frame, LinuxSllFrame};
+use packedit::l4::Port;
+use packedit::pcap::{self, PcapReader, PcapWriter};
+use packedit::stack::{Layer, PacketStack};
+use packedit::stats::Stats;
+use packedit::tshark::{TsharkFormat, TsharkWriter};
+use packedit::util::{self, Deserializable, DeserializeError, Serializable, format_hex};
+
+const USAGE: &str = "Usage:
+  packedit dissect [--ip] [FILE]   Dissects pcap file or hex dump with one packet per line, reads stdin if FILE is omitted
+                                   --ip: hex dump packets start with IP header instead of Ethernet header
+                                   --json, --ek: writes packets as tshark -T json or -T ek output instead
+  packedit stats [--ip] [FILE]     Prints packet counts by protocol and port and packet size distribution of pcap file or hex dump
+  packedit build [--pcap] FILE     Builds packet from JSON, YAML or TOML description and prints it as hex
+                                   --pcap: writes pcap file with built packet to stdout instead";
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, String> {
+    util::parse_hex(hex).ok_or(format!("invalid hex {}", hex))
+}
+
+fn error_message(error: DeserializeError) -> String {
+    match error {
+        DeserializeError::WrongDataLength => "wrong data length".to_string(),
+        DeserializeError::WrongData => "wrong data".to_string(),
+        DeserializeError::Incomplete {needed} => format!("incomplete data, {} more bytes needed", needed)
+    }
+}
+
+fn describe(layer: &Layer) -> String {
+    match layer {
+        Layer::Ethernet(frame) => format!("Ethernet {} > {} type 0x{:04x}", ethernet::format_mac(frame.source), ethernet::format_mac(frame.destination), frame.protocol),
+        Layer::Ipv4(packet) => format!("IPv4 {} > {} protocol {} ttl {} id {} checksum 0x{:04x}{}", packet.source, packet.destination, packet.protocol, packet.ttl, packet.id, packet.checksum, if packet.dont_fragment {" DF"} else {""}),
+        Layer::Ipv6(packet) => format!("IPv6 {} > {} next header {} hop limit {} flow label {}", packet.source, packet.destination, packet.next_header, packet.hop_limit, packet.flow_label),
+        Layer::Tcp(segment) => format!("TCP {:#} > {:#} [{}] seq {} ack {} window {} checksum 0x{:04x}", Port(segment.source), Port(segment.destination), segment.flags.letters(), segment.sequence_number, segment.acknowledgement_number, segment.window_size, segment.checksum),
+        Layer::Udp(datagram) => format!("UDP {:#} > {:#} checksum {}", Port(datagram.source), Port(datagram.destination), datagram.checksum.map(|checksum| format!("0x{:04x}", checksum)).unwrap_or("none".to_string())),
+        Layer::Icmp(packet) => format!("ICMP type {} code {} checksum 0x{:04x}", packet.icmp_type, packet.code, packet.checksum),
+        Layer::Icmpv6(packet) => format!("ICMPv6 type {} code {} checksum 0x{:04x}", packet.icmp_type, packet.code, packet.checksum)
+    }
+}
+
+fn print_stack(stack: &PacketStack) {
+    for layer in &stack.layers {
+        println!("  {}", describe(layer));
+    }
+    if !stack.payload.is_empty() {
+        println!("  Payload {} bytes: {}", stack.payload.len(), format_hex(&stack.payload));
+    }
+}
+
+/// Parses packet `data` of `link_type` into layers, Linux cooked capture header is printed if `describe_link` is set
+fn parse_bytes(data: &[u8], link_type: u32, describe_link: bool) -> Result<PacketStack, String> {
+    let stack = match link_type {
+        pcap::LINKTYPE_ETHERNET => PacketStack::from_ethernet(data),
+        pcap::LINKTYPE_RAW | pcap::LINKTYPE_IPV4 | pcap::LINKTYPE_IPV6 => PacketStack::from_ip(data),
+        pcap::LINKTYPE_LINUX_SLL => {
+            let frame = LinuxSllFrame::deserialize(data).map_err(error_message)?;
+            if describe_link {println!("  Linux SLL type 0x{:04x} protocol 0x{:04x}", frame.packet_type, frame.protocol);}
+            PacketStack::from_ip(&frame.payload)
+        }
+        pcap::LINKTYPE_LINUX_SLL2 => {
+            let frame = LinuxSll2Frame::deserialize(data).map_err(error_message)?;
+            if describe_link {println!("  Linux SLL2 interface {} type {} protocol 0x{:04x}", frame.interface_index, frame.packet_type, frame.protocol);}
+            PacketStack::from_ip(&frame.payload)
+        }
+        _ => return Err(format!("unsupported link type {}", link_type))
+    };
+    stack.map_err(error_message)
+}
+
+/// Prints packet `number` with `header` line, or writes it to `writer` in tshark format if it is given
+fn dissect_packet(number: usize, header: &str, timestamp: Duration, data: &[u8], link_type: u32, writer: Option<&mut TsharkWriter<io::StdoutLock>>) -> Result<(), String> {
+    match writer {
+        Some(writer) => match parse_bytes(data, link_type, false) {
+            Ok(stack) => writer.write_packet(timestamp, &stack).map_err(|error| error.to_string())?,
+            Err(error) => eprintln!("#{} {}", number, error)
+        },
+        None => {
+            println!("#{}{}", number, header);
+            match parse_bytes(data, link_type, true) {
+                Ok(stack) => print_stack(&stack),
+                Err(error) => println!("  {}", error)
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_input(path: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut input = Vec::new();
+    match path {
+        Some(path) => input = std::fs::read(path).map_err(|error| format!("can't read {}: {}", path, error))?,
+        None => {io::stdin().read_to_end(&mut input).map_err(|error| format!("can't read stdin: {}", error))?;}
+    }
+    Ok(input)
+}
+
+fn dissect(args: &[String]) -> Result<(), String> {
+    let ip = args.iter().any(|arg| arg == "--ip");
+    let format = if args.iter().any(|arg| arg == "--json") {Some(TsharkFormat::Json)}
+        else if args.iter().any(|arg| arg == "--ek") {Some(TsharkFormat::Ek)}
+        else {None};
+    let path = args.iter().find(|arg| !arg.starts_with("--")).map(String::as_str);
+    let input = read_input(path)?;
+    let mut writer = format.map(|format| TsharkWriter::new(io::stdout().lock(), format));
+    if pcap::is_pcap(&input) {
+        let reader = PcapReader::new(input.as_slice()).map_err(|error| error.to_string())?;
+        let link_type = reader.link_type;
+        for (i, record) in reader.enumerate() {
+            let record = record.map_err(|error| error.to_string())?;
+            let header = format!(" {}.{:09} {}/{} bytes", record.timestamp.as_secs(), record.timestamp.subsec_nanos(), record.data.len(), record.original_length);
+            dissect_packet(i + 1, &header, record.timestamp, &record.data, link_type, writer.as_mut())?;
+        }
+    }
+    else {
+        let text = String::from_utf8(input).map_err(|_| "input is neither pcap file nor hex dump".to_string())?;
+        let lines = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for (i, line) in lines.enumerate() {
+            let data = parse_hex(line)?;
+            dissect_packet(i + 1, "", Duration::ZERO, &data, if ip {pcap::LINKTYPE_RAW} else {pcap::LINKTYPE_ETHERNET}, writer.as_mut())?;
+        }
+    }
+    if let Some(writer) = writer {writer.finish().and_then(|mut stdout| stdout.flush()).map_err(|error| error.to_string())?;}
+    Ok(())
+}
+
+fn stats(args: &[String]) -> Result<(), String> {
+    let ip = args.iter().any(|arg| arg == "--ip");
+    let path = args.iter().find(|arg| !arg.starts_with("--")).map(String::as_str);
+    let input = read_input(path)?;
+    let mut stats = Stats::new();
+    let mut account = |data: &[u8], length: usize, link_type: u32| match parse_bytes(data, link_type, false) {
+        Ok(stack) => stats.record(&stack, length),
+        Err(_) => stats.record_malformed(length)
+    };
+    if pcap::is_pcap(&input) {
+        let reader = PcapReader::new(input.as_slice()).map_err(|error| error.to_string())?;
+        let link_type = reader.link_type;
+        for record in reader {
+            let record = record.map_err(|error| error.to_string())?;
+            account(&record.data, record.original_length as usize, link_type);
+        }
+    }
+    else {
+        let text = String::from_utf8(input).map_err(|_| "input is neither pcap file nor hex dump".to_string())?;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let data = parse_hex(line)?;
+            account(&data, data.len(), if ip {pcap::LINKTYPE_RAW} else {pcap::LINKTYPE_ETHERNET});
+        }
+    }
+    print!("{}", stats.snapshot());
+    Ok(())
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let write_pcap = args.iter().any(|arg| arg == "--pcap");
+    let path = args.iter().find(|arg| !arg.starts_with("--")).ok_or(USAGE.to_string())?;
+    let text = String::from_utf8(read_input(Some(path))?).map_err(|_| format!("{} is not UTF-8 text", path))?;
+    let description: Description = if path.ends_with(".toml") {
+        toml::from_str(&text).map_err(|error| error.to_string())?
+    }
+    else {
+        Description::parse(&text, DescriptionFormat::from_path(path).unwrap_or(DescriptionFormat::Json)).map_err(|error| error.to_string())?
+    };
+    let stack = description.into_stack().map_err(|error| error.to_string())?;
+    let link_type = match stack.layers.first() {
+        Some(Layer::Ethernet(_)) => pcap::LINKTYPE_ETHERNET,
+        _ => pcap::LINKTYPE_RAW
+    };
+    let bytes = stack.serialize();
+    if write_pcap {
+        let mut writer = PcapWriter::new(io::stdout().lock(), link_type).map_err(|error| error.to_string())?;
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        writer.write_packet(timestamp, &bytes).map_err(|error| error.to_string())?;
+        writer.into_inner().flush().map_err(|error| error.to_string())?;
+    }
+    else {
+        println!("{}", format_hex(&bytes));
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("dissect") => dissect(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("build") => build(&args[1..]),
+        _ => Err(USAGE.to_string())
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,148 @@
+use etherparse::{EtherType, Ethernet2Header, Icmpv4Header, Icmpv6Header, IpHeaders, Ipv4Header, Ipv6Header, LinkHeader, NetHeaders, PacketBuilderStep, PacketHeaders, TcpHeader, UdpHeader};
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::TcpSegment;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Deserializable, DeserializeError, Serializable};
+
+impl From<&EthernetFrame> for Ethernet2Header {
+    fn from(frame: &EthernetFrame) -> Self {
+        Self {
+            source: frame.source,
+            destination: frame.destination,
+            ether_type: EtherType(frame.protocol)
+        }
+    }
+}
+impl From<&Ethernet2Header> for EthernetFrame {
+    /// Constructs `EthernetFrame` with empty payload
+    fn from(header: &Ethernet2Header) -> Self {
+        Self {
+            destination: header.destination,
+            source: header.source,
+            protocol: header.ether_type.0,
+            payload: Vec::new()
+        }
+    }
+}
+impl TryFrom<&Ipv4Packet> for Ipv4Header {
+    type Error = DeserializeError;
+    /// Converts header of `packet`, total length accounts for its payload
+    fn try_from(packet: &Ipv4Packet) -> Result<Self, DeserializeError> {
+        let header = packet.serialize_header();
+        Ipv4Header::from_slice(&header).map(|(header, _)| header).map_err(|_| DeserializeError::WrongData)
+    }
+}
+impl TryFrom<&Ipv4Header> for Ipv4Packet {
+    type Error = DeserializeError;
+    /// Constructs `Ipv4Packet` with empty payload
+    fn try_from(header: &Ipv4Header) -> Result<Self, DeserializeError> {
+        Ipv4Packet::deserialize(&header.to_bytes())
+    }
+}
+impl TryFrom<&Ipv6Packet> for Ipv6Header {
+    type Error = DeserializeError;
+    /// Converts fixed header of `packet`, payload length accounts for its extension headers and payload
+    fn try_from(packet: &Ipv6Packet) -> Result<Self, DeserializeError> {
+        let bytes = packet.clone().serialize();
+        Ipv6Header::from_slice(&bytes).map(|(header, _)| header).map_err(|_| DeserializeError::WrongData)
+    }
+}
+impl TryFrom<&Ipv6Header> for Ipv6Packet {
+    type Error = DeserializeError;
+    /// Constructs `Ipv6Packet` with empty payload
+    /// Note that `next_header` is kept as it is, so extension headers following it have to be added separately
+    fn try_from(header: &Ipv6Header) -> Result<Self, DeserializeError> {
+        let mut bytes = header.to_bytes();
+        bytes[6] = 59;
+        let mut packet = Ipv6Packet::deserialize(&bytes)?;
+        packet.next_header = header.next_header.0.into();
+        Ok(packet)
+    }
+}
+impl TryFrom<&TcpSegment> for TcpHeader {
+    type Error = DeserializeError;
+    /// Converts header of `segment` with options, payload isn't converted
+    fn try_from(segment: &TcpSegment) -> Result<Self, DeserializeError> {
+        let bytes = segment.clone_header().serialize();
+        TcpHeader::from_slice(&bytes).map(|(header, _)| header).map_err(|_| DeserializeError::WrongData)
+    }
+}
+impl TryFrom<&TcpHeader> for TcpSegment {
+    type Error = DeserializeError;
+    /// Constructs `TcpSegment` with empty payload
+    fn try_from(header: &TcpHeader) -> Result<Self, DeserializeError> {
+        TcpSegment::deserialize(&header.to_bytes())
+    }
+}
+impl From<&UdpDatagram> for UdpHeader {
+    /// Converts header of `datagram`, length accounts for its payload
+    fn from(datagram: &UdpDatagram) -> Self {
+        Self {
+            source_port: datagram.source,
+            destination_port: datagram.destination,
+            length: 8 + datagram.payload.len() as u16,
+            checksum: datagram.checksum.filter(|_| !datagram.checksum_offloaded).unwrap_or(0)
+        }
+    }
+}
+impl From<&UdpHeader> for UdpDatagram {
+    /// Constructs `UdpDatagram` with empty payload
+    fn from(header: &UdpHeader) -> Self {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = header.source_port;
+        datagram.destination = header.destination_port;
+        datagram.checksum = if header.checksum == 0 {None} else {Some(header.checksum)};
+        datagram
+    }
+}
+
+/// Constructs `EthernetFrame` from headers and payload parsed by etherparse, e.g. to edit packet with packedit
+/// Headers are written as they are, so their length and checksum fields are kept
+/// Returns `Err(DeserializeError::WrongData)` if there is no Ethernet II header or headers can't be written
+pub fn frame_from_headers(headers: &PacketHeaders) -> Result<EthernetFrame, DeserializeError> {
+    let mut bytes = Vec::new();
+    match &headers.link {
+        Some(LinkHeader::Ethernet2(header)) => bytes.extend_from_slice(&header.to_bytes()),
+        _ => return Err(DeserializeError::WrongData)
+    }
+    if let Some(vlan) = &headers.vlan {
+        vlan.write(&mut bytes).map_err(|_| DeserializeError::WrongData)?;
+    }
+    match &headers.net {
+        Some(NetHeaders::Ipv4(header, extensions)) => IpHeaders::Ipv4(header.clone(), extensions.clone()).write(&mut bytes).map_err(|_| DeserializeError::WrongData)?,
+        Some(NetHeaders::Ipv6(header, extensions)) => IpHeaders::Ipv6(header.clone(), extensions.clone()).write(&mut bytes).map_err(|_| DeserializeError::WrongData)?,
+        None => {}
+    }
+    if let Some(transport) = &headers.transport {
+        transport.write(&mut bytes).map_err(|_| DeserializeError::WrongData)?;
+    }
+    bytes.extend_from_slice(headers.payload.slice());
+    EthernetFrame::deserialize(&bytes)
+}
+
+/// Final step of etherparse `PacketBuilder`, i.e. after `udp()`, `tcp()`, `icmpv4()` or `icmpv6()`
+pub trait FinalBuilderStep {
+    fn write_to(self, bytes: &mut Vec<u8>, payload: &[u8]) -> Result<(), DeserializeError>;
+}
+macro_rules! impl_final_builder_step {
+    ($($header:ty),*) => {
+        $(
+            impl FinalBuilderStep for PacketBuilderStep<$header> {
+                fn write_to(self, bytes: &mut Vec<u8>, payload: &[u8]) -> Result<(), DeserializeError> {
+                    self.write(bytes, payload).map_err(|_| DeserializeError::WrongData)
+                }
+            }
+        )*
+    };
+}
+impl_final_builder_step!(UdpHeader, TcpHeader, Icmpv4Header, Icmpv6Header);
+
+/// Builds packet with etherparse `builder` and `payload` and parses it as packedit packet `P`
+/// `P` has to match the first builder step, e.g. `EthernetFrame` for `PacketBuilder::ethernet2()` or `IpPacket` for `PacketBuilder::ip()`
+pub fn build<P: Deserializable, B: FinalBuilderStep>(builder: B, payload: &[u8]) -> Result<P, DeserializeError> {
+    let mut bytes = Vec::new();
+    builder.write_to(&mut bytes, payload)?;
+    P::deserialize(&bytes)
+}
\ No newline at end of file
@@ -0,0 +1,4 @@
+#[cfg(feature = "etherparse")]
+pub mod etherparse;
+#[cfg(feature = "smoltcp")]
+pub mod smoltcp;
\ No newline at end of file
@@ -0,0 +1,124 @@
+use smoltcp::wire;
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::arp::ArpPacket;
+use crate::l3::icmp::IcmpPacket;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::TcpSegment;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Deserializable, DeserializeError, Serializable};
+
+macro_rules! impl_wire_conversions {
+    ($($packet:ty => $wire:ident),*) => {
+        $(
+            impl<T: AsRef<[u8]>> TryFrom<&wire::$wire<T>> for $packet {
+                type Error = DeserializeError;
+                fn try_from(packet: &wire::$wire<T>) -> Result<Self, DeserializeError> {
+                    Self::deserialize(packet.as_ref())
+                }
+            }
+            impl From<$packet> for wire::$wire<Vec<u8>> {
+                /// Wraps serialized packet, length and checksum fields are written as they are
+                fn from(packet: $packet) -> Self {
+                    wire::$wire::new_unchecked(packet.serialize())
+                }
+            }
+        )*
+    };
+}
+impl_wire_conversions!(
+    EthernetFrame => EthernetFrame,
+    ArpPacket => ArpPacket,
+    Ipv4Packet => Ipv4Packet,
+    Ipv6Packet => Ipv6Packet,
+    IcmpPacket => Icmpv4Packet,
+    Icmpv6Packet => Icmpv6Packet,
+    TcpSegment => TcpPacket,
+    UdpDatagram => UdpPacket
+);
+
+impl From<&EthernetFrame> for wire::EthernetRepr {
+    fn from(frame: &EthernetFrame) -> Self {
+        Self {
+            src_addr: wire::EthernetAddress(frame.source),
+            dst_addr: wire::EthernetAddress(frame.destination),
+            ethertype: wire::EthernetProtocol::from(frame.protocol)
+        }
+    }
+}
+impl From<&wire::EthernetRepr> for EthernetFrame {
+    /// Constructs `EthernetFrame` with empty payload
+    fn from(repr: &wire::EthernetRepr) -> Self {
+        Self {
+            destination: repr.dst_addr.0,
+            source: repr.src_addr.0,
+            protocol: u16::from(repr.ethertype),
+            payload: Vec::new()
+        }
+    }
+}
+impl From<&Ipv4Packet> for wire::Ipv4Repr {
+    /// Note that smoltcp representation has no options, DSCP, ECN and fragmentation fields
+    fn from(packet: &Ipv4Packet) -> Self {
+        Self {
+            src_addr: packet.source,
+            dst_addr: packet.destination,
+            next_header: wire::IpProtocol::from(u8::from(packet.protocol)),
+            payload_len: packet.payload.len(),
+            hop_limit: packet.ttl
+        }
+    }
+}
+impl From<&wire::Ipv4Repr> for Ipv4Packet {
+    /// Constructs `Ipv4Packet` with empty payload, checksum isn't calculated
+    fn from(repr: &wire::Ipv4Repr) -> Self {
+        let mut packet = Ipv4Packet::new();
+        packet.source = repr.src_addr;
+        packet.destination = repr.dst_addr;
+        packet.protocol = u8::from(repr.next_header).into();
+        packet.ttl = repr.hop_limit;
+        packet
+    }
+}
+impl From<&Ipv6Packet> for wire::Ipv6Repr {
+    /// Payload length accounts for extension headers of `packet`
+    fn from(packet: &Ipv6Packet) -> Self {
+        Self {
+            src_addr: packet.source,
+            dst_addr: packet.destination,
+            next_header: wire::IpProtocol::from(u8::from(packet.next_header)),
+            payload_len: packet.wire_len() - 40,
+            hop_limit: packet.hop_limit
+        }
+    }
+}
+impl From<&wire::Ipv6Repr> for Ipv6Packet {
+    /// Constructs `Ipv6Packet` with empty payload
+    /// Note that `next_header` is kept as it is, so extension headers following it have to be added separately
+    fn from(repr: &wire::Ipv6Repr) -> Self {
+        let mut packet = Ipv6Packet::new();
+        packet.source = repr.src_addr;
+        packet.destination = repr.dst_addr;
+        packet.next_header = u8::from(repr.next_header).into();
+        packet.hop_limit = repr.hop_limit;
+        packet
+    }
+}
+impl From<&UdpDatagram> for wire::UdpRepr {
+    fn from(datagram: &UdpDatagram) -> Self {
+        Self {
+            src_port: datagram.source,
+            dst_port: datagram.destination
+        }
+    }
+}
+impl From<&wire::UdpRepr> for UdpDatagram {
+    /// Constructs `UdpDatagram` with empty payload
+    fn from(repr: &wire::UdpRepr) -> Self {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = repr.src_port;
+        datagram.destination = repr.dst_port;
+        datagram
+    }
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+use core::hash::Hasher;
+use core::time::Duration;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use crate::l2::sll::{LinuxSll2Frame, LinuxSllFrame};
+use crate::pcap::{self, PcapReader, PcapRecord, PcapWriter};
+use crate::stack::{Layer, PacketStack};
+use crate::util::{Deserializable, Serializable};
+
+/// Counts of packets processed by `Deduplicator::dedup_pcap()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    /// Packets written to deduplicated capture
+    pub packets: usize,
+    /// Duplicate packets which were dropped
+    pub duplicates: usize
+}
+
+/// Filter which drops duplicate frames, i.e. the same packet captured by several taps or SPAN ports
+/// Frames are compared by hash of normalized packet: TTL and hop limit are zeroed and checksums are recalculated, so packet seen on both sides of a router is still a duplicate
+/// Packets which can't be parsed are compared by hash of raw data
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    /// Frames with the same hash are duplicates if they are captured within this time after the first one
+    pub window: Duration,
+    /// Ignore Ethernet header, so frames with different MAC addresses are duplicates too
+    /// Linux cooked capture headers are always ignored, since they differ between interfaces
+    pub ignore_link_layer: bool,
+    /// Hashes of frames within window in order of capture
+    recent: VecDeque<(Duration, u64)>,
+    /// Capture time of the first frame with each hash within window
+    first_seen: HashMap<u64, Duration>
+}
+impl Deduplicator {
+    /// Constructs `Deduplicator` with `window`, which compares whole frames including Ethernet header
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            ignore_link_layer: false,
+            recent: VecDeque::new(),
+            first_seen: HashMap::new()
+        }
+    }
+    /// Returns hash of normalized packet `data` captured with `link_type`
+    pub fn normalized_hash(&self, data: &[u8], link_type: u32) -> u64 {
+        let stack = match link_type {
+            pcap::LINKTYPE_ETHERNET => PacketStack::from_ethernet(data).ok(),
+            pcap::LINKTYPE_RAW | pcap::LINKTYPE_IPV4 | pcap::LINKTYPE_IPV6 => PacketStack::from_ip(data).ok(),
+            pcap::LINKTYPE_LINUX_SLL => LinuxSllFrame::deserialize(data).ok().and_then(|frame| PacketStack::from_ip(&frame.payload).ok()),
+            pcap::LINKTYPE_LINUX_SLL2 => LinuxSll2Frame::deserialize(data).ok().and_then(|frame| PacketStack::from_ip(&frame.payload).ok()),
+            _ => None
+        };
+        let mut hasher = DefaultHasher::new();
+        match stack {
+            Some(stack) => hasher.write(&self.normalize(stack).serialize()),
+            None => hasher.write(data)
+        }
+        hasher.finish()
+    }
+    /// Zeroes TTL and hop limit of all IP layers and removes Ethernet layer if link layer is ignored
+    fn normalize(&self, mut stack: PacketStack) -> PacketStack {
+        if self.ignore_link_layer && matches!(stack.layers.first(), Some(Layer::Ethernet(_))) {stack.layers.remove(0);}
+        for layer in &mut stack.layers {
+            match layer {
+                Layer::Ipv4(packet) => packet.ttl = 0,
+                Layer::Ipv6(packet) => packet.hop_limit = 0,
+                _ => {}
+            }
+        }
+        stack
+    }
+    /// Checks if packet `data` captured with `link_type` at `timestamp` duplicates a previous one within window and remembers it otherwise
+    /// Packets are expected in order of capture, i.e. from merged capture
+    pub fn is_duplicate(&mut self, timestamp: Duration, data: &[u8], link_type: u32) -> bool {
+        while let Some((seen, hash)) = self.recent.front().copied() && timestamp.saturating_sub(seen) > self.window {
+            self.recent.pop_front();
+            if self.first_seen.get(&hash) == Some(&seen) {self.first_seen.remove(&hash);}
+        }
+        let hash = self.normalized_hash(data, link_type);
+        if self.first_seen.contains_key(&hash) {return true;}
+        self.first_seen.insert(hash, timestamp);
+        self.recent.push_back((timestamp, hash));
+        false
+    }
+    /// Forgets all remembered frames
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        self.first_seen.clear();
+    }
+    /// Checks if pcap `record` captured with `link_type` duplicates a previous one, the same as `is_duplicate()`
+    pub fn is_duplicate_record(&mut self, record: &PcapRecord, link_type: u32) -> bool {
+        self.is_duplicate(record.timestamp, &record.data, link_type)
+    }
+    /// Reads pcap file from `reader` and writes capture without duplicate frames with the same link type to `writer`
+    pub fn dedup_pcap<R: Read, W: Write>(&mut self, reader: R, writer: W) -> io::Result<DedupReport> {
+        let reader = PcapReader::new(reader)?;
+        let link_type = reader.link_type;
+        let mut writer = PcapWriter::new(writer, link_type)?;
+        let mut report = DedupReport::default();
+        for record in reader {
+            let record = record?;
+            if self.is_duplicate_record(&record, link_type) {
+                report.duplicates += 1;
+                continue;
+            }
+            writer.write_record(&record)?;
+            report.packets += 1;
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,318 @@
+use core::fmt;
+use core::net::{Ipv4Addr, Ipv6Addr};
+use serde::{Deserialize, Serialize};
+use crate::l2::ethernet::{self, EthernetFrame};
+use crate::l3::icmp::IcmpPacket;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::{TcpFlags, TcpSegment};
+use crate::l4::udp::UdpDatagram;
+use crate::stack::{Layer, PacketStack};
+use crate::util::{format_hex, parse_hex};
+
+/// Textual format of packet description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptionFormat {
+    Json,
+    Yaml
+}
+impl DescriptionFormat {
+    /// Picks format by extension of file `path`, i.e. `.json`, `.yaml` or `.yml`
+    /// Returns `None` for other extensions
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptionError {
+    /// Text isn't valid JSON or YAML, or doesn't match description structure
+    Syntax(String),
+    /// Field has value which can't be converted, i.e. malformed MAC address or hex payload
+    InvalidValue {
+        field: &'static str,
+        value: String
+    }
+}
+impl fmt::Display for DescriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(message) => write!(f, "{}", message),
+            Self::InvalidValue {field, value} => write!(f, "invalid {} {}", field, value)
+        }
+    }
+}
+
+/// Human-editable description of packet stack, so packet fixtures can be kept as JSON or YAML files instead of hex dumps
+/// Omitted fields keep values of `new()` constructors, lengths and checksums are calculated on serialization of stack, so they can't be set
+/// Protocol fields are filled from the next layer too, so setting them makes sense only for the last layer
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Description {
+    pub layers: Vec<LayerDescription>,
+    /// Payload after the last layer as hex string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    /// Payload after the last layer as text, appended to `payload`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_text: Option<String>
+}
+impl Description {
+    /// Describes all layers of `stack` with all fields set
+    /// Payload is described as hex, it is taken from the last layer if raw payload of `stack` is empty
+    /// Note that IPv4 options, IPv6 extension headers and TCP options aren't described
+    pub fn from_stack(stack: &PacketStack) -> Self {
+        let payload = match stack.layers.last() {
+            Some(layer) if stack.payload.is_empty() => layer.payload(),
+            _ => &stack.payload
+        };
+        Self {
+            layers: stack.layers.iter().map(LayerDescription::from_layer).collect(),
+            payload: if payload.is_empty() {None} else {Some(format_hex(payload))},
+            payload_text: None
+        }
+    }
+    /// Builds `PacketStack` from description
+    /// Returns `Err(DescriptionError::InvalidValue)` if MAC address, TCP flags or payload can't be parsed
+    pub fn into_stack(self) -> Result<PacketStack, DescriptionError> {
+        let mut stack = PacketStack::new();
+        for layer in self.layers {
+            stack.push(layer.into_layer()?);
+        }
+        if let Some(payload) = self.payload {stack.payload = parse_hex(&payload).ok_or(DescriptionError::InvalidValue {field: "payload", value: payload})?;}
+        if let Some(payload) = self.payload_text {stack.payload.extend_from_slice(payload.as_bytes());}
+        Ok(stack)
+    }
+    /// Parses description from `text` in `format`
+    pub fn parse(text: &str, format: DescriptionFormat) -> Result<Self, DescriptionError> {
+        match format {
+            DescriptionFormat::Json => serde_json::from_str(text).map_err(|error| DescriptionError::Syntax(error.to_string())),
+            DescriptionFormat::Yaml => serde_yaml::from_str(text).map_err(|error| DescriptionError::Syntax(error.to_string()))
+        }
+    }
+    /// Converts description to text in `format`, JSON is pretty-printed
+    pub fn to_text(&self, format: DescriptionFormat) -> String {
+        match format {
+            DescriptionFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+            DescriptionFormat::Yaml => serde_yaml::to_string(self).unwrap_or_default()
+        }
+    }
+}
+
+/// Description of one layer, tagged with `layer` field, i.e. `layer: tcp`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "layer", rename_all = "lowercase")]
+pub enum LayerDescription {
+    Ethernet {
+        /// MAC address in `aa:bb:cc:dd:ee:ff` form
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol: Option<u16>
+    },
+    Ipv4 {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<Ipv4Addr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<Ipv4Addr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dont_fragment: Option<bool>
+    },
+    Ipv6 {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<Ipv6Addr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<Ipv6Addr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        next_header: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hop_limit: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flow_label: Option<u32>
+    },
+    Tcp {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence_number: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        acknowledgement_number: Option<u32>,
+        /// Flags as letters, i.e. `SA` for SYN and ACK
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flags: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        window_size: Option<u16>
+    },
+    Udp {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<u16>
+    },
+    /// Echo Request by default, `id` and `seq` are the first and the second halves of rest of header
+    Icmp {
+        #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+        icmp_type: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seq: Option<u16>
+    },
+    /// Echo Request by default, `id` and `seq` are the first and the second halves of rest of header
+    Icmpv6 {
+        #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+        icmp_type: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seq: Option<u16>
+    }
+}
+impl LayerDescription {
+    /// Describes `layer` with all fields set
+    pub fn from_layer(layer: &Layer) -> Self {
+        match layer {
+            Layer::Ethernet(frame) => Self::Ethernet {
+                source: Some(ethernet::format_mac(frame.source)),
+                destination: Some(ethernet::format_mac(frame.destination)),
+                protocol: Some(frame.protocol)
+            },
+            Layer::Ipv4(packet) => Self::Ipv4 {
+                source: Some(packet.source),
+                destination: Some(packet.destination),
+                protocol: Some(packet.protocol.into()),
+                ttl: Some(packet.ttl),
+                id: Some(packet.id),
+                dont_fragment: Some(packet.dont_fragment)
+            },
+            Layer::Ipv6(packet) => Self::Ipv6 {
+                source: Some(packet.source),
+                destination: Some(packet.destination),
+                next_header: Some(packet.next_header.into()),
+                hop_limit: Some(packet.hop_limit),
+                flow_label: Some(packet.flow_label)
+            },
+            Layer::Tcp(segment) => Self::Tcp {
+                source: Some(segment.source),
+                destination: Some(segment.destination),
+                sequence_number: Some(segment.sequence_number),
+                acknowledgement_number: Some(segment.acknowledgement_number),
+                flags: Some(segment.flags.letters()),
+                window_size: Some(segment.window_size)
+            },
+            Layer::Udp(datagram) => Self::Udp {
+                source: Some(datagram.source),
+                destination: Some(datagram.destination)
+            },
+            Layer::Icmp(packet) => Self::Icmp {
+                icmp_type: Some(packet.icmp_type),
+                code: Some(packet.code),
+                id: Some(u16::from_be_bytes([packet.rest_of_header[0], packet.rest_of_header[1]])),
+                seq: Some(u16::from_be_bytes([packet.rest_of_header[2], packet.rest_of_header[3]]))
+            },
+            Layer::Icmpv6(packet) => Self::Icmpv6 {
+                icmp_type: Some(packet.icmp_type),
+                code: Some(packet.code),
+                id: Some(u16::from_be_bytes([packet.rest_of_header[0], packet.rest_of_header[1]])),
+                seq: Some(u16::from_be_bytes([packet.rest_of_header[2], packet.rest_of_header[3]]))
+            }
+        }
+    }
+    /// Constructs layer from description, omitted fields keep values of `new()` constructor, or of `Default` for IP packets, i.e. TTL 64
+    /// Returns `Err(DescriptionError::InvalidValue)` if MAC address or TCP flags can't be parsed
+    pub fn into_layer(self) -> Result<Layer, DescriptionError> {
+        Ok(match self {
+            Self::Ethernet {source, destination, protocol} => {
+                let mut frame = EthernetFrame::new();
+                if let Some(source) = source {frame.source = ethernet::parse_mac(&source).ok_or(DescriptionError::InvalidValue {field: "MAC address", value: source})?;}
+                if let Some(destination) = destination {frame.destination = ethernet::parse_mac(&destination).ok_or(DescriptionError::InvalidValue {field: "MAC address", value: destination})?;}
+                if let Some(protocol) = protocol {frame.protocol = protocol;}
+                Layer::Ethernet(frame)
+            }
+            Self::Ipv4 {source, destination, protocol, ttl, id, dont_fragment} => {
+                let mut packet = Ipv4Packet::default();
+                if let Some(source) = source {packet.source = source;}
+                if let Some(destination) = destination {packet.destination = destination;}
+                if let Some(protocol) = protocol {packet.protocol = protocol.into();}
+                if let Some(ttl) = ttl {packet.ttl = ttl;}
+                if let Some(id) = id {packet.id = id;}
+                if let Some(dont_fragment) = dont_fragment {packet.dont_fragment = dont_fragment;}
+                Layer::Ipv4(packet)
+            }
+            Self::Ipv6 {source, destination, next_header, hop_limit, flow_label} => {
+                let mut packet = Ipv6Packet::default();
+                if let Some(source) = source {packet.source = source;}
+                if let Some(destination) = destination {packet.destination = destination;}
+                if let Some(next_header) = next_header {packet.next_header = next_header.into();}
+                if let Some(hop_limit) = hop_limit {packet.hop_limit = hop_limit;}
+                if let Some(flow_label) = flow_label {packet.flow_label = flow_label;}
+                Layer::Ipv6(packet)
+            }
+            Self::Tcp {source, destination, sequence_number, acknowledgement_number, flags, window_size} => {
+                let mut segment = TcpSegment::new();
+                if let Some(source) = source {segment.source = source;}
+                if let Some(destination) = destination {segment.destination = destination;}
+                if let Some(sequence_number) = sequence_number {segment.sequence_number = sequence_number;}
+                if let Some(acknowledgement_number) = acknowledgement_number {segment.acknowledgement_number = acknowledgement_number;}
+                if let Some(flags) = flags {
+                    segment.flags = TcpFlags::from_letters(&flags).ok_or(DescriptionError::InvalidValue {field: "TCP flags", value: flags})?;
+                }
+                if let Some(window_size) = window_size {segment.window_size = window_size;}
+                Layer::Tcp(segment)
+            }
+            Self::Udp {source, destination} => {
+                let mut datagram = UdpDatagram::new();
+                if let Some(source) = source {datagram.source = source;}
+                if let Some(destination) = destination {datagram.destination = destination;}
+                Layer::Udp(datagram)
+            }
+            Self::Icmp {icmp_type, code, id, seq} => {
+                let mut packet = IcmpPacket::new();
+                packet.icmp_type = icmp_type.unwrap_or(8);
+                packet.code = code.unwrap_or(0);
+                packet.rest_of_header[0..2].copy_from_slice(&id.unwrap_or(0).to_be_bytes());
+                packet.rest_of_header[2..4].copy_from_slice(&seq.unwrap_or(0).to_be_bytes());
+                Layer::Icmp(packet)
+            }
+            Self::Icmpv6 {icmp_type, code, id, seq} => {
+                let mut packet = Icmpv6Packet::new();
+                packet.icmp_type = icmp_type.unwrap_or(128);
+                packet.code = code.unwrap_or(0);
+                packet.rest_of_header[0..2].copy_from_slice(&id.unwrap_or(0).to_be_bytes());
+                packet.rest_of_header[2..4].copy_from_slice(&seq.unwrap_or(0).to_be_bytes());
+                Layer::Icmpv6(packet)
+            }
+        })
+    }
+}
+
+impl PacketStack {
+    /// Builds stack from JSON or YAML description `text`, see `Description` for its structure
+    pub fn from_description(text: &str, format: DescriptionFormat) -> Result<Self, DescriptionError> {
+        Description::parse(text, format)?.into_stack()
+    }
+    /// Describes stack as JSON or YAML text, which `from_description()` builds the same packet from
+    /// Note that IPv4 options, IPv6 extension headers and TCP options aren't described
+    pub fn to_description(&self, format: DescriptionFormat) -> String {
+        Description::from_stack(self).to_text(format)
+    }
+}
\ No newline at end of file
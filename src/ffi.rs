@@ -0,0 +1,317 @@
+//! C-ABI functions for parsing and editing Ethernet frames, see `include/packedit.h`
+//! All pointers passed to these functions must be either null or valid for the given length,
+//! handles must come from `packedit_frame_parse()` and buffers from `packedit_frame_build()`
+//! Build shared library with `cargo rustc --lib --release --features ffi --crate-type cdylib`, or static one with `--crate-type staticlib`
+#![allow(clippy::missing_safety_doc)]
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::slice;
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::{IpPacket, IpProtocol};
+use crate::l4::tcp::TcpSegment;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Deserializable, Serializable};
+
+enum Transport {
+    Tcp(TcpSegment),
+    Udp(UdpDatagram)
+}
+
+/// Opaque handle of parsed Ethernet frame
+/// IP packet and TCP or UDP segment are parsed when frame carries them, their payloads are kept only in the innermost layer
+pub struct PackeditFrame {
+    ethernet: EthernetFrame,
+    ip: Option<IpPacket>,
+    transport: Option<Transport>
+}
+impl PackeditFrame {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut ethernet = EthernetFrame::deserialize(bytes).ok()?;
+        let mut ip = match ethernet.protocol {
+            0x0800 | 0x86DD => IpPacket::deserialize(&ethernet.payload).ok(),
+            _ => None
+        };
+        if ip.is_some() {ethernet.payload = Vec::new();}
+        let transport = match &mut ip {
+            Some(ip) => {
+                let transport = match ip.protocol() {
+                    IpProtocol::Tcp => TcpSegment::deserialize(ip.payload()).ok().map(Transport::Tcp),
+                    IpProtocol::Udp => UdpDatagram::deserialize(ip.payload()).ok().map(Transport::Udp),
+                    _ => None
+                };
+                if transport.is_some() {ip_payload_mut(ip).clear();}
+                transport
+            }
+            None => None
+        };
+        Some(Self {ethernet, ip, transport})
+    }
+    fn build(&self) -> Vec<u8> {
+        let mut ethernet = self.ethernet.clone();
+        if let Some(ip) = &self.ip {
+            let mut ip = ip.clone();
+            if let Some(transport) = &self.transport {
+                let payload = match transport {
+                    Transport::Tcp(segment) => {
+                        let mut segment = segment.clone();
+                        let _ = segment.recalculate_checksum(ip.source(), ip.destination());
+                        segment.serialize()
+                    }
+                    Transport::Udp(datagram) => {
+                        let mut datagram = datagram.clone();
+                        let _ = datagram.recalculate_checksum(ip.source(), ip.destination());
+                        datagram.serialize()
+                    }
+                };
+                *ip_payload_mut(&mut ip) = payload;
+            }
+            if let IpPacket::V4(packet) = &mut ip {packet.recalculate_checksum();}
+            ethernet.payload = ip.serialize();
+        }
+        ethernet.serialize()
+    }
+    fn payload_mut(&mut self) -> &mut Vec<u8> {
+        match (&mut self.transport, &mut self.ip) {
+            (Some(Transport::Tcp(segment)), _) => &mut segment.payload,
+            (Some(Transport::Udp(datagram)), _) => &mut datagram.payload,
+            (None, Some(ip)) => ip_payload_mut(ip),
+            (None, None) => &mut self.ethernet.payload
+        }
+    }
+}
+
+fn ip_payload_mut(packet: &mut IpPacket) -> &mut Vec<u8> {
+    match packet {
+        IpPacket::V4(packet) => &mut packet.payload,
+        IpPacket::V6(packet) => &mut packet.payload
+    }
+}
+
+unsafe fn frame<'a>(frame: *const PackeditFrame) -> Option<&'a PackeditFrame> {
+    unsafe {frame.as_ref()}
+}
+
+unsafe fn frame_mut<'a>(frame: *mut PackeditFrame) -> Option<&'a mut PackeditFrame> {
+    unsafe {frame.as_mut()}
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if data.is_null() {return if len == 0 {Some(&[])} else {None};}
+    Some(unsafe {slice::from_raw_parts(data, len)})
+}
+
+/// Parses Ethernet frame of `len` bytes
+/// Returns null if `data` is not a valid frame, returned handle must be freed with `packedit_frame_free()`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_parse(data: *const u8, len: usize) -> *mut PackeditFrame {
+    match unsafe {bytes(data, len)}.and_then(PackeditFrame::parse) {
+        Some(frame) => Box::into_raw(Box::new(frame)),
+        None => core::ptr::null_mut()
+    }
+}
+
+/// Frees handle returned by `packedit_frame_parse()`, null is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_free(frame: *mut PackeditFrame) {
+    if !frame.is_null() {drop(unsafe {Box::from_raw(frame)});}
+}
+
+/// Copies source MAC address into `out`, which must hold `6 bytes`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_source_mac(frame: *const PackeditFrame, out: *mut u8) -> bool {
+    let Some(frame) = (unsafe {self::frame(frame)}) else {return false;};
+    if out.is_null() {return false;}
+    unsafe {out.copy_from_nonoverlapping(frame.ethernet.source.as_ptr(), 6)};
+    true
+}
+
+/// Copies destination MAC address into `out`, which must hold `6 bytes`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_destination_mac(frame: *const PackeditFrame, out: *mut u8) -> bool {
+    let Some(frame) = (unsafe {self::frame(frame)}) else {return false;};
+    if out.is_null() {return false;}
+    unsafe {out.copy_from_nonoverlapping(frame.ethernet.destination.as_ptr(), 6)};
+    true
+}
+
+/// Returns EtherType of frame, or 0 for null handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_ether_type(frame: *const PackeditFrame) -> u16 {
+    unsafe {self::frame(frame)}.map(|frame| frame.ethernet.protocol).unwrap_or(0)
+}
+
+/// Returns IP version of carried packet, or 0 if frame doesn't carry IP packet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_ip_version(frame: *const PackeditFrame) -> u8 {
+    match unsafe {self::frame(frame)}.and_then(|frame| frame.ip.as_ref()) {
+        Some(IpPacket::V4(_)) => 4,
+        Some(IpPacket::V6(_)) => 6,
+        None => 0
+    }
+}
+
+/// Returns upper-layer protocol of carried IP packet, or 0 if frame doesn't carry IP packet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_ip_protocol(frame: *const PackeditFrame) -> u8 {
+    unsafe {self::frame(frame)}.and_then(|frame| frame.ip.as_ref()).map(|ip| ip.protocol().into()).unwrap_or(0)
+}
+
+/// Returns TTL of carried IPv4 packet or hop limit of IPv6 packet, or 0 if frame doesn't carry IP packet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_hop_limit(frame: *const PackeditFrame) -> u8 {
+    match unsafe {self::frame(frame)}.and_then(|frame| frame.ip.as_ref()) {
+        Some(IpPacket::V4(packet)) => packet.ttl,
+        Some(IpPacket::V6(packet)) => packet.hop_limit,
+        None => 0
+    }
+}
+
+fn copy_address(address: IpAddr, out: *mut u8, out_len: usize) -> usize {
+    let octets = match address {
+        IpAddr::V4(address) => address.octets().to_vec(),
+        IpAddr::V6(address) => address.octets().to_vec()
+    };
+    if out.is_null() || out_len < octets.len() {return 0;}
+    unsafe {out.copy_from_nonoverlapping(octets.as_ptr(), octets.len())};
+    octets.len()
+}
+
+/// Copies source IP address into `out` of `out_len` bytes
+/// Returns address length, i.e. 4 or 16, or 0 if frame doesn't carry IP packet or `out` is too short
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_ip_source(frame: *const PackeditFrame, out: *mut u8, out_len: usize) -> usize {
+    match unsafe {self::frame(frame)}.and_then(|frame| frame.ip.as_ref()) {
+        Some(ip) => copy_address(ip.source(), out, out_len),
+        None => 0
+    }
+}
+
+/// Copies destination IP address into `out` of `out_len` bytes
+/// Returns address length, i.e. 4 or 16, or 0 if frame doesn't carry IP packet or `out` is too short
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_ip_destination(frame: *const PackeditFrame, out: *mut u8, out_len: usize) -> usize {
+    match unsafe {self::frame(frame)}.and_then(|frame| frame.ip.as_ref()) {
+        Some(ip) => copy_address(ip.destination(), out, out_len),
+        None => 0
+    }
+}
+
+/// Returns source port of carried TCP or UDP segment, or 0 if there is none
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_source_port(frame: *const PackeditFrame) -> u16 {
+    match unsafe {self::frame(frame)}.and_then(|frame| frame.transport.as_ref()) {
+        Some(Transport::Tcp(segment)) => segment.source,
+        Some(Transport::Udp(datagram)) => datagram.source,
+        None => 0
+    }
+}
+
+/// Returns destination port of carried TCP or UDP segment, or 0 if there is none
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_destination_port(frame: *const PackeditFrame) -> u16 {
+    match unsafe {self::frame(frame)}.and_then(|frame| frame.transport.as_ref()) {
+        Some(Transport::Tcp(segment)) => segment.destination,
+        Some(Transport::Udp(datagram)) => datagram.destination,
+        None => 0
+    }
+}
+
+/// Returns pointer to payload of the innermost parsed layer and writes its length into `len`
+/// Pointer is valid until frame is edited or freed, returns null for null handle
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_payload(frame: *mut PackeditFrame, len: *mut usize) -> *const u8 {
+    let Some(frame) = (unsafe {frame_mut(frame)}) else {return core::ptr::null();};
+    let payload = frame.payload_mut();
+    if !len.is_null() {unsafe {*len = payload.len()};}
+    payload.as_ptr()
+}
+
+/// Sets source MAC address from `6 bytes` of `mac`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_source_mac(frame: *mut PackeditFrame, mac: *const u8) -> bool {
+    let (Some(frame), Some(mac)) = (unsafe {frame_mut(frame)}, unsafe {bytes(mac, 6)}) else {return false;};
+    frame.ethernet.source.copy_from_slice(mac);
+    true
+}
+
+/// Sets destination MAC address from `6 bytes` of `mac`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_destination_mac(frame: *mut PackeditFrame, mac: *const u8) -> bool {
+    let (Some(frame), Some(mac)) = (unsafe {frame_mut(frame)}, unsafe {bytes(mac, 6)}) else {return false;};
+    frame.ethernet.destination.copy_from_slice(mac);
+    true
+}
+
+fn set_address(packet: &mut IpPacket, address: &[u8], source: bool) -> bool {
+    match packet {
+        IpPacket::V4(packet) => {
+            let Some(address) = address.as_array().map(|octets| Ipv4Addr::from_octets(*octets)) else {return false;};
+            if source {packet.source = address;} else {packet.destination = address;}
+        }
+        IpPacket::V6(packet) => {
+            let Some(address) = address.as_array().map(|octets| Ipv6Addr::from_octets(*octets)) else {return false;};
+            if source {packet.source = address;} else {packet.destination = address;}
+        }
+    }
+    true
+}
+
+/// Sets source IP address from `len` bytes of `address`, which must match IP version of carried packet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_ip_source(frame: *mut PackeditFrame, address: *const u8, len: usize) -> bool {
+    let (Some(frame), Some(address)) = (unsafe {frame_mut(frame)}, unsafe {bytes(address, len)}) else {return false;};
+    frame.ip.as_mut().is_some_and(|ip| set_address(ip, address, true))
+}
+
+/// Sets destination IP address from `len` bytes of `address`, which must match IP version of carried packet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_ip_destination(frame: *mut PackeditFrame, address: *const u8, len: usize) -> bool {
+    let (Some(frame), Some(address)) = (unsafe {frame_mut(frame)}, unsafe {bytes(address, len)}) else {return false;};
+    frame.ip.as_mut().is_some_and(|ip| set_address(ip, address, false))
+}
+
+/// Sets TTL of carried IPv4 packet or hop limit of IPv6 packet
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_hop_limit(frame: *mut PackeditFrame, hop_limit: u8) -> bool {
+    match unsafe {frame_mut(frame)}.and_then(|frame| frame.ip.as_mut()) {
+        Some(IpPacket::V4(packet)) => packet.ttl = hop_limit,
+        Some(IpPacket::V6(packet)) => packet.hop_limit = hop_limit,
+        None => return false
+    }
+    true
+}
+
+/// Sets source and destination ports of carried TCP or UDP segment
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_ports(frame: *mut PackeditFrame, source: u16, destination: u16) -> bool {
+    match unsafe {frame_mut(frame)}.and_then(|frame| frame.transport.as_mut()) {
+        Some(Transport::Tcp(segment)) => (segment.source, segment.destination) = (source, destination),
+        Some(Transport::Udp(datagram)) => (datagram.source, datagram.destination) = (source, destination),
+        None => return false
+    }
+    true
+}
+
+/// Replaces payload of the innermost parsed layer with `len` bytes of `data`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_set_payload(frame: *mut PackeditFrame, data: *const u8, len: usize) -> bool {
+    let (Some(frame), Some(data)) = (unsafe {frame_mut(frame)}, unsafe {bytes(data, len)}) else {return false;};
+    *frame.payload_mut() = data.to_vec();
+    true
+}
+
+/// Serializes frame into new buffer and writes its length into `len`
+/// TCP, UDP and IPv4 header checksums are recalculated, returned buffer must be freed with `packedit_buffer_free()`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_frame_build(frame: *const PackeditFrame, len: *mut usize) -> *mut u8 {
+    let Some(frame) = (unsafe {self::frame(frame)}) else {return core::ptr::null_mut();};
+    if len.is_null() {return core::ptr::null_mut();}
+    let bytes = frame.build().into_boxed_slice();
+    unsafe {*len = bytes.len()};
+    Box::into_raw(bytes) as *mut u8
+}
+
+/// Frees buffer of `len` bytes returned by `packedit_frame_build()`, null is ignored
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn packedit_buffer_free(buffer: *mut u8, len: usize) {
+    if !buffer.is_null() {drop(unsafe {Box::from_raw(core::ptr::slice_from_raw_parts_mut(buffer, len))});}
+}
\ No newline at end of file
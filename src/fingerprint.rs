@@ -0,0 +1,502 @@
+use core::fmt;
+use crate::l3::{EcnType, IpProtocol};
+use crate::l3::ipv6::{Ipv6ExtensionHeader, Ipv6Packet};
+use crate::util::DeserializableRef;
+
+/// Maximal distance in hops between host and observer, TTL further below initial one doesn't match signature
+pub const MAX_DISTANCE: u8 = 35;
+
+/// One element of TCP option layout, as written in p0f signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptionLayout {
+    /// `eol+N` - End of Option List followed by `N` bytes of padding
+    Eol(u8),
+    /// `nop` - No-Operation
+    Nop,
+    /// `mss` - Maximum Segment Size
+    Mss,
+    /// `ws` - Window Scale
+    WindowScale,
+    /// `sok` - SACK Permitted
+    SackPermitted,
+    /// `sack` - SACK
+    Sack,
+    /// `ts` - Timestamps
+    Timestamp,
+    /// `?N` - other option of kind `N`
+    Other(u8)
+}
+impl OptionLayout {
+    /// Parses layout element written as in p0f signatures, i.e. `mss` or `eol+2`
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "nop" => Some(Self::Nop),
+            "mss" => Some(Self::Mss),
+            "ws" => Some(Self::WindowScale),
+            "sok" => Some(Self::SackPermitted),
+            "sack" => Some(Self::Sack),
+            "ts" => Some(Self::Timestamp),
+            _ => match (text.strip_prefix("eol+"), text.strip_prefix('?')) {
+                (Some(padding), _) => padding.parse().ok().map(Self::Eol),
+                (_, Some(kind)) => kind.parse().ok().map(Self::Other),
+                _ => None
+            }
+        }
+    }
+}
+impl fmt::Display for OptionLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eol(padding) => write!(f, "eol+{}", padding),
+            Self::Nop => write!(f, "nop"),
+            Self::Mss => write!(f, "mss"),
+            Self::WindowScale => write!(f, "ws"),
+            Self::SackPermitted => write!(f, "sok"),
+            Self::Sack => write!(f, "sack"),
+            Self::Timestamp => write!(f, "ts"),
+            Self::Other(kind) => write!(f, "?{}", kind)
+        }
+    }
+}
+
+/// Peculiarity of IP or TCP header, named as in p0f signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Quirk {
+    /// `df` - Don't Fragment is set
+    DontFragment,
+    /// `id+` - Don't Fragment is set, but IP ID is not zero
+    NonZeroId,
+    /// `id-` - Don't Fragment is not set, but IP ID is zero
+    ZeroId,
+    /// `ecn` - ECN bits of IP header or ECE and CWR flags are set
+    Ecn,
+    /// `0+` - reserved bit of IPv4 flags is set
+    MustBeZero,
+    /// `flow` - IPv6 flow label is not zero
+    FlowLabel,
+    /// `seq-` - sequence number is zero
+    ZeroSequence,
+    /// `ack+` - acknowledgement number is not zero, but ACK flag is not set
+    NonZeroAck,
+    /// `ack-` - acknowledgement number is zero, but ACK flag is set
+    ZeroAck,
+    /// `uptr+` - urgent pointer is not zero, but URG flag is not set
+    NonZeroUrgentPointer,
+    /// `urgf+` - URG flag is set
+    Urgent,
+    /// `pushf+` - PSH flag is set
+    Push,
+    /// `ts1-` - own timestamp is zero
+    ZeroTimestamp,
+    /// `ts2+` - echoed timestamp is not zero in initial SYN
+    NonZeroEchoTimestamp,
+    /// `opt+` - non-zero bytes after End of Option List
+    TrailingOptionData,
+    /// `exws` - window scale exceeds 14
+    ExcessiveWindowScale,
+    /// `bad` - malformed TCP options
+    MalformedOptions
+}
+impl Quirk {
+    const NAMES: [(Self, &str); 17] = [
+        (Self::DontFragment, "df"),
+        (Self::NonZeroId, "id+"),
+        (Self::ZeroId, "id-"),
+        (Self::Ecn, "ecn"),
+        (Self::MustBeZero, "0+"),
+        (Self::FlowLabel, "flow"),
+        (Self::ZeroSequence, "seq-"),
+        (Self::NonZeroAck, "ack+"),
+        (Self::ZeroAck, "ack-"),
+        (Self::NonZeroUrgentPointer, "uptr+"),
+        (Self::Urgent, "urgf+"),
+        (Self::Push, "pushf+"),
+        (Self::ZeroTimestamp, "ts1-"),
+        (Self::NonZeroEchoTimestamp, "ts2+"),
+        (Self::TrailingOptionData, "opt+"),
+        (Self::ExcessiveWindowScale, "exws"),
+        (Self::MalformedOptions, "bad")
+    ];
+    /// Parses quirk written as in p0f signatures, i.e. `id+`
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::NAMES.iter().find(|(_, name)| *name == text).map(|(quirk, _)| *quirk)
+    }
+    /// Returns name of quirk in p0f signatures
+    pub fn name(&self) -> &'static str {
+        Self::NAMES.iter().find(|(quirk, _)| quirk == self).map(|(_, name)| *name).unwrap_or("")
+    }
+}
+impl fmt::Display for Quirk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Features of TCP SYN or SYN+ACK packet, which identify operating system of its sender
+/// You can extract it with `SynFingerprint::from_ip()` and find matching signature with `SignatureSet::find()`
+/// Formats as p0f signature with guessed initial TTL, so it can be added to signature set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynFingerprint {
+    /// 4 or 6
+    pub ip_version: u8,
+    /// Packet is SYN+ACK, i.e. response of server
+    pub response: bool,
+    /// Observed TTL or hop limit
+    pub ttl: u8,
+    /// Length of IPv4 options, always 0 for IPv6
+    pub ip_options_len: u8,
+    pub mss: Option<u16>,
+    pub window_size: u16,
+    pub window_scale: Option<u8>,
+    /// TCP options in order of appearance, including padding
+    pub options: Vec<OptionLayout>,
+    /// Quirks in order of `Quirk` variants
+    pub quirks: Vec<Quirk>,
+    /// Segment carries payload
+    pub has_payload: bool
+}
+impl SynFingerprint {
+    /// Extracts fingerprint from raw IPv4 or IPv6 packet `bytes` carrying TCP SYN or SYN+ACK segment
+    /// Raw bytes are needed, since parsed `TcpSegment` doesn't keep No-Operation options, which are part of option layout
+    /// Returns `None` if packet is malformed, is a fragment, or doesn't carry SYN segment
+    pub fn from_ip(bytes: &[u8]) -> Option<Self> {
+        let mut quirks = Vec::new();
+        let (ip_version, ttl, ip_options_len, segment) = match bytes.first()? >> 4 {
+            4 => {
+                if bytes.len() < 20 || bytes[9] != 6 {return None;}
+                let header_len = (bytes[0] & 15) as usize * 4;
+                let total_len = (u16::from_be_bytes([bytes[2], bytes[3]]) as usize).min(bytes.len());
+                if header_len < 20 || total_len < header_len {return None;}
+                let id = u16::from_be_bytes([bytes[4], bytes[5]]);
+                let flags = bytes[6];
+                if flags & 0x20 != 0 || u16::from_be_bytes([flags & 0x1F, bytes[7]]) != 0 {return None;}
+                let dont_fragment = flags & 0x40 != 0;
+                if dont_fragment {quirks.push(Quirk::DontFragment);}
+                if dont_fragment && id != 0 {quirks.push(Quirk::NonZeroId);}
+                if !dont_fragment && id == 0 {quirks.push(Quirk::ZeroId);}
+                if bytes[1] & 3 != 0 {quirks.push(Quirk::Ecn);}
+                if flags & 0x80 != 0 {quirks.push(Quirk::MustBeZero);}
+                (4, bytes[8], (header_len - 20) as u8, &bytes[header_len..total_len])
+            }
+            6 => {
+                let packet = Ipv6Packet::deserialize_ref(bytes).ok()?;
+                let (packet, segment) = (packet.header, packet.payload);
+                let protocol = IpProtocol::from(packet.extension_headers.last().map(|header| header.get_next_header_type()).unwrap_or(packet.next_header));
+                if protocol != IpProtocol::Tcp {return None;}
+                if packet.extension_headers.iter().any(|header| matches!(header, Ipv6ExtensionHeader::Fragment {..})) {return None;}
+                if !matches!(packet.ecn, EcnType::NotECT) {quirks.push(Quirk::Ecn);}
+                if packet.flow_label != 0 {quirks.push(Quirk::FlowLabel);}
+                (6, packet.hop_limit, 0, segment)
+            }
+            _ => return None
+        };
+        if segment.len() < 20 {return None;}
+        let flags = segment[13];
+        let (syn, ack) = (flags & 0x02 != 0, flags & 0x10 != 0);
+        if !syn || flags & 0x04 != 0 {return None;}
+        let data_offset = (segment[12] >> 4) as usize * 4;
+        if data_offset < 20 || data_offset > segment.len() {return None;}
+        if flags & 0xC0 != 0 && !quirks.contains(&Quirk::Ecn) {quirks.push(Quirk::Ecn);}
+        if segment[4..8] == [0; 4] {quirks.push(Quirk::ZeroSequence);}
+        let acknowledgement_number = u32::from_be_bytes([segment[8], segment[9], segment[10], segment[11]]);
+        if !ack && acknowledgement_number != 0 {quirks.push(Quirk::NonZeroAck);}
+        if ack && acknowledgement_number == 0 {quirks.push(Quirk::ZeroAck);}
+        if flags & 0x20 == 0 && segment[18..20] != [0; 2] {quirks.push(Quirk::NonZeroUrgentPointer);}
+        if flags & 0x20 != 0 {quirks.push(Quirk::Urgent);}
+        if flags & 0x08 != 0 {quirks.push(Quirk::Push);}
+        let mut fingerprint = Self {
+            ip_version,
+            response: ack,
+            ttl,
+            ip_options_len,
+            mss: None,
+            window_size: u16::from_be_bytes([segment[14], segment[15]]),
+            window_scale: None,
+            options: Vec::new(),
+            quirks,
+            has_payload: segment.len() > data_offset
+        };
+        fingerprint.parse_options(&segment[20..data_offset]);
+        fingerprint.quirks.sort();
+        Some(fingerprint)
+    }
+    /// Walks raw TCP option area, filling option layout, MSS, window scale and option quirks
+    fn parse_options(&mut self, options: &[u8]) {
+        let mut position = 0;
+        while position < options.len() {
+            let kind = options[position];
+            match kind {
+                0 => {
+                    let padding = &options[position + 1..];
+                    self.options.push(OptionLayout::Eol(padding.len() as u8));
+                    if padding.iter().any(|byte| *byte != 0) {self.quirks.push(Quirk::TrailingOptionData);}
+                    return;
+                }
+                1 => {
+                    self.options.push(OptionLayout::Nop);
+                    position += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let length = options.get(position + 1).copied().unwrap_or(0) as usize;
+            if length < 2 || position + length > options.len() {
+                self.quirks.push(Quirk::MalformedOptions);
+                return;
+            }
+            let data = &options[position + 2..position + length];
+            let (layout, valid) = match kind {
+                2 => {
+                    if let [high, low] = data {self.mss = Some(u16::from_be_bytes([*high, *low]));}
+                    (OptionLayout::Mss, data.len() == 2)
+                }
+                3 => {
+                    if let [shift] = data {
+                        self.window_scale = Some(*shift);
+                        if *shift > 14 {self.quirks.push(Quirk::ExcessiveWindowScale);}
+                    }
+                    (OptionLayout::WindowScale, data.len() == 1)
+                }
+                4 => (OptionLayout::SackPermitted, data.is_empty()),
+                5 => (OptionLayout::Sack, data.len().is_multiple_of(8) && !data.is_empty()),
+                8 => {
+                    if data.len() == 8 {
+                        if data[..4] == [0; 4] {self.quirks.push(Quirk::ZeroTimestamp);}
+                        if !self.response && data[4..] != [0; 4] {self.quirks.push(Quirk::NonZeroEchoTimestamp);}
+                    }
+                    (OptionLayout::Timestamp, data.len() == 8)
+                }
+                kind => (OptionLayout::Other(kind), true)
+            };
+            self.options.push(layout);
+            if !valid {self.quirks.push(Quirk::MalformedOptions);}
+            position += length;
+        }
+    }
+    /// Guesses initial TTL of sender, which is the nearest of common defaults 32, 64, 128 and 255 not below observed TTL
+    pub fn initial_ttl(&self) -> u8 {
+        [32, 64, 128].into_iter().find(|ttl| self.ttl <= *ttl).unwrap_or(255)
+    }
+    /// Returns guessed distance to sender in hops
+    pub fn distance(&self) -> u8 {
+        self.initial_ttl() - self.ttl
+    }
+}
+impl fmt::Display for SynFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:", self.ip_version, self.initial_ttl(), self.ip_options_len)?;
+        match self.mss {
+            Some(mss) => write!(f, "{}:", mss)?,
+            None => write!(f, "*:")?
+        }
+        write!(f, "{},{}:", self.window_size, self.window_scale.unwrap_or(0))?;
+        write_list(f, &self.options)?;
+        write!(f, ":")?;
+        write_list(f, &self.quirks)?;
+        write!(f, ":{}", if self.has_payload {"+"} else {"0"})
+    }
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {write!(f, ",")?;}
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Window size of signature, as written in p0f signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSize {
+    /// `*` - any window size
+    Any,
+    /// `N` - exactly `N`
+    Exact(u16),
+    /// `mss*N` - `N` times MSS
+    MssMultiple(u16),
+    /// `mtu*N` - `N` times MTU, which is MSS with `40 bytes` of IPv4 and TCP headers or `60 bytes` of IPv6 and TCP headers
+    MtuMultiple(u16),
+    /// `%N` - multiple of `N`
+    Modulo(u16)
+}
+impl WindowSize {
+    fn parse(text: &str) -> Option<Self> {
+        if text == "*" {return Some(Self::Any);}
+        if let Some(multiplier) = text.strip_prefix("mss*") {return multiplier.parse().ok().map(Self::MssMultiple);}
+        if let Some(multiplier) = text.strip_prefix("mtu*") {return multiplier.parse().ok().map(Self::MtuMultiple);}
+        if let Some(divisor) = text.strip_prefix('%') {return divisor.parse().ok().filter(|divisor| *divisor != 0).map(Self::Modulo);}
+        text.parse().ok().map(Self::Exact)
+    }
+    /// Checks if window size of `fingerprint` matches
+    pub fn matches(&self, fingerprint: &SynFingerprint) -> bool {
+        let window_size = fingerprint.window_size as u32;
+        let headers_len = if fingerprint.ip_version == 4 {40} else {60};
+        match *self {
+            Self::Any => true,
+            Self::Exact(size) => window_size == size as u32,
+            Self::MssMultiple(multiplier) => fingerprint.mss.is_some_and(|mss| window_size == mss as u32 * multiplier as u32),
+            Self::MtuMultiple(multiplier) => fingerprint.mss.is_some_and(|mss| window_size == (mss as u32 + headers_len) * multiplier as u32),
+            Self::Modulo(divisor) => window_size.is_multiple_of(divisor as u32)
+        }
+    }
+}
+impl fmt::Display for WindowSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "*"),
+            Self::Exact(size) => write!(f, "{}", size),
+            Self::MssMultiple(multiplier) => write!(f, "mss*{}", multiplier),
+            Self::MtuMultiple(multiplier) => write!(f, "mtu*{}", multiplier),
+            Self::Modulo(divisor) => write!(f, "%{}", divisor)
+        }
+    }
+}
+
+/// TCP signature of operating system in p0f format `ver:ittl:olen:mss:wsize,scale:olayout:quirks:pclass`, i.e. `4:64:0:*:mss*20,7:mss,sok,ts,nop,ws:df,id+:0`
+/// Fields written as `*` match anything and are `None`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Label of signature, i.e. `s:unix:Linux:3.11 and newer`
+    pub label: String,
+    /// Signature describes SYN+ACK packets
+    pub response: bool,
+    pub ip_version: Option<u8>,
+    pub initial_ttl: u8,
+    pub ip_options_len: u8,
+    pub mss: Option<u16>,
+    pub window_size: WindowSize,
+    /// Window scale, where 0 also matches packets without Window Scale option
+    pub window_scale: Option<u8>,
+    pub options: Vec<OptionLayout>,
+    pub quirks: Vec<Quirk>,
+    pub has_payload: Option<bool>
+}
+impl Signature {
+    /// Parses signature for SYN packets from p0f format `text`, labeled with `label`
+    /// Returns `None` if signature is malformed
+    pub fn parse(label: &str, text: &str) -> Option<Self> {
+        let fields: Vec<&str> = text.trim().split(':').collect();
+        let [ip_version, initial_ttl, ip_options_len, mss, window, options, quirks, has_payload] = fields[..] else {return None;};
+        let (window_size, window_scale) = window.split_once(',')?;
+        let any = |field: &str| field == "*";
+        let mut quirks = match quirks {
+            "" => Vec::new(),
+            _ => quirks.split(',').map(Quirk::parse).collect::<Option<Vec<_>>>()?
+        };
+        quirks.sort();
+        Some(Self {
+            label: label.to_string(),
+            response: false,
+            ip_version: if any(ip_version) {None} else {Some(ip_version.parse().ok().filter(|version| matches!(version, 4 | 6))?)},
+            initial_ttl: initial_ttl.split(['+', '-']).next()?.parse().ok()?,
+            ip_options_len: ip_options_len.parse().ok()?,
+            mss: if any(mss) {None} else {Some(mss.parse().ok()?)},
+            window_size: WindowSize::parse(window_size)?,
+            window_scale: if any(window_scale) {None} else {Some(window_scale.parse().ok()?)},
+            options: match options {
+                "" => Vec::new(),
+                _ => options.split(',').map(OptionLayout::parse).collect::<Option<Vec<_>>>()?
+            },
+            quirks,
+            has_payload: match has_payload {
+                "*" => None,
+                "0" => Some(false),
+                "+" => Some(true),
+                _ => return None
+            }
+        })
+    }
+    /// Checks if `fingerprint` matches this signature
+    /// TTL matches if it's below initial TTL by at most `MAX_DISTANCE` hops
+    pub fn matches(&self, fingerprint: &SynFingerprint) -> bool {
+        self.response == fingerprint.response
+            && self.ip_version.is_none_or(|version| version == fingerprint.ip_version)
+            && fingerprint.ttl <= self.initial_ttl && self.initial_ttl - fingerprint.ttl <= MAX_DISTANCE
+            && self.ip_options_len == fingerprint.ip_options_len
+            && self.mss.is_none_or(|mss| fingerprint.mss == Some(mss))
+            && self.window_size.matches(fingerprint)
+            && self.window_scale.is_none_or(|scale| fingerprint.window_scale.unwrap_or(0) == scale)
+            && self.options == fingerprint.options
+            && self.quirks == fingerprint.quirks
+            && self.has_payload.is_none_or(|has_payload| has_payload == fingerprint.has_payload)
+    }
+}
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ip_version {
+            Some(version) => write!(f, "{}:", version)?,
+            None => write!(f, "*:")?
+        }
+        write!(f, "{}:{}:", self.initial_ttl, self.ip_options_len)?;
+        match self.mss {
+            Some(mss) => write!(f, "{}:", mss)?,
+            None => write!(f, "*:")?
+        }
+        match self.window_scale {
+            Some(scale) => write!(f, "{},{}:", self.window_size, scale)?,
+            None => write!(f, "{},*:", self.window_size)?
+        }
+        write_list(f, &self.options)?;
+        write!(f, ":")?;
+        write_list(f, &self.quirks)?;
+        match self.has_payload {
+            Some(true) => write!(f, ":+"),
+            Some(false) => write!(f, ":0"),
+            None => write!(f, ":*")
+        }
+    }
+}
+
+/// Line of p0f signature file, which can't be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureFileError {
+    /// Line number starting from 1
+    pub line: usize
+}
+
+/// Set of signatures, searched in order they were added
+#[derive(Debug, Clone, Default)]
+pub struct SignatureSet {
+    pub signatures: Vec<Signature>
+}
+impl SignatureSet {
+    /// Constructs an empty `SignatureSet`
+    pub fn new() -> Self {
+        Self {signatures: Vec::new()}
+    }
+    /// Parses `[tcp:request]` and `[tcp:response]` sections of p0f signature file, i.e. `p0f.fp`
+    /// Each `sig = ...` line gets label of the preceding `label = ...` line, other sections and keys are skipped
+    pub fn parse_p0f(text: &str) -> Result<Self, SignatureFileError> {
+        let mut set = Self::new();
+        let mut section = None;
+        let mut label = "";
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {continue;}
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                section = match name {
+                    "tcp:request" => Some(false),
+                    "tcp:response" => Some(true),
+                    _ => None
+                };
+                continue;
+            }
+            let Some(response) = section else {continue;};
+            let Some((key, value)) = line.split_once('=') else {return Err(SignatureFileError {line: i + 1});};
+            match key.trim() {
+                "label" => label = value.trim(),
+                "sig" => {
+                    let mut signature = Signature::parse(label, value).ok_or(SignatureFileError {line: i + 1})?;
+                    signature.response = response;
+                    set.signatures.push(signature);
+                }
+                _ => {}
+            }
+        }
+        Ok(set)
+    }
+    /// Returns the first signature matching `fingerprint`
+    pub fn find(&self, fingerprint: &SynFingerprint) -> Option<&Signature> {
+        self.signatures.iter().find(|signature| signature.matches(fingerprint))
+    }
+}
@@ -0,0 +1,172 @@
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::ops::RangeInclusive;
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::icmp::IcmpPacket;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::{TcpFlags, TcpSegment};
+use crate::l4::udp::UdpDatagram;
+use crate::stack::PacketStack;
+use crate::util::{Serializable, SplitMix64};
+
+/// TCP flag combinations seen in normal connections, generated segments use one of them
+const TCP_FLAGS: [&str; 7] = ["S", "SA", "A", "PA", "FA", "R", "RA"];
+
+/// Generator of random, but structurally valid packets: lengths, protocol fields and checksums are consistent
+/// Sequence of packets is determined by seed, so failing cases of differential fuzzing can be reproduced
+/// Layer mix is set with relative weights, i.e. `ipv4_weight: 3, ipv6_weight: 1` makes 75% of packets IPv4
+#[derive(Debug, Clone)]
+pub struct PacketGenerator {
+    rng: SplitMix64,
+    /// Wrap IP packets into Ethernet frames with random unicast MAC addresses
+    pub ethernet: bool,
+    pub ipv4_weight: u32,
+    pub ipv6_weight: u32,
+    pub tcp_weight: u32,
+    pub udp_weight: u32,
+    /// ICMP Echo for IPv4 and ICMPv6 Echo for IPv6
+    pub icmp_weight: u32,
+    /// IP packets with raw payload and no transport layer
+    pub raw_weight: u32,
+    pub ipv4_sources: RangeInclusive<Ipv4Addr>,
+    pub ipv4_destinations: RangeInclusive<Ipv4Addr>,
+    pub ipv6_sources: RangeInclusive<Ipv6Addr>,
+    pub ipv6_destinations: RangeInclusive<Ipv6Addr>,
+    pub source_ports: RangeInclusive<u16>,
+    pub destination_ports: RangeInclusive<u16>,
+    /// Size of payload after the last layer, note that it should keep IPv4 total length within `65535 bytes`
+    pub payload_sizes: RangeInclusive<usize>
+}
+impl PacketGenerator {
+    /// Constructs `PacketGenerator` with `seed`, which generates Ethernet frames with equal mix of IPv4 and IPv6, TCP, UDP and ICMP
+    /// Addresses are from `10.0.0.0/8` and `fd00::/8`, payload is up to `1024 bytes`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64(seed),
+            ethernet: true,
+            ipv4_weight: 1,
+            ipv6_weight: 1,
+            tcp_weight: 1,
+            udp_weight: 1,
+            icmp_weight: 1,
+            raw_weight: 0,
+            ipv4_sources: Ipv4Addr::new(10, 0, 0, 0)..=Ipv4Addr::new(10, 255, 255, 255),
+            ipv4_destinations: Ipv4Addr::new(10, 0, 0, 0)..=Ipv4Addr::new(10, 255, 255, 255),
+            ipv6_sources: Ipv6Addr::new(0xFD00, 0, 0, 0, 0, 0, 0, 0)..=Ipv6Addr::new(0xFDFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF),
+            ipv6_destinations: Ipv6Addr::new(0xFD00, 0, 0, 0, 0, 0, 0, 0)..=Ipv6Addr::new(0xFDFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF),
+            source_ports: 1024..=65535,
+            destination_ports: 1..=65535,
+            payload_sizes: 0..=1024
+        }
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+    fn range(&mut self, low: u128, high: u128) -> u128 {
+        self.rng.range(low, high)
+    }
+    /// Picks index of one of `weights`, returns `None` if all weights are zero
+    fn pick(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: u64 = weights.iter().map(|weight| *weight as u64).sum();
+        if total == 0 {return None;}
+        let mut value = self.range(0, total as u128 - 1) as u64;
+        for (i, weight) in weights.iter().enumerate() {
+            if value < *weight as u64 {return Some(i);}
+            value -= *weight as u64;
+        }
+        None
+    }
+    fn mac(&mut self) -> [u8; 6] {
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(&self.next_u64().to_be_bytes()[..6]);
+        mac[0] = (mac[0] & 0xFC) | 0x02;
+        mac
+    }
+    fn payload(&mut self) -> Vec<u8> {
+        let size = self.range(*self.payload_sizes.start() as u128, *self.payload_sizes.end() as u128) as usize;
+        let mut payload = Vec::with_capacity(size + 8);
+        while payload.len() < size {
+            payload.extend_from_slice(&self.next_u64().to_be_bytes());
+        }
+        payload.truncate(size);
+        payload
+    }
+    /// Generates next packet as `PacketStack`, which can be edited before serialization
+    /// Returns empty stack if both IP version weights are zero
+    pub fn next_stack(&mut self) -> PacketStack {
+        let mut stack = PacketStack::new();
+        let Some(version) = self.pick(&[self.ipv4_weight, self.ipv6_weight]) else {return stack;};
+        if self.ethernet {
+            let mut frame = EthernetFrame::new();
+            frame.source = self.mac();
+            frame.destination = self.mac();
+            stack.push(frame);
+        }
+        let ipv4 = version == 0;
+        if ipv4 {
+            let mut packet = Ipv4Packet::new();
+            packet.source = Ipv4Addr::from_bits(self.range(self.ipv4_sources.start().to_bits() as u128, self.ipv4_sources.end().to_bits() as u128) as u32);
+            packet.destination = Ipv4Addr::from_bits(self.range(self.ipv4_destinations.start().to_bits() as u128, self.ipv4_destinations.end().to_bits() as u128) as u32);
+            packet.id = self.next_u64() as u16;
+            packet.ttl = self.range(32, 128) as u8;
+            packet.dont_fragment = self.next_u64() & 1 != 0;
+            stack.push(packet);
+        }
+        else {
+            let mut packet = Ipv6Packet::new();
+            packet.source = Ipv6Addr::from_bits(self.range(self.ipv6_sources.start().to_bits(), self.ipv6_sources.end().to_bits()));
+            packet.destination = Ipv6Addr::from_bits(self.range(self.ipv6_destinations.start().to_bits(), self.ipv6_destinations.end().to_bits()));
+            packet.flow_label = self.next_u64() as u32 & 0xFFFFF;
+            packet.hop_limit = self.range(32, 128) as u8;
+            stack.push(packet);
+        }
+        match self.pick(&[self.tcp_weight, self.udp_weight, self.icmp_weight, self.raw_weight]) {
+            Some(0) => {
+                let mut segment = TcpSegment::new();
+                segment.source = self.range(*self.source_ports.start() as u128, *self.source_ports.end() as u128) as u16;
+                segment.destination = self.range(*self.destination_ports.start() as u128, *self.destination_ports.end() as u128) as u16;
+                segment.sequence_number = self.next_u64() as u32;
+                let flags = TCP_FLAGS[self.range(0, TCP_FLAGS.len() as u128 - 1) as usize];
+                segment.flags = TcpFlags::from_letters(flags).unwrap_or_default();
+                if segment.flags.ack {segment.acknowledgement_number = self.next_u64() as u32;}
+                segment.window_size = self.next_u64() as u16;
+                stack.push(segment);
+            }
+            Some(1) => {
+                let mut datagram = UdpDatagram::new();
+                datagram.source = self.range(*self.source_ports.start() as u128, *self.source_ports.end() as u128) as u16;
+                datagram.destination = self.range(*self.destination_ports.start() as u128, *self.destination_ports.end() as u128) as u16;
+                stack.push(datagram);
+            }
+            Some(2) => {
+                let identifier = (self.next_u64() as u32).to_be_bytes();
+                if ipv4 {
+                    let mut packet = IcmpPacket::new();
+                    packet.icmp_type = 8;
+                    packet.rest_of_header = identifier;
+                    stack.push(packet);
+                }
+                else {
+                    let mut packet = Icmpv6Packet::new();
+                    packet.icmp_type = 128;
+                    packet.rest_of_header = identifier;
+                    stack.push(packet);
+                }
+            }
+            _ => {}
+        }
+        stack.payload = self.payload();
+        stack
+    }
+    /// Generates next packet bytes
+    pub fn next_packet(&mut self) -> Vec<u8> {
+        self.next_stack().serialize()
+    }
+}
+impl Iterator for PacketGenerator {
+    type Item = Vec<u8>;
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_packet())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,249 @@
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// Data of EAP-TLS, EAP-TTLS and PEAP messages, which share the same framing of fragmented TLS records
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EapTlsData {
+    /// More fragments of TLS message follow
+    pub more_fragments: bool,
+    /// Start of exchange, set by server in the first request
+    pub start: bool,
+    /// Version of TTLS or PEAP, carried in low 3 bits of flags, 0 for EAP-TLS
+    pub version: u8,
+    /// Total TLS Message Length, usually present in the first fragment only
+    pub message_length: Option<u32>,
+    /// TLS records or their fragment
+    pub data: Vec<u8>
+}
+impl EapTlsData {
+    /// Returns length of serialized data, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        1 + self.message_length.map_or(0, |_| 4) + self.data.len()
+    }
+}
+impl Serializable for EapTlsData {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(5 + self.data.len());
+        result.push((self.message_length.is_some() as u8) << 7 | (self.more_fragments as u8) << 6 | (self.start as u8) << 5 | (self.version & 7));
+        if let Some(length) = self.message_length {result.extend_from_slice(&length.to_be_bytes());}
+        result.append(&mut self.data);
+        result
+    }
+}
+impl Deserializable for EapTlsData {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        let flags = bytes[0];
+        let (message_length, data) = if flags & 0x80 != 0 {
+            if bytes.len() < 5 {return Err(DeserializeError::WrongDataLength);}
+            (Some(u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]])), &bytes[5..])
+        }
+        else {
+            (None, &bytes[1..])
+        };
+        Ok(Self {
+            more_fragments: flags & 0x40 != 0,
+            start: flags & 0x20 != 0,
+            version: flags & 7,
+            message_length,
+            data: data.to_vec()
+        })
+    }
+}
+
+/// Method of EAP Request or Response, its variant is EAP type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EapMethod {
+    /// Type 1, request may carry displayable prompt, response carries user identity
+    Identity(Vec<u8>),
+    /// Type 2, displayable message
+    Notification(Vec<u8>),
+    /// Type 3, response only, lists authentication types peer desires instead of requested one
+    Nak(Vec<u8>),
+    /// Type 4, as described in RFC 3748
+    Md5Challenge {
+        value: Vec<u8>,
+        name: Vec<u8>
+    },
+    /// Type 13, as described in RFC 5216
+    Tls(EapTlsData),
+    /// Type 21, as described in RFC 5281
+    Ttls(EapTlsData),
+    /// Type 25, Protected EAP
+    Peap(EapTlsData),
+    /// Other types, i.e. 26 for MSCHAPv2, 43 for EAP-FAST, 254 for Expanded Types
+    Other {
+        method_type: u8,
+        data: Vec<u8>
+    }
+}
+impl EapMethod {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Identity(_) => 1,
+            Self::Notification(_) => 2,
+            Self::Nak(_) => 3,
+            Self::Md5Challenge {..} => 4,
+            Self::Tls(_) => 13,
+            Self::Ttls(_) => 21,
+            Self::Peap(_) => 25,
+            Self::Other {method_type, ..} => *method_type
+        }
+    }
+    /// Returns length of serialized method data without type
+    fn data_len(&self) -> usize {
+        match self {
+            Self::Identity(data) | Self::Notification(data) | Self::Nak(data) | Self::Other {data, ..} => data.len(),
+            Self::Md5Challenge {value, name} => 1 + value.len() + name.len(),
+            Self::Tls(data) | Self::Ttls(data) | Self::Peap(data) => data.wire_len()
+        }
+    }
+    /// Returns type and data of method
+    fn serialize(self) -> (u8, Vec<u8>) {
+        let method_type = self.get_type();
+        let data = match self {
+            Self::Identity(data) | Self::Notification(data) | Self::Nak(data) | Self::Other {data, ..} => data,
+            Self::Md5Challenge {mut value, mut name} => {
+                let mut data = Vec::with_capacity(1 + value.len() + name.len());
+                data.push(value.len() as u8);
+                data.append(&mut value);
+                data.append(&mut name);
+                data
+            }
+            Self::Tls(data) | Self::Ttls(data) | Self::Peap(data) => data.serialize()
+        };
+        (method_type, data)
+    }
+    fn deserialize(method_type: u8, data: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(match method_type {
+            1 => Self::Identity(data.to_vec()),
+            2 => Self::Notification(data.to_vec()),
+            3 => Self::Nak(data.to_vec()),
+            4 => {
+                if data.is_empty() || data.len() < 1 + data[0] as usize {return Err(DeserializeError::WrongDataLength);}
+                let size = data[0] as usize;
+                Self::Md5Challenge {value: data[1..1 + size].to_vec(), name: data[1 + size..].to_vec()}
+            }
+            13 => Self::Tls(EapTlsData::deserialize(data)?),
+            21 => Self::Ttls(EapTlsData::deserialize(data)?),
+            25 => Self::Peap(EapTlsData::deserialize(data)?),
+            method_type => Self::Other {method_type, data: data.to_vec()}
+        })
+    }
+}
+
+/// Extensible Authentication Protocol packet, as described in RFC 3748
+/// It is carried by EAPOL frames, RADIUS EAP-Message attributes and PPP
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EapPacket {
+    /// Code 1
+    Request {
+        identifier: u8,
+        method: EapMethod
+    },
+    /// Code 2, `identifier` matches the request
+    Response {
+        identifier: u8,
+        method: EapMethod
+    },
+    /// Code 3
+    Success {
+        identifier: u8
+    },
+    /// Code 4
+    Failure {
+        identifier: u8
+    }
+}
+impl EapPacket {
+    pub fn get_code(&self) -> u8 {
+        match self {
+            Self::Request {..} => 1,
+            Self::Response {..} => 2,
+            Self::Success {..} => 3,
+            Self::Failure {..} => 4
+        }
+    }
+    pub fn identifier(&self) -> u8 {
+        match self {
+            Self::Request {identifier, ..} |
+            Self::Response {identifier, ..} |
+            Self::Success {identifier} |
+            Self::Failure {identifier} => *identifier
+        }
+    }
+    pub fn method(&self) -> Option<&EapMethod> {
+        match self {
+            Self::Request {method, ..} | Self::Response {method, ..} => Some(method),
+            _ => None
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self.method() {
+            Some(method) => 5 + method.data_len(),
+            None => 4
+        }
+    }
+    /// Converts packet to RADIUS EAP-Message attributes(type 79), split into values of at most `253 bytes`, as described in RFC 3579
+    /// Note that RADIUS packet carrying them also needs Message-Authenticator attribute
+    pub fn to_radius_attributes(self) -> Vec<u8> {
+        let bytes = self.serialize();
+        let mut result = Vec::with_capacity(bytes.len() + bytes.len().div_ceil(253) * 2);
+        for chunk in bytes.chunks(253) {
+            result.push(79);
+            result.push(chunk.len() as u8 + 2);
+            result.extend_from_slice(chunk);
+        }
+        result
+    }
+    /// Constructs `EapPacket` from RADIUS `attributes`, concatenating values of all EAP-Message attributes(type 79) in order
+    /// Returns `Err(DeserializeError::WrongData)` if attributes are malformed or have no EAP-Message
+    pub fn from_radius_attributes(attributes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut bytes = Vec::new();
+        let mut i = 0usize;
+        while i < attributes.len() {
+            if attributes.len() < i + 2 {return Err(DeserializeError::WrongData);}
+            let length = attributes[i + 1] as usize;
+            if length < 2 || attributes.len() < i + length {return Err(DeserializeError::WrongData);}
+            if attributes[i] == 79 {bytes.extend_from_slice(&attributes[i + 2..i + length]);}
+            i += length;
+        }
+        if bytes.is_empty() {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&bytes)
+    }
+}
+impl Serializable for EapPacket {
+    fn serialize(self) -> Vec<u8> {
+        let code = self.get_code();
+        let identifier = self.identifier();
+        let mut result = vec![code, identifier, 0, 0];
+        if let Self::Request {method, ..} | Self::Response {method, ..} = self {
+            let (method_type, mut data) = method.serialize();
+            result.push(method_type);
+            result.append(&mut data);
+        }
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for EapPacket {
+    /// Constructs `EapPacket` from bytes, trailing bytes after packet length are ignored
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 4 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        let identifier = bytes[1];
+        match bytes[0] {
+            1 | 2 => {
+                if length < 5 {return Err(DeserializeError::WrongDataLength);}
+                let method = EapMethod::deserialize(bytes[4], &bytes[5..length])?;
+                Ok(if bytes[0] == 1 {Self::Request {identifier, method}} else {Self::Response {identifier, method}})
+            }
+            3 => Ok(Self::Success {identifier}),
+            4 => Ok(Self::Failure {identifier}),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
\ No newline at end of file
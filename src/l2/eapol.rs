@@ -0,0 +1,279 @@
+use crate::l2::eap::EapPacket;
+use crate::util::{Serializable, Deserializable, DeserializeError, EtherPayload, ValidationIssue};
+
+/// Key Information field of EAPOL-Key frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyInformation {
+    /// Key Descriptor Version, 1 for HMAC-MD5 and RC4, 2 for HMAC-SHA1 and AES key wrap, 3 for AES-CMAC, actually only 3 bits
+    pub descriptor_version: u8,
+    /// Key is pairwise, otherwise it is group key
+    pub pairwise: bool,
+    /// Index of group key in WPA, actually only 2 bits
+    pub key_index: u8,
+    pub install: bool,
+    pub ack: bool,
+    pub mic: bool,
+    pub secure: bool,
+    pub error: bool,
+    pub request: bool,
+    pub encrypted_key_data: bool,
+    pub smk_message: bool
+}
+impl KeyInformation {
+    pub fn from_u16(value: u16) -> Self {
+        Self {
+            descriptor_version: (value & 7) as u8,
+            pairwise: value & (1 << 3) != 0,
+            key_index: ((value >> 4) & 3) as u8,
+            install: value & (1 << 6) != 0,
+            ack: value & (1 << 7) != 0,
+            mic: value & (1 << 8) != 0,
+            secure: value & (1 << 9) != 0,
+            error: value & (1 << 10) != 0,
+            request: value & (1 << 11) != 0,
+            encrypted_key_data: value & (1 << 12) != 0,
+            smk_message: value & (1 << 13) != 0
+        }
+    }
+    pub fn to_u16(&self) -> u16 {
+        (self.descriptor_version & 7) as u16
+            | (self.pairwise as u16) << 3
+            | ((self.key_index & 3) as u16) << 4
+            | (self.install as u16) << 6
+            | (self.ack as u16) << 7
+            | (self.mic as u16) << 8
+            | (self.secure as u16) << 9
+            | (self.error as u16) << 10
+            | (self.request as u16) << 11
+            | (self.encrypted_key_data as u16) << 12
+            | (self.smk_message as u16) << 13
+    }
+}
+
+/// EAPOL-Key frame body with IEEE 802.11 key descriptor, used by WPA and RSN(WPA2/WPA3) 4-Way and Group Key Handshakes
+/// Note that MIC is assumed to be `16 bytes`, as for all AKMs except Suite B 192-bit and FILS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EapolKey {
+    /// Descriptor type, 2 for RSN or 254 for WPA
+    pub descriptor_type: u8,
+    pub information: KeyInformation,
+    /// Length of pairwise key, i.e. 16 for CCMP, 32 for TKIP
+    pub key_length: u16,
+    pub replay_counter: u64,
+    pub nonce: [u8; 32],
+    pub iv: [u8; 16],
+    /// Receive Sequence Counter of group key
+    pub rsc: [u8; 8],
+    /// Reserved in RSN, key identifier in WPA
+    pub id: [u8; 8],
+    pub mic: [u8; 16],
+    /// Key Data, i.e. RSN IE or encrypted GTK KDE
+    pub data: Vec<u8>
+}
+impl EapolKey {
+    /// Constructs an empty RSN key descriptor
+    pub fn new() -> Self {
+        Self {
+            descriptor_type: 2,
+            information: KeyInformation::default(),
+            key_length: 0,
+            replay_counter: 0,
+            nonce: [0; 32],
+            iv: [0; 16],
+            rsc: [0; 8],
+            id: [0; 8],
+            mic: [0; 16],
+            data: Vec::new()
+        }
+    }
+    /// Returns length of serialized key descriptor, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        95 + self.data.len()
+    }
+    /// Returns number of 4-Way Handshake message(1 to 4) this frame is, determined by Key Information flags
+    /// Returns `None` for group key and other frames
+    pub fn handshake_message(&self) -> Option<u8> {
+        let information = &self.information;
+        if !information.pairwise || information.request || information.error {return None;}
+        match (information.ack, information.mic, information.install, information.secure) {
+            (true, false, false, _) => Some(1),
+            (false, true, false, false) => Some(2),
+            (true, true, true, _) => Some(3),
+            (false, true, false, true) => Some(4),
+            _ => None
+        }
+    }
+}
+impl Default for EapolKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for EapolKey {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(95 + self.data.len());
+        result.push(self.descriptor_type);
+        result.extend_from_slice(&self.information.to_u16().to_be_bytes());
+        result.extend_from_slice(&self.key_length.to_be_bytes());
+        result.extend_from_slice(&self.replay_counter.to_be_bytes());
+        result.extend_from_slice(&self.nonce);
+        result.extend_from_slice(&self.iv);
+        result.extend_from_slice(&self.rsc);
+        result.extend_from_slice(&self.id);
+        result.extend_from_slice(&self.mic);
+        result.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+        result.append(&mut self.data);
+        result
+    }
+}
+impl Deserializable for EapolKey {
+    /// Constructs `EapolKey` from EAPOL-Key frame body
+    /// Returns `Err(DeserializeError::WrongData)` if descriptor type is neither RSN nor WPA
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 95 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0] != 2 && bytes[0] != 254 {return Err(DeserializeError::WrongData);}
+        let data_length = u16::from_be_bytes([bytes[93], bytes[94]]) as usize;
+        if bytes.len() < 95 + data_length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            descriptor_type: bytes[0],
+            information: KeyInformation::from_u16(u16::from_be_bytes([bytes[1], bytes[2]])),
+            key_length: u16::from_be_bytes([bytes[3], bytes[4]]),
+            replay_counter: u64::from_be_bytes(bytes[5..13].try_into().unwrap()),
+            nonce: bytes[13..45].try_into().unwrap(),
+            iv: bytes[45..61].try_into().unwrap(),
+            rsc: bytes[61..69].try_into().unwrap(),
+            id: bytes[69..77].try_into().unwrap(),
+            mic: bytes[77..93].try_into().unwrap(),
+            data: bytes[95..95 + data_length].to_vec()
+        })
+    }
+}
+
+/// Body of EAPOL frame, its variant is packet type of frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EapolBody {
+    /// Type 0, carries EAP packet
+    Eap(EapPacket),
+    /// Type 1, supplicant asks authenticator to start authentication
+    Start,
+    /// Type 2, supplicant tells authenticator it leaves
+    Logoff,
+    /// Type 3 with RSN or WPA key descriptor
+    Key(EapolKey),
+    /// Other types, i.e. 4 for Encapsulated-ASF-Alert, 5 for MKA, EAPOL-Key with other descriptors and malformed EAP packets
+    Other {
+        packet_type: u8,
+        body: Vec<u8>
+    }
+}
+impl EapolBody {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Eap(_) => 0,
+            Self::Start => 1,
+            Self::Logoff => 2,
+            Self::Key(_) => 3,
+            Self::Other {packet_type, ..} => *packet_type
+        }
+    }
+}
+
+/// 802.1X EAP over LAN frame, which is payload of Ethernet frame with protocol 0x888E
+/// You can construct it from scratch with `EapolFrame::new()` and consistently editing
+/// Or construct from existing frame payload with `EapolFrame::deserialize()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EapolFrame {
+    /// Protocol version, 1 for 802.1X-2001, 2 for 802.1X-2004, 3 for 802.1X-2010
+    pub version: u8,
+    pub body: EapolBody
+}
+impl EapolFrame {
+    /// Constructs EAPOL-Start frame of version 2
+    pub fn new() -> Self {
+        Self {
+            version: 2,
+            body: EapolBody::Start
+        }
+    }
+    /// Returns length of serialized frame, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        4 + match &self.body {
+            EapolBody::Eap(packet) => packet.wire_len(),
+            EapolBody::Other {body, ..} => body.len(),
+            EapolBody::Start | EapolBody::Logoff => 0,
+            EapolBody::Key(key) => key.wire_len()
+        }
+    }
+    /// Checks raw EAPOL frame `bytes` for problems: truncation, unknown version and EAP length mismatch
+    /// Bytes after body length are considered padding of short Ethernet frames
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 4 {
+            issues.push(ValidationIssue::Truncated {layer: "eapol", needed: 4, actual: bytes.len()});
+            return issues;
+        }
+        if !(1..=3).contains(&bytes[0]) {
+            issues.push(ValidationIssue::InvalidField {layer: "eapol", field: "version", value: bytes[0] as u32});
+        }
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if 4 + length > bytes.len() {
+            issues.push(ValidationIssue::Truncated {layer: "eapol", needed: 4 + length, actual: bytes.len()});
+            return issues;
+        }
+        let body = &bytes[4..4 + length];
+        if bytes[1] == 0 && body.len() >= 4 {
+            let eap_length = u16::from_be_bytes([body[2], body[3]]) as usize;
+            if eap_length != body.len() {
+                issues.push(ValidationIssue::LengthMismatch {layer: "eap", field: "length", expected: body.len(), actual: eap_length});
+            }
+        }
+        issues
+    }
+}
+impl Default for EapolFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for EapolFrame {
+    fn serialize(self) -> Vec<u8> {
+        let packet_type = self.body.get_type();
+        let body = match self.body {
+            EapolBody::Eap(packet) => packet.serialize(),
+            EapolBody::Other {body, ..} => body,
+            EapolBody::Start | EapolBody::Logoff => Vec::new(),
+            EapolBody::Key(key) => key.serialize()
+        };
+        let mut result = Vec::with_capacity(4 + body.len());
+        result.push(self.version);
+        result.push(packet_type);
+        result.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        result.extend_from_slice(&body);
+        result
+    }
+}
+impl Deserializable for EapolFrame {
+    /// Constructs `EapolFrame` from bytes, padding of short Ethernet frames is stripped using body length
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if bytes.len() < 4 + length {return Err(DeserializeError::WrongDataLength);}
+        let body = &bytes[4..4 + length];
+        let body = match bytes[1] {
+            0 => match EapPacket::deserialize(body) {
+                Ok(packet) => EapolBody::Eap(packet),
+                Err(_) => EapolBody::Other {packet_type: 0, body: body.to_vec()}
+            },
+            1 => EapolBody::Start,
+            2 => EapolBody::Logoff,
+            3 if matches!(body.first(), Some(2 | 254)) => EapolBody::Key(EapolKey::deserialize(body)?),
+            packet_type => EapolBody::Other {packet_type, body: body.to_vec()}
+        };
+        Ok(Self {version: bytes[0], body})
+    }
+}
+impl EtherPayload for EapolFrame {
+    fn ether_type(&self) -> u16 {
+        0x888E
+    }
+}
\ No newline at end of file
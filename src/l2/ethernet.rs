@@ -1,4 +1,135 @@
-use crate::util::{Deserializable, DeserializeError, Serializable};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::l3::{ipv4::Ipv4Packet, ipv6::Ipv6Packet};
+#[cfg(feature = "arp")]
+use crate::l3::arp::ArpPacket;
+#[cfg(feature = "ppp")]
+use crate::l2::ppp::PppoeSession;
+#[cfg(feature = "eapol")]
+use crate::l2::eapol::EapolFrame;
+use crate::util::{Deserializable, DeserializeError, DeserializableRef, PacketRef, EtherPayload, Serializable, ValidationIssue, crc32};
+
+/// Formats MAC address as `aa:bb:cc:dd:ee:ff`
+pub fn format_mac(mac: [u8; 6]) -> String {
+    mac.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(":")
+}
+
+/// Parses MAC address with `:` or `-` separators
+/// Returns `None` if `mac` is not a valid MAC address
+pub fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mut result = [0u8; 6];
+    let mut parts = mac.split([':', '-']);
+    for byte in result.iter_mut() {
+        let part = parts.next()?;
+        if part.len() != 2 {return None;}
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    if parts.next().is_some() {return None;}
+    Some(result)
+}
+
+/// Returns Ethernet multicast MAC address `01:00:5e:XX:XX:XX` of IPv4 multicast `group`, which has its low 23 bits, as described in RFC 1112
+/// Note that 32 groups share each MAC address, since high 5 bits of group are dropped
+pub fn ipv4_multicast_mac(group: Ipv4Addr) -> [u8; 6] {
+    let octets = group.octets();
+    [0x01, 0x00, 0x5E, octets[1] & 0x7F, octets[2], octets[3]]
+}
+
+/// Returns Ethernet multicast MAC address `33:33:XX:XX:XX:XX` of IPv6 multicast `group`, which has its low 32 bits, as described in RFC 2464
+pub fn ipv6_multicast_mac(group: Ipv6Addr) -> [u8; 6] {
+    let octets = group.octets();
+    [0x33, 0x33, octets[12], octets[13], octets[14], octets[15]]
+}
+
+/// Returns Ethernet multicast MAC address of IPv4 or IPv6 multicast `group`
+/// Returns `None` if `group` is not multicast address
+pub fn multicast_mac(group: IpAddr) -> Option<[u8; 6]> {
+    match group {
+        IpAddr::V4(group) if group.is_multicast() => Some(ipv4_multicast_mac(group)),
+        IpAddr::V6(group) if group.is_multicast() => Some(ipv6_multicast_mac(group)),
+        _ => None
+    }
+}
+
+/// Generates random locally administered unicast MAC address
+#[cfg(feature = "rand")]
+pub fn random_mac() -> [u8; 6] {
+    let mut mac = [0u8; 6];
+    fastrand::fill(&mut mac);
+    mac[0] = (mac[0] & 0xFC) | 0x02;
+    mac
+}
+
+/// Generates random locally administered unicast MAC address with `oui` and random last `3 bytes`
+/// Locally administered bit is set and multicast bit is cleared in the first byte of `oui`, the rest of it is kept
+#[cfg(feature = "rand")]
+pub fn random_mac_with_oui(oui: [u8; 3]) -> [u8; 6] {
+    let mut mac = [(oui[0] & 0xFC) | 0x02, oui[1], oui[2], 0, 0, 0];
+    fastrand::fill(&mut mac[3..]);
+    mac
+}
+
+/// Priority Code Point of VLAN tag, variants are traffic types recommended for each priority by IEEE 802.1Q
+/// Note that `Background`(1) is lower priority than `BestEffort`(0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum VlanPriority {
+    BestEffort = 0,
+    Background = 1,
+    ExcellentEffort = 2,
+    CriticalApplications = 3,
+    Video = 4,
+    Voice = 5,
+    InternetworkControl = 6,
+    NetworkControl = 7
+}
+impl VlanPriority {
+    /// Constructs priority from low 3 bits of `value`
+    pub fn from_u8(value: u8) -> Self {
+        match value & 7 {
+            0 => Self::BestEffort,
+            1 => Self::Background,
+            2 => Self::ExcellentEffort,
+            3 => Self::CriticalApplications,
+            4 => Self::Video,
+            5 => Self::Voice,
+            6 => Self::InternetworkControl,
+            _ => Self::NetworkControl
+        }
+    }
+}
+
+/// 802.1Q VLAN tag, which is Tag Control Information following `0x8100` protocol of tagged frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VlanTag {
+    /// Priority Code Point
+    pub priority: VlanPriority,
+    /// Drop Eligible Indicator, such frames may be dropped first under congestion
+    pub drop_eligible: bool,
+    /// VLAN identifier, actually only 12 bits
+    pub id: u16
+}
+impl VlanTag {
+    /// Constructs tag of VLAN `id` with `BestEffort` priority
+    pub fn new(id: u16) -> Self {
+        Self {
+            priority: VlanPriority::BestEffort,
+            drop_eligible: false,
+            id: id & 0xFFF
+        }
+    }
+    /// Constructs tag from `16 bits` of Tag Control Information
+    pub fn from_tci(tci: u16) -> Self {
+        Self {
+            priority: VlanPriority::from_u8((tci >> 13) as u8),
+            drop_eligible: tci & 0x1000 != 0,
+            id: tci & 0xFFF
+        }
+    }
+    /// Returns `16 bits` of Tag Control Information
+    pub fn to_tci(&self) -> u16 {
+        (self.priority as u16) << 13 | (self.drop_eligible as u16) << 12 | (self.id & 0xFFF)
+    }
+}
 
 /// Struct for oridinary Ethernet Frame
 /// You can construct it from scratch with `EthernetPacket::new()` and consistently editing
@@ -20,6 +151,118 @@ impl EthernetFrame {
             payload: Vec::new()
         }
     }
+    /// Sets `payload` to serialized `packet` and `protocol` to its EtherType
+    pub fn set_payload_packet<P: EtherPayload + Serializable + Clone>(&mut self, packet: &P) {
+        self.protocol = packet.ether_type();
+        self.payload = packet.clone().serialize();
+    }
+    /// Returns length of serialized frame without padding and Frame Check Sequence, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        14 + self.payload.len()
+    }
+    /// Calculates Frame Check Sequence of this frame
+    pub fn fcs(&self) -> u32 {
+        crc32(&self.clone().serialize())
+    }
+    /// Checks if frame is VLAN tagged, i.e. its protocol is `0x8100`(802.1Q) or `0x88A8`(802.1ad service tag)
+    pub fn is_vlan_tagged(&self) -> bool {
+        matches!(self.protocol, 0x8100 | 0x88A8) && self.payload.len() >= 4
+    }
+    /// Returns outermost VLAN tag, which is at the start of payload of tagged frame
+    /// Returns `None` if frame isn't tagged
+    pub fn vlan_tag(&self) -> Option<VlanTag> {
+        if !self.is_vlan_tagged() {return None;}
+        Some(VlanTag::from_tci(u16::from_be_bytes([self.payload[0], self.payload[1]])))
+    }
+    /// Replaces outermost VLAN tag of tagged frame with `tag`
+    /// Returns `false` if frame isn't tagged, it remains untouched in that case
+    pub fn set_vlan_tag(&mut self, tag: VlanTag) -> bool {
+        if !self.is_vlan_tagged() {return false;}
+        self.payload[0..2].copy_from_slice(&tag.to_tci().to_be_bytes());
+        true
+    }
+    /// Adds 802.1Q `tag` in front of existing tags, current protocol moves into the tag
+    pub fn push_vlan_tag(&mut self, tag: VlanTag) {
+        let mut header = [0u8; 4];
+        header[0..2].copy_from_slice(&tag.to_tci().to_be_bytes());
+        header[2..4].copy_from_slice(&self.protocol.to_be_bytes());
+        self.payload.splice(0..0, header);
+        self.protocol = 0x8100;
+    }
+    /// Removes outermost VLAN tag, protocol is restored from the tag
+    /// Returns `None` if frame isn't tagged
+    pub fn pop_vlan_tag(&mut self) -> Option<VlanTag> {
+        let tag = self.vlan_tag()?;
+        self.protocol = u16::from_be_bytes([self.payload[2], self.payload[3]]);
+        self.payload.drain(0..4);
+        Some(tag)
+    }
+    /// Sets Priority Code Point of outermost VLAN tag to `priority`, keeping Drop Eligible Indicator and VLAN identifier
+    /// Returns previous priority, or `None` if frame isn't tagged
+    pub fn remark_priority(&mut self, priority: VlanPriority) -> Option<VlanPriority> {
+        let mut tag = self.vlan_tag()?;
+        let previous = tag.priority;
+        tag.priority = priority;
+        self.set_vlan_tag(tag);
+        Some(previous)
+    }
+    /// Converts frame to bytes padded with zeroes to minimal Ethernet frame size of `60 bytes`
+    pub fn serialize_padded(self) -> Vec<u8> {
+        let mut result = self.serialize();
+        if result.len() < 60 {
+            result.resize(60, 0);
+        }
+        result
+    }
+    /// Converts frame to bytes with Frame Check Sequence appended, as it is transmitted on wire
+    /// Frame is padded to `60 bytes` before calculating Frame Check Sequence, so result is at least `64 bytes`
+    pub fn serialize_with_fcs(self) -> Vec<u8> {
+        let mut result = self.serialize_padded();
+        let fcs = crc32(&result);
+        result.extend_from_slice(&fcs.to_le_bytes());
+        result
+    }
+    /// Returns `true` if last `4 bytes` of `bytes` are correct Frame Check Sequence of preceding bytes
+    pub fn verify_fcs(bytes: &[u8]) -> bool {
+        if bytes.len() < 4 {return false;}
+        let (frame, fcs) = bytes.split_at(bytes.len() - 4);
+        crc32(frame).to_le_bytes() == fcs
+    }
+    /// Constructs `EthernetFrame` from bytes ending with Frame Check Sequence, which is verified and stripped
+    /// Returns `Err(DeserializeError::WrongData)` if Frame Check Sequence is wrong
+    pub fn deserialize_with_fcs(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        if !Self::verify_fcs(bytes) {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&bytes[..bytes.len() - 4])
+    }
+    /// Checks raw Ethernet frame `bytes` without Frame Check Sequence for problems
+    /// IPv4, IPv6, ARP, PPPoE session and EAPOL payload is validated too, after stripping padding of short frames
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        if bytes.len() < 14 {
+            return vec![ValidationIssue::Truncated {layer: "ethernet", needed: 14, actual: bytes.len()}];
+        }
+        let protocol = u16::from_be_bytes([bytes[12], bytes[13]]);
+        let mut payload = &bytes[14..];
+        if let Some(length) = payload_length(protocol, payload) && length < payload.len() {
+            payload = &payload[..length];
+        }
+        match protocol {
+            0x0800 => Ipv4Packet::validate(payload),
+            0x86DD => Ipv6Packet::validate(payload),
+            #[cfg(feature = "arp")]
+            0x0806 => ArpPacket::validate(payload),
+            #[cfg(feature = "ppp")]
+            0x8864 => PppoeSession::validate(payload),
+            #[cfg(feature = "eapol")]
+            0x888E => EapolFrame::validate(payload),
+            _ => Vec::new()
+        }
+    }
+}
+impl Default for EthernetFrame {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Serializable for EthernetFrame {
     fn serialize(mut self) -> Vec<u8> {
@@ -31,14 +274,48 @@ impl Serializable for EthernetFrame {
         result
     }
 }
+/// Returns real length of IPv4, IPv6 or ARP `payload`, so padding of short frames can be stripped
+/// Length of ARP packet is calculated from its hardware and protocol address lengths, so it works for any address types
+pub(crate) fn payload_length(protocol: u16, payload: &[u8]) -> Option<usize> {
+    match protocol {
+        0x0800 if payload.len() >= 20 => {
+            let length = u16::from_be_bytes([payload[2], payload[3]]) as usize;
+            if length >= 20 {Some(length)} else {None}
+        }
+        0x86DD if payload.len() >= 40 => {
+            let length = u16::from_be_bytes([payload[4], payload[5]]) as usize;
+            if length != 0 {Some(40 + length)} else {None}
+        }
+        0x0806 if payload.len() >= 8 => Some(8 + 2 * (payload[4] as usize + payload[5] as usize)),
+        _ => None
+    }
+}
 impl Deserializable for EthernetFrame {
+    /// Constructs `EthernetFrame` from bytes
+    /// Padding of short frames carrying IPv4, IPv6 or ARP is stripped from payload using length from inner packet header
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for EthernetFrame {
+    /// The same as `deserialize()`, but payload is borrowed from `bytes`
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
         if bytes.len() < 15 {return Err(DeserializeError::WrongDataLength);}
-        Ok(Self {
+        let protocol = u16::from_be_bytes([bytes[12], bytes[13]]);
+        let mut payload = &bytes[14..];
+        if let Some(length) = payload_length(protocol, payload) && length < payload.len() {
+            payload = &payload[..length];
+        }
+        let header = Self {
             destination: bytes[0..6].as_array().unwrap().clone(),
             source: bytes[6..12].as_array().unwrap().clone(),
-            protocol: u16::from_be_bytes([bytes[12], bytes[13]]),
-            payload: bytes[14..].to_vec()
-        })
+            protocol,
+            payload: Vec::new()
+        };
+        Ok(PacketRef {header, payload})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
     }
 }
\ No newline at end of file
@@ -0,0 +1,521 @@
+use crate::util::{Serializable, Deserializable, DeserializeError, crc32};
+
+/// Frame Control field of 802.11 MAC header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameControl {
+    /// Protocol version, always 0
+    pub protocol_version: u8,
+    /// Frame type, 0 for Management, 1 for Control, 2 for Data, 3 for Extension
+    pub frame_type: u8,
+    /// Frame subtype, i.e. 8 for Beacon, 4 for Probe Request, 8 for QoS Data
+    pub subtype: u8,
+    /// `To DS` flag - frame is sent to distribution system
+    pub to_ds: bool,
+    /// `From DS` flag - frame is sent from distribution system
+    pub from_ds: bool,
+    pub more_fragments: bool,
+    pub retry: bool,
+    pub power_management: bool,
+    pub more_data: bool,
+    /// `Protected Frame` flag - body is encrypted
+    pub protected: bool,
+    /// `+HTC/Order` flag
+    pub order: bool
+}
+impl FrameControl {
+    /// Constructs Frame Control of given `frame_type` and `subtype` without flags
+    pub fn new(frame_type: u8, subtype: u8) -> Self {
+        Self {
+            protocol_version: 0,
+            frame_type,
+            subtype,
+            to_ds: false,
+            from_ds: false,
+            more_fragments: false,
+            retry: false,
+            power_management: false,
+            more_data: false,
+            protected: false,
+            order: false
+        }
+    }
+    pub fn is_management(&self) -> bool {
+        self.frame_type == 0
+    }
+    pub fn is_control(&self) -> bool {
+        self.frame_type == 1
+    }
+    pub fn is_data(&self) -> bool {
+        self.frame_type == 2
+    }
+    /// Returns `true` for QoS Data subtypes, which carry QoS Control field
+    pub fn is_qos_data(&self) -> bool {
+        self.is_data() && self.subtype & 0x8 != 0
+    }
+}
+impl Serializable for FrameControl {
+    fn serialize(self) -> Vec<u8> {
+        vec![
+            (self.subtype & 0xF) << 4 | (self.frame_type & 0b11) << 2 | self.protocol_version & 0b11,
+            self.to_ds as u8 |
+            (self.from_ds as u8) << 1 |
+            (self.more_fragments as u8) << 2 |
+            (self.retry as u8) << 3 |
+            (self.power_management as u8) << 4 |
+            (self.more_data as u8) << 5 |
+            (self.protected as u8) << 6 |
+            (self.order as u8) << 7
+        ]
+    }
+}
+impl Deserializable for FrameControl {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            protocol_version: bytes[0] & 0b11,
+            frame_type: (bytes[0] >> 2) & 0b11,
+            subtype: bytes[0] >> 4,
+            to_ds: bytes[1] & 0x01 != 0,
+            from_ds: bytes[1] & 0x02 != 0,
+            more_fragments: bytes[1] & 0x04 != 0,
+            retry: bytes[1] & 0x08 != 0,
+            power_management: bytes[1] & 0x10 != 0,
+            more_data: bytes[1] & 0x20 != 0,
+            protected: bytes[1] & 0x40 != 0,
+            order: bytes[1] & 0x80 != 0
+        })
+    }
+}
+
+/// Tagged parameter (Information Element) of Management frame body
+#[derive(Debug, Clone)]
+pub struct InformationElement {
+    /// Element ID, i.e. 0 for SSID, 1 for Supported Rates, 3 for DS Parameter Set
+    pub id: u8,
+    pub data: Vec<u8>
+}
+impl InformationElement {
+    /// Returns length of serialized element
+    pub fn wire_len(&self) -> usize {
+        2 + self.data.len()
+    }
+    /// Reads elements until end of `bytes`
+    pub fn read_all(bytes: &[u8]) -> Result<Vec<Self>, DeserializeError> {
+        let mut elements = Vec::new();
+        let mut i = 0usize;
+        while i < bytes.len() {
+            if bytes.len() < i + 2 {return Err(DeserializeError::WrongDataLength);}
+            let end = i + 2 + bytes[i + 1] as usize;
+            if bytes.len() < end {return Err(DeserializeError::WrongDataLength);}
+            elements.push(Self {
+                id: bytes[i],
+                data: bytes[i + 2..end].to_vec()
+            });
+            i = end;
+        }
+        Ok(elements)
+    }
+    /// Writes elements one after another
+    pub fn write_all(elements: Vec<Self>, result: &mut Vec<u8>) {
+        for element in elements {
+            result.push(element.id);
+            result.push(element.data.len() as u8);
+            result.extend_from_slice(&element.data);
+        }
+    }
+}
+
+/// Body of Management frame with fixed fields decoded
+/// All fixed fields **are in native order**
+#[derive(Debug, Clone)]
+pub enum ManagementBody {
+    /// Subtype 0
+    AssociationRequest {
+        capability: u16,
+        listen_interval: u16,
+        elements: Vec<InformationElement>
+    },
+    /// Subtype 1 or 3
+    AssociationResponse {
+        capability: u16,
+        status: u16,
+        association_id: u16,
+        elements: Vec<InformationElement>
+    },
+    /// Subtype 2
+    ReassociationRequest {
+        capability: u16,
+        listen_interval: u16,
+        current_ap: [u8; 6],
+        elements: Vec<InformationElement>
+    },
+    /// Subtype 4
+    ProbeRequest {
+        elements: Vec<InformationElement>
+    },
+    /// Subtype 5 or 8
+    Beacon {
+        timestamp: u64,
+        /// Beacon interval in Time Units of `1024 microseconds`
+        interval: u16,
+        capability: u16,
+        elements: Vec<InformationElement>
+    },
+    /// Subtype 10 or 12
+    Deauthentication {
+        reason: u16
+    },
+    /// Subtype 11
+    Authentication {
+        algorithm: u16,
+        transaction: u16,
+        status: u16,
+        elements: Vec<InformationElement>
+    },
+    /// Any other subtype presented just with raw body
+    Other(Vec<u8>)
+}
+impl ManagementBody {
+    /// Decodes `body` of Management frame of `subtype`
+    pub fn parse(subtype: u8, body: &[u8]) -> Result<Self, DeserializeError> {
+        let field = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+        let fixed = match subtype {
+            0 => 4,
+            1 | 3 => 6,
+            2 => 10,
+            4 => 0,
+            5 | 8 => 12,
+            10 | 12 => 2,
+            11 => 6,
+            _ => return Ok(Self::Other(body.to_vec()))
+        };
+        if body.len() < fixed {return Err(DeserializeError::WrongDataLength);}
+        let elements = InformationElement::read_all(&body[fixed..]);
+        Ok(match subtype {
+            0 => Self::AssociationRequest {
+                capability: field(0),
+                listen_interval: field(2),
+                elements: elements?
+            },
+            1 | 3 => Self::AssociationResponse {
+                capability: field(0),
+                status: field(2),
+                association_id: field(4),
+                elements: elements?
+            },
+            2 => Self::ReassociationRequest {
+                capability: field(0),
+                listen_interval: field(2),
+                current_ap: *body[4..10].as_array().unwrap(),
+                elements: elements?
+            },
+            4 => Self::ProbeRequest {
+                elements: elements?
+            },
+            5 | 8 => Self::Beacon {
+                timestamp: u64::from_le_bytes(*body[..8].as_array().unwrap()),
+                interval: field(8),
+                capability: field(10),
+                elements: elements?
+            },
+            10 | 12 => Self::Deauthentication {
+                reason: field(0)
+            },
+            _ => Self::Authentication {
+                algorithm: field(0),
+                transaction: field(2),
+                status: field(4),
+                elements: elements?
+            }
+        })
+    }
+    /// Returns length of serialized body, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let fixed = match self {
+            Self::AssociationRequest {..} => 4,
+            Self::AssociationResponse {..} => 6,
+            Self::ReassociationRequest {..} => 10,
+            Self::ProbeRequest {..} => 0,
+            Self::Beacon {..} => 12,
+            Self::Deauthentication {..} => 2,
+            Self::Authentication {..} => 6,
+            Self::Other(body) => return body.len()
+        };
+        fixed + self.elements().iter().map(InformationElement::wire_len).sum::<usize>()
+    }
+    /// Returns tagged parameters of body, empty for bodies without them
+    pub fn elements(&self) -> &[InformationElement] {
+        match self {
+            Self::AssociationRequest {elements, ..} |
+            Self::AssociationResponse {elements, ..} |
+            Self::ReassociationRequest {elements, ..} |
+            Self::ProbeRequest {elements} |
+            Self::Beacon {elements, ..} |
+            Self::Authentication {elements, ..} => elements,
+            Self::Deauthentication {..} |
+            Self::Other(_) => &[]
+        }
+    }
+    /// Returns data of first element with `id`
+    pub fn element(&self, id: u8) -> Option<&[u8]> {
+        self.elements().iter().find(|element| element.id == id).map(|element| element.data.as_slice())
+    }
+    /// Returns SSID from element 0, empty SSID means wildcard in Probe Request or hidden network in Beacon
+    pub fn ssid(&self) -> Option<&[u8]> {
+        self.element(0)
+    }
+    /// Returns channel number from DS Parameter Set element 3
+    pub fn channel(&self) -> Option<u8> {
+        self.element(3).and_then(|data| data.first().copied())
+    }
+}
+impl Serializable for ManagementBody {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::new();
+        let elements = match self {
+            Self::AssociationRequest {capability, listen_interval, elements} => {
+                result.extend_from_slice(&capability.to_le_bytes());
+                result.extend_from_slice(&listen_interval.to_le_bytes());
+                elements
+            }
+            Self::AssociationResponse {capability, status, association_id, elements} => {
+                result.extend_from_slice(&capability.to_le_bytes());
+                result.extend_from_slice(&status.to_le_bytes());
+                result.extend_from_slice(&association_id.to_le_bytes());
+                elements
+            }
+            Self::ReassociationRequest {capability, listen_interval, current_ap, elements} => {
+                result.extend_from_slice(&capability.to_le_bytes());
+                result.extend_from_slice(&listen_interval.to_le_bytes());
+                result.extend_from_slice(&current_ap);
+                elements
+            }
+            Self::ProbeRequest {elements} => elements,
+            Self::Beacon {timestamp, interval, capability, elements} => {
+                result.extend_from_slice(&timestamp.to_le_bytes());
+                result.extend_from_slice(&interval.to_le_bytes());
+                result.extend_from_slice(&capability.to_le_bytes());
+                elements
+            }
+            Self::Deauthentication {reason} => {
+                result.extend_from_slice(&reason.to_le_bytes());
+                Vec::new()
+            }
+            Self::Authentication {algorithm, transaction, status, elements} => {
+                result.extend_from_slice(&algorithm.to_le_bytes());
+                result.extend_from_slice(&transaction.to_le_bytes());
+                result.extend_from_slice(&status.to_le_bytes());
+                elements
+            }
+            Self::Other(body) => return body
+        };
+        InformationElement::write_all(elements, &mut result);
+        result
+    }
+}
+
+/// Struct for IEEE 802.11 MAC Frame without Frame Check Sequence
+/// You can construct it from scratch with `Ieee80211Frame::new()` and consistently editing
+/// Or construct from existing frame bytes with `Ieee80211Frame::deserialize()`
+/// Presence of optional fields is determined by `frame_control` on deserialization, and they are serialized as is
+/// All `u16` and `u32` fields of this frame **are in native order**
+#[derive(Debug, Clone)]
+pub struct Ieee80211Frame {
+    pub frame_control: FrameControl,
+    /// Duration or Association ID
+    pub duration: u16,
+    pub address1: [u8; 6],
+    /// Absent in ACK and CTS frames
+    pub address2: Option<[u8; 6]>,
+    /// Absent in Control frames
+    pub address3: Option<[u8; 6]>,
+    /// Absent in Control frames
+    pub sequence_control: Option<u16>,
+    /// Present in Data frames with both `to_ds` and `from_ds` set
+    pub address4: Option<[u8; 6]>,
+    /// Present in QoS Data frames
+    pub qos_control: Option<u16>,
+    /// Present in QoS Data and Management frames with `order` flag set
+    pub ht_control: Option<u32>,
+    pub body: Vec<u8>
+}
+impl Ieee80211Frame {
+    /// Constructs an empty Data `Ieee80211Frame` with three addresses
+    pub fn new() -> Self {
+        Self {
+            frame_control: FrameControl::new(2, 0),
+            duration: 0,
+            address1: [0u8; 6],
+            address2: Some([0u8; 6]),
+            address3: Some([0u8; 6]),
+            sequence_control: Some(0),
+            address4: None,
+            qos_control: None,
+            ht_control: None,
+            body: Vec::new()
+        }
+    }
+    /// Returns length of serialized frame without Frame Check Sequence, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let addresses = [self.address2, self.address3, self.address4].iter().flatten().count();
+        10 + addresses * 6
+            + self.sequence_control.map_or(0, |_| 2)
+            + self.qos_control.map_or(0, |_| 2)
+            + self.ht_control.map_or(0, |_| 4)
+            + self.body.len()
+    }
+    /// Returns receiver address, which is always `address1`
+    pub fn receiver(&self) -> [u8; 6] {
+        self.address1
+    }
+    /// Returns transmitter address, which is `address2` when present
+    pub fn transmitter(&self) -> Option<[u8; 6]> {
+        self.address2
+    }
+    /// Returns destination address according to `to_ds` and `from_ds` flags
+    pub fn destination(&self) -> Option<[u8; 6]> {
+        if self.frame_control.is_control() {return None;}
+        match self.frame_control.to_ds {
+            false => Some(self.address1),
+            true => self.address3
+        }
+    }
+    /// Returns source address according to `to_ds` and `from_ds` flags
+    pub fn source(&self) -> Option<[u8; 6]> {
+        if self.frame_control.is_control() {return None;}
+        match (self.frame_control.to_ds, self.frame_control.from_ds) {
+            (_, false) => self.address2,
+            (false, true) => self.address3,
+            (true, true) => self.address4
+        }
+    }
+    /// Returns BSSID according to `to_ds` and `from_ds` flags
+    /// Returns `None` for frames between distribution systems, which have no BSSID
+    pub fn bssid(&self) -> Option<[u8; 6]> {
+        if self.frame_control.is_control() {return None;}
+        match (self.frame_control.to_ds, self.frame_control.from_ds) {
+            (false, false) => self.address3,
+            (false, true) => self.address2,
+            (true, false) => Some(self.address1),
+            (true, true) => None
+        }
+    }
+    /// Returns `12 bits` sequence number from `sequence_control`
+    pub fn sequence_number(&self) -> Option<u16> {
+        self.sequence_control.map(|control| control >> 4)
+    }
+    /// Returns `4 bits` fragment number from `sequence_control`
+    pub fn fragment_number(&self) -> Option<u8> {
+        self.sequence_control.map(|control| (control & 0xF) as u8)
+    }
+    /// Returns Traffic Identifier from `qos_control`
+    pub fn tid(&self) -> Option<u8> {
+        self.qos_control.map(|control| (control & 0xF) as u8)
+    }
+    /// Decodes `body` of Management frame
+    /// Returns `Err(DeserializeError::WrongData)` if this isn't Management frame or its body is protected
+    pub fn management_body(&self) -> Result<ManagementBody, DeserializeError> {
+        if !self.frame_control.is_management() || self.frame_control.protected {return Err(DeserializeError::WrongData);}
+        ManagementBody::parse(self.frame_control.subtype, &self.body)
+    }
+    /// Returns EtherType and payload of unprotected Data frame body with LLC/SNAP header
+    pub fn snap_payload(&self) -> Option<(u16, &[u8])> {
+        if !self.frame_control.is_data() || self.frame_control.protected || self.body.len() < 8 {return None;}
+        if self.body[..3] != [0xAA, 0xAA, 0x03] || (self.body[3..6] != [0, 0, 0] && self.body[3..6] != [0, 0, 0xF8]) {return None;}
+        Some((u16::from_be_bytes([self.body[6], self.body[7]]), &self.body[8..]))
+    }
+    /// Returns `true` if last `4 bytes` of `bytes` are correct Frame Check Sequence of preceding bytes
+    pub fn verify_fcs(bytes: &[u8]) -> bool {
+        if bytes.len() < 4 {return false;}
+        let (frame, fcs) = bytes.split_at(bytes.len() - 4);
+        crc32(frame).to_le_bytes() == fcs
+    }
+    /// Constructs `Ieee80211Frame` from bytes ending with Frame Check Sequence, which is verified and stripped
+    /// Returns `Err(DeserializeError::WrongData)` if Frame Check Sequence is wrong
+    pub fn deserialize_with_fcs(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        if !Self::verify_fcs(bytes) {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&bytes[..bytes.len() - 4])
+    }
+}
+impl Default for Ieee80211Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for Ieee80211Frame {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = self.frame_control.serialize();
+        result.extend_from_slice(&self.duration.to_le_bytes());
+        result.extend_from_slice(&self.address1);
+        for address in [self.address2, self.address3].into_iter().flatten() {
+            result.extend_from_slice(&address);
+        }
+        if let Some(control) = self.sequence_control {
+            result.extend_from_slice(&control.to_le_bytes());
+        }
+        if let Some(address) = self.address4 {
+            result.extend_from_slice(&address);
+        }
+        if let Some(control) = self.qos_control {
+            result.extend_from_slice(&control.to_le_bytes());
+        }
+        if let Some(control) = self.ht_control {
+            result.extend_from_slice(&control.to_le_bytes());
+        }
+        result.extend_from_slice(&self.body);
+        result
+    }
+}
+impl Deserializable for Ieee80211Frame {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 10 {return Err(DeserializeError::WrongDataLength);}
+        let frame_control = FrameControl::deserialize(bytes)?;
+        let address = |i: usize| -> Result<[u8; 6], DeserializeError> {
+            bytes.get(i..i + 6).map(|address| *address.as_array().unwrap()).ok_or(DeserializeError::WrongDataLength)
+        };
+        let field = |i: usize| -> Result<u16, DeserializeError> {
+            bytes.get(i..i + 2).map(|field| u16::from_le_bytes([field[0], field[1]])).ok_or(DeserializeError::WrongDataLength)
+        };
+        let mut frame = Self {
+            frame_control,
+            duration: u16::from_le_bytes([bytes[2], bytes[3]]),
+            address1: address(4)?,
+            address2: None,
+            address3: None,
+            sequence_control: None,
+            address4: None,
+            qos_control: None,
+            ht_control: None,
+            body: Vec::new()
+        };
+        let mut i = 10usize;
+        if frame_control.is_control() {
+            // ACK, CTS and Control Wrapper frames carry only receiver address
+            if !matches!(frame_control.subtype, 12 | 13 | 6) {
+                frame.address2 = Some(address(10)?);
+                i = 16;
+            }
+        } else {
+            frame.address2 = Some(address(10)?);
+            frame.address3 = Some(address(16)?);
+            frame.sequence_control = Some(field(22)?);
+            i = 24;
+            if frame_control.is_data() && frame_control.to_ds && frame_control.from_ds {
+                frame.address4 = Some(address(i)?);
+                i += 6;
+            }
+            if frame_control.is_qos_data() {
+                frame.qos_control = Some(field(i)?);
+                i += 2;
+            }
+            if frame_control.order && (frame_control.is_qos_data() || frame_control.is_management()) {
+                let control = bytes.get(i..i + 4).ok_or(DeserializeError::WrongDataLength)?;
+                frame.ht_control = Some(u32::from_le_bytes(*control.as_array().unwrap()));
+                i += 4;
+            }
+        }
+        frame.body = bytes[i..].to_vec();
+        Ok(frame)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,169 @@
+use core::ops::Range;
+use crate::util::{ByteReader, ByteWriter, Serializable, Deserializable, DeserializeError, EtherPayload};
+
+/// Length of ICV of GCM-AES cipher suites, which is default and the only one defined by 802.1AE
+pub const DEFAULT_ICV_LENGTH: usize = 16;
+
+/// Secure Channel Identifier, MAC address of transmitting system and port number within it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sci {
+    pub mac: [u8; 6],
+    pub port: u16
+}
+impl Sci {
+    pub fn new(mac: [u8; 6], port: u16) -> Self {
+        Self {mac, port}
+    }
+    pub fn from_u64(value: u64) -> Self {
+        let bytes = value.to_be_bytes();
+        Self {
+            mac: bytes[..6].try_into().unwrap(),
+            port: u16::from_be_bytes([bytes[6], bytes[7]])
+        }
+    }
+    pub fn to_u64(&self) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes[..6].copy_from_slice(&self.mac);
+        bytes[6..].copy_from_slice(&self.port.to_be_bytes());
+        u64::from_be_bytes(bytes)
+    }
+}
+
+/// 802.1AE MACsec frame, which is payload of Ethernet frame with protocol 0x88E5
+/// Consists of SecTAG, Secure Data, which is encrypted when `encrypted` and `changed` are set, and ICV
+/// Padding of short Ethernet frames is stripped using Short Length field, which is computed when serializing
+/// You can construct it from scratch with `MacsecFrame::new()` and consistently editing
+/// Or construct from existing frame payload with `MacsecFrame::deserialize()`, or with `MacsecFrame::deserialize_with_icv_length()` for non-default cipher suites
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacsecFrame {
+    /// ES bit, frame is sent by end station, so its SCI can be omitted and is derived from source MAC address
+    pub end_station: bool,
+    /// SCB bit, frame belongs to EPON Single Copy Broadcast channel
+    pub single_copy_broadcast: bool,
+    /// E bit, Secure Data is encrypted
+    pub encrypted: bool,
+    /// C bit, Secure Data differs from user data, set together with `encrypted` for confidentiality protection
+    pub changed: bool,
+    /// Association Number of Secure Association in channel, actually only 2 bits
+    pub association_number: u8,
+    /// Packet Number, low 32 bits of it for Extended Packet Numbering cipher suites
+    pub packet_number: u32,
+    /// Explicit SCI, its presence is SC bit
+    pub sci: Option<Sci>,
+    /// User data starting with its EtherType if frame is integrity only, otherwise ciphertext
+    pub secure_data: Vec<u8>,
+    /// Integrity Check Value, `16 bytes` for GCM-AES cipher suites
+    pub icv: Vec<u8>
+}
+impl MacsecFrame {
+    /// Constructs an empty integrity only frame with explicit SCI and zeroed default length ICV
+    pub fn new(sci: Sci) -> Self {
+        Self {
+            end_station: false,
+            single_copy_broadcast: false,
+            encrypted: false,
+            changed: false,
+            association_number: 0,
+            packet_number: 1,
+            sci: Some(sci),
+            secure_data: Vec::new(),
+            icv: vec![0; DEFAULT_ICV_LENGTH]
+        }
+    }
+    /// Returns length of SecTAG, `8 bytes` without SCI or `16 bytes` with it, including EtherType
+    pub fn sectag_len(&self) -> usize {
+        if self.sci.is_some() {16} else {8}
+    }
+    /// Returns length of serialized frame, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.sectag_len() - 2 + self.secure_data.len() + self.icv.len()
+    }
+    /// Checks if Secure Data is encrypted, i.e. both E and C bits are set
+    pub fn is_confidential(&self) -> bool {
+        self.encrypted && self.changed
+    }
+    /// Returns EtherType of protected user data
+    /// Returns `None` if Secure Data is encrypted or too short
+    pub fn protected_ether_type(&self) -> Option<u16> {
+        if self.encrypted || self.changed || self.secure_data.len() < 2 {return None;}
+        Some(u16::from_be_bytes([self.secure_data[0], self.secure_data[1]]))
+    }
+    /// Returns protected user data without its EtherType
+    /// Returns `None` if Secure Data is encrypted or too short
+    pub fn protected_payload(&self) -> Option<&[u8]> {
+        self.protected_ether_type()?;
+        Some(&self.secure_data[2..])
+    }
+    /// Returns SCI of channel, which is explicit one or one derived from `source` MAC address with port 1 when it's omitted
+    pub fn effective_sci(&self, source: [u8; 6]) -> Sci {
+        self.sci.unwrap_or(Sci::new(source, 1))
+    }
+    /// Returns range of Secure Data in frame payload `bytes`, so encrypted data can be located without copying it
+    /// Data after ICV is padding of short Ethernet frames and is not included
+    /// Returns `Err(DeserializeError::WrongData)` if version bit of SecTAG is set
+    pub fn secure_data_bounds(bytes: &[u8], icv_length: usize) -> Result<Range<usize>, DeserializeError> {
+        let mut reader = ByteReader::new(bytes);
+        let tci = reader.read_u8()?;
+        if tci & 0x80 != 0 {return Err(DeserializeError::WrongData);}
+        let short_length = (reader.read_u8()? & 0x3F) as usize;
+        reader.skip(if tci & 0x20 != 0 {12} else {4})?;
+        let start = reader.position();
+        let length = match short_length {
+            0 => reader.remaining().checked_sub(icv_length).ok_or(DeserializeError::WrongDataLength)?,
+            _ if reader.remaining() < short_length + icv_length => return Err(DeserializeError::WrongDataLength),
+            _ => short_length
+        };
+        Ok(start..start + length)
+    }
+    /// Constructs `MacsecFrame` from bytes with ICV of `icv_length`, i.e. for cipher suites with shorter ICV
+    /// Returns `Err(DeserializeError::WrongData)` if version bit of SecTAG is set
+    pub fn deserialize_with_icv_length(bytes: &[u8], icv_length: usize) -> Result<Self, DeserializeError> {
+        let bounds = Self::secure_data_bounds(bytes, icv_length)?;
+        let tci = bytes[0];
+        let mut reader = ByteReader::new(&bytes[2..bounds.start]);
+        let packet_number = reader.read_u32()?;
+        let sci = match tci & 0x20 != 0 {
+            true => Some(Sci::from_u64(reader.read_u64()?)),
+            false => None
+        };
+        Ok(Self {
+            end_station: tci & 0x40 != 0,
+            single_copy_broadcast: tci & 0x10 != 0,
+            encrypted: tci & 0x08 != 0,
+            changed: tci & 0x04 != 0,
+            association_number: tci & 3,
+            packet_number,
+            sci,
+            secure_data: bytes[bounds.clone()].to_vec(),
+            icv: bytes[bounds.end..bounds.end + icv_length].to_vec()
+        })
+    }
+}
+impl Serializable for MacsecFrame {
+    fn serialize(self) -> Vec<u8> {
+        let tci = (self.end_station as u8) << 6
+            | (self.sci.is_some() as u8) << 5
+            | (self.single_copy_broadcast as u8) << 4
+            | (self.encrypted as u8) << 3
+            | (self.changed as u8) << 2
+            | self.association_number & 3;
+        let short_length = if self.secure_data.len() < 48 {self.secure_data.len() as u8} else {0};
+        let mut writer = ByteWriter::with_capacity(self.wire_len());
+        writer.write_u8(tci).write_u8(short_length).write_u32(self.packet_number);
+        if let Some(sci) = self.sci {writer.write_u64(sci.to_u64());}
+        writer.write_slice(&self.secure_data).write_slice(&self.icv);
+        writer.into_bytes()
+    }
+}
+impl Deserializable for MacsecFrame {
+    /// Constructs `MacsecFrame` from bytes, assuming default `16 bytes` ICV
+    /// Returns `Err(DeserializeError::WrongData)` if version bit of SecTAG is set
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with_icv_length(bytes, DEFAULT_ICV_LENGTH)
+    }
+}
+impl EtherPayload for MacsecFrame {
+    fn ether_type(&self) -> u16 {
+        0x88E5
+    }
+}
@@ -1 +1,14 @@
-pub mod ethernet;
\ No newline at end of file
+pub mod ethernet;
+#[cfg(feature = "ppp")]
+pub mod ppp;
+#[cfg(feature = "ieee80211")]
+pub mod ieee80211;
+#[cfg(feature = "radiotap")]
+pub mod radiotap;
+pub mod sll;
+#[cfg(feature = "eapol")]
+pub mod eapol;
+#[cfg(feature = "eap")]
+pub mod eap;
+#[cfg(feature = "macsec")]
+pub mod macsec;
\ No newline at end of file
@@ -0,0 +1,397 @@
+use crate::l3::{ipv4::Ipv4Packet, ipv6::Ipv6Packet};
+use crate::util::{Serializable, Deserializable, DeserializeError, EtherPayload, ValidationIssue};
+
+/// LCP, IPCP or IPv6CP packet
+/// All of these Control Protocols share the same format: code, identifier, length and data
+#[derive(Debug, Clone)]
+pub struct PppControlPacket {
+    /// Packet code, i.e. 1 for Configure-Request, 2 for Configure-Ack, 5 for Terminate-Request, 9 for Echo-Request
+    pub code: u8,
+    /// Identifier for matching requests and replies
+    pub identifier: u8,
+    pub data: Vec<u8>
+}
+impl PppControlPacket {
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        4 + self.data.len()
+    }
+    /// Returns configuration options(`type`, `data`) of Configure-Request, Configure-Ack, Configure-Nak or Configure-Reject packet
+    /// Returns `None` for packets of other codes or if options are malformed
+    pub fn options(&self) -> Option<Vec<(u8, Vec<u8>)>> {
+        if !(1..=4).contains(&self.code) {return None;}
+        let mut options = Vec::new();
+        let mut i = 0usize;
+        while i < self.data.len() {
+            if self.data.len() < i + 2 {return None;}
+            let length = self.data[i + 1] as usize;
+            if length < 2 || self.data.len() < i + length {return None;}
+            options.push((self.data[i], self.data[i + 2..i + length].to_vec()));
+            i += length;
+        }
+        Some(options)
+    }
+}
+impl Serializable for PppControlPacket {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = vec![self.code, self.identifier];
+        result.extend_from_slice(&(self.data.len() as u16 + 4).to_be_bytes());
+        result.append(&mut self.data);
+        result
+    }
+}
+impl Deserializable for PppControlPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 4 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            code: bytes[0],
+            identifier: bytes[1],
+            data: bytes[4..length].to_vec()
+        })
+    }
+}
+
+/// Password Authentication Protocol packet
+#[derive(Debug, Clone)]
+pub enum PapPacket {
+    /// Code 1
+    AuthenticateRequest {
+        identifier: u8,
+        peer_id: Vec<u8>,
+        password: Vec<u8>
+    },
+    /// Code 2
+    AuthenticateAck {
+        identifier: u8,
+        message: Vec<u8>
+    },
+    /// Code 3
+    AuthenticateNak {
+        identifier: u8,
+        message: Vec<u8>
+    }
+}
+impl PapPacket {
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::AuthenticateRequest {peer_id, password, ..} => 6 + peer_id.len() + password.len(),
+            Self::AuthenticateAck {message, ..} |
+            Self::AuthenticateNak {message, ..} => 5 + message.len()
+        }
+    }
+}
+impl Serializable for PapPacket {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 4];
+        let (code, identifier, fields) = match self {
+            Self::AuthenticateRequest {identifier, peer_id, password} => (1, identifier, vec![peer_id, password]),
+            Self::AuthenticateAck {identifier, message} => (2, identifier, vec![message]),
+            Self::AuthenticateNak {identifier, message} => (3, identifier, vec![message])
+        };
+        result[0] = code;
+        result[1] = identifier;
+        for mut field in fields {
+            result.push(field.len() as u8);
+            result.append(&mut field);
+        }
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for PapPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let packet = PppControlPacket::deserialize(bytes)?;
+        let data = &packet.data;
+        let read_field = |start: usize| -> Result<Vec<u8>, DeserializeError> {
+            if data.len() < start + 1 {return Err(DeserializeError::WrongDataLength);}
+            let end = start + 1 + data[start] as usize;
+            if data.len() < end {return Err(DeserializeError::WrongDataLength);}
+            Ok(data[start + 1..end].to_vec())
+        };
+        let identifier = packet.identifier;
+        match packet.code {
+            1 => {
+                let peer_id = read_field(0)?;
+                let password = read_field(1 + peer_id.len())?;
+                Ok(Self::AuthenticateRequest {identifier, peer_id, password})
+            }
+            2 => Ok(Self::AuthenticateAck {identifier, message: read_field(0)?}),
+            3 => Ok(Self::AuthenticateNak {identifier, message: read_field(0)?}),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+
+/// Challenge Handshake Authentication Protocol packet
+#[derive(Debug, Clone)]
+pub enum ChapPacket {
+    /// Code 1
+    Challenge {
+        identifier: u8,
+        value: Vec<u8>,
+        name: Vec<u8>
+    },
+    /// Code 2
+    Response {
+        identifier: u8,
+        /// Hash of identifier, secret and challenge value
+        value: Vec<u8>,
+        name: Vec<u8>
+    },
+    /// Code 3
+    Success {
+        identifier: u8,
+        message: Vec<u8>
+    },
+    /// Code 4
+    Failure {
+        identifier: u8,
+        message: Vec<u8>
+    }
+}
+impl ChapPacket {
+    pub fn get_code(&self) -> u8 {
+        match self {
+            Self::Challenge {..} => 1,
+            Self::Response {..} => 2,
+            Self::Success {..} => 3,
+            Self::Failure {..} => 4
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::Challenge {value, name, ..} |
+            Self::Response {value, name, ..} => 5 + value.len() + name.len(),
+            Self::Success {message, ..} |
+            Self::Failure {message, ..} => 4 + message.len()
+        }
+    }
+}
+impl Serializable for ChapPacket {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![self.get_code(), 0, 0, 0];
+        match self {
+            Self::Challenge {identifier, mut value, mut name} |
+            Self::Response {identifier, mut value, mut name} => {
+                result[1] = identifier;
+                result.push(value.len() as u8);
+                result.append(&mut value);
+                result.append(&mut name);
+            }
+            Self::Success {identifier, mut message} |
+            Self::Failure {identifier, mut message} => {
+                result[1] = identifier;
+                result.append(&mut message);
+            }
+        }
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for ChapPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let packet = PppControlPacket::deserialize(bytes)?;
+        let identifier = packet.identifier;
+        match packet.code {
+            1 | 2 => {
+                if packet.data.is_empty() {return Err(DeserializeError::WrongDataLength);}
+                let value_end = 1 + packet.data[0] as usize;
+                if packet.data.len() < value_end {return Err(DeserializeError::WrongDataLength);}
+                let value = packet.data[1..value_end].to_vec();
+                let name = packet.data[value_end..].to_vec();
+                if packet.code == 1 {
+                    Ok(Self::Challenge {identifier, value, name})
+                }
+                else {
+                    Ok(Self::Response {identifier, value, name})
+                }
+            }
+            3 => Ok(Self::Success {identifier, message: packet.data}),
+            4 => Ok(Self::Failure {identifier, message: packet.data}),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+
+/// Decoded payload of PPP frame
+#[derive(Debug, Clone)]
+pub enum PppPayload {
+    /// Protocol 0xC021 - Link Control Protocol
+    Lcp(PppControlPacket),
+    /// Protocol 0x8021 - IP Control Protocol
+    Ipcp(PppControlPacket),
+    /// Protocol 0x8057 - IPv6 Control Protocol
+    Ipv6cp(PppControlPacket),
+    /// Protocol 0xC023
+    Pap(PapPacket),
+    /// Protocol 0xC223
+    Chap(ChapPacket),
+    /// Protocol 0x0021
+    Ipv4(Ipv4Packet),
+    /// Protocol 0x0057
+    Ipv6(Ipv6Packet),
+    /// Any other protocol presented just with raw data
+    Unknown {
+        protocol: u16,
+        data: Vec<u8>
+    }
+}
+
+/// Struct for PPP frame, as it is carried by PPPoE session(`PppoeSession`) and L2TP data messages
+/// You can construct it from scratch with `PppFrame::new()` and consistently editing
+/// Or construct from existing frame bytes with `PppFrame::deserialize()`
+#[derive(Debug, Clone)]
+pub struct PppFrame {
+    /// HDLC-like Address and Control fields `0xFF 0x03` precede protocol field
+    /// Usually they are present in L2TP and absent in PPPoE
+    pub address_control: bool,
+    /// Protocol of payload, i.e. 0x0021 for IPv4, 0xC021 for LCP
+    /// Note that protocol is always serialized in `2 bytes`, but compressed `1 byte` protocol field is accepted on deserialization
+    pub protocol: u16,
+    pub payload: Vec<u8>
+}
+impl PppFrame {
+    /// Constructs an empty IPv4 `PppFrame` without Address and Control fields
+    pub fn new() -> Self {
+        Self {
+            address_control: false,
+            protocol: 0x0021,
+            payload: Vec::new()
+        }
+    }
+    /// Returns length of serialized frame, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        2 + self.address_control as usize * 2 + self.payload.len()
+    }
+    /// Decodes payload according to `protocol`
+    pub fn decode(&self) -> Result<PppPayload, DeserializeError> {
+        match self.protocol {
+            0xC021 => Ok(PppPayload::Lcp(PppControlPacket::deserialize(&self.payload)?)),
+            0x8021 => Ok(PppPayload::Ipcp(PppControlPacket::deserialize(&self.payload)?)),
+            0x8057 => Ok(PppPayload::Ipv6cp(PppControlPacket::deserialize(&self.payload)?)),
+            0xC023 => Ok(PppPayload::Pap(PapPacket::deserialize(&self.payload)?)),
+            0xC223 => Ok(PppPayload::Chap(ChapPacket::deserialize(&self.payload)?)),
+            0x0021 => Ok(PppPayload::Ipv4(Ipv4Packet::deserialize(&self.payload)?)),
+            0x0057 => Ok(PppPayload::Ipv6(Ipv6Packet::deserialize(&self.payload)?)),
+            protocol => Ok(PppPayload::Unknown {protocol, data: self.payload.clone()})
+        }
+    }
+}
+impl Default for PppFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for PppFrame {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.payload.len() + 4);
+        if self.address_control {
+            result.extend_from_slice(&[0xFF, 0x03]);
+        }
+        result.extend_from_slice(&self.protocol.to_be_bytes());
+        result.append(&mut self.payload);
+        result
+    }
+}
+impl Deserializable for PppFrame {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let address_control = bytes.starts_with(&[0xFF, 0x03]);
+        let bytes = if address_control {&bytes[2..]} else {bytes};
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        let (protocol, header_len) = if bytes[0] & 1 != 0 {
+            (bytes[0] as u16, 1)
+        }
+        else {
+            if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+            (u16::from_be_bytes([bytes[0], bytes[1]]), 2)
+        };
+        Ok(Self {
+            address_control,
+            protocol,
+            payload: bytes[header_len..].to_vec()
+        })
+    }
+}
+/// PPPoE session stage packet, which is payload of Ethernet frame with protocol 0x8864 and carries PPP frame, as described in RFC 2516
+/// You can construct it from scratch with `PppoeSession::new()` and consistently editing
+/// Or construct from existing frame payload with `PppoeSession::deserialize()`
+#[derive(Debug, Clone)]
+pub struct PppoeSession {
+    /// Session identifier assigned by Access Concentrator in PADS packet
+    pub session_id: u16,
+    /// PPP frame without Address and Control fields
+    pub payload: Vec<u8>
+}
+impl PppoeSession {
+    /// Constructs `PppoeSession` of `session_id` carrying serialized `frame`
+    pub fn new(session_id: u16, frame: PppFrame) -> Self {
+        Self {
+            session_id,
+            payload: frame.serialize()
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        6 + self.payload.len()
+    }
+    /// Returns PPP frame carried by this packet
+    pub fn ppp_frame(&self) -> Result<PppFrame, DeserializeError> {
+        PppFrame::deserialize(&self.payload)
+    }
+    /// Checks raw PPPoE session packet `bytes` for problems: truncation, wrong version, type or code
+    /// Bytes after payload length are considered padding of short Ethernet frames
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 6 {
+            issues.push(ValidationIssue::Truncated {layer: "pppoe", needed: 6, actual: bytes.len()});
+            return issues;
+        }
+        if bytes[0] != 0x11 {
+            issues.push(ValidationIssue::InvalidField {layer: "pppoe", field: "version_type", value: bytes[0] as u32});
+        }
+        if bytes[1] != 0 {
+            issues.push(ValidationIssue::InvalidField {layer: "pppoe", field: "code", value: bytes[1] as u32});
+        }
+        let length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        if 6 + length > bytes.len() {
+            issues.push(ValidationIssue::Truncated {layer: "pppoe", needed: 6 + length, actual: bytes.len()});
+        }
+        issues
+    }
+}
+impl Serializable for PppoeSession {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(6 + self.payload.len());
+        result.extend_from_slice(&[0x11, 0x00]);
+        result.extend_from_slice(&self.session_id.to_be_bytes());
+        result.extend_from_slice(&(self.payload.len() as u16).to_be_bytes());
+        result.append(&mut self.payload);
+        result
+    }
+}
+impl Deserializable for PppoeSession {
+    /// Constructs `PppoeSession` from bytes, padding of short Ethernet frames is stripped using payload length
+    /// Returns `Err(DeserializeError::WrongData)` if version or type isn't 1, or code isn't 0, which is the only code of session stage
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 6 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0] != 0x11 || bytes[1] != 0 {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        if bytes.len() < 6 + length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            session_id: u16::from_be_bytes([bytes[2], bytes[3]]),
+            payload: bytes[6..6 + length].to_vec()
+        })
+    }
+}
+impl EtherPayload for PppoeSession {
+    fn ether_type(&self) -> u16 {
+        0x8864
+    }
+}
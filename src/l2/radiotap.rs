@@ -0,0 +1,209 @@
+use crate::l2::ieee80211::Ieee80211Frame;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// Alignment and size of Radiotap fields by their bit number in `present` bitmap
+const FIELDS: [(usize, usize); 23] = [
+    (8, 8), (1, 1), (1, 1), (2, 4), (1, 2), (1, 1), (1, 1), (2, 2),
+    (2, 2), (2, 2), (1, 1), (1, 1), (1, 1), (1, 1), (2, 2), (2, 2),
+    (1, 1), (1, 1), (4, 8), (1, 3), (4, 8), (2, 12), (8, 12)
+];
+
+/// Radiotap Channel field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RadiotapChannel {
+    /// Center frequency in MHz
+    pub frequency: u16,
+    /// Channel flags, i.e. 0x0080 for 2 GHz spectrum, 0x0100 for 5 GHz spectrum
+    pub flags: u16
+}
+impl RadiotapChannel {
+    /// Returns IEEE channel number for frequency in 2.4 GHz or 5 GHz bands
+    pub fn number(&self) -> Option<u8> {
+        match self.frequency {
+            2484 => Some(14),
+            2412..=2472 => Some(((self.frequency - 2407) / 5) as u8),
+            5000..=5895 => Some(((self.frequency - 5000) / 5) as u8),
+            _ => None
+        }
+    }
+}
+
+/// Struct for Radiotap Header, which precedes 802.11 frames in monitor-mode captures
+/// You can construct it from scratch with `RadiotapHeader::new()` and consistently editing
+/// Or construct from existing header bytes with `RadiotapHeader::deserialize()`
+/// Only fields of the first `present` bitmap are decoded, fields of extended bitmaps and other namespaces are skipped
+/// `present` bitmap is calculated automatically on serialization
+#[derive(Debug, Clone)]
+pub struct RadiotapHeader {
+    /// Header version, always 0
+    pub version: u8,
+    /// Bit 0 - TSF timer in microseconds
+    pub tsft: Option<u64>,
+    /// Bit 1 - frame flags, i.e. 0x10 when frame includes FCS, 0x40 when FCS is bad
+    pub flags: Option<u8>,
+    /// Bit 2 - data rate in `500 kbps` units
+    pub rate: Option<u8>,
+    /// Bit 3
+    pub channel: Option<RadiotapChannel>,
+    /// Bit 5 - signal power in dBm
+    pub antenna_signal: Option<i8>,
+    /// Bit 6 - noise power in dBm
+    pub antenna_noise: Option<i8>,
+    /// Bit 10 - transmit power in dBm
+    pub tx_power: Option<i8>,
+    /// Bit 11 - antenna index
+    pub antenna: Option<u8>,
+    /// Bit 14
+    pub rx_flags: Option<u16>,
+    /// Bit 19 - known, flags and MCS index
+    pub mcs: Option<[u8; 3]>,
+    /// Other fields of the first bitmap presented with bit number and raw content
+    pub other_fields: Vec<(u8, Vec<u8>)>
+}
+impl RadiotapHeader {
+    /// Constructs an empty `RadiotapHeader`
+    pub fn new() -> Self {
+        Self {
+            version: 0,
+            tsft: None,
+            flags: None,
+            rate: None,
+            channel: None,
+            antenna_signal: None,
+            antenna_noise: None,
+            tx_power: None,
+            antenna: None,
+            rx_flags: None,
+            mcs: None,
+            other_fields: Vec::new()
+        }
+    }
+    /// Returns `true` if `flags` tell that 802.11 frame ends with Frame Check Sequence
+    pub fn has_fcs(&self) -> bool {
+        self.flags.is_some_and(|flags| flags & 0x10 != 0)
+    }
+    /// Returns length of serialized header with alignment padding, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let known = [
+            (0, self.tsft.is_some()),
+            (1, self.flags.is_some()),
+            (2, self.rate.is_some()),
+            (3, self.channel.is_some()),
+            (5, self.antenna_signal.is_some()),
+            (6, self.antenna_noise.is_some()),
+            (10, self.tx_power.is_some()),
+            (11, self.antenna.is_some()),
+            (14, self.rx_flags.is_some()),
+            (19, self.mcs.is_some())
+        ];
+        let present = known.iter().filter(|(_, present)| *present).map(|(bit, _)| *bit)
+            .chain(self.other_fields.iter().map(|(bit, _)| *bit as usize).filter(|bit| *bit < FIELDS.len()))
+            .fold(0u32, |present, bit| present | 1 << bit);
+        (0..FIELDS.len()).filter(|bit| present & 1 << bit != 0).fold(8, |length, bit| {
+            let (align, size) = FIELDS[bit];
+            length.next_multiple_of(align) + size
+        })
+    }
+    /// Returns header length from the start of header `bytes`
+    pub fn header_length(bytes: &[u8]) -> Result<usize, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        let length = u16::from_le_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 8 || bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        Ok(length)
+    }
+    /// Constructs `RadiotapHeader` and following 802.11 frame from monitor-mode captured `bytes`
+    /// Frame Check Sequence is verified and stripped if `flags` tell that frame includes it
+    pub fn deserialize_with_frame(bytes: &[u8]) -> Result<(Self, Ieee80211Frame), DeserializeError> {
+        let header = Self::deserialize(bytes)?;
+        let frame = &bytes[Self::header_length(bytes)?..];
+        let frame = match header.has_fcs() {
+            true => Ieee80211Frame::deserialize_with_fcs(frame)?,
+            false => Ieee80211Frame::deserialize(frame)?
+        };
+        Ok((header, frame))
+    }
+}
+impl Default for RadiotapHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for RadiotapHeader {
+    fn serialize(self) -> Vec<u8> {
+        let mut fields: Vec<(u8, Vec<u8>)> = self.other_fields.into_iter().filter(|(bit, _)| (*bit as usize) < FIELDS.len()).collect();
+        let known = [
+            (0, self.tsft.map(|value| value.to_le_bytes().to_vec())),
+            (1, self.flags.map(|value| vec![value])),
+            (2, self.rate.map(|value| vec![value])),
+            (3, self.channel.map(|channel| [channel.frequency.to_le_bytes(), channel.flags.to_le_bytes()].concat())),
+            (5, self.antenna_signal.map(|value| vec![value as u8])),
+            (6, self.antenna_noise.map(|value| vec![value as u8])),
+            (10, self.tx_power.map(|value| vec![value as u8])),
+            (11, self.antenna.map(|value| vec![value])),
+            (14, self.rx_flags.map(|value| value.to_le_bytes().to_vec())),
+            (19, self.mcs.map(|value| value.to_vec()))
+        ];
+        for (bit, content) in known {
+            if let Some(content) = content {
+                fields.push((bit, content));
+            }
+        }
+        fields.sort_by_key(|(bit, _)| *bit);
+        fields.dedup_by_key(|(bit, _)| *bit);
+        let present = fields.iter().fold(0u32, |present, (bit, _)| present | 1 << bit);
+        let mut result = vec![self.version, 0, 0, 0];
+        result.extend_from_slice(&present.to_le_bytes());
+        for (bit, mut content) in fields {
+            let (align, size) = FIELDS[bit as usize];
+            content.resize(size, 0);
+            result.resize(result.len().next_multiple_of(align), 0);
+            result.append(&mut content);
+        }
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_le_bytes());
+        result
+    }
+}
+impl Deserializable for RadiotapHeader {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let length = Self::header_length(bytes)?;
+        let bytes = &bytes[..length];
+        let present = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        // Extended bitmaps follow the first one while bit 31 is set
+        let mut i = 8usize;
+        let mut extended = present;
+        while extended & 0x8000_0000 != 0 {
+            if bytes.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+            extended = u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+            i += 4;
+        }
+        let mut header = Self::new();
+        header.version = bytes[0];
+        for bit in 0..29u8 {
+            if present & 1 << bit == 0 {continue;}
+            // Size of unknown field isn't known, so fields after it can't be located
+            let Some((align, size)) = FIELDS.get(bit as usize).copied() else {break;};
+            i = i.next_multiple_of(align);
+            if bytes.len() < i + size {return Err(DeserializeError::WrongDataLength);}
+            let content = &bytes[i..i + size];
+            i += size;
+            match bit {
+                0 => header.tsft = Some(u64::from_le_bytes(*content.as_array().unwrap())),
+                1 => header.flags = Some(content[0]),
+                2 => header.rate = Some(content[0]),
+                3 => header.channel = Some(RadiotapChannel {
+                    frequency: u16::from_le_bytes([content[0], content[1]]),
+                    flags: u16::from_le_bytes([content[2], content[3]])
+                }),
+                5 => header.antenna_signal = Some(content[0] as i8),
+                6 => header.antenna_noise = Some(content[0] as i8),
+                10 => header.tx_power = Some(content[0] as i8),
+                11 => header.antenna = Some(content[0]),
+                14 => header.rx_flags = Some(u16::from_le_bytes([content[0], content[1]])),
+                19 => header.mcs = Some(*content.as_array().unwrap()),
+                _ => header.other_fields.push((bit, content.to_vec()))
+            }
+        }
+        Ok(header)
+    }
+}
\ No newline at end of file
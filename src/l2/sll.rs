@@ -0,0 +1,196 @@
+use crate::l2::ethernet::payload_length;
+use crate::l3::IpPacket;
+#[cfg(feature = "arp")]
+use crate::l3::arp::ArpPacket;
+use crate::util::{ByteReader, ByteWriter, Serializable, Deserializable, DeserializeError};
+
+/// Returns payload with padding of short frames stripped, when `protocol` is IPv4, IPv6 or ARP
+fn strip_padding(protocol: u16, payload: &[u8]) -> &[u8] {
+    match payload_length(protocol, payload) {
+        Some(length) if length < payload.len() => &payload[..length],
+        _ => payload
+    }
+}
+
+/// Decodes `payload` of `protocol` as IP packet
+fn ip_packet(protocol: u16, payload: &[u8]) -> Result<IpPacket, DeserializeError> {
+    match protocol {
+        0x0800 | 0x86DD => IpPacket::deserialize(payload),
+        _ => Err(DeserializeError::WrongData)
+    }
+}
+
+/// Decodes `payload` of `protocol` as ARP packet
+#[cfg(feature = "arp")]
+fn arp_packet(protocol: u16, payload: &[u8]) -> Result<ArpPacket, DeserializeError> {
+    match protocol {
+        0x0806 => ArpPacket::deserialize(payload),
+        _ => Err(DeserializeError::WrongData)
+    }
+}
+
+/// Struct for Linux cooked capture (SLL) pseudo-header with payload, produced by `tcpdump -i any`
+/// You can construct it from scratch with `LinuxSllFrame::new()` and consistently editing
+/// Or construct from existing capture bytes with `LinuxSllFrame::deserialize()`
+#[derive(Debug, Clone)]
+pub struct LinuxSllFrame {
+    /// Packet type, 0 for sent to us, 1 for broadcast, 2 for multicast, 3 for sent to other host, 4 for sent by us
+    pub packet_type: u16,
+    /// ARPHRD type of interface, i.e. 1 for Ethernet, 772 for loopback
+    pub arphrd_type: u16,
+    /// Link-layer source address, up to `8 bytes`
+    pub address: Vec<u8>,
+    /// EtherType of payload, i.e. 0x0800 for IPv4
+    pub protocol: u16,
+    pub payload: Vec<u8>
+}
+impl LinuxSllFrame {
+    /// Constructs an empty `LinuxSllFrame`
+    pub fn new() -> Self {
+        Self {
+            packet_type: 0,
+            arphrd_type: 1,
+            address: Vec::new(),
+            protocol: 0,
+            payload: Vec::new()
+        }
+    }
+    /// Returns length of serialized frame, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        16 + self.payload.len()
+    }
+    /// Decodes payload as IPv4 or IPv6 packet
+    /// Returns `Err(DeserializeError::WrongData)` if `protocol` is neither IPv4 nor IPv6
+    pub fn ip_packet(&self) -> Result<IpPacket, DeserializeError> {
+        ip_packet(self.protocol, &self.payload)
+    }
+    /// Decodes payload as ARP packet
+    /// Returns `Err(DeserializeError::WrongData)` if `protocol` isn't ARP
+    #[cfg(feature = "arp")]
+    pub fn arp_packet(&self) -> Result<ArpPacket, DeserializeError> {
+        arp_packet(self.protocol, &self.payload)
+    }
+}
+impl Default for LinuxSllFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for LinuxSllFrame {
+    fn serialize(self) -> Vec<u8> {
+        let mut writer = ByteWriter::with_capacity(16 + self.payload.len());
+        writer.write_u16(self.packet_type)
+            .write_u16(self.arphrd_type)
+            .write_u16(self.address.len().min(8) as u16)
+            .write_padded(&self.address, 8)
+            .write_u16(self.protocol)
+            .write_slice(&self.payload);
+        writer.into_bytes()
+    }
+}
+impl Deserializable for LinuxSllFrame {
+    /// Constructs `LinuxSllFrame` from bytes
+    /// Padding of short frames carrying IPv4, IPv6 or ARP is stripped from payload using length from inner packet header
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut reader = ByteReader::new(bytes);
+        let packet_type = reader.read_u16()?;
+        let arphrd_type = reader.read_u16()?;
+        let length = (reader.read_u16()? as usize).min(8);
+        let address = reader.read_array::<8>()?;
+        let protocol = reader.read_u16()?;
+        Ok(Self {
+            packet_type,
+            arphrd_type,
+            address: address[..length].to_vec(),
+            protocol,
+            payload: strip_padding(protocol, reader.read_rest()).to_vec()
+        })
+    }
+}
+
+/// Struct for Linux cooked capture v2 (SLL2) pseudo-header with payload, produced by `tcpdump -i any` since libpcap 1.10
+/// You can construct it from scratch with `LinuxSll2Frame::new()` and consistently editing
+/// Or construct from existing capture bytes with `LinuxSll2Frame::deserialize()`
+#[derive(Debug, Clone)]
+pub struct LinuxSll2Frame {
+    /// EtherType of payload, i.e. 0x0800 for IPv4
+    pub protocol: u16,
+    /// Index of interface packet was captured on
+    pub interface_index: u32,
+    /// ARPHRD type of interface, i.e. 1 for Ethernet, 772 for loopback
+    pub arphrd_type: u16,
+    /// Packet type, 0 for sent to us, 1 for broadcast, 2 for multicast, 3 for sent to other host, 4 for sent by us
+    pub packet_type: u8,
+    /// Link-layer source address, up to `8 bytes`
+    pub address: Vec<u8>,
+    pub payload: Vec<u8>
+}
+impl LinuxSll2Frame {
+    /// Constructs an empty `LinuxSll2Frame`
+    pub fn new() -> Self {
+        Self {
+            protocol: 0,
+            interface_index: 0,
+            arphrd_type: 1,
+            packet_type: 0,
+            address: Vec::new(),
+            payload: Vec::new()
+        }
+    }
+    /// Returns length of serialized frame, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        20 + self.payload.len()
+    }
+    /// Decodes payload as IPv4 or IPv6 packet
+    /// Returns `Err(DeserializeError::WrongData)` if `protocol` is neither IPv4 nor IPv6
+    pub fn ip_packet(&self) -> Result<IpPacket, DeserializeError> {
+        ip_packet(self.protocol, &self.payload)
+    }
+    /// Decodes payload as ARP packet
+    /// Returns `Err(DeserializeError::WrongData)` if `protocol` isn't ARP
+    #[cfg(feature = "arp")]
+    pub fn arp_packet(&self) -> Result<ArpPacket, DeserializeError> {
+        arp_packet(self.protocol, &self.payload)
+    }
+}
+impl Default for LinuxSll2Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for LinuxSll2Frame {
+    fn serialize(self) -> Vec<u8> {
+        let mut writer = ByteWriter::with_capacity(20 + self.payload.len());
+        writer.write_u16(self.protocol)
+            .write_u16(0)
+            .write_u32(self.interface_index)
+            .write_u16(self.arphrd_type)
+            .write_u8(self.packet_type)
+            .write_u8(self.address.len().min(8) as u8)
+            .write_padded(&self.address, 8)
+            .write_slice(&self.payload);
+        writer.into_bytes()
+    }
+}
+impl Deserializable for LinuxSll2Frame {
+    /// Constructs `LinuxSll2Frame` from bytes
+    /// Padding of short frames carrying IPv4, IPv6 or ARP is stripped from payload using length from inner packet header
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut reader = ByteReader::new(bytes);
+        let protocol = reader.read_u16()?;
+        reader.skip(2)?;
+        let interface_index = reader.read_u32()?;
+        let arphrd_type = reader.read_u16()?;
+        let packet_type = reader.read_u8()?;
+        let length = (reader.read_u8()? as usize).min(8);
+        let address = reader.read_array::<8>()?;
+        Ok(Self {
+            protocol,
+            interface_index,
+            arphrd_type,
+            packet_type,
+            address: address[..length].to_vec(),
+            payload: strip_padding(protocol, reader.read_rest()).to_vec()
+        })
+    }
+}
\ No newline at end of file
@@ -1,14 +1,19 @@
 use core::net::Ipv4Addr;
+#[cfg(feature = "advanced-arp")]
+use core::net::Ipv6Addr;
+use core::time::Duration;
+use std::collections::HashMap;
 
-use crate::util::{Serializable, Deserializable, DeserializeError};
+use crate::util::{ByteReader, ByteWriter, Serializable, Deserializable, DeserializeError, EtherPayload, ValidationIssue};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArpOperation {
     Request = 1,
     Reply = 2
 }
 impl Serializable for ArpOperation {
     fn serialize(self) -> Vec<u8> {
-        vec![self as u8]
+        (self as u16).to_be_bytes().to_vec()
     }
 }
 impl Deserializable for ArpOperation {
@@ -22,6 +27,48 @@ impl Deserializable for ArpOperation {
     }
 }
 
+/// Hardware type of ARP packet, which is ARPHRD type of interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HardwareType {
+    /// 1 - Ethernet
+    Ethernet,
+    /// 6 - IEEE 802 networks
+    Ieee802,
+    /// 15 - Frame Relay
+    FrameRelay,
+    /// 16 - Asynchronous Transfer Mode
+    Atm,
+    /// 32 - InfiniBand
+    Infiniband,
+    /// Any other hardware type
+    Other(u16)
+}
+impl From<u16> for HardwareType {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => Self::Ethernet,
+            6 => Self::Ieee802,
+            15 => Self::FrameRelay,
+            16 => Self::Atm,
+            32 => Self::Infiniband,
+            other => Self::Other(other)
+        }
+    }
+}
+impl From<HardwareType> for u16 {
+    fn from(value: HardwareType) -> Self {
+        match value {
+            HardwareType::Ethernet => 1,
+            HardwareType::Ieee802 => 6,
+            HardwareType::FrameRelay => 15,
+            HardwareType::Atm => 16,
+            HardwareType::Infiniband => 32,
+            HardwareType::Other(other) => other
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ArpPacket {
     pub operation: ArpOperation,
     pub sender_mac: [u8; 6],
@@ -39,35 +86,376 @@ impl ArpPacket {
             target_ip: Ipv4Addr::UNSPECIFIED
         }
     }
+    /// Returns length of serialized packet, which is always `28 bytes` for Ethernet and IPv4
+    pub fn wire_len(&self) -> usize {
+        28
+    }
+    /// Checks raw ARP packet `bytes` for problems: wrong length, unsupported hardware or protocol type and unknown operation
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 28 {
+            issues.push(ValidationIssue::Truncated {layer: "arp", needed: 28, actual: bytes.len()});
+            return issues;
+        }
+        if bytes.len() > 28 {
+            issues.push(ValidationIssue::LengthMismatch {layer: "arp", field: "packet", expected: 28, actual: bytes.len()});
+        }
+        let fields = [
+            ("hardware_type", u16::from_be_bytes([bytes[0], bytes[1]]), 1),
+            ("protocol_type", u16::from_be_bytes([bytes[2], bytes[3]]), 0x0800),
+            ("hardware_length", bytes[4] as u16, 6),
+            ("protocol_length", bytes[5] as u16, 4)
+        ];
+        for (field, value, expected) in fields {
+            if value != expected {
+                issues.push(ValidationIssue::InvalidField {layer: "arp", field, value: value as u32});
+            }
+        }
+        let operation = u16::from_be_bytes([bytes[6], bytes[7]]);
+        if operation != 1 && operation != 2 {
+            issues.push(ValidationIssue::InvalidField {layer: "arp", field: "operation", value: operation as u32});
+        }
+        issues
+    }
+}
+impl Default for ArpPacket {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Serializable for ArpPacket {
     fn serialize(self) -> Vec<u8> {
-        let mut result = vec![0u8; 28];
-        result[1] = 1;
-        result[2] = 8;
-        result[4] = 6;
-        result[5] = 4;
-        result[6..8].copy_from_slice(&self.operation.serialize());
-        result[8..14].copy_from_slice(&self.sender_mac);
-        result[14..18].copy_from_slice(&self.sender_ip.octets());
-        result[18..24].copy_from_slice(&self.target_mac);
-        result[24..28].copy_from_slice(&self.target_ip.octets());
-        result
+        let mut writer = ByteWriter::with_capacity(28);
+        writer.write_u16(1)
+            .write_u16(0x0800)
+            .write_u8(6)
+            .write_u8(4)
+            .write_u16(self.operation as u16)
+            .write_slice(&self.sender_mac)
+            .write_slice(&self.sender_ip.octets())
+            .write_slice(&self.target_mac)
+            .write_slice(&self.target_ip.octets());
+        writer.into_bytes()
     }
 }
 impl Deserializable for ArpPacket {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
         if bytes.len() != 28 {return Err(DeserializeError::WrongDataLength);}
-        if bytes[0] != 0 || bytes[1] != 1 {return Err(DeserializeError::WrongData);}
-        if bytes[2] != 8 || bytes[3] != 0 {return Err(DeserializeError::WrongData);}
-        if bytes[4] != 6 {return Err(DeserializeError::WrongData);}
-        if bytes[5] != 4 {return Err(DeserializeError::WrongData);}
+        let mut reader = ByteReader::new(bytes);
+        if reader.read_u16()? != 1 || reader.read_u16()? != 0x0800 {return Err(DeserializeError::WrongData);}
+        if reader.read_u8()? != 6 || reader.read_u8()? != 4 {return Err(DeserializeError::WrongData);}
         Ok(Self {
-            operation: ArpOperation::deserialize(&bytes[6..8])?,
-            sender_mac: bytes[8..14].as_array().unwrap().clone(),
-            sender_ip: Ipv4Addr::from_octets(bytes[14..18].as_array().unwrap().clone()),
-            target_mac: bytes[18..24].as_array().unwrap().clone(),
-            target_ip: Ipv4Addr::from_octets(bytes[24..28].as_array().unwrap().clone())
+            operation: ArpOperation::deserialize(reader.read_slice(2)?)?,
+            sender_mac: reader.read_array()?,
+            sender_ip: reader.read_ipv4()?,
+            target_mac: reader.read_array()?,
+            target_ip: reader.read_ipv4()?
         })
     }
+}
+impl EtherPayload for ArpPacket {
+    fn ether_type(&self) -> u16 {
+        0x0806
+    }
+}
+
+/// ARP packet of any hardware and protocol type with addresses of any length, i.e. ARP over InfiniBand or IPv6 over ARP-like protocols
+/// `ArpPacket` covers only Ethernet and IPv4, convert with `TryFrom` when addresses fit it
+#[cfg(feature = "advanced-arp")]
+#[derive(Debug, Clone)]
+pub struct ArpMessage {
+    pub hardware_type: HardwareType,
+    /// EtherType of protocol addresses, i.e. 0x0800 for IPv4
+    pub protocol_type: u16,
+    /// Operation code, i.e. 1 for request, 2 for reply, 3 and 4 for RARP, 8 and 9 for InARP
+    pub operation: u16,
+    pub sender_hardware_address: Vec<u8>,
+    pub sender_protocol_address: Vec<u8>,
+    pub target_hardware_address: Vec<u8>,
+    pub target_protocol_address: Vec<u8>
+}
+#[cfg(feature = "advanced-arp")]
+impl ArpMessage {
+    /// Constructs an empty Ethernet and IPv4 request with zero-length addresses
+    pub fn new() -> Self {
+        Self {
+            hardware_type: HardwareType::Ethernet,
+            protocol_type: 0x0800,
+            operation: 1,
+            sender_hardware_address: Vec::new(),
+            sender_protocol_address: Vec::new(),
+            target_hardware_address: Vec::new(),
+            target_protocol_address: Vec::new()
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        8 + 2 * self.sender_hardware_address.len().max(self.target_hardware_address.len()) + 2 * self.sender_protocol_address.len().max(self.target_protocol_address.len())
+    }
+    /// Returns sender hardware address as MAC address, if it is `6 bytes` long
+    pub fn sender_mac(&self) -> Option<[u8; 6]> {
+        self.sender_hardware_address.as_slice().try_into().ok()
+    }
+    /// Returns target hardware address as MAC address, if it is `6 bytes` long
+    pub fn target_mac(&self) -> Option<[u8; 6]> {
+        self.target_hardware_address.as_slice().try_into().ok()
+    }
+    /// Returns sender protocol address as IPv4 address, if protocol type is IPv4 and address is `4 bytes` long
+    pub fn sender_ipv4(&self) -> Option<Ipv4Addr> {
+        if self.protocol_type != 0x0800 {return None;}
+        Some(Ipv4Addr::from_octets(self.sender_protocol_address.as_slice().try_into().ok()?))
+    }
+    /// Returns target protocol address as IPv4 address, if protocol type is IPv4 and address is `4 bytes` long
+    pub fn target_ipv4(&self) -> Option<Ipv4Addr> {
+        if self.protocol_type != 0x0800 {return None;}
+        Some(Ipv4Addr::from_octets(self.target_protocol_address.as_slice().try_into().ok()?))
+    }
+    /// Returns sender protocol address as IPv6 address, if protocol type is IPv6 and address is `16 bytes` long
+    pub fn sender_ipv6(&self) -> Option<Ipv6Addr> {
+        if self.protocol_type != 0x86DD {return None;}
+        Some(Ipv6Addr::from_octets(self.sender_protocol_address.as_slice().try_into().ok()?))
+    }
+    /// Returns target protocol address as IPv6 address, if protocol type is IPv6 and address is `16 bytes` long
+    pub fn target_ipv6(&self) -> Option<Ipv6Addr> {
+        if self.protocol_type != 0x86DD {return None;}
+        Some(Ipv6Addr::from_octets(self.target_protocol_address.as_slice().try_into().ok()?))
+    }
+}
+#[cfg(feature = "advanced-arp")]
+impl Default for ArpMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(feature = "advanced-arp")]
+impl Serializable for ArpMessage {
+    /// Shorter of sender and target addresses is padded with zeros, as both have to be of the same length
+    fn serialize(self) -> Vec<u8> {
+        let hardware_length = self.sender_hardware_address.len().max(self.target_hardware_address.len()).min(255);
+        let protocol_length = self.sender_protocol_address.len().max(self.target_protocol_address.len()).min(255);
+        let mut writer = ByteWriter::with_capacity(8 + 2 * (hardware_length + protocol_length));
+        writer.write_u16(self.hardware_type.into())
+            .write_u16(self.protocol_type)
+            .write_u8(hardware_length as u8)
+            .write_u8(protocol_length as u8)
+            .write_u16(self.operation)
+            .write_padded(&self.sender_hardware_address, hardware_length)
+            .write_padded(&self.sender_protocol_address, protocol_length)
+            .write_padded(&self.target_hardware_address, hardware_length)
+            .write_padded(&self.target_protocol_address, protocol_length);
+        writer.into_bytes()
+    }
+}
+#[cfg(feature = "advanced-arp")]
+impl Deserializable for ArpMessage {
+    /// Bytes after the last address, i.e. Ethernet padding, are ignored
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut reader = ByteReader::new(bytes);
+        let hardware_type = HardwareType::from(reader.read_u16()?);
+        let protocol_type = reader.read_u16()?;
+        let hardware_length = reader.read_u8()? as usize;
+        let protocol_length = reader.read_u8()? as usize;
+        Ok(Self {
+            hardware_type,
+            protocol_type,
+            operation: reader.read_u16()?,
+            sender_hardware_address: reader.read_slice(hardware_length)?.to_vec(),
+            sender_protocol_address: reader.read_slice(protocol_length)?.to_vec(),
+            target_hardware_address: reader.read_slice(hardware_length)?.to_vec(),
+            target_protocol_address: reader.read_slice(protocol_length)?.to_vec()
+        })
+    }
+}
+#[cfg(feature = "advanced-arp")]
+impl EtherPayload for ArpMessage {
+    fn ether_type(&self) -> u16 {
+        0x0806
+    }
+}
+#[cfg(feature = "advanced-arp")]
+impl From<ArpPacket> for ArpMessage {
+    fn from(packet: ArpPacket) -> Self {
+        Self {
+            hardware_type: HardwareType::Ethernet,
+            protocol_type: 0x0800,
+            operation: packet.operation as u16,
+            sender_hardware_address: packet.sender_mac.to_vec(),
+            sender_protocol_address: packet.sender_ip.octets().to_vec(),
+            target_hardware_address: packet.target_mac.to_vec(),
+            target_protocol_address: packet.target_ip.octets().to_vec()
+        }
+    }
+}
+#[cfg(feature = "advanced-arp")]
+impl TryFrom<&ArpMessage> for ArpPacket {
+    type Error = DeserializeError;
+    /// Returns `Err(DeserializeError::WrongData)` if message is not Ethernet and IPv4 request or reply
+    fn try_from(message: &ArpMessage) -> Result<Self, Self::Error> {
+        if message.hardware_type != HardwareType::Ethernet {return Err(DeserializeError::WrongData);}
+        Ok(Self {
+            operation: ArpOperation::deserialize(&message.operation.to_be_bytes())?,
+            sender_mac: message.sender_mac().ok_or(DeserializeError::WrongData)?,
+            sender_ip: message.sender_ipv4().ok_or(DeserializeError::WrongData)?,
+            target_mac: message.target_mac().ok_or(DeserializeError::WrongData)?,
+            target_ip: message.target_ipv4().ok_or(DeserializeError::WrongData)?
+        })
+    }
+}
+
+/// State of `ArpCache` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpState {
+    /// Request was sent, but no reply received yet
+    Incomplete,
+    /// Mapping was confirmed by reply recently
+    Reachable,
+    /// Mapping is not confirmed for a while or learned from request, it is still used, but should be refreshed
+    Stale
+}
+
+/// Entry of `ArpCache`
+#[derive(Debug, Clone)]
+pub struct ArpEntry {
+    /// `None` while entry is incomplete
+    pub mac: Option<[u8; 6]>,
+    pub state: ArpState,
+    /// Time of the last state change
+    pub updated: Duration,
+    /// Number of requests sent since entry became incomplete or stale
+    pub requests: u32,
+    /// Time of the last sent request
+    last_request: Option<Duration>,
+    /// Stale entry was used by `resolve()`, so it has to be refreshed
+    refresh: bool
+}
+
+/// Cache of IPv4 to MAC mappings, which learns them from ARP packets as described in RFC 826 and ages them
+/// It doesn't send anything itself: methods return ARP packets, which should be sent in Ethernet frames by caller
+/// Time is given explicitly as `now`, i.e. time since any fixed moment, so cache can be driven by capture timestamps
+#[derive(Debug, Clone)]
+pub struct ArpCache {
+    entries: HashMap<Ipv4Addr, ArpEntry>,
+    /// Own MAC address, which requests are sent from
+    pub mac: [u8; 6],
+    /// Own IPv4 address, which requests are sent from and replies are answered for
+    pub ip: Ipv4Addr,
+    /// Time after which reachable entry becomes stale
+    pub reachable_time: Duration,
+    /// Time after which unused stale entry is removed
+    pub stale_time: Duration,
+    /// Interval between requests for incomplete entry
+    pub retransmit_interval: Duration,
+    /// Number of unanswered requests after which incomplete entry is removed
+    pub max_requests: u32
+}
+impl ArpCache {
+    /// Constructs an empty `ArpCache` for host with `mac` and `ip`
+    /// Entries are reachable for 30 seconds and removed after 60 seconds of staleness, requests are retransmitted each second 3 times, as Linux does by default
+    pub fn new(mac: [u8; 6], ip: Ipv4Addr) -> Self {
+        Self {
+            entries: HashMap::new(),
+            mac,
+            ip,
+            reachable_time: Duration::from_secs(30),
+            stale_time: Duration::from_secs(60),
+            retransmit_interval: Duration::from_secs(1),
+            max_requests: 3
+        }
+    }
+    pub fn get(&self, ip: Ipv4Addr) -> Option<&ArpEntry> {
+        self.entries.get(&ip)
+    }
+    pub fn entries(&self) -> impl Iterator<Item = (Ipv4Addr, &ArpEntry)> + '_ {
+        self.entries.iter().map(|(ip, entry)| (*ip, entry))
+    }
+    pub fn remove(&mut self, ip: Ipv4Addr) -> Option<ArpEntry> {
+        self.entries.remove(&ip)
+    }
+    /// Returns MAC address of `ip` if it is known, even if entry is stale
+    pub fn lookup(&self, ip: Ipv4Addr) -> Option<[u8; 6]> {
+        self.entries.get(&ip)?.mac
+    }
+    /// Constructs request for `ip` from own addresses, `target_mac` is zero for broadcast request, as usual
+    pub fn request(&self, ip: Ipv4Addr, target_mac: Option<[u8; 6]>) -> ArpPacket {
+        ArpPacket {
+            operation: ArpOperation::Request,
+            sender_mac: self.mac,
+            sender_ip: self.ip,
+            target_mac: target_mac.unwrap_or([0; 6]),
+            target_ip: ip
+        }
+    }
+    /// Learns sender mapping from received `packet`
+    /// Mapping is added if packet is addressed to own IP, otherwise only existing entry is updated, as RFC 826 requires
+    /// Reply addressed to own IP makes entry reachable, other packets make it stale if they change MAC address or complete entry
+    /// Returns reply, which should be sent if `packet` is a request for own IP
+    pub fn process(&mut self, packet: &ArpPacket, now: Duration) -> Option<ArpPacket> {
+        let for_us = packet.target_ip == self.ip;
+        if packet.sender_ip != Ipv4Addr::UNSPECIFIED && packet.sender_ip != self.ip && (for_us || self.entries.contains_key(&packet.sender_ip)) {
+            let confirmed = for_us && packet.operation == ArpOperation::Reply;
+            let entry = self.entries.entry(packet.sender_ip).or_insert(ArpEntry {mac: None, state: ArpState::Incomplete, updated: now, requests: 0, last_request: None, refresh: false});
+            let state = match (confirmed, entry.state) {
+                (true, _) => ArpState::Reachable,
+                (false, ArpState::Reachable) if entry.mac == Some(packet.sender_mac) => ArpState::Reachable,
+                _ => ArpState::Stale
+            };
+            if state != entry.state || entry.mac != Some(packet.sender_mac) || confirmed {
+                *entry = ArpEntry {mac: Some(packet.sender_mac), state, updated: now, requests: 0, last_request: None, refresh: false};
+            }
+        }
+        if !for_us || packet.operation != ArpOperation::Request {return None;}
+        Some(ArpPacket {
+            operation: ArpOperation::Reply,
+            sender_mac: self.mac,
+            sender_ip: self.ip,
+            target_mac: packet.sender_mac,
+            target_ip: packet.sender_ip
+        })
+    }
+    /// Resolves `ip` to MAC address for sending packet to it
+    /// Returns `Ok` with MAC address if it is known, stale entry is refreshed with unicast request on the next `poll()`
+    /// Returns `Err` with broadcast request, which should be sent, if `ip` is unknown, or `Err(None)` if request for it is already pending
+    pub fn resolve(&mut self, ip: Ipv4Addr, now: Duration) -> Result<[u8; 6], Option<ArpPacket>> {
+        match self.entries.get_mut(&ip) {
+            Some(ArpEntry {mac: Some(mac), state, refresh, ..}) => {
+                *refresh |= *state == ArpState::Stale;
+                Ok(*mac)
+            }
+            Some(_) => Err(None),
+            None => {
+                self.entries.insert(ip, ArpEntry {mac: None, state: ArpState::Incomplete, updated: now, requests: 1, last_request: Some(now), refresh: false});
+                Err(Some(self.request(ip, None)))
+            }
+        }
+    }
+    /// Ages entries and returns requests, which should be sent: retransmissions for incomplete entries and unicast requests refreshing stale ones
+    /// Reachable entries become stale after `reachable_time`, incomplete entries are removed after `max_requests` unanswered requests
+    /// Stale entries used by `resolve()` are refreshed with up to `max_requests` unicast requests, unused ones are removed after `stale_time`
+    pub fn poll(&mut self, now: Duration) -> Vec<ArpPacket> {
+        let mut targets = Vec::new();
+        let mut expired = Vec::new();
+        for (ip, entry) in self.entries.iter_mut() {
+            if entry.state == ArpState::Reachable && now.saturating_sub(entry.updated) >= self.reachable_time {
+                entry.state = ArpState::Stale;
+                entry.updated = now;
+            }
+            let due = entry.last_request.is_none_or(|last| now.saturating_sub(last) >= self.retransmit_interval);
+            match entry.state {
+                ArpState::Reachable => {}
+                ArpState::Incomplete if due && entry.requests >= self.max_requests => expired.push(*ip),
+                ArpState::Stale if !entry.refresh && now.saturating_sub(entry.updated) >= self.stale_time => expired.push(*ip),
+                ArpState::Stale if due && entry.refresh && entry.requests >= self.max_requests => expired.push(*ip),
+                ArpState::Incomplete | ArpState::Stale if due && (entry.state == ArpState::Incomplete || entry.refresh) => {
+                    entry.requests += 1;
+                    entry.last_request = Some(now);
+                    targets.push((*ip, entry.mac));
+                }
+                _ => {}
+            }
+        }
+        for ip in expired {
+            self.entries.remove(&ip);
+        }
+        targets.into_iter().map(|(ip, mac)| self.request(ip, mac)).collect()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::gre::GrePacket;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// ERSPAN header, which type is determined by GRE protocol and `S` flag
+#[derive(Debug, Clone)]
+pub enum ErspanHeader {
+    /// GRE protocol 0x88BE without sequence number, there is no ERSPAN header at all
+    TypeI,
+    /// GRE protocol 0x88BE with sequence number, `8 bytes` header
+    TypeII {
+        /// Original VLAN of mirrored frame
+        vlan: u16,
+        /// Class of Service of mirrored frame
+        cos: u8,
+        /// Encapsulation of mirrored frame, 0 for untagged, 1 for ISL, 2 for 802.1Q, 3 for VLAN preserved
+        encapsulation: u8,
+        /// `T` flag - mirrored frame was truncated
+        truncated: bool,
+        /// `10 bits` session ID
+        session_id: u16,
+        /// `20 bits` port index
+        index: u32
+    },
+    /// GRE protocol 0x22EB, `12 bytes` header with optional `8 bytes` platform specific subheader
+    TypeIII {
+        vlan: u16,
+        cos: u8,
+        /// Bad/Short/Oversized frame status
+        bso: u8,
+        truncated: bool,
+        session_id: u16,
+        /// Timestamp in units of `granularity`
+        timestamp: u32,
+        /// Security Group Tag
+        sgt: u16,
+        /// `P` flag - mirrored frame is Ethernet protocol frame
+        ethernet_protocol: bool,
+        /// Frame type, 0 for Ethernet, 2 for IP
+        frame_type: u8,
+        /// Hardware ID of mirroring engine
+        hardware_id: u8,
+        /// `D` flag - frame was mirrored on egress
+        egress: bool,
+        /// Timestamp granularity, 0 for `100 microseconds`, 1 for `100 nanoseconds`, 2 for IEEE 1588, 3 for custom
+        granularity: u8,
+        /// Platform specific subheader, present when `O` flag is set
+        platform_subheader: Option<[u8; 8]>
+    }
+}
+impl ErspanHeader {
+    /// Returns GRE protocol for this header type
+    pub fn get_protocol(&self) -> u16 {
+        match self {
+            Self::TypeI | Self::TypeII {..} => 0x88BE,
+            Self::TypeIII {..} => 0x22EB
+        }
+    }
+}
+
+/// Struct for ERSPAN Packet with mirrored frame, carried by GRE packets
+/// You can construct it from GRE packet with `ErspanPacket::from_gre()`
+/// Or from scratch and wrap it into GRE packet with `ErspanPacket::into_gre()`
+#[derive(Debug, Clone)]
+pub struct ErspanPacket {
+    pub header: ErspanHeader,
+    /// Mirrored frame
+    pub payload: Vec<u8>
+}
+impl ErspanPacket {
+    /// Returns session ID, which is absent in Type I
+    pub fn session_id(&self) -> Option<u16> {
+        match self.header {
+            ErspanHeader::TypeI => None,
+            ErspanHeader::TypeII {session_id, ..} |
+            ErspanHeader::TypeIII {session_id, ..} => Some(session_id)
+        }
+    }
+    /// Returns timestamp with its granularity, which are present only in Type III
+    pub fn timestamp(&self) -> Option<(u32, u8)> {
+        match self.header {
+            ErspanHeader::TypeIII {timestamp, granularity, ..} => Some((timestamp, granularity)),
+            _ => None
+        }
+    }
+    /// Decodes mirrored frame as Ethernet frame
+    pub fn ethernet_frame(&self) -> Result<EthernetFrame, DeserializeError> {
+        EthernetFrame::deserialize(&self.payload)
+    }
+    /// Constructs `ErspanPacket` from payload of `packet`
+    /// Returns `Err(DeserializeError::WrongData)` if GRE protocol is neither 0x88BE nor 0x22EB
+    pub fn from_gre(packet: &GrePacket) -> Result<Self, DeserializeError> {
+        let bytes = &packet.payload;
+        let field = |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        match packet.protocol {
+            0x88BE if packet.sequence_number.is_none() => Ok(Self {
+                header: ErspanHeader::TypeI,
+                payload: bytes.clone()
+            }),
+            0x88BE => {
+                if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+                if bytes[0] >> 4 != 1 {return Err(DeserializeError::WrongData);}
+                let word = field(0);
+                Ok(Self {
+                    header: ErspanHeader::TypeII {
+                        vlan: (word >> 16) as u16 & 0xFFF,
+                        cos: (word >> 13) as u8 & 0b111,
+                        encapsulation: (word >> 11) as u8 & 0b11,
+                        truncated: word & 0x400 != 0,
+                        session_id: word as u16 & 0x3FF,
+                        index: field(4) & 0xF_FFFF
+                    },
+                    payload: bytes[8..].to_vec()
+                })
+            }
+            0x22EB => {
+                if bytes.len() < 12 {return Err(DeserializeError::WrongDataLength);}
+                if bytes[0] >> 4 != 2 {return Err(DeserializeError::WrongData);}
+                let word = field(0);
+                let flags = field(8);
+                let mut platform_subheader = None;
+                let mut i = 12;
+                if flags & 1 != 0 {
+                    if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+                    platform_subheader = Some(*bytes[12..20].as_array().unwrap());
+                    i = 20;
+                }
+                Ok(Self {
+                    header: ErspanHeader::TypeIII {
+                        vlan: (word >> 16) as u16 & 0xFFF,
+                        cos: (word >> 13) as u8 & 0b111,
+                        bso: (word >> 11) as u8 & 0b11,
+                        truncated: word & 0x400 != 0,
+                        session_id: word as u16 & 0x3FF,
+                        timestamp: field(4),
+                        sgt: (flags >> 16) as u16,
+                        ethernet_protocol: flags & 0x8000 != 0,
+                        frame_type: (flags >> 10) as u8 & 0x1F,
+                        hardware_id: (flags >> 4) as u8 & 0x3F,
+                        egress: flags & 0x8 != 0,
+                        granularity: (flags >> 1) as u8 & 0b11,
+                        platform_subheader
+                    },
+                    payload: bytes[i..].to_vec()
+                })
+            }
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+    /// Returns length of serialized header and mirrored frame, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let header = match &self.header {
+            ErspanHeader::TypeI => 0,
+            ErspanHeader::TypeII {..} => 8,
+            ErspanHeader::TypeIII {platform_subheader, ..} => 12 + platform_subheader.map_or(0, |_| 8)
+        };
+        header + self.payload.len()
+    }
+    /// Wraps packet into GRE packet with matching protocol
+    /// Type II and Type III require `sequence_number` to be present, so it's set for them, and Type I has none
+    pub fn into_gre(self, sequence_number: u32) -> GrePacket {
+        let mut packet = GrePacket::new();
+        packet.protocol = self.header.get_protocol();
+        if !matches!(self.header, ErspanHeader::TypeI) {
+            packet.sequence_number = Some(sequence_number);
+        }
+        packet.payload = self.serialize();
+        packet
+    }
+}
+impl Serializable for ErspanPacket {
+    /// Converts header and mirrored frame to bytes of GRE payload
+    fn serialize(mut self) -> Vec<u8> {
+        let common = |version: u32, vlan: u16, cos: u8, bits: u8, truncated: bool, session_id: u16| {
+            version << 28 | ((vlan & 0xFFF) as u32) << 16 | ((cos & 0b111) as u32) << 13 | ((bits & 0b11) as u32) << 11 | (truncated as u32) << 10 | (session_id & 0x3FF) as u32
+        };
+        let mut result = Vec::new();
+        match self.header {
+            ErspanHeader::TypeI => {}
+            ErspanHeader::TypeII {vlan, cos, encapsulation, truncated, session_id, index} => {
+                result.extend_from_slice(&common(1, vlan, cos, encapsulation, truncated, session_id).to_be_bytes());
+                result.extend_from_slice(&(index & 0xF_FFFF).to_be_bytes());
+            }
+            ErspanHeader::TypeIII {vlan, cos, bso, truncated, session_id, timestamp, sgt, ethernet_protocol, frame_type, hardware_id, egress, granularity, platform_subheader} => {
+                result.extend_from_slice(&common(2, vlan, cos, bso, truncated, session_id).to_be_bytes());
+                result.extend_from_slice(&timestamp.to_be_bytes());
+                let flags = (sgt as u32) << 16 |
+                    (ethernet_protocol as u32) << 15 |
+                    ((frame_type & 0x1F) as u32) << 10 |
+                    ((hardware_id & 0x3F) as u32) << 4 |
+                    (egress as u32) << 3 |
+                    ((granularity & 0b11) as u32) << 1 |
+                    platform_subheader.is_some() as u32;
+                result.extend_from_slice(&flags.to_be_bytes());
+                if let Some(subheader) = platform_subheader {
+                    result.extend_from_slice(&subheader);
+                }
+            }
+        }
+        result.append(&mut self.payload);
+        result
+    }
+}
\ No newline at end of file
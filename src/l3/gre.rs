@@ -0,0 +1,191 @@
+use core::net::Ipv4Addr;
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::{IpPacket, IpProtocol, ipv4::Ipv4Packet, ipv6::Ipv6Packet};
+use crate::util::{Serializable, Deserializable, DeserializeError, EtherPayload, IpPayload, ChecksumAccumulator, ValidationIssue};
+
+/// EtherType of Transparent Ethernet Bridging, used for GRE packets carrying Ethernet frames
+pub const TRANSPARENT_ETHERNET_BRIDGING: u16 = 0x6558;
+
+/// Struct for GRE Packet, carried by IP packets with protocol 47
+/// You can construct it from scratch with `GrePacket::new()` and consistently editing
+/// Or construct from existing packet bytes with `GrePacket::deserialize()` or from IP packet with `GrePacket::from_ip()`
+/// Optional fields are present when they are `Some`, and corresponding flags are set automatically on serialization
+#[derive(Debug, Clone)]
+pub struct GrePacket {
+    /// Checksum of header and payload, present when `C` flag is set
+    pub checksum: Option<u16>,
+    /// Key, present when `K` flag is set
+    pub key: Option<u32>,
+    /// Sequence number, present when `S` flag is set
+    pub sequence_number: Option<u32>,
+    /// EtherType of payload, i.e. 0x0800 for IPv4, 0x6558 for Ethernet
+    pub protocol: u16,
+    pub payload: Vec<u8>
+}
+impl GrePacket {
+    /// Constructs an empty `GrePacket` without optional fields
+    pub fn new() -> Self {
+        Self {
+            checksum: None,
+            key: None,
+            sequence_number: None,
+            protocol: 0,
+            payload: Vec::new()
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        4 + [self.checksum.is_some(), self.key.is_some(), self.sequence_number.is_some()].iter().filter(|present| **present).count() * 4 + self.payload.len()
+    }
+    /// Recalculates `checksum` field and makes it present if it wasn't
+    pub fn recalculate_checksum(&mut self) {
+        self.checksum = Some(0);
+        self.checksum = Some(ChecksumAccumulator::new().update(&self.serialize_header()).update(&self.payload).finish());
+    }
+    /// Constructs NVGRE packet carrying `frame` of Virtual Subnet `vsid`
+    /// Only lower `24 bits` of `vsid` are used, `flow_id` is used for per-flow entropy
+    pub fn nvgre(vsid: u32, flow_id: u8, frame: EthernetFrame) -> Self {
+        Self {
+            key: Some((vsid & 0xFF_FFFF) << 8 | flow_id as u32),
+            protocol: TRANSPARENT_ETHERNET_BRIDGING,
+            payload: frame.serialize(),
+            ..Self::new()
+        }
+    }
+    /// Returns `true` if packet carries Ethernet frame and has a key, as NVGRE packets do
+    pub fn is_nvgre(&self) -> bool {
+        self.protocol == TRANSPARENT_ETHERNET_BRIDGING && self.key.is_some()
+    }
+    /// Returns NVGRE Virtual Subnet ID from upper `24 bits` of `key`
+    pub fn vsid(&self) -> Option<u32> {
+        if !self.is_nvgre() {return None;}
+        self.key.map(|key| key >> 8)
+    }
+    /// Returns NVGRE FlowID from lower `8 bits` of `key`
+    pub fn flow_id(&self) -> Option<u8> {
+        if !self.is_nvgre() {return None;}
+        self.key.map(|key| key as u8)
+    }
+    /// Sets `payload` to serialized `packet` and `protocol` to its EtherType
+    pub fn set_payload_packet<P: EtherPayload + Serializable + Clone>(&mut self, packet: &P) {
+        self.protocol = packet.ether_type();
+        self.payload = packet.clone().serialize();
+    }
+    /// Decodes payload as Ethernet frame
+    /// Returns `Err(DeserializeError::WrongData)` if `protocol` isn't Transparent Ethernet Bridging
+    pub fn ethernet_frame(&self) -> Result<EthernetFrame, DeserializeError> {
+        if self.protocol != TRANSPARENT_ETHERNET_BRIDGING {return Err(DeserializeError::WrongData);}
+        EthernetFrame::deserialize(&self.payload)
+    }
+    /// Constructs `GrePacket` from payload of `packet`
+    /// Returns `Err(DeserializeError::WrongData)` if protocol of `packet` isn't 47
+    pub fn from_ip(packet: &IpPacket) -> Result<Self, DeserializeError> {
+        if packet.protocol() != IpProtocol::Gre {return Err(DeserializeError::WrongData);}
+        Self::deserialize(packet.payload())
+    }
+    /// Wraps packet into IPv4 packet with protocol 47, TTL 64 and calculated checksum
+    pub fn into_ipv4(self, source: Ipv4Addr, destination: Ipv4Addr) -> Ipv4Packet {
+        let mut packet = Ipv4Packet::new();
+        packet.ttl = 64;
+        packet.protocol = IpProtocol::Gre;
+        packet.source = source;
+        packet.destination = destination;
+        packet.payload = self.serialize();
+        packet.recalculate_checksum();
+        packet
+    }
+    /// Serializes header with optional fields, but without payload
+    /// Checks raw GRE packet `bytes` for problems: truncation, nonzero version or reserved bits and wrong checksum
+    /// IPv4 and IPv6 payload is validated too
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 4 {
+            issues.push(ValidationIssue::Truncated {layer: "gre", needed: 4, actual: bytes.len()});
+            return issues;
+        }
+        if bytes[1] & 7 != 0 {
+            issues.push(ValidationIssue::InvalidField {layer: "gre", field: "version", value: (bytes[1] & 7) as u32});
+        }
+        let reserved = u16::from_be_bytes([bytes[0], bytes[1]]) & 0x4FF8;
+        if reserved != 0 {
+            issues.push(ValidationIssue::InvalidField {layer: "gre", field: "reserved", value: reserved as u32});
+        }
+        let header_len = 4 + [0x80, 0x20, 0x10].iter().filter(|flag| bytes[0] & **flag != 0).count() * 4;
+        if header_len > bytes.len() {
+            issues.push(ValidationIssue::Truncated {layer: "gre", needed: header_len, actual: bytes.len()});
+            return issues;
+        }
+        if bytes[0] & 0x80 != 0 {
+            let declared = u16::from_be_bytes([bytes[4], bytes[5]]);
+            let calculated = ChecksumAccumulator::new().update(&bytes[..4]).update(&[0, 0]).update(&bytes[6..]).finish();
+            if calculated != declared {
+                issues.push(ValidationIssue::ChecksumMismatch {layer: "gre", declared, calculated});
+            }
+        }
+        match u16::from_be_bytes([bytes[2], bytes[3]]) {
+            0x0800 => issues.append(&mut Ipv4Packet::validate(&bytes[header_len..])),
+            0x86DD => issues.append(&mut Ipv6Packet::validate(&bytes[header_len..])),
+            _ => {}
+        }
+        issues
+    }
+    fn serialize_header(&self) -> Vec<u8> {
+        let mut result = vec![0u8; 4];
+        result[0] = (self.checksum.is_some() as u8) << 7 | (self.key.is_some() as u8) << 5 | (self.sequence_number.is_some() as u8) << 4;
+        result[2..4].copy_from_slice(&self.protocol.to_be_bytes());
+        if let Some(checksum) = self.checksum {
+            result.extend_from_slice(&checksum.to_be_bytes());
+            result.extend_from_slice(&[0, 0]);
+        }
+        if let Some(key) = self.key {
+            result.extend_from_slice(&key.to_be_bytes());
+        }
+        if let Some(sequence_number) = self.sequence_number {
+            result.extend_from_slice(&sequence_number.to_be_bytes());
+        }
+        result
+    }
+}
+impl Default for GrePacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for GrePacket {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = self.serialize_header();
+        result.append(&mut self.payload);
+        result
+    }
+}
+impl Deserializable for GrePacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[1] & 0b111 != 0 {return Err(DeserializeError::WrongData);}
+        let mut packet = Self::new();
+        packet.protocol = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let field = |i: usize| -> Result<u32, DeserializeError> {
+            bytes.get(i..i + 4).map(|field| u32::from_be_bytes([field[0], field[1], field[2], field[3]])).ok_or(DeserializeError::WrongDataLength)
+        };
+        let mut i = 4usize;
+        if bytes[0] & 0x80 != 0 {
+            packet.checksum = Some((field(i)? >> 16) as u16);
+            i += 4;
+        }
+        if bytes[0] & 0x20 != 0 {
+            packet.key = Some(field(i)?);
+            i += 4;
+        }
+        if bytes[0] & 0x10 != 0 {
+            packet.sequence_number = Some(field(i)?);
+            i += 4;
+        }
+        packet.payload = bytes[i..].to_vec();
+        Ok(packet)
+    }
+}
+impl IpPayload for GrePacket {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Gre
+    }
+}
\ No newline at end of file
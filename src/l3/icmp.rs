@@ -0,0 +1,520 @@
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use super::icmpv6::Icmpv6Packet;
+use super::{IpProtocol, ipv4::Ipv4Packet};
+use crate::util::{Serializable, Deserializable, DeserializeError, DeserializableRef, PacketRef, IpPayload, ValidationIssue, ChecksumAccumulator, Checksummed, ChecksumContext, checksum};
+
+/// Defines enum of ICMP or ICMPv6 codes of a message type with `Other` fallback for unassigned codes
+macro_rules! icmp_code {
+    ($(#[$meta:meta])* $name:ident {$($(#[$variant_meta:meta])* $variant:ident = $value:literal),* $(,)?}) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// Unassigned or unknown code
+            Other(u8)
+        }
+        impl From<u8> for $name {
+            fn from(value: u8) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    other => Self::Other(other)
+                }
+            }
+        }
+        impl From<$name> for u8 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Other(other) => other
+                }
+            }
+        }
+        impl PartialEq<u8> for $name {
+            fn eq(&self, other: &u8) -> bool {
+                u8::from(*self) == *other
+            }
+        }
+        impl core::fmt::Display for $name {
+            /// Formats code number, as it is shown in packet dumps
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{}", u8::from(*self))
+            }
+        }
+    };
+}
+pub(super) use icmp_code;
+
+icmp_code! {
+    /// Code of ICMP Destination Unreachable(type 3) message
+    DestinationUnreachableCode {
+        NetUnreachable = 0,
+        HostUnreachable = 1,
+        ProtocolUnreachable = 2,
+        PortUnreachable = 3,
+        /// Fragmentation Needed and DF set, MTU of next hop is in `rest_of_header`
+        FragmentationNeeded = 4,
+        SourceRouteFailed = 5,
+        DestinationNetworkUnknown = 6,
+        DestinationHostUnknown = 7,
+        SourceHostIsolated = 8,
+        NetworkProhibited = 9,
+        HostProhibited = 10,
+        NetworkUnreachableForTos = 11,
+        HostUnreachableForTos = 12,
+        /// Communication Administratively Prohibited, usually sent by firewalls
+        CommunicationProhibited = 13,
+        HostPrecedenceViolation = 14,
+        PrecedenceCutoff = 15
+    }
+}
+icmp_code! {
+    /// Code of ICMP Redirect(type 5) message
+    RedirectCode {
+        Network = 0,
+        Host = 1,
+        TosNetwork = 2,
+        TosHost = 3
+    }
+}
+icmp_code! {
+    /// Code of ICMP Time Exceeded(type 11) message
+    TimeExceededCode {
+        /// TTL reached 0 in transit, which is what traceroute relies on
+        TtlExpired = 0,
+        FragmentReassembly = 1
+    }
+}
+icmp_code! {
+    /// Code of ICMP Parameter Problem(type 12) message
+    ParameterProblemCode {
+        /// Pointer in `rest_of_header` indicates erroneous octet
+        PointerIndicatesError = 0,
+        MissingOption = 1,
+        BadLength = 2
+    }
+}
+icmp_code! {
+    /// Code of ICMP Extended Echo Reply(type 43) or ICMPv6 Extended Echo Reply(type 161) message
+    ExtendedEchoReplyCode {
+        NoError = 0,
+        MalformedQuery = 1,
+        NoSuchInterface = 2,
+        NoSuchTableEntry = 3,
+        MultipleInterfacesSatisfyQuery = 4
+    }
+}
+
+/// ICMP message type together with its code
+/// Types which define only code 0 have no code, so they are `Other` with any other code and every type/code pair converts back unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IcmpType {
+    /// 0
+    EchoReply,
+    /// 3
+    DestinationUnreachable(DestinationUnreachableCode),
+    /// 4 - deprecated by RFC 6633
+    SourceQuench,
+    /// 5
+    Redirect(RedirectCode),
+    /// 8
+    EchoRequest,
+    /// 9 - code 16 is Does Not Route Common Traffic and is `Other`
+    RouterAdvertisement,
+    /// 10
+    RouterSolicitation,
+    /// 11
+    TimeExceeded(TimeExceededCode),
+    /// 12
+    ParameterProblem(ParameterProblemCode),
+    /// 13
+    Timestamp,
+    /// 14
+    TimestampReply,
+    /// 42
+    ExtendedEchoRequest,
+    /// 43
+    ExtendedEchoReply(ExtendedEchoReplyCode),
+    /// Other types or codes
+    Other {
+        icmp_type: u8,
+        code: u8
+    }
+}
+impl IcmpType {
+    /// Constructs `IcmpType` from raw type and code
+    pub fn from_type_code(icmp_type: u8, code: u8) -> Self {
+        match (icmp_type, code) {
+            (0, 0) => Self::EchoReply,
+            (3, code) => Self::DestinationUnreachable(code.into()),
+            (4, 0) => Self::SourceQuench,
+            (5, code) => Self::Redirect(code.into()),
+            (8, 0) => Self::EchoRequest,
+            (9, 0) => Self::RouterAdvertisement,
+            (10, 0) => Self::RouterSolicitation,
+            (11, code) => Self::TimeExceeded(code.into()),
+            (12, code) => Self::ParameterProblem(code.into()),
+            (13, 0) => Self::Timestamp,
+            (14, 0) => Self::TimestampReply,
+            (42, 0) => Self::ExtendedEchoRequest,
+            (43, code) => Self::ExtendedEchoReply(code.into()),
+            (icmp_type, code) => Self::Other {icmp_type, code}
+        }
+    }
+    /// Returns raw type and code
+    pub fn to_type_code(&self) -> (u8, u8) {
+        match *self {
+            Self::EchoReply => (0, 0),
+            Self::DestinationUnreachable(code) => (3, code.into()),
+            Self::SourceQuench => (4, 0),
+            Self::Redirect(code) => (5, code.into()),
+            Self::EchoRequest => (8, 0),
+            Self::RouterAdvertisement => (9, 0),
+            Self::RouterSolicitation => (10, 0),
+            Self::TimeExceeded(code) => (11, code.into()),
+            Self::ParameterProblem(code) => (12, code.into()),
+            Self::Timestamp => (13, 0),
+            Self::TimestampReply => (14, 0),
+            Self::ExtendedEchoRequest => (42, 0),
+            Self::ExtendedEchoReply(code) => (43, code.into()),
+            Self::Other {icmp_type, code} => (icmp_type, code)
+        }
+    }
+}
+
+/// Struct for ordinary ICMP Packet
+/// You can construct it from scratch with `IcmpPacket::new()` and consistently editing
+/// Or construct from existing packet bytes with `IcmpPacket::deserialize()`
+#[derive(Debug, Clone)]
+pub struct IcmpPacket {
+    /// ICMP message type, i.e. 8 for Echo Request, 0 for Echo Reply, 3 for Destination Unreachable, etc.
+    pub icmp_type: u8,
+    /// ICMP message code, meaning depends on `icmp_type`
+    pub code: u8,
+    /// Checksum of whole ICMP message, including payload
+    pub checksum: u16,
+    /// 4 bytes after checksum, meaning depends on `icmp_type`, i.e. identifier and sequence number for Echo messages
+    pub rest_of_header: [u8; 4],
+    /// Message Data
+    pub payload: Vec<u8>
+}
+impl IcmpPacket {
+    /// Constructs an empty `IcmpPacket`
+    pub fn new() -> Self {
+        Self {
+            icmp_type: 0,
+            code: 0,
+            checksum: 0,
+            rest_of_header: [0; 4],
+            payload: Vec::new()
+        }
+    }
+    /// Recalculates `checksum` field in `IcmpPacket`
+    /// Note that this checksum covers payload too
+    pub fn recalculate_checksum(&mut self) {
+        let mut header = self.serialize_header();
+        header[2..4].fill(0);
+        self.checksum = ChecksumAccumulator::new().update(&header).update(&self.payload).finish();
+    }
+    /// Serializes `8 bytes` header without payload
+    fn serialize_header(&self) -> [u8; 8] {
+        let mut header = [0u8; 8];
+        header[0] = self.icmp_type;
+        header[1] = self.code;
+        header[2..4].copy_from_slice(&self.checksum.to_be_bytes());
+        header[4..8].copy_from_slice(&self.rest_of_header);
+        header
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        8 + self.payload.len()
+    }
+    pub fn clone_header(&self) -> Self {
+        Self {
+            payload: Vec::new(),
+            ..self.clone()
+        }
+    }
+    /// Returns type and code of message as `IcmpType`
+    pub fn message_type(&self) -> IcmpType {
+        IcmpType::from_type_code(self.icmp_type, self.code)
+    }
+    /// Sets `icmp_type` and `code` fields from `message_type`
+    pub fn set_message_type(&mut self, message_type: IcmpType) {
+        (self.icmp_type, self.code) = message_type.to_type_code();
+    }
+    /// Returns `true` if this is an error message, which quotes original datagram: Destination Unreachable, Source Quench, Redirect, Time Exceeded or Parameter Problem
+    pub fn is_error(&self) -> bool {
+        matches!(self.icmp_type, 3 | 4 | 5 | 11 | 12)
+    }
+    /// Parses original datagram quoted in error message, its payload is usually truncated, often to the first `8 bytes`
+    /// If message has RFC 4884 extensions, only quote of declared length is parsed
+    /// Returns `None` if this is not an error message or quote is shorter than IPv4 header
+    pub fn original_datagram(&self) -> Option<Ipv4Packet> {
+        if !self.is_error() {return None;}
+        let length = self.rest_of_header[1] as usize * 4;
+        let quote = match self.icmp_type {
+            3 | 11 | 12 if length != 0 && length <= self.payload.len() => &self.payload[..length],
+            _ => &self.payload[..]
+        };
+        Ipv4Packet::deserialize(quote).ok()
+    }
+    /// Returns source and destination ports of TCP or UDP original datagram, which are within `8 bytes` quoted by every error message
+    /// Returns `None` if this is not an error message or original datagram is not TCP or UDP
+    pub fn original_ports(&self) -> Option<(u16, u16)> {
+        let packet = self.original_datagram()?;
+        if !matches!(packet.protocol, IpProtocol::Tcp | IpProtocol::Udp) || packet.fragment_offset != 0 || packet.payload.len() < 4 {return None;}
+        Some((u16::from_be_bytes([packet.payload[0], packet.payload[1]]), u16::from_be_bytes([packet.payload[2], packet.payload[3]])))
+    }
+    /// Checks raw ICMP packet `bytes` for problems: truncation and wrong checksum
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 8 {
+            issues.push(ValidationIssue::Truncated {layer: "icmp", needed: 8, actual: bytes.len()});
+            return issues;
+        }
+        let declared = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let calculated = ChecksumAccumulator::new().update(&bytes[..2]).update(&[0, 0]).update(&bytes[4..]).finish();
+        if calculated != declared {
+            issues.push(ValidationIssue::ChecksumMismatch {layer: "icmp", declared, calculated});
+        }
+        issues
+    }
+}
+impl Default for IcmpPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for IcmpPacket {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = self.serialize_header().to_vec();
+        result.append(&mut self.payload);
+        result
+    }
+}
+impl Deserializable for IcmpPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for IcmpPacket {
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        let header = Self {
+            icmp_type: bytes[0],
+            code: bytes[1],
+            checksum: u16::from_be_bytes([bytes[2], bytes[3]]),
+            rest_of_header: [bytes[4], bytes[5], bytes[6], bytes[7]],
+            payload: Vec::new()
+        };
+        Ok(PacketRef {header, payload: &bytes[8..]})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+impl Checksummed for IcmpPacket {
+    /// Computes checksum over header and payload, `context` is ignored
+    fn compute_checksum(&self, _context: &ChecksumContext) -> Option<u16> {
+        let mut header = self.serialize_header();
+        header[2..4].fill(0);
+        Some(ChecksumAccumulator::new().update(&header).update(&self.payload).finish())
+    }
+    fn stored_checksum(&self) -> Option<u16> {
+        Some(self.checksum)
+    }
+    fn set_checksum(&mut self, checksum: u16) {
+        self.checksum = checksum;
+    }
+    fn clear_checksum(&mut self) {
+        self.checksum = 0;
+    }
+}
+impl IpPayload for IcmpPacket {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Icmp
+    }
+}
+
+/// Interface Identification Object of ICMP Extended Echo Request, which names probed interface, as described in RFC 8335
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceIdentification {
+    /// C-Type 1, interface name, i.e. "eth0"
+    Name(String),
+    /// C-Type 2, ifIndex of interface
+    Index(u32),
+    /// C-Type 3, address assigned to interface
+    Address(IpAddr),
+    /// Other C-Types
+    Other {
+        c_type: u8,
+        data: Vec<u8>
+    }
+}
+impl InterfaceIdentification {
+    pub fn get_c_type(&self) -> u8 {
+        match self {
+            Self::Name(_) => 1,
+            Self::Index(_) => 2,
+            Self::Address(_) => 3,
+            Self::Other {c_type, ..} => *c_type
+        }
+    }
+    /// Serializes ICMP extension structure with this object: extension header with checksum and object padded to `4 bytes` boundary
+    fn serialize_extension(self) -> Vec<u8> {
+        let c_type = self.get_c_type();
+        let mut data = match self {
+            Self::Name(name) => name.into_bytes(),
+            Self::Index(index) => index.to_be_bytes().to_vec(),
+            Self::Address(IpAddr::V4(address)) => [&[0, 1, 4, 0][..], &address.octets()].concat(),
+            Self::Address(IpAddr::V6(address)) => [&[0, 2, 16, 0][..], &address.octets()].concat(),
+            Self::Other {data, ..} => data
+        };
+        data.resize(data.len().next_multiple_of(4), 0);
+        let mut result = vec![0x20, 0, 0, 0];
+        result.extend_from_slice(&(4 + data.len() as u16).to_be_bytes());
+        result.push(3);
+        result.push(c_type);
+        result.append(&mut data);
+        let checksum = checksum(&result);
+        result[2..4].copy_from_slice(&checksum.to_be_bytes());
+        result
+    }
+    /// Parses the first object of ICMP extension structure, which should be Interface Identification Object
+    fn deserialize_extension(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 8 || bytes[0] >> 4 != 2 {return None;}
+        let length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        if length < 4 || bytes.len() < 4 + length || bytes[6] != 3 {return None;}
+        let data = &bytes[8..4 + length];
+        match bytes[7] {
+            1 => Some(Self::Name(String::from_utf8(data.to_vec()).ok()?.trim_end_matches('\0').to_string())),
+            2 if data.len() == 4 => Some(Self::Index(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))),
+            3 if data.len() >= 8 && data[0..3] == [0, 1, 4] => Some(Self::Address(IpAddr::V4(Ipv4Addr::from_octets(*data[4..8].as_array()?)))),
+            3 if data.len() >= 20 && data[0..3] == [0, 2, 16] => Some(Self::Address(IpAddr::V6(Ipv6Addr::from_octets(*data[4..20].as_array()?)))),
+            2 | 3 => None,
+            c_type => Some(Self::Other {c_type, data: data.to_vec()})
+        }
+    }
+}
+
+/// ICMP Extended Echo Request(type 42) or ICMPv6 Extended Echo Request(type 160), which probes status of interface on target node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedEchoRequest {
+    pub id: u16,
+    /// Sequence number, only `1 byte` unlike ordinary Echo
+    pub seq: u8,
+    /// L-bit - probed interface is on the node receiving request
+    pub local: bool,
+    pub interface: InterfaceIdentification
+}
+impl ExtendedEchoRequest {
+    /// Parses Extended Echo Request from ICMP `packet`
+    /// Returns `None` if `packet` is another message or Interface Identification Object is malformed
+    pub fn from_icmp(packet: &IcmpPacket) -> Option<Self> {
+        if packet.icmp_type != 42 || packet.code != 0 {return None;}
+        Self::parse(&packet.rest_of_header, &packet.payload)
+    }
+    /// Parses Extended Echo Request from ICMPv6 `packet`
+    pub fn from_icmpv6(packet: &Icmpv6Packet) -> Option<Self> {
+        if packet.icmp_type != 160 || packet.code != 0 {return None;}
+        Self::parse(&packet.rest_of_header, &packet.payload)
+    }
+    fn parse(rest_of_header: &[u8; 4], payload: &[u8]) -> Option<Self> {
+        Some(Self {
+            id: u16::from_be_bytes([rest_of_header[0], rest_of_header[1]]),
+            seq: rest_of_header[2],
+            local: rest_of_header[3] & 1 != 0,
+            interface: InterfaceIdentification::deserialize_extension(payload)?
+        })
+    }
+    fn rest_of_header(&self) -> [u8; 4] {
+        let id = self.id.to_be_bytes();
+        [id[0], id[1], self.seq, self.local as u8]
+    }
+    /// Constructs ICMP packet with calculated checksum
+    pub fn into_icmp(self) -> IcmpPacket {
+        let mut packet = IcmpPacket::new();
+        packet.icmp_type = 42;
+        packet.rest_of_header = self.rest_of_header();
+        packet.payload = self.interface.serialize_extension();
+        packet.recalculate_checksum();
+        packet
+    }
+    /// Constructs ICMPv6 packet with checksum calculated for `source` and `destination` addresses
+    pub fn into_icmpv6(self, source: Ipv6Addr, destination: Ipv6Addr) -> Icmpv6Packet {
+        let mut packet = Icmpv6Packet::new();
+        packet.icmp_type = 160;
+        packet.rest_of_header = self.rest_of_header();
+        packet.payload = self.interface.serialize_extension();
+        packet.recalculate_checksum(source, destination);
+        packet
+    }
+}
+
+/// ICMP Extended Echo Reply(type 43) or ICMPv6 Extended Echo Reply(type 161)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedEchoReply {
+    /// 0 for No Error, 1 for Malformed Query, 2 for No Such Interface, 3 for No Such Table Entry, 4 for Multiple Interfaces Satisfy Query
+    pub code: u8,
+    pub id: u16,
+    pub seq: u8,
+    /// State of neighbor cache entry when probed interface is not local, i.e. 1 for Incomplete, 2 for Reachable, 3 for Stale
+    pub state: u8,
+    /// A-bit - probed interface is active
+    pub active: bool,
+    /// 4-bit - IPv4 is running on probed interface
+    pub ipv4: bool,
+    /// 6-bit - IPv6 is running on probed interface
+    pub ipv6: bool
+}
+impl ExtendedEchoReply {
+    /// Constructs successful reply to `request` with the same identifier and sequence number
+    pub fn reply_to(request: &ExtendedEchoRequest, active: bool, ipv4: bool, ipv6: bool) -> Self {
+        Self {code: 0, id: request.id, seq: request.seq, state: 0, active, ipv4, ipv6}
+    }
+    /// Parses Extended Echo Reply from ICMP `packet`
+    /// Returns `None` if `packet` is another message
+    pub fn from_icmp(packet: &IcmpPacket) -> Option<Self> {
+        if packet.icmp_type != 43 {return None;}
+        Some(Self::parse(packet.code, &packet.rest_of_header))
+    }
+    /// Parses Extended Echo Reply from ICMPv6 `packet`
+    pub fn from_icmpv6(packet: &Icmpv6Packet) -> Option<Self> {
+        if packet.icmp_type != 161 {return None;}
+        Some(Self::parse(packet.code, &packet.rest_of_header))
+    }
+    fn parse(code: u8, rest_of_header: &[u8; 4]) -> Self {
+        Self {
+            code,
+            id: u16::from_be_bytes([rest_of_header[0], rest_of_header[1]]),
+            seq: rest_of_header[2],
+            state: rest_of_header[3] >> 5,
+            active: rest_of_header[3] & 0x04 != 0,
+            ipv4: rest_of_header[3] & 0x02 != 0,
+            ipv6: rest_of_header[3] & 0x01 != 0
+        }
+    }
+    fn rest_of_header(&self) -> [u8; 4] {
+        let id = self.id.to_be_bytes();
+        [id[0], id[1], self.seq, (self.state & 7) << 5 | (self.active as u8) << 2 | (self.ipv4 as u8) << 1 | self.ipv6 as u8]
+    }
+    /// Constructs ICMP packet with calculated checksum
+    pub fn into_icmp(self) -> IcmpPacket {
+        let mut packet = IcmpPacket::new();
+        packet.icmp_type = 43;
+        packet.code = self.code;
+        packet.rest_of_header = self.rest_of_header();
+        packet.recalculate_checksum();
+        packet
+    }
+    /// Constructs ICMPv6 packet with checksum calculated for `source` and `destination` addresses
+    pub fn into_icmpv6(self, source: Ipv6Addr, destination: Ipv6Addr) -> Icmpv6Packet {
+        let mut packet = Icmpv6Packet::new();
+        packet.icmp_type = 161;
+        packet.code = self.code;
+        packet.rest_of_header = self.rest_of_header();
+        packet.recalculate_checksum(source, destination);
+        packet
+    }
+}
\ No newline at end of file
@@ -0,0 +1,299 @@
+use core::net::{IpAddr, Ipv6Addr};
+use super::{IpPacket, IpProtocol};
+use super::icmp::{ExtendedEchoReplyCode, icmp_code};
+use super::ipv6::{Ipv6ExtensionHeader, Ipv6Packet};
+use crate::util::{Serializable, Deserializable, DeserializeError, DeserializableRef, PacketRef, IpPayload, Checksummed, ChecksumContext, ValidationIssue, transport_checksum};
+
+icmp_code! {
+    /// Code of ICMPv6 Destination Unreachable(type 1) message
+    DestinationUnreachableCode {
+        NoRoute = 0,
+        /// Communication with destination administratively prohibited, usually sent by firewalls
+        AdministrativelyProhibited = 1,
+        BeyondScopeOfSource = 2,
+        AddressUnreachable = 3,
+        PortUnreachable = 4,
+        SourcePolicyFailed = 5,
+        RejectRoute = 6,
+        SourceRoutingHeaderError = 7,
+        /// Headers too long, as described in RFC 8883
+        HeadersTooLong = 8
+    }
+}
+icmp_code! {
+    /// Code of ICMPv6 Time Exceeded(type 3) message
+    TimeExceededCode {
+        /// Hop limit reached 0 in transit, which is what traceroute relies on
+        HopLimitExceeded = 0,
+        FragmentReassembly = 1
+    }
+}
+icmp_code! {
+    /// Code of ICMPv6 Parameter Problem(type 4) message
+    ParameterProblemCode {
+        /// Pointer in `rest_of_header` indicates erroneous octet
+        ErroneousHeaderField = 0,
+        UnrecognizedNextHeader = 1,
+        UnrecognizedOption = 2,
+        /// First fragment doesn't contain whole header chain, as described in RFC 7112
+        IncompleteHeaderChain = 3
+    }
+}
+
+/// ICMPv6 message type together with its code
+/// Types which define only code 0 have no code, so they are `Other` with any other code and every type/code pair converts back unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Icmpv6Type {
+    /// 1
+    DestinationUnreachable(DestinationUnreachableCode),
+    /// 2 - MTU of next hop is in `rest_of_header`
+    PacketTooBig,
+    /// 3
+    TimeExceeded(TimeExceededCode),
+    /// 4
+    ParameterProblem(ParameterProblemCode),
+    /// 128
+    EchoRequest,
+    /// 129
+    EchoReply,
+    /// 130
+    MulticastListenerQuery,
+    /// 131
+    MulticastListenerReport,
+    /// 132
+    MulticastListenerDone,
+    /// 133
+    RouterSolicitation,
+    /// 134
+    RouterAdvertisement,
+    /// 135
+    NeighborSolicitation,
+    /// 136
+    NeighborAdvertisement,
+    /// 137
+    Redirect,
+    /// 143
+    MulticastListenerReportV2,
+    /// 160
+    ExtendedEchoRequest,
+    /// 161
+    ExtendedEchoReply(ExtendedEchoReplyCode),
+    /// Other types or codes
+    Other {
+        icmp_type: u8,
+        code: u8
+    }
+}
+impl Icmpv6Type {
+    /// Constructs `Icmpv6Type` from raw type and code
+    pub fn from_type_code(icmp_type: u8, code: u8) -> Self {
+        match (icmp_type, code) {
+            (1, code) => Self::DestinationUnreachable(code.into()),
+            (2, 0) => Self::PacketTooBig,
+            (3, code) => Self::TimeExceeded(code.into()),
+            (4, code) => Self::ParameterProblem(code.into()),
+            (128, 0) => Self::EchoRequest,
+            (129, 0) => Self::EchoReply,
+            (130, 0) => Self::MulticastListenerQuery,
+            (131, 0) => Self::MulticastListenerReport,
+            (132, 0) => Self::MulticastListenerDone,
+            (133, 0) => Self::RouterSolicitation,
+            (134, 0) => Self::RouterAdvertisement,
+            (135, 0) => Self::NeighborSolicitation,
+            (136, 0) => Self::NeighborAdvertisement,
+            (137, 0) => Self::Redirect,
+            (143, 0) => Self::MulticastListenerReportV2,
+            (160, 0) => Self::ExtendedEchoRequest,
+            (161, code) => Self::ExtendedEchoReply(code.into()),
+            (icmp_type, code) => Self::Other {icmp_type, code}
+        }
+    }
+    /// Returns raw type and code
+    pub fn to_type_code(&self) -> (u8, u8) {
+        match *self {
+            Self::DestinationUnreachable(code) => (1, code.into()),
+            Self::PacketTooBig => (2, 0),
+            Self::TimeExceeded(code) => (3, code.into()),
+            Self::ParameterProblem(code) => (4, code.into()),
+            Self::EchoRequest => (128, 0),
+            Self::EchoReply => (129, 0),
+            Self::MulticastListenerQuery => (130, 0),
+            Self::MulticastListenerReport => (131, 0),
+            Self::MulticastListenerDone => (132, 0),
+            Self::RouterSolicitation => (133, 0),
+            Self::RouterAdvertisement => (134, 0),
+            Self::NeighborSolicitation => (135, 0),
+            Self::NeighborAdvertisement => (136, 0),
+            Self::Redirect => (137, 0),
+            Self::MulticastListenerReportV2 => (143, 0),
+            Self::ExtendedEchoRequest => (160, 0),
+            Self::ExtendedEchoReply(code) => (161, code.into()),
+            Self::Other {icmp_type, code} => (icmp_type, code)
+        }
+    }
+    /// Returns `true` if this is an error message, i.e. type is below 128
+    pub fn is_error(&self) -> bool {
+        self.to_type_code().0 < 128
+    }
+}
+
+/// Struct for ordinary ICMPv6 Packet, carried by IPv6 with next header 58
+/// You can construct it from scratch with `Icmpv6Packet::new()` and consistently editing
+/// Or construct from existing packet bytes with `Icmpv6Packet::deserialize()`
+#[derive(Debug, Clone)]
+pub struct Icmpv6Packet {
+    /// ICMPv6 message type, i.e. 128 for Echo Request, 1 for Destination Unreachable, 135 for Neighbor Solicitation
+    pub icmp_type: u8,
+    /// ICMPv6 message code, meaning depends on `icmp_type`
+    pub code: u8,
+    /// Checksum of whole ICMPv6 message with IPv6 pseudo-header
+    pub checksum: u16,
+    /// 4 bytes after checksum, meaning depends on `icmp_type`, i.e. MTU for Packet Too Big
+    pub rest_of_header: [u8; 4],
+    /// Message Data
+    pub payload: Vec<u8>
+}
+impl Icmpv6Packet {
+    /// Constructs an empty `Icmpv6Packet`
+    pub fn new() -> Self {
+        Self {
+            icmp_type: 0,
+            code: 0,
+            checksum: 0,
+            rest_of_header: [0; 4],
+            payload: Vec::new()
+        }
+    }
+    /// Recalculates `checksum` field in `Icmpv6Packet`
+    /// Note that to calculate ICMPv6 Checksum you also need source ip and destination ip from IPv6 packet
+    pub fn recalculate_checksum(&mut self, source_ip: Ipv6Addr, destination_ip: Ipv6Addr) {
+        let mut header = self.serialize_header();
+        header[2..4].fill(0);
+        self.checksum = transport_checksum(IpAddr::V6(source_ip), IpAddr::V6(destination_ip), 58, &[&header, &self.payload]).unwrap_or(0);
+    }
+    /// Serializes `8 bytes` header without payload
+    fn serialize_header(&self) -> [u8; 8] {
+        let mut header = [0u8; 8];
+        header[0] = self.icmp_type;
+        header[1] = self.code;
+        header[2..4].copy_from_slice(&self.checksum.to_be_bytes());
+        header[4..8].copy_from_slice(&self.rest_of_header);
+        header
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        8 + self.payload.len()
+    }
+    pub fn clone_header(&self) -> Self {
+        Self {
+            payload: Vec::new(),
+            ..self.clone()
+        }
+    }
+    /// Returns type and code of message as `Icmpv6Type`
+    pub fn message_type(&self) -> Icmpv6Type {
+        Icmpv6Type::from_type_code(self.icmp_type, self.code)
+    }
+    /// Sets `icmp_type` and `code` fields from `message_type`
+    pub fn set_message_type(&mut self, message_type: Icmpv6Type) {
+        (self.icmp_type, self.code) = message_type.to_type_code();
+    }
+    /// Returns `true` if this is an error message, which quotes original packet, i.e. type is below 128
+    pub fn is_error(&self) -> bool {
+        self.icmp_type < 128
+    }
+    /// Parses original packet quoted in error message, its payload or even extension headers can be truncated
+    /// If Destination Unreachable or Time Exceeded message has RFC 4884 extensions, only quote of declared length is parsed
+    /// Returns `None` if this is not an error message or quote is shorter than IPv6 header
+    pub fn original_packet(&self) -> Option<Ipv6Packet> {
+        if !self.is_error() {return None;}
+        let length = self.rest_of_header[0] as usize * 8;
+        let quote = match self.icmp_type {
+            1 | 3 if length != 0 && length <= self.payload.len() => &self.payload[..length],
+            _ => &self.payload[..]
+        };
+        Ipv6Packet::deserialize_truncated(quote).ok()
+    }
+    /// Returns source and destination ports of TCP or UDP original packet
+    /// Returns `None` if this is not an error message, original packet is not TCP or UDP, or its transport header is not quoted
+    pub fn original_ports(&self) -> Option<(u16, u16)> {
+        let packet = self.original_packet()?;
+        if packet.extension_headers.iter().any(|header| matches!(header, Ipv6ExtensionHeader::Fragment {fragment_offset, ..} if *fragment_offset != 0)) {return None;}
+        let packet = IpPacket::V6(packet);
+        let payload = packet.payload();
+        if !matches!(packet.protocol(), IpProtocol::Tcp | IpProtocol::Udp) || payload.len() < 4 {return None;}
+        Some((u16::from_be_bytes([payload[0], payload[1]]), u16::from_be_bytes([payload[2], payload[3]])))
+    }
+    /// Checks raw ICMPv6 packet `bytes` for problems: truncation and wrong checksum
+    pub fn validate(bytes: &[u8], source_ip: Ipv6Addr, destination_ip: Ipv6Addr) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 8 {
+            issues.push(ValidationIssue::Truncated {layer: "icmpv6", needed: 8, actual: bytes.len()});
+            return issues;
+        }
+        let declared = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let calculated = transport_checksum(IpAddr::V6(source_ip), IpAddr::V6(destination_ip), 58, &[&bytes[..2], &[0, 0], &bytes[4..]]).unwrap_or(0);
+        if calculated != declared {
+            issues.push(ValidationIssue::ChecksumMismatch {layer: "icmpv6", declared, calculated});
+        }
+        issues
+    }
+}
+impl Default for Icmpv6Packet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for Icmpv6Packet {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = self.serialize_header().to_vec();
+        result.append(&mut self.payload);
+        result
+    }
+}
+impl Deserializable for Icmpv6Packet {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for Icmpv6Packet {
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        let header = Self {
+            icmp_type: bytes[0],
+            code: bytes[1],
+            checksum: u16::from_be_bytes([bytes[2], bytes[3]]),
+            rest_of_header: [bytes[4], bytes[5], bytes[6], bytes[7]],
+            payload: Vec::new()
+        };
+        Ok(PacketRef {header, payload: &bytes[8..]})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+impl Checksummed for Icmpv6Packet {
+    /// Computes checksum with pseudo-header from `context` addresses, which must be IPv6
+    fn compute_checksum(&self, context: &ChecksumContext) -> Option<u16> {
+        let (source, destination) = context.addresses?;
+        if !source.is_ipv6() {return None;}
+        let mut header = self.serialize_header();
+        header[2..4].fill(0);
+        transport_checksum(source, destination, 58, &[&header, &self.payload])
+    }
+    fn stored_checksum(&self) -> Option<u16> {
+        Some(self.checksum)
+    }
+    fn set_checksum(&mut self, checksum: u16) {
+        self.checksum = checksum;
+    }
+    fn clear_checksum(&mut self) {
+        self.checksum = 0;
+    }
+}
+impl IpPayload for Icmpv6Packet {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Icmpv6
+    }
+}
\ No newline at end of file
@@ -0,0 +1,434 @@
+use core::net::Ipv4Addr;
+use crate::l3::ipv4::{IpProtocol, Ipv4Option, Ipv4OptionClass, Ipv4Packet};
+use crate::util::{Serializable, Deserializable, DeserializeError, IpPayload, ValidationIssue, ChecksumAccumulator, checksum};
+
+/// IPv4 address IGMPv3 reports are sent to
+pub const IGMPV3_ROUTERS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 22);
+
+/// IGMPv3 Group Record type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgmpRecordType {
+    /// Current-State Record - interface has INCLUDE filter mode for group
+    ModeIsInclude = 1,
+    /// Current-State Record - interface has EXCLUDE filter mode for group
+    ModeIsExclude = 2,
+    /// Filter-Mode-Change Record
+    ChangeToInclude = 3,
+    /// Filter-Mode-Change Record
+    ChangeToExclude = 4,
+    /// Source-List-Change Record
+    AllowNewSources = 5,
+    /// Source-List-Change Record
+    BlockOldSources = 6
+}
+impl Serializable for IgmpRecordType {
+    fn serialize(self) -> Vec<u8> {
+        vec![self as u8]
+    }
+}
+impl Deserializable for IgmpRecordType {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        match bytes[0] {
+            1 => Ok(Self::ModeIsInclude),
+            2 => Ok(Self::ModeIsExclude),
+            3 => Ok(Self::ChangeToInclude),
+            4 => Ok(Self::ChangeToExclude),
+            5 => Ok(Self::AllowNewSources),
+            6 => Ok(Self::BlockOldSources),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+
+/// IGMPv3 Group Record of Membership Report
+#[derive(Debug, Clone)]
+pub struct IgmpGroupRecord {
+    pub record_type: IgmpRecordType,
+    pub multicast_address: Ipv4Addr,
+    pub sources: Vec<Ipv4Addr>,
+    /// Auxiliary data, its length should be multiple of 4
+    pub auxiliary_data: Vec<u8>
+}
+impl IgmpGroupRecord {
+    /// Returns length of this record in bytes
+    pub fn wire_len(&self) -> usize {
+        8 + self.sources.len() * 4 + self.auxiliary_data.len()
+    }
+}
+impl Serializable for IgmpGroupRecord {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.wire_len());
+        result.push(self.record_type.serialize()[0]);
+        result.push((self.auxiliary_data.len() / 4) as u8);
+        result.extend_from_slice(&(self.sources.len() as u16).to_be_bytes());
+        result.extend_from_slice(&self.multicast_address.octets());
+        for source in self.sources {
+            result.extend_from_slice(&source.octets());
+        }
+        result.append(&mut self.auxiliary_data);
+        result
+    }
+}
+impl Deserializable for IgmpGroupRecord {
+    /// Constructs `IgmpGroupRecord` from bytes
+    /// Note that this method is know where record ends
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        let sources_count = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        let sources_end = 8 + sources_count * 4;
+        let end = sources_end + bytes[1] as usize * 4;
+        if bytes.len() < end {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            record_type: IgmpRecordType::deserialize(&bytes[0..1])?,
+            multicast_address: Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]),
+            sources: bytes[8..sources_end].chunks(4)
+                .map(|source| Ipv4Addr::new(source[0], source[1], source[2], source[3]))
+                .collect(),
+            auxiliary_data: bytes[sources_end..end].to_vec()
+        })
+    }
+}
+
+/// IGMP Message
+#[derive(Debug, Clone)]
+pub enum IgmpMessage {
+    /// Type 0x11 - Membership Query of any version
+    /// IGMPv1 and IGMPv2 queries are `8 bytes` long and have no `suppress`, `qrv`, `qqic` and `sources`
+    Query {
+        /// Max Response Time in `0.1 second` units, coded as floating point above 127 for IGMPv3
+        max_response_code: u8,
+        /// `0.0.0.0` for General Query
+        group: Ipv4Addr,
+        /// `S` flag - Suppress Router-Side Processing
+        suppress: bool,
+        /// Querier's Robustness Variable
+        qrv: u8,
+        /// Querier's Query Interval Code
+        qqic: u8,
+        sources: Vec<Ipv4Addr>
+    },
+    /// Type 0x12
+    V1Report {
+        group: Ipv4Addr
+    },
+    /// Type 0x16
+    V2Report {
+        group: Ipv4Addr
+    },
+    /// Type 0x17
+    Leave {
+        group: Ipv4Addr
+    },
+    /// Type 0x22
+    V3Report {
+        records: Vec<IgmpGroupRecord>
+    }
+}
+impl IgmpMessage {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Query {..} => 0x11,
+            Self::V1Report {..} => 0x12,
+            Self::V2Report {..} => 0x16,
+            Self::Leave {..} => 0x17,
+            Self::V3Report {..} => 0x22
+        }
+    }
+}
+
+/// Struct for IGMP Packet, carried by IPv4 with protocol number 2
+/// You can construct it from scratch with `IgmpPacket::new()` and consistently editing
+/// Or construct from existing packet bytes with `IgmpPacket::deserialize()`
+#[derive(Debug, Clone)]
+pub struct IgmpPacket {
+    /// Checksum of whole IGMP message
+    pub checksum: u16,
+    pub message: IgmpMessage
+}
+impl IgmpPacket {
+    /// Constructs an empty IGMPv3 Membership Report
+    pub fn new() -> Self {
+        Self {
+            checksum: 0,
+            message: IgmpMessage::V3Report {
+                records: Vec::new()
+            }
+        }
+    }
+    /// Returns length of serialized packet in bytes, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match &self.message {
+            IgmpMessage::Query {suppress, qrv, qqic, sources, ..} => {
+                if *suppress || *qrv != 0 || *qqic != 0 || !sources.is_empty() {12 + sources.len() * 4} else {8}
+            }
+            IgmpMessage::V1Report {..} |
+            IgmpMessage::V2Report {..} |
+            IgmpMessage::Leave {..} => 8,
+            IgmpMessage::V3Report {records} => 8 + records.iter().map(IgmpGroupRecord::wire_len).sum::<usize>()
+        }
+    }
+    /// Recalculates `checksum` field in `IgmpPacket`
+    pub fn recalculate_checksum(&mut self) {
+        let mut packet = self.clone();
+        packet.checksum = 0;
+        self.checksum = checksum(&packet.serialize());
+    }
+    /// Wraps packet into IPv4 packet from `source` with TTL 1 and Router Alert option, as IGMP requires
+    /// Destination is `IGMPV3_ROUTERS` for IGMPv3 reports, `224.0.0.2` for Leave and group address for other messages
+    pub fn into_ipv4(self, source: Ipv4Addr) -> Ipv4Packet {
+        let mut packet = Ipv4Packet::new();
+        packet.ttl = 1;
+        packet.protocol = IpProtocol::Igmp;
+        packet.source = source;
+        packet.destination = match &self.message {
+            IgmpMessage::V3Report {..} => IGMPV3_ROUTERS,
+            IgmpMessage::Leave {..} => Ipv4Addr::new(224, 0, 0, 2),
+            IgmpMessage::Query {group, ..} if group.is_unspecified() => Ipv4Addr::new(224, 0, 0, 1),
+            IgmpMessage::Query {group, ..} |
+            IgmpMessage::V1Report {group} |
+            IgmpMessage::V2Report {group} => *group
+        };
+        packet.options.push(Ipv4Option {
+            copy: true,
+            class: Ipv4OptionClass::Control,
+            type_number: 20,
+            data: vec![0, 0]
+        });
+        packet.payload = self.serialize();
+        packet.recalculate_checksum();
+        packet
+    }
+    /// Checks raw IGMP packet `bytes` for problems: truncation and wrong checksum
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 8 {
+            issues.push(ValidationIssue::Truncated {layer: "igmp", needed: 8, actual: bytes.len()});
+            return issues;
+        }
+        let declared = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let calculated = ChecksumAccumulator::new().update(&bytes[..2]).update(&[0, 0]).update(&bytes[4..]).finish();
+        if calculated != declared {
+            issues.push(ValidationIssue::ChecksumMismatch {layer: "igmp", declared, calculated});
+        }
+        issues
+    }
+}
+impl Default for IgmpPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for IgmpPacket {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 8];
+        result[0] = self.message.get_type();
+        result[2..4].copy_from_slice(&self.checksum.to_be_bytes());
+        match self.message {
+            IgmpMessage::Query {max_response_code, group, suppress, qrv, qqic, sources} => {
+                result[1] = max_response_code;
+                result[4..8].copy_from_slice(&group.octets());
+                if suppress || qrv != 0 || qqic != 0 || !sources.is_empty() {
+                    result.push((suppress as u8) << 3 | (qrv & 7));
+                    result.push(qqic);
+                    result.extend_from_slice(&(sources.len() as u16).to_be_bytes());
+                    for source in sources {
+                        result.extend_from_slice(&source.octets());
+                    }
+                }
+            }
+            IgmpMessage::V1Report {group} |
+            IgmpMessage::V2Report {group} |
+            IgmpMessage::Leave {group} => result[4..8].copy_from_slice(&group.octets()),
+            IgmpMessage::V3Report {records} => {
+                result[6..8].copy_from_slice(&(records.len() as u16).to_be_bytes());
+                for record in records {
+                    result.append(&mut record.serialize());
+                }
+            }
+        }
+        result
+    }
+}
+impl Deserializable for IgmpPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        let group = Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]);
+        let message = match bytes[0] {
+            0x11 => {
+                let mut message = IgmpMessage::Query {
+                    max_response_code: bytes[1],
+                    group,
+                    suppress: false,
+                    qrv: 0,
+                    qqic: 0,
+                    sources: Vec::new()
+                };
+                if bytes.len() >= 12 {
+                    let sources_count = u16::from_be_bytes([bytes[10], bytes[11]]) as usize;
+                    if bytes.len() < 12 + sources_count * 4 {return Err(DeserializeError::WrongDataLength);}
+                    if let IgmpMessage::Query {suppress, qrv, qqic, sources, ..} = &mut message {
+                        *suppress = bytes[8] & 8 != 0;
+                        *qrv = bytes[8] & 7;
+                        *qqic = bytes[9];
+                        *sources = bytes[12..12 + sources_count * 4].chunks(4)
+                            .map(|source| Ipv4Addr::new(source[0], source[1], source[2], source[3]))
+                            .collect();
+                    }
+                }
+                message
+            }
+            0x12 => IgmpMessage::V1Report {group},
+            0x16 => IgmpMessage::V2Report {group},
+            0x17 => IgmpMessage::Leave {group},
+            0x22 => {
+                let count = u16::from_be_bytes([bytes[6], bytes[7]]);
+                let mut records = Vec::new();
+                let mut i = 8usize;
+                for _ in 0..count {
+                    let record = IgmpGroupRecord::deserialize(&bytes[i..])?;
+                    i += record.wire_len();
+                    records.push(record);
+                }
+                IgmpMessage::V3Report {records}
+            }
+            _ => return Err(DeserializeError::WrongData)
+        };
+        Ok(Self {
+            checksum: u16::from_be_bytes([bytes[2], bytes[3]]),
+            message
+        })
+    }
+}
+
+
+/// Filter mode of interface for multicast group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Receive traffic only from listed sources
+    Include,
+    /// Receive traffic from all sources except listed ones
+    Exclude
+}
+
+/// Composes IGMPv3 Membership Reports with Current-State records from `(group, filter mode, sources)` memberships
+/// See `pack_reports()` for how records are packed
+pub fn current_state_reports(memberships: &[(Ipv4Addr, FilterMode, Vec<Ipv4Addr>)], mtu: usize) -> Vec<IgmpPacket> {
+    pack_reports(memberships_to_records(memberships, IgmpRecordType::ModeIsInclude, IgmpRecordType::ModeIsExclude), mtu)
+}
+
+/// Composes IGMPv3 Membership Reports with Filter-Mode-Change records from `(group, new filter mode, sources)` changes
+/// See `pack_reports()` for how records are packed
+pub fn state_change_reports(changes: &[(Ipv4Addr, FilterMode, Vec<Ipv4Addr>)], mtu: usize) -> Vec<IgmpPacket> {
+    pack_reports(memberships_to_records(changes, IgmpRecordType::ChangeToInclude, IgmpRecordType::ChangeToExclude), mtu)
+}
+
+fn memberships_to_records(memberships: &[(Ipv4Addr, FilterMode, Vec<Ipv4Addr>)], include: IgmpRecordType, exclude: IgmpRecordType) -> Vec<IgmpGroupRecord> {
+    memberships.iter().map(|(group, mode, sources)| IgmpGroupRecord {
+        record_type: if *mode == FilterMode::Include {include} else {exclude},
+        multicast_address: *group,
+        sources: sources.clone(),
+        auxiliary_data: Vec::new()
+    }).collect()
+}
+
+/// Packs `records` into as few IGMPv3 Membership Reports as possible, so each report fits into IPv4 packet of `mtu` bytes with Router Alert option
+/// As RFC 3376 requires, record with too many sources is split into several records with different sources subsets
+/// But EXCLUDE records can't be split, so they are truncated to fit and remaining sources are not reported
+/// Checksums of all reports are calculated
+pub fn pack_reports(records: Vec<IgmpGroupRecord>, mtu: usize) -> Vec<IgmpPacket> {
+    let capacity = mtu.saturating_sub(24 + 8).max(8);
+    let mut reports = Vec::new();
+    let mut current: Vec<IgmpGroupRecord> = Vec::new();
+    let mut current_len = 0usize;
+    let mut flush = |current: &mut Vec<IgmpGroupRecord>, current_len: &mut usize| {
+        if current.is_empty() {return;}
+        let mut report = IgmpPacket {
+            checksum: 0,
+            message: IgmpMessage::V3Report {records: core::mem::take(current)}
+        };
+        report.recalculate_checksum();
+        reports.push(report);
+        *current_len = 0;
+    };
+    for record in records {
+        let max_sources = (capacity.saturating_sub(8 + record.auxiliary_data.len()) / 4).max(1);
+        let parts = if record.sources.len() <= max_sources {
+            vec![record]
+        }
+        else if matches!(record.record_type, IgmpRecordType::ModeIsExclude | IgmpRecordType::ChangeToExclude) {
+            vec![IgmpGroupRecord {
+                sources: record.sources[..max_sources].to_vec(),
+                ..record
+            }]
+        }
+        else {
+            record.sources.chunks(max_sources).map(|sources| IgmpGroupRecord {
+                sources: sources.to_vec(),
+                ..record.clone()
+            }).collect()
+        };
+        for part in parts {
+            if current_len + part.wire_len() > capacity {
+                flush(&mut current, &mut current_len);
+            }
+            current_len += part.wire_len();
+            current.push(part);
+        }
+    }
+    flush(&mut current, &mut current_len);
+    reports
+}
+impl IpPayload for IgmpPacket {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Igmp
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUP: Ipv4Addr = Ipv4Addr::new(239, 1, 2, 3);
+    const SOURCE: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+
+    fn query(suppress: bool, qrv: u8, qqic: u8, sources: Vec<Ipv4Addr>) -> IgmpPacket {
+        IgmpPacket {
+            checksum: 0,
+            message: IgmpMessage::Query {max_response_code: 100, group: GROUP, suppress, qrv, qqic, sources}
+        }
+    }
+
+    #[test]
+    fn wire_len_matches_serialize() {
+        let packets = [
+            query(false, 0, 0, Vec::new()),
+            query(true, 0, 0, Vec::new()),
+            query(false, 2, 125, Vec::new()),
+            query(false, 0, 0, vec![SOURCE, SOURCE]),
+            IgmpPacket {checksum: 0, message: IgmpMessage::V1Report {group: GROUP}},
+            IgmpPacket {checksum: 0, message: IgmpMessage::V2Report {group: GROUP}},
+            IgmpPacket {checksum: 0, message: IgmpMessage::Leave {group: GROUP}},
+            IgmpPacket::new(),
+            IgmpPacket {
+                checksum: 0,
+                message: IgmpMessage::V3Report {records: vec![
+                    IgmpGroupRecord {
+                        record_type: IgmpRecordType::ModeIsInclude,
+                        multicast_address: GROUP,
+                        sources: vec![SOURCE; 3],
+                        auxiliary_data: Vec::new()
+                    },
+                    IgmpGroupRecord {
+                        record_type: IgmpRecordType::ChangeToExclude,
+                        multicast_address: GROUP,
+                        sources: Vec::new(),
+                        auxiliary_data: vec![0; 8]
+                    }
+                ]}
+            }
+        ];
+        for packet in packets {
+            assert_eq!(packet.wire_len(), packet.serialize().len());
+        }
+    }
+}
@@ -1,6 +1,14 @@
-use core::net::Ipv4Addr;
-use crate::util::{Serializable, Deserializable, DeserializeError, checksum};
-pub use super::{DscpType, EcnType};
+use core::net::{IpAddr, Ipv4Addr};
+use crate::l3::{icmp::IcmpPacket, ipv6::Ipv6Packet};
+use crate::l4::{tcp::TcpSegment, udp::UdpDatagram};
+#[cfg(feature = "igmp")]
+use crate::l3::igmp::IgmpPacket;
+#[cfg(feature = "gre")]
+use crate::l3::gre::GrePacket;
+#[cfg(feature = "ospf")]
+use crate::l4::ospf::OspfPacket;
+use crate::util::{ByteReader, Serializable, Deserializable, DeserializeError, DeserializableRef, PacketRef, EtherPayload, IpPayload, ValidationIssue, ChecksumAccumulator, Checksummed, ChecksumContext, checksum, TlvFormat, TlvReader, validate_options};
+pub use super::{DscpType, EcnType, IpProtocol};
 
 /// IPv4 Option Class
 /// Takes up 2nd and 3rd bits of an IPv4 Option
@@ -69,6 +77,15 @@ impl Ipv4Option {
             data: Vec::new()
         }
     }
+    /// Returns length of serialized option without padding
+    pub fn wire_len(&self) -> usize {
+        2 + self.data.len()
+    }
+}
+impl Default for Ipv4Option {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Serializable for Ipv4Option {
     /// Converts option to bytes without padding
@@ -87,15 +104,78 @@ impl Deserializable for Ipv4Option {
     /// Note that this method is know where option ends
     /// So, dont worry about it
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
-        if bytes.len() < 3 {return Err(DeserializeError::WrongDataLength);}
-        Ok(Self {
-            copy: (bytes[0] & 128) != 0,
-            class: Ipv4OptionClass::deserialize(&[(bytes[0] & 96) >> 5])?,
-            type_number: bytes[0] & 31,
-            data: bytes[2..bytes[1] as usize].to_vec()
-        })
+        let mut reader = ByteReader::new(bytes);
+        let option_type = reader.read_u8()?;
+        let length = reader.read_u8()? as usize;
+        if length < 2 {return Err(DeserializeError::WrongData);}
+        Ok(Ipv4OptionRef {option_type, data: reader.read_slice(length - 2)?}.into_owned())
+    }
+}
+
+/// Borrowed view of one IPv4 option, yielded by `Ipv4OptionIter`
+#[derive(Debug, Clone, Copy)]
+pub struct Ipv4OptionRef<'a> {
+    /// Whole option type byte with `copy` flag, class and type number
+    pub option_type: u8,
+    pub data: &'a [u8]
+}
+impl Ipv4OptionRef<'_> {
+    /// Returns `copy` flag of option type
+    pub fn copy(&self) -> bool {
+        (self.option_type & 128) != 0
+    }
+    /// Returns option class bits of option type
+    pub fn class(&self) -> u8 {
+        (self.option_type & 96) >> 5
+    }
+    /// Returns type number of option type
+    pub fn type_number(&self) -> u8 {
+        self.option_type & 31
+    }
+    /// Copies option into owned `Ipv4Option`
+    pub fn into_owned(self) -> Ipv4Option {
+        Ipv4Option {
+            copy: self.copy(),
+            class: match self.class() {
+                0 => Ipv4OptionClass::Control,
+                1 => Ipv4OptionClass::Reserved1,
+                2 => Ipv4OptionClass::Debug,
+                _ => Ipv4OptionClass::Reserved2
+            },
+            type_number: self.type_number(),
+            data: self.data.to_vec()
+        }
+    }
+}
+
+/// Iterator over options of raw IPv4 header without allocation
+/// No-Operation options are skipped, iteration stops at End of Option List
+/// Malformed option is yielded as `Err(DeserializeError::WrongDataLength)` and ends iteration
+#[derive(Debug, Clone)]
+pub struct Ipv4OptionIter<'a> {
+    reader: TlvReader<'a>
+}
+impl<'a> Ipv4OptionIter<'a> {
+    /// Constructs iterator over raw option area, i.e. bytes between `20th byte` and end of IPv4 header
+    pub fn new(options: &'a [u8]) -> Self {
+        Self {reader: TlvReader::new(TlvFormat::IPV4_OPTIONS, options)}
+    }
+    /// Constructs iterator over options of raw IPv4 packet `bytes`, using its header length
+    /// Returns `Err(DeserializeError::WrongDataLength)` if header is truncated or header length is wrong
+    pub fn from_packet(bytes: &'a [u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        let header_len = (bytes[0] as usize & 15) * 4;
+        if header_len < 20 || header_len > bytes.len() {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self::new(&bytes[20..header_len]))
+    }
+}
+impl<'a> Iterator for Ipv4OptionIter<'a> {
+    type Item = Result<Ipv4OptionRef<'a>, DeserializeError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.next().map(|option| option.map(|option| Ipv4OptionRef {option_type: option.kind as u8, data: option.value}))
     }
 }
+impl core::iter::FusedIterator for Ipv4OptionIter<'_> {}
 
 /// Struct for oridinary IPv4 Packet
 /// You can construct it from scratch with `Ipv4Packet::new()` and consistently editing
@@ -123,8 +203,8 @@ pub struct Ipv4Packet {
     /// On each router that this packet passes through, this field is decreased by 1
     /// When the value reaches 0, this packet is discarded
     pub ttl: u8,
-    /// Next Level Packet protocol, i.e. 6 for TCP, 17 for UDP, etc.
-    pub protocol: u8,
+    /// Next Level Packet protocol, i.e. `IpProtocol::Tcp`, `IpProtocol::Udp`, etc.
+    pub protocol: IpProtocol,
     /// Header checksum of this packet
     /// Note that this checksum affects only header, payload remains untouched
     pub checksum: u16,
@@ -139,6 +219,7 @@ pub struct Ipv4Packet {
 }
 impl Ipv4Packet {
     /// Constructs an empty `Ipv4Packet`
+    /// Note that TTL is 0, use `Ipv4Packet::default()` for TTL 64
     pub fn new() -> Self {
         Self {
             dscp: DscpType::BE,
@@ -148,7 +229,7 @@ impl Ipv4Packet {
             more_fragments: false,
             fragment_offset: 0,
             ttl: 0,
-            protocol: 0,
+            protocol: IpProtocol::Other(0),
             checksum: 0,
             source: Ipv4Addr::from_bits(0),
             destination: Ipv4Addr::from_bits(0),
@@ -159,10 +240,15 @@ impl Ipv4Packet {
     /// Recalculates `checksum` field in `Ipv4Packet`
     /// Note that this checksum affects only header, payload remains untouched
     pub fn recalculate_checksum(&mut self) -> () {
-        let mut serialized = self.clone_header().serialize();
-        let length = serialized.len() + self.payload.len();
-        serialized[2..4].copy_from_slice(&(length as u16).to_be_bytes());
-        self.checksum = checksum(serialized);
+        let mut header = self.serialize_header();
+        header[10..12].fill(0);
+        self.checksum = checksum(&header);
+    }
+    /// Sets `payload` to serialized `packet` and `protocol` to its protocol number
+    /// Note that `checksum` isn't recalculated
+    pub fn set_payload_packet<P: IpPayload + Serializable + Clone>(&mut self, packet: &P) {
+        self.protocol = packet.ip_protocol();
+        self.payload = packet.clone().serialize();
     }
     pub fn clone_header(&self) -> Self {
         Self {
@@ -170,9 +256,71 @@ impl Ipv4Packet {
             ..self.clone()
         }
     }
-}
-impl Serializable for Ipv4Packet {
-    fn serialize(mut self) -> Vec<u8> {
+    /// Checks raw IPv4 packet `bytes` for problems: truncation, wrong header length, total length mismatch, wrong header checksum, reserved flag and malformed options
+    /// TCP, UDP, ICMP, IGMP, GRE, OSPF and tunneled IP payload of unfragmented packet is validated too, including its checksum
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 20 {
+            issues.push(ValidationIssue::Truncated {layer: "ipv4", needed: 20, actual: bytes.len()});
+            return issues;
+        }
+        if bytes[0] >> 4 != 4 {
+            issues.push(ValidationIssue::InvalidField {layer: "ipv4", field: "version", value: (bytes[0] >> 4) as u32});
+        }
+        let header_len = (bytes[0] & 15) as usize * 4;
+        if header_len < 20 {
+            issues.push(ValidationIssue::InvalidField {layer: "ipv4", field: "ihl", value: (bytes[0] & 15) as u32});
+            return issues;
+        }
+        if header_len > bytes.len() {
+            issues.push(ValidationIssue::Truncated {layer: "ipv4", needed: header_len, actual: bytes.len()});
+            return issues;
+        }
+        let total_length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if total_length != bytes.len() {
+            issues.push(ValidationIssue::LengthMismatch {layer: "ipv4", field: "total_length", expected: bytes.len(), actual: total_length});
+        }
+        let declared = u16::from_be_bytes([bytes[10], bytes[11]]);
+        let calculated = ChecksumAccumulator::new().update(&bytes[..10]).update(&[0, 0]).update(&bytes[12..header_len]).finish();
+        if calculated != declared {
+            issues.push(ValidationIssue::ChecksumMismatch {layer: "ipv4", declared, calculated});
+        }
+        if bytes[6] & 0x80 != 0 {
+            issues.push(ValidationIssue::InvalidField {layer: "ipv4", field: "reserved_flag", value: 1});
+        }
+        validate_options("ipv4", &bytes[20..header_len], 20, &mut issues);
+        let fragmented = bytes[6] & 0x20 != 0 || u16::from_be_bytes([bytes[6] & 31, bytes[7]]) != 0;
+        if !fragmented && (header_len..=bytes.len()).contains(&total_length) {
+            let payload = &bytes[header_len..total_length];
+            let source = IpAddr::V4(Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]));
+            let destination = IpAddr::V4(Ipv4Addr::new(bytes[16], bytes[17], bytes[18], bytes[19]));
+            match bytes[9] {
+                1 => issues.append(&mut IcmpPacket::validate(payload)),
+                6 => issues.append(&mut TcpSegment::validate_with_addresses(payload, source, destination)),
+                17 => issues.append(&mut UdpDatagram::validate_with_addresses(payload, source, destination)),
+                4 => issues.append(&mut Ipv4Packet::validate(payload)),
+                41 => issues.append(&mut Ipv6Packet::validate(payload)),
+                #[cfg(feature = "igmp")]
+                2 => issues.append(&mut IgmpPacket::validate(payload)),
+                #[cfg(feature = "gre")]
+                47 => issues.append(&mut GrePacket::validate(payload)),
+                #[cfg(feature = "ospf")]
+                89 => issues.append(&mut OspfPacket::validate(payload)),
+                _ => {}
+            }
+        }
+        issues
+    }
+    /// Returns length of header with options, including padding
+    pub fn header_len(&self) -> usize {
+        (20 + self.options.iter().map(Ipv4Option::wire_len).sum::<usize>()).div_ceil(4) * 4
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.header_len() + self.payload.len()
+    }
+    /// Serializes header with options, but without payload, total length field accounts for payload
+    pub(crate) fn serialize_header(&self) -> Vec<u8> {
         let mut result = vec![0u8; 20];
         result[0] = 4 << 4;
         result[1] = self.ecn.serialize()[0];
@@ -184,54 +332,102 @@ impl Serializable for Ipv4Packet {
         result[6] |= fragment_offset[0];
         result[7] = fragment_offset[1];
         result[8] = self.ttl;
-        result[9] = self.protocol;
+        result[9] = self.protocol.into();
         result[10..12].copy_from_slice(&self.checksum.to_be_bytes());
         result[12..16].copy_from_slice(&self.source.octets());
         result[16..20].copy_from_slice(&self.destination.octets());
-        for option in self.options {
+        for option in self.options.clone() {
             result.append(&mut option.serialize());
         }
-        let padding = 4 - result.len() % 4;
+        let padding = (4 - result.len() % 4) % 4;
         if padding != 0 {
-            result.append(&mut vec![1; 4 - padding - 1]);
+            result.append(&mut vec![1; padding - 1]);
             result.push(0);
         }
         result[0] |= (result.len() / 4) as u8 & 0xF;
-        result.append(&mut self.payload);
-        let length = result.len();
+        let length = result.len() + self.payload.len();
         result[2..4].copy_from_slice(&(length as u16).to_be_bytes());
         result
     }
 }
+impl Default for Ipv4Packet {
+    /// Constructs an empty `Ipv4Packet` with TTL 64
+    fn default() -> Self {
+        Self {
+            ttl: 64,
+            ..Self::new()
+        }
+    }
+}
+impl Serializable for Ipv4Packet {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = self.serialize_header();
+        result.append(&mut self.payload);
+        result
+    }
+}
 impl Deserializable for Ipv4Packet {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for Ipv4Packet {
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
         if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
-        if (bytes[0] >> 4) != 4 {return Err(DeserializeError::WrongData);}
+        let mut reader = ByteReader::new(bytes);
+        let version = reader.read_u8()?;
+        if (version >> 4) != 4 {return Err(DeserializeError::WrongData);}
         let mut packet = Self::new();
-        let header_len = (bytes[0] & 15) * 4;
-        packet.dscp = DscpType::deserialize(&[bytes[1] >> 2])?;
-        packet.ecn = EcnType::deserialize(&[bytes[1] & 3])?;
-        packet.id = u16::from_be_bytes([bytes[4], bytes[5]]);
-        packet.dont_fragment = (bytes[6] & 64) != 0;
-        packet.more_fragments = (bytes[6] & 32) != 0;
-        packet.fragment_offset = u16::from_be_bytes([bytes[6] & 31, bytes[7]]) * 8;
-        packet.ttl = bytes[8];
-        packet.protocol = bytes[9];
-        packet.checksum = u16::from_be_bytes([bytes[10], bytes[11]]);
-        packet.source = Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]);
-        packet.destination = Ipv4Addr::new(bytes[16], bytes[17], bytes[18], bytes[19]);
-        if header_len > 20 {
-            let mut i = 20usize;
-            while i < header_len as usize {
-                if bytes[i] == 0 || bytes[i] == 1 {
-                    i += 1;
-                    continue;
-                }
-                packet.options.push(Ipv4Option::deserialize(&bytes[i..])?);
-                i += bytes[i + 1] as usize;
-            }
+        let header_len = (version & 15) as usize * 4;
+        let tos = reader.read_u8()?;
+        packet.dscp = DscpType::deserialize(&[tos >> 2])?;
+        packet.ecn = EcnType::deserialize(&[tos & 3])?;
+        reader.skip(2)?;
+        packet.id = reader.read_u16()?;
+        let fragment = reader.read_u16()?;
+        packet.dont_fragment = (fragment & 0x4000) != 0;
+        packet.more_fragments = (fragment & 0x2000) != 0;
+        packet.fragment_offset = (fragment & 0x1FFF) * 8;
+        packet.ttl = reader.read_u8()?;
+        packet.protocol = IpProtocol::from(reader.read_u8()?);
+        packet.checksum = reader.read_u16()?;
+        packet.source = reader.read_ipv4()?;
+        packet.destination = reader.read_ipv4()?;
+        let options = reader.read_slice(header_len.checked_sub(20).ok_or(DeserializeError::WrongDataLength)?)?;
+        for option in Ipv4OptionIter::new(options) {
+            packet.options.push(option?.into_owned());
         }
-        packet.payload = bytes[header_len as usize..].to_vec();
-        Ok(packet)
+        Ok(PacketRef {header: packet, payload: reader.read_rest()})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+impl Checksummed for Ipv4Packet {
+    /// Computes header checksum, `context` is ignored
+    fn compute_checksum(&self, _context: &ChecksumContext) -> Option<u16> {
+        let mut header = self.serialize_header();
+        header[10..12].fill(0);
+        Some(checksum(&header))
+    }
+    fn stored_checksum(&self) -> Option<u16> {
+        Some(self.checksum)
+    }
+    fn set_checksum(&mut self, checksum: u16) {
+        self.checksum = checksum;
+    }
+    fn clear_checksum(&mut self) {
+        self.checksum = 0;
+    }
+}
+impl IpPayload for Ipv4Packet {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::IpInIp
+    }
+}
+impl EtherPayload for Ipv4Packet {
+    fn ether_type(&self) -> u16 {
+        0x0800
     }
 }
\ No newline at end of file
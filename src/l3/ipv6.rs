@@ -1,20 +1,124 @@
-use core::net::Ipv6Addr;
-use crate::util::{Deserializable, DeserializeError, Serializable};
-pub use super::{DscpType, EcnType};
+use core::net::{IpAddr, Ipv6Addr};
+use crate::l3::{icmpv6::Icmpv6Packet, ipv4::Ipv4Packet};
+use crate::l4::{tcp::TcpSegment, udp::UdpDatagram};
+#[cfg(feature = "gre")]
+use crate::l3::gre::GrePacket;
+use crate::util::{ByteReader, Deserializable, DeserializeError, DeserializableRef, PacketRef, EtherPayload, IpPayload, Serializable, Tlv, TlvFormat, TlvReader, ValidationIssue};
+pub use super::{DscpType, EcnType, IpProtocol};
+
+/// Router Alert option value for Multicast Listener Discovery messages
+pub const ROUTER_ALERT_MLD: u16 = 0;
+/// Router Alert option value for RSVP messages
+pub const ROUTER_ALERT_RSVP: u16 = 1;
+/// Router Alert option value for Active Networks messages
+pub const ROUTER_ALERT_ACTIVE_NETWORKS: u16 = 2;
+
+/// Returns solicited-node multicast address `ff02::1:ffXX:XXXX` of unicast or anycast `address`, which Neighbor Solicitations for it are sent to, as described in RFC 4291
+pub fn solicited_node_address(address: Ipv6Addr) -> Ipv6Addr {
+    Ipv6Addr::from_bits(0xFF02_0000_0000_0000_0000_0001_FF00_0000 | (address.to_bits() & 0xFF_FFFF))
+}
+
+/// Next Header field of IPv6 header or extension header, which is either extension header or upper-layer protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NextHeader {
+    /// 0 - Hop-by-Hop Options header
+    HopByHopOptions,
+    /// 43 - Routing header
+    Routing,
+    /// 44 - Fragment header
+    Fragment,
+    /// 60 - Destination Options header
+    DestinationOptions,
+    /// 135 - Mobility header
+    Mobility,
+    /// 59 - nothing follows the header
+    NoNextHeader,
+    /// Upper-layer protocol, i.e. `IpProtocol::Tcp`
+    Protocol(IpProtocol)
+}
+impl NextHeader {
+    /// Returns `true` for extension headers, which are parsed into `Ipv6ExtensionHeader`
+    pub fn is_extension_header(&self) -> bool {
+        matches!(self, Self::HopByHopOptions | Self::Routing | Self::Fragment | Self::DestinationOptions | Self::Mobility)
+    }
+    /// Returns upper-layer protocol, or `None` for extension headers and No Next Header
+    pub fn protocol(&self) -> Option<IpProtocol> {
+        match self {
+            Self::Protocol(protocol) => Some(*protocol),
+            _ => None
+        }
+    }
+    /// Returns length of extension header starting at `bytes`, or `None` if this is not an extension header or its length field is cut
+    pub fn extension_header_len(&self, bytes: &[u8]) -> Option<usize> {
+        match self {
+            Self::Fragment => Some(8),
+            Self::HopByHopOptions | Self::Routing | Self::DestinationOptions | Self::Mobility => bytes.get(1).map(|length| (*length as usize + 1) * 8),
+            _ => None
+        }
+    }
+}
+impl From<u8> for NextHeader {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::HopByHopOptions,
+            43 => Self::Routing,
+            44 => Self::Fragment,
+            60 => Self::DestinationOptions,
+            135 => Self::Mobility,
+            59 => Self::NoNextHeader,
+            protocol => Self::Protocol(IpProtocol::from(protocol))
+        }
+    }
+}
+impl From<NextHeader> for u8 {
+    fn from(value: NextHeader) -> Self {
+        match value {
+            NextHeader::HopByHopOptions => 0,
+            NextHeader::Routing => 43,
+            NextHeader::Fragment => 44,
+            NextHeader::DestinationOptions => 60,
+            NextHeader::Mobility => 135,
+            NextHeader::NoNextHeader => 59,
+            NextHeader::Protocol(protocol) => protocol.into()
+        }
+    }
+}
+impl From<IpProtocol> for NextHeader {
+    fn from(value: IpProtocol) -> Self {
+        Self::from(u8::from(value))
+    }
+}
+impl From<NextHeader> for IpProtocol {
+    /// Extension headers and No Next Header are converted to `IpProtocol::Other` with their number
+    fn from(value: NextHeader) -> Self {
+        Self::from(u8::from(value))
+    }
+}
+impl PartialEq<u8> for NextHeader {
+    fn eq(&self, other: &u8) -> bool {
+        u8::from(*self) == *other
+    }
+}
+impl core::fmt::Display for NextHeader {
+    /// Formats next header number, as it is shown in packet dumps
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", u8::from(*self))
+    }
+}
 
 /// For now Ipv6ExtensionHeader fully supports only `HopByHopOptions`, `DestinationOptions` and `Fragment`, other variants presented just with `payload: Vec<u8>`
 #[derive(Debug, Clone)]
 pub enum Ipv6ExtensionHeader {
     HopByHopOptions {
-        next_header: u8,
+        next_header: NextHeader,
         options: Vec<Ipv6Option>
     },
     Routing {
-        next_header: u8,
+        next_header: NextHeader,
         payload: Vec<u8>
     },
     Fragment {
-        next_header: u8,
+        next_header: NextHeader,
         /// Actually only 13 bits
         fragment_offset: u16,
         more_fragments: bool,
@@ -22,11 +126,11 @@ pub enum Ipv6ExtensionHeader {
         id: u32
     },
     DestinationOptions {
-        next_header: u8,
+        next_header: NextHeader,
         options: Vec<Ipv6Option>
     },
     Mobility {
-        next_header: u8,
+        next_header: NextHeader,
         payload: Vec<u8>
     }
 }
@@ -37,11 +141,11 @@ impl Serializable for Ipv6ExtensionHeader {
         match self {
             Self::HopByHopOptions {next_header, options} |
             Self::DestinationOptions {next_header, options} => {
-                result[0] = next_header;
+                result[0] = next_header.into();
                 for option in options {
                     result.append(&mut option.serialize());
                 }
-                let padding = 8 - result.len() % 8;
+                let padding = (8 - result.len() % 8) % 8;
                 if padding == 1 {
                     result.push(0);
                 }
@@ -53,12 +157,12 @@ impl Serializable for Ipv6ExtensionHeader {
                 result[1] = (result.len() / 8 - 1) as u8;
             }
             Self::Routing {next_header, mut payload} => {
-                result[0] = next_header;
+                result[0] = next_header.into();
                 result.append(&mut payload);
                 result[1] = (result.len() / 8 - 1) as u8;
             }
             Self::Fragment {next_header, fragment_offset, more_fragments, id} => {
-                result[0] = next_header;
+                result[0] = next_header.into();
                 result[1] = 0;
                 let fragment_offset = (fragment_offset << 3).to_be_bytes();
                 result.push(fragment_offset[0]);
@@ -66,7 +170,7 @@ impl Serializable for Ipv6ExtensionHeader {
                 result.append(&mut id.to_be_bytes().to_vec());
             }
             Self::Mobility {next_header, mut payload} => {
-                result[0] = next_header;
+                result[0] = next_header.into();
                 result.append(&mut payload);
                 result[1] = (result.len() / 8 - 1) as u8;
             }
@@ -74,7 +178,29 @@ impl Serializable for Ipv6ExtensionHeader {
         result
     }
 }
+/// Returns length of Hop-by-Hop or Destination Options header with `options_len` bytes of options, including padding
+fn options_header_len(options_len: usize) -> usize {
+    (2 + options_len).div_ceil(8) * 8
+}
 impl Ipv6ExtensionHeader {
+    /// Returns length of serialized header, including padding, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::HopByHopOptions {options, ..} |
+            Self::DestinationOptions {options, ..} => options_header_len(options.iter().map(Ipv6Option::wire_len).sum()),
+            Self::Routing {payload, ..} |
+            Self::Mobility {payload, ..} => 2 + payload.len(),
+            Self::Fragment {..} => 8
+        }
+    }
+    /// Constructs Hop-by-Hop Options header with single Router Alert option with `value`, e.g. `ROUTER_ALERT_MLD`
+    /// Option is placed right after header start, so it meets its 2n+0 alignment requirement, and padding is added on serialization
+    pub fn router_alert(next_header: NextHeader, value: u16) -> Self {
+        Self::HopByHopOptions {
+            next_header,
+            options: vec![Ipv6Option::router_alert(value)]
+        }
+    }
     pub fn get_order(&self) -> usize {
         match self {
             Self::HopByHopOptions {..} => 0,
@@ -84,16 +210,25 @@ impl Ipv6ExtensionHeader {
             Self::Mobility {..} => 4
         }
     }
-    pub fn get_type(&self) -> u8 {
+    pub fn get_type(&self) -> NextHeader {
+        match self {
+            Self::HopByHopOptions {next_header: _, options: _} => NextHeader::HopByHopOptions,
+            Self::Routing {next_header: _, payload: _} => NextHeader::Routing,
+            Self::Fragment {next_header: _, fragment_offset: _, more_fragments: _, id: _} => NextHeader::Fragment,
+            Self::DestinationOptions {next_header: _, options: _} => NextHeader::DestinationOptions,
+            Self::Mobility {next_header: _, payload: _} => NextHeader::Mobility
+        }
+    }
+    pub fn set_next_header_type(&mut self, next_header_type: NextHeader) {
         match self {
-            Self::HopByHopOptions {next_header: _, options: _} => 0,
-            Self::Routing {next_header: _, payload: _} => 43,
-            Self::Fragment {next_header: _, fragment_offset: _, more_fragments: _, id: _} => 44,
-            Self::DestinationOptions {next_header: _, options: _} => 60,
-            Self::Mobility {next_header: _, payload: _} => 135
+            Self::HopByHopOptions {next_header, ..} |
+            Self::Routing {next_header, ..} |
+            Self::Fragment {next_header, ..} |
+            Self::DestinationOptions {next_header, ..} |
+            Self::Mobility {next_header, ..} => *next_header = next_header_type
         }
     }
-    pub fn get_next_header_type(&self) -> u8 {
+    pub fn get_next_header_type(&self) -> NextHeader {
         match self {
             Self::HopByHopOptions {next_header, options: _} => *next_header,
             Self::Routing {next_header, payload: _} => *next_header,
@@ -109,6 +244,28 @@ pub struct Ipv6Option {
     pub kind: u8,
     pub data: Vec<u8>
 }
+impl Ipv6Option {
+    /// Constructs Jumbo Payload option(type 0xC2) with `length` of jumbogram payload, as described in RFC 2675
+    /// Note that `Ipv6Packet` adds and fills it automatically on serialization when payload exceeds `65535` bytes
+    pub fn jumbo_payload(length: u32) -> Self {
+        Self {
+            kind: 0xC2,
+            data: length.to_be_bytes().to_vec()
+        }
+    }
+    /// Constructs Router Alert option(type 5) with `value`, as described in RFC 2711
+    /// Note that it requires 2n+0 alignment, so prefer `Ipv6ExtensionHeader::router_alert()` over placing it manually
+    pub fn router_alert(value: u16) -> Self {
+        Self {
+            kind: 5,
+            data: value.to_be_bytes().to_vec()
+        }
+    }
+    /// Returns length of serialized option
+    pub fn wire_len(&self) -> usize {
+        2 + self.data.len()
+    }
+}
 impl Serializable for Ipv6Option {
     fn serialize(mut self) -> Vec<u8> {
         let mut result: Vec<u8> = vec![];
@@ -120,10 +277,10 @@ impl Serializable for Ipv6Option {
 }
 impl Deserializable for Ipv6Option {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
-        if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+        let mut reader = ByteReader::new(bytes);
         Ok(Self {
-            kind: bytes[0],
-            data: bytes[2..2 + bytes[1] as usize].to_vec()
+            kind: reader.read_u8()?,
+            data: reader.read_u8_prefixed()?.to_vec()
         })
     }
 }
@@ -136,22 +293,25 @@ pub struct Ipv6Packet {
     pub ecn: EcnType,
     /// In fact, this field has to be 20 bits size, but rust dont allow this, so, just keep this in mind
     pub flow_label: u32,
-    /// Next header type
-    pub next_header: u8,
+    /// Next header type, which is the first extension header or upper-layer protocol
+    pub next_header: NextHeader,
     /// The same as TTL in Ipv4Packet
     pub hop_limit: u8,
     pub source: Ipv6Addr,
     pub destination: Ipv6Addr,
     pub extension_headers: Vec<Ipv6ExtensionHeader>,
+    /// Payload over `65535` bytes makes packet a jumbogram: payload length is serialized as zero and Jumbo Payload option is added to Hop-by-Hop header
     pub payload: Vec<u8>
 }
 impl Ipv6Packet {
+    /// Constructs an empty `Ipv6Packet`
+    /// Note that hop limit and next header are 0, use `Ipv6Packet::default()` for hop limit 64 and next header 59(No Next Header)
     pub fn new() -> Self {
         Self {
             dscp: DscpType::BE,
             ecn: EcnType::NotECT,
             flow_label: 0,
-            next_header: 0,
+            next_header: NextHeader::HopByHopOptions,
             hop_limit: 0,
             source: Ipv6Addr::from_bits(0),
             destination: Ipv6Addr::from_bits(0),
@@ -160,6 +320,237 @@ impl Ipv6Packet {
         }
     }
 }
+impl Default for Ipv6Packet {
+    /// Constructs an empty `Ipv6Packet` with hop limit 64 and next header 59(No Next Header)
+    fn default() -> Self {
+        Self {
+            next_header: NextHeader::NoNextHeader,
+            hop_limit: 64,
+            ..Self::new()
+        }
+    }
+}
+impl Ipv6Packet {
+    /// Sets `payload` to serialized `packet` and its protocol number as next header of the last extension header, or `next_header` if there are none
+    pub fn set_payload_packet<P: IpPayload + Serializable + Clone>(&mut self, packet: &P) {
+        match self.extension_headers.last_mut() {
+            Some(header) => header.set_next_header_type(packet.ip_protocol().into()),
+            None => self.next_header = packet.ip_protocol().into()
+        }
+        self.payload = packet.clone().serialize();
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    /// Jumbo Payload option, which is added on serialization when payload exceeds `65535` bytes, is accounted for
+    pub fn wire_len(&self) -> usize {
+        self.wire_len_with(self.payload.len())
+    }
+    /// The same as `wire_len()`, but for packet with payload of `payload_len` bytes instead of `payload`
+    pub(crate) fn wire_len_with(&self, payload_len: usize) -> usize {
+        let headers_len = self.headers_len();
+        if headers_len + payload_len <= 0xFFFF && !self.is_jumbogram() {return 40 + headers_len + payload_len;}
+        let jumbo_headers_len = match self.extension_headers.first() {
+            Some(header @ Ipv6ExtensionHeader::HopByHopOptions {options, ..}) => {
+                let options_len: usize = options.iter().filter(|option| option.kind != 0xC2).map(Ipv6Option::wire_len).sum();
+                headers_len - header.wire_len() + options_header_len(6 + options_len)
+            }
+            _ => headers_len + 8
+        };
+        40 + jumbo_headers_len + payload_len
+    }
+    /// Returns length of all extension headers, including padding
+    fn headers_len(&self) -> usize {
+        self.extension_headers.iter().map(Ipv6ExtensionHeader::wire_len).sum()
+    }
+    /// Returns length from Jumbo Payload option of Hop-by-Hop header, if packet has one
+    pub fn jumbo_payload_length(&self) -> Option<u32> {
+        match self.extension_headers.first() {
+            Some(Ipv6ExtensionHeader::HopByHopOptions {options, ..}) => options.iter()
+                .find(|option| option.kind == 0xC2 && option.data.len() == 4)
+                .map(|option| u32::from_be_bytes([option.data[0], option.data[1], option.data[2], option.data[3]])),
+            _ => None
+        }
+    }
+    /// Checks if packet is a jumbogram, i.e. has Jumbo Payload option
+    pub fn is_jumbogram(&self) -> bool {
+        self.jumbo_payload_length().is_some()
+    }
+    /// Puts Jumbo Payload option first into Hop-by-Hop header, adding the header if needed, so option meets its 4n+2 alignment requirement
+    fn insert_jumbo_payload_option(&mut self) {
+        if !matches!(self.extension_headers.first(), Some(Ipv6ExtensionHeader::HopByHopOptions {..})) {
+            self.extension_headers.insert(0, Ipv6ExtensionHeader::HopByHopOptions {
+                next_header: self.next_header,
+                options: Vec::new()
+            });
+            self.next_header = NextHeader::HopByHopOptions;
+        }
+        if let Some(Ipv6ExtensionHeader::HopByHopOptions {options, ..}) = self.extension_headers.first_mut() {
+            options.retain(|option| option.kind != 0xC2);
+            options.insert(0, Ipv6Option::jumbo_payload(0));
+        }
+    }
+    /// Checks if packet is an atomic fragment, i.e. has Fragment header with zero offset and without more fragments flag, as described in RFC 8021
+    pub fn is_atomic_fragment(&self) -> bool {
+        self.extension_headers.iter().any(|header| matches!(header, Ipv6ExtensionHeader::Fragment {fragment_offset: 0, more_fragments: false, ..}))
+    }
+    /// Removes redundant Fragment headers of atomic fragment, relinking next header chain around them
+    /// Returns `true` if any header was removed
+    pub fn strip_atomic_fragment(&mut self) -> bool {
+        let mut stripped = false;
+        let mut i = 0usize;
+        while i < self.extension_headers.len() {
+            if let Ipv6ExtensionHeader::Fragment {next_header, fragment_offset: 0, more_fragments: false, ..} = self.extension_headers[i] {
+                match i {
+                    0 => self.next_header = next_header,
+                    _ => self.extension_headers[i - 1].set_next_header_type(next_header)
+                }
+                self.extension_headers.remove(i);
+                stripped = true;
+            }
+            else {
+                i += 1;
+            }
+        }
+        stripped
+    }
+    /// Splits packet into fragments with Fragment header of identification `id`, so each of them fits into `mtu`, as described in RFC 8200
+    /// Hop-by-Hop Options and Routing headers, with Destination Options headers before them, are repeated in each fragment, other extension headers are fragmented with payload
+    /// Returns packet itself if it already fits, or `None` if it's a fragment already or `mtu` leaves no room for `8 bytes` of data in each fragment
+    pub fn fragment(&self, mtu: usize, id: u32) -> Option<Vec<Self>> {
+        if self.wire_len() <= mtu {return Some(vec![self.clone()]);}
+        if self.extension_headers.iter().any(|header| matches!(header, Ipv6ExtensionHeader::Fragment {..})) {return None;}
+        let unfragmentable_len = self.extension_headers.iter()
+            .rposition(|header| matches!(header, Ipv6ExtensionHeader::HopByHopOptions {..} | Ipv6ExtensionHeader::Routing {..}))
+            .map_or(0, |i| i + 1);
+        let mut header = Self {
+            extension_headers: self.extension_headers[..unfragmentable_len].to_vec(),
+            payload: Vec::new(),
+            ..self.clone()
+        };
+        let chunk_len = mtu.checked_sub(48 + header.headers_len())? & !7;
+        if chunk_len == 0 {return None;}
+        let next_header = match header.extension_headers.last_mut() {
+            Some(last) => {
+                let next_header = last.get_next_header_type();
+                last.set_next_header_type(NextHeader::Fragment);
+                next_header
+            }
+            None => core::mem::replace(&mut header.next_header, NextHeader::Fragment)
+        };
+        let mut data = Vec::new();
+        for extension_header in &self.extension_headers[unfragmentable_len..] {
+            data.append(&mut extension_header.clone().serialize());
+        }
+        data.extend_from_slice(&self.payload);
+        let count = data.len().div_ceil(chunk_len);
+        Some(data.chunks(chunk_len).enumerate().map(|(i, chunk)| {
+            let mut fragment = header.clone();
+            fragment.extension_headers.push(Ipv6ExtensionHeader::Fragment {
+                next_header,
+                fragment_offset: (i * chunk_len / 8) as u16,
+                more_fragments: i + 1 < count,
+                id
+            });
+            fragment.payload = chunk.to_vec();
+            fragment
+        }).collect())
+    }
+    /// Constructs `Ipv6Packet` from `bytes`, which can be cut anywhere after the fixed header, i.e. packet quoted in ICMPv6 error message
+    /// If an extension header is cut, extension headers are left unparsed at the start of `payload` and `next_header` is kept
+    pub fn deserialize_truncated(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 40 {return Err(DeserializeError::WrongDataLength);}
+        let mut next_header = NextHeader::from(bytes[6]);
+        let mut i = 40usize;
+        let complete = loop {
+            if !next_header.is_extension_header() {break true;}
+            let header_len = match next_header.extension_header_len(&bytes[i.min(bytes.len())..]) {
+                Some(header_len) => header_len,
+                None => break false
+            };
+            if bytes.len() < i + header_len {break false;}
+            next_header = NextHeader::from(bytes[i]);
+            i += header_len;
+        };
+        if complete {return Self::deserialize(bytes);}
+        let mut header = *bytes[..40].as_array::<40>().unwrap();
+        header[6] = 59;
+        let mut packet = Self::deserialize(&header)?;
+        packet.next_header = NextHeader::from(bytes[6]);
+        packet.payload = bytes[40..].to_vec();
+        Ok(packet)
+    }
+    /// Checks raw IPv6 packet `bytes` for problems: truncation, payload length mismatch, truncated extension headers and malformed options
+    /// TCP, UDP, ICMPv6, GRE and tunneled IP payload of unfragmented packet is validated too, including its checksum
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 40 {
+            issues.push(ValidationIssue::Truncated {layer: "ipv6", needed: 40, actual: bytes.len()});
+            return issues;
+        }
+        if bytes[0] >> 4 != 6 {
+            issues.push(ValidationIssue::InvalidField {layer: "ipv6", field: "version", value: (bytes[0] >> 4) as u32});
+        }
+        let payload_length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        let mut end = bytes.len();
+        if payload_length != 0 && 40 + payload_length != bytes.len() {
+            issues.push(ValidationIssue::LengthMismatch {layer: "ipv6", field: "payload_length", expected: bytes.len() - 40, actual: payload_length});
+            end = end.min(40 + payload_length);
+        }
+        let mut next_header = NextHeader::from(bytes[6]);
+        let mut fragmented = false;
+        let mut i = 40usize;
+        while next_header.is_extension_header() {
+            let Some(header_len) = next_header.extension_header_len(&bytes[i.min(end)..end]) else {
+                issues.push(ValidationIssue::Truncated {layer: "ipv6", needed: i + 2, actual: end});
+                return issues;
+            };
+            if end < i + header_len {
+                issues.push(ValidationIssue::Truncated {layer: "ipv6", needed: i + header_len, actual: end});
+                return issues;
+            }
+            match next_header {
+                NextHeader::HopByHopOptions | NextHeader::DestinationOptions => validate_ipv6_options(&bytes[i + 2..i + header_len], i + 2, &mut issues),
+                NextHeader::Fragment => fragmented |= u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) >> 3 != 0 || bytes[i + 3] & 1 != 0,
+                _ => {}
+            }
+            next_header = NextHeader::from(bytes[i]);
+            i += header_len;
+        }
+        if !fragmented {
+            let payload = &bytes[i..end];
+            let source = Ipv6Addr::from_octets(*bytes[8..24].as_array().unwrap());
+            let destination = Ipv6Addr::from_octets(*bytes[24..40].as_array().unwrap());
+            match next_header.protocol() {
+                Some(IpProtocol::Tcp) => issues.append(&mut TcpSegment::validate_with_addresses(payload, IpAddr::V6(source), IpAddr::V6(destination))),
+                Some(IpProtocol::Udp) => issues.append(&mut UdpDatagram::validate_with_addresses(payload, IpAddr::V6(source), IpAddr::V6(destination))),
+                Some(IpProtocol::Icmpv6) => issues.append(&mut Icmpv6Packet::validate(payload, source, destination)),
+                Some(IpProtocol::IpInIp) => issues.append(&mut Ipv4Packet::validate(payload)),
+                Some(IpProtocol::Ipv6) => issues.append(&mut Ipv6Packet::validate(payload)),
+                #[cfg(feature = "gre")]
+                Some(IpProtocol::Gre) => issues.append(&mut GrePacket::validate(payload)),
+                _ => {}
+            }
+        }
+        issues
+    }
+}
+
+/// Walks options of Hop-by-Hop or Destination Options header, which start at `offset` from packet start, and reports malformed ones
+fn validate_ipv6_options(options: &[u8], offset: usize, issues: &mut Vec<ValidationIssue>) {
+    let mut reader = TlvReader::new(TlvFormat::IPV6_OPTIONS, options);
+    while let Some(option) = reader.next() {
+        if option.is_err() {
+            issues.push(ValidationIssue::MalformedOption {layer: "ipv6", offset: offset + reader.position()});
+            return;
+        }
+    }
+}
+/// Deserializes options of Hop-by-Hop or Destination Options header, skipping Pad1 and PadN
+fn deserialize_ipv6_options(options: &[u8]) -> Result<Vec<Ipv6Option>, DeserializeError> {
+    TlvReader::new(TlvFormat::IPV6_OPTIONS, options)
+        .filter(|option| !matches!(option, Ok(Tlv {kind: 1, ..})))
+        .map(|option| option.map(|option| Ipv6Option {kind: option.kind as u8, data: option.value.to_vec()}))
+        .collect()
+}
 impl Serializable for Ipv6Packet {
     fn serialize(mut self) -> Vec<u8> {
         let mut result = vec![0u8; 40];
@@ -172,121 +563,102 @@ impl Serializable for Ipv6Packet {
         result[1] |= flow_label[1] & 0xF;
         result[2] = flow_label[2];
         result[3] = flow_label[3];
-        result[6] = self.next_header;
         result[7] = self.hop_limit;
         result[8..24].copy_from_slice(&self.source.octets());
         result[24..40].copy_from_slice(&self.destination.octets());
-        let mut payload_length = 0;
+        let jumbo = self.headers_len() + self.payload.len() > 0xFFFF || self.is_jumbogram();
+        if jumbo {
+            self.insert_jumbo_payload_option();
+        }
+        result[6] = self.next_header.into();
         for header in self.extension_headers {
-            let mut header_bytes = header.serialize();
-            payload_length += header_bytes.len();
-            result.append(&mut header_bytes);
+            result.append(&mut header.serialize());
         }
-        payload_length += self.payload.len();
         result.append(&mut self.payload);
-        result[4..6].copy_from_slice(&(payload_length as u16).to_be_bytes());
+        let payload_length = result.len() - 40;
+        if jumbo {
+            result[44..48].copy_from_slice(&(payload_length as u32).to_be_bytes());
+        }
+        else {
+            result[4..6].copy_from_slice(&(payload_length as u16).to_be_bytes());
+        }
         result
     }
 }
 impl Deserializable for Ipv6Packet {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for Ipv6Packet {
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
         if bytes.len() < 40 {return Err(DeserializeError::WrongDataLength);}
-        if (bytes[0] >> 4) != 6 {return Err(DeserializeError::WrongData);}
+        let mut reader = ByteReader::new(bytes);
+        let first = reader.read_u32()?;
+        if (first >> 28) != 6 {return Err(DeserializeError::WrongData);}
         let mut packet = Self::new();
-        packet.dscp = DscpType::deserialize(&[((bytes[0] & 0xF) << 2) | ((bytes[1] & 192) >> 6)])?;
-        packet.ecn = EcnType::deserialize(&[(bytes[1] & 48) >> 4])?;
-        packet.flow_label = u32::from_be_bytes([0u8, bytes[1] & 0xF, bytes[2], bytes[3]]);
-        packet.next_header = bytes[6];
-        packet.hop_limit = bytes[7];
-        packet.source = Ipv6Addr::from_octets(bytes[8..24].as_array().unwrap().clone());
-        packet.destination = Ipv6Addr::from_octets(bytes[24..40].as_array().unwrap().clone());
-        let mut next_header = bytes[6];
-        let mut i = 40usize;
-        loop {
-            match next_header {
-                0 => {
-                    let length = (bytes[i + 1] as usize + 1) * 8 - 2;
-                    let data = &bytes[i + 2..i + 2 + length];
-                    let mut  options: Vec<Ipv6Option> = Vec::new();
-                    let mut j = 0usize;
-                    while j < length {
-                        if data[j] == 0 {
-                            j += 1;
-                        }
-                        else if data[j] == 1 {
-                            j += data[j + 1] as usize + 2;
-                        }
-                        else {
-                            options.push(Ipv6Option::deserialize(&data[j..])?);
-                            j += data[j + 1] as usize + 2;
-                        }
-                    }
-                    packet.extension_headers.push(Ipv6ExtensionHeader::HopByHopOptions {
-                        next_header: bytes[i],
-                        options: options
-                    });
-                    next_header = bytes[i];
-                    i += length + 2;
-                }
-                43 => {
-                    let length = (bytes[i + 1] as usize + 1) * 8;
-                    packet.extension_headers.push(Ipv6ExtensionHeader::Routing {
-                        next_header: bytes[i],
-                        payload: bytes[i + 2..i + length].to_vec()
-                    });
-                    next_header = bytes[i];
-                    i += length;
-                }
-                44 => {
-                    let fragment_offset = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) >> 3;
-                    packet.extension_headers.push(Ipv6ExtensionHeader::Fragment {
-                        next_header: bytes[i],
-                        fragment_offset,
-                        more_fragments: (bytes[i + 3] & 1) != 0,
-                        id: u32::from_be_bytes(bytes[i + 4..i + 8].as_array().unwrap().clone())
-                    });
-                    next_header = bytes[i];
-                    i += 8;
-                }
-                60 => {
-                    let length = (bytes[i + 1] as usize + 1) * 8 - 2;
-                    let data = &bytes[i + 2..i + 2 + length];
-                    let mut  options: Vec<Ipv6Option> = Vec::new();
-                    let mut j = 0usize;
-                    while j < length {
-                        if data[j] == 0 {
-                            j += 1;
-                        }
-                        else if data[j] == 1 {
-                            j += data[j + 1] as usize + 2;
-                        }
-                        else {
-                            options.push(Ipv6Option::deserialize(&data[j..])?);
-                            j += data[j + 1] as usize + 2;
-                        }
-                    }
-                    packet.extension_headers.push(Ipv6ExtensionHeader::DestinationOptions {
-                        next_header: bytes[i],
-                        options: options
-                    });
-                    next_header = bytes[i];
-                    i += length + 2;
-                }
-                135 => {
-                    let length = (bytes[i + 1] as u16 + 1) * 8;
-                    packet.extension_headers.push(Ipv6ExtensionHeader::Mobility {
-                        next_header: bytes[i],
-                        payload: bytes[i + 2..i + length as usize].to_vec()
-                    });
-                    next_header = bytes[i];
-                    i += length as usize;
-                }
+        packet.dscp = DscpType::deserialize(&[(first >> 22) as u8 & 63])?;
+        packet.ecn = EcnType::deserialize(&[(first >> 20) as u8 & 3])?;
+        packet.flow_label = first & 0xF_FFFF;
+        let payload_length = reader.read_u16()?;
+        packet.next_header = NextHeader::from(reader.read_u8()?);
+        packet.hop_limit = reader.read_u8()?;
+        packet.source = reader.read_ipv6()?;
+        packet.destination = reader.read_ipv6()?;
+        let mut next_header = packet.next_header;
+        while next_header.is_extension_header() {
+            let header_len = next_header.extension_header_len(reader.rest()).ok_or(DeserializeError::WrongDataLength)?;
+            let mut header = ByteReader::new(reader.read_slice(header_len)?);
+            let header_next = NextHeader::from(header.read_u8()?);
+            header.skip(1)?;
+            packet.extension_headers.push(match next_header {
+                NextHeader::HopByHopOptions => Ipv6ExtensionHeader::HopByHopOptions {
+                    next_header: header_next,
+                    options: deserialize_ipv6_options(header.read_rest())?
+                },
+                NextHeader::DestinationOptions => Ipv6ExtensionHeader::DestinationOptions {
+                    next_header: header_next,
+                    options: deserialize_ipv6_options(header.read_rest())?
+                },
+                NextHeader::Routing => Ipv6ExtensionHeader::Routing {
+                    next_header: header_next,
+                    payload: header.read_rest().to_vec()
+                },
+                NextHeader::Mobility => Ipv6ExtensionHeader::Mobility {
+                    next_header: header_next,
+                    payload: header.read_rest().to_vec()
+                },
                 _ => {
-                    packet.payload = bytes[i..].to_vec();
-                    break;
+                    let fragment = header.read_u16()?;
+                    Ipv6ExtensionHeader::Fragment {
+                        next_header: header_next,
+                        fragment_offset: fragment >> 3,
+                        more_fragments: (fragment & 1) != 0,
+                        id: header.read_u32()?
+                    }
                 }
-            }
+            });
+            next_header = header_next;
         }
-        Ok(packet)
+        let mut payload = reader.read_rest();
+        if payload_length == 0 && let Some(length) = packet.jumbo_payload_length() {
+            let length = (40 + length as usize).checked_sub(reader.position() - payload.len()).ok_or(DeserializeError::WrongDataLength)?;
+            payload = payload.get(..length).ok_or(DeserializeError::WrongDataLength)?;
+        }
+        Ok(PacketRef {header: packet, payload})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+impl IpPayload for Ipv6Packet {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Ipv6
+    }
+}
+impl EtherPayload for Ipv6Packet {
+    fn ether_type(&self) -> u16 {
+        0x86DD
     }
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+use core::net::Ipv6Addr;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv6::{IpProtocol, Ipv6ExtensionHeader, Ipv6Packet, NextHeader, ROUTER_ALERT_MLD};
+use crate::util::{Serializable, Deserializable, DeserializeError};
+pub use crate::l3::igmp::IgmpRecordType as MldRecordType;
+
+/// IPv6 address MLDv2 reports are sent to
+pub const MLDV2_ROUTERS: Ipv6Addr = Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 0, 0x16);
+
+fn read_addresses(bytes: &[u8]) -> Vec<Ipv6Addr> {
+    bytes.chunks_exact(16).map(|address| Ipv6Addr::from_octets(*address.as_array().unwrap())).collect()
+}
+
+/// MLDv2 Multicast Address Record of Listener Report
+/// Record types are the same as for IGMPv3 Group Records
+#[derive(Debug, Clone)]
+pub struct MldAddressRecord {
+    pub record_type: MldRecordType,
+    pub multicast_address: Ipv6Addr,
+    pub sources: Vec<Ipv6Addr>,
+    /// Auxiliary data, its length should be multiple of 4
+    pub auxiliary_data: Vec<u8>
+}
+impl MldAddressRecord {
+    /// Returns length of this record in bytes
+    pub fn wire_len(&self) -> usize {
+        20 + self.sources.len() * 16 + self.auxiliary_data.len()
+    }
+}
+impl Serializable for MldAddressRecord {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.wire_len());
+        result.push(self.record_type.serialize()[0]);
+        result.push((self.auxiliary_data.len() / 4) as u8);
+        result.extend_from_slice(&(self.sources.len() as u16).to_be_bytes());
+        result.extend_from_slice(&self.multicast_address.octets());
+        for source in self.sources {
+            result.extend_from_slice(&source.octets());
+        }
+        result.append(&mut self.auxiliary_data);
+        result
+    }
+}
+impl Deserializable for MldAddressRecord {
+    /// Constructs `MldAddressRecord` from bytes
+    /// Note that this method is know where record ends
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        let sources_end = 20 + u16::from_be_bytes([bytes[2], bytes[3]]) as usize * 16;
+        let end = sources_end + bytes[1] as usize * 4;
+        if bytes.len() < end {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            record_type: MldRecordType::deserialize(&bytes[0..1])?,
+            multicast_address: Ipv6Addr::from_octets(*bytes[4..20].as_array().unwrap()),
+            sources: read_addresses(&bytes[20..sources_end]),
+            auxiliary_data: bytes[sources_end..end].to_vec()
+        })
+    }
+}
+
+/// Multicast Listener Discovery message, carried by ICMPv6
+#[derive(Debug, Clone)]
+pub enum MldMessage {
+    /// ICMPv6 type 130 - Multicast Listener Query of any version
+    /// MLDv1 queries are `24 bytes` long and have no `suppress`, `qrv`, `qqic` and `sources`
+    Query {
+        /// Maximum Response Delay in milliseconds, coded as floating point above 32767 for MLDv2
+        max_response_code: u16,
+        /// `::` for General Query
+        multicast_address: Ipv6Addr,
+        /// `S` flag - Suppress Router-Side Processing
+        suppress: bool,
+        /// Querier's Robustness Variable
+        qrv: u8,
+        /// Querier's Query Interval Code
+        qqic: u8,
+        sources: Vec<Ipv6Addr>
+    },
+    /// ICMPv6 type 131 - MLDv1 Multicast Listener Report
+    V1Report {
+        multicast_address: Ipv6Addr
+    },
+    /// ICMPv6 type 132 - MLDv1 Multicast Listener Done
+    Done {
+        multicast_address: Ipv6Addr
+    },
+    /// ICMPv6 type 143 - MLDv2 Multicast Listener Report
+    V2Report {
+        records: Vec<MldAddressRecord>
+    }
+}
+impl MldMessage {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Query {..} => 130,
+            Self::V1Report {..} => 131,
+            Self::Done {..} => 132,
+            Self::V2Report {..} => 143
+        }
+    }
+    /// Constructs `MldMessage` from ICMPv6 `packet`
+    /// Returns `Err(DeserializeError::WrongData)` if `packet` is not an MLD message
+    pub fn from_icmpv6(packet: &Icmpv6Packet) -> Result<Self, DeserializeError> {
+        let header = &packet.rest_of_header;
+        let body = &packet.payload;
+        match packet.icmp_type {
+            130 => {
+                if body.len() < 16 {return Err(DeserializeError::WrongDataLength);}
+                let mut sources = Vec::new();
+                let (mut suppress, mut qrv, mut qqic) = (false, 0, 0);
+                if body.len() >= 20 {
+                    let sources_end = 20 + u16::from_be_bytes([body[18], body[19]]) as usize * 16;
+                    if body.len() < sources_end {return Err(DeserializeError::WrongDataLength);}
+                    suppress = body[16] & 8 != 0;
+                    qrv = body[16] & 7;
+                    qqic = body[17];
+                    sources = read_addresses(&body[20..sources_end]);
+                }
+                Ok(Self::Query {
+                    max_response_code: u16::from_be_bytes([header[0], header[1]]),
+                    multicast_address: Ipv6Addr::from_octets(*body[0..16].as_array().unwrap()),
+                    suppress,
+                    qrv,
+                    qqic,
+                    sources
+                })
+            }
+            131 | 132 => {
+                if body.len() < 16 {return Err(DeserializeError::WrongDataLength);}
+                let multicast_address = Ipv6Addr::from_octets(*body[0..16].as_array().unwrap());
+                if packet.icmp_type == 131 {
+                    Ok(Self::V1Report {multicast_address})
+                }
+                else {
+                    Ok(Self::Done {multicast_address})
+                }
+            }
+            143 => {
+                let count = u16::from_be_bytes([header[2], header[3]]);
+                let mut records = Vec::new();
+                let mut i = 0usize;
+                for _ in 0..count {
+                    let record = MldAddressRecord::deserialize(&body[i..])?;
+                    i += record.wire_len();
+                    records.push(record);
+                }
+                Ok(Self::V2Report {records})
+            }
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+    /// Converts message to ICMPv6 packet
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` with IPv6 addresses or just `into_ipv6()`
+    pub fn into_icmpv6(self) -> Icmpv6Packet {
+        let mut packet = Icmpv6Packet::new();
+        packet.icmp_type = self.get_type();
+        match self {
+            Self::Query {max_response_code, multicast_address, suppress, qrv, qqic, sources} => {
+                packet.rest_of_header[0..2].copy_from_slice(&max_response_code.to_be_bytes());
+                packet.payload.extend_from_slice(&multicast_address.octets());
+                if suppress || qrv != 0 || qqic != 0 || !sources.is_empty() {
+                    packet.payload.push((suppress as u8) << 3 | (qrv & 7));
+                    packet.payload.push(qqic);
+                    packet.payload.extend_from_slice(&(sources.len() as u16).to_be_bytes());
+                    for source in sources {
+                        packet.payload.extend_from_slice(&source.octets());
+                    }
+                }
+            }
+            Self::V1Report {multicast_address} |
+            Self::Done {multicast_address} => packet.payload.extend_from_slice(&multicast_address.octets()),
+            Self::V2Report {records} => {
+                packet.rest_of_header[2..4].copy_from_slice(&(records.len() as u16).to_be_bytes());
+                for record in records {
+                    packet.payload.append(&mut record.serialize());
+                }
+            }
+        }
+        packet
+    }
+    /// Wraps message into IPv6 packet from link-local `source` with hop limit 1 and Router Alert option, as MLD requires
+    /// Destination is `MLDV2_ROUTERS` for MLDv2 reports, `ff02::2` for Done, `ff02::1` for General Query and multicast address for other messages
+    /// ICMPv6 checksum is calculated
+    pub fn into_ipv6(self, source: Ipv6Addr) -> Ipv6Packet {
+        let destination = match &self {
+            Self::V2Report {..} => MLDV2_ROUTERS,
+            Self::Done {..} => Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 0, 2),
+            Self::Query {multicast_address, ..} if multicast_address.is_unspecified() => Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 0, 1),
+            Self::Query {multicast_address, ..} |
+            Self::V1Report {multicast_address} => *multicast_address
+        };
+        let mut icmp = self.into_icmpv6();
+        icmp.recalculate_checksum(source, destination);
+        let mut packet = Ipv6Packet::new();
+        packet.next_header = NextHeader::HopByHopOptions;
+        packet.hop_limit = 1;
+        packet.source = source;
+        packet.destination = destination;
+        packet.extension_headers.push(Ipv6ExtensionHeader::router_alert(NextHeader::Protocol(IpProtocol::Icmpv6), ROUTER_ALERT_MLD));
+        packet.payload = icmp.serialize();
+        packet
+    }
+}
\ No newline at end of file
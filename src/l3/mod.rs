@@ -1,7 +1,241 @@
 pub mod ipv4;
 pub mod ipv6;
+#[cfg(feature = "arp")]
 pub mod arp;
-use crate::util::{Deserializable, DeserializeError, Serializable};
+pub mod icmp;
+pub mod icmpv6;
+#[cfg(feature = "igmp")]
+pub mod igmp;
+#[cfg(feature = "mld")]
+pub mod mld;
+pub mod ping;
+#[cfg(feature = "gre")]
+pub mod gre;
+#[cfg(feature = "erspan")]
+pub mod erspan;
+pub mod pmtud;
+pub mod siit;
+#[cfg(feature = "ndp")]
+pub mod ndp;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::util::{ChecksumContext, Deserializable, DeserializeError, EtherPayload, IpPayload, Serializable};
+use ipv4::Ipv4Packet;
+use ipv6::Ipv6Packet;
+
+/// IPv4 or IPv6 packet, distinguished by version field when deserializing
+#[derive(Debug, Clone)]
+pub enum IpPacket {
+    V4(Ipv4Packet),
+    V6(Ipv6Packet)
+}
+impl IpPacket {
+    /// Returns upper-layer protocol, which for IPv6 is next header of the last extension header
+    pub fn protocol(&self) -> IpProtocol {
+        match self {
+            Self::V4(packet) => packet.protocol,
+            Self::V6(packet) => packet.extension_headers.last().map(|header| header.get_next_header_type()).unwrap_or(packet.next_header).into()
+        }
+    }
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            Self::V4(packet) => &packet.payload,
+            Self::V6(packet) => &packet.payload
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::V4(packet) => packet.wire_len(),
+            Self::V6(packet) => packet.wire_len()
+        }
+    }
+    /// Returns context for checksums of upper-layer protocol carried by this packet
+    pub fn checksum_context(&self) -> ChecksumContext {
+        ChecksumContext::with_addresses(self.source(), self.destination())
+    }
+    pub fn source(&self) -> IpAddr {
+        match self {
+            Self::V4(packet) => IpAddr::V4(packet.source),
+            Self::V6(packet) => IpAddr::V6(packet.source)
+        }
+    }
+    pub fn destination(&self) -> IpAddr {
+        match self {
+            Self::V4(packet) => IpAddr::V4(packet.destination),
+            Self::V6(packet) => IpAddr::V6(packet.destination)
+        }
+    }
+    /// Constructs an empty packet of the same version and flow, with addresses swapped if `reverse` is `true`
+    /// DSCP, ECN and TTL or hop limit are copied, but IPv4 options, IPv6 extension headers and fragmentation fields are not
+    pub fn flow_header(&self, reverse: bool) -> IpPacket {
+        match self {
+            Self::V4(packet) => {
+                let mut header = Ipv4Packet::new();
+                header.dscp = packet.dscp;
+                header.ecn = packet.ecn;
+                header.ttl = packet.ttl;
+                header.dont_fragment = packet.dont_fragment;
+                (header.source, header.destination) = if reverse {(packet.destination, packet.source)} else {(packet.source, packet.destination)};
+                Self::V4(header)
+            }
+            Self::V6(packet) => {
+                let mut header = Ipv6Packet::new();
+                header.dscp = packet.dscp;
+                header.ecn = packet.ecn;
+                header.flow_label = packet.flow_label;
+                header.hop_limit = packet.hop_limit;
+                (header.source, header.destination) = if reverse {(packet.destination, packet.source)} else {(packet.source, packet.destination)};
+                Self::V6(header)
+            }
+        }
+    }
+    /// Returns inner packet of IP-in-IP (protocol 4) or 6in4/6in6 (protocol 41) tunnel
+    /// Returns `Err(DeserializeError::WrongData)` if packet is not a tunnel
+    pub fn decapsulate(&self) -> Result<IpPacket, DeserializeError> {
+        match self.protocol() {
+            IpProtocol::IpInIp => Ok(Self::V4(Ipv4Packet::deserialize(self.payload())?)),
+            IpProtocol::Ipv6 => Ok(Self::V6(Ipv6Packet::deserialize(self.payload())?)),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+    /// Wraps packet into IPv4 tunnel packet with protocol 4 or 41, i.e. IP-in-IP or 6in4
+    /// Outer packet has TTL 64 and calculated checksum
+    pub fn into_ipv4_tunnel(self, source: Ipv4Addr, destination: Ipv4Addr) -> Ipv4Packet {
+        let mut packet = Ipv4Packet::new();
+        packet.ttl = 64;
+        packet.protocol = self.ip_protocol();
+        packet.source = source;
+        packet.destination = destination;
+        packet.payload = self.serialize();
+        packet.recalculate_checksum();
+        packet
+    }
+    /// Wraps packet into IPv6 tunnel packet with next header 4 or 41, i.e. 4in6 or IPv6-in-IPv6
+    /// Outer packet has hop limit 64
+    pub fn into_ipv6_tunnel(self, source: Ipv6Addr, destination: Ipv6Addr) -> Ipv6Packet {
+        let mut packet = Ipv6Packet::new();
+        packet.hop_limit = 64;
+        packet.next_header = self.ip_protocol().into();
+        packet.source = source;
+        packet.destination = destination;
+        packet.payload = self.serialize();
+        packet
+    }
+}
+impl IpPayload for IpPacket {
+    fn ip_protocol(&self) -> IpProtocol {
+        match self {
+            Self::V4(_) => IpProtocol::IpInIp,
+            Self::V6(_) => IpProtocol::Ipv6
+        }
+    }
+}
+impl EtherPayload for IpPacket {
+    fn ether_type(&self) -> u16 {
+        match self {
+            Self::V4(_) => 0x0800,
+            Self::V6(_) => 0x86DD
+        }
+    }
+}
+impl Serializable for IpPacket {
+    fn serialize(self) -> Vec<u8> {
+        match self {
+            Self::V4(packet) => packet.serialize(),
+            Self::V6(packet) => packet.serialize()
+        }
+    }
+}
+impl Deserializable for IpPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        match bytes[0] >> 4 {
+            4 => Ok(Self::V4(Ipv4Packet::deserialize(bytes)?)),
+            6 => Ok(Self::V6(Ipv6Packet::deserialize(bytes)?)),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+
+/// Protocol number of IPv4 protocol field or IPv6 upper-layer next header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpProtocol {
+    /// 1 - Internet Control Message Protocol
+    Icmp,
+    /// 2 - Internet Group Management Protocol
+    Igmp,
+    /// 4 - IPv4 encapsulated in IP
+    IpInIp,
+    /// 6 - Transmission Control Protocol
+    Tcp,
+    /// 17 - User Datagram Protocol
+    Udp,
+    /// 41 - IPv6 encapsulated in IP
+    Ipv6,
+    /// 47 - Generic Routing Encapsulation
+    Gre,
+    /// 50 - Encapsulating Security Payload
+    Esp,
+    /// 51 - Authentication Header
+    Ah,
+    /// 58 - ICMP for IPv6
+    Icmpv6,
+    /// 89 - Open Shortest Path First
+    Ospf,
+    /// 132 - Stream Control Transmission Protocol
+    Sctp,
+    /// Any other protocol number
+    Other(u8)
+}
+impl From<u8> for IpProtocol {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Icmp,
+            2 => Self::Igmp,
+            4 => Self::IpInIp,
+            6 => Self::Tcp,
+            17 => Self::Udp,
+            41 => Self::Ipv6,
+            47 => Self::Gre,
+            50 => Self::Esp,
+            51 => Self::Ah,
+            58 => Self::Icmpv6,
+            89 => Self::Ospf,
+            132 => Self::Sctp,
+            other => Self::Other(other)
+        }
+    }
+}
+impl From<IpProtocol> for u8 {
+    fn from(value: IpProtocol) -> Self {
+        match value {
+            IpProtocol::Icmp => 1,
+            IpProtocol::Igmp => 2,
+            IpProtocol::IpInIp => 4,
+            IpProtocol::Tcp => 6,
+            IpProtocol::Udp => 17,
+            IpProtocol::Ipv6 => 41,
+            IpProtocol::Gre => 47,
+            IpProtocol::Esp => 50,
+            IpProtocol::Ah => 51,
+            IpProtocol::Icmpv6 => 58,
+            IpProtocol::Ospf => 89,
+            IpProtocol::Sctp => 132,
+            IpProtocol::Other(other) => other
+        }
+    }
+}
+impl PartialEq<u8> for IpProtocol {
+    fn eq(&self, other: &u8) -> bool {
+        u8::from(*self) == *other
+    }
+}
+impl core::fmt::Display for IpProtocol {
+    /// Formats protocol number, as it is shown in packet dumps
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", u8::from(*self))
+    }
+}
 
 /// Differentiated Services Code Point, used for classify and mark packets within the framework of QoS(Quality of Service)
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,328 @@
+use core::net::Ipv6Addr;
+use core::time::Duration;
+use std::collections::HashMap;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv6::{self, IpProtocol, Ipv6Packet, NextHeader};
+use crate::util::{Serializable, DeserializeError};
+
+/// IPv6 address of all nodes on link, which unsolicited advertisements are sent to
+pub const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Returns value of link-layer address option of `kind` from NDP `options`
+fn link_address(options: &[u8], kind: u8) -> Option<[u8; 6]> {
+    let mut i = 0usize;
+    while i + 2 <= options.len() {
+        let length = options[i + 1] as usize * 8;
+        if length == 0 || i + length > options.len() {return None;}
+        if options[i] == kind && length >= 8 {return Some(*options[i + 2..i + 8].as_array().unwrap());}
+        i += length;
+    }
+    None
+}
+
+/// Neighbor Discovery message of address resolution, carried by ICMPv6, as described in RFC 4861
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NdpMessage {
+    /// ICMPv6 type 135 - Neighbor Solicitation
+    NeighborSolicitation {
+        target: Ipv6Addr,
+        /// Source Link-Layer Address option, which must be absent when source address is unspecified
+        source_link_address: Option<[u8; 6]>
+    },
+    /// ICMPv6 type 136 - Neighbor Advertisement
+    NeighborAdvertisement {
+        /// `R` flag - sender is a router
+        router: bool,
+        /// `S` flag - advertisement answers solicitation
+        solicited: bool,
+        /// `O` flag - advertisement overrides cached link-layer address
+        override_flag: bool,
+        target: Ipv6Addr,
+        /// Target Link-Layer Address option
+        target_link_address: Option<[u8; 6]>
+    }
+}
+impl NdpMessage {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::NeighborSolicitation {..} => 135,
+            Self::NeighborAdvertisement {..} => 136
+        }
+    }
+    /// Constructs `NdpMessage` from ICMPv6 `packet`, options other than link-layer addresses are skipped
+    /// Returns `Err(DeserializeError::WrongData)` if `packet` is not a Neighbor Solicitation or Advertisement
+    pub fn from_icmpv6(packet: &Icmpv6Packet) -> Result<Self, DeserializeError> {
+        if !matches!(packet.icmp_type, 135 | 136) || packet.code != 0 {return Err(DeserializeError::WrongData);}
+        if packet.payload.len() < 16 {return Err(DeserializeError::WrongDataLength);}
+        let target = Ipv6Addr::from_octets(*packet.payload[0..16].as_array().unwrap());
+        let options = &packet.payload[16..];
+        if packet.icmp_type == 135 {
+            return Ok(Self::NeighborSolicitation {target, source_link_address: link_address(options, 1)});
+        }
+        let flags = packet.rest_of_header[0];
+        Ok(Self::NeighborAdvertisement {
+            router: flags & 0x80 != 0,
+            solicited: flags & 0x40 != 0,
+            override_flag: flags & 0x20 != 0,
+            target,
+            target_link_address: link_address(options, 2)
+        })
+    }
+    /// Converts message to ICMPv6 packet
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` with IPv6 addresses or just `into_ipv6()`
+    pub fn into_icmpv6(self) -> Icmpv6Packet {
+        let mut packet = Icmpv6Packet::new();
+        packet.icmp_type = self.get_type();
+        let (target, option) = match self {
+            Self::NeighborSolicitation {target, source_link_address} => (target, source_link_address.map(|mac| (1, mac))),
+            Self::NeighborAdvertisement {router, solicited, override_flag, target, target_link_address} => {
+                packet.rest_of_header[0] = (router as u8) << 7 | (solicited as u8) << 6 | (override_flag as u8) << 5;
+                (target, target_link_address.map(|mac| (2, mac)))
+            }
+        };
+        packet.payload.extend_from_slice(&target.octets());
+        if let Some((kind, mac)) = option {
+            packet.payload.extend_from_slice(&[kind, 1]);
+            packet.payload.extend_from_slice(&mac);
+        }
+        packet
+    }
+    /// Wraps message into IPv6 packet from `source` to `destination` with hop limit 255, as Neighbor Discovery requires
+    /// ICMPv6 checksum is calculated
+    pub fn into_ipv6(self, source: Ipv6Addr, destination: Ipv6Addr) -> Ipv6Packet {
+        let mut icmp = self.into_icmpv6();
+        icmp.recalculate_checksum(source, destination);
+        let mut packet = Ipv6Packet::new();
+        packet.next_header = NextHeader::Protocol(IpProtocol::Icmpv6);
+        packet.hop_limit = 255;
+        packet.source = source;
+        packet.destination = destination;
+        packet.payload = icmp.serialize();
+        packet
+    }
+}
+
+/// State of `NeighborCache` entry, as described in RFC 4861
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighborState {
+    /// Solicitation was sent to solicited-node multicast address, but no advertisement received yet
+    Incomplete,
+    /// Neighbor was confirmed reachable recently
+    Reachable,
+    /// Reachability is not confirmed for a while, address is still used, but first use starts verification
+    Stale,
+    /// Entry was used while stale, probe will be sent if reachability isn't confirmed by upper layer soon
+    Delay,
+    /// Unicast solicitations are sent to verify reachability
+    Probe
+}
+
+/// Entry of `NeighborCache`
+#[derive(Debug, Clone)]
+pub struct NeighborEntry {
+    /// `None` while entry is incomplete
+    pub mac: Option<[u8; 6]>,
+    pub state: NeighborState,
+    /// Neighbor is a router, as its last advertisement says
+    pub router: bool,
+    /// Time of the last state change
+    pub updated: Duration,
+    /// Number of solicitations sent in current `Incomplete` or `Probe` state
+    pub solicitations: u32,
+    /// Time of the last sent solicitation
+    last_solicitation: Option<Duration>
+}
+impl NeighborEntry {
+    fn new(mac: Option<[u8; 6]>, state: NeighborState, now: Duration) -> Self {
+        Self {
+            mac,
+            state,
+            router: false,
+            updated: now,
+            solicitations: 0,
+            last_solicitation: None
+        }
+    }
+    fn set_state(&mut self, state: NeighborState, now: Duration) {
+        self.state = state;
+        self.updated = now;
+        self.solicitations = 0;
+        self.last_solicitation = None;
+    }
+}
+
+/// Cache of IPv6 to MAC mappings, which learns them from Neighbor Solicitations and Advertisements following RFC 4861 state machine
+/// It doesn't send anything itself: methods return IPv6 packets with solicitations and advertisements, which should be sent in Ethernet frames by caller
+/// Time is given explicitly as `now`, i.e. time since any fixed moment, so cache can be driven by capture timestamps
+#[derive(Debug, Clone)]
+pub struct NeighborCache {
+    entries: HashMap<Ipv6Addr, NeighborEntry>,
+    /// Own MAC address, which is advertised in solicitations and advertisements
+    pub mac: [u8; 6],
+    /// Own IPv6 address, which solicitations are sent from and solicitations for are answered
+    pub ip: Ipv6Addr,
+    /// Time after which reachable entry becomes stale, `REACHABLE_TIME`
+    pub reachable_time: Duration,
+    /// Interval between solicitations, `RETRANS_TIMER`
+    pub retransmit_interval: Duration,
+    /// Time in `Delay` state before the first probe, `DELAY_FIRST_PROBE_TIME`
+    pub delay_first_probe: Duration,
+    /// Number of multicast solicitations after which incomplete entry is removed, `MAX_MULTICAST_SOLICIT`
+    pub max_multicast_solicit: u32,
+    /// Number of unicast probes after which entry is removed, `MAX_UNICAST_SOLICIT`
+    pub max_unicast_solicit: u32,
+    /// Time after which unused stale entry is removed
+    pub stale_time: Duration
+}
+impl NeighborCache {
+    /// Constructs an empty `NeighborCache` for host with `mac` and `ip`
+    /// Timers and counters have default values of RFC 4861, stale entries are removed after 60 seconds as Linux does by default
+    pub fn new(mac: [u8; 6], ip: Ipv6Addr) -> Self {
+        Self {
+            entries: HashMap::new(),
+            mac,
+            ip,
+            reachable_time: Duration::from_secs(30),
+            retransmit_interval: Duration::from_secs(1),
+            delay_first_probe: Duration::from_secs(5),
+            max_multicast_solicit: 3,
+            max_unicast_solicit: 3,
+            stale_time: Duration::from_secs(60)
+        }
+    }
+    pub fn get(&self, ip: Ipv6Addr) -> Option<&NeighborEntry> {
+        self.entries.get(&ip)
+    }
+    pub fn entries(&self) -> impl Iterator<Item = (Ipv6Addr, &NeighborEntry)> + '_ {
+        self.entries.iter().map(|(ip, entry)| (*ip, entry))
+    }
+    pub fn remove(&mut self, ip: Ipv6Addr) -> Option<NeighborEntry> {
+        self.entries.remove(&ip)
+    }
+    /// Returns MAC address of `ip` if it is known, without changing entry state
+    pub fn lookup(&self, ip: Ipv6Addr) -> Option<[u8; 6]> {
+        self.entries.get(&ip)?.mac
+    }
+    /// Constructs Neighbor Solicitation for `ip` from own addresses
+    /// It is sent to solicited-node multicast address of `ip` for address resolution, or to `ip` itself when `unicast` is set for reachability probe
+    pub fn solicitation(&self, ip: Ipv6Addr, unicast: bool) -> Ipv6Packet {
+        let destination = if unicast {ip} else {ipv6::solicited_node_address(ip)};
+        NdpMessage::NeighborSolicitation {target: ip, source_link_address: Some(self.mac)}.into_ipv6(self.ip, destination)
+    }
+    /// Updates cache with `message` received from `source`
+    /// Solicitation with source link-layer address creates or updates stale entry of `source`, advertisement updates existing entry of its target as RFC 4861 describes
+    /// Returns solicited advertisement, which should be sent if `message` solicits own address
+    pub fn process(&mut self, message: &NdpMessage, source: Ipv6Addr, now: Duration) -> Option<Ipv6Packet> {
+        match message {
+            NdpMessage::NeighborSolicitation {target, source_link_address} => {
+                if let Some(mac) = source_link_address && !source.is_unspecified() && source != self.ip {
+                    match self.entries.get_mut(&source) {
+                        Some(entry) if entry.mac == Some(*mac) => {}
+                        Some(entry) => {
+                            entry.mac = Some(*mac);
+                            entry.set_state(NeighborState::Stale, now);
+                        }
+                        None => {
+                            self.entries.insert(source, NeighborEntry::new(Some(*mac), NeighborState::Stale, now));
+                        }
+                    }
+                }
+                if *target != self.ip {return None;}
+                let advertisement = NdpMessage::NeighborAdvertisement {
+                    router: false,
+                    solicited: !source.is_unspecified(),
+                    override_flag: true,
+                    target: self.ip,
+                    target_link_address: Some(self.mac)
+                };
+                Some(advertisement.into_ipv6(self.ip, if source.is_unspecified() {ALL_NODES} else {source}))
+            }
+            NdpMessage::NeighborAdvertisement {router, solicited, override_flag, target, target_link_address} => {
+                let entry = self.entries.get_mut(target)?;
+                if entry.state == NeighborState::Incomplete {
+                    let mac = (*target_link_address)?;
+                    entry.mac = Some(mac);
+                    entry.router = *router;
+                    entry.set_state(if *solicited {NeighborState::Reachable} else {NeighborState::Stale}, now);
+                    return None;
+                }
+                let changed = target_link_address.is_some_and(|mac| entry.mac != Some(mac));
+                if !override_flag && changed {
+                    if entry.state == NeighborState::Reachable {entry.set_state(NeighborState::Stale, now);}
+                    return None;
+                }
+                if let Some(mac) = target_link_address {entry.mac = Some(*mac);}
+                entry.router = *router;
+                if *solicited {
+                    entry.set_state(NeighborState::Reachable, now);
+                }
+                else if changed {
+                    entry.set_state(NeighborState::Stale, now);
+                }
+                None
+            }
+        }
+    }
+    /// Marks `ip` reachable on upper-layer confirmation, i.e. TCP acknowledgement of new data
+    /// Returns `false` if `ip` is unknown or its entry is incomplete
+    pub fn confirm_reachability(&mut self, ip: Ipv6Addr, now: Duration) -> bool {
+        match self.entries.get_mut(&ip) {
+            Some(entry) if entry.state != NeighborState::Incomplete => {
+                entry.set_state(NeighborState::Reachable, now);
+                true
+            }
+            _ => false
+        }
+    }
+    /// Resolves `ip` to MAC address for sending packet to it, first use of stale entry moves it to `Delay` state
+    /// Returns `Err` with multicast solicitation, which should be sent, if `ip` is unknown, or `Err(None)` if resolution is already in progress
+    pub fn resolve(&mut self, ip: Ipv6Addr, now: Duration) -> Result<[u8; 6], Option<Ipv6Packet>> {
+        match self.entries.get_mut(&ip) {
+            Some(entry) if let Some(mac) = entry.mac => {
+                if entry.state == NeighborState::Stale {entry.set_state(NeighborState::Delay, now);}
+                Ok(mac)
+            }
+            Some(_) => Err(None),
+            None => {
+                let mut entry = NeighborEntry::new(None, NeighborState::Incomplete, now);
+                entry.solicitations = 1;
+                entry.last_solicitation = Some(now);
+                self.entries.insert(ip, entry);
+                Err(Some(self.solicitation(ip, false)))
+            }
+        }
+    }
+    /// Runs timers of entries and returns solicitations, which should be sent: multicast retransmissions for incomplete entries and unicast probes
+    /// Reachable entries become stale after `reachable_time`, delayed ones start probing after `delay_first_probe`
+    /// Incomplete and probed entries are removed after `max_multicast_solicit` or `max_unicast_solicit` unanswered solicitations, unused stale ones after `stale_time`
+    pub fn poll(&mut self, now: Duration) -> Vec<Ipv6Packet> {
+        let mut targets = Vec::new();
+        let mut expired = Vec::new();
+        for (ip, entry) in self.entries.iter_mut() {
+            let elapsed = now.saturating_sub(entry.updated);
+            match entry.state {
+                NeighborState::Reachable if elapsed >= self.reachable_time => entry.set_state(NeighborState::Stale, now),
+                NeighborState::Stale if elapsed >= self.stale_time => expired.push(*ip),
+                NeighborState::Delay if elapsed >= self.delay_first_probe => entry.set_state(NeighborState::Probe, now),
+                _ => {}
+            }
+            let (limit, unicast) = match entry.state {
+                NeighborState::Incomplete => (self.max_multicast_solicit, false),
+                NeighborState::Probe => (self.max_unicast_solicit, true),
+                _ => continue
+            };
+            if entry.last_solicitation.is_some_and(|last| now.saturating_sub(last) < self.retransmit_interval) {continue;}
+            if entry.solicitations >= limit {
+                expired.push(*ip);
+                continue;
+            }
+            entry.solicitations += 1;
+            entry.last_solicitation = Some(now);
+            targets.push((*ip, unicast));
+        }
+        for ip in expired {
+            self.entries.remove(&ip);
+        }
+        targets.into_iter().map(|(ip, unicast)| self.solicitation(ip, unicast)).collect()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,144 @@
+use core::time::Duration;
+use std::collections::HashMap;
+use super::icmp::{IcmpPacket, IcmpType};
+
+/// Kind of ICMP Echo message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EchoKind {
+    /// ICMP type 8
+    Request,
+    /// ICMP type 0
+    Reply
+}
+
+/// Parsed ICMP Echo Request or Echo Reply message
+#[derive(Debug, Clone)]
+pub struct Echo {
+    pub kind: EchoKind,
+    /// Identifier, usually process id of ping utility
+    pub id: u16,
+    /// Sequence number, increased by 1 on each request
+    pub seq: u16,
+    pub payload: Vec<u8>
+}
+impl Echo {
+    /// Parses Echo message from `packet`
+    /// Returns `None` if `packet` is not an Echo Request or Echo Reply
+    pub fn parse(packet: &IcmpPacket) -> Option<Self> {
+        let kind = match packet.message_type() {
+            IcmpType::EchoRequest => EchoKind::Request,
+            IcmpType::EchoReply => EchoKind::Reply,
+            _ => return None
+        };
+        Some(Self {
+            kind,
+            id: u16::from_be_bytes([packet.rest_of_header[0], packet.rest_of_header[1]]),
+            seq: u16::from_be_bytes([packet.rest_of_header[2], packet.rest_of_header[3]]),
+            payload: packet.payload.clone()
+        })
+    }
+    /// Returns timestamp from payload made with `timestamped_payload()`
+    pub fn timestamp(&self) -> Option<Duration> {
+        payload_timestamp(&self.payload)
+    }
+}
+
+/// Constructs an ICMP Echo Request with calculated checksum
+pub fn echo_request(id: u16, seq: u16, payload: Vec<u8>) -> IcmpPacket {
+    echo(8, id, seq, payload)
+}
+
+/// Constructs an ICMP Echo Reply answering `request` with the same identifier, sequence number and payload
+/// Returns `None` if `request` is not an Echo Request
+pub fn echo_reply(request: &IcmpPacket) -> Option<IcmpPacket> {
+    let request = Echo::parse(request)?;
+    if request.kind != EchoKind::Request {return None;}
+    Some(echo(0, request.id, request.seq, request.payload))
+}
+
+fn echo(icmp_type: u8, id: u16, seq: u16, payload: Vec<u8>) -> IcmpPacket {
+    let mut packet = IcmpPacket::new();
+    packet.icmp_type = icmp_type;
+    packet.rest_of_header[0..2].copy_from_slice(&id.to_be_bytes());
+    packet.rest_of_header[2..4].copy_from_slice(&seq.to_be_bytes());
+    packet.payload = payload;
+    packet.recalculate_checksum();
+    packet
+}
+
+/// Constructs Echo payload of `size` bytes in the same way as `ping` utility does
+/// First `16 bytes` are `timestamp` as seconds and microseconds, both `u64` in little-endian order, all following bytes are equal to their offset in payload
+/// If `size` is less than 16, payload consists only of pattern bytes
+pub fn timestamped_payload(timestamp: Duration, size: usize) -> Vec<u8> {
+    let mut payload: Vec<u8> = (0..size).map(|i| i as u8).collect();
+    if size >= 16 {
+        payload[0..8].copy_from_slice(&timestamp.as_secs().to_le_bytes());
+        payload[8..16].copy_from_slice(&(timestamp.subsec_micros() as u64).to_le_bytes());
+    }
+    payload
+}
+
+/// Extracts timestamp from payload made with `timestamped_payload()`
+/// Returns `None` if payload is shorter than `16 bytes` or microseconds are out of range
+pub fn payload_timestamp(payload: &[u8]) -> Option<Duration> {
+    if payload.len() < 16 {return None;}
+    let secs = u64::from_le_bytes(payload[0..8].try_into().ok()?);
+    let micros = u64::from_le_bytes(payload[8..16].try_into().ok()?);
+    if micros >= 1_000_000 {return None;}
+    Some(Duration::from_secs(secs) + Duration::from_micros(micros))
+}
+
+/// Echo Request paired with its Echo Reply
+#[derive(Debug, Clone)]
+pub struct EchoPair {
+    pub request: Echo,
+    pub reply: Echo
+}
+impl EchoPair {
+    /// Returns `true` if reply payload is the same as request payload
+    pub fn payload_matches(&self) -> bool {
+        self.request.payload == self.reply.payload
+    }
+    /// Returns round-trip time using timestamp from request payload and `received_at` time of reply
+    /// Returns `None` if request payload has no timestamp or `received_at` is earlier than it
+    pub fn rtt(&self, received_at: Duration) -> Option<Duration> {
+        received_at.checked_sub(self.request.timestamp()?)
+    }
+}
+
+/// Pairs Echo Requests with Echo Replies by identifier and sequence number
+#[derive(Debug, Clone, Default)]
+pub struct EchoTracker {
+    pending: HashMap<(u16, u16), Echo>
+}
+impl EchoTracker {
+    /// Constructs an empty `EchoTracker`
+    pub fn new() -> Self {
+        Self {
+            pending: HashMap::new()
+        }
+    }
+    /// Remembers sent Echo Request
+    /// Returns `false` if `request` is not an Echo Request
+    pub fn register_request(&mut self, request: &IcmpPacket) -> bool {
+        match Echo::parse(request) {
+            Some(echo) if echo.kind == EchoKind::Request => {
+                self.pending.insert((echo.id, echo.seq), echo);
+                true
+            }
+            _ => false
+        }
+    }
+    /// Pairs received Echo Reply with remembered Echo Request and forgets that request
+    /// Returns `None` if `reply` is not an Echo Reply or there is no matching request
+    pub fn match_reply(&mut self, reply: &IcmpPacket) -> Option<EchoPair> {
+        let reply = Echo::parse(reply)?;
+        if reply.kind != EchoKind::Reply {return None;}
+        let request = self.pending.remove(&(reply.id, reply.seq))?;
+        Some(EchoPair {request, reply})
+    }
+    /// Returns `(id, seq)` of requests which still wait for reply
+    pub fn pending(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.pending.keys().copied()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,192 @@
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::time::Duration;
+use std::collections::HashMap;
+use super::{IpPacket, IpProtocol};
+use super::icmp::{DestinationUnreachableCode, IcmpPacket, IcmpType};
+use super::icmpv6::{Icmpv6Packet, Icmpv6Type};
+use super::ipv4::Ipv4Packet;
+use super::ipv6::Ipv6Packet;
+use crate::util::Deserializable;
+
+/// ICMP Fragmentation Needed(type 3, code 4) or ICMPv6 Packet Too Big(type 2) message, which router sends when packet exceeds next-hop MTU
+#[derive(Debug, Clone)]
+pub struct PathMtuMessage {
+    /// Next-hop MTU reported by router
+    /// Note that routers predating RFC 1191 report 0 in ICMP Fragmentation Needed
+    pub mtu: u32,
+    /// Quoted beginning of the original packet, which is usually truncated
+    pub quoted: Vec<u8>
+}
+impl PathMtuMessage {
+    /// Parses ICMP Fragmentation Needed message
+    /// Returns `None` if `packet` is another ICMP message
+    pub fn from_icmp(packet: &IcmpPacket) -> Option<Self> {
+        if packet.message_type() != IcmpType::DestinationUnreachable(DestinationUnreachableCode::FragmentationNeeded) {return None;}
+        Some(Self {
+            mtu: u16::from_be_bytes([packet.rest_of_header[2], packet.rest_of_header[3]]) as u32,
+            quoted: packet.payload.clone()
+        })
+    }
+    /// Parses ICMPv6 Packet Too Big message
+    /// Returns `None` if `packet` is another ICMPv6 message
+    pub fn from_icmpv6(packet: &Icmpv6Packet) -> Option<Self> {
+        if packet.message_type() != Icmpv6Type::PacketTooBig {return None;}
+        Some(Self {
+            mtu: u32::from_be_bytes(packet.rest_of_header),
+            quoted: packet.payload.clone()
+        })
+    }
+    /// Parses message carried by IPv4 or IPv6 `packet`
+    /// Returns `None` if `packet` doesn't carry Fragmentation Needed or Packet Too Big message
+    pub fn from_ip(packet: &IpPacket) -> Option<Self> {
+        match (packet, packet.protocol()) {
+            (IpPacket::V4(_), IpProtocol::Icmp) => Self::from_icmp(&IcmpPacket::deserialize(packet.payload()).ok()?),
+            (IpPacket::V6(_), IpProtocol::Icmpv6) => Self::from_icmpv6(&Icmpv6Packet::deserialize(packet.payload()).ok()?),
+            _ => None
+        }
+    }
+    /// Returns source and destination addresses of quoted original packet
+    /// Returns `None` if quoted packet is too short to contain them
+    pub fn quoted_addresses(&self) -> Option<(IpAddr, IpAddr)> {
+        match self.quoted.first()? >> 4 {
+            4 if self.quoted.len() >= 20 => Some((
+                IpAddr::V4(Ipv4Addr::from_octets(*self.quoted[12..16].as_array()?)),
+                IpAddr::V4(Ipv4Addr::from_octets(*self.quoted[16..20].as_array()?))
+            )),
+            6 if self.quoted.len() >= 40 => Some((
+                IpAddr::V6(Ipv6Addr::from_octets(*self.quoted[8..24].as_array()?)),
+                IpAddr::V6(Ipv6Addr::from_octets(*self.quoted[24..40].as_array()?))
+            )),
+            _ => None
+        }
+    }
+    /// Parses quoted original packet, note that its payload is usually truncated
+    pub fn quoted_packet(&self) -> Option<IpPacket> {
+        match self.quoted.first()? >> 4 {
+            6 => Ipv6Packet::deserialize_truncated(&self.quoted).ok().map(IpPacket::V6),
+            _ => IpPacket::deserialize(&self.quoted).ok()
+        }
+    }
+}
+
+/// Constructs PMTUD probe of exactly `size` bytes: ICMP Echo Request in IPv4 packet with Don't Fragment flag, or ICMPv6 Echo Request in IPv6 packet
+/// All checksums are calculated
+/// Returns `None` if `source` and `destination` are not same version, `size` is less than IP and ICMP headers or exceeds IPv4 total length limit
+pub fn probe(source: IpAddr, destination: IpAddr, size: usize, id: u16, seq: u16) -> Option<IpPacket> {
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            if !(28..=0xFFFF).contains(&size) {return None;}
+            let mut icmp = IcmpPacket::new();
+            icmp.icmp_type = 8;
+            icmp.rest_of_header[0..2].copy_from_slice(&id.to_be_bytes());
+            icmp.rest_of_header[2..4].copy_from_slice(&seq.to_be_bytes());
+            icmp.payload = vec![0; size - 28];
+            icmp.recalculate_checksum();
+            let mut packet = Ipv4Packet {
+                dont_fragment: true,
+                source,
+                destination,
+                ..Default::default()
+            };
+            packet.set_payload_packet(&icmp);
+            packet.recalculate_checksum();
+            Some(IpPacket::V4(packet))
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            if size < 48 {return None;}
+            let mut icmp = Icmpv6Packet::new();
+            icmp.icmp_type = 128;
+            icmp.rest_of_header[0..2].copy_from_slice(&id.to_be_bytes());
+            icmp.rest_of_header[2..4].copy_from_slice(&seq.to_be_bytes());
+            icmp.payload = vec![0; size - 48];
+            icmp.recalculate_checksum(source, destination);
+            let mut packet = Ipv6Packet {
+                source,
+                destination,
+                ..Default::default()
+            };
+            packet.set_payload_packet(&icmp);
+            Some(IpPacket::V6(packet))
+        }
+        _ => None
+    }
+}
+
+/// Minimum link MTU of IPv6, path MTU estimate is never reduced below it
+pub const IPV6_MIN_MTU: u32 = 1280;
+/// Minimum MTU of IPv4, Fragmentation Needed messages reporting less are ignored
+pub const IPV4_MIN_MTU: u32 = 68;
+
+/// Per-destination path MTU estimates learned from ICMP Fragmentation Needed and ICMPv6 Packet Too Big messages, as described in RFC 1191 and RFC 8201
+/// Messages can only lower estimate, it is raised back to `link_mtu` when entry expires after `expiry` since the last message
+/// Time is given explicitly as `now`, i.e. time since any fixed moment, so cache can be driven by capture timestamps
+#[derive(Debug, Clone)]
+pub struct PathMtuCache {
+    /// Estimate and time it was updated by destination
+    entries: HashMap<IpAddr, (u32, Duration)>,
+    /// MTU of own link, which is estimate for destinations without entry
+    pub link_mtu: u32,
+    /// Time after which estimate is forgotten, so larger path MTU can be discovered
+    pub expiry: Duration
+}
+impl PathMtuCache {
+    /// Constructs an empty `PathMtuCache` for link with `link_mtu`, entries expire after 10 minutes as RFC 1191 and RFC 8201 recommend
+    pub fn new(link_mtu: u32) -> Self {
+        Self {
+            entries: HashMap::new(),
+            link_mtu,
+            expiry: Duration::from_secs(600)
+        }
+    }
+    /// Lowers estimate for destination of packet quoted in `message`
+    /// Returns destination and its new estimate, or `None` if quote is too short or MTU is below minimum of IPv4 and can't be used
+    pub fn update(&mut self, message: &PathMtuMessage, now: Duration) -> Option<(IpAddr, u32)> {
+        let (_, destination) = message.quoted_addresses()?;
+        let mtu = match destination {
+            IpAddr::V4(_) if message.mtu < IPV4_MIN_MTU => return None,
+            IpAddr::V4(_) => message.mtu,
+            IpAddr::V6(_) => message.mtu.max(IPV6_MIN_MTU)
+        };
+        let mtu = mtu.min(self.mtu(destination, now));
+        self.entries.insert(destination, (mtu, now));
+        Some((destination, mtu))
+    }
+    /// Lowers estimate if IPv4 or IPv6 `packet` carries Fragmentation Needed or Packet Too Big message
+    pub fn update_from_ip(&mut self, packet: &IpPacket, now: Duration) -> Option<(IpAddr, u32)> {
+        self.update(&PathMtuMessage::from_ip(packet)?, now)
+    }
+    /// Returns estimate for `destination`, i.e. size of the largest IP packet which should be sent to it without fragmentation
+    pub fn mtu(&self, destination: IpAddr, now: Duration) -> u32 {
+        match self.entries.get(&destination) {
+            Some((mtu, updated)) if now.saturating_sub(*updated) < self.expiry => (*mtu).min(self.link_mtu),
+            _ => self.link_mtu
+        }
+    }
+    /// Returns the largest IP packet `size` not exceeding estimate for `destination`
+    pub fn clamp(&self, destination: IpAddr, size: usize, now: Duration) -> usize {
+        size.min(self.mtu(destination, now) as usize)
+    }
+    /// Splits IPv6 `packet` with `Ipv6Packet::fragment()`, so each fragment fits into estimate for its destination
+    pub fn fragment_ipv6(&self, packet: &Ipv6Packet, id: u32, now: Duration) -> Option<Vec<Ipv6Packet>> {
+        packet.fragment(self.mtu(IpAddr::V6(packet.destination), now) as usize, id)
+    }
+    /// Returns `true` if serialized `packet` fits into estimate for its destination
+    pub fn fits(&self, packet: &IpPacket, now: Duration) -> bool {
+        packet.wire_len() <= self.mtu(packet.destination(), now) as usize
+    }
+    /// Removes expired entries
+    pub fn expire(&mut self, now: Duration) {
+        let expiry = self.expiry;
+        self.entries.retain(|_, (_, updated)| now.saturating_sub(*updated) < expiry);
+    }
+    /// Returns destinations with estimate lower than `link_mtu`, including expired ones until `expire()` is called
+    pub fn destinations(&self) -> impl Iterator<Item = (IpAddr, u32)> + '_ {
+        self.entries.iter().map(|(destination, (mtu, _))| (*destination, *mtu))
+    }
+}
+impl Default for PathMtuCache {
+    /// Constructs cache for Ethernet link with MTU 1500
+    fn default() -> Self {
+        Self::new(1500)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,583 @@
+use core::net::{Ipv4Addr, Ipv6Addr};
+use super::IpProtocol;
+use super::icmp::IcmpPacket;
+use super::icmpv6::Icmpv6Packet;
+use super::ipv4::Ipv4Packet;
+use super::ipv6::{Ipv6ExtensionHeader, Ipv6Packet, NextHeader};
+use super::pmtud::IPV6_MIN_MTU;
+use crate::util::{ChecksumAccumulator, Deserializable, Serializable, checksum, transport_checksum, update_checksum};
+
+/// Maximum length of translated ICMP error message, including IPv4 header, as limited by RFC 1812
+const ICMP_ERROR_MAX_LEN: usize = 576;
+
+/// IPv6 prefix, which IPv4 addresses are embedded into, as described in RFC 6052
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Nat64Prefix {
+    prefix: Ipv6Addr,
+    length: u8
+}
+impl Nat64Prefix {
+    /// Well-Known Prefix `64:ff9b::/96`
+    pub const WELL_KNOWN: Self = Self {
+        prefix: Ipv6Addr::new(0x64, 0xFF9B, 0, 0, 0, 0, 0, 0),
+        length: 96
+    };
+    /// Constructs prefix of `length` bits, bits of `prefix` after it are ignored
+    /// Returns `None` if `length` is not 32, 40, 48, 56, 64 or 96
+    pub fn new(prefix: Ipv6Addr, length: u8) -> Option<Self> {
+        if ![32, 40, 48, 56, 64, 96].contains(&length) {return None;}
+        let mask = u128::MAX << (128 - length as u32);
+        Some(Self {prefix: Ipv6Addr::from_bits(prefix.to_bits() & mask), length})
+    }
+    pub fn prefix(&self) -> Ipv6Addr {
+        self.prefix
+    }
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+    /// Returns positions of embedded IPv4 address bytes, which skip reserved bits 64 to 71
+    fn positions(&self) -> [usize; 4] {
+        let mut positions = [0usize; 4];
+        let mut position = self.length as usize / 8;
+        for slot in positions.iter_mut() {
+            if position == 8 {position += 1;}
+            *slot = position;
+            position += 1;
+        }
+        positions
+    }
+    /// Returns IPv4-embedded IPv6 address of `address`
+    pub fn embed(&self, address: Ipv4Addr) -> Ipv6Addr {
+        let mut octets = self.prefix.octets();
+        for (position, byte) in self.positions().into_iter().zip(address.octets()) {
+            octets[position] = byte;
+        }
+        Ipv6Addr::from_octets(octets)
+    }
+    /// Returns IPv4 address embedded into `address`
+    /// Returns `None` if `address` doesn't have this prefix
+    pub fn extract(&self, address: Ipv6Addr) -> Option<Ipv4Addr> {
+        let octets = address.octets();
+        if octets[..self.length as usize / 8] != self.prefix.octets()[..self.length as usize / 8] {return None;}
+        Some(Ipv4Addr::from_octets(self.positions().map(|position| octets[position])))
+    }
+}
+impl Default for Nat64Prefix {
+    fn default() -> Self {
+        Self::WELL_KNOWN
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslateError {
+    /// IPv6 address has neither explicit mapping nor translator prefix
+    UnmappableAddress,
+    /// Packet has no counterpart in other version and is silently dropped, i.e. IGMP, ICMP Redirect, fragmented ICMP or unexpired Routing header
+    Untranslatable,
+    /// TTL or hop limit expired when translator decremented it, ICMP Time Exceeded should be sent back
+    TtlExpired,
+    /// Translated IPv4 packet with Don't Fragment flag exceeds `ipv6_mtu`, ICMP Fragmentation Needed with `mtu` should be sent back
+    PacketTooBig {
+        mtu: u16
+    },
+    /// Payload of ICMP message or packet quoted in ICMP error can't be parsed
+    Malformed
+}
+
+/// Stateless IP/ICMP translator between IPv4 and IPv6 packets, as described in RFC 7915
+/// Addresses are mapped with `explicit_mappings` first, as described in RFC 7757, and embedded into `prefix` otherwise
+/// IPv4 options and IPv6 extension headers other than Fragment are dropped, TCP, UDP and ICMP checksums are adjusted for new addresses
+/// You can construct it with `SiitTranslator::new()` and consistently editing
+#[derive(Debug, Clone)]
+pub struct SiitTranslator {
+    pub prefix: Nat64Prefix,
+    /// Pairs of IPv4 and IPv6 addresses, which are translated into each other instead of using `prefix`
+    pub explicit_mappings: Vec<(Ipv4Addr, Ipv6Addr)>,
+    /// MTU of IPv6 side, translated packets exceeding it are fragmented, or rejected if IPv4 packet has Don't Fragment flag
+    pub ipv6_mtu: usize,
+    /// Copy DSCP and ECN between Type of Service and Traffic Class, otherwise they are zeroed
+    pub translate_traffic_class: bool,
+    /// Decrement TTL or hop limit as router does, packets quoted by ICMP errors are never decremented
+    pub decrement_ttl: bool
+}
+impl SiitTranslator {
+    /// Constructs translator with `prefix`, minimum IPv6 MTU `1280 bytes` and TTL decrement
+    pub fn new(prefix: Nat64Prefix) -> Self {
+        Self {
+            prefix,
+            explicit_mappings: Vec::new(),
+            ipv6_mtu: IPV6_MIN_MTU as usize,
+            translate_traffic_class: true,
+            decrement_ttl: true
+        }
+    }
+    pub fn map_ipv4(&self, address: Ipv4Addr) -> Ipv6Addr {
+        match self.explicit_mappings.iter().find(|(ipv4, _)| *ipv4 == address) {
+            Some((_, ipv6)) => *ipv6,
+            None => self.prefix.embed(address)
+        }
+    }
+    pub fn map_ipv6(&self, address: Ipv6Addr) -> Option<Ipv4Addr> {
+        match self.explicit_mappings.iter().find(|(_, ipv6)| *ipv6 == address) {
+            Some((ipv4, _)) => Some(*ipv4),
+            None => self.prefix.extract(address)
+        }
+    }
+    /// Translates IPv4 `packet` into one IPv6 packet, or several IPv6 fragments if it exceeds `ipv6_mtu` and has no Don't Fragment flag
+    /// Fragments of IPv4 packet get Fragment header with its identification
+    pub fn ipv4_to_ipv6(&self, packet: &Ipv4Packet) -> Result<Vec<Ipv6Packet>, TranslateError> {
+        let mut translated = self.translate_ipv4(packet, true, packet.payload.len())?;
+        if translated.wire_len() <= self.ipv6_mtu {return Ok(vec![translated]);}
+        if packet.dont_fragment {
+            return Err(TranslateError::PacketTooBig {mtu: self.ipv6_mtu.saturating_sub(20).min(0xFFFF) as u16});
+        }
+        let chunk_len = self.ipv6_mtu.saturating_sub(48) & !7;
+        if chunk_len == 0 {return Err(TranslateError::Untranslatable);}
+        let protocol = match translated.extension_headers.first() {
+            Some(header) => header.get_next_header_type(),
+            None => translated.next_header
+        };
+        let payload = core::mem::take(&mut translated.payload);
+        let count = payload.len().div_ceil(chunk_len);
+        Ok(payload.chunks(chunk_len).enumerate().map(|(i, chunk)| Ipv6Packet {
+            next_header: NextHeader::Fragment,
+            extension_headers: vec![Ipv6ExtensionHeader::Fragment {
+                next_header: protocol,
+                fragment_offset: ((packet.fragment_offset as usize + i * chunk_len) / 8) as u16,
+                more_fragments: i + 1 < count || packet.more_fragments,
+                id: packet.id as u32
+            }],
+            payload: chunk.to_vec(),
+            ..translated.clone()
+        }).collect())
+    }
+    /// Translates IPv6 `packet` into IPv4 packet
+    /// Packet without Fragment header gets Don't Fragment flag, since IPv6 routers don't fragment either
+    pub fn ipv6_to_ipv4(&self, packet: &Ipv6Packet) -> Result<Ipv4Packet, TranslateError> {
+        self.translate_ipv6(packet, true, packet.payload.len())
+    }
+    /// Translates headers and payload of IPv4 `packet`, whose upper-layer data is `upper_len` bytes long before truncation
+    /// Packets quoted in ICMP errors are translated with `outer` set to `false`
+    fn translate_ipv4(&self, packet: &Ipv4Packet, outer: bool, upper_len: usize) -> Result<Ipv6Packet, TranslateError> {
+        let mut result = Ipv6Packet::new();
+        if self.translate_traffic_class {
+            result.dscp = packet.dscp;
+            result.ecn = packet.ecn;
+        }
+        result.hop_limit = packet.ttl;
+        if outer && self.decrement_ttl {
+            if packet.ttl <= 1 {return Err(TranslateError::TtlExpired);}
+            result.hop_limit -= 1;
+        }
+        result.source = self.map_ipv4(packet.source);
+        result.destination = self.map_ipv4(packet.destination);
+        let fragmented = packet.more_fragments || packet.fragment_offset != 0;
+        let old_sum = address_sum(&packet.source.octets(), &packet.destination.octets());
+        let new_sum = address_sum(&result.source.octets(), &result.destination.octets());
+        let mut payload = packet.payload.clone();
+        let protocol = match packet.protocol {
+            IpProtocol::Icmp if fragmented => return Err(TranslateError::Untranslatable),
+            IpProtocol::Icmp if outer => {
+                let mut message = self.icmp_to_icmpv6(&IcmpPacket::deserialize(&payload).map_err(|_| TranslateError::Malformed)?)?;
+                message.recalculate_checksum(result.source, result.destination);
+                payload = message.serialize();
+                IpProtocol::Icmpv6
+            }
+            IpProtocol::Icmp => {
+                let pseudo_sum = icmpv6_pseudo_sum(&result, upper_len);
+                translate_quoted_echo(&mut payload, 0, pseudo_sum, &[(8, 128), (0, 129)]);
+                IpProtocol::Icmpv6
+            }
+            IpProtocol::Igmp => return Err(TranslateError::Untranslatable),
+            IpProtocol::Udp if outer && packet.fragment_offset == 0 && payload.get(6..8) == Some(&[0, 0]) => {
+                if fragmented || payload.len() < 8 {return Err(TranslateError::Untranslatable);}
+                let udp_checksum = transport_checksum(result.source.into(), result.destination.into(), 17, &[&payload]).unwrap_or(0);
+                payload[6..8].copy_from_slice(&(if udp_checksum == 0 {0xFFFF} else {udp_checksum}).to_be_bytes());
+                IpProtocol::Udp
+            }
+            protocol @ (IpProtocol::Tcp | IpProtocol::Udp) => {
+                if packet.fragment_offset == 0 {adjust_transport_checksum(protocol, &mut payload, old_sum, new_sum);}
+                protocol
+            }
+            protocol => protocol
+        };
+        if fragmented {
+            result.next_header = NextHeader::Fragment;
+            result.extension_headers.push(Ipv6ExtensionHeader::Fragment {
+                next_header: protocol.into(),
+                fragment_offset: packet.fragment_offset / 8,
+                more_fragments: packet.more_fragments,
+                id: packet.id as u32
+            });
+        }
+        else {
+            result.next_header = protocol.into();
+        }
+        result.payload = payload;
+        Ok(result)
+    }
+    /// Translates headers and payload of IPv6 `packet`, whose upper-layer data is `upper_len` bytes long before truncation
+    /// Packets quoted in ICMP errors are translated with `outer` set to `false`
+    fn translate_ipv6(&self, packet: &Ipv6Packet, outer: bool, upper_len: usize) -> Result<Ipv4Packet, TranslateError> {
+        let mut result = Ipv4Packet::new();
+        if self.translate_traffic_class {
+            result.dscp = packet.dscp;
+            result.ecn = packet.ecn;
+        }
+        result.ttl = packet.hop_limit;
+        if outer && self.decrement_ttl {
+            if packet.hop_limit <= 1 {return Err(TranslateError::TtlExpired);}
+            result.ttl -= 1;
+        }
+        result.source = self.map_ipv6(packet.source).ok_or(TranslateError::UnmappableAddress)?;
+        result.destination = self.map_ipv6(packet.destination).ok_or(TranslateError::UnmappableAddress)?;
+        let mut next_header = packet.next_header;
+        let mut fragment = None;
+        for header in &packet.extension_headers {
+            match header {
+                Ipv6ExtensionHeader::Fragment {fragment_offset, more_fragments, id, ..} => fragment = Some((*fragment_offset, *more_fragments, *id)),
+                Ipv6ExtensionHeader::Routing {payload, ..} if payload.get(1).is_some_and(|segments_left| *segments_left != 0) => return Err(TranslateError::Untranslatable),
+                _ => {}
+            }
+            next_header = header.get_next_header_type();
+        }
+        if next_header.is_extension_header() {return Err(TranslateError::Malformed);}
+        match fragment {
+            Some((fragment_offset, more_fragments, id)) => {
+                result.id = id as u16;
+                result.more_fragments = more_fragments;
+                result.fragment_offset = fragment_offset * 8;
+            }
+            None => result.dont_fragment = true
+        }
+        let fragmented = result.more_fragments || result.fragment_offset != 0;
+        let old_sum = address_sum(&packet.source.octets(), &packet.destination.octets());
+        let new_sum = address_sum(&result.source.octets(), &result.destination.octets());
+        let mut payload = packet.payload.clone();
+        result.protocol = match IpProtocol::from(u8::from(next_header)) {
+            IpProtocol::Icmpv6 if fragmented => return Err(TranslateError::Untranslatable),
+            IpProtocol::Icmpv6 if outer => {
+                let mut message = self.icmpv6_to_icmp(&Icmpv6Packet::deserialize(&payload).map_err(|_| TranslateError::Malformed)?)?;
+                message.recalculate_checksum();
+                payload = message.serialize();
+                IpProtocol::Icmp
+            }
+            IpProtocol::Icmpv6 => {
+                let pseudo_sum = icmpv6_pseudo_sum(packet, upper_len);
+                translate_quoted_echo(&mut payload, pseudo_sum, 0, &[(128, 8), (129, 0)]);
+                IpProtocol::Icmp
+            }
+            protocol @ (IpProtocol::Tcp | IpProtocol::Udp) => {
+                if result.fragment_offset == 0 {adjust_transport_checksum(protocol, &mut payload, old_sum, new_sum);}
+                protocol
+            }
+            protocol => protocol
+        };
+        result.payload = payload;
+        result.recalculate_checksum();
+        Ok(result)
+    }
+    /// Translates ICMP message into ICMPv6 one, without calculating checksum
+    fn icmp_to_icmpv6(&self, message: &IcmpPacket) -> Result<Icmpv6Packet, TranslateError> {
+        let mut result = Icmpv6Packet::new();
+        let pointer = message.rest_of_header[0];
+        let (icmp_type, code, rest_of_header) = match (message.icmp_type, message.code) {
+            (8, _) => (128, 0, message.rest_of_header),
+            (0, _) => (129, 0, message.rest_of_header),
+            (3, 0 | 1 | 5 | 6 | 7 | 8 | 11 | 12) => (1, 0, [0; 4]),
+            (3, 2) => (4, 1, 6u32.to_be_bytes()),
+            (3, 3) => (1, 4, [0; 4]),
+            (3, 4) => {
+                let mtu = u16::from_be_bytes([message.rest_of_header[2], message.rest_of_header[3]]) as usize;
+                let mtu = if mtu == 0 {self.ipv6_mtu} else {(mtu + 20).min(self.ipv6_mtu)};
+                (2, 0, (mtu.max(IPV6_MIN_MTU as usize) as u32).to_be_bytes())
+            }
+            (3, 9 | 10 | 13 | 15) => (1, 1, [0; 4]),
+            (11, code) => (3, code, [0; 4]),
+            (12, 0 | 2) => (4, 0, ipv4_pointer_to_ipv6(pointer).ok_or(TranslateError::Untranslatable)?.to_be_bytes()),
+            _ => return Err(TranslateError::Untranslatable)
+        };
+        result.icmp_type = icmp_type;
+        result.code = code;
+        result.rest_of_header = rest_of_header;
+        result.payload = match message.is_error() {
+            true => {
+                let length = message.rest_of_header[1] as usize * 4;
+                let quote = if length != 0 && length <= message.payload.len() {&message.payload[..length]} else {&message.payload[..]};
+                self.translate_quote_ipv4(quote)?
+            }
+            false => message.payload.clone()
+        };
+        Ok(result)
+    }
+    /// Translates ICMPv6 message into ICMP one, without calculating checksum
+    fn icmpv6_to_icmp(&self, message: &Icmpv6Packet) -> Result<IcmpPacket, TranslateError> {
+        let mut result = IcmpPacket::new();
+        let (icmp_type, code, rest_of_header) = match (message.icmp_type, message.code) {
+            (128, _) => (8, 0, message.rest_of_header),
+            (129, _) => (0, 0, message.rest_of_header),
+            (1, 0 | 2 | 3) => (3, 1, [0; 4]),
+            (1, 1) => (3, 10, [0; 4]),
+            (1, 4) => (3, 3, [0; 4]),
+            (2, _) => {
+                let mtu = u32::from_be_bytes(message.rest_of_header).saturating_sub(20).min(0xFFFF) as u16;
+                let mtu = mtu.to_be_bytes();
+                (3, 4, [0, 0, mtu[0], mtu[1]])
+            }
+            (3, code) => (11, code, [0; 4]),
+            (4, 0) => (12, 0, [ipv6_pointer_to_ipv4(u32::from_be_bytes(message.rest_of_header)).ok_or(TranslateError::Untranslatable)?, 0, 0, 0]),
+            (4, 1) => (3, 2, [0; 4]),
+            _ => return Err(TranslateError::Untranslatable)
+        };
+        result.icmp_type = icmp_type;
+        result.code = code;
+        result.rest_of_header = rest_of_header;
+        result.payload = match message.is_error() {
+            true => {
+                let length = message.rest_of_header[0] as usize * 8;
+                let quote = if matches!(message.icmp_type, 1 | 3) && length != 0 && length <= message.payload.len() {&message.payload[..length]} else {&message.payload[..]};
+                self.translate_quote_ipv6(quote)?
+            }
+            false => message.payload.clone()
+        };
+        Ok(result)
+    }
+    /// Translates IPv4 packet quoted in ICMP error, keeping its original length field and cutting it to fit into minimum IPv6 MTU
+    fn translate_quote_ipv4(&self, quote: &[u8]) -> Result<Vec<u8>, TranslateError> {
+        let packet = Ipv4Packet::deserialize(quote).map_err(|_| TranslateError::Malformed)?;
+        let upper_len = (u16::from_be_bytes([quote[2], quote[3]]) as usize).saturating_sub(packet.header_len());
+        let translated = self.translate_ipv4(&packet, false, upper_len)?;
+        let headers_len = translated.wire_len() - 40 - translated.payload.len();
+        let mut bytes = translated.serialize();
+        bytes[4..6].copy_from_slice(&((headers_len + upper_len).min(0xFFFF) as u16).to_be_bytes());
+        bytes.truncate(IPV6_MIN_MTU as usize - 48);
+        Ok(bytes)
+    }
+    /// Translates IPv6 packet quoted in ICMPv6 error, keeping its original length field and cutting it to fit into `576 bytes` ICMP error
+    fn translate_quote_ipv6(&self, quote: &[u8]) -> Result<Vec<u8>, TranslateError> {
+        let packet = Ipv6Packet::deserialize_truncated(quote).map_err(|_| TranslateError::Malformed)?;
+        let headers_len = packet.wire_len() - 40 - packet.payload.len();
+        let upper_len = (u16::from_be_bytes([quote[4], quote[5]]) as usize).saturating_sub(headers_len);
+        let translated = self.translate_ipv6(&packet, false, upper_len)?;
+        let mut bytes = translated.serialize();
+        bytes[2..4].copy_from_slice(&((20 + upper_len).min(0xFFFF) as u16).to_be_bytes());
+        bytes[10..12].fill(0);
+        let header_checksum = checksum(&bytes[..20]);
+        bytes[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+        bytes.truncate(ICMP_ERROR_MAX_LEN - 28);
+        Ok(bytes)
+    }
+}
+impl Default for SiitTranslator {
+    fn default() -> Self {
+        Self::new(Nat64Prefix::WELL_KNOWN)
+    }
+}
+
+/// Returns one's complement sum of `source` and `destination` addresses, which is the only part of pseudo-header changed by translation
+fn address_sum(source: &[u8], destination: &[u8]) -> u16 {
+    !ChecksumAccumulator::new().update(source).update(destination).finish()
+}
+
+/// Returns one's complement sum of ICMPv6 pseudo-header of `packet` for message of `length` bytes
+fn icmpv6_pseudo_sum(packet: &Ipv6Packet, length: usize) -> u16 {
+    !ChecksumAccumulator::new()
+        .update(&packet.source.octets())
+        .update(&packet.destination.octets())
+        .update(&(length as u32).to_be_bytes())
+        .update(&[0, 0, 0, 58])
+        .finish()
+}
+
+/// Incrementally adjusts checksum of TCP or UDP `segment` after its pseudo-header address sum changed from `old_sum` to `new_sum`
+/// Segment can be truncated, only its checksum field has to be present, and zero UDP checksum is kept as absent
+fn adjust_transport_checksum(protocol: IpProtocol, segment: &mut [u8], old_sum: u16, new_sum: u16) {
+    let offset = if protocol == IpProtocol::Tcp {16} else {6};
+    let Some(field) = segment.get_mut(offset..offset + 2) else {return;};
+    let old = u16::from_be_bytes([field[0], field[1]]);
+    if protocol == IpProtocol::Udp && old == 0 {return;}
+    let mut new = update_checksum(old, old_sum, new_sum);
+    if protocol == IpProtocol::Udp && new == 0 {new = 0xFFFF;}
+    field.copy_from_slice(&new.to_be_bytes());
+}
+
+/// Translates type of Echo message quoted in ICMP error by `types` pairs and incrementally adjusts its checksum for pseudo-header sum change from `old_sum` to `new_sum`
+/// Other messages are left intact, since errors are never sent about errors
+fn translate_quoted_echo(message: &mut [u8], old_sum: u16, new_sum: u16, types: &[(u8, u8)]) {
+    if message.len() < 4 {return;}
+    let Some((_, icmp_type)) = types.iter().find(|(old, _)| *old == message[0]) else {return;};
+    let old = u16::from_be_bytes([message[0], message[1]]);
+    message[0] = *icmp_type;
+    let new = u16::from_be_bytes([message[0], message[1]]);
+    let message_checksum = update_checksum(update_checksum(u16::from_be_bytes([message[2], message[3]]), old, new), old_sum, new_sum);
+    message[2..4].copy_from_slice(&message_checksum.to_be_bytes());
+}
+
+/// Maps pointer of ICMP Parameter Problem to IPv6 header field of ICMPv6 one, as described in RFC 7915 section 4.2
+fn ipv4_pointer_to_ipv6(pointer: u8) -> Option<u32> {
+    match pointer {
+        0 => Some(0),
+        1 => Some(1),
+        2 | 3 => Some(4),
+        8 => Some(7),
+        9 => Some(6),
+        12..=15 => Some(8),
+        16..=19 => Some(24),
+        _ => None
+    }
+}
+
+/// Maps pointer of ICMPv6 Parameter Problem to IPv4 header field of ICMP one, as described in RFC 7915 section 5.2
+fn ipv6_pointer_to_ipv4(pointer: u32) -> Option<u8> {
+    match pointer {
+        0 => Some(0),
+        1 => Some(1),
+        4 | 5 => Some(2),
+        6 => Some(9),
+        7 => Some(8),
+        8..=23 => Some(12),
+        24..=39 => Some(16),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::net::IpAddr;
+    use super::*;
+    use crate::l4::udp::UdpDatagram;
+
+    const HOST: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+    const SERVER: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 2);
+
+    fn udp_packet(source: Ipv4Addr, destination: Ipv4Addr, length: usize) -> Ipv4Packet {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = 5000;
+        datagram.destination = 53;
+        datagram.payload = (0..length).map(|i| i as u8).collect();
+        datagram.recalculate_checksum(source.into(), destination.into()).unwrap();
+        let mut packet = Ipv4Packet {
+            id: 0x1234,
+            source,
+            destination,
+            ..Default::default()
+        };
+        packet.set_payload_packet(&datagram);
+        packet.recalculate_checksum();
+        packet
+    }
+
+    fn icmp_packet(source: Ipv4Addr, destination: Ipv4Addr, icmp_type: u8, code: u8, payload: Vec<u8>) -> Ipv4Packet {
+        let mut message = IcmpPacket::new();
+        message.icmp_type = icmp_type;
+        message.code = code;
+        message.payload = payload;
+        message.recalculate_checksum();
+        let mut packet = Ipv4Packet {
+            dont_fragment: true,
+            source,
+            destination,
+            ..Default::default()
+        };
+        packet.set_payload_packet(&message);
+        packet.recalculate_checksum();
+        packet
+    }
+
+    #[test]
+    fn embeds_rfc6052_examples() {
+        let address = Ipv4Addr::new(192, 0, 2, 33);
+        let examples = [
+            ("2001:db8::", 32, "2001:db8:c000:221::"),
+            ("2001:db8:100::", 40, "2001:db8:1c0:2:21::"),
+            ("2001:db8:122::", 48, "2001:db8:122:c000:2:2100::"),
+            ("2001:db8:122:300::", 56, "2001:db8:122:3c0:0:221::"),
+            ("2001:db8:122:344::", 64, "2001:db8:122:344:c0:2:2100:0"),
+            ("2001:db8:122:344::", 96, "2001:db8:122:344::192.0.2.33"),
+            ("64:ff9b::", 96, "64:ff9b::192.0.2.33")
+        ];
+        for (prefix, length, embedded) in examples {
+            let prefix = Nat64Prefix::new(prefix.parse().unwrap(), length).unwrap();
+            let embedded: Ipv6Addr = embedded.parse().unwrap();
+            assert_eq!(prefix.embed(address), embedded, "/{}", length);
+            assert_eq!(prefix.extract(embedded), Some(address), "/{}", length);
+        }
+        assert_eq!(Nat64Prefix::new("2001:db8::".parse().unwrap(), 33), None);
+        assert_eq!(Nat64Prefix::WELL_KNOWN.extract("2001:db8::c000:221".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn translates_echo_both_ways() {
+        let translator = SiitTranslator {decrement_ttl: false, ..SiitTranslator::default()};
+        let original = icmp_packet(HOST, SERVER, 8, 0, vec![0, 1, 0, 7, 0xAA, 0xBB]);
+        let translated = translator.ipv4_to_ipv6(&original).unwrap();
+        assert_eq!(translated.len(), 1);
+        let bytes = translated[0].clone().serialize();
+        assert_eq!(Ipv6Packet::validate(&bytes), Vec::new());
+        assert_eq!(translated[0].next_header, NextHeader::Protocol(IpProtocol::Icmpv6));
+        assert_eq!(translated[0].payload[0], 128);
+        assert_eq!(translated[0].source, Nat64Prefix::WELL_KNOWN.embed(HOST));
+
+        let back = translator.ipv6_to_ipv4(&translated[0]).unwrap();
+        assert_eq!(Ipv4Packet::validate(&back.clone().serialize()), Vec::new());
+        assert_eq!(back.source, HOST);
+        assert_eq!(back.destination, SERVER);
+        assert_eq!(back.ttl, original.ttl);
+        assert_eq!(back.payload, original.payload);
+    }
+
+    #[test]
+    fn translates_error_with_quoted_packet_both_ways() {
+        let translator = SiitTranslator {decrement_ttl: false, ..SiitTranslator::default()};
+        let mut quoted = udp_packet(HOST, SERVER, 12);
+        quoted.id = 0;
+        quoted.dont_fragment = true;
+        quoted.recalculate_checksum();
+        let quoted = quoted.serialize();
+        let original = icmp_packet(SERVER, HOST, 3, 3, quoted.clone());
+
+        let translated = translator.ipv4_to_ipv6(&original).unwrap().remove(0);
+        assert_eq!(Ipv6Packet::validate(&translated.clone().serialize()), Vec::new());
+        let message = Icmpv6Packet::deserialize(&translated.payload).ok().unwrap();
+        assert_eq!((message.icmp_type, message.code), (1, 4));
+        let inner = Ipv6Packet::deserialize(&message.payload).ok().unwrap();
+        assert_eq!(inner.source, Nat64Prefix::WELL_KNOWN.embed(HOST));
+        assert_eq!(Ipv6Packet::validate(&message.payload), Vec::new());
+
+        let back = translator.ipv6_to_ipv4(&translated).unwrap();
+        assert_eq!(Ipv4Packet::validate(&back.clone().serialize()), Vec::new());
+        let message = IcmpPacket::deserialize(&back.payload).ok().unwrap();
+        assert_eq!((message.icmp_type, message.code), (3, 3));
+        assert_eq!(Ipv4Packet::validate(&message.payload), Vec::new());
+        assert_eq!(message.payload, quoted);
+    }
+
+    #[test]
+    fn rejects_oversized_packet_with_dont_fragment() {
+        let translator = SiitTranslator::default();
+        let mut packet = udp_packet(HOST, SERVER, 1400);
+        packet.dont_fragment = true;
+        assert_eq!(translator.ipv4_to_ipv6(&packet).unwrap_err(), TranslateError::PacketTooBig {mtu: 1260});
+    }
+
+    #[test]
+    fn fragments_oversized_packet_without_dont_fragment() {
+        let translator = SiitTranslator::default();
+        let packet = udp_packet(HOST, SERVER, 1400);
+        let fragments = translator.ipv4_to_ipv6(&packet).unwrap();
+        assert_eq!(fragments.len(), 2);
+        let mut payload = Vec::new();
+        for (i, fragment) in fragments.iter().enumerate() {
+            assert!(fragment.wire_len() <= translator.ipv6_mtu);
+            let Some(Ipv6ExtensionHeader::Fragment {next_header, fragment_offset, more_fragments, id}) = fragment.extension_headers.first() else {panic!("no Fragment header")};
+            assert_eq!(*next_header, NextHeader::Protocol(IpProtocol::Udp));
+            assert_eq!(*fragment_offset as usize * 8, payload.len());
+            assert_eq!(*more_fragments, i + 1 < fragments.len());
+            assert_eq!(*id, 0x1234);
+            payload.extend_from_slice(&fragment.payload);
+        }
+        let (source, destination) = (IpAddr::V6(fragments[0].source), IpAddr::V6(fragments[0].destination));
+        assert_eq!(payload.len(), packet.payload.len());
+        assert_eq!(UdpDatagram::validate_with_addresses(&payload, source, destination), Vec::new());
+    }
+}
@@ -0,0 +1,439 @@
+use core::net::{IpAddr, SocketAddr};
+use core::time::Duration;
+use std::collections::HashMap;
+use super::tcp::{SeqNum, TcpSegment};
+use crate::l3::{IpPacket, IpProtocol};
+use crate::stack::{Layer, PacketStack};
+use crate::util::Deserializable;
+
+/// State of tracked TCP connection, as seen by an observer between endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcpState {
+    /// Client sent SYN
+    SynSent,
+    /// Server answered with SYN+ACK
+    SynReceived,
+    /// Client acknowledged SYN+ACK, or connection was picked up midstream
+    Established,
+    /// One side sent FIN, which is not acknowledged yet
+    FinWait,
+    /// FIN of one side is acknowledged, the other side can still send data
+    CloseWait,
+    /// Both sides sent FIN, the last one is not acknowledged yet
+    LastAck,
+    /// FINs of both sides are acknowledged
+    TimeWait,
+    /// Connection was reset
+    Closed
+}
+impl TcpState {
+    /// Checks if connection is over, i.e. it's in `TimeWait` or `Closed` state, so SYN with the same addresses in either direction opens a new connection
+    pub fn is_terminated(&self) -> bool {
+        matches!(self, Self::TimeWait | Self::Closed)
+    }
+}
+
+/// Direction of segment within connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// From endpoint which sent the first SYN
+    ClientToServer,
+    ServerToClient
+}
+impl Direction {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Addresses of connection endpoints, client is the one which sent the first SYN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnKey {
+    pub client: SocketAddr,
+    pub server: SocketAddr
+}
+
+/// Change of connection state reported by `ConnTracker`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnEvent {
+    /// Client sent SYN of a new connection
+    Opened(ConnKey),
+    /// Three-way handshake completed, or connection was picked up midstream
+    Established(ConnKey),
+    /// The first FIN of connection was sent in given direction
+    Closing(ConnKey, Direction),
+    /// FINs of both sides were acknowledged
+    Finished(ConnKey),
+    /// RST was sent in given direction
+    Reset(ConnKey, Direction)
+}
+
+/// Tracked TCP connection
+#[derive(Debug, Clone)]
+pub struct Connection {
+    pub key: ConnKey,
+    pub state: TcpState,
+    /// Initial sequence number of client
+    pub client_isn: SeqNum,
+    /// Initial sequence number of server, known after SYN+ACK
+    pub server_isn: Option<SeqNum>,
+    /// Time of the first segment
+    pub started: Duration,
+    /// Time of the last segment
+    pub last_seen: Duration,
+    /// Number of segments in each direction, indexed by `Direction`
+    pub packets: [u64; 2],
+    /// Number of payload bytes in each direction, indexed by `Direction`, including retransmissions
+    pub bytes: [u64; 2],
+    /// Sequence number following FIN of each direction
+    fin_end: [Option<SeqNum>; 2],
+    fin_acked: [bool; 2]
+}
+impl Connection {
+    fn new(key: ConnKey, state: TcpState, client_isn: SeqNum, now: Duration) -> Self {
+        Self {
+            key,
+            state,
+            client_isn,
+            server_isn: None,
+            started: now,
+            last_seen: now,
+            packets: [0; 2],
+            bytes: [0; 2],
+            fin_end: [None; 2],
+            fin_acked: [false; 2]
+        }
+    }
+    /// Returns direction which sent FIN first, if connection is closing
+    pub fn closed_by(&self) -> Option<Direction> {
+        match self.fin_end {
+            [Some(_), None] => Some(Direction::ClientToServer),
+            [None, Some(_)] => Some(Direction::ServerToClient),
+            _ => None
+        }
+    }
+    /// Applies segment sent in `direction` to state machine and returns resulting event
+    fn update(&mut self, direction: Direction, segment: &TcpSegment, payload_len: usize) -> Option<ConnEvent> {
+        let flags = &segment.flags;
+        let from_client = direction == Direction::ClientToServer;
+        let sequence_number = SeqNum(segment.sequence_number);
+        let acknowledgement_number = SeqNum(segment.acknowledgement_number);
+        if flags.rst {
+            if self.state == TcpState::Closed {return None;}
+            self.state = TcpState::Closed;
+            return Some(ConnEvent::Reset(self.key, direction));
+        }
+        match self.state {
+            TcpState::SynSent => {
+                if from_client || !flags.syn || !flags.ack || acknowledgement_number != self.client_isn + 1 {return None;}
+                self.server_isn = Some(sequence_number);
+                self.state = TcpState::SynReceived;
+                None
+            }
+            TcpState::SynReceived => {
+                let server_isn = self.server_isn?;
+                if !from_client || flags.syn || !flags.ack || acknowledgement_number != server_isn + 1 {return None;}
+                self.state = TcpState::Established;
+                self.update_closing(direction, segment, payload_len);
+                Some(ConnEvent::Established(self.key))
+            }
+            TcpState::Established | TcpState::FinWait | TcpState::CloseWait | TcpState::LastAck => self.update_closing(direction, segment, payload_len),
+            TcpState::TimeWait | TcpState::Closed => None
+        }
+    }
+    /// Tracks FINs and their acknowledgements
+    fn update_closing(&mut self, direction: Direction, segment: &TcpSegment, payload_len: usize) -> Option<ConnEvent> {
+        let index = direction.index();
+        let other = 1 - index;
+        let was_closing = self.fin_end.iter().any(Option::is_some);
+        if segment.flags.fin && self.fin_end[index].is_none() {
+            self.fin_end[index] = Some(SeqNum(segment.sequence_number) + payload_len as u32 + segment.flags.syn as u32 + 1);
+        }
+        if segment.flags.ack && let Some(fin_end) = self.fin_end[other] && SeqNum(segment.acknowledgement_number) >= fin_end {
+            self.fin_acked[other] = true;
+        }
+        let previous = self.state;
+        self.state = match (self.fin_end, self.fin_acked) {
+            (_, [true, true]) => TcpState::TimeWait,
+            ([Some(_), Some(_)], _) => TcpState::LastAck,
+            (_, [true, false] | [false, true]) => TcpState::CloseWait,
+            ([Some(_), None] | [None, Some(_)], _) => TcpState::FinWait,
+            _ => TcpState::Established
+        };
+        match self.state {
+            TcpState::TimeWait if previous != TcpState::TimeWait => Some(ConnEvent::Finished(self.key)),
+            _ if !was_closing && self.fin_end[index].is_some() => Some(ConnEvent::Closing(self.key, direction)),
+            _ => None
+        }
+    }
+}
+
+/// Tracks state of TCP connections from observed segments of both directions
+/// Time is given explicitly as `now`, i.e. time since any fixed moment, so tracker can be driven by capture timestamps
+#[derive(Debug, Clone)]
+pub struct ConnTracker {
+    connections: HashMap<ConnKey, Connection>,
+    /// Track connections without observed handshake, guessing that sender of the first segment is client
+    pub pick_up_midstream: bool,
+    /// Connections in `SynSent` or `SynReceived` state are expired after this time without segments, `2 minutes` by default
+    pub handshake_timeout: Duration,
+    /// Established connections are expired after this time without segments, `5 days` by default
+    pub established_timeout: Duration,
+    /// Closing connections are expired after this time without segments, `2 minutes` by default
+    pub closing_timeout: Duration,
+    /// Connections in `TimeWait` or `Closed` state are expired after this time, `2 minutes` by default
+    pub time_wait_timeout: Duration
+}
+impl ConnTracker {
+    /// Constructs an empty `ConnTracker` with default timeouts, which only tracks connections starting with SYN
+    pub fn new() -> Self {
+        Self {
+            connections: HashMap::new(),
+            pick_up_midstream: false,
+            handshake_timeout: Duration::from_secs(120),
+            established_timeout: Duration::from_secs(5 * 24 * 3600),
+            closing_timeout: Duration::from_secs(120),
+            time_wait_timeout: Duration::from_secs(120)
+        }
+    }
+    /// Returns tracked connection and direction of segment from `source` to `destination` within it
+    fn lookup(&self, source: SocketAddr, destination: SocketAddr) -> Option<(ConnKey, Direction)> {
+        let key = ConnKey {client: source, server: destination};
+        if self.connections.contains_key(&key) {return Some((key, Direction::ClientToServer));}
+        let key = ConnKey {client: destination, server: source};
+        if self.connections.contains_key(&key) {return Some((key, Direction::ServerToClient));}
+        None
+    }
+    /// Applies `segment` sent from `source` to `destination` with `payload_len` bytes of payload
+    fn track(&mut self, source: SocketAddr, destination: SocketAddr, segment: &TcpSegment, payload_len: usize, now: Duration) -> Option<ConnEvent> {
+        let opens = segment.flags.syn && !segment.flags.ack && !segment.flags.rst;
+        let mut existing = self.lookup(source, destination);
+        if let Some((key, _)) = existing && opens && self.connections[&key].state.is_terminated() {
+            self.connections.remove(&key);
+            existing = None;
+        }
+        let Some((key, direction)) = existing else {
+            let key = ConnKey {client: source, server: destination};
+            let (state, event) = match opens {
+                true => (TcpState::SynSent, ConnEvent::Opened(key)),
+                false if self.pick_up_midstream && !segment.flags.rst && !segment.flags.syn => (TcpState::Established, ConnEvent::Established(key)),
+                false => return None
+            };
+            let mut connection = Connection::new(key, state, SeqNum(segment.sequence_number), now);
+            connection.packets[0] = 1;
+            connection.bytes[0] = payload_len as u64;
+            if state == TcpState::Established {connection.update_closing(Direction::ClientToServer, segment, payload_len);}
+            self.connections.insert(key, connection);
+            return Some(event);
+        };
+        let connection = self.connections.get_mut(&key)?;
+        connection.last_seen = now;
+        connection.packets[direction.index()] += 1;
+        connection.bytes[direction.index()] += payload_len as u64;
+        connection.update(direction, segment, payload_len)
+    }
+    /// Applies `segment` sent from `source` to `destination` at `now`
+    /// Returns event if connection state changed, or `None` if it didn't or segment doesn't belong to tracked connection
+    pub fn process(&mut self, source: IpAddr, destination: IpAddr, segment: &TcpSegment, now: Duration) -> Option<ConnEvent> {
+        self.track(SocketAddr::new(source, segment.source), SocketAddr::new(destination, segment.destination), segment, segment.payload.len(), now)
+    }
+    /// Applies TCP segment carried by IPv4 or IPv6 `packet`
+    /// Returns `None` if `packet` doesn't carry TCP segment
+    pub fn process_ip(&mut self, packet: &IpPacket, now: Duration) -> Option<ConnEvent> {
+        if packet.protocol() != IpProtocol::Tcp {return None;}
+        let segment = TcpSegment::deserialize(packet.payload()).ok()?;
+        self.process(packet.source(), packet.destination(), &segment, now)
+    }
+    /// Applies TCP layer of `stack`, which must follow IP layer
+    /// Raw payload of stack is counted as payload of segment if TCP is the last layer
+    /// Returns `None` if stack has no TCP layer
+    pub fn process_stack(&mut self, stack: &PacketStack, now: Duration) -> Option<ConnEvent> {
+        let position = stack.layers.iter().position(|layer| matches!(layer, Layer::Tcp(_)))?;
+        let Layer::Tcp(segment) = &stack.layers[position] else {return None;};
+        let (source, destination) = stack.layers[..position].last()?.addresses()?;
+        let payload_len = match position + 1 == stack.layers.len() {
+            true => segment.payload.len() + stack.payload.len(),
+            false => segment.payload.len()
+        };
+        self.track(SocketAddr::new(source, segment.source), SocketAddr::new(destination, segment.destination), segment, payload_len, now)
+    }
+    /// Returns connection between `a` and `b` in either direction
+    pub fn connection(&self, a: SocketAddr, b: SocketAddr) -> Option<&Connection> {
+        let (key, _) = self.lookup(a, b)?;
+        self.connections.get(&key)
+    }
+    /// Returns state of connection between `a` and `b` in either direction
+    pub fn state(&self, a: SocketAddr, b: SocketAddr) -> Option<TcpState> {
+        self.connection(a, b).map(|connection| connection.state)
+    }
+    /// Returns all tracked connections in arbitrary order
+    pub fn connections(&self) -> impl Iterator<Item = &Connection> + '_ {
+        self.connections.values()
+    }
+    /// Returns number of tracked connections
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+    /// Forgets connections, which timed out at `now` according to their state, and returns them
+    pub fn expire(&mut self, now: Duration) -> Vec<Connection> {
+        let mut expired = Vec::new();
+        let timeouts = (self.handshake_timeout, self.established_timeout, self.closing_timeout, self.time_wait_timeout);
+        self.connections.retain(|_, connection| {
+            let timeout = match connection.state {
+                TcpState::SynSent | TcpState::SynReceived => timeouts.0,
+                TcpState::Established => timeouts.1,
+                TcpState::FinWait | TcpState::CloseWait | TcpState::LastAck => timeouts.2,
+                TcpState::TimeWait | TcpState::Closed => timeouts.3
+            };
+            let alive = now.saturating_sub(connection.last_seen) < timeout;
+            if !alive {expired.push(connection.clone());}
+            alive
+        });
+        expired
+    }
+}
+impl Default for ConnTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::net::Ipv4Addr;
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const SERVER: IpAddr = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2));
+    const KEY: ConnKey = ConnKey {
+        client: SocketAddr::new(CLIENT, 40000),
+        server: SocketAddr::new(SERVER, 80)
+    };
+
+    /// Sends segment with `flags` like "SA" for SYN+ACK from client if `from_client`, otherwise from server
+    fn send(tracker: &mut ConnTracker, from_client: bool, flags: &str, sequence_number: u32, acknowledgement_number: u32, payload_len: usize) -> Option<ConnEvent> {
+        let mut segment = TcpSegment::new();
+        segment.flags.syn = flags.contains('S');
+        segment.flags.ack = flags.contains('A');
+        segment.flags.fin = flags.contains('F');
+        segment.flags.rst = flags.contains('R');
+        segment.sequence_number = sequence_number;
+        segment.acknowledgement_number = acknowledgement_number;
+        segment.payload = vec![0; payload_len];
+        let (source, destination) = if from_client {(KEY.client, KEY.server)} else {(KEY.server, KEY.client)};
+        segment.source = source.port();
+        segment.destination = destination.port();
+        tracker.process(source.ip(), destination.ip(), &segment, Duration::from_secs(1))
+    }
+
+    fn handshake(tracker: &mut ConnTracker) {
+        assert_eq!(send(tracker, true, "S", 1000, 0, 0), Some(ConnEvent::Opened(KEY)));
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::SynSent));
+        assert_eq!(send(tracker, false, "SA", 5000, 1001, 0), None);
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::SynReceived));
+        assert_eq!(send(tracker, true, "A", 1001, 5001, 0), Some(ConnEvent::Established(KEY)));
+        assert_eq!(tracker.state(KEY.server, KEY.client), Some(TcpState::Established));
+    }
+
+    #[test]
+    fn tracks_handshake() {
+        let mut tracker = ConnTracker::new();
+        handshake(&mut tracker);
+        assert_eq!(send(&mut tracker, true, "A", 1001, 5001, 10), None);
+        let connection = tracker.connection(KEY.client, KEY.server).unwrap();
+        assert_eq!(connection.key, KEY);
+        assert_eq!(connection.client_isn, SeqNum(1000));
+        assert_eq!(connection.server_isn, Some(SeqNum(5000)));
+        assert_eq!(connection.packets, [3, 1]);
+        assert_eq!(connection.bytes, [10, 0]);
+    }
+
+    #[test]
+    fn ignores_syn_ack_with_wrong_acknowledgement() {
+        let mut tracker = ConnTracker::new();
+        send(&mut tracker, true, "S", 1000, 0, 0);
+        assert_eq!(send(&mut tracker, false, "SA", 5000, 2000, 0), None);
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::SynSent));
+    }
+
+    #[test]
+    fn tracks_simultaneous_close() {
+        let mut tracker = ConnTracker::new();
+        handshake(&mut tracker);
+        assert_eq!(send(&mut tracker, true, "FA", 1001, 5001, 0), Some(ConnEvent::Closing(KEY, Direction::ClientToServer)));
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::FinWait));
+        assert_eq!(send(&mut tracker, false, "FA", 5001, 1001, 0), None);
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::LastAck));
+        assert_eq!(tracker.connection(KEY.client, KEY.server).unwrap().closed_by(), None);
+        assert_eq!(send(&mut tracker, true, "A", 1002, 5002, 0), None);
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::LastAck));
+        assert_eq!(send(&mut tracker, false, "A", 5002, 1002, 0), Some(ConnEvent::Finished(KEY)));
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::TimeWait));
+    }
+
+    #[test]
+    fn tracks_half_close() {
+        let mut tracker = ConnTracker::new();
+        handshake(&mut tracker);
+        assert_eq!(send(&mut tracker, false, "FA", 5001, 1001, 0), Some(ConnEvent::Closing(KEY, Direction::ServerToClient)));
+        assert_eq!(tracker.connection(KEY.client, KEY.server).unwrap().closed_by(), Some(Direction::ServerToClient));
+        assert_eq!(send(&mut tracker, true, "A", 1001, 5002, 0), None);
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::CloseWait));
+        assert_eq!(send(&mut tracker, true, "FA", 1001, 5002, 0), None);
+        assert_eq!(send(&mut tracker, false, "A", 5002, 1002, 0), Some(ConnEvent::Finished(KEY)));
+    }
+
+    #[test]
+    fn tracks_reset() {
+        let mut tracker = ConnTracker::new();
+        handshake(&mut tracker);
+        assert_eq!(send(&mut tracker, false, "R", 5001, 0, 0), Some(ConnEvent::Reset(KEY, Direction::ServerToClient)));
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::Closed));
+        assert_eq!(send(&mut tracker, true, "R", 1001, 0, 0), None);
+        assert_eq!(send(&mut tracker, true, "A", 1001, 5001, 0), None);
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::Closed));
+    }
+
+    #[test]
+    fn picks_up_midstream_only_when_enabled() {
+        let mut tracker = ConnTracker::new();
+        assert_eq!(send(&mut tracker, false, "A", 5001, 1001, 100), None);
+        assert!(tracker.is_empty());
+        tracker.pick_up_midstream = true;
+        assert_eq!(send(&mut tracker, true, "R", 1001, 0, 0), None);
+        assert_eq!(send(&mut tracker, true, "SA", 1001, 0, 0), None);
+        assert!(tracker.is_empty());
+        let reversed = ConnKey {client: KEY.server, server: KEY.client};
+        assert_eq!(send(&mut tracker, false, "A", 5001, 1001, 100), Some(ConnEvent::Established(reversed)));
+        assert_eq!(tracker.state(KEY.client, KEY.server), Some(TcpState::Established));
+        assert_eq!(tracker.connection(KEY.client, KEY.server).unwrap().bytes, [100, 0]);
+    }
+
+    #[test]
+    fn reopens_terminated_connection() {
+        let mut tracker = ConnTracker::new();
+        handshake(&mut tracker);
+        send(&mut tracker, true, "R", 1001, 0, 0);
+        assert_eq!(send(&mut tracker, true, "S", 3000, 0, 0), Some(ConnEvent::Opened(KEY)));
+        assert_eq!(tracker.connection(KEY.client, KEY.server).unwrap().client_isn, SeqNum(3000));
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn reopens_terminated_connection_in_reverse_direction() {
+        let mut tracker = ConnTracker::new();
+        handshake(&mut tracker);
+        send(&mut tracker, true, "R", 1001, 0, 0);
+        let reversed = ConnKey {client: KEY.server, server: KEY.client};
+        assert_eq!(send(&mut tracker, false, "S", 7000, 0, 0), Some(ConnEvent::Opened(reversed)));
+        assert_eq!(tracker.len(), 1);
+        let connection = tracker.connection(KEY.client, KEY.server).unwrap();
+        assert_eq!(connection.key, reversed);
+        assert_eq!(connection.state, TcpState::SynSent);
+        assert_eq!(send(&mut tracker, true, "SA", 9000, 7001, 0), None);
+        assert_eq!(send(&mut tracker, false, "A", 7001, 9001, 0), Some(ConnEvent::Established(reversed)));
+    }
+}
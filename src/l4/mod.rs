@@ -1,2 +1,250 @@
 pub mod tcp;
-pub mod udp;
\ No newline at end of file
+pub mod udp;
+pub mod conntrack;
+#[cfg(feature = "ospf")]
+pub mod ospf;
+#[cfg(feature = "mptcp")]
+pub mod mptcp;
+
+use core::fmt;
+
+/// Service names of well-known and registered ports from IANA Service Name and Transport Protocol Port Number Registry, sorted by port
+/// Ports with different services for TCP and UDP are named after the most common one
+const SERVICES: [(u16, &str); 62] = [
+    (20, "ftp-data"),
+    (21, "ftp"),
+    (22, "ssh"),
+    (23, "telnet"),
+    (25, "smtp"),
+    (49, "tacacs"),
+    (53, "domain"),
+    (67, "bootps"),
+    (68, "bootpc"),
+    (69, "tftp"),
+    (80, "http"),
+    (88, "kerberos"),
+    (110, "pop3"),
+    (119, "nntp"),
+    (123, "ntp"),
+    (135, "epmap"),
+    (137, "netbios-ns"),
+    (138, "netbios-dgm"),
+    (139, "netbios-ssn"),
+    (143, "imap"),
+    (161, "snmp"),
+    (162, "snmptrap"),
+    (179, "bgp"),
+    (319, "ptp-event"),
+    (320, "ptp-general"),
+    (389, "ldap"),
+    (443, "https"),
+    (445, "microsoft-ds"),
+    (500, "isakmp"),
+    (502, "mbap"),
+    (514, "syslog"),
+    (520, "router"),
+    (546, "dhcpv6-client"),
+    (547, "dhcpv6-server"),
+    (587, "submission"),
+    (636, "ldaps"),
+    (853, "domain-s"),
+    (993, "imaps"),
+    (995, "pop3s"),
+    (1701, "l2tp"),
+    (1812, "radius"),
+    (1813, "radius-acct"),
+    (1883, "mqtt"),
+    (2049, "nfs"),
+    (2123, "gtp-control"),
+    (2152, "gtp-user"),
+    (3306, "mysql"),
+    (3389, "ms-wbt-server"),
+    (3478, "stun"),
+    (4500, "ipsec-nat-t"),
+    (4739, "ipfix"),
+    (4789, "vxlan"),
+    (5060, "sip"),
+    (5353, "mdns"),
+    (5355, "llmnr"),
+    (5432, "postgresql"),
+    (5683, "coap"),
+    (6081, "geneve"),
+    (8080, "http-alt"),
+    (8883, "secure-mqtt"),
+    (9996, "netflow"),
+    (20000, "dnp"),
+];
+
+/// TCP or UDP port number with IANA service name lookup
+/// Converts to and from `u16`, so it can be assigned to `source` and `destination` fields of `TcpSegment` and `UdpDatagram`, i.e. `segment.destination = Port::HTTPS.into()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Port(pub u16);
+impl Port {
+    /// 20 - File Transfer Protocol data
+    pub const FTP_DATA: Self = Self(20);
+    /// 21 - File Transfer Protocol control
+    pub const FTP: Self = Self(21);
+    /// 22 - Secure Shell
+    pub const SSH: Self = Self(22);
+    /// 23 - Telnet
+    pub const TELNET: Self = Self(23);
+    /// 25 - Simple Mail Transfer Protocol
+    pub const SMTP: Self = Self(25);
+    /// 49 - TACACS+
+    pub const TACACS: Self = Self(49);
+    /// 53 - Domain Name System
+    pub const DNS: Self = Self(53);
+    /// 67 - DHCP and BOOTP server
+    pub const BOOTPS: Self = Self(67);
+    /// 68 - DHCP and BOOTP client
+    pub const BOOTPC: Self = Self(68);
+    /// 69 - Trivial File Transfer Protocol
+    pub const TFTP: Self = Self(69);
+    /// 80 - Hypertext Transfer Protocol
+    pub const HTTP: Self = Self(80);
+    /// 88 - Kerberos
+    pub const KERBEROS: Self = Self(88);
+    /// 110 - Post Office Protocol 3
+    pub const POP3: Self = Self(110);
+    /// 119 - Network News Transfer Protocol
+    pub const NNTP: Self = Self(119);
+    /// 123 - Network Time Protocol
+    pub const NTP: Self = Self(123);
+    /// 135 - DCE/RPC endpoint mapper
+    pub const EPMAP: Self = Self(135);
+    /// 137 - NetBIOS Name Service
+    pub const NETBIOS_NS: Self = Self(137);
+    /// 138 - NetBIOS Datagram Service
+    pub const NETBIOS_DGM: Self = Self(138);
+    /// 139 - NetBIOS Session Service
+    pub const NETBIOS_SSN: Self = Self(139);
+    /// 143 - Internet Message Access Protocol
+    pub const IMAP: Self = Self(143);
+    /// 161 - Simple Network Management Protocol
+    pub const SNMP: Self = Self(161);
+    /// 162 - SNMP traps
+    pub const SNMP_TRAP: Self = Self(162);
+    /// 179 - Border Gateway Protocol
+    pub const BGP: Self = Self(179);
+    /// 319 - Precision Time Protocol event messages
+    pub const PTP_EVENT: Self = Self(319);
+    /// 320 - Precision Time Protocol general messages
+    pub const PTP_GENERAL: Self = Self(320);
+    /// 389 - Lightweight Directory Access Protocol
+    pub const LDAP: Self = Self(389);
+    /// 443 - HTTP over TLS, also QUIC
+    pub const HTTPS: Self = Self(443);
+    /// 445 - Server Message Block
+    pub const SMB: Self = Self(445);
+    /// 500 - IKE
+    pub const ISAKMP: Self = Self(500);
+    /// 502 - Modbus/TCP
+    pub const MODBUS: Self = Self(502);
+    /// 514 - Syslog
+    pub const SYSLOG: Self = Self(514);
+    /// 520 - Routing Information Protocol
+    pub const RIP: Self = Self(520);
+    /// 546 - DHCPv6 client
+    pub const DHCPV6_CLIENT: Self = Self(546);
+    /// 547 - DHCPv6 server
+    pub const DHCPV6_SERVER: Self = Self(547);
+    /// 587 - Mail submission
+    pub const SUBMISSION: Self = Self(587);
+    /// 636 - LDAP over TLS
+    pub const LDAPS: Self = Self(636);
+    /// 853 - DNS over TLS
+    pub const DNS_OVER_TLS: Self = Self(853);
+    /// 993 - IMAP over TLS
+    pub const IMAPS: Self = Self(993);
+    /// 995 - POP3 over TLS
+    pub const POP3S: Self = Self(995);
+    /// 1701 - Layer 2 Tunneling Protocol
+    pub const L2TP: Self = Self(1701);
+    /// 1812 - RADIUS authentication
+    pub const RADIUS: Self = Self(1812);
+    /// 1813 - RADIUS accounting
+    pub const RADIUS_ACCOUNTING: Self = Self(1813);
+    /// 1883 - MQ Telemetry Transport
+    pub const MQTT: Self = Self(1883);
+    /// 2049 - Network File System
+    pub const NFS: Self = Self(2049);
+    /// 2123 - GTP control plane
+    pub const GTP_C: Self = Self(2123);
+    /// 2152 - GTP user plane
+    pub const GTP_U: Self = Self(2152);
+    /// 3306 - MySQL
+    pub const MYSQL: Self = Self(3306);
+    /// 3389 - Remote Desktop Protocol
+    pub const RDP: Self = Self(3389);
+    /// 3478 - Session Traversal Utilities for NAT
+    pub const STUN: Self = Self(3478);
+    /// 4500 - IPsec NAT traversal
+    pub const IPSEC_NAT_T: Self = Self(4500);
+    /// 4739 - IP Flow Information Export
+    pub const IPFIX: Self = Self(4739);
+    /// 4789 - Virtual eXtensible LAN
+    pub const VXLAN: Self = Self(4789);
+    /// 5060 - Session Initiation Protocol
+    pub const SIP: Self = Self(5060);
+    /// 5353 - Multicast DNS
+    pub const MDNS: Self = Self(5353);
+    /// 5355 - Link-Local Multicast Name Resolution
+    pub const LLMNR: Self = Self(5355);
+    /// 5432 - PostgreSQL
+    pub const POSTGRESQL: Self = Self(5432);
+    /// 5683 - Constrained Application Protocol
+    pub const COAP: Self = Self(5683);
+    /// 6081 - Generic Network Virtualization Encapsulation
+    pub const GENEVE: Self = Self(6081);
+    /// 8080 - Alternative HTTP
+    pub const HTTP_ALT: Self = Self(8080);
+    /// 8883 - MQTT over TLS
+    pub const MQTT_TLS: Self = Self(8883);
+    /// 9996 - Cisco NetFlow, registered as palace-5 by IANA
+    pub const NETFLOW: Self = Self(9996);
+    /// 20000 - Distributed Network Protocol 3
+    pub const DNP3: Self = Self(20000);
+
+    /// Returns IANA service name of this port, i.e. `https` for 443
+    /// Returns `None` if port isn't in the built-in table
+    pub fn service_name(&self) -> Option<&'static str> {
+        SERVICES.binary_search_by_key(&self.0, |(port, _)| *port).ok().map(|index| SERVICES[index].1)
+    }
+    /// Returns port of IANA service `name`, compared case-insensitively
+    /// Returns `None` if service isn't in the built-in table
+    pub fn from_service_name(name: &str) -> Option<Self> {
+        SERVICES.iter().find(|(_, service)| service.eq_ignore_ascii_case(name)).map(|(port, _)| Self(*port))
+    }
+    /// Checks if port is in well-known(system) range `0..=1023`, which only privileged processes can bind on most systems
+    pub fn is_well_known(&self) -> bool {
+        self.0 < 1024
+    }
+    /// Checks if port is in dynamic(ephemeral) range `49152..=65535`, which IANA never assigns
+    pub fn is_dynamic(&self) -> bool {
+        self.0 >= 49152
+    }
+}
+impl From<u16> for Port {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+impl From<Port> for u16 {
+    fn from(value: Port) -> Self {
+        value.0
+    }
+}
+impl PartialEq<u16> for Port {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+impl fmt::Display for Port {
+    /// Formats port number, or number with service name in parentheses, i.e. `443(https)`, with `{:#}`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.service_name() {
+            Some(name) if f.alternate() => write!(f, "{}({})", self.0, name),
+            _ => write!(f, "{}", self.0)
+        }
+    }
+}
\ No newline at end of file
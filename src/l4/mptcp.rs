@@ -0,0 +1,290 @@
+use core::net::IpAddr;
+use crate::l4::tcp::{TcpOption, TcpSegment};
+use crate::util::{ByteReader, ByteWriter, DeserializeError, OptionSpaceExceeded};
+
+/// Data sequence mapping of DSS option, which maps subflow bytes onto data sequence space of connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DssMapping {
+    /// Data Sequence Number of the first mapped byte
+    pub data_sequence: u64,
+    /// DSN is `8 bytes` long, otherwise only its low `4 bytes` are sent
+    pub data_sequence_long: bool,
+    /// Subflow Sequence Number of the first mapped byte, relative to subflow initial sequence number
+    pub subflow_sequence: u32,
+    /// Count of mapped bytes, 0 for infinite mapping after fallback
+    pub data_length: u16,
+    /// DSS checksum, present if it was negotiated in MP_CAPABLE
+    pub checksum: Option<u16>
+}
+
+/// Data Sequence Signal option, which carries data-level acknowledgement and data sequence mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DssOption {
+    /// DATA_FIN flag, the last byte of mapping closes data stream
+    pub data_fin: bool,
+    pub data_ack: Option<u64>,
+    /// Data ACK is `8 bytes` long, otherwise only its low `4 bytes` are sent
+    pub data_ack_long: bool,
+    pub mapping: Option<DssMapping>
+}
+
+/// Multipath TCP option(kind 30), variant is its subtype, as described in RFC 8684
+/// Option layouts are told apart by length, like receivers do, so variants of MP_CAPABLE and MP_JOIN keep only fields present on wire
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MptcpOption {
+    /// Subtype 0, negotiates MPTCP and exchanges keys, keys and data-level length are present depending on handshake step
+    Capable {
+        /// MPTCP version, 1 for RFC 8684
+        version: u8,
+        /// A(checksum required), B(extensibility), C(no more subflows to source address), H(HMAC-SHA256) and reserved flags
+        flags: u8,
+        sender_key: Option<u64>,
+        receiver_key: Option<u64>,
+        /// Data-level length of data carried by third ACK
+        data_length: Option<u16>,
+        checksum: Option<u16>
+    },
+    /// Subtype 1 in SYN, which starts new subflow
+    JoinSyn {
+        backup: bool,
+        address_id: u8,
+        receiver_token: u32,
+        sender_random: u32
+    },
+    /// Subtype 1 in SYN-ACK
+    JoinSynAck {
+        backup: bool,
+        address_id: u8,
+        /// Truncated HMAC of sender
+        sender_hmac: u64,
+        sender_random: u32
+    },
+    /// Subtype 1 in the third ACK
+    JoinAck {
+        sender_hmac: [u8; 20]
+    },
+    /// Subtype 2
+    Dss(DssOption),
+    /// Subtype 3, announces additional address, echoed by receiver with `echo` set and without HMAC
+    AddAddress {
+        echo: bool,
+        address_id: u8,
+        address: IpAddr,
+        port: Option<u16>,
+        /// Truncated HMAC, present in announcements but not in echoes
+        hmac: Option<u64>
+    },
+    /// Subtype 4, withdraws addresses by their IDs
+    RemoveAddress {
+        address_ids: Vec<u8>
+    },
+    /// Subtype 5, changes backup priority of subflow, or of subflows of address with `address_id`
+    Priority {
+        backup: bool,
+        address_id: Option<u8>
+    },
+    /// Subtype 6, reports checksum failure at data sequence number
+    Fail {
+        data_sequence: u64
+    },
+    /// Subtype 7, closes connection abruptly
+    FastClose {
+        receiver_key: u64
+    },
+    /// Subtype 8, carries reason of subflow reset
+    Reset {
+        /// U, V, W and T flags, actually only 4 bits
+        flags: u8,
+        reason: u8
+    },
+    /// Other subtypes, i.e. 15 for MP_EXPERIMENTAL, with bytes after subtype nibble
+    Other {
+        subtype: u8,
+        /// Low 4 bits of the first byte, whose high 4 bits are subtype
+        low_bits: u8,
+        data: Vec<u8>
+    }
+}
+impl MptcpOption {
+    pub fn get_subtype(&self) -> u8 {
+        match self {
+            Self::Capable {..} => 0,
+            Self::JoinSyn {..} | Self::JoinSynAck {..} | Self::JoinAck {..} => 1,
+            Self::Dss(_) => 2,
+            Self::AddAddress {..} => 3,
+            Self::RemoveAddress {..} => 4,
+            Self::Priority {..} => 5,
+            Self::Fail {..} => 6,
+            Self::FastClose {..} => 7,
+            Self::Reset {..} => 8,
+            Self::Other {subtype, ..} => *subtype
+        }
+    }
+    /// Parses MPTCP option from `option`
+    /// Returns `None` if this is not MPTCP option(kind 30) or its length doesn't match any layout of its subtype
+    pub fn from_option(option: &TcpOption) -> Option<Self> {
+        if option.kind != 30 {return None;}
+        Self::parse(&option.data).ok()
+    }
+    /// Parses all MPTCP options of `segment`, skipping malformed ones
+    pub fn from_segment(segment: &TcpSegment) -> Vec<Self> {
+        segment.options.iter().filter_map(Self::from_option).collect()
+    }
+    /// Parses option `data`, which starts with subtype nibble
+    fn parse(data: &[u8]) -> Result<Self, DeserializeError> {
+        let mut reader = ByteReader::new(data);
+        let first = reader.read_u8()?;
+        let (subtype, low_bits) = (first >> 4, first & 15);
+        let option = match (subtype, data.len() + 2) {
+            (0, 4 | 12 | 20 | 22 | 24) => {
+                let flags = reader.read_u8()?;
+                let sender_key = if reader.is_empty() {None} else {Some(reader.read_u64()?)};
+                let receiver_key = if reader.is_empty() {None} else {Some(reader.read_u64()?)};
+                let data_length = if reader.is_empty() {None} else {Some(reader.read_u16()?)};
+                let checksum = if reader.is_empty() {None} else {Some(reader.read_u16()?)};
+                Self::Capable {version: low_bits, flags, sender_key, receiver_key, data_length, checksum}
+            }
+            (1, 12) => Self::JoinSyn {
+                backup: low_bits & 1 != 0,
+                address_id: reader.read_u8()?,
+                receiver_token: reader.read_u32()?,
+                sender_random: reader.read_u32()?
+            },
+            (1, 16) => Self::JoinSynAck {
+                backup: low_bits & 1 != 0,
+                address_id: reader.read_u8()?,
+                sender_hmac: reader.read_u64()?,
+                sender_random: reader.read_u32()?
+            },
+            (1, 24) => {
+                reader.skip(1)?;
+                Self::JoinAck {sender_hmac: reader.read_array()?}
+            }
+            (2, _) => {
+                let flags = reader.read_u8()?;
+                let data_ack = match flags & 1 != 0 {
+                    true if flags & 2 != 0 => Some(reader.read_u64()?),
+                    true => Some(reader.read_u32()? as u64),
+                    false => None
+                };
+                let mapping = match flags & 4 != 0 {
+                    true => {
+                        let data_sequence_long = flags & 8 != 0;
+                        let data_sequence = if data_sequence_long {reader.read_u64()?} else {reader.read_u32()? as u64};
+                        let subflow_sequence = reader.read_u32()?;
+                        let data_length = reader.read_u16()?;
+                        let checksum = if reader.is_empty() {None} else {Some(reader.read_u16()?)};
+                        Some(DssMapping {data_sequence, data_sequence_long, subflow_sequence, data_length, checksum})
+                    }
+                    false => None
+                };
+                if !reader.is_empty() {return Err(DeserializeError::WrongDataLength);}
+                Self::Dss(DssOption {data_fin: flags & 16 != 0, data_ack, data_ack_long: flags & 3 == 3, mapping})
+            }
+            (3, length @ (8 | 10 | 16 | 18 | 20 | 22 | 28 | 30)) => {
+                let echo = low_bits & 1 != 0;
+                let address_id = reader.read_u8()?;
+                let ipv6 = length >= 20;
+                let address = if ipv6 {IpAddr::V6(reader.read_ipv6()?)} else {IpAddr::V4(reader.read_ipv4()?)};
+                let port = if matches!(length - if ipv6 {12} else {0}, 10 | 18) {Some(reader.read_u16()?)} else {None};
+                let hmac = if reader.is_empty() {None} else {Some(reader.read_u64()?)};
+                Self::AddAddress {echo, address_id, address, port, hmac}
+            }
+            (4, 4..) => Self::RemoveAddress {address_ids: reader.read_rest().to_vec()},
+            (5, 3 | 4) => Self::Priority {
+                backup: low_bits & 1 != 0,
+                address_id: if reader.is_empty() {None} else {Some(reader.read_u8()?)}
+            },
+            (6, 12) => {
+                reader.skip(1)?;
+                Self::Fail {data_sequence: reader.read_u64()?}
+            }
+            (7, 12) => {
+                reader.skip(1)?;
+                Self::FastClose {receiver_key: reader.read_u64()?}
+            }
+            (8, 4) => Self::Reset {flags: low_bits, reason: reader.read_u8()?},
+            (0..=8, _) => return Err(DeserializeError::WrongDataLength),
+            _ => Self::Other {subtype, low_bits, data: reader.read_rest().to_vec()}
+        };
+        Ok(option)
+    }
+    /// Returns `Err(OptionSpaceExceeded)` if option doesn't fit in TCP option space, i.e. REMOVE_ADDR with more than 37 addresses
+    pub fn into_option(self) -> Result<TcpOption, OptionSpaceExceeded> {
+        let mut writer = ByteWriter::new();
+        let subtype = self.get_subtype() << 4;
+        match self {
+            Self::Capable {version, flags, sender_key, receiver_key, data_length, checksum} => {
+                writer.write_u8(subtype | (version & 15)).write_u8(flags);
+                for key in [sender_key, receiver_key].into_iter().flatten() {
+                    writer.write_u64(key);
+                }
+                for value in [data_length, checksum].into_iter().flatten() {
+                    writer.write_u16(value);
+                }
+            }
+            Self::JoinSyn {backup, address_id, receiver_token, sender_random} => {
+                writer.write_u8(subtype | backup as u8).write_u8(address_id).write_u32(receiver_token).write_u32(sender_random);
+            }
+            Self::JoinSynAck {backup, address_id, sender_hmac, sender_random} => {
+                writer.write_u8(subtype | backup as u8).write_u8(address_id).write_u64(sender_hmac).write_u32(sender_random);
+            }
+            Self::JoinAck {sender_hmac} => {
+                writer.write_u8(subtype).write_u8(0).write_slice(&sender_hmac);
+            }
+            Self::Dss(dss) => {
+                let mut flags = (dss.data_fin as u8) << 4;
+                if dss.data_ack.is_some() {flags |= 1 | (dss.data_ack_long as u8) << 1;}
+                if let Some(mapping) = &dss.mapping {flags |= 4 | (mapping.data_sequence_long as u8) << 3;}
+                writer.write_u8(subtype).write_u8(flags);
+                match dss.data_ack {
+                    Some(data_ack) if dss.data_ack_long => {writer.write_u64(data_ack);}
+                    Some(data_ack) => {writer.write_u32(data_ack as u32);}
+                    None => {}
+                }
+                if let Some(mapping) = dss.mapping {
+                    match mapping.data_sequence_long {
+                        true => writer.write_u64(mapping.data_sequence),
+                        false => writer.write_u32(mapping.data_sequence as u32)
+                    };
+                    writer.write_u32(mapping.subflow_sequence).write_u16(mapping.data_length);
+                    if let Some(checksum) = mapping.checksum {writer.write_u16(checksum);}
+                }
+            }
+            Self::AddAddress {echo, address_id, address, port, hmac} => {
+                writer.write_u8(subtype | echo as u8).write_u8(address_id);
+                match address {
+                    IpAddr::V4(address) => writer.write_slice(&address.octets()),
+                    IpAddr::V6(address) => writer.write_slice(&address.octets())
+                };
+                if let Some(port) = port {writer.write_u16(port);}
+                if let Some(hmac) = hmac {writer.write_u64(hmac);}
+            }
+            Self::RemoveAddress {address_ids} => {
+                if address_ids.len() > 37 {return Err(OptionSpaceExceeded);}
+                writer.write_u8(subtype).write_slice(&address_ids);
+            }
+            Self::Priority {backup, address_id} => {
+                writer.write_u8(subtype | backup as u8);
+                if let Some(address_id) = address_id {writer.write_u8(address_id);}
+            }
+            Self::Fail {data_sequence} => {
+                writer.write_u8(subtype).write_u8(0).write_u64(data_sequence);
+            }
+            Self::FastClose {receiver_key} => {
+                writer.write_u8(subtype).write_u8(0).write_u64(receiver_key);
+            }
+            Self::Reset {flags, reason} => {
+                writer.write_u8(subtype | (flags & 15)).write_u8(reason);
+            }
+            Self::Other {low_bits, data, ..} => {
+                if data.len() > 37 {return Err(OptionSpaceExceeded);}
+                writer.write_u8(subtype | (low_bits & 15)).write_slice(&data);
+            }
+        }
+        Ok(TcpOption {
+            kind: 30,
+            data: writer.into_bytes()
+        })
+    }
+}
@@ -0,0 +1,511 @@
+use core::net::Ipv4Addr;
+use crate::l3::{IpProtocol, ipv4::Ipv4Packet};
+use crate::util::{Serializable, Deserializable, DeserializeError, IpPayload, ValidationIssue, ChecksumAccumulator, checksum};
+
+/// OSPFv2 Authentication, which takes up `AuType` and `Authentication` fields of OSPF header
+#[derive(Debug, Clone)]
+pub enum OspfAuthentication {
+    /// AuType 0 - no authentication
+    Null,
+    /// AuType 1 - simple password, up to 8 bytes, padded with zeroes
+    Simple([u8; 8]),
+    /// AuType 2 - cryptographic authentication, message digest is appended after OSPF packet
+    Cryptographic {
+        key_id: u8,
+        /// Length of message digest appended after OSPF packet
+        auth_data_len: u8,
+        /// Non-decreasing sequence number protecting against replay attacks
+        sequence_number: u32
+    },
+    /// Unknown AuType with raw `Authentication` field
+    Other {
+        au_type: u16,
+        data: [u8; 8]
+    }
+}
+impl OspfAuthentication {
+    pub fn get_type(&self) -> u16 {
+        match self {
+            Self::Null => 0,
+            Self::Simple(_) => 1,
+            Self::Cryptographic {..} => 2,
+            Self::Other {au_type, ..} => *au_type
+        }
+    }
+}
+impl Serializable for OspfAuthentication {
+    /// Converts authentication to `10 bytes`: `AuType` and `Authentication` fields
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 10];
+        result[0..2].copy_from_slice(&self.get_type().to_be_bytes());
+        match self {
+            Self::Null => {}
+            Self::Simple(password) => result[2..10].copy_from_slice(&password),
+            Self::Cryptographic {key_id, auth_data_len, sequence_number} => {
+                result[4] = key_id;
+                result[5] = auth_data_len;
+                result[6..10].copy_from_slice(&sequence_number.to_be_bytes());
+            }
+            Self::Other {au_type: _, data} => result[2..10].copy_from_slice(&data)
+        }
+        result
+    }
+}
+impl Deserializable for OspfAuthentication {
+    /// Constructs authentication from `AuType` and `Authentication` fields
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 10 {return Err(DeserializeError::WrongDataLength);}
+        let data = bytes[2..10].as_array().unwrap();
+        match u16::from_be_bytes([bytes[0], bytes[1]]) {
+            0 => Ok(Self::Null),
+            1 => Ok(Self::Simple(*data)),
+            2 => Ok(Self::Cryptographic {
+                key_id: bytes[4],
+                auth_data_len: bytes[5],
+                sequence_number: u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]])
+            }),
+            au_type => Ok(Self::Other {au_type, data: *data})
+        }
+    }
+}
+
+/// Link State Advertisement header, `20 bytes` long
+/// It is used by itself in Database Description and Link State Acknowledgment packets and precedes LSA body in Link State Update packets
+#[derive(Debug, Clone)]
+pub struct LsaHeader {
+    /// Time in seconds since LSA was originated
+    pub age: u16,
+    pub options: u8,
+    /// LS type, i.e. 1 for Router-LSA, 2 for Network-LSA, 3 and 4 for Summary-LSAs, 5 for AS-External-LSA
+    pub ls_type: u8,
+    pub link_state_id: Ipv4Addr,
+    pub advertising_router: Ipv4Addr,
+    pub sequence_number: u32,
+    /// Fletcher checksum of whole LSA, except `age` field
+    pub checksum: u16,
+    /// Length of whole LSA, including this header
+    pub length: u16
+}
+impl LsaHeader {
+    /// Constructs an empty `LsaHeader`
+    pub fn new() -> Self {
+        Self {
+            age: 0,
+            options: 0,
+            ls_type: 0,
+            link_state_id: Ipv4Addr::UNSPECIFIED,
+            advertising_router: Ipv4Addr::UNSPECIFIED,
+            sequence_number: 0,
+            checksum: 0,
+            length: 20
+        }
+    }
+}
+impl Default for LsaHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for LsaHeader {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 20];
+        result[0..2].copy_from_slice(&self.age.to_be_bytes());
+        result[2] = self.options;
+        result[3] = self.ls_type;
+        result[4..8].copy_from_slice(&self.link_state_id.octets());
+        result[8..12].copy_from_slice(&self.advertising_router.octets());
+        result[12..16].copy_from_slice(&self.sequence_number.to_be_bytes());
+        result[16..18].copy_from_slice(&self.checksum.to_be_bytes());
+        result[18..20].copy_from_slice(&self.length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for LsaHeader {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            age: u16::from_be_bytes([bytes[0], bytes[1]]),
+            options: bytes[2],
+            ls_type: bytes[3],
+            link_state_id: Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]),
+            advertising_router: Ipv4Addr::new(bytes[8], bytes[9], bytes[10], bytes[11]),
+            sequence_number: u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+            checksum: u16::from_be_bytes([bytes[16], bytes[17]]),
+            length: u16::from_be_bytes([bytes[18], bytes[19]])
+        })
+    }
+}
+
+/// Full Link State Advertisement, carried by Link State Update packets
+/// For now LSA body is presented just with `body: Vec<u8>`
+#[derive(Debug, Clone)]
+pub struct Lsa {
+    pub header: LsaHeader,
+    pub body: Vec<u8>
+}
+impl Lsa {
+    /// Returns length of serialized LSA, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        20 + self.body.len()
+    }
+}
+impl Serializable for Lsa {
+    /// Converts LSA to bytes, `header.length` is set automatically
+    fn serialize(mut self) -> Vec<u8> {
+        self.header.length = 20 + self.body.len() as u16;
+        let mut result = self.header.serialize();
+        result.append(&mut self.body);
+        result
+    }
+}
+impl Deserializable for Lsa {
+    /// Constructs `Lsa` from bytes
+    /// Note that this method is know where LSA ends from `length` field of its header
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let header = LsaHeader::deserialize(bytes)?;
+        let length = header.length as usize;
+        if length < 20 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            header,
+            body: bytes[20..length].to_vec()
+        })
+    }
+}
+
+/// Link State Request entry, `12 bytes` long
+#[derive(Debug, Clone)]
+pub struct LsRequest {
+    pub ls_type: u32,
+    pub link_state_id: Ipv4Addr,
+    pub advertising_router: Ipv4Addr
+}
+impl Serializable for LsRequest {
+    fn serialize(self) -> Vec<u8> {
+        [
+            self.ls_type.to_be_bytes(),
+            self.link_state_id.octets(),
+            self.advertising_router.octets()
+        ].concat()
+    }
+}
+impl Deserializable for LsRequest {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 12 {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            ls_type: u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            link_state_id: Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]),
+            advertising_router: Ipv4Addr::new(bytes[8], bytes[9], bytes[10], bytes[11])
+        })
+    }
+}
+
+/// Body of OSPFv2 packet, which follows common OSPF header
+#[derive(Debug, Clone)]
+pub enum OspfMessage {
+    /// Type 1 - discovers and maintains neighbor relationships
+    Hello {
+        network_mask: Ipv4Addr,
+        /// Seconds between Hello packets
+        hello_interval: u16,
+        options: u8,
+        /// Router priority for Designated Router election
+        priority: u8,
+        /// Seconds before declaring silent router down
+        dead_interval: u32,
+        designated_router: Ipv4Addr,
+        backup_designated_router: Ipv4Addr,
+        /// Router IDs of routers from which valid Hello packets have been seen recently
+        neighbors: Vec<Ipv4Addr>
+    },
+    /// Type 2 - summarizes link state database contents
+    DatabaseDescription {
+        interface_mtu: u16,
+        options: u8,
+        /// `I` flag - this is the first packet in sequence
+        init: bool,
+        /// `M` flag - more packets follow
+        more: bool,
+        /// `MS` flag - sender is master during database exchange
+        master: bool,
+        sequence_number: u32,
+        lsa_headers: Vec<LsaHeader>
+    },
+    /// Type 3 - requests pieces of neighbor's database
+    LinkStateRequest(Vec<LsRequest>),
+    /// Type 4 - floods LSAs
+    LinkStateUpdate(Vec<Lsa>),
+    /// Type 5 - acknowledges flooded LSAs
+    LinkStateAck(Vec<LsaHeader>)
+}
+impl OspfMessage {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Hello {..} => 1,
+            Self::DatabaseDescription {..} => 2,
+            Self::LinkStateRequest(_) => 3,
+            Self::LinkStateUpdate(_) => 4,
+            Self::LinkStateAck(_) => 5
+        }
+    }
+    /// Returns length of serialized message, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::Hello {neighbors, ..} => 20 + neighbors.len() * 4,
+            Self::DatabaseDescription {lsa_headers, ..} => 8 + lsa_headers.len() * 20,
+            Self::LinkStateRequest(requests) => requests.len() * 12,
+            Self::LinkStateUpdate(lsas) => 4 + lsas.iter().map(Lsa::wire_len).sum::<usize>(),
+            Self::LinkStateAck(headers) => headers.len() * 20
+        }
+    }
+    /// Constructs message of `message_type` from body bytes, which follow common OSPF header
+    pub fn deserialize_typed(message_type: u8, bytes: &[u8]) -> Result<Self, DeserializeError> {
+        match message_type {
+            1 => {
+                if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+                Ok(Self::Hello {
+                    network_mask: Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]),
+                    hello_interval: u16::from_be_bytes([bytes[4], bytes[5]]),
+                    options: bytes[6],
+                    priority: bytes[7],
+                    dead_interval: u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+                    designated_router: Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]),
+                    backup_designated_router: Ipv4Addr::new(bytes[16], bytes[17], bytes[18], bytes[19]),
+                    neighbors: bytes[20..].chunks_exact(4)
+                        .map(|neighbor| Ipv4Addr::new(neighbor[0], neighbor[1], neighbor[2], neighbor[3]))
+                        .collect()
+                })
+            }
+            2 => {
+                if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+                let mut lsa_headers = Vec::new();
+                for header in bytes[8..].chunks_exact(20) {
+                    lsa_headers.push(LsaHeader::deserialize(header)?);
+                }
+                Ok(Self::DatabaseDescription {
+                    interface_mtu: u16::from_be_bytes([bytes[0], bytes[1]]),
+                    options: bytes[2],
+                    init: bytes[3] & 4 != 0,
+                    more: bytes[3] & 2 != 0,
+                    master: bytes[3] & 1 != 0,
+                    sequence_number: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+                    lsa_headers
+                })
+            }
+            3 => {
+                let mut requests = Vec::new();
+                for request in bytes.chunks_exact(12) {
+                    requests.push(LsRequest::deserialize(request)?);
+                }
+                Ok(Self::LinkStateRequest(requests))
+            }
+            4 => {
+                if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+                let count = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                let mut lsas = Vec::new();
+                let mut i = 4usize;
+                for _ in 0..count {
+                    let lsa = Lsa::deserialize(&bytes[i..])?;
+                    i += lsa.header.length as usize;
+                    lsas.push(lsa);
+                }
+                Ok(Self::LinkStateUpdate(lsas))
+            }
+            5 => {
+                let mut headers = Vec::new();
+                for header in bytes.chunks_exact(20) {
+                    headers.push(LsaHeader::deserialize(header)?);
+                }
+                Ok(Self::LinkStateAck(headers))
+            }
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+impl Serializable for OspfMessage {
+    /// Converts message to body bytes, without common OSPF header
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::new();
+        match self {
+            Self::Hello {network_mask, hello_interval, options, priority, dead_interval, designated_router, backup_designated_router, neighbors} => {
+                result.extend_from_slice(&network_mask.octets());
+                result.extend_from_slice(&hello_interval.to_be_bytes());
+                result.push(options);
+                result.push(priority);
+                result.extend_from_slice(&dead_interval.to_be_bytes());
+                result.extend_from_slice(&designated_router.octets());
+                result.extend_from_slice(&backup_designated_router.octets());
+                for neighbor in neighbors {
+                    result.extend_from_slice(&neighbor.octets());
+                }
+            }
+            Self::DatabaseDescription {interface_mtu, options, init, more, master, sequence_number, lsa_headers} => {
+                result.extend_from_slice(&interface_mtu.to_be_bytes());
+                result.push(options);
+                result.push((init as u8) << 2 | (more as u8) << 1 | master as u8);
+                result.extend_from_slice(&sequence_number.to_be_bytes());
+                for header in lsa_headers {
+                    result.append(&mut header.serialize());
+                }
+            }
+            Self::LinkStateRequest(requests) => {
+                for request in requests {
+                    result.append(&mut request.serialize());
+                }
+            }
+            Self::LinkStateUpdate(lsas) => {
+                result.extend_from_slice(&(lsas.len() as u32).to_be_bytes());
+                for lsa in lsas {
+                    result.append(&mut lsa.serialize());
+                }
+            }
+            Self::LinkStateAck(headers) => {
+                for header in headers {
+                    result.append(&mut header.serialize());
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Struct for OSPFv2 Packet, carried directly by IPv4 with protocol number 89
+/// You can construct it from scratch with `OspfPacket::new()` and consistently editing
+/// Or construct from existing packet bytes with `OspfPacket::deserialize()` or from IPv4 packet with `OspfPacket::from_ipv4()`
+#[derive(Debug, Clone)]
+pub struct OspfPacket {
+    /// Router ID of packet source
+    pub router_id: Ipv4Addr,
+    /// Area this packet belongs to, `0.0.0.0` for backbone
+    pub area_id: Ipv4Addr,
+    /// Checksum of whole packet, except `Authentication` field
+    /// Note that it is not used with cryptographic authentication and should be zero
+    pub checksum: u16,
+    pub authentication: OspfAuthentication,
+    pub message: OspfMessage,
+    /// Message digest for cryptographic authentication, which is appended after packet and not included in packet length
+    pub auth_trailer: Vec<u8>
+}
+impl OspfPacket {
+    /// Constructs an empty Hello `OspfPacket` without authentication
+    pub fn new() -> Self {
+        Self {
+            router_id: Ipv4Addr::UNSPECIFIED,
+            area_id: Ipv4Addr::UNSPECIFIED,
+            checksum: 0,
+            authentication: OspfAuthentication::Null,
+            message: OspfMessage::Hello {
+                network_mask: Ipv4Addr::UNSPECIFIED,
+                hello_interval: 10,
+                options: 0,
+                priority: 1,
+                dead_interval: 40,
+                designated_router: Ipv4Addr::UNSPECIFIED,
+                backup_designated_router: Ipv4Addr::UNSPECIFIED,
+                neighbors: Vec::new()
+            },
+            auth_trailer: Vec::new()
+        }
+    }
+    /// Constructs `OspfPacket` from payload of `packet`
+    /// Returns `Err(DeserializeError::WrongData)` if `packet` doesn't carry OSPF, i.e. its protocol is not 89
+    pub fn from_ipv4(packet: &Ipv4Packet) -> Result<Self, DeserializeError> {
+        if packet.protocol != IpProtocol::Ospf {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&packet.payload)
+    }
+    /// Returns length of serialized packet including authentication trailer, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        24 + self.message.wire_len() + self.auth_trailer.len()
+    }
+    /// Recalculates `checksum` field in `OspfPacket`
+    /// With cryptographic authentication checksum is just set to zero
+    pub fn recalculate_checksum(&mut self) {
+        if let OspfAuthentication::Cryptographic {..} = self.authentication {
+            self.checksum = 0;
+            return;
+        }
+        let mut packet = self.clone();
+        packet.checksum = 0;
+        packet.auth_trailer = Vec::new();
+        let mut bytes = packet.serialize();
+        bytes[16..24].fill(0);
+        self.checksum = checksum(&bytes);
+    }
+    /// Checks raw OSPFv2 packet `bytes` for problems: truncation, wrong version, packet length mismatch and wrong checksum
+    /// With cryptographic authentication message digest is expected after packet and checksum isn't checked
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 24 {
+            issues.push(ValidationIssue::Truncated {layer: "ospf", needed: 24, actual: bytes.len()});
+            return issues;
+        }
+        if bytes[0] != 2 {
+            issues.push(ValidationIssue::InvalidField {layer: "ospf", field: "version", value: bytes[0] as u32});
+        }
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 24 {
+            issues.push(ValidationIssue::InvalidField {layer: "ospf", field: "packet_length", value: length as u32});
+            return issues;
+        }
+        let cryptographic = u16::from_be_bytes([bytes[14], bytes[15]]) == 2;
+        let trailer_len = if cryptographic {bytes[19] as usize} else {0};
+        if length + trailer_len > bytes.len() {
+            issues.push(ValidationIssue::Truncated {layer: "ospf", needed: length + trailer_len, actual: bytes.len()});
+            return issues;
+        }
+        if length + trailer_len != bytes.len() {
+            issues.push(ValidationIssue::LengthMismatch {layer: "ospf", field: "packet_length", expected: bytes.len() - trailer_len, actual: length});
+        }
+        if !cryptographic {
+            let declared = u16::from_be_bytes([bytes[12], bytes[13]]);
+            let calculated = ChecksumAccumulator::new().update(&bytes[..12]).update(&[0; 2]).update(&bytes[14..16]).update(&[0; 8]).update(&bytes[24..length]).finish();
+            if calculated != declared {
+                issues.push(ValidationIssue::ChecksumMismatch {layer: "ospf", declared, calculated});
+            }
+        }
+        issues
+    }
+}
+impl Default for OspfPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for OspfPacket {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = vec![0u8; 14];
+        result[0] = 2;
+        result[1] = self.message.get_type();
+        result[4..8].copy_from_slice(&self.router_id.octets());
+        result[8..12].copy_from_slice(&self.area_id.octets());
+        result[12..14].copy_from_slice(&self.checksum.to_be_bytes());
+        result.append(&mut self.authentication.serialize());
+        result.append(&mut self.message.serialize());
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result.append(&mut self.auth_trailer);
+        result
+    }
+}
+impl Deserializable for OspfPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 24 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0] != 2 {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 24 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            router_id: Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]),
+            area_id: Ipv4Addr::new(bytes[8], bytes[9], bytes[10], bytes[11]),
+            checksum: u16::from_be_bytes([bytes[12], bytes[13]]),
+            authentication: OspfAuthentication::deserialize(&bytes[14..24])?,
+            message: OspfMessage::deserialize_typed(bytes[1], &bytes[24..length])?,
+            auth_trailer: bytes[length..].to_vec()
+        })
+    }
+}
+impl IpPayload for OspfPacket {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Ospf
+    }
+}
\ No newline at end of file
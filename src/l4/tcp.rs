@@ -1,5 +1,9 @@
+use core::cmp::Ordering;
+use core::fmt;
 use core::net::IpAddr;
-use crate::util::{Serializable, Deserializable, DeserializeError, checksum};
+use core::ops::{Add, AddAssign, Sub};
+use crate::l3::{IpPacket, IpProtocol};
+use crate::util::{ByteReader, Serializable, Deserializable, DeserializeError, DeserializableRef, PacketRef, IpPayload, Checksummed, ChecksumContext, OptionSpaceExceeded, ValidationIssue, TlvFormat, TlvReader, transport_checksum, update_checksum, validate_options};
 
 /// TCP Packet Option struct for `TcpPacket`
 /// TCP Option are consist of:
@@ -19,6 +23,46 @@ impl TcpOption {
             data: Vec::new()
         }
     }
+    /// Constructs a SACK-Permitted `TcpOption`(kind 4)
+    /// Note that this option is allowed only in segments with `syn` flag
+    pub fn sack_permitted() -> Self {
+        Self {
+            kind: 4,
+            data: Vec::new()
+        }
+    }
+    /// Constructs a SACK `TcpOption`(kind 5) from `(left_edge, right_edge)` blocks
+    /// Returns `Err(OptionSpaceExceeded)` when blocks can't fit in TCP option space, i.e. there are more than 4 of them
+    pub fn sack(blocks: &[(u32, u32)]) -> Result<Self, OptionSpaceExceeded> {
+        if 2 + blocks.len() * 8 > 40 {return Err(OptionSpaceExceeded);}
+        let mut data = Vec::with_capacity(blocks.len() * 8);
+        for (left, right) in blocks {
+            data.extend_from_slice(&left.to_be_bytes());
+            data.extend_from_slice(&right.to_be_bytes());
+        }
+        Ok(Self {
+            kind: 5,
+            data
+        })
+    }
+    /// Returns `(left_edge, right_edge)` blocks of SACK option
+    /// Returns `None` if this is not a SACK option(kind 5) or its data length is not multiple of 8
+    pub fn sack_blocks(&self) -> Option<Vec<(u32, u32)>> {
+        if self.kind != 5 || !self.data.len().is_multiple_of(8) {return None;}
+        Some(self.data.chunks(8).map(|block| (
+            u32::from_be_bytes([block[0], block[1], block[2], block[3]]),
+            u32::from_be_bytes([block[4], block[5], block[6], block[7]])
+        )).collect())
+    }
+    /// Returns length of this option in TCP header, including padding to `4 bytes` boundary
+    pub fn padded_len(&self) -> usize {
+        (self.data.len() + 2).div_ceil(4) * 4
+    }
+}
+impl Default for TcpOption {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Serializable for TcpOption {
     fn serialize(self) -> Vec<u8> {
@@ -31,17 +75,193 @@ impl Serializable for TcpOption {
 }
 impl Deserializable for TcpOption {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
-        if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+        let mut reader = ByteReader::new(bytes);
+        let kind = reader.read_u8()?;
+        let length = reader.read_u8()? as usize;
+        if length < 2 {return Err(DeserializeError::WrongData);}
         Ok(Self {
-            kind: bytes[0],
-            data: bytes[2..2 + bytes[1] as usize].to_vec()
+            kind,
+            data: reader.read_slice(length - 2)?.to_vec()
         })
     }
 }
 
+/// TCP Authentication Option(kind 29), as described in RFC 5925
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpAoOption {
+    /// SendID of Master Key Tuple, which MAC was computed with
+    pub key_id: u8,
+    /// RecvID of Master Key Tuple, which sender wants to receive segments with
+    pub rnext_key_id: u8,
+    /// Message Authentication Code, usually `12 bytes` for HMAC-SHA-1-96 and AES-128-CMAC-96
+    pub mac: Vec<u8>
+}
+impl TcpAoOption {
+    /// Parses TCP-AO from `option`
+    /// Returns `None` if this is not TCP-AO option(kind 29) or it is shorter than `4 bytes`
+    pub fn from_option(option: &TcpOption) -> Option<Self> {
+        if option.kind != 29 || option.data.len() < 2 {return None;}
+        Some(Self {
+            key_id: option.data[0],
+            rnext_key_id: option.data[1],
+            mac: option.data[2..].to_vec()
+        })
+    }
+    /// Returns `Err(OptionSpaceExceeded)` if MAC is longer than `36 bytes`, so option can't fit in TCP option space
+    pub fn into_option(self) -> Result<TcpOption, OptionSpaceExceeded> {
+        if 4 + self.mac.len() > 40 {return Err(OptionSpaceExceeded);}
+        let mut data = Vec::with_capacity(2 + self.mac.len());
+        data.push(self.key_id);
+        data.push(self.rnext_key_id);
+        data.extend_from_slice(&self.mac);
+        Ok(TcpOption {
+            kind: 29,
+            data
+        })
+    }
+}
+
+/// Encoding of TCP Fast Open option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastOpenEncoding {
+    /// Kind 34, as described in RFC 7413
+    Standard,
+    /// Experimental kind 254 with ExID `0xF989`, as described in RFC 6994, used by early implementations
+    Experimental
+}
+
+/// TCP Fast Open option, which requests cookie with empty `cookie` or carries cookie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastOpenOption {
+    pub encoding: FastOpenEncoding,
+    /// Cookie issued by server, it is `4` to `16 bytes` of even length, or empty for cookie request
+    pub cookie: Vec<u8>
+}
+impl FastOpenOption {
+    /// Constructs cookie request, which client sends in SYN without data to obtain cookie
+    pub fn request(encoding: FastOpenEncoding) -> Self {
+        Self {encoding, cookie: Vec::new()}
+    }
+    /// Constructs option carrying `cookie`, which server sends in SYN-ACK and client sends in SYN with data
+    pub fn cookie(encoding: FastOpenEncoding, cookie: &[u8]) -> Self {
+        Self {encoding, cookie: cookie.to_vec()}
+    }
+    pub fn is_request(&self) -> bool {
+        self.cookie.is_empty()
+    }
+    /// Checks if cookie has length allowed by RFC 7413, i.e. `4` to `16 bytes` and even
+    pub fn is_cookie_length_valid(&self) -> bool {
+        (4..=16).contains(&self.cookie.len()) && self.cookie.len().is_multiple_of(2)
+    }
+    /// Parses TCP Fast Open from `option`
+    /// Returns `None` if this is neither kind 34 nor kind 254 with ExID `0xF989`
+    pub fn from_option(option: &TcpOption) -> Option<Self> {
+        match (option.kind, option.data.as_slice()) {
+            (34, cookie) => Some(Self::cookie(FastOpenEncoding::Standard, cookie)),
+            (254, [0xF9, 0x89, cookie @ ..]) => Some(Self::cookie(FastOpenEncoding::Experimental, cookie)),
+            _ => None
+        }
+    }
+    /// Returns `Err(OptionSpaceExceeded)` if cookie is too long to fit in TCP option space
+    pub fn into_option(self) -> Result<TcpOption, OptionSpaceExceeded> {
+        let (kind, mut data) = match self.encoding {
+            FastOpenEncoding::Standard => (34, Vec::with_capacity(self.cookie.len())),
+            FastOpenEncoding::Experimental => (254, vec![0xF9, 0x89])
+        };
+        data.extend_from_slice(&self.cookie);
+        if 2 + data.len() > 40 {return Err(OptionSpaceExceeded);}
+        Ok(TcpOption {kind, data})
+    }
+}
+
+/// Parameters of TCP-AO Master Key Tuple, which determine layout of option and input of MAC
+/// Key itself, key derivation and MAC algorithm are left to `TcpAoMac`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpAoKey {
+    /// Key ID put into segments sent with this key
+    pub send_id: u8,
+    /// Key ID expected in segments received with this key
+    pub recv_id: u8,
+    /// Length of MAC field, computed MAC is truncated to it
+    pub mac_length: usize,
+    /// Cover TCP options other than TCP-AO by MAC, otherwise they are skipped
+    pub include_options: bool
+}
+
+/// Hook which computes TCP-AO MAC, i.e. HMAC-SHA-1 or AES-128-CMAC keyed with traffic key of connection, since crate has no cryptography
+/// Implemented for closures `Fn(u8, &[u8]) -> Vec<u8>`
+pub trait TcpAoMac {
+    /// Returns MAC of `message` with traffic key of Master Key Tuple with `key_id`, it may be longer than MAC field and is truncated then
+    fn compute_mac(&self, key_id: u8, message: &[u8]) -> Vec<u8>;
+}
+impl<F: Fn(u8, &[u8]) -> Vec<u8>> TcpAoMac for F {
+    fn compute_mac(&self, key_id: u8, message: &[u8]) -> Vec<u8> {
+        self(key_id, message)
+    }
+}
+
+/// Borrowed view of one TCP option, yielded by `TcpOptionIter`
+#[derive(Debug, Clone, Copy)]
+pub struct TcpOptionRef<'a> {
+    pub kind: u8,
+    pub data: &'a [u8]
+}
+impl TcpOptionRef<'_> {
+    /// Returns Maximum Segment Size, if this is MSS option(kind 2)
+    pub fn mss(&self) -> Option<u16> {
+        match (self.kind, self.data) {
+            (2, [high, low]) => Some(u16::from_be_bytes([*high, *low])),
+            _ => None
+        }
+    }
+    /// Returns shift count, if this is Window Scale option(kind 3)
+    pub fn window_scale(&self) -> Option<u8> {
+        match (self.kind, self.data) {
+            (3, [shift]) => Some(*shift),
+            _ => None
+        }
+    }
+    /// Copies option into owned `TcpOption`
+    pub fn into_owned(self) -> TcpOption {
+        TcpOption {
+            kind: self.kind,
+            data: self.data.to_vec()
+        }
+    }
+}
+
+/// Iterator over options of raw TCP header without allocation
+/// No-Operation options are skipped, iteration stops at End of Option List
+/// Malformed option is yielded as `Err(DeserializeError::WrongDataLength)` and ends iteration
+#[derive(Debug, Clone)]
+pub struct TcpOptionIter<'a> {
+    reader: TlvReader<'a>
+}
+impl<'a> TcpOptionIter<'a> {
+    /// Constructs iterator over raw option area, i.e. bytes between `20th byte` and data offset of TCP header
+    pub fn new(options: &'a [u8]) -> Self {
+        Self {reader: TlvReader::new(TlvFormat::TCP_OPTIONS, options)}
+    }
+    /// Constructs iterator over options of raw TCP segment `bytes`, using its data offset
+    /// Returns `Err(DeserializeError::WrongDataLength)` if header is truncated or data offset is wrong
+    pub fn from_segment(bytes: &'a [u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        let data_offset = (bytes[12] as usize >> 4) * 4;
+        if data_offset < 20 || data_offset > bytes.len() {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self::new(&bytes[20..data_offset]))
+    }
+}
+impl<'a> Iterator for TcpOptionIter<'a> {
+    type Item = Result<TcpOptionRef<'a>, DeserializeError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.next().map(|option| option.map(|option| TcpOptionRef {kind: option.kind as u8, data: option.value}))
+    }
+}
+impl core::iter::FusedIterator for TcpOptionIter<'_> {}
+
 /// Struct for TCP Packet Flags in normal order for `TcpPacket`
 /// Note that normal TCP Packet Flags order are: `nonce_sum`, `cwr`, `ece`, `urg`, `ack`, `psh`, `rst`, `syn` and `fin`
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TcpFlags {
     /// Nonce Sum - an experimental flag used to protect against accidental or malicious concealment of marked packets
     pub ns: bool,
@@ -77,36 +297,190 @@ impl TcpFlags {
             fin: false
         }
     }
+    /// Constructs `TcpFlags` with only SYN set, which opens connection
+    pub fn syn() -> Self {
+        Self {syn: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` with SYN and ACK set, which answers SYN
+    pub fn syn_ack() -> Self {
+        Self {syn: true, ack: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` with only ACK set
+    pub fn ack() -> Self {
+        Self {ack: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` with PSH and ACK set, which data segments usually have
+    pub fn psh_ack() -> Self {
+        Self {psh: true, ack: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` with FIN and ACK set, which closes connection gracefully
+    pub fn fin_ack() -> Self {
+        Self {fin: true, ack: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` with only RST set
+    pub fn rst() -> Self {
+        Self {rst: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` with RST and ACK set, which refuses connection
+    pub fn rst_ack() -> Self {
+        Self {rst: true, ack: true, ..Self::new()}
+    }
+    /// Constructs `TcpFlags` from all 9 flag bits in one value, as they are in the low bits of 13th and 14th bytes of TCP header, i.e. `0x012` for SYN-ACK
+    /// Higher bits are ignored
+    pub fn from_u16(value: u16) -> Self {
+        Self {
+            ns: value & 0x100 != 0,
+            cwr: value & 0x80 != 0,
+            ece: value & 0x40 != 0,
+            urg: value & 0x20 != 0,
+            ack: value & 0x10 != 0,
+            psh: value & 0x08 != 0,
+            rst: value & 0x04 != 0,
+            syn: value & 0x02 != 0,
+            fin: value & 0x01 != 0
+        }
+    }
+    /// Returns all 9 flag bits in one value, the same as `from_u16()` takes
+    pub fn to_u16(&self) -> u16 {
+        (self.ns as u16) << 8 |
+        (self.cwr as u16) << 7 |
+        (self.ece as u16) << 6 |
+        (self.urg as u16) << 5 |
+        (self.ack as u16) << 4 |
+        (self.psh as u16) << 3 |
+        (self.rst as u16) << 2 |
+        (self.syn as u16) << 1 |
+        self.fin as u16
+    }
+    /// Returns `true` if segment opens connection or answers opening, i.e. SYN or SYN-ACK, but not with FIN or RST
+    pub fn is_connection_establishment(&self) -> bool {
+        self.syn && !self.fin && !self.rst
+    }
+    /// Returns `true` if segment closes connection gracefully with FIN or aborts it with RST
+    pub fn is_connection_termination(&self) -> bool {
+        self.fin || self.rst
+    }
+    /// Returns `true` if segment carries only acknowledgement, i.e. ACK without SYN, FIN, RST and PSH
+    pub fn is_pure_ack(&self) -> bool {
+        self.ack && !self.syn && !self.fin && !self.rst && !self.psh
+    }
+    /// Returns `true` if flags are a combination that must not appear, i.e. SYN with FIN or RST, FIN with RST, or no flags at all
+    pub fn is_illegal(&self) -> bool {
+        (self.syn && (self.fin || self.rst)) || (self.fin && self.rst) || self.to_u16() == 0
+    }
+    /// Returns set flags as letters in scapy notation, i.e. `"SA"` for SYN-ACK
+    pub fn letters(&self) -> String {
+        [(self.fin, 'F'), (self.syn, 'S'), (self.rst, 'R'), (self.psh, 'P'), (self.ack, 'A'), (self.urg, 'U'), (self.ece, 'E'), (self.cwr, 'C'), (self.ns, 'N')]
+            .iter().filter(|(set, _)| *set).map(|(_, letter)| *letter).collect()
+    }
+    /// Constructs `TcpFlags` from letters in scapy notation, case insensitive
+    /// Returns `None` if there is an unknown letter
+    pub fn from_letters(letters: &str) -> Option<Self> {
+        let mut flags = Self::new();
+        for letter in letters.chars() {
+            match letter.to_ascii_uppercase() {
+                'F' => flags.fin = true,
+                'S' => flags.syn = true,
+                'R' => flags.rst = true,
+                'P' => flags.psh = true,
+                'A' => flags.ack = true,
+                'U' => flags.urg = true,
+                'E' => flags.ece = true,
+                'C' => flags.cwr = true,
+                'N' => flags.ns = true,
+                _ => return None
+            }
+        }
+        Some(flags)
+    }
+}
+impl Default for TcpFlags {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+/// Formats set flags as names joined with `|`, i.e. `SYN|ACK`, or `NONE` if no flag is set
+impl fmt::Display for TcpFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [(self.fin, "FIN"), (self.syn, "SYN"), (self.rst, "RST"), (self.psh, "PSH"), (self.ack, "ACK"), (self.urg, "URG"), (self.ece, "ECE"), (self.cwr, "CWR"), (self.ns, "NS")];
+        let names: Vec<&str> = names.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+        if names.is_empty() {return f.write_str("NONE");}
+        f.write_str(&names.join("|"))
+    }
 }
 impl Serializable for TcpFlags {
     fn serialize(self) -> Vec<u8> {
-        vec![
-            self.ns as u8,
-            (self.cwr as u8) << 7 |
-            (self.ece as u8) << 6 |
-            (self.urg as u8) << 5 |
-            (self.ack as u8) << 4 |
-            (self.psh as u8) << 3 |
-            (self.rst as u8) << 2 |
-            (self.syn as u8) << 1 |
-            self.fin as u8
-        ]
+        self.to_u16().to_be_bytes().to_vec()
     }
 }
 impl Deserializable for TcpFlags {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
         if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
-        Ok(Self {
-            ns: bytes[0] & 1 != 0,
-            cwr: bytes[1] & 128 != 0,
-            ece: bytes[1] & 64 != 0,
-            urg: bytes[1] & 32 != 0,
-            ack: bytes[1] & 16 != 0,
-            psh: bytes[1] & 8 != 0,
-            rst: bytes[1] & 4 != 0,
-            syn: bytes[1] & 2 != 0,
-            fin: bytes[1] & 1 != 0
-        })
+        Ok(Self::from_u16(u16::from_be_bytes([bytes[0] & 1, bytes[1]])))
+    }
+}
+
+/// TCP Sequence Number with wraparound-correct arithmetic, as described in RFC 1982 and RFC 9293
+/// Comparison treats numbers less than `2^31` ahead as greater, so `SeqNum(5) > SeqNum(u32::MAX)`
+/// Note that numbers exactly `2^31` apart are neither greater nor less, but not equal, so `partial_cmp()` returns `None` for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SeqNum(pub u32);
+impl SeqNum {
+    /// Returns signed distance from `other` to `self`, e.g. `SeqNum(2).distance(SeqNum(u32::MAX)) == 3`
+    pub fn distance(self, other: SeqNum) -> i32 {
+        self.0.wrapping_sub(other.0) as i32
+    }
+    /// Returns `true` if `self` is in half-open range `start..end` in sequence space
+    pub fn is_in(self, start: SeqNum, end: SeqNum) -> bool {
+        self.0.wrapping_sub(start.0) < end.0.wrapping_sub(start.0)
+    }
+    /// Returns `true` if `self` is in closed range `start..=end` in sequence space
+    pub fn is_in_inclusive(self, start: SeqNum, end: SeqNum) -> bool {
+        self.0.wrapping_sub(start.0) <= end.0.wrapping_sub(start.0)
+    }
+}
+impl PartialOrd for SeqNum {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.distance(*other) {
+            0 => Some(Ordering::Equal),
+            i32::MIN => None,
+            distance if distance > 0 => Some(Ordering::Greater),
+            _ => Some(Ordering::Less)
+        }
+    }
+}
+impl Add<u32> for SeqNum {
+    type Output = SeqNum;
+    fn add(self, rhs: u32) -> SeqNum {
+        SeqNum(self.0.wrapping_add(rhs))
+    }
+}
+impl AddAssign<u32> for SeqNum {
+    fn add_assign(&mut self, rhs: u32) {
+        self.0 = self.0.wrapping_add(rhs);
+    }
+}
+impl Sub<u32> for SeqNum {
+    type Output = SeqNum;
+    fn sub(self, rhs: u32) -> SeqNum {
+        SeqNum(self.0.wrapping_sub(rhs))
+    }
+}
+impl Sub<SeqNum> for SeqNum {
+    type Output = i32;
+    /// The same as `distance()`
+    fn sub(self, rhs: SeqNum) -> i32 {
+        self.distance(rhs)
+    }
+}
+impl From<u32> for SeqNum {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+impl From<SeqNum> for u32 {
+    fn from(value: SeqNum) -> Self {
+        value.0
     }
 }
 
@@ -125,6 +499,9 @@ pub struct TcpSegment {
     pub flags: TcpFlags,
     pub window_size: u16,
     pub checksum: u16,
+    /// Checksum is offloaded to NIC, so it is serialized as zero and `recalculate_checksum()` keeps it zero
+    /// Set it to match packets captured on sending host with checksum offload enabled
+    pub checksum_offloaded: bool,
     pub urgent_pointer: u16,
     pub options: Vec<TcpOption>,
     pub payload: Vec<u8>
@@ -140,107 +517,534 @@ impl TcpSegment {
             flags: TcpFlags::new(),
             window_size: 0,
             checksum: 0,
+            checksum_offloaded: false,
             urgent_pointer: 0,
             options: Vec::new(),
             payload: Vec::new()
         }
     }
+    /// Constructs a SYN segment opening connection from port `source` to port `destination` with initial sequence number `isn`
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn syn(source: u16, destination: u16, isn: u32) -> Self {
+        let mut segment = Self::new();
+        segment.source = source;
+        segment.destination = destination;
+        segment.sequence_number = isn;
+        segment.flags.syn = true;
+        segment.window_size = 64240;
+        segment
+    }
+    /// Constructs a SYN-ACK segment answering SYN segment, where `ack` is peer's initial sequence number plus 1
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn syn_ack(source: u16, destination: u16, isn: u32, ack: u32) -> Self {
+        let mut segment = Self::syn(source, destination, isn);
+        segment.acknowledgement_number = ack;
+        segment.flags.ack = true;
+        segment
+    }
+    /// Constructs a pure ACK segment without payload
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn ack(source: u16, destination: u16, seq: u32, ack: u32) -> Self {
+        let mut segment = Self::new();
+        segment.source = source;
+        segment.destination = destination;
+        segment.sequence_number = seq;
+        segment.acknowledgement_number = ack;
+        segment.flags.ack = true;
+        segment.window_size = 64240;
+        segment
+    }
+    /// Constructs a FIN-ACK segment gracefully closing connection
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn fin(source: u16, destination: u16, seq: u32, ack: u32) -> Self {
+        let mut segment = Self::ack(source, destination, seq, ack);
+        segment.flags.fin = true;
+        segment
+    }
+    /// Constructs a RST segment, which receiver of `observed` segment would send to reject it, as described in RFC 9293
+    /// If `observed` has `ack` flag, RST takes its sequence number from `observed.acknowledgement_number`
+    /// Otherwise RST has zero sequence number and acknowledges whole `observed` segment
+    /// Ports are swapped relative to `observed`
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn rst_for(observed: &TcpSegment) -> Self {
+        let mut segment = Self::new();
+        segment.source = observed.destination;
+        segment.destination = observed.source;
+        segment.flags.rst = true;
+        if observed.flags.ack {
+            segment.sequence_number = observed.acknowledgement_number;
+        }
+        else {
+            segment.acknowledgement_number = observed.next_seq_num().0;
+            segment.flags.ack = true;
+        }
+        segment
+    }
+    /// Constructs IP packet with RST segment from `rst_for()` in response to `observed`, carried by `ip_header`
+    /// Addresses are swapped relative to `ip_header` and all checksums are calculated
+    pub fn rst_in_response_to(observed: &TcpSegment, ip_header: &IpPacket) -> IpPacket {
+        Self::rst_for(observed).into_ip(ip_header.flow_header(true))
+    }
+    /// Constructs IP packet with FIN segment, which receiver of `observed` segment, carried by `ip_header`, would send to close connection
+    /// FIN takes its sequence number from `observed.acknowledgement_number` and acknowledges whole `observed` segment
+    /// Ports and addresses are swapped relative to `observed` and `ip_header`, all checksums are calculated
+    pub fn fin_in_response_to(observed: &TcpSegment, ip_header: &IpPacket) -> IpPacket {
+        let segment = Self::fin(observed.destination, observed.source, observed.acknowledgement_number, observed.next_seq_num().0);
+        segment.into_ip(ip_header.flow_header(true))
+    }
+    /// Constructs `TcpSegment` from payload of `packet`
+    /// Returns `Err(DeserializeError::WrongData)` if protocol of `packet` isn't 6
+    pub fn from_ip(packet: &IpPacket) -> Result<Self, DeserializeError> {
+        if packet.protocol() != IpProtocol::Tcp {return Err(DeserializeError::WrongData);}
+        Self::deserialize(packet.payload())
+    }
+    /// Wraps segment into `header` packet, calculating TCP checksum with its addresses and IPv4 header checksum
+    pub fn into_ip(mut self, header: IpPacket) -> IpPacket {
+        let _ = self.recalculate_checksum(header.source(), header.destination());
+        match header {
+            IpPacket::V4(mut packet) => {
+                packet.set_payload_packet(&self);
+                packet.recalculate_checksum();
+                IpPacket::V4(packet)
+            }
+            IpPacket::V6(mut packet) => {
+                packet.set_payload_packet(&self);
+                IpPacket::V6(packet)
+            }
+        }
+    }
+    /// Constructs keepalive probe for connection of `last`, which is IP packet with the last segment sent by probing side
+    /// Probe has sequence number `SND.NXT - 1`, so peer answers with ACK, and carries one garbage byte if `garbage` is `true`, as RFC 9293 allows
+    /// Probe is sent in the same direction as `last` and has all checksums calculated
+    /// Returns `Err(DeserializeError::WrongData)` if `last` doesn't carry TCP segment
+    pub fn keepalive_probe(last: &IpPacket, garbage: bool) -> Result<IpPacket, DeserializeError> {
+        let observed = Self::from_ip(last)?;
+        let mut probe = Self::ack(observed.source, observed.destination, (observed.next_seq_num() - 1).0, observed.acknowledgement_number);
+        probe.window_size = observed.window_size;
+        if garbage {
+            probe.payload = vec![0];
+        }
+        Ok(probe.into_ip(last.flow_header(false)))
+    }
     /// Recalculates `checksum` field in `TcpPacket`
     /// Note that to calculate TCP Checksum you also need source ip and destination ip from IP packet
     /// Returns `Err(())` only when `source_ip` and `destination_ip` not same version, e.g. IPv4 and IPv6
+    /// If `checksum_offloaded` is set, `checksum` is just reset to zero
     pub fn recalculate_checksum(&mut self, source_ip: IpAddr, destination_ip: IpAddr) -> Result<(), ()> {
-        let mut packet = self.clone_header().serialize();
-        packet[16] = 0;
-        packet[17] = 0;
+        if self.checksum_offloaded {
+            self.checksum = 0;
+            return Ok(());
+        }
+        let mut header = self.serialize_header();
+        header[16..18].fill(0);
+        self.checksum = transport_checksum(source_ip, destination_ip, 6, &[&header, &self.payload]).ok_or(())?;
+        Ok(())
+    }
+    pub fn clone_header(&self) -> Self {
+        Self {
+            payload: Vec::new(),
+            ..self.clone()
+        }
+    }
+    /// Marks checksum as offloaded to NIC, so segment is serialized with zero checksum
+    pub fn with_checksum_offload(mut self) -> Self {
+        self.checksum_offloaded = true;
+        self.checksum = 0;
+        self
+    }
+    /// Sets `payload` to `urgent` data followed by `normal` data, sets URG flag and points `urgent_pointer` past the last urgent byte, as described in RFC 9293
+    /// Urgent data longer than `65535 bytes` can't be pointed to, so pointer is limited to it
+    pub fn with_urgent_data(mut self, urgent: &[u8], normal: &[u8]) -> Self {
+        self.payload = [urgent, normal].concat();
+        self.flags.urg = !urgent.is_empty();
+        self.urgent_pointer = u16::try_from(urgent.len()).unwrap_or(u16::MAX);
+        self
+    }
+    /// Returns urgent part of payload, which is before `urgent_pointer`, or empty slice if URG flag is not set
+    /// Pointer beyond payload end means urgent data continues in following segments, so the whole payload is urgent
+    pub fn urgent_data(&self) -> &[u8] {
+        if !self.flags.urg {return &[];}
+        &self.payload[..(self.urgent_pointer as usize).min(self.payload.len())]
+    }
+    /// Returns normal part of payload after urgent data, which is the whole payload if URG flag is not set
+    pub fn normal_data(&self) -> &[u8] {
+        &self.payload[self.urgent_data().len()..]
+    }
+    /// Returns the last byte of urgent data, which BSD sockets deliver out of band unless `SO_OOBINLINE` is set
+    /// Returns `None` if URG flag is not set or urgent data ends beyond this segment
+    pub fn oob_byte(&self) -> Option<u8> {
+        if !self.flags.urg || self.urgent_pointer == 0 {return None;}
+        self.payload.get(self.urgent_pointer as usize - 1).copied()
+    }
+    /// Returns `sequence_number` as wraparound-correct `SeqNum`
+    pub fn seq_num(&self) -> SeqNum {
+        SeqNum(self.sequence_number)
+    }
+    /// Returns `acknowledgement_number` as wraparound-correct `SeqNum`
+    pub fn ack_num(&self) -> SeqNum {
+        SeqNum(self.acknowledgement_number)
+    }
+    /// Returns amount of sequence space this segment occupies: payload length plus 1 for each of `syn` and `fin` flags
+    pub fn sequence_len(&self) -> u32 {
+        self.payload.len() as u32 + self.flags.syn as u32 + self.flags.fin as u32
+    }
+    /// Returns sequence number following this segment, i.e. `seq_num() + sequence_len()`
+    pub fn next_seq_num(&self) -> SeqNum {
+        self.seq_num() + self.sequence_len()
+    }
+    /// Splits payload into segments of at most `mss` bytes, as Generic Segmentation Offload does in kernel
+    /// Each segment has the same header and options, sequence number is advanced by payload offset, urgent pointer is shifted and dropped past urgent data
+    /// FIN is kept only on the last segment, which also gets PSH if it carries data, CWR is kept only on the first one, checksum of each segment is recalculated with `source_ip` and `destination_ip`
+    /// Segment with payload of at most `mss` bytes, or `mss` of zero, gives one segment
+    /// Returns `None` when `source_ip` and `destination_ip` are not same version, e.g. IPv4 and IPv6
+    pub fn segment(&self, mss: usize, source_ip: IpAddr, destination_ip: IpAddr) -> Option<Vec<Self>> {
+        let chunks: Vec<&[u8]> = if mss == 0 || self.payload.len() <= mss {vec![&self.payload]} else {self.payload.chunks(mss).collect()};
+        let count = chunks.len();
+        let mut segments = Vec::with_capacity(count);
+        let mut offset = 0usize;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let mut segment = self.clone_header();
+            segment.payload = chunk.to_vec();
+            segment.sequence_number = (self.seq_num() + offset as u32).0;
+            segment.flags.cwr &= i == 0;
+            segment.flags.fin &= i + 1 == count;
+            segment.flags.psh = i + 1 == count && (self.flags.psh || !chunk.is_empty());
+            if self.flags.urg && self.urgent_pointer as usize > offset {
+                segment.urgent_pointer = self.urgent_pointer - offset as u16;
+            }
+            else {
+                segment.flags.urg = false;
+                segment.urgent_pointer = 0;
+            }
+            segment.recalculate_checksum(source_ip, destination_ip).ok()?;
+            offset += chunk.len();
+            segments.push(segment);
+        }
+        Some(segments)
+    }
+    /// Returns length of all options in TCP header, including padding
+    /// This value must not exceed `40 bytes`
+    pub fn options_len(&self) -> usize {
+        self.options.iter().map(TcpOption::padded_len).sum()
+    }
+    /// Returns length of header with options, including padding
+    pub fn header_len(&self) -> usize {
+        20 + self.options_len()
+    }
+    /// Returns length of serialized segment, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.header_len() + self.payload.len()
+    }
+    /// Returns `true` if segment carries SACK-Permitted option
+    pub fn is_sack_permitted(&self) -> bool {
+        self.options.iter().any(|option| option.kind == 4)
+    }
+    /// Adds or removes SACK-Permitted option
+    /// Returns `Err(OptionSpaceExceeded)` if option doesn't fit in TCP option space, segment remains untouched in that case
+    pub fn set_sack_permitted(&mut self, permitted: bool) -> Result<(), OptionSpaceExceeded> {
+        if !permitted {
+            self.options.retain(|option| option.kind != 4);
+            return Ok(());
+        }
+        if self.is_sack_permitted() {return Ok(());}
+        let option = TcpOption::sack_permitted();
+        if self.options_len() + option.padded_len() > 40 {return Err(OptionSpaceExceeded);}
+        self.options.push(option);
+        Ok(())
+    }
+    /// Returns `(left_edge, right_edge)` blocks from SACK option of this segment
+    /// Returns `None` if segment has no valid SACK option
+    pub fn sack_blocks(&self) -> Option<Vec<(u32, u32)>> {
+        self.options.iter().find_map(TcpOption::sack_blocks)
+    }
+    /// Replaces SACK option of this segment with new one made of `blocks`
+    /// Empty `blocks` just removes SACK option
+    /// Returns `Err(OptionSpaceExceeded)` if option doesn't fit in TCP option space together with other options, segment remains untouched in that case
+    pub fn set_sack_blocks(&mut self, blocks: &[(u32, u32)]) -> Result<(), OptionSpaceExceeded> {
+        let others_len: usize = self.options.iter()
+            .filter(|option| option.kind != 5)
+            .map(TcpOption::padded_len)
+            .sum();
+        if blocks.is_empty() {
+            self.options.retain(|option| option.kind != 5);
+            return Ok(());
+        }
+        let option = TcpOption::sack(blocks)?;
+        if others_len + option.padded_len() > 40 {return Err(OptionSpaceExceeded);}
+        match self.options.iter_mut().find(|option| option.kind == 5) {
+            Some(existing) => *existing = option,
+            None => self.options.push(option)
+        }
+        Ok(())
+    }
+    /// Returns Maximum Segment Size from MSS option(kind 2)
+    /// Returns `None` if segment has no valid MSS option
+    pub fn mss(&self) -> Option<u16> {
+        self.options.iter()
+            .find(|option| option.kind == 2 && option.data.len() == 2)
+            .map(|option| u16::from_be_bytes([option.data[0], option.data[1]]))
+    }
+    /// Rewrites MSS option to `max` if segment has `syn` flag and its MSS exceeds `max`
+    /// `checksum` is updated incrementally, so you dont need IP addresses for it, but it has to be correct before clamping
+    /// Returns `true` if MSS was rewritten
+    pub fn clamp_mss(&mut self, max: u16) -> bool {
+        if !self.flags.syn {return false;}
+        let Some(option) = self.options.iter_mut().find(|option| option.kind == 2 && option.data.len() == 2) else {return false;};
+        let mss = u16::from_be_bytes([option.data[0], option.data[1]]);
+        if mss <= max {return false;}
+        option.data.copy_from_slice(&max.to_be_bytes());
+        if !self.checksum_offloaded {
+            self.checksum = update_checksum(self.checksum, mss, max);
+        }
+        true
+    }
+    /// Returns TCP Fast Open option of this segment in either encoding
+    /// Returns `None` if segment has no TCP Fast Open option
+    pub fn fast_open(&self) -> Option<FastOpenOption> {
+        self.options.iter().find_map(FastOpenOption::from_option)
+    }
+    /// Adds or replaces TCP Fast Open option of this segment with `option`, or just removes it if `option` is `None`
+    /// Returns `Err(OptionSpaceExceeded)` if option doesn't fit in TCP option space together with other options, segment remains untouched in that case
+    pub fn set_fast_open(&mut self, option: Option<FastOpenOption>) -> Result<(), OptionSpaceExceeded> {
+        let is_fast_open = |option: &TcpOption| FastOpenOption::from_option(option).is_some();
+        let Some(option) = option else {
+            self.options.retain(|option| !is_fast_open(option));
+            return Ok(());
+        };
+        let option = option.into_option()?;
+        let others_len: usize = self.options.iter()
+            .filter(|option| !is_fast_open(option))
+            .map(TcpOption::padded_len)
+            .sum();
+        if others_len + option.padded_len() > 40 {return Err(OptionSpaceExceeded);}
+        match self.options.iter_mut().find(|option| is_fast_open(option)) {
+            Some(existing) => *existing = option,
+            None => self.options.push(option)
+        }
+        Ok(())
+    }
+    /// Checks if this is SYN of TCP Fast Open client requesting cookie
+    pub fn is_fast_open_request(&self) -> bool {
+        self.flags.syn && !self.flags.ack && self.fast_open().is_some_and(|option| option.is_request())
+    }
+    /// Checks if this is SYN of TCP Fast Open client presenting cookie, data of such SYN is delivered before handshake completes if cookie is valid
+    pub fn is_fast_open_attempt(&self) -> bool {
+        self.flags.syn && !self.flags.ack && self.fast_open().is_some_and(|option| !option.is_request())
+    }
+    /// Returns TCP Authentication Option of this segment
+    /// Returns `None` if segment has no valid TCP-AO option
+    pub fn ao_option(&self) -> Option<TcpAoOption> {
+        self.options.iter().find_map(TcpAoOption::from_option)
+    }
+    /// Returns data covered by TCP-AO MAC: Sequence Number Extension `sne`, pseudo-header, TCP header with zeroed checksum and MAC field, and payload, as described in RFC 5925
+    /// Options other than TCP-AO are skipped unless `include_options` is `true`
+    /// Returns `None` if segment has no TCP-AO option or `source_ip` and `destination_ip` are not same version
+    pub fn ao_mac_input(&self, source_ip: IpAddr, destination_ip: IpAddr, sne: u32, include_options: bool) -> Option<Vec<u8>> {
+        let length = self.wire_len();
+        let mut segment = self.clone_header();
+        segment.checksum = 0;
+        let option = segment.options.iter_mut().find(|option| option.kind == 29 && option.data.len() >= 2)?;
+        option.data[2..].fill(0);
+        let ao_option = option.clone();
+        let mut header = segment.serialize_header();
+        if !include_options {
+            header.truncate(20);
+            header.append(&mut ao_option.serialize());
+        }
+        let mut result = Vec::with_capacity(44 + header.len() + self.payload.len());
+        result.extend_from_slice(&sne.to_be_bytes());
         match (source_ip, destination_ip) {
             (IpAddr::V4(source), IpAddr::V4(destination)) => {
-                let mut pseudo_header = Vec::<u8>::with_capacity(32 + packet.len());
-                pseudo_header.append(&mut source.octets().to_vec());
-                pseudo_header.append(&mut destination.octets().to_vec());
-                pseudo_header.push(0);
-                pseudo_header.push(6);
-                pseudo_header.append(&mut (packet.len() as u16).to_be_bytes().to_vec());
-                pseudo_header.append(&mut packet);
-                self.checksum = checksum(pseudo_header);
-                Ok(())
+                result.extend_from_slice(&source.octets());
+                result.extend_from_slice(&destination.octets());
+                result.extend_from_slice(&[0, 6]);
+                result.extend_from_slice(&(length as u16).to_be_bytes());
             }
             (IpAddr::V6(source), IpAddr::V6(destination)) => {
-                let mut pseudo_header = Vec::<u8>::with_capacity(60 + packet.len());
-                pseudo_header.append(&mut source.octets().to_vec());
-                pseudo_header.append(&mut destination.octets().to_vec());
-                pseudo_header.append(&mut (packet.len() as u32).to_be_bytes().to_vec());
-                pseudo_header.append(&mut vec![0; 3]);
-                pseudo_header.push(6);
-                pseudo_header.append(&mut packet);
-                self.checksum = checksum(pseudo_header);
-                Ok(())
+                result.extend_from_slice(&source.octets());
+                result.extend_from_slice(&destination.octets());
+                result.extend_from_slice(&(length as u32).to_be_bytes());
+                result.extend_from_slice(&[0, 0, 0, 6]);
             }
-            _ => Err(())
+            _ => return None
         }
+        result.append(&mut header);
+        result.extend_from_slice(&self.payload);
+        Some(result)
     }
-    pub fn clone_header(&self) -> Self {
-        Self {
-            payload: Vec::new(),
-            ..self.clone()
+    /// Adds or replaces TCP-AO option with MAC computed by `mac` with `key`, then recalculates checksum
+    /// `sne` is Sequence Number Extension, i.e. count of sequence number wraps of this direction
+    /// MAC is left zeroed and checksum isn't recalculated if `source_ip` and `destination_ip` are not same version
+    /// Returns `Err(OptionSpaceExceeded)` if option doesn't fit in TCP option space together with other options, segment remains untouched in that case
+    pub fn sign_ao(&mut self, key: &TcpAoKey, rnext_key_id: u8, sne: u32, source_ip: IpAddr, destination_ip: IpAddr, mac: &impl TcpAoMac) -> Result<(), OptionSpaceExceeded> {
+        let option = TcpAoOption {key_id: key.send_id, rnext_key_id, mac: vec![0; key.mac_length]}.into_option()?;
+        let others_len: usize = self.options.iter()
+            .filter(|option| option.kind != 29)
+            .map(TcpOption::padded_len)
+            .sum();
+        if others_len + option.padded_len() > 40 {return Err(OptionSpaceExceeded);}
+        match self.options.iter_mut().find(|option| option.kind == 29) {
+            Some(existing) => *existing = option,
+            None => self.options.push(option)
+        }
+        if let Some(input) = self.ao_mac_input(source_ip, destination_ip, sne, key.include_options) {
+            let mut code = mac.compute_mac(key.send_id, &input);
+            code.resize(key.mac_length, 0);
+            if let Some(option) = self.options.iter_mut().find(|option| option.kind == 29) {
+                option.data[2..].copy_from_slice(&code);
+            }
         }
+        let _ = self.recalculate_checksum(source_ip, destination_ip);
+        Ok(())
     }
-}
-impl Serializable for TcpSegment {
-    fn serialize(mut self) -> Vec<u8> {
+    /// Checks TCP-AO MAC of received segment with `key`, computed by `mac`
+    /// Returns `false` if segment has no TCP-AO option, its key ID isn't `recv_id` of `key` or MAC doesn't match
+    pub fn verify_ao(&self, key: &TcpAoKey, sne: u32, source_ip: IpAddr, destination_ip: IpAddr, mac: &impl TcpAoMac) -> bool {
+        let Some(option) = self.ao_option() else {return false;};
+        if option.key_id != key.recv_id || option.mac.len() != key.mac_length {return false;}
+        let Some(input) = self.ao_mac_input(source_ip, destination_ip, sne, key.include_options) else {return false;};
+        let mut code = mac.compute_mac(key.recv_id, &input);
+        code.resize(key.mac_length, 0);
+        code == option.mac
+    }
+    /// Checks raw TCP segment `bytes` for problems: truncation, wrong data offset, illegal flags combinations and malformed options
+    /// Checksum is not checked, since it requires IP addresses, use `validate_with_addresses()` for it
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 20 {
+            issues.push(ValidationIssue::Truncated {layer: "tcp", needed: 20, actual: bytes.len()});
+            return issues;
+        }
+        let data_offset = (bytes[12] >> 4) as usize * 4;
+        if data_offset < 20 {
+            issues.push(ValidationIssue::InvalidField {layer: "tcp", field: "data_offset", value: (bytes[12] >> 4) as u32});
+        }
+        else if data_offset > bytes.len() {
+            issues.push(ValidationIssue::Truncated {layer: "tcp", needed: data_offset, actual: bytes.len()});
+        }
+        else {
+            validate_options("tcp", &bytes[20..data_offset], 20, &mut issues);
+        }
+        if bytes[12] & 0x0E != 0 {
+            issues.push(ValidationIssue::InvalidField {layer: "tcp", field: "reserved", value: ((bytes[12] & 0x0E) >> 1) as u32});
+        }
+        let (syn, fin, rst) = (bytes[13] & 2 != 0, bytes[13] & 1 != 0, bytes[13] & 4 != 0);
+        if syn && fin {
+            issues.push(ValidationIssue::IllegalFlags {layer: "tcp", flags: "SYN+FIN"});
+        }
+        if syn && rst {
+            issues.push(ValidationIssue::IllegalFlags {layer: "tcp", flags: "SYN+RST"});
+        }
+        if fin && rst {
+            issues.push(ValidationIssue::IllegalFlags {layer: "tcp", flags: "FIN+RST"});
+        }
+        if bytes[13] == 0 && bytes[12] & 1 == 0 {
+            issues.push(ValidationIssue::IllegalFlags {layer: "tcp", flags: "NONE"});
+        }
+        issues
+    }
+    /// The same as `validate()`, but also checks checksum using `source_ip` and `destination_ip` from IP packet
+    pub fn validate_with_addresses(bytes: &[u8], source_ip: IpAddr, destination_ip: IpAddr) -> Vec<ValidationIssue> {
+        let mut issues = Self::validate(bytes);
+        if bytes.len() < 20 {return issues;}
+        let declared = u16::from_be_bytes([bytes[16], bytes[17]]);
+        if let Some(calculated) = transport_checksum(source_ip, destination_ip, 6, &[&bytes[..16], &[0, 0], &bytes[18..]]) && calculated != declared {
+            issues.push(ValidationIssue::ChecksumMismatch {layer: "tcp", declared, calculated});
+        }
+        issues
+    }
+    /// Serializes header with options, but without payload
+    fn serialize_header(&self) -> Vec<u8> {
         let mut packet = vec![0u8; 20];
         packet[0..2].copy_from_slice(&self.source.to_be_bytes());
         packet[2..4].copy_from_slice(&self.destination.to_be_bytes());
         packet[4..8].copy_from_slice(&self.sequence_number.to_be_bytes());
         packet[8..12].copy_from_slice(&self.acknowledgement_number.to_be_bytes());
-        let flags = self.flags.serialize();
+        let flags = self.flags.clone().serialize();
         packet[12] = flags[0];
         packet[13] = flags[1];
         packet[14..16].copy_from_slice(&self.window_size.to_be_bytes());
-        packet[16..18].copy_from_slice(&self.checksum.to_be_bytes());
+        if !self.checksum_offloaded {
+            packet[16..18].copy_from_slice(&self.checksum.to_be_bytes());
+        }
         packet[18..20].copy_from_slice(&self.urgent_pointer.to_be_bytes());
-        for option in self.options {
+        for option in self.options.clone() {
             let mut option_bytes = option.serialize();
-            let option_padding = 4 - option_bytes.len() % 4;
+            let option_padding = (4 - option_bytes.len() % 4) % 4;
             if option_padding != 0 {
                 option_bytes.append(&mut vec![1; option_padding]);
             }
             packet.append(&mut option_bytes);
         }
-        let padding = 4 - packet.len() % 4;
+        let padding = (4 - packet.len() % 4) % 4;
         if padding != 0 {
             packet.append(&mut vec![0; padding]);
         }
         packet[12] |= (packet.len() as u8 / 4) << 4;
+        packet
+    }
+}
+impl Default for TcpSegment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for TcpSegment {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut packet = self.serialize_header();
         packet.append(&mut self.payload);
         packet
     }
 }
 impl Deserializable for TcpSegment {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
-        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for TcpSegment {
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
+        let mut reader = ByteReader::new(bytes);
         let mut packet = Self::new();
-        packet.source = u16::from_be_bytes([bytes[0], bytes[1]]);
-        packet.destination = u16::from_be_bytes([bytes[2], bytes[3]]);
-        packet.sequence_number = u32::from_be_bytes(bytes[4..8].as_array().unwrap().clone());
-        packet.acknowledgement_number = u32::from_be_bytes(bytes[8..12].as_array().unwrap().clone());
-        let data_offset = (bytes[12] as usize >> 4) * 4;
-        packet.flags = TcpFlags::deserialize(&bytes[12..14])?;
-        packet.window_size = u16::from_be_bytes([bytes[14], bytes[15]]);
-        packet.checksum = u16::from_be_bytes([bytes[16], bytes[17]]);
-        packet.urgent_pointer = u16::from_be_bytes([bytes[18], bytes[19]]);
-        if data_offset > 20 {
-            let mut i = 20usize;
-            while i < data_offset as usize {
-                if bytes[i] == 0 {break;}
-                if bytes[i] == 1 {
-                    i += 1;
-                    continue;
-                }
-                packet.options.push(TcpOption::deserialize(&bytes[i..])?);
-                i += bytes[i + 1] as usize;
-            }
+        packet.source = reader.read_u16()?;
+        packet.destination = reader.read_u16()?;
+        packet.sequence_number = reader.read_u32()?;
+        packet.acknowledgement_number = reader.read_u32()?;
+        let data_offset = (reader.peek_u8()? as usize >> 4) * 4;
+        packet.flags = TcpFlags::deserialize(reader.read_slice(2)?)?;
+        packet.window_size = reader.read_u16()?;
+        packet.checksum = reader.read_u16()?;
+        packet.urgent_pointer = reader.read_u16()?;
+        let options = reader.read_slice(data_offset.checked_sub(20).ok_or(DeserializeError::WrongDataLength)?)?;
+        for option in TcpOptionIter::new(options) {
+            packet.options.push(option?.into_owned());
         }
-        packet.payload = bytes[data_offset as usize..].to_vec();
-        Ok(packet)
+        Ok(PacketRef {header: packet, payload: reader.read_rest()})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+impl Checksummed for TcpSegment {
+    /// Computes checksum with pseudo-header from `context` addresses, even if `checksum_offloaded` is set
+    fn compute_checksum(&self, context: &ChecksumContext) -> Option<u16> {
+        let (source, destination) = context.addresses?;
+        let mut header = self.serialize_header();
+        header[16..18].fill(0);
+        transport_checksum(source, destination, 6, &[&header, &self.payload])
+    }
+    fn stored_checksum(&self) -> Option<u16> {
+        Some(self.checksum)
+    }
+    fn set_checksum(&mut self, checksum: u16) {
+        self.checksum = checksum;
+    }
+    fn clear_checksum(&mut self) {
+        self.checksum = 0;
+    }
+}
+impl IpPayload for TcpSegment {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Tcp
     }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use core::net::IpAddr;
-use crate::util::{Serializable, Deserializable, DeserializeError, checksum};
+use crate::l3::IpProtocol;
+use crate::util::{ByteReader, Serializable, Deserializable, DeserializeError, DeserializableRef, PacketRef, IpPayload, Checksummed, ChecksumContext, ValidationIssue, transport_checksum};
 
 /// Struct for ordinary TCP Packet
 /// You can construct it from scratch with `UdpPacket::new()` and consistently editing
@@ -12,6 +13,9 @@ pub struct UdpDatagram {
     /// Destination Port
     pub destination: u16,
     pub checksum: Option<u16>,
+    /// Checksum is offloaded to NIC, so it is serialized as zero and `recalculate_checksum()` keeps it `None`
+    /// Set it to match datagrams captured on sending host with checksum offload enabled
+    pub checksum_offloaded: bool,
     pub payload: Vec<u8>
 }
 impl UdpDatagram {
@@ -21,41 +25,27 @@ impl UdpDatagram {
             source: 0,
             destination: 0,
             checksum: None,
+            checksum_offloaded: false,
             payload: Vec::new()
         }
     }
     /// Recalculates `checksum` field in `TcpPacket`
     /// Note that to calculate TCP Checksum you also need source ip and destination ip from IP packet
     /// Returns `Err(())` only when `source_ip` and `destination_ip` not same version, e.g. IPv4 and IPv6
+    /// If `checksum_offloaded` is set, `checksum` is just reset to `None`
     pub fn recalculate_checksum(&mut self, source_ip: IpAddr, destination_ip: IpAddr) -> Result<(), ()> {
-        let mut packet = self.clone_header().serialize();
-        packet[6] = 0;
-        packet[7] = 0;
-        match (source_ip, destination_ip) {
-            (IpAddr::V4(source), IpAddr::V4(destination)) => {
-                let mut pseudo_header = Vec::<u8>::with_capacity(8 + packet.len());
-                pseudo_header.append(&mut source.octets().to_vec());
-                pseudo_header.append(&mut destination.octets().to_vec());
-                pseudo_header.push(0);
-                pseudo_header.push(17);
-                pseudo_header.append(&mut (packet.len() as u16).to_be_bytes().to_vec());
-                pseudo_header.append(&mut packet);
-                self.checksum = Some(checksum(pseudo_header));
-                Ok(())
-            }
-            (IpAddr::V6(source), IpAddr::V6(destination)) => {
-                let mut pseudo_header = Vec::<u8>::with_capacity(48 + packet.len());
-                pseudo_header.append(&mut source.octets().to_vec());
-                pseudo_header.append(&mut destination.octets().to_vec());
-                pseudo_header.append(&mut (packet.len() as u32).to_be_bytes().to_vec());
-                pseudo_header.append(&mut vec![0; 3]);
-                pseudo_header.push(17);
-                pseudo_header.append(&mut packet);
-                self.checksum = Some(checksum(pseudo_header));
-                Ok(())
-            }
-            _ => Err(())
+        if self.checksum_offloaded {
+            self.checksum = None;
+            return Ok(());
         }
+        let header = self.serialize_header(0);
+        let checksum = transport_checksum(source_ip, destination_ip, 17, &[&header, &self.payload]).ok_or(())?;
+        self.checksum = Some(if checksum == 0 {0xFFFF} else {checksum});
+        Ok(())
+    }
+    /// Returns length of serialized datagram, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        8 + self.payload.len()
     }
     pub fn clone_header(&self) -> Self {
         Self {
@@ -63,28 +53,128 @@ impl UdpDatagram {
             ..self.clone()
         }
     }
+    /// Marks checksum as offloaded to NIC, so datagram is serialized with zero checksum
+    pub fn with_checksum_offload(mut self) -> Self {
+        self.checksum_offloaded = true;
+        self.checksum = None;
+        self
+    }
+    /// Splits payload into datagrams of `segment_size` bytes, the last one can be shorter, as UDP Generic Segmentation Offload does in kernel
+    /// Each datagram has the same ports, its length and checksum are calculated for its part with `source_ip` and `destination_ip`
+    /// Datagram with payload of at most `segment_size` bytes, or `segment_size` of zero, gives one datagram
+    /// Returns `None` when `source_ip` and `destination_ip` are not same version, e.g. IPv4 and IPv6
+    pub fn segment(&self, segment_size: usize, source_ip: IpAddr, destination_ip: IpAddr) -> Option<Vec<Self>> {
+        let chunks: Vec<&[u8]> = if segment_size == 0 || self.payload.len() <= segment_size {vec![&self.payload]} else {self.payload.chunks(segment_size).collect()};
+        chunks.into_iter().map(|chunk| {
+            let mut datagram = self.clone_header();
+            datagram.payload = chunk.to_vec();
+            datagram.recalculate_checksum(source_ip, destination_ip).ok()?;
+            Some(datagram)
+        }).collect()
+    }
+    /// Checks raw UDP datagram `bytes` for problems: truncation and `length` field mismatch
+    /// Checksum is not checked, since it requires IP addresses, use `validate_with_addresses()` for it
+    pub fn validate(bytes: &[u8]) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if bytes.len() < 8 {
+            issues.push(ValidationIssue::Truncated {layer: "udp", needed: 8, actual: bytes.len()});
+            return issues;
+        }
+        let length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        if length != bytes.len() {
+            issues.push(ValidationIssue::LengthMismatch {layer: "udp", field: "length", expected: bytes.len(), actual: length});
+        }
+        issues
+    }
+    /// The same as `validate()`, but also checks checksum using `source_ip` and `destination_ip` from IP packet
+    /// Note that zero checksum means no checksum for IPv4, but is illegal for IPv6
+    pub fn validate_with_addresses(bytes: &[u8], source_ip: IpAddr, destination_ip: IpAddr) -> Vec<ValidationIssue> {
+        let mut issues = Self::validate(bytes);
+        if bytes.len() < 8 {return issues;}
+        let declared = u16::from_be_bytes([bytes[6], bytes[7]]);
+        if declared == 0 {
+            if source_ip.is_ipv6() {
+                issues.push(ValidationIssue::InvalidField {layer: "udp", field: "checksum", value: 0});
+            }
+            return issues;
+        }
+        if let Some(calculated) = transport_checksum(source_ip, destination_ip, 17, &[&bytes[..6], &[0, 0], &bytes[8..]]) {
+            let calculated = if calculated == 0 {0xFFFF} else {calculated};
+            if calculated != declared {
+                issues.push(ValidationIssue::ChecksumMismatch {layer: "udp", declared, calculated});
+            }
+        }
+        issues
+    }
+    /// Serializes `8 bytes` header with `checksum`, length field accounts for payload
+    fn serialize_header(&self, checksum: u16) -> [u8; 8] {
+        let mut header = [0u8; 8];
+        header[0..2].copy_from_slice(&self.source.to_be_bytes());
+        header[2..4].copy_from_slice(&self.destination.to_be_bytes());
+        header[4..6].copy_from_slice(&(8 + self.payload.len() as u16).to_be_bytes());
+        header[6..8].copy_from_slice(&checksum.to_be_bytes());
+        header
+    }
+}
+impl Default for UdpDatagram {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Serializable for UdpDatagram {
     fn serialize(mut self) -> Vec<u8> {
-        let mut result = [
-            self.source.to_be_bytes(),
-            self.destination.to_be_bytes(),
-            (8 + self.payload.len() as u16).to_be_bytes(),
-            self.checksum.unwrap_or(0).to_be_bytes()
-        ].concat();
+        let mut result = self.serialize_header(self.checksum.filter(|_| !self.checksum_offloaded).unwrap_or(0)).to_vec();
         result.append(&mut self.payload);
         result
     }
 }
 impl Deserializable for UdpDatagram {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
-        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
-        let checksum = u16::from_be_bytes([bytes[6], bytes[7]]);
-        Ok(Self {
-            source: u16::from_be_bytes([bytes[0], bytes[1]]),
-            destination: u16::from_be_bytes([bytes[2], bytes[3]]),
+        Ok(Self::deserialize_ref(bytes)?.into_owned())
+    }
+}
+impl DeserializableRef for UdpDatagram {
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError> {
+        let mut reader = ByteReader::new(bytes);
+        let source = reader.read_u16()?;
+        let destination = reader.read_u16()?;
+        reader.skip(2)?;
+        let checksum = reader.read_u16()?;
+        let header = Self {
+            source,
+            destination,
             checksum: if checksum == 0 {None} else {Some(checksum)},
-            payload: bytes[8..].to_vec()
-        })
+            checksum_offloaded: false,
+            payload: Vec::new()
+        };
+        Ok(PacketRef {header, payload: reader.read_rest()})
+    }
+    fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+impl Checksummed for UdpDatagram {
+    /// Computes checksum with pseudo-header from `context` addresses, computed zero is transmitted as `0xFFFF`
+    fn compute_checksum(&self, context: &ChecksumContext) -> Option<u16> {
+        let (source, destination) = context.addresses?;
+        let header = self.serialize_header(0);
+        let checksum = transport_checksum(source, destination, 17, &[&header, &self.payload])?;
+        Some(if checksum == 0 {0xFFFF} else {checksum})
+    }
+    /// Returns `None` if datagram has no checksum, which is allowed over IPv4 only
+    fn stored_checksum(&self) -> Option<u16> {
+        self.checksum
+    }
+    fn set_checksum(&mut self, checksum: u16) {
+        self.checksum = Some(checksum);
+    }
+    fn clear_checksum(&mut self) {
+        self.checksum = None;
+    }
+}
+impl IpPayload for UdpDatagram {
+    fn ip_protocol(&self) -> IpProtocol {
+        IpProtocol::Udp
     }
 }
\ No newline at end of file
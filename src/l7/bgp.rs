@@ -0,0 +1,483 @@
+use core::net::Ipv4Addr;
+use crate::util::{Serializable, Deserializable, DeserializeError, require_stream_length};
+
+/// IPv4 prefix as it is encoded in BGP NLRI and Withdrawn Routes fields: `1 byte` length in bits followed by minimal count of address bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ipv4Prefix {
+    pub address: Ipv4Addr,
+    /// Prefix length in bits, from 0 to 32
+    pub length: u8
+}
+impl Ipv4Prefix {
+    /// Returns length of serialized prefix, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        1 + (self.length as usize).div_ceil(8).min(4)
+    }
+}
+impl Serializable for Ipv4Prefix {
+    /// Converts prefix to bytes, at most 4 address bytes are written even if `length` exceeds 32
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![self.length];
+        result.extend_from_slice(&self.address.octets()[..(self.length as usize).div_ceil(8).min(4)]);
+        result
+    }
+}
+impl Deserializable for Ipv4Prefix {
+    /// Constructs `Ipv4Prefix` from bytes
+    /// Note that this method is know where prefix ends
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0] > 32 {return Err(DeserializeError::WrongData);}
+        let octets = (bytes[0] as usize).div_ceil(8);
+        if bytes.len() < 1 + octets {return Err(DeserializeError::WrongDataLength);}
+        let mut address = [0u8; 4];
+        address[..octets].copy_from_slice(&bytes[1..1 + octets]);
+        Ok(Self {
+            address: Ipv4Addr::from_octets(address),
+            length: bytes[0]
+        })
+    }
+}
+
+fn deserialize_prefixes(bytes: &[u8]) -> Result<Vec<Ipv4Prefix>, DeserializeError> {
+    let mut prefixes = Vec::new();
+    let mut i = 0usize;
+    while i < bytes.len() {
+        let prefix = Ipv4Prefix::deserialize(&bytes[i..])?;
+        i += 1 + (prefix.length as usize).div_ceil(8);
+        prefixes.push(prefix);
+    }
+    Ok(prefixes)
+}
+
+/// AS_PATH segment
+#[derive(Debug, Clone)]
+pub struct AsPathSegment {
+    /// Segment type, 1 for AS_SET, 2 for AS_SEQUENCE
+    pub segment_type: u8,
+    pub asns: Vec<u32>
+}
+
+/// Typed value of BGP Path Attribute
+#[derive(Debug, Clone)]
+pub enum PathAttributeValue {
+    /// Type 1 - 0 for IGP, 1 for EGP, 2 for INCOMPLETE
+    Origin(u8),
+    /// Type 2
+    AsPath(Vec<AsPathSegment>),
+    /// Type 3
+    NextHop(Ipv4Addr),
+    /// Type 4 - MULTI_EXIT_DISC
+    MultiExitDisc(u32),
+    /// Type 5
+    LocalPref(u32),
+    /// Type 6
+    AtomicAggregate,
+    /// Type 7
+    Aggregator {
+        asn: u32,
+        address: Ipv4Addr
+    },
+    /// Type 8 - list of 32 bit communities
+    Communities(Vec<u32>),
+    /// Any other attribute presented just with raw value
+    Unknown {
+        type_code: u8,
+        data: Vec<u8>
+    }
+}
+impl PathAttributeValue {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Origin(_) => 1,
+            Self::AsPath(_) => 2,
+            Self::NextHop(_) => 3,
+            Self::MultiExitDisc(_) => 4,
+            Self::LocalPref(_) => 5,
+            Self::AtomicAggregate => 6,
+            Self::Aggregator {..} => 7,
+            Self::Communities(_) => 8,
+            Self::Unknown {type_code, ..} => *type_code
+        }
+    }
+}
+
+/// BGP Path Attribute of UPDATE message
+/// Note that AS numbers are `4 bytes` when `four_octet_as` is `true`(RFC 6793) and `2 bytes` otherwise
+#[derive(Debug, Clone)]
+pub struct PathAttribute {
+    /// Attribute flags: `optional`(0x80), `transitive`(0x40), `partial`(0x20) and `extended length`(0x10)
+    /// Note that `extended length` flag is set automatically on serialization when value is longer than 255 bytes
+    pub flags: u8,
+    pub value: PathAttributeValue
+}
+impl PathAttribute {
+    /// Constructs `PathAttribute` from bytes
+    /// Returns parsed attribute and its length in bytes
+    pub fn deserialize_with(bytes: &[u8], four_octet_as: bool) -> Result<(Self, usize), DeserializeError> {
+        if bytes.len() < 3 {return Err(DeserializeError::WrongDataLength);}
+        let flags = bytes[0];
+        let (length, header_len) = if flags & 0x10 != 0 {
+            if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+            (u16::from_be_bytes([bytes[2], bytes[3]]) as usize, 4)
+        }
+        else {
+            (bytes[2] as usize, 3)
+        };
+        if bytes.len() < header_len + length {return Err(DeserializeError::WrongDataLength);}
+        let data = &bytes[header_len..header_len + length];
+        let asn_len = if four_octet_as {4} else {2};
+        let read_asn = |bytes: &[u8]| if four_octet_as {
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }
+        else {
+            u16::from_be_bytes([bytes[0], bytes[1]]) as u32
+        };
+        let value = match bytes[1] {
+            1 if length == 1 => PathAttributeValue::Origin(data[0]),
+            2 => {
+                let mut segments = Vec::new();
+                let mut i = 0usize;
+                while i < data.len() {
+                    if data.len() < i + 2 {return Err(DeserializeError::WrongDataLength);}
+                    let count = data[i + 1] as usize;
+                    if data.len() < i + 2 + count * asn_len {return Err(DeserializeError::WrongDataLength);}
+                    segments.push(AsPathSegment {
+                        segment_type: data[i],
+                        asns: data[i + 2..i + 2 + count * asn_len].chunks(asn_len).map(read_asn).collect()
+                    });
+                    i += 2 + count * asn_len;
+                }
+                PathAttributeValue::AsPath(segments)
+            }
+            3 if length == 4 => PathAttributeValue::NextHop(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            4 if length == 4 => PathAttributeValue::MultiExitDisc(u32::from_be_bytes([data[0], data[1], data[2], data[3]])),
+            5 if length == 4 => PathAttributeValue::LocalPref(u32::from_be_bytes([data[0], data[1], data[2], data[3]])),
+            6 if length == 0 => PathAttributeValue::AtomicAggregate,
+            7 if length == asn_len + 4 => PathAttributeValue::Aggregator {
+                asn: read_asn(data),
+                address: Ipv4Addr::new(data[asn_len], data[asn_len + 1], data[asn_len + 2], data[asn_len + 3])
+            },
+            8 if length % 4 == 0 => PathAttributeValue::Communities(
+                data.chunks(4).map(|community| u32::from_be_bytes([community[0], community[1], community[2], community[3]])).collect()
+            ),
+            type_code => PathAttributeValue::Unknown {type_code, data: data.to_vec()}
+        };
+        Ok((Self {flags, value}, header_len + length))
+    }
+    /// Returns length of attribute serialized with `serialize_with()`, computed without serializing it
+    pub fn wire_len_with(&self, four_octet_as: bool) -> usize {
+        let asn_len = if four_octet_as {4} else {2};
+        let data_len = match &self.value {
+            PathAttributeValue::Origin(_) => 1,
+            PathAttributeValue::AsPath(segments) => segments.iter().map(|segment| 2 + segment.asns.len() * asn_len).sum(),
+            PathAttributeValue::NextHop(_) |
+            PathAttributeValue::MultiExitDisc(_) |
+            PathAttributeValue::LocalPref(_) => 4,
+            PathAttributeValue::AtomicAggregate => 0,
+            PathAttributeValue::Aggregator {..} => asn_len + 4,
+            PathAttributeValue::Communities(communities) => communities.len() * 4,
+            PathAttributeValue::Unknown {data, ..} => data.len()
+        };
+        if data_len > 255 {4 + data_len} else {3 + data_len}
+    }
+    /// Converts attribute to bytes with AS numbers of `4 bytes` when `four_octet_as` is `true` and `2 bytes` otherwise
+    pub fn serialize_with(self, four_octet_as: bool) -> Vec<u8> {
+        let type_code = self.value.get_type();
+        let write_asn = |data: &mut Vec<u8>, asn: u32| if four_octet_as {
+            data.extend_from_slice(&asn.to_be_bytes());
+        }
+        else {
+            data.extend_from_slice(&(asn as u16).to_be_bytes());
+        };
+        let mut data = Vec::new();
+        match self.value {
+            PathAttributeValue::Origin(origin) => data.push(origin),
+            PathAttributeValue::AsPath(segments) => {
+                for segment in segments {
+                    data.push(segment.segment_type);
+                    data.push(segment.asns.len() as u8);
+                    for asn in segment.asns {
+                        write_asn(&mut data, asn);
+                    }
+                }
+            }
+            PathAttributeValue::NextHop(address) => data.extend_from_slice(&address.octets()),
+            PathAttributeValue::MultiExitDisc(value) |
+            PathAttributeValue::LocalPref(value) => data.extend_from_slice(&value.to_be_bytes()),
+            PathAttributeValue::AtomicAggregate => {}
+            PathAttributeValue::Aggregator {asn, address} => {
+                write_asn(&mut data, asn);
+                data.extend_from_slice(&address.octets());
+            }
+            PathAttributeValue::Communities(communities) => {
+                for community in communities {
+                    data.extend_from_slice(&community.to_be_bytes());
+                }
+            }
+            PathAttributeValue::Unknown {type_code: _, data: mut value} => data.append(&mut value)
+        }
+        let mut result = Vec::with_capacity(data.len() + 4);
+        if data.len() > 255 {
+            result.push(self.flags | 0x10);
+            result.push(type_code);
+            result.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        }
+        else {
+            result.push(self.flags & !0x10);
+            result.push(type_code);
+            result.push(data.len() as u8);
+        }
+        result.append(&mut data);
+        result
+    }
+}
+
+/// BGP OPEN message Optional Parameter, i.e. type 2 for Capabilities
+#[derive(Debug, Clone)]
+pub struct OptionalParameter {
+    pub kind: u8,
+    pub data: Vec<u8>
+}
+
+/// BGP Message
+#[derive(Debug, Clone)]
+pub enum BgpMessage {
+    /// Type 1 - opens BGP session
+    Open {
+        /// BGP version, 4 for now
+        version: u8,
+        /// Sender AS number, 23456(AS_TRANS) if real AS number doesn't fit in 2 bytes
+        my_as: u16,
+        /// Seconds before peer is considered dead
+        hold_time: u16,
+        bgp_identifier: Ipv4Addr,
+        optional_parameters: Vec<OptionalParameter>
+    },
+    /// Type 2 - advertises and withdraws routes
+    Update {
+        withdrawn_routes: Vec<Ipv4Prefix>,
+        path_attributes: Vec<PathAttribute>,
+        /// Network Layer Reachability Information - advertised prefixes
+        nlri: Vec<Ipv4Prefix>
+    },
+    /// Type 3 - reports error and closes session
+    Notification {
+        error_code: u8,
+        error_subcode: u8,
+        data: Vec<u8>
+    },
+    /// Type 4 - keeps session alive
+    Keepalive,
+    /// Type 5 - requests routes re-advertisement, RFC 2918
+    RouteRefresh {
+        /// Address Family Identifier
+        afi: u16,
+        /// Subsequent Address Family Identifier
+        safi: u8
+    }
+}
+impl BgpMessage {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Open {..} => 1,
+            Self::Update {..} => 2,
+            Self::Notification {..} => 3,
+            Self::Keepalive => 4,
+            Self::RouteRefresh {..} => 5
+        }
+    }
+    /// Returns capabilities(`code`, `value`) advertised in Capabilities optional parameters of OPEN message
+    pub fn capabilities(&self) -> Vec<(u8, Vec<u8>)> {
+        let Self::Open {optional_parameters, ..} = self else {return Vec::new();};
+        let mut capabilities = Vec::new();
+        for parameter in optional_parameters.iter().filter(|parameter| parameter.kind == 2) {
+            let mut i = 0usize;
+            while i + 2 <= parameter.data.len() {
+                let end = (i + 2 + parameter.data[i + 1] as usize).min(parameter.data.len());
+                capabilities.push((parameter.data[i], parameter.data[i + 2..end].to_vec()));
+                i = end;
+            }
+        }
+        capabilities
+    }
+    /// Constructs `BgpMessage` from bytes, with AS numbers in UPDATE of `4 bytes` when `four_octet_as` is `true` and `2 bytes` otherwise
+    /// Note that this method is know where message ends from its header, so bytes after it are ignored
+    pub fn deserialize_with(bytes: &[u8], four_octet_as: bool) -> Result<Self, DeserializeError> {
+        if bytes.len() < 19 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0..16].iter().any(|byte| *byte != 0xFF) {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+        if !(19..=4096).contains(&length) {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        let body = &bytes[19..length];
+        match bytes[18] {
+            1 => {
+                if body.len() < 10 {return Err(DeserializeError::WrongDataLength);}
+                let parameters_len = body[9] as usize;
+                if body.len() < 10 + parameters_len {return Err(DeserializeError::WrongDataLength);}
+                let parameters_end = 10 + parameters_len;
+                let mut optional_parameters = Vec::new();
+                let mut i = 10usize;
+                while i < parameters_end {
+                    if parameters_end < i + 2 {return Err(DeserializeError::WrongDataLength);}
+                    let end = i + 2 + body[i + 1] as usize;
+                    if parameters_end < end {return Err(DeserializeError::WrongDataLength);}
+                    optional_parameters.push(OptionalParameter {
+                        kind: body[i],
+                        data: body[i + 2..end].to_vec()
+                    });
+                    i = end;
+                }
+                Ok(Self::Open {
+                    version: body[0],
+                    my_as: u16::from_be_bytes([body[1], body[2]]),
+                    hold_time: u16::from_be_bytes([body[3], body[4]]),
+                    bgp_identifier: Ipv4Addr::new(body[5], body[6], body[7], body[8]),
+                    optional_parameters
+                })
+            }
+            2 => {
+                if body.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+                let withdrawn_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                if body.len() < 4 + withdrawn_len {return Err(DeserializeError::WrongDataLength);}
+                let withdrawn_routes = deserialize_prefixes(&body[2..2 + withdrawn_len])?;
+                let attributes_start = 4 + withdrawn_len;
+                let attributes_len = u16::from_be_bytes([body[attributes_start - 2], body[attributes_start - 1]]) as usize;
+                if body.len() < attributes_start + attributes_len {return Err(DeserializeError::WrongDataLength);}
+                let attributes = &body[attributes_start..attributes_start + attributes_len];
+                let mut path_attributes = Vec::new();
+                let mut i = 0usize;
+                while i < attributes.len() {
+                    let (attribute, length) = PathAttribute::deserialize_with(&attributes[i..], four_octet_as)?;
+                    path_attributes.push(attribute);
+                    i += length;
+                }
+                Ok(Self::Update {
+                    withdrawn_routes,
+                    path_attributes,
+                    nlri: deserialize_prefixes(&body[attributes_start + attributes_len..])?
+                })
+            }
+            3 => {
+                if body.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+                Ok(Self::Notification {
+                    error_code: body[0],
+                    error_subcode: body[1],
+                    data: body[2..].to_vec()
+                })
+            }
+            4 => Ok(Self::Keepalive),
+            5 => {
+                if body.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+                Ok(Self::RouteRefresh {
+                    afi: u16::from_be_bytes([body[0], body[1]]),
+                    safi: body[3]
+                })
+            }
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+    /// Returns length of message serialized with `serialize_with()`, computed without serializing it
+    pub fn wire_len_with(&self, four_octet_as: bool) -> usize {
+        19 + match self {
+            Self::Open {optional_parameters, ..} => 10 + optional_parameters.iter().map(|parameter| 2 + parameter.data.len()).sum::<usize>(),
+            Self::Update {withdrawn_routes, path_attributes, nlri} => {
+                4 + withdrawn_routes.iter().chain(nlri).map(Ipv4Prefix::wire_len).sum::<usize>()
+                    + path_attributes.iter().map(|attribute| attribute.wire_len_with(four_octet_as)).sum::<usize>()
+            }
+            Self::Notification {data, ..} => 2 + data.len(),
+            Self::Keepalive => 0,
+            Self::RouteRefresh {..} => 4
+        }
+    }
+    /// Returns length of message serialized with `4 bytes` AS numbers, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.wire_len_with(true)
+    }
+    /// Converts message to bytes with AS numbers in UPDATE of `4 bytes` when `four_octet_as` is `true` and `2 bytes` otherwise
+    pub fn serialize_with(self, four_octet_as: bool) -> Vec<u8> {
+        let mut result = vec![0xFFu8; 19];
+        result[18] = self.get_type();
+        match self {
+            Self::Open {version, my_as, hold_time, bgp_identifier, optional_parameters} => {
+                result.push(version);
+                result.extend_from_slice(&my_as.to_be_bytes());
+                result.extend_from_slice(&hold_time.to_be_bytes());
+                result.extend_from_slice(&bgp_identifier.octets());
+                let mut parameters = Vec::new();
+                for mut parameter in optional_parameters {
+                    parameters.push(parameter.kind);
+                    parameters.push(parameter.data.len() as u8);
+                    parameters.append(&mut parameter.data);
+                }
+                result.push(parameters.len() as u8);
+                result.append(&mut parameters);
+            }
+            Self::Update {withdrawn_routes, path_attributes, nlri} => {
+                let mut withdrawn = Vec::new();
+                for prefix in withdrawn_routes {
+                    withdrawn.append(&mut prefix.serialize());
+                }
+                result.extend_from_slice(&(withdrawn.len() as u16).to_be_bytes());
+                result.append(&mut withdrawn);
+                let mut attributes = Vec::new();
+                for attribute in path_attributes {
+                    attributes.append(&mut attribute.serialize_with(four_octet_as));
+                }
+                result.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+                result.append(&mut attributes);
+                for prefix in nlri {
+                    result.append(&mut prefix.serialize());
+                }
+            }
+            Self::Notification {error_code, error_subcode, mut data} => {
+                result.push(error_code);
+                result.push(error_subcode);
+                result.append(&mut data);
+            }
+            Self::Keepalive => {}
+            Self::RouteRefresh {afi, safi} => {
+                result.extend_from_slice(&afi.to_be_bytes());
+                result.push(0);
+                result.push(safi);
+            }
+        }
+        let length = result.len() as u16;
+        result[16..18].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+    /// Parses the first BGP message of reassembled TCP `stream`, with AS numbers in UPDATE of `4 bytes` when `four_octet_as` is `true` and `2 bytes` otherwise
+    /// Returns message and its length in bytes or `Err(DeserializeError::Incomplete)` if message isn't fully received yet
+    pub fn deserialize_next(stream: &[u8], four_octet_as: bool) -> Result<(Self, usize), DeserializeError> {
+        require_stream_length(stream, 19)?;
+        let length = u16::from_be_bytes([stream[16], stream[17]]) as usize;
+        if length < 19 {return Err(DeserializeError::WrongData);}
+        require_stream_length(stream, length)?;
+        Ok((Self::deserialize_with(&stream[..length], four_octet_as)?, length))
+    }
+    /// Splits reassembled TCP stream into BGP messages, with AS numbers in UPDATE of `4 bytes` when `four_octet_as` is `true` and `2 bytes` otherwise
+    /// Returns parsed messages and count of consumed bytes, so incomplete message at the end of `stream` can be kept until more data arrives
+    pub fn deserialize_stream(stream: &[u8], four_octet_as: bool) -> Result<(Vec<Self>, usize), DeserializeError> {
+        let mut messages = Vec::new();
+        let mut i = 0usize;
+        while stream.len() >= i + 19 {
+            let length = u16::from_be_bytes([stream[i + 16], stream[i + 17]]) as usize;
+            if stream.len() < i + length {break;}
+            messages.push(Self::deserialize_with(&stream[i..], four_octet_as)?);
+            i += length;
+        }
+        Ok((messages, i))
+    }
+}
+impl Serializable for BgpMessage {
+    /// Converts message to bytes with `4 bytes` AS numbers
+    fn serialize(self) -> Vec<u8> {
+        self.serialize_with(true)
+    }
+}
+impl Deserializable for BgpMessage {
+    /// Constructs `BgpMessage` from bytes with `4 bytes` AS numbers, which are used by all modern BGP speakers
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(bytes, true)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,287 @@
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// UDP port of CoAP
+pub const COAP_PORT: u16 = 5683;
+/// UDP port of CoAP over DTLS, such datagrams can be recognized with `dtls::is_dtls()`
+pub const COAPS_PORT: u16 = 5684;
+
+/// Option 3
+pub const COAP_OPTION_URI_HOST: u16 = 3;
+/// Option 4
+pub const COAP_OPTION_ETAG: u16 = 4;
+/// Option 6, used by Observe extension of RFC 7641
+pub const COAP_OPTION_OBSERVE: u16 = 6;
+/// Option 7
+pub const COAP_OPTION_URI_PORT: u16 = 7;
+/// Option 11, repeated for each path segment
+pub const COAP_OPTION_URI_PATH: u16 = 11;
+/// Option 12
+pub const COAP_OPTION_CONTENT_FORMAT: u16 = 12;
+/// Option 14
+pub const COAP_OPTION_MAX_AGE: u16 = 14;
+/// Option 15, repeated for each query parameter
+pub const COAP_OPTION_URI_QUERY: u16 = 15;
+/// Option 17
+pub const COAP_OPTION_ACCEPT: u16 = 17;
+/// Option 23, used by block-wise transfer of RFC 7959
+pub const COAP_OPTION_BLOCK2: u16 = 23;
+/// Option 27
+pub const COAP_OPTION_BLOCK1: u16 = 27;
+
+/// CoAP message type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoapType {
+    Confirmable = 0,
+    NonConfirmable = 1,
+    Acknowledgement = 2,
+    Reset = 3
+}
+impl CoapType {
+    fn from_u8(value: u8) -> Self {
+        match value & 3 {
+            0 => Self::Confirmable,
+            1 => Self::NonConfirmable,
+            2 => Self::Acknowledgement,
+            _ => Self::Reset
+        }
+    }
+}
+
+/// CoAP option, `value` is opaque, string or big-endian unsigned integer without leading zeroes depending on `number`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoapOption {
+    pub number: u16,
+    pub value: Vec<u8>
+}
+impl CoapOption {
+    pub fn new(number: u16, value: Vec<u8>) -> Self {
+        Self {number, value}
+    }
+    /// Constructs option with unsigned integer `value` in the shortest form
+    pub fn uint(number: u16, value: u32) -> Self {
+        let bytes = value.to_be_bytes();
+        let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+        Self {number, value: bytes[skip..].to_vec()}
+    }
+    /// Returns value as unsigned integer, or `None` if it is longer than `4 bytes`
+    pub fn as_uint(&self) -> Option<u32> {
+        if self.value.len() > 4 {return None;}
+        Some(self.value.iter().fold(0u32, |result, byte| result << 8 | *byte as u32))
+    }
+}
+
+/// Writes option delta or length nibble extension
+fn option_nibble(value: usize, extension: &mut Vec<u8>) -> u8 {
+    match value {
+        0..=12 => value as u8,
+        13..=268 => {
+            extension.push((value - 13) as u8);
+            13
+        }
+        _ => {
+            extension.extend_from_slice(&((value - 269) as u16).to_be_bytes());
+            14
+        }
+    }
+}
+
+/// Returns length of extended option delta or length for `value`
+fn option_nibble_len(value: usize) -> usize {
+    match value {
+        0..=12 => 0,
+        13..=268 => 1,
+        _ => 2
+    }
+}
+
+/// Reads extended option delta or length for `nibble` at `*i` and moves `i` after it
+fn read_option_nibble(nibble: u8, bytes: &[u8], i: &mut usize) -> Result<usize, DeserializeError> {
+    match nibble {
+        0..=12 => Ok(nibble as usize),
+        13 => {
+            if bytes.len() <= *i {return Err(DeserializeError::WrongDataLength);}
+            *i += 1;
+            Ok(bytes[*i - 1] as usize + 13)
+        }
+        14 => {
+            if bytes.len() < *i + 2 {return Err(DeserializeError::WrongDataLength);}
+            *i += 2;
+            Ok(u16::from_be_bytes([bytes[*i - 2], bytes[*i - 1]]) as usize + 269)
+        }
+        _ => Err(DeserializeError::WrongData)
+    }
+}
+
+/// Struct for CoAP message, as described in RFC 7252
+/// `code` is `class.detail` packed into a byte, i.e. 0.01 for GET is 0x01, 2.05 for Content is 0x45, 4.04 for Not Found is 0x84
+/// Options are serialized in order of their numbers, options with the same number keep their relative order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoapMessage {
+    /// Always 1
+    pub version: u8,
+    pub message_type: CoapType,
+    pub code: u8,
+    /// Identifier for duplicate detection and matching acknowledgements to confirmable messages
+    pub message_id: u16,
+    /// Identifier for matching responses to requests, up to `8 bytes`
+    pub token: Vec<u8>,
+    pub options: Vec<CoapOption>,
+    pub payload: Vec<u8>
+}
+impl CoapMessage {
+    /// Constructs an empty confirmable message, i.e. CoAP ping
+    pub fn new() -> Self {
+        Self {
+            version: 1,
+            message_type: CoapType::Confirmable,
+            code: 0,
+            message_id: 0,
+            token: Vec::new(),
+            options: Vec::new(),
+            payload: Vec::new()
+        }
+    }
+    /// Constructs confirmable request with method `code` to `path`, which is split into Uri-Path options by '/'
+    pub fn request(code: u8, message_id: u16, token: Vec<u8>, path: &str) -> Self {
+        let mut message = Self {code, message_id, token, ..Self::new()};
+        message.set_uri_path(path);
+        message
+    }
+    /// Constructs piggybacked response to confirmable `request`, i.e. acknowledgement with the same message ID and token
+    pub fn response_to(request: &CoapMessage, code: u8, payload: Vec<u8>) -> Self {
+        Self {
+            message_type: if request.message_type == CoapType::Confirmable {CoapType::Acknowledgement} else {CoapType::NonConfirmable},
+            code,
+            message_id: request.message_id,
+            token: request.token.clone(),
+            payload,
+            ..Self::new()
+        }
+    }
+    /// Returns class of `code`: 0 for requests, 2 for success, 4 for client errors and 5 for server errors
+    pub fn code_class(&self) -> u8 {
+        self.code >> 5
+    }
+    pub fn code_detail(&self) -> u8 {
+        self.code & 0x1F
+    }
+    /// Returns `true` for GET(0.01), POST(0.02), PUT(0.03), DELETE(0.04) and other request codes
+    pub fn is_request(&self) -> bool {
+        self.code_class() == 0 && self.code != 0
+    }
+    pub fn is_response(&self) -> bool {
+        self.code_class() >= 2
+    }
+    /// Returns length of serialized message, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let mut options: Vec<(u16, usize)> = self.options.iter().map(|option| (option.number, option.value.len())).collect();
+        options.sort_by_key(|(number, _)| *number);
+        let mut previous = 0u16;
+        let mut length = 4 + self.token.len();
+        for (number, value_len) in options {
+            length += 1 + option_nibble_len((number - previous) as usize) + option_nibble_len(value_len) + value_len;
+            previous = number;
+        }
+        if !self.payload.is_empty() {
+            length += 1 + self.payload.len();
+        }
+        length
+    }
+    /// Returns first option with `number`
+    pub fn option(&self, number: u16) -> Option<&CoapOption> {
+        self.options.iter().find(|option| option.number == number)
+    }
+    /// Returns path from Uri-Path options, with leading '/'
+    pub fn uri_path(&self) -> String {
+        self.options.iter().filter(|option| option.number == COAP_OPTION_URI_PATH)
+            .map(|option| format!("/{}", String::from_utf8_lossy(&option.value))).collect()
+    }
+    /// Replaces Uri-Path options with segments of `path`
+    pub fn set_uri_path(&mut self, path: &str) {
+        self.options.retain(|option| option.number != COAP_OPTION_URI_PATH);
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            self.options.push(CoapOption::new(COAP_OPTION_URI_PATH, segment.as_bytes().to_vec()));
+        }
+    }
+    /// Constructs `CoapMessage` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 5683
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        if datagram.source != COAP_PORT && datagram.destination != COAP_PORT {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps message into UDP datagram from `source` port to port 5683
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn into_udp(self, source: u16) -> UdpDatagram {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = source;
+        datagram.destination = COAP_PORT;
+        datagram.payload = self.serialize();
+        datagram
+    }
+}
+impl Default for CoapMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for CoapMessage {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(4 + self.token.len() + self.payload.len() + 16);
+        result.push((self.version & 3) << 6 | (self.message_type as u8) << 4 | self.token.len() as u8 & 0xF);
+        result.push(self.code);
+        result.extend_from_slice(&self.message_id.to_be_bytes());
+        result.extend_from_slice(&self.token);
+        self.options.sort_by_key(|option| option.number);
+        let mut previous = 0u16;
+        for option in self.options {
+            let mut extension = Vec::new();
+            let delta = option_nibble((option.number - previous) as usize, &mut extension);
+            let length = option_nibble(option.value.len(), &mut extension);
+            result.push(delta << 4 | length);
+            result.extend_from_slice(&extension);
+            result.extend_from_slice(&option.value);
+            previous = option.number;
+        }
+        if !self.payload.is_empty() {
+            result.push(0xFF);
+            result.extend_from_slice(&self.payload);
+        }
+        result
+    }
+}
+impl Deserializable for CoapMessage {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        let token_length = (bytes[0] & 0xF) as usize;
+        if token_length > 8 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < 4 + token_length {return Err(DeserializeError::WrongDataLength);}
+        let mut message = Self {
+            version: bytes[0] >> 6,
+            message_type: CoapType::from_u8(bytes[0] >> 4),
+            code: bytes[1],
+            message_id: u16::from_be_bytes([bytes[2], bytes[3]]),
+            token: bytes[4..4 + token_length].to_vec(),
+            options: Vec::new(),
+            payload: Vec::new()
+        };
+        let mut i = 4 + token_length;
+        let mut number = 0usize;
+        while i < bytes.len() {
+            if bytes[i] == 0xFF {
+                if i + 1 == bytes.len() {return Err(DeserializeError::WrongData);}
+                message.payload = bytes[i + 1..].to_vec();
+                break;
+            }
+            let header = bytes[i];
+            i += 1;
+            number += read_option_nibble(header >> 4, bytes, &mut i)?;
+            let length = read_option_nibble(header & 0xF, bytes, &mut i)?;
+            if number > u16::MAX as usize {return Err(DeserializeError::WrongData);}
+            if bytes.len() < i + length {return Err(DeserializeError::WrongDataLength);}
+            message.options.push(CoapOption::new(number as u16, bytes[i..i + length].to_vec()));
+            i += length;
+        }
+        Ok(message)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,438 @@
+use core::net::Ipv4Addr;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Serializable, Deserializable, DeserializeError, TlvFormat, TlvReader, TlvWriter};
+
+/// DHCP Message Type, carried by option 53
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpMessageType {
+    Discover = 1,
+    Offer = 2,
+    Request = 3,
+    Decline = 4,
+    Ack = 5,
+    Nak = 6,
+    Release = 7,
+    Inform = 8
+}
+impl Serializable for DhcpMessageType {
+    fn serialize(self) -> Vec<u8> {
+        vec![self as u8]
+    }
+}
+impl Deserializable for DhcpMessageType {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        match bytes[0] {
+            1 => Ok(Self::Discover),
+            2 => Ok(Self::Offer),
+            3 => Ok(Self::Request),
+            4 => Ok(Self::Decline),
+            5 => Ok(Self::Ack),
+            6 => Ok(Self::Nak),
+            7 => Ok(Self::Release),
+            8 => Ok(Self::Inform),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+
+/// Route of Classless Static Route option(121)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClasslessRoute {
+    pub destination: Ipv4Addr,
+    /// Destination prefix length in bits, from 0 to 32, at most 4 destination bytes are written for greater values
+    pub prefix_length: u8,
+    pub router: Ipv4Addr
+}
+
+/// Typed DHCP option
+/// Pad(0) and End(255) options are not presented here, since they are handled on serialization and deserialization automatically
+#[derive(Debug, Clone)]
+pub enum DhcpOption {
+    /// Option 1
+    SubnetMask(Ipv4Addr),
+    /// Option 3
+    Routers(Vec<Ipv4Addr>),
+    /// Option 6
+    DomainNameServers(Vec<Ipv4Addr>),
+    /// Option 12
+    HostName(String),
+    /// Option 15
+    DomainName(String),
+    /// Option 26
+    InterfaceMtu(u16),
+    /// Option 28
+    BroadcastAddress(Ipv4Addr),
+    /// Option 42
+    NtpServers(Vec<Ipv4Addr>),
+    /// Option 43
+    VendorSpecific(Vec<u8>),
+    /// Option 50
+    RequestedIpAddress(Ipv4Addr),
+    /// Option 51 - lease time in seconds
+    LeaseTime(u32),
+    /// Option 53
+    MessageType(DhcpMessageType),
+    /// Option 54
+    ServerIdentifier(Ipv4Addr),
+    /// Option 55 - list of option codes client wants to receive
+    ParameterRequestList(Vec<u8>),
+    /// Option 56 - error message
+    Message(String),
+    /// Option 57
+    MaxMessageSize(u16),
+    /// Option 58 - T1 time in seconds
+    RenewalTime(u32),
+    /// Option 59 - T2 time in seconds
+    RebindingTime(u32),
+    /// Option 60
+    VendorClassIdentifier(Vec<u8>),
+    /// Option 61
+    ClientIdentifier {
+        /// Hardware type, i.e. 1 for Ethernet, 255 for RFC 4361 identifiers
+        hardware_type: u8,
+        identifier: Vec<u8>
+    },
+    /// Option 66
+    TftpServerName(String),
+    /// Option 67
+    BootfileName(String),
+    /// Option 121 - RFC 3442
+    ClasslessStaticRoutes(Vec<ClasslessRoute>),
+    /// Any other option or known option with malformed data, presented just with code and raw data
+    /// Note that codes 0 and 255 are Pad and End, so such options are skipped on serialization
+    Unknown(u8, Vec<u8>)
+}
+impl DhcpOption {
+    pub fn get_code(&self) -> u8 {
+        match self {
+            Self::SubnetMask(_) => 1,
+            Self::Routers(_) => 3,
+            Self::DomainNameServers(_) => 6,
+            Self::HostName(_) => 12,
+            Self::DomainName(_) => 15,
+            Self::InterfaceMtu(_) => 26,
+            Self::BroadcastAddress(_) => 28,
+            Self::NtpServers(_) => 42,
+            Self::VendorSpecific(_) => 43,
+            Self::RequestedIpAddress(_) => 50,
+            Self::LeaseTime(_) => 51,
+            Self::MessageType(_) => 53,
+            Self::ServerIdentifier(_) => 54,
+            Self::ParameterRequestList(_) => 55,
+            Self::Message(_) => 56,
+            Self::MaxMessageSize(_) => 57,
+            Self::RenewalTime(_) => 58,
+            Self::RebindingTime(_) => 59,
+            Self::VendorClassIdentifier(_) => 60,
+            Self::ClientIdentifier {..} => 61,
+            Self::TftpServerName(_) => 66,
+            Self::BootfileName(_) => 67,
+            Self::ClasslessStaticRoutes(_) => 121,
+            Self::Unknown(code, _) => *code
+        }
+    }
+    /// Returns option data without code and length
+    pub fn data(self) -> Vec<u8> {
+        let addresses = |addresses: Vec<Ipv4Addr>| addresses.iter().flat_map(|address| address.octets()).collect();
+        match self {
+            Self::SubnetMask(address) |
+            Self::BroadcastAddress(address) |
+            Self::RequestedIpAddress(address) |
+            Self::ServerIdentifier(address) => address.octets().to_vec(),
+            Self::Routers(list) |
+            Self::DomainNameServers(list) |
+            Self::NtpServers(list) => addresses(list),
+            Self::HostName(text) |
+            Self::DomainName(text) |
+            Self::Message(text) |
+            Self::TftpServerName(text) |
+            Self::BootfileName(text) => text.into_bytes(),
+            Self::InterfaceMtu(value) |
+            Self::MaxMessageSize(value) => value.to_be_bytes().to_vec(),
+            Self::LeaseTime(value) |
+            Self::RenewalTime(value) |
+            Self::RebindingTime(value) => value.to_be_bytes().to_vec(),
+            Self::MessageType(message_type) => message_type.serialize(),
+            Self::VendorSpecific(data) |
+            Self::ParameterRequestList(data) |
+            Self::VendorClassIdentifier(data) |
+            Self::Unknown(_, data) => data,
+            Self::ClientIdentifier {hardware_type, mut identifier} => {
+                identifier.insert(0, hardware_type);
+                identifier
+            }
+            Self::ClasslessStaticRoutes(routes) => {
+                let mut data = Vec::new();
+                for route in routes {
+                    data.push(route.prefix_length);
+                    data.extend_from_slice(&route.destination.octets()[..(route.prefix_length as usize).div_ceil(8).min(4)]);
+                    data.extend_from_slice(&route.router.octets());
+                }
+                data
+            }
+        }
+    }
+    /// Returns length of option data, computed without serializing it
+    pub fn data_len(&self) -> usize {
+        match self {
+            Self::SubnetMask(_) |
+            Self::BroadcastAddress(_) |
+            Self::RequestedIpAddress(_) |
+            Self::ServerIdentifier(_) |
+            Self::LeaseTime(_) |
+            Self::RenewalTime(_) |
+            Self::RebindingTime(_) => 4,
+            Self::Routers(list) |
+            Self::DomainNameServers(list) |
+            Self::NtpServers(list) => list.len() * 4,
+            Self::HostName(text) |
+            Self::DomainName(text) |
+            Self::Message(text) |
+            Self::TftpServerName(text) |
+            Self::BootfileName(text) => text.len(),
+            Self::InterfaceMtu(_) |
+            Self::MaxMessageSize(_) => 2,
+            Self::MessageType(_) => 1,
+            Self::VendorSpecific(data) |
+            Self::ParameterRequestList(data) |
+            Self::VendorClassIdentifier(data) |
+            Self::Unknown(_, data) => data.len(),
+            Self::ClientIdentifier {identifier, ..} => 1 + identifier.len(),
+            Self::ClasslessStaticRoutes(routes) => routes.iter().map(|route| 5 + (route.prefix_length as usize).div_ceil(8).min(4)).sum()
+        }
+    }
+    /// Returns length of serialized option, which is split into several instances when data exceeds 255 bytes
+    /// Returns 0 for `DhcpOption::Unknown` with code 0 or 255, which isn't serialized
+    pub fn wire_len(&self) -> usize {
+        if let Self::Unknown(0 | 255, _) = self {return 0;}
+        let data_len = self.data_len();
+        data_len + data_len.div_ceil(255).max(1) * 2
+    }
+    /// Constructs typed option from `code` and `data`
+    /// Falls back to `DhcpOption::Unknown` if option is unknown or its data is malformed
+    pub fn from_code_and_data(code: u8, data: &[u8]) -> Self {
+        let address = || -> Option<Ipv4Addr> {Some(Ipv4Addr::from_octets(*data.as_array()?))};
+        let addresses = || -> Option<Vec<Ipv4Addr>> {
+            if data.is_empty() || !data.len().is_multiple_of(4) {return None;}
+            Some(data.chunks(4).map(|address| Ipv4Addr::new(address[0], address[1], address[2], address[3])).collect())
+        };
+        let text = || String::from_utf8(data.to_vec()).ok();
+        let short = || Some(u16::from_be_bytes(*data.as_array()?));
+        let long = || Some(u32::from_be_bytes(*data.as_array()?));
+        let option = match code {
+            1 => address().map(Self::SubnetMask),
+            3 => addresses().map(Self::Routers),
+            6 => addresses().map(Self::DomainNameServers),
+            12 => text().map(Self::HostName),
+            15 => text().map(Self::DomainName),
+            26 => short().map(Self::InterfaceMtu),
+            28 => address().map(Self::BroadcastAddress),
+            42 => addresses().map(Self::NtpServers),
+            43 => Some(Self::VendorSpecific(data.to_vec())),
+            50 => address().map(Self::RequestedIpAddress),
+            51 => long().map(Self::LeaseTime),
+            53 if data.len() == 1 => DhcpMessageType::deserialize(data).ok().map(Self::MessageType),
+            54 => address().map(Self::ServerIdentifier),
+            55 => Some(Self::ParameterRequestList(data.to_vec())),
+            56 => text().map(Self::Message),
+            57 => short().map(Self::MaxMessageSize),
+            58 => long().map(Self::RenewalTime),
+            59 => long().map(Self::RebindingTime),
+            60 => Some(Self::VendorClassIdentifier(data.to_vec())),
+            61 if data.len() >= 2 => Some(Self::ClientIdentifier {
+                hardware_type: data[0],
+                identifier: data[1..].to_vec()
+            }),
+            66 => text().map(Self::TftpServerName),
+            67 => text().map(Self::BootfileName),
+            121 => parse_classless_routes(data).map(Self::ClasslessStaticRoutes),
+            _ => None
+        };
+        option.unwrap_or_else(|| Self::Unknown(code, data.to_vec()))
+    }
+}
+
+fn parse_classless_routes(data: &[u8]) -> Option<Vec<ClasslessRoute>> {
+    let mut routes = Vec::new();
+    let mut i = 0usize;
+    while i < data.len() {
+        let prefix_length = data[i];
+        if prefix_length > 32 {return None;}
+        let octets = (prefix_length as usize).div_ceil(8);
+        if data.len() < i + 1 + octets + 4 {return None;}
+        let mut destination = [0u8; 4];
+        destination[..octets].copy_from_slice(&data[i + 1..i + 1 + octets]);
+        let router = i + 1 + octets;
+        routes.push(ClasslessRoute {
+            destination: Ipv4Addr::from_octets(destination),
+            prefix_length,
+            router: Ipv4Addr::new(data[router], data[router + 1], data[router + 2], data[router + 3])
+        });
+        i = router + 4;
+    }
+    Some(routes)
+}
+
+/// Serializes `options` with End option, splitting options longer than `255 bytes` into several instances as RFC 3396 describes
+/// `DhcpOption::Unknown` with Pad or End code is skipped, since receivers would read it as padding or end of options
+pub fn serialize_options(options: Vec<DhcpOption>) -> Vec<u8> {
+    let mut writer = TlvWriter::new(TlvFormat::DHCP_OPTIONS);
+    for option in options {
+        if let DhcpOption::Unknown(0 | 255, _) = option {continue;}
+        let code = option.get_code() as u32;
+        let data = option.data();
+        if data.is_empty() {
+            writer.write(code, &[]);
+        }
+        for chunk in data.chunks(255) {
+            writer.write(code, chunk);
+        }
+    }
+    writer.end();
+    writer.into_bytes()
+}
+
+/// Deserializes options until End option or end of `bytes`, skipping Pad options
+/// Several instances of the same option are concatenated as RFC 3396 describes
+pub fn deserialize_options(bytes: &[u8]) -> Result<Vec<DhcpOption>, DeserializeError> {
+    let mut raw: Vec<(u8, Vec<u8>)> = Vec::new();
+    for option in TlvReader::new(TlvFormat::DHCP_OPTIONS, bytes) {
+        let option = option?;
+        let code = option.kind as u8;
+        match raw.iter_mut().find(|(existing, _)| *existing == code) {
+            Some((_, data)) => data.extend_from_slice(option.value),
+            None => raw.push((code, option.value.to_vec()))
+        }
+    }
+    Ok(raw.into_iter().map(|(code, data)| DhcpOption::from_code_and_data(code, &data)).collect())
+}
+
+/// Struct for DHCP Message, carried by UDP datagrams between ports 67 and 68
+/// You can construct it from scratch with `DhcpMessage::new()` and consistently editing
+/// Or construct from existing message bytes with `DhcpMessage::deserialize()` or from UDP datagram with `DhcpMessage::from_udp()`
+#[derive(Debug, Clone)]
+pub struct DhcpMessage {
+    /// 1 for BOOTREQUEST, 2 for BOOTREPLY
+    pub op: u8,
+    /// Hardware address type, 1 for Ethernet
+    pub hardware_type: u8,
+    /// Hardware address length, 6 for Ethernet
+    pub hardware_length: u8,
+    pub hops: u8,
+    /// Transaction ID
+    pub xid: u32,
+    /// Seconds elapsed since client began address acquisition
+    pub secs: u16,
+    /// `broadcast` flag - client can't receive unicast replies
+    pub broadcast: bool,
+    /// Client IP address, when client already has one
+    pub ciaddr: Ipv4Addr,
+    /// "Your" IP address, offered to client
+    pub yiaddr: Ipv4Addr,
+    /// Next server IP address
+    pub siaddr: Ipv4Addr,
+    /// Relay agent IP address
+    pub giaddr: Ipv4Addr,
+    /// Client hardware address, padded with zeroes
+    pub chaddr: [u8; 16],
+    /// Server host name
+    pub sname: [u8; 64],
+    /// Boot file name
+    pub file: [u8; 128],
+    pub options: Vec<DhcpOption>
+}
+impl DhcpMessage {
+    /// Constructs an empty Ethernet BOOTREQUEST `DhcpMessage`
+    pub fn new() -> Self {
+        Self {
+            op: 1,
+            hardware_type: 1,
+            hardware_length: 6,
+            hops: 0,
+            xid: 0,
+            secs: 0,
+            broadcast: false,
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: [0; 16],
+            sname: [0; 64],
+            file: [0; 128],
+            options: Vec::new()
+        }
+    }
+    /// Returns DHCP Message Type from option 53
+    pub fn message_type(&self) -> Option<DhcpMessageType> {
+        self.options.iter().find_map(|option| match option {
+            DhcpOption::MessageType(message_type) => Some(*message_type),
+            _ => None
+        })
+    }
+    /// Returns first option with `code`
+    pub fn option(&self, code: u8) -> Option<&DhcpOption> {
+        self.options.iter().find(|option| option.get_code() == code)
+    }
+    /// Returns length of serialized message including End option, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        241 + self.options.iter().map(DhcpOption::wire_len).sum::<usize>()
+    }
+    /// Constructs `DhcpMessage` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 67 or 68
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        let ports = [67, 68];
+        if !ports.contains(&datagram.source) && !ports.contains(&datagram.destination) {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+}
+impl Default for DhcpMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for DhcpMessage {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 240];
+        result[0] = self.op;
+        result[1] = self.hardware_type;
+        result[2] = self.hardware_length;
+        result[3] = self.hops;
+        result[4..8].copy_from_slice(&self.xid.to_be_bytes());
+        result[8..10].copy_from_slice(&self.secs.to_be_bytes());
+        result[10] = (self.broadcast as u8) << 7;
+        result[12..16].copy_from_slice(&self.ciaddr.octets());
+        result[16..20].copy_from_slice(&self.yiaddr.octets());
+        result[20..24].copy_from_slice(&self.siaddr.octets());
+        result[24..28].copy_from_slice(&self.giaddr.octets());
+        result[28..44].copy_from_slice(&self.chaddr);
+        result[44..108].copy_from_slice(&self.sname);
+        result[108..236].copy_from_slice(&self.file);
+        result[236..240].copy_from_slice(&[99, 130, 83, 99]);
+        result.append(&mut serialize_options(self.options));
+        result
+    }
+}
+impl Deserializable for DhcpMessage {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 240 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[236..240] != [99, 130, 83, 99] {return Err(DeserializeError::WrongData);}
+        Ok(Self {
+            op: bytes[0],
+            hardware_type: bytes[1],
+            hardware_length: bytes[2],
+            hops: bytes[3],
+            xid: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            secs: u16::from_be_bytes([bytes[8], bytes[9]]),
+            broadcast: bytes[10] & 0x80 != 0,
+            ciaddr: Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]),
+            yiaddr: Ipv4Addr::new(bytes[16], bytes[17], bytes[18], bytes[19]),
+            siaddr: Ipv4Addr::new(bytes[20], bytes[21], bytes[22], bytes[23]),
+            giaddr: Ipv4Addr::new(bytes[24], bytes[25], bytes[26], bytes[27]),
+            chaddr: *bytes[28..44].as_array().unwrap(),
+            sname: *bytes[44..108].as_array().unwrap(),
+            file: *bytes[108..236].as_array().unwrap(),
+            options: deserialize_options(&bytes[240..])?
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use crate::util::{Serializable, Deserializable, DeserializeError, require_stream_length};
+
+/// TCP and UDP port of DNP3
+pub const DNP3_PORT: u16 = 20000;
+/// Maximum length of user data in a single link-layer frame
+pub const DNP3_MAX_USER_DATA: usize = 250;
+
+const CRC16_DNP_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {(crc >> 1) ^ 0xA6BC} else {crc >> 1};
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Calculates **CRC-16/DNP** of `bytes`, which protects DNP3 link header and each `16 bytes` block of user data
+/// It is transmitted in little-endian order
+pub fn crc16_dnp(bytes: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC16_DNP_TABLE[((crc ^ *byte as u16) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/// Control field of DNP3 link header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Dnp3Control {
+    /// DIR - frame is sent by master
+    pub direction: bool,
+    /// PRM - frame is sent by primary station, i.e. it is request and not response
+    pub primary: bool,
+    /// FCB in primary frames, alternates between successive frames when `frame_count_valid` is set
+    /// ACD(access demand) in secondary frames, which means that station has class 1 data
+    pub frame_count_bit: bool,
+    /// FCV in primary frames
+    /// DFC(data flow control) in secondary frames, which means that station buffers are full
+    pub frame_count_valid: bool,
+    /// Link function code, i.e. 3 for CONFIRMED_USER_DATA, 4 for UNCONFIRMED_USER_DATA and 9 for REQUEST_LINK_STATUS in primary frames
+    pub function: u8
+}
+impl Dnp3Control {
+    pub fn from_u8(value: u8) -> Self {
+        Self {
+            direction: value & 0x80 != 0,
+            primary: value & 0x40 != 0,
+            frame_count_bit: value & 0x20 != 0,
+            frame_count_valid: value & 0x10 != 0,
+            function: value & 0xF
+        }
+    }
+    pub fn to_u8(&self) -> u8 {
+        (self.direction as u8) << 7 |
+        (self.primary as u8) << 6 |
+        (self.frame_count_bit as u8) << 5 |
+        (self.frame_count_valid as u8) << 4 |
+        self.function & 0xF
+    }
+}
+
+/// Transport segment, which is user data of DNP3 frame carrying part of application fragment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dnp3TransportSegment {
+    /// FIN - the last segment of fragment
+    pub fin: bool,
+    /// FIR - the first segment of fragment
+    pub fir: bool,
+    /// Sequence number, only 6 bits, increased by 1 for each segment
+    pub sequence: u8,
+    pub data: Vec<u8>
+}
+impl Dnp3TransportSegment {
+    /// Returns length of serialized segment, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        1 + self.data.len()
+    }
+}
+impl Serializable for Dnp3TransportSegment {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(1 + self.data.len());
+        result.push((self.fin as u8) << 7 | (self.fir as u8) << 6 | self.sequence & 0x3F);
+        result.extend_from_slice(&self.data);
+        result
+    }
+}
+impl Deserializable for Dnp3TransportSegment {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            fin: bytes[0] & 0x80 != 0,
+            fir: bytes[0] & 0x40 != 0,
+            sequence: bytes[0] & 0x3F,
+            data: bytes[1..].to_vec()
+        })
+    }
+}
+
+/// Struct for DNP3 link-layer frame, as described in IEEE 1815
+/// On the wire `10 bytes` header is followed by user data split into blocks of `16 bytes`, each block and header end with CRC
+/// CRCs are calculated on serialization and validated on deserialization, so they aren't stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dnp3Frame {
+    pub control: Dnp3Control,
+    pub destination: u16,
+    pub source: u16,
+    /// User data, up to `250 bytes`, usually a transport segment
+    pub data: Vec<u8>
+}
+impl Dnp3Frame {
+    /// Constructs an empty frame with unconfirmed user data function sent by master
+    pub fn new() -> Self {
+        Self {
+            control: Dnp3Control {direction: true, primary: true, function: 4, ..Default::default()},
+            destination: 0,
+            source: 0,
+            data: Vec::new()
+        }
+    }
+    /// Returns length of serialized frame with header and all CRCs, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        wire_length(self.data.len().min(DNP3_MAX_USER_DATA))
+    }
+    /// Parses user data as transport segment
+    pub fn transport_segment(&self) -> Result<Dnp3TransportSegment, DeserializeError> {
+        Dnp3TransportSegment::deserialize(&self.data)
+    }
+    /// Parses the first frame of TCP `stream` or UDP payload
+    /// Returns frame and its length in bytes or `Err(DeserializeError::Incomplete)` if frame isn't fully received yet
+    pub fn deserialize_next(stream: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        require_stream_length(stream, 3)?;
+        if stream[0..2] != [0x05, 0x64] {return Err(DeserializeError::WrongData);}
+        if stream[2] < 5 {return Err(DeserializeError::WrongData);}
+        let length = wire_length(stream[2] as usize - 5);
+        require_stream_length(stream, length)?;
+        Ok((Self::deserialize(&stream[..length])?, length))
+    }
+    /// Splits TCP stream into DNP3 frames
+    /// Returns parsed frames and count of consumed bytes, so incomplete frame at the end of `stream` can be kept until more data arrives
+    pub fn deserialize_stream(stream: &[u8]) -> Result<(Vec<Self>, usize), DeserializeError> {
+        let mut frames = Vec::new();
+        let mut i = 0usize;
+        loop {
+            match Self::deserialize_next(&stream[i..]) {
+                Ok((frame, length)) => {
+                    frames.push(frame);
+                    i += length;
+                }
+                Err(DeserializeError::Incomplete {..}) => break,
+                Err(error) => return Err(error)
+            }
+        }
+        Ok((frames, i))
+    }
+}
+impl Default for Dnp3Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns length of frame with `data_length` bytes of user data, including header and all CRCs
+fn wire_length(data_length: usize) -> usize {
+    10 + data_length + data_length.div_ceil(16) * 2
+}
+
+impl Serializable for Dnp3Frame {
+    /// Converts frame to bytes, user data is truncated to `250 bytes`
+    fn serialize(self) -> Vec<u8> {
+        let data = &self.data[..self.data.len().min(DNP3_MAX_USER_DATA)];
+        let mut result = Vec::with_capacity(wire_length(data.len()));
+        result.extend_from_slice(&[0x05, 0x64, 5 + data.len() as u8, self.control.to_u8()]);
+        result.extend_from_slice(&self.destination.to_le_bytes());
+        result.extend_from_slice(&self.source.to_le_bytes());
+        result.extend_from_slice(&crc16_dnp(&result).to_le_bytes());
+        for block in data.chunks(16) {
+            result.extend_from_slice(block);
+            result.extend_from_slice(&crc16_dnp(block).to_le_bytes());
+        }
+        result
+    }
+}
+impl Deserializable for Dnp3Frame {
+    /// Constructs `Dnp3Frame` from bytes of a single frame
+    /// Returns `Err(DeserializeError::WrongData)` if start bytes are wrong or any CRC doesn't match
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 10 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0..2] != [0x05, 0x64] || bytes[2] < 5 {return Err(DeserializeError::WrongData);}
+        if bytes.len() != wire_length(bytes[2] as usize - 5) {return Err(DeserializeError::WrongDataLength);}
+        if crc16_dnp(&bytes[0..8]).to_le_bytes() != bytes[8..10] {return Err(DeserializeError::WrongData);}
+        let mut data = Vec::with_capacity(bytes[2] as usize - 5);
+        for block in bytes[10..].chunks(18) {
+            let (block, crc) = block.split_at(block.len() - 2);
+            if crc16_dnp(block).to_le_bytes() != crc {return Err(DeserializeError::WrongData);}
+            data.extend_from_slice(block);
+        }
+        Ok(Self {
+            control: Dnp3Control::from_u8(bytes[3]),
+            destination: u16::from_le_bytes([bytes[4], bytes[5]]),
+            source: u16::from_le_bytes([bytes[6], bytes[7]]),
+            data
+        })
+    }
+}
+
+/// Reassembles application fragments from transport segments, separately for each pair of link addresses
+/// Segment with FIR flag starts new fragment, segments out of sequence discard the fragment in progress as IEEE 1815 requires
+#[derive(Debug, Clone, Default)]
+pub struct Dnp3Reassembler {
+    /// Next expected sequence number and received data by `(source, destination)`
+    fragments: HashMap<(u16, u16), (u8, Vec<u8>)>
+}
+impl Dnp3Reassembler {
+    /// Constructs an empty `Dnp3Reassembler`
+    pub fn new() -> Self {
+        Self {
+            fragments: HashMap::new()
+        }
+    }
+    /// Adds transport segment carried by `frame`
+    /// Returns application fragment if it is completed by this segment
+    pub fn push(&mut self, frame: &Dnp3Frame) -> Option<Vec<u8>> {
+        let segment = frame.transport_segment().ok()?;
+        let key = (frame.source, frame.destination);
+        if segment.fir {
+            self.fragments.insert(key, (segment.sequence, Vec::new()));
+        }
+        let (sequence, data) = self.fragments.get_mut(&key)?;
+        if *sequence != segment.sequence {
+            self.fragments.remove(&key);
+            return None;
+        }
+        *sequence = (segment.sequence + 1) & 0x3F;
+        data.extend_from_slice(&segment.data);
+        if segment.fin {return self.fragments.remove(&key).map(|(_, data)| data);}
+        None
+    }
+    /// Splits application `fragment` into frames from `source` to `destination` with transport segments starting at `sequence`
+    /// Returns frames and sequence number for the next fragment
+    pub fn segment(fragment: &[u8], control: Dnp3Control, destination: u16, source: u16, sequence: u8) -> (Vec<Dnp3Frame>, u8) {
+        let chunks: Vec<&[u8]> = if fragment.is_empty() {vec![&[]]} else {fragment.chunks(DNP3_MAX_USER_DATA - 1).collect()};
+        let mut sequence = sequence & 0x3F;
+        let mut frames = Vec::with_capacity(chunks.len());
+        for (i, chunk) in chunks.iter().enumerate() {
+            let segment = Dnp3TransportSegment {fin: i + 1 == chunks.len(), fir: i == 0, sequence, data: chunk.to_vec()};
+            frames.push(Dnp3Frame {control, destination, source, data: segment.serialize()});
+            sequence = (sequence + 1) & 0x3F;
+        }
+        (frames, sequence)
+    }
+    /// Forgets all fragments in progress
+    pub fn clear(&mut self) {
+        self.fragments.clear();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,649 @@
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::collections::HashMap;
+use crate::util::{Serializable, Deserializable, DeserializeError, require_stream_length};
+
+/// Reads possibly compressed domain name starting at `offset` of whole DNS `message`
+/// Returns dot-separated name without trailing dot and offset right after name
+pub(crate) fn read_name(message: &[u8], offset: usize) -> Result<(String, usize), DeserializeError> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut i = offset;
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        if message.len() <= i {return Err(DeserializeError::WrongDataLength);}
+        let length = message[i] as usize;
+        match length & 0xC0 {
+            0x00 => {
+                if length == 0 {
+                    i += 1;
+                    break;
+                }
+                if message.len() < i + 1 + length {return Err(DeserializeError::WrongDataLength);}
+                labels.push(String::from_utf8_lossy(&message[i + 1..i + 1 + length]).into_owned());
+                i += 1 + length;
+            }
+            0xC0 => {
+                if message.len() < i + 2 {return Err(DeserializeError::WrongDataLength);}
+                jumps += 1;
+                if jumps > 64 {return Err(DeserializeError::WrongData);}
+                end.get_or_insert(i + 2);
+                i = u16::from_be_bytes([message[i] & 0x3F, message[i + 1]]) as usize;
+            }
+            _ => return Err(DeserializeError::WrongData)
+        }
+    }
+    Ok((labels.join("."), end.unwrap_or(i)))
+}
+
+/// Maximum length of domain name label
+pub const DNS_MAX_LABEL: usize = 63;
+/// Maximum length of encoded domain name, including length bytes of labels and terminating zero
+pub const DNS_MAX_NAME: usize = 255;
+
+/// Error for domain name, which has label longer than `63 bytes` or is longer than `255 bytes` encoded, so it can't be written to DNS message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDomainName(pub String);
+
+/// Splits domain name into labels, which are truncated to `DNS_MAX_LABEL` bytes, and labels past `DNS_MAX_NAME` bytes of encoded name are dropped
+/// Returns labels and `false` if anything was truncated or dropped
+fn name_labels(name: &str) -> (Vec<&[u8]>, bool) {
+    let mut labels = Vec::new();
+    let mut length = 1usize;
+    let mut valid = true;
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let truncated = &label.as_bytes()[..label.len().min(DNS_MAX_LABEL)];
+        valid &= truncated.len() == label.len();
+        length += 1 + truncated.len();
+        if length > DNS_MAX_NAME {return (labels, false);}
+        labels.push(truncated);
+    }
+    (labels, valid)
+}
+
+/// Writes domain name as sequence of labels
+/// If `compression` table is given, already written suffix of name is replaced with pointer to it, and new suffixes are remembered
+/// Offsets in table are relative to the start of DNS message, so `result` should start with it
+/// Returns `Err(InvalidDomainName)` without writing anything if name has too long label or is too long, unless `truncate` is set, then it's written truncated as `name_labels()` does
+pub(crate) fn write_name(name: &str, result: &mut Vec<u8>, mut compression: Option<&mut HashMap<Vec<u8>, u16>>, truncate: bool) -> Result<(), InvalidDomainName> {
+    let (labels, valid) = name_labels(name);
+    if !valid && !truncate {return Err(InvalidDomainName(name.to_string()));}
+    for i in 0..labels.len() {
+        if let Some(table) = compression.as_deref_mut() {
+            let suffix = labels[i..].join(&b'.').to_ascii_lowercase();
+            if let Some(offset) = table.get(&suffix) {
+                result.extend_from_slice(&(0xC000 | offset).to_be_bytes());
+                return Ok(());
+            }
+            if result.len() < 0x4000 {
+                table.insert(suffix, result.len() as u16);
+            }
+        }
+        result.push(labels[i].len() as u8);
+        result.extend_from_slice(labels[i]);
+    }
+    result.push(0);
+    Ok(())
+}
+
+/// Returns length of domain name written by `write_name()` without compression, truncated if it's invalid
+pub(crate) fn name_len(name: &str) -> usize {
+    name_labels(name).0.iter().map(|label| 1 + label.len()).sum::<usize>() + 1
+}
+
+/// DNS Question section entry
+#[derive(Debug, Clone)]
+pub struct DnsQuestion {
+    /// Domain name without trailing dot, i.e. "example.com"
+    pub name: String,
+    /// Query type, i.e. 1 for A, 28 for AAAA, 255 for ANY
+    pub qtype: u16,
+    /// Query class, 1 for IN
+    pub qclass: u16
+}
+impl DnsQuestion {
+    /// Constructs Question of IN class
+    pub fn new(name: &str, qtype: u16) -> Self {
+        Self {
+            name: name.to_string(),
+            qtype,
+            qclass: 1
+        }
+    }
+}
+
+/// Typed data of DNS Resource Record
+#[derive(Debug, Clone)]
+pub enum DnsRecordData {
+    /// Type 1
+    A(Ipv4Addr),
+    /// Type 2
+    Ns(String),
+    /// Type 5
+    Cname(String),
+    /// Type 12
+    Ptr(String),
+    /// Type 15
+    Mx {
+        preference: u16,
+        exchange: String
+    },
+    /// Type 16 - list of character strings
+    Txt(Vec<Vec<u8>>),
+    /// Type 28
+    Aaaa(Ipv6Addr),
+    /// Type 41 - EDNS options of OPT pseudo-record, see `Edns` for the rest of its fields
+    Opt(Vec<EdnsOption>),
+    /// Any other type presented just with raw data
+    Raw(Vec<u8>)
+}
+
+/// DNS Resource Record of Answer, Authority or Additional section
+#[derive(Debug, Clone)]
+pub struct DnsRecord {
+    /// Domain name without trailing dot, i.e. "example.com"
+    pub name: String,
+    /// Record type, i.e. 1 for A, 28 for AAAA, 5 for CNAME
+    /// Note that `data` is serialized as is, so it should match this type
+    pub rtype: u16,
+    /// Record class, 1 for IN
+    pub class: u16,
+    /// Time to Live in seconds
+    pub ttl: u32,
+    pub data: DnsRecordData
+}
+impl DnsRecord {
+    /// Constructs record of IN class with type taken from `data`
+    /// For `DnsRecordData::Raw` type is 0 and should be set manually
+    pub fn new(name: &str, ttl: u32, data: DnsRecordData) -> Self {
+        let rtype = match data {
+            DnsRecordData::A(_) => 1,
+            DnsRecordData::Ns(_) => 2,
+            DnsRecordData::Cname(_) => 5,
+            DnsRecordData::Ptr(_) => 12,
+            DnsRecordData::Mx {..} => 15,
+            DnsRecordData::Txt(_) => 16,
+            DnsRecordData::Aaaa(_) => 28,
+            DnsRecordData::Opt(_) => 41,
+            DnsRecordData::Raw(_) => 0
+        };
+        Self {
+            name: name.to_string(),
+            rtype,
+            class: 1,
+            ttl,
+            data
+        }
+    }
+    /// Reads record starting at `offset` of whole DNS `message`, so compressed names inside it can be resolved
+    /// Returns record and offset right after it
+    pub(crate) fn read(message: &[u8], offset: usize) -> Result<(Self, usize), DeserializeError> {
+        let (name, i) = read_name(message, offset)?;
+        if message.len() < i + 10 {return Err(DeserializeError::WrongDataLength);}
+        let rtype = u16::from_be_bytes([message[i], message[i + 1]]);
+        let class = u16::from_be_bytes([message[i + 2], message[i + 3]]);
+        let ttl = u32::from_be_bytes([message[i + 4], message[i + 5], message[i + 6], message[i + 7]]);
+        let length = u16::from_be_bytes([message[i + 8], message[i + 9]]) as usize;
+        let start = i + 10;
+        if message.len() < start + length {return Err(DeserializeError::WrongDataLength);}
+        let raw = &message[start..start + length];
+        let data = match rtype {
+            1 if length == 4 => DnsRecordData::A(Ipv4Addr::new(raw[0], raw[1], raw[2], raw[3])),
+            2 => DnsRecordData::Ns(read_name(message, start)?.0),
+            5 => DnsRecordData::Cname(read_name(message, start)?.0),
+            12 => DnsRecordData::Ptr(read_name(message, start)?.0),
+            15 if length >= 3 => DnsRecordData::Mx {
+                preference: u16::from_be_bytes([raw[0], raw[1]]),
+                exchange: read_name(message, start + 2)?.0
+            },
+            16 => {
+                let mut strings = Vec::new();
+                let mut j = 0usize;
+                while j < raw.len() {
+                    let end = j + 1 + raw[j] as usize;
+                    if raw.len() < end {return Err(DeserializeError::WrongDataLength);}
+                    strings.push(raw[j + 1..end].to_vec());
+                    j = end;
+                }
+                DnsRecordData::Txt(strings)
+            }
+            28 if length == 16 => DnsRecordData::Aaaa(Ipv6Addr::from_octets(*raw.as_array().unwrap())),
+            41 => match read_edns_options(raw) {
+                Ok(options) => DnsRecordData::Opt(options),
+                Err(_) => DnsRecordData::Raw(raw.to_vec())
+            },
+            _ => DnsRecordData::Raw(raw.to_vec())
+        };
+        Ok((Self {name, rtype, class, ttl, data}, start + length))
+    }
+    /// Returns length of record written without name compression, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let data_len = match &self.data {
+            DnsRecordData::A(_) => 4,
+            DnsRecordData::Ns(name) |
+            DnsRecordData::Cname(name) |
+            DnsRecordData::Ptr(name) => name_len(name),
+            DnsRecordData::Mx {exchange, ..} => 2 + name_len(exchange),
+            DnsRecordData::Txt(strings) => strings.iter().map(|string| 1 + string.len()).sum(),
+            DnsRecordData::Aaaa(_) => 16,
+            DnsRecordData::Opt(options) => options.iter().map(|option| 4 + option.data_len()).sum(),
+            DnsRecordData::Raw(data) => data.len()
+        };
+        name_len(&self.name) + 10 + data_len
+    }
+    /// Writes record, compressing names with `compression` table if it's given
+    /// Returns `Err(InvalidDomainName)` if any name is invalid and `truncate` isn't set, see `write_name()`
+    pub(crate) fn write(self, result: &mut Vec<u8>, mut compression: Option<&mut HashMap<Vec<u8>, u16>>, truncate: bool) -> Result<(), InvalidDomainName> {
+        write_name(&self.name, result, compression.as_deref_mut(), truncate)?;
+        result.extend_from_slice(&self.rtype.to_be_bytes());
+        result.extend_from_slice(&self.class.to_be_bytes());
+        result.extend_from_slice(&self.ttl.to_be_bytes());
+        let length_position = result.len();
+        result.extend_from_slice(&[0, 0]);
+        match self.data {
+            DnsRecordData::A(address) => result.extend_from_slice(&address.octets()),
+            DnsRecordData::Ns(name) |
+            DnsRecordData::Cname(name) |
+            DnsRecordData::Ptr(name) => write_name(&name, result, compression, truncate)?,
+            DnsRecordData::Mx {preference, exchange} => {
+                result.extend_from_slice(&preference.to_be_bytes());
+                write_name(&exchange, result, compression, truncate)?;
+            }
+            DnsRecordData::Txt(strings) => {
+                for string in strings {
+                    result.push(string.len() as u8);
+                    result.extend_from_slice(&string);
+                }
+            }
+            DnsRecordData::Aaaa(address) => result.extend_from_slice(&address.octets()),
+            DnsRecordData::Opt(options) => {
+                for option in options {
+                    let code = option.get_code();
+                    let data = option.data();
+                    result.extend_from_slice(&code.to_be_bytes());
+                    result.extend_from_slice(&(data.len() as u16).to_be_bytes());
+                    result.extend_from_slice(&data);
+                }
+            }
+            DnsRecordData::Raw(data) => result.extend_from_slice(&data)
+        }
+        let length = (result.len() - length_position - 2) as u16;
+        result[length_position..length_position + 2].copy_from_slice(&length.to_be_bytes());
+        Ok(())
+    }
+}
+
+/// Option of EDNS OPT pseudo-record
+#[derive(Debug, Clone)]
+pub enum EdnsOption {
+    /// Option 8 - RFC 7871
+    ClientSubnet {
+        /// Source prefix length in bits, address is truncated and masked to it on serialization
+        source_prefix: u8,
+        /// Scope prefix length in bits, 0 in queries
+        scope_prefix: u8,
+        address: IpAddr
+    },
+    /// Option 10 - RFC 7873
+    Cookie {
+        client: [u8; 8],
+        /// Server cookie of `8 to 32 bytes`, empty if client doesn't know it yet
+        server: Vec<u8>
+    },
+    /// Any other option or known option with malformed data, presented just with code and raw data
+    Unknown(u16, Vec<u8>)
+}
+impl EdnsOption {
+    pub fn get_code(&self) -> u16 {
+        match self {
+            Self::ClientSubnet {..} => 8,
+            Self::Cookie {..} => 10,
+            Self::Unknown(code, _) => *code
+        }
+    }
+    /// Returns option data without code and length
+    pub fn data(self) -> Vec<u8> {
+        match self {
+            Self::ClientSubnet {source_prefix, scope_prefix, address} => {
+                let (family, octets) = match address {
+                    IpAddr::V4(address) => (1u16, address.octets().to_vec()),
+                    IpAddr::V6(address) => (2u16, address.octets().to_vec())
+                };
+                let mut data = family.to_be_bytes().to_vec();
+                data.push(source_prefix);
+                data.push(scope_prefix);
+                let length = (source_prefix as usize).div_ceil(8).min(octets.len());
+                data.extend_from_slice(&octets[..length]);
+                // RFC 7871 requires bits beyond source prefix to be zero
+                if source_prefix % 8 != 0 && (source_prefix as usize) < octets.len() * 8 {
+                    *data.last_mut().unwrap() &= 0xFF << (8 - source_prefix % 8);
+                }
+                data
+            }
+            Self::Cookie {client, server} => {
+                let mut data = client.to_vec();
+                data.extend_from_slice(&server);
+                data
+            }
+            Self::Unknown(_, data) => data
+        }
+    }
+    /// Returns length of option data, computed without serializing it
+    pub fn data_len(&self) -> usize {
+        match self {
+            Self::ClientSubnet {source_prefix, address, ..} => {
+                let octets = if address.is_ipv4() {4} else {16};
+                4 + (*source_prefix as usize).div_ceil(8).min(octets)
+            }
+            Self::Cookie {server, ..} => 8 + server.len(),
+            Self::Unknown(_, data) => data.len()
+        }
+    }
+    /// Constructs typed option from `code` and `data`
+    /// Falls back to `EdnsOption::Unknown` if option is unknown or its data is malformed
+    pub fn from_code_and_data(code: u16, data: &[u8]) -> Self {
+        match code {
+            8 if data.len() >= 4 => {
+                let length = (data[2] as usize).div_ceil(8);
+                let address = match u16::from_be_bytes([data[0], data[1]]) {
+                    1 if length <= 4 && data.len() == 4 + length => {
+                        let mut octets = [0u8; 4];
+                        octets[..length].copy_from_slice(&data[4..]);
+                        Some(IpAddr::V4(Ipv4Addr::from_octets(octets)))
+                    }
+                    2 if length <= 16 && data.len() == 4 + length => {
+                        let mut octets = [0u8; 16];
+                        octets[..length].copy_from_slice(&data[4..]);
+                        Some(IpAddr::V6(Ipv6Addr::from_octets(octets)))
+                    }
+                    _ => None
+                };
+                match address {
+                    Some(address) => Self::ClientSubnet {
+                        source_prefix: data[2],
+                        scope_prefix: data[3],
+                        address
+                    },
+                    None => Self::Unknown(code, data.to_vec())
+                }
+            }
+            10 if data.len() == 8 || (16..=40).contains(&data.len()) => Self::Cookie {
+                client: *data[..8].as_array().unwrap(),
+                server: data[8..].to_vec()
+            },
+            _ => Self::Unknown(code, data.to_vec())
+        }
+    }
+}
+
+fn read_edns_options(data: &[u8]) -> Result<Vec<EdnsOption>, DeserializeError> {
+    let mut options = Vec::new();
+    let mut i = 0usize;
+    while i < data.len() {
+        if data.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+        let code = u16::from_be_bytes([data[i], data[i + 1]]);
+        let end = i + 4 + u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        if data.len() < end {return Err(DeserializeError::WrongDataLength);}
+        options.push(EdnsOption::from_code_and_data(code, &data[i + 4..end]));
+        i = end;
+    }
+    Ok(options)
+}
+
+/// EDNS(0) parameters, carried by OPT pseudo-record in Additional section
+/// Use `DnsMessage::edns()` and `DnsMessage::set_edns()` to access it
+#[derive(Debug, Clone)]
+pub struct Edns {
+    /// Maximum UDP payload size sender can receive, carried in record class field
+    pub udp_payload_size: u16,
+    /// Upper `8 bits` of `12 bits` response code
+    pub extended_rcode: u8,
+    /// EDNS version, 0 for EDNS(0)
+    pub version: u8,
+    /// `DO` flag - sender accepts DNSSEC records
+    pub dnssec_ok: bool,
+    pub options: Vec<EdnsOption>
+}
+impl Edns {
+    /// Constructs EDNS(0) parameters without options
+    pub fn new(udp_payload_size: u16) -> Self {
+        Self {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            options: Vec::new()
+        }
+    }
+    /// Constructs `Edns` from OPT pseudo-record
+    /// Returns `None` if `record` isn't OPT record
+    pub fn from_record(record: &DnsRecord) -> Option<Self> {
+        if record.rtype != 41 {return None;}
+        let options = match &record.data {
+            DnsRecordData::Opt(options) => options.clone(),
+            DnsRecordData::Raw(data) if data.is_empty() => Vec::new(),
+            _ => return None
+        };
+        let ttl = record.ttl.to_be_bytes();
+        Some(Self {
+            udp_payload_size: record.class,
+            extended_rcode: ttl[0],
+            version: ttl[1],
+            dnssec_ok: ttl[2] & 0x80 != 0,
+            options
+        })
+    }
+    /// Constructs OPT pseudo-record with root name
+    pub fn into_record(self) -> DnsRecord {
+        DnsRecord {
+            name: String::new(),
+            rtype: 41,
+            class: self.udp_payload_size,
+            ttl: u32::from_be_bytes([self.extended_rcode, self.version, (self.dnssec_ok as u8) << 7, 0]),
+            data: DnsRecordData::Opt(self.options)
+        }
+    }
+}
+
+/// Struct for DNS Message
+/// You can construct it from scratch with `DnsMessage::new()` and consistently editing
+/// Or construct from existing message bytes with `DnsMessage::deserialize()`
+/// Note that section counts are calculated automatically on serialization
+#[derive(Debug, Clone)]
+pub struct DnsMessage {
+    /// Identifier for matching queries and responses
+    pub id: u16,
+    /// `QR` flag - this message is response
+    pub response: bool,
+    /// Operation code, 0 for standard query
+    pub opcode: u8,
+    /// `AA` flag - Authoritative Answer
+    pub authoritative: bool,
+    /// `TC` flag - message was truncated
+    pub truncated: bool,
+    /// `RD` flag - Recursion Desired
+    pub recursion_desired: bool,
+    /// `RA` flag - Recursion Available
+    pub recursion_available: bool,
+    /// `AD` flag - Authentic Data
+    pub authentic_data: bool,
+    /// `CD` flag - Checking Disabled
+    pub checking_disabled: bool,
+    /// Response code, i.e. 0 for NOERROR, 3 for NXDOMAIN
+    pub rcode: u8,
+    pub questions: Vec<DnsQuestion>,
+    pub answers: Vec<DnsRecord>,
+    pub authorities: Vec<DnsRecord>,
+    pub additionals: Vec<DnsRecord>
+}
+impl DnsMessage {
+    /// Constructs an empty `DnsMessage`
+    pub fn new() -> Self {
+        Self {
+            id: 0,
+            response: false,
+            opcode: 0,
+            authoritative: false,
+            truncated: false,
+            recursion_desired: false,
+            recursion_available: false,
+            authentic_data: false,
+            checking_disabled: false,
+            rcode: 0,
+            questions: Vec::new(),
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new()
+        }
+    }
+    /// Constructs standard query with single question and `RD` flag set
+    pub fn query(id: u16, name: &str, qtype: u16) -> Self {
+        Self {
+            id,
+            recursion_desired: true,
+            questions: vec![DnsQuestion::new(name, qtype)],
+            ..Self::new()
+        }
+    }
+    /// Constructs response to `query` with the same identifier, opcode, `RD` flag and questions
+    pub fn response_to(query: &DnsMessage, answers: Vec<DnsRecord>) -> Self {
+        Self {
+            id: query.id,
+            response: true,
+            opcode: query.opcode,
+            recursion_desired: query.recursion_desired,
+            questions: query.questions.clone(),
+            answers,
+            ..Self::new()
+        }
+    }
+    /// Returns `2 bytes` of header flags
+    pub fn flags(&self) -> u16 {
+        (self.response as u16) << 15 |
+        ((self.opcode & 0xF) as u16) << 11 |
+        (self.authoritative as u16) << 10 |
+        (self.truncated as u16) << 9 |
+        (self.recursion_desired as u16) << 8 |
+        (self.recursion_available as u16) << 7 |
+        (self.authentic_data as u16) << 5 |
+        (self.checking_disabled as u16) << 4 |
+        (self.rcode & 0xF) as u16
+    }
+    /// Sets header flags from `2 bytes`
+    pub fn set_flags(&mut self, flags: u16) {
+        self.response = flags & 0x8000 != 0;
+        self.opcode = ((flags >> 11) & 0xF) as u8;
+        self.authoritative = flags & 0x0400 != 0;
+        self.truncated = flags & 0x0200 != 0;
+        self.recursion_desired = flags & 0x0100 != 0;
+        self.recursion_available = flags & 0x0080 != 0;
+        self.authentic_data = flags & 0x0020 != 0;
+        self.checking_disabled = flags & 0x0010 != 0;
+        self.rcode = (flags & 0xF) as u8;
+    }
+    /// Returns EDNS parameters from the first OPT pseudo-record of Additional section
+    pub fn edns(&self) -> Option<Edns> {
+        self.additionals.iter().find_map(Edns::from_record)
+    }
+    /// Replaces OPT pseudo-record of Additional section with `edns` or removes it if `edns` is `None`
+    pub fn set_edns(&mut self, edns: Option<Edns>) {
+        self.additionals.retain(|record| record.rtype != 41);
+        if let Some(edns) = edns {
+            self.additionals.push(edns.into_record());
+        }
+    }
+    /// Returns full `12 bits` response code, combining header `rcode` with extended one from EDNS
+    pub fn full_rcode(&self) -> u16 {
+        let extended = self.edns().map(|edns| edns.extended_rcode).unwrap_or(0);
+        (extended as u16) << 4 | (self.rcode & 0xF) as u16
+    }
+    /// Serializes message without name compression, as `serialize()` does
+    /// Returns `Err(InvalidDomainName)` with the first name, which has label longer than `63 bytes` or is longer than `255 bytes` encoded
+    pub fn try_serialize(self) -> Result<Vec<u8>, InvalidDomainName> {
+        self.serialize_with(None, false)
+    }
+    /// Serializes message with name compression, replacing repeated name suffixes with pointers like real servers do
+    /// Names inside NS, CNAME, PTR and MX records data are compressed too
+    /// Returns `Err(InvalidDomainName)` as `try_serialize()` does
+    pub fn serialize_compressed(self) -> Result<Vec<u8>, InvalidDomainName> {
+        self.serialize_with(Some(&mut HashMap::new()), false)
+    }
+    /// Serializes message for DNS over TCP, i.e. compressed and prefixed with `2 bytes` length, as described in RFC 7766
+    /// Returns `Err(InvalidDomainName)` as `try_serialize()` does
+    pub fn serialize_tcp(self) -> Result<Vec<u8>, InvalidDomainName> {
+        let message = self.serialize_compressed()?;
+        let mut result = Vec::with_capacity(2 + message.len());
+        result.extend_from_slice(&(message.len() as u16).to_be_bytes());
+        result.extend_from_slice(&message);
+        Ok(result)
+    }
+    /// Parses the first length-prefixed message of DNS over TCP `stream`
+    /// Returns message and count of consumed bytes or `Err(DeserializeError::Incomplete)` if message isn't fully received yet
+    pub fn deserialize_tcp(stream: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        require_stream_length(stream, 2)?;
+        let length = u16::from_be_bytes([stream[0], stream[1]]) as usize;
+        require_stream_length(stream, 2 + length)?;
+        Ok((Self::deserialize(&stream[2..2 + length])?, 2 + length))
+    }
+    /// Returns length of message serialized without name compression, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        12 + self.questions.iter().map(|question| name_len(&question.name) + 4).sum::<usize>()
+            + self.answers.iter().chain(&self.authorities).chain(&self.additionals).map(DnsRecord::wire_len).sum::<usize>()
+    }
+    fn serialize_with(self, mut compression: Option<&mut HashMap<Vec<u8>, u16>>, truncate: bool) -> Result<Vec<u8>, InvalidDomainName> {
+        let mut result = Vec::with_capacity(512);
+        result.extend_from_slice(&self.id.to_be_bytes());
+        result.extend_from_slice(&self.flags().to_be_bytes());
+        for count in [self.questions.len(), self.answers.len(), self.authorities.len(), self.additionals.len()] {
+            result.extend_from_slice(&(count as u16).to_be_bytes());
+        }
+        for question in self.questions {
+            write_name(&question.name, &mut result, compression.as_deref_mut(), truncate)?;
+            result.extend_from_slice(&question.qtype.to_be_bytes());
+            result.extend_from_slice(&question.qclass.to_be_bytes());
+        }
+        for record in self.answers.into_iter().chain(self.authorities).chain(self.additionals) {
+            record.write(&mut result, compression.as_deref_mut(), truncate)?;
+        }
+        Ok(result)
+    }
+}
+impl Default for DnsMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for DnsMessage {
+    /// Converts message to bytes without name compression
+    /// Labels longer than `63 bytes` are truncated and labels past `255 bytes` of encoded name are dropped, use `try_serialize()` to get error instead
+    fn serialize(self) -> Vec<u8> {
+        self.serialize_with(None, true).unwrap_or_default()
+    }
+}
+impl Deserializable for DnsMessage {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 12 {return Err(DeserializeError::WrongDataLength);}
+        let mut message = Self::new();
+        message.id = u16::from_be_bytes([bytes[0], bytes[1]]);
+        message.set_flags(u16::from_be_bytes([bytes[2], bytes[3]]));
+        let count = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        let mut i = 12usize;
+        for _ in 0..count(4) {
+            let (name, end) = read_name(bytes, i)?;
+            if bytes.len() < end + 4 {return Err(DeserializeError::WrongDataLength);}
+            message.questions.push(DnsQuestion {
+                name,
+                qtype: u16::from_be_bytes([bytes[end], bytes[end + 1]]),
+                qclass: u16::from_be_bytes([bytes[end + 2], bytes[end + 3]])
+            });
+            i = end + 4;
+        }
+        for (section, records) in [(6, &mut message.answers), (8, &mut message.authorities), (10, &mut message.additionals)] {
+            for _ in 0..count(section) {
+                let (record, end) = DnsRecord::read(bytes, i)?;
+                records.push(record);
+                i = end;
+            }
+        }
+        Ok(message)
+    }
+}
\ No newline at end of file
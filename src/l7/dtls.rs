@@ -0,0 +1,112 @@
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// Record layer version of DTLS 1.0
+pub const DTLS_1_0: u16 = 0xFEFF;
+/// Record layer version of DTLS 1.2, also used by DTLS 1.3 records with full header
+pub const DTLS_1_2: u16 = 0xFEFD;
+
+/// Content type of DTLS record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DtlsContentType {
+    ChangeCipherSpec = 20,
+    Alert = 21,
+    Handshake = 22,
+    ApplicationData = 23,
+    Heartbeat = 24,
+    /// Record with Connection ID of RFC 9146
+    Tls12Cid = 25
+}
+impl DtlsContentType {
+    /// Returns `None` if `value` isn't a known content type
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            20 => Some(Self::ChangeCipherSpec),
+            21 => Some(Self::Alert),
+            22 => Some(Self::Handshake),
+            23 => Some(Self::ApplicationData),
+            24 => Some(Self::Heartbeat),
+            25 => Some(Self::Tls12Cid),
+            _ => None
+        }
+    }
+}
+
+/// DTLS record with full `13 bytes` header, as seen by on-path observer
+/// Fragment is protected after handshake, so it's presented as is
+/// You can construct it from existing record bytes with `DtlsRecord::deserialize()`
+/// Or all records of UDP datagram with `DtlsRecord::deserialize_datagram()`
+/// Note that records with Connection ID and DTLS 1.3 unified headers are not parsed, since their length depends on negotiated parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtlsRecord {
+    pub content_type: DtlsContentType,
+    /// `DTLS_1_0` or `DTLS_1_2`
+    pub version: u16,
+    /// Incremented on each cipher state change
+    pub epoch: u16,
+    /// Record sequence number, actually only 48 bits
+    pub sequence_number: u64,
+    pub fragment: Vec<u8>
+}
+impl DtlsRecord {
+    /// Returns handshake message type of unprotected handshake record, i.e. 1 for ClientHello, 2 for ServerHello, 3 for HelloVerifyRequest
+    /// Returns `None` for other records and handshake records of non-zero epoch, which are encrypted
+    pub fn handshake_type(&self) -> Option<u8> {
+        if self.content_type != DtlsContentType::Handshake || self.epoch != 0 {return None;}
+        self.fragment.first().copied()
+    }
+    /// Returns length of serialized record, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        13 + self.fragment.len()
+    }
+    /// Deserializes all records of UDP datagram payload
+    pub fn deserialize_datagram(bytes: &[u8]) -> Result<Vec<Self>, DeserializeError> {
+        let mut records = Vec::new();
+        let mut i = 0usize;
+        while i < bytes.len() {
+            let record = Self::deserialize(&bytes[i..])?;
+            i += 13 + record.fragment.len();
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+impl Serializable for DtlsRecord {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(13 + self.fragment.len());
+        result.push(self.content_type as u8);
+        result.extend_from_slice(&self.version.to_be_bytes());
+        result.extend_from_slice(&self.epoch.to_be_bytes());
+        result.extend_from_slice(&self.sequence_number.to_be_bytes()[2..]);
+        result.extend_from_slice(&(self.fragment.len() as u16).to_be_bytes());
+        result.append(&mut self.fragment);
+        result
+    }
+}
+impl Deserializable for DtlsRecord {
+    /// Constructs `DtlsRecord` from bytes
+    /// Returns `Err(DeserializeError::WrongData)` if content type or version is unknown
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 13 {return Err(DeserializeError::WrongDataLength);}
+        let content_type = DtlsContentType::from_u8(bytes[0]).ok_or(DeserializeError::WrongData)?;
+        if content_type == DtlsContentType::Tls12Cid {return Err(DeserializeError::WrongData);}
+        let version = u16::from_be_bytes([bytes[1], bytes[2]]);
+        if version != DTLS_1_0 && version != DTLS_1_2 {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[11], bytes[12]]) as usize;
+        if bytes.len() < 13 + length {return Err(DeserializeError::WrongDataLength);}
+        let mut sequence_number = [0u8; 8];
+        sequence_number[2..].copy_from_slice(&bytes[5..11]);
+        Ok(Self {
+            content_type,
+            version,
+            epoch: u16::from_be_bytes([bytes[3], bytes[4]]),
+            sequence_number: u64::from_be_bytes(sequence_number),
+            fragment: bytes[13..13 + length].to_vec()
+        })
+    }
+}
+
+/// Checks whether UDP datagram `payload` consists of DTLS records, i.e. every record has known content type 20-24 and version `0xFEFF` or `0xFEFD`, and record lengths add up to payload length
+/// Useful to tell CoAP over DTLS or any other DTLS traffic on non-standard ports from plain UDP protocols
+pub fn is_dtls(payload: &[u8]) -> bool {
+    !payload.is_empty() && DtlsRecord::deserialize_datagram(payload).is_ok()
+}
@@ -0,0 +1,162 @@
+use crate::l3::IpPacket;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// Maximum length of extension header content, which `1 byte` length field in `4 bytes` units can hold
+pub const GTP_EXTENSION_MAX_CONTENT: usize = 1018;
+
+/// GTP-U Extension Header
+/// GTP-U Extension Header are consist of:
+///   1. 1 byte length in `4 bytes` units
+///   2. N bytes content
+///   3. 1 byte next extension header type
+#[derive(Debug, Clone)]
+pub struct GtpExtensionHeader {
+    /// Extension header type, i.e. 0x85 for PDU Session Container, 0xC0 for PDCP PDU Number
+    pub kind: u8,
+    /// Header content, zero-padded so `content.len() + 2` is multiple of 4 and truncated to `GTP_EXTENSION_MAX_CONTENT` bytes on serialization
+    pub content: Vec<u8>
+}
+impl GtpExtensionHeader {
+    /// Returns length of content after truncation and padding
+    fn padded_len(&self) -> usize {
+        (self.content.len().min(GTP_EXTENSION_MAX_CONTENT) + 2).div_ceil(4) * 4 - 2
+    }
+}
+
+/// Struct for GTPv1-U Packet, carried by UDP datagrams on port 2152
+/// You can construct it from scratch with `GtpuPacket::new()` and consistently editing
+/// Or construct from existing packet bytes with `GtpuPacket::deserialize()` or from UDP datagram with `GtpuPacket::from_udp()`
+#[derive(Debug, Clone)]
+pub struct GtpuPacket {
+    /// Message type, i.e. 255 for G-PDU which carries user IP packet, 1 and 2 for Echo Request and Echo Response
+    pub message_type: u8,
+    /// Tunnel Endpoint Identifier
+    pub teid: u32,
+    /// Sequence number, present when `S` flag is set
+    pub sequence_number: Option<u16>,
+    /// N-PDU number, present when `PN` flag is set
+    pub n_pdu_number: Option<u8>,
+    /// Extension headers, present when `E` flag is set
+    pub extension_headers: Vec<GtpExtensionHeader>,
+    pub payload: Vec<u8>
+}
+impl GtpuPacket {
+    /// Constructs an empty G-PDU `GtpuPacket`
+    pub fn new() -> Self {
+        Self {
+            message_type: 255,
+            teid: 0,
+            sequence_number: None,
+            n_pdu_number: None,
+            extension_headers: Vec::new(),
+            payload: Vec::new()
+        }
+    }
+    /// Constructs a G-PDU carrying `packet` through tunnel `teid`
+    pub fn encapsulate(teid: u32, packet: IpPacket) -> Self {
+        Self {
+            teid,
+            payload: packet.serialize(),
+            ..Self::new()
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let optional = self.sequence_number.is_some() || self.n_pdu_number.is_some() || !self.extension_headers.is_empty();
+        let extensions = self.extension_headers.iter().map(|header| 2 + header.padded_len()).sum::<usize>();
+        8 + optional as usize * (4 + extensions) + self.payload.len()
+    }
+    /// Returns inner IP packet of G-PDU
+    /// Returns `Err(DeserializeError::WrongData)` if this is not a G-PDU or its payload is not an IP packet
+    pub fn decapsulate(&self) -> Result<IpPacket, DeserializeError> {
+        if self.message_type != 255 {return Err(DeserializeError::WrongData);}
+        IpPacket::deserialize(&self.payload)
+    }
+    /// Constructs `GtpuPacket` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 2152
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        if datagram.source != 2152 && datagram.destination != 2152 {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps packet into UDP datagram with both ports set to 2152
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn into_udp(self) -> UdpDatagram {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = 2152;
+        datagram.destination = 2152;
+        datagram.payload = self.serialize();
+        datagram
+    }
+}
+impl Default for GtpuPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for GtpuPacket {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = vec![0u8; 8];
+        result[0] = 0b0011_0000;
+        result[1] = self.message_type;
+        result[4..8].copy_from_slice(&self.teid.to_be_bytes());
+        let has_extensions = !self.extension_headers.is_empty();
+        if self.sequence_number.is_some() || self.n_pdu_number.is_some() || has_extensions {
+            result[0] |= (has_extensions as u8) << 2;
+            result[0] |= (self.sequence_number.is_some() as u8) << 1;
+            result[0] |= self.n_pdu_number.is_some() as u8;
+            result.extend_from_slice(&self.sequence_number.unwrap_or(0).to_be_bytes());
+            result.push(self.n_pdu_number.unwrap_or(0));
+            for mut header in self.extension_headers {
+                let length = header.padded_len();
+                header.content.resize(length, 0);
+                result.push(header.kind);
+                result.push(((length + 2) / 4) as u8);
+                result.append(&mut header.content);
+            }
+            result.push(0);
+        }
+        result.append(&mut self.payload);
+        let length = (result.len() - 8) as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for GtpuPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[0] >> 5 != 1 || bytes[0] & 0x10 == 0 {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if bytes.len() < 8 + length {return Err(DeserializeError::WrongDataLength);}
+        let bytes = &bytes[..8 + length];
+        let mut packet = Self::new();
+        packet.message_type = bytes[1];
+        packet.teid = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let mut i = 8usize;
+        if bytes[0] & 0b111 != 0 {
+            if bytes.len() < 12 {return Err(DeserializeError::WrongDataLength);}
+            if bytes[0] & 2 != 0 {
+                packet.sequence_number = Some(u16::from_be_bytes([bytes[8], bytes[9]]));
+            }
+            if bytes[0] & 1 != 0 {
+                packet.n_pdu_number = Some(bytes[10]);
+            }
+            let mut next_type = if bytes[0] & 4 != 0 {bytes[11]} else {0};
+            i = 12;
+            while next_type != 0 {
+                if bytes.len() < i + 1 {return Err(DeserializeError::WrongDataLength);}
+                let header_len = bytes[i] as usize * 4;
+                if header_len == 0 {return Err(DeserializeError::WrongData);}
+                if bytes.len() < i + header_len {return Err(DeserializeError::WrongDataLength);}
+                packet.extension_headers.push(GtpExtensionHeader {
+                    kind: next_type,
+                    content: bytes[i + 1..i + header_len - 1].to_vec()
+                });
+                next_type = bytes[i + header_len - 1];
+                i += header_len;
+            }
+        }
+        packet.payload = bytes[i..].to_vec();
+        Ok(packet)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,151 @@
+use crate::l4::udp::UdpDatagram;
+use crate::l7::netflow::{FlowRecord, FlowSet, FlowTemplate, TemplateCache};
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// IANA registered IPFIX port
+pub const IPFIX_PORT: u16 = 4739;
+
+/// Struct for IPFIX Message, RFC 7011
+/// You can construct it from scratch with `IpfixMessage::new()` and consistently editing, or use `IpfixExporter` to produce export streams
+/// Or construct from existing message bytes with `IpfixMessage::deserialize()` or from UDP datagram with `IpfixMessage::from_udp()`
+/// Note that message length is calculated automatically on serialization
+#[derive(Debug, Clone)]
+pub struct IpfixMessage {
+    /// Export time in seconds since UNIX epoch
+    pub export_time: u32,
+    /// Total number of data records sent in previous messages of this observation domain
+    pub sequence: u32,
+    /// Observation domain ID, used as template cache key
+    pub observation_domain: u32,
+    pub sets: Vec<FlowSet>
+}
+impl IpfixMessage {
+    /// Constructs an empty `IpfixMessage`
+    pub fn new(observation_domain: u32) -> Self {
+        Self {
+            export_time: 0,
+            sequence: 0,
+            observation_domain,
+            sets: Vec::new()
+        }
+    }
+    /// Returns length of serialized message, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        16 + self.sets.iter().map(|set| set.wire_len(true)).sum::<usize>()
+    }
+    /// Learns templates of this message into `cache` and decodes all data sets it can
+    /// Data sets with unknown templates are skipped
+    pub fn decode_records(&self, cache: &mut TemplateCache) -> Result<Vec<FlowRecord>, DeserializeError> {
+        let mut records = Vec::new();
+        for set in &self.sets {
+            cache.learn(self.observation_domain, set);
+            if let Some(decoded) = cache.decode(self.observation_domain, set) {
+                records.append(&mut decoded?);
+            }
+        }
+        Ok(records)
+    }
+    /// Constructs `IpfixMessage` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 4739
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        if datagram.source != IPFIX_PORT && datagram.destination != IPFIX_PORT {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps message into UDP datagram sent from `source_port` to port 4739
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn into_udp(self, source_port: u16) -> UdpDatagram {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = source_port;
+        datagram.destination = IPFIX_PORT;
+        datagram.payload = self.serialize();
+        datagram
+    }
+}
+impl Serializable for IpfixMessage {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(1500);
+        result.extend_from_slice(&10u16.to_be_bytes());
+        result.extend_from_slice(&[0, 0]);
+        result.extend_from_slice(&self.export_time.to_be_bytes());
+        result.extend_from_slice(&self.sequence.to_be_bytes());
+        result.extend_from_slice(&self.observation_domain.to_be_bytes());
+        for set in self.sets {
+            set.write(&mut result, true);
+        }
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for IpfixMessage {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 16 {return Err(DeserializeError::WrongDataLength);}
+        if u16::from_be_bytes([bytes[0], bytes[1]]) != 10 {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 16 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        let long = |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Ok(Self {
+            export_time: long(4),
+            sequence: long(8),
+            observation_domain: long(12),
+            sets: FlowSet::read_all(&bytes[16..length], true)?
+        })
+    }
+}
+
+/// Builder of IPFIX export stream for one observation domain
+/// Keeps sequence number consistent and sends templates with the first message and after `resend_templates()`
+#[derive(Debug, Clone)]
+pub struct IpfixExporter {
+    observation_domain: u32,
+    sequence: u32,
+    templates: Vec<FlowTemplate>,
+    templates_sent: bool
+}
+impl IpfixExporter {
+    pub fn new(observation_domain: u32) -> Self {
+        Self {
+            observation_domain,
+            sequence: 0,
+            templates: Vec::new(),
+            templates_sent: false
+        }
+    }
+    /// Adds `template`, replacing one with the same ID, and schedules templates to be sent again
+    pub fn template(&mut self, template: FlowTemplate) -> &mut Self {
+        self.templates.retain(|existing| existing.template_id != template.template_id);
+        self.templates.push(template);
+        self.templates_sent = false;
+        self
+    }
+    /// Schedules templates to be sent with the next message, as UDP exporters do periodically
+    pub fn resend_templates(&mut self) -> &mut Self {
+        self.templates_sent = false;
+        self
+    }
+    /// Constructs next message with data set of `records` encoded with template `template_id`
+    /// Returns `None` if template `template_id` wasn't added
+    pub fn export(&mut self, export_time: u32, template_id: u16, records: &[FlowRecord]) -> Option<IpfixMessage> {
+        let template = self.templates.iter().find(|template| template.template_id == template_id)?;
+        let data = template.encode_records(records);
+        let mut message = IpfixMessage::new(self.observation_domain);
+        message.export_time = export_time;
+        message.sequence = self.sequence;
+        if !self.templates_sent {
+            let (options, templates): (Vec<FlowTemplate>, Vec<FlowTemplate>) = self.templates.iter().cloned().partition(|template| template.scope_field_count != 0);
+            if !templates.is_empty() {
+                message.sets.push(FlowSet::Templates(templates));
+            }
+            if !options.is_empty() {
+                message.sets.push(FlowSet::OptionsTemplates(options));
+            }
+            self.templates_sent = true;
+        }
+        if !records.is_empty() {
+            message.sets.push(FlowSet::Data {template_id, data});
+        }
+        self.sequence = self.sequence.wrapping_add(records.len() as u32);
+        Some(message)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,237 @@
+use crate::l2::ppp::PppFrame;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// Maximum length of AVP value, which `10 bits` length field of whole AVP can hold
+pub const L2TP_AVP_MAX_VALUE: usize = 1017;
+
+/// L2TP Attribute Value Pair, which makes up body of control messages
+/// L2TP AVP are consist of:
+///   1. 1 bit Mandatory flag, 1 bit Hidden flag, 4 reserved bits and 10 bits length of whole AVP
+///   2. 2 bytes Vendor ID
+///   3. 2 bytes Attribute Type
+///   4. N bytes value
+#[derive(Debug, Clone)]
+pub struct L2tpAvp {
+    /// `M` flag - receiver must terminate tunnel or session if it doesn't recognize this AVP
+    pub mandatory: bool,
+    /// `H` flag - value is hidden(encrypted) with shared secret
+    pub hidden: bool,
+    /// 0 for IETF defined attributes
+    pub vendor_id: u16,
+    /// Attribute type, i.e. 0 for Message Type, 9 for Assigned Tunnel ID, 14 for Assigned Session ID
+    pub attribute_type: u16,
+    /// Value of at most `L2TP_AVP_MAX_VALUE` bytes, longer value is truncated on serialization
+    pub value: Vec<u8>
+}
+impl L2tpAvp {
+    /// Constructs mandatory IETF Message Type AVP
+    pub fn message_type(message_type: u16) -> Self {
+        Self {
+            mandatory: true,
+            hidden: false,
+            vendor_id: 0,
+            attribute_type: 0,
+            value: message_type.to_be_bytes().to_vec()
+        }
+    }
+    /// Returns length of serialized AVP, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        6 + self.value.len().min(L2TP_AVP_MAX_VALUE)
+    }
+}
+impl Serializable for L2tpAvp {
+    /// Converts AVP to bytes, value is truncated to `L2TP_AVP_MAX_VALUE` bytes
+    fn serialize(mut self) -> Vec<u8> {
+        self.value.truncate(L2TP_AVP_MAX_VALUE);
+        let length = self.value.len() as u16 + 6;
+        let mut result = Vec::with_capacity(length as usize);
+        result.extend_from_slice(&((self.mandatory as u16) << 15 | (self.hidden as u16) << 14 | length).to_be_bytes());
+        result.extend_from_slice(&self.vendor_id.to_be_bytes());
+        result.extend_from_slice(&self.attribute_type.to_be_bytes());
+        result.append(&mut self.value);
+        result
+    }
+}
+impl Deserializable for L2tpAvp {
+    /// Constructs `L2tpAvp` from bytes
+    /// Note that this method is know where AVP ends
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 6 {return Err(DeserializeError::WrongDataLength);}
+        let length = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x3FF) as usize;
+        if length < 6 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            mandatory: bytes[0] & 0x80 != 0,
+            hidden: bytes[0] & 0x40 != 0,
+            vendor_id: u16::from_be_bytes([bytes[2], bytes[3]]),
+            attribute_type: u16::from_be_bytes([bytes[4], bytes[5]]),
+            value: bytes[6..length].to_vec()
+        })
+    }
+}
+
+/// Struct for L2TPv2 Packet, carried by UDP datagrams on port 1701
+/// You can construct it from scratch with `L2tpPacket::new()` and consistently editing
+/// Or construct from existing packet bytes with `L2tpPacket::deserialize()` or from UDP datagram with `L2tpPacket::from_udp()`
+/// Note that `Length` field is always emitted on serialization
+#[derive(Debug, Clone)]
+pub struct L2tpPacket {
+    /// `T` flag - this is control message, which has AVPs instead of PPP frame
+    pub control: bool,
+    /// `P` flag - data message should be treated preferentially in local queuing
+    pub priority: bool,
+    pub tunnel_id: u16,
+    pub session_id: u16,
+    /// `Ns` and `Nr` sequence numbers, present when `S` flag is set
+    /// Note that they are mandatory for control messages
+    pub sequence: Option<(u16, u16)>,
+    /// Offset padding size, present when `O` flag is set
+    pub offset_size: Option<u16>,
+    /// AVPs of control message, empty for data messages and Zero-Length Body acknowledgements
+    pub avps: Vec<L2tpAvp>,
+    /// PPP frame of data message, empty for control messages
+    pub payload: Vec<u8>
+}
+impl L2tpPacket {
+    /// Constructs an empty data `L2tpPacket`
+    pub fn new() -> Self {
+        Self {
+            control: false,
+            priority: false,
+            tunnel_id: 0,
+            session_id: 0,
+            sequence: None,
+            offset_size: None,
+            avps: Vec::new(),
+            payload: Vec::new()
+        }
+    }
+    /// Constructs a control `L2tpPacket` with Message Type AVP, i.e. 1 for SCCRQ, 2 for SCCRP, 6 for HELLO
+    pub fn control(tunnel_id: u16, session_id: u16, ns: u16, nr: u16, message_type: u16) -> Self {
+        Self {
+            control: true,
+            tunnel_id,
+            session_id,
+            sequence: Some((ns, nr)),
+            avps: vec![L2tpAvp::message_type(message_type)],
+            ..Self::new()
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        8 + self.sequence.map_or(0, |_| 4)
+            + self.offset_size.map_or(0, |offset_size| 2 + offset_size as usize)
+            + self.avps.iter().map(L2tpAvp::wire_len).sum::<usize>()
+            + self.payload.len()
+    }
+    /// Returns value of Message Type AVP, which has to be first AVP of control message
+    /// Returns `None` for data messages and Zero-Length Body acknowledgements
+    pub fn message_type(&self) -> Option<u16> {
+        let avp = self.avps.first()?;
+        if avp.vendor_id != 0 || avp.attribute_type != 0 || avp.value.len() != 2 {return None;}
+        Some(u16::from_be_bytes([avp.value[0], avp.value[1]]))
+    }
+    /// Returns PPP frame carried by data message
+    /// Returns `Err(DeserializeError::WrongData)` for control messages
+    pub fn ppp_frame(&self) -> Result<PppFrame, DeserializeError> {
+        if self.control {return Err(DeserializeError::WrongData);}
+        PppFrame::deserialize(&self.payload)
+    }
+    /// Constructs `L2tpPacket` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 1701
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        if datagram.source != 1701 && datagram.destination != 1701 {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps packet into UDP datagram with both ports set to 1701
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn into_udp(self) -> UdpDatagram {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = 1701;
+        datagram.destination = 1701;
+        datagram.payload = self.serialize();
+        datagram
+    }
+}
+impl Default for L2tpPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for L2tpPacket {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut flags = 0x4002u16;
+        flags |= (self.control as u16) << 15;
+        flags |= (self.sequence.is_some() as u16) << 11;
+        flags |= (self.offset_size.is_some() as u16) << 9;
+        flags |= (self.priority as u16) << 8;
+        let mut result = flags.to_be_bytes().to_vec();
+        result.extend_from_slice(&[0, 0]);
+        result.extend_from_slice(&self.tunnel_id.to_be_bytes());
+        result.extend_from_slice(&self.session_id.to_be_bytes());
+        if let Some((ns, nr)) = self.sequence {
+            result.extend_from_slice(&ns.to_be_bytes());
+            result.extend_from_slice(&nr.to_be_bytes());
+        }
+        if let Some(offset_size) = self.offset_size {
+            result.extend_from_slice(&offset_size.to_be_bytes());
+            result.append(&mut vec![0; offset_size as usize]);
+        }
+        for avp in self.avps {
+            result.append(&mut avp.serialize());
+        }
+        result.append(&mut self.payload);
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for L2tpPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 6 {return Err(DeserializeError::WrongDataLength);}
+        let flags = u16::from_be_bytes([bytes[0], bytes[1]]);
+        if flags & 0xF != 2 {return Err(DeserializeError::WrongData);}
+        let mut packet = Self::new();
+        packet.control = flags & 0x8000 != 0;
+        packet.priority = flags & 0x0100 != 0;
+        let mut bytes = bytes;
+        let mut i = 2usize;
+        if flags & 0x4000 != 0 {
+            let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+            if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+            bytes = &bytes[..length];
+            i += 2;
+        }
+        if bytes.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+        packet.tunnel_id = u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        packet.session_id = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]);
+        i += 4;
+        if flags & 0x0800 != 0 {
+            if bytes.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+            packet.sequence = Some((
+                u16::from_be_bytes([bytes[i], bytes[i + 1]]),
+                u16::from_be_bytes([bytes[i + 2], bytes[i + 3]])
+            ));
+            i += 4;
+        }
+        if flags & 0x0200 != 0 {
+            if bytes.len() < i + 2 {return Err(DeserializeError::WrongDataLength);}
+            let offset_size = u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+            i += 2 + offset_size as usize;
+            if bytes.len() < i {return Err(DeserializeError::WrongDataLength);}
+            packet.offset_size = Some(offset_size);
+        }
+        if packet.control {
+            while i < bytes.len() {
+                let avp = L2tpAvp::deserialize(&bytes[i..])?;
+                i += avp.value.len() + 6;
+                packet.avps.push(avp);
+            }
+        }
+        else {
+            packet.payload = bytes[i..].to_vec();
+        }
+        Ok(packet)
+    }
+}
\ No newline at end of file
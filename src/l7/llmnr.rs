@@ -0,0 +1,93 @@
+use core::net::{Ipv4Addr, Ipv6Addr};
+use crate::l4::udp::UdpDatagram;
+use crate::l7::dns::{DnsMessage, DnsRecord, InvalidDomainName};
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// IPv4 multicast address LLMNR queries are sent to
+pub const LLMNR_MULTICAST_V4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 252);
+/// IPv6 multicast address LLMNR queries are sent to
+pub const LLMNR_MULTICAST_V6: Ipv6Addr = Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 1, 3);
+/// UDP and TCP port of LLMNR
+pub const LLMNR_PORT: u16 = 5355;
+
+/// Struct for LLMNR Message, as described in RFC 4795
+/// Use `conflict()` and `tentative()` instead of `authoritative` and `recursion_desired` fields of inner `DnsMessage`
+/// LLMNR reuses DNS wire format, but header flags have different meaning:
+///   1. `C`(Conflict) flag takes place of DNS `AA` flag
+///   2. `T`(Tentative) flag takes place of DNS `RD` flag
+///   3. `RA`, `AD` and `CD` flags are reserved and should be zero
+#[derive(Debug, Clone)]
+pub struct LlmnrMessage {
+    pub message: DnsMessage
+}
+impl LlmnrMessage {
+    /// Constructs LLMNR query for `name`, which should be single-label host name
+    pub fn query(id: u16, name: &str, qtype: u16) -> Self {
+        let mut message = DnsMessage::query(id, name, qtype);
+        message.recursion_desired = false;
+        Self {message}
+    }
+    /// Constructs LLMNR response to `query` with the same identifier and question
+    pub fn response_to(query: &LlmnrMessage, answers: Vec<DnsRecord>) -> Self {
+        let mut message = DnsMessage::response_to(&query.message, answers);
+        message.recursion_desired = false;
+        Self {message}
+    }
+    /// Returns `C` flag
+    /// In query it means that sender detected conflict, in response it means that name is not unique
+    pub fn conflict(&self) -> bool {
+        self.message.authoritative
+    }
+    /// Sets `C` flag
+    pub fn set_conflict(&mut self, conflict: bool) {
+        self.message.authoritative = conflict;
+    }
+    /// Returns `T` flag, which means that responder hasn't verified uniqueness of name yet
+    pub fn tentative(&self) -> bool {
+        self.message.recursion_desired
+    }
+    /// Sets `T` flag
+    pub fn set_tentative(&mut self, tentative: bool) {
+        self.message.recursion_desired = tentative;
+    }
+    /// Returns length of serialized message, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.message.wire_len()
+    }
+    /// Returns `true` if message is a valid query: standard opcode and exactly one question
+    pub fn is_valid_query(&self) -> bool {
+        !self.message.response && self.message.opcode == 0 && self.message.questions.len() == 1
+    }
+    /// Constructs `LlmnrMessage` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 5355
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        if datagram.source != LLMNR_PORT && datagram.destination != LLMNR_PORT {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps query into UDP datagram from `source` port to port 5355
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses, usually `LLMNR_MULTICAST_V4` or `LLMNR_MULTICAST_V6`
+    pub fn into_udp(self, source: u16) -> UdpDatagram {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = source;
+        datagram.destination = LLMNR_PORT;
+        datagram.payload = self.serialize();
+        datagram
+    }
+    /// Serializes message as `DnsMessage::try_serialize()` does
+    /// Returns `Err(InvalidDomainName)` if any name has label longer than `63 bytes` or is longer than `255 bytes` encoded
+    pub fn try_serialize(self) -> Result<Vec<u8>, InvalidDomainName> {
+        self.message.try_serialize()
+    }
+}
+impl Serializable for LlmnrMessage {
+    fn serialize(self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+impl Deserializable for LlmnrMessage {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self {
+            message: DnsMessage::deserialize(bytes)?
+        })
+    }
+}
\ No newline at end of file
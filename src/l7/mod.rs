@@ -0,0 +1,34 @@
+#[cfg(feature = "bgp")]
+pub mod bgp;
+#[cfg(feature = "rip")]
+pub mod rip;
+#[cfg(feature = "gtp")]
+pub mod gtp;
+#[cfg(feature = "l2tp")]
+pub mod l2tp;
+#[cfg(feature = "wol")]
+pub mod wol;
+#[cfg(feature = "dns")]
+pub mod dns;
+#[cfg(feature = "llmnr")]
+pub mod llmnr;
+#[cfg(feature = "dhcp")]
+pub mod dhcp;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "netflow")]
+pub mod netflow;
+#[cfg(feature = "ipfix")]
+pub mod ipfix;
+#[cfg(feature = "ptp")]
+pub mod ptp;
+#[cfg(feature = "modbus")]
+pub mod modbus;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "coap")]
+pub mod coap;
+#[cfg(feature = "dtls")]
+pub mod dtls;
+#[cfg(feature = "dnp3")]
+pub mod dnp3;
\ No newline at end of file
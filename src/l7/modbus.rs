@@ -0,0 +1,461 @@
+use crate::l4::tcp::TcpSegment;
+use crate::util::{Serializable, Deserializable, DeserializeError, require_stream_length};
+
+/// TCP port of Modbus servers
+pub const MODBUS_PORT: u16 = 502;
+
+/// Packs coil values into bytes, the first coil is the least significant bit of the first byte
+fn pack_coils(values: &[bool]) -> Vec<u8> {
+    let mut result = vec![0u8; values.len().div_ceil(8)];
+    for (i, value) in values.iter().enumerate() {
+        if *value {result[i / 8] |= 1 << (i % 8);}
+    }
+    result
+}
+
+fn unpack_coils(bytes: &[u8], count: usize) -> Vec<bool> {
+    (0..count).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect()
+}
+
+fn registers(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks_exact(2).map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]])).collect()
+}
+
+/// Parses `byte count` field at `bytes[index]` and returns data after it, which should take the rest of PDU
+fn counted_data(bytes: &[u8], index: usize) -> Result<&[u8], DeserializeError> {
+    if bytes.len() <= index {return Err(DeserializeError::WrongDataLength);}
+    let count = bytes[index] as usize;
+    if bytes.len() != index + 1 + count {return Err(DeserializeError::WrongDataLength);}
+    Ok(&bytes[index + 1..])
+}
+
+/// Modbus request PDU, its variant is function code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModbusRequest {
+    /// Function 1
+    ReadCoils {
+        address: u16,
+        /// Number of coils, `1..=2000`
+        quantity: u16
+    },
+    /// Function 2
+    ReadDiscreteInputs {
+        address: u16,
+        quantity: u16
+    },
+    /// Function 3
+    ReadHoldingRegisters {
+        address: u16,
+        /// Number of registers, `1..=125`
+        quantity: u16
+    },
+    /// Function 4
+    ReadInputRegisters {
+        address: u16,
+        quantity: u16
+    },
+    /// Function 5
+    WriteSingleCoil {
+        address: u16,
+        /// Encoded as 0xFF00 for ON and 0x0000 for OFF
+        value: bool
+    },
+    /// Function 6
+    WriteSingleRegister {
+        address: u16,
+        value: u16
+    },
+    /// Function 15
+    WriteMultipleCoils {
+        address: u16,
+        values: Vec<bool>
+    },
+    /// Function 16
+    WriteMultipleRegisters {
+        address: u16,
+        values: Vec<u16>
+    },
+    /// Other functions, i.e. 23 for Read/Write Multiple Registers, 43 for Read Device Identification
+    Other {
+        function: u8,
+        data: Vec<u8>
+    }
+}
+impl ModbusRequest {
+    pub fn get_function(&self) -> u8 {
+        match self {
+            Self::ReadCoils {..} => 1,
+            Self::ReadDiscreteInputs {..} => 2,
+            Self::ReadHoldingRegisters {..} => 3,
+            Self::ReadInputRegisters {..} => 4,
+            Self::WriteSingleCoil {..} => 5,
+            Self::WriteSingleRegister {..} => 6,
+            Self::WriteMultipleCoils {..} => 15,
+            Self::WriteMultipleRegisters {..} => 16,
+            Self::Other {function, ..} => *function
+        }
+    }
+    /// Returns length of serialized PDU including function code, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::WriteMultipleCoils {values, ..} => 6 + values.len().div_ceil(8),
+            Self::WriteMultipleRegisters {values, ..} => 6 + values.len() * 2,
+            Self::Other {data, ..} => 1 + data.len(),
+            _ => 5
+        }
+    }
+}
+impl Serializable for ModbusRequest {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![self.get_function()];
+        match self {
+            Self::ReadCoils {address, quantity} | Self::ReadDiscreteInputs {address, quantity} |
+            Self::ReadHoldingRegisters {address, quantity} | Self::ReadInputRegisters {address, quantity} => {
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&quantity.to_be_bytes());
+            }
+            Self::WriteSingleCoil {address, value} => {
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&(if value {0xFF00u16} else {0}).to_be_bytes());
+            }
+            Self::WriteSingleRegister {address, value} => {
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&value.to_be_bytes());
+            }
+            Self::WriteMultipleCoils {address, values} => {
+                let coils = pack_coils(&values);
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&(values.len() as u16).to_be_bytes());
+                result.push(coils.len() as u8);
+                result.extend_from_slice(&coils);
+            }
+            Self::WriteMultipleRegisters {address, values} => {
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&(values.len() as u16).to_be_bytes());
+                result.push((values.len() * 2) as u8);
+                for value in values {
+                    result.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            Self::Other {data, ..} => result.extend_from_slice(&data)
+        }
+        result
+    }
+}
+impl Deserializable for ModbusRequest {
+    /// Constructs `ModbusRequest` from PDU bytes, which start with function code
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        let function = bytes[0];
+        if !matches!(function, 1..=6 | 15 | 16) {return Ok(Self::Other {function, data: bytes[1..].to_vec()});}
+        if bytes.len() < 5 {return Err(DeserializeError::WrongDataLength);}
+        let address = u16::from_be_bytes([bytes[1], bytes[2]]);
+        let value = u16::from_be_bytes([bytes[3], bytes[4]]);
+        if function <= 6 && bytes.len() != 5 {return Err(DeserializeError::WrongDataLength);}
+        Ok(match function {
+            1 => Self::ReadCoils {address, quantity: value},
+            2 => Self::ReadDiscreteInputs {address, quantity: value},
+            3 => Self::ReadHoldingRegisters {address, quantity: value},
+            4 => Self::ReadInputRegisters {address, quantity: value},
+            5 => match value {
+                0xFF00 => Self::WriteSingleCoil {address, value: true},
+                0x0000 => Self::WriteSingleCoil {address, value: false},
+                _ => return Err(DeserializeError::WrongData)
+            },
+            6 => Self::WriteSingleRegister {address, value},
+            15 => {
+                let coils = counted_data(bytes, 5)?;
+                if coils.len() != (value as usize).div_ceil(8) {return Err(DeserializeError::WrongData);}
+                Self::WriteMultipleCoils {address, values: unpack_coils(coils, value as usize)}
+            }
+            _ => {
+                let data = counted_data(bytes, 5)?;
+                if data.len() != value as usize * 2 {return Err(DeserializeError::WrongData);}
+                Self::WriteMultipleRegisters {address, values: registers(data)}
+            }
+        })
+    }
+}
+
+/// Modbus response PDU, its variant is function code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModbusResponse {
+    /// Function 1
+    /// Response carries only byte count, so `values` are padded with `false` to multiple of 8
+    ReadCoils {
+        values: Vec<bool>
+    },
+    /// Function 2, `values` are padded as in `ReadCoils`
+    ReadDiscreteInputs {
+        values: Vec<bool>
+    },
+    /// Function 3
+    ReadHoldingRegisters {
+        values: Vec<u16>
+    },
+    /// Function 4
+    ReadInputRegisters {
+        values: Vec<u16>
+    },
+    /// Function 5, echo of request
+    WriteSingleCoil {
+        address: u16,
+        value: bool
+    },
+    /// Function 6, echo of request
+    WriteSingleRegister {
+        address: u16,
+        value: u16
+    },
+    /// Function 15
+    WriteMultipleCoils {
+        address: u16,
+        quantity: u16
+    },
+    /// Function 16
+    WriteMultipleRegisters {
+        address: u16,
+        quantity: u16
+    },
+    /// Function code of request with the highest bit set
+    Exception {
+        /// Function code of request, without the highest bit
+        function: u8,
+        /// Exception code, i.e. 1 for illegal function, 2 for illegal data address, 3 for illegal data value
+        code: u8
+    },
+    Other {
+        function: u8,
+        data: Vec<u8>
+    }
+}
+impl ModbusResponse {
+    pub fn get_function(&self) -> u8 {
+        match self {
+            Self::ReadCoils {..} => 1,
+            Self::ReadDiscreteInputs {..} => 2,
+            Self::ReadHoldingRegisters {..} => 3,
+            Self::ReadInputRegisters {..} => 4,
+            Self::WriteSingleCoil {..} => 5,
+            Self::WriteSingleRegister {..} => 6,
+            Self::WriteMultipleCoils {..} => 15,
+            Self::WriteMultipleRegisters {..} => 16,
+            Self::Exception {function, ..} => *function | 0x80,
+            Self::Other {function, ..} => *function
+        }
+    }
+    pub fn is_exception(&self) -> bool {
+        matches!(self, Self::Exception {..})
+    }
+    /// Returns length of serialized PDU including function code, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::ReadCoils {values} | Self::ReadDiscreteInputs {values} => 2 + values.len().div_ceil(8),
+            Self::ReadHoldingRegisters {values} | Self::ReadInputRegisters {values} => 2 + values.len() * 2,
+            Self::Exception {..} => 2,
+            Self::Other {data, ..} => 1 + data.len(),
+            _ => 5
+        }
+    }
+}
+impl Deserializable for ModbusResponse {
+    /// Constructs `ModbusResponse` from PDU bytes, which start with function code
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        let function = bytes[0];
+        Ok(match function {
+            0x80.. => {
+                if bytes.len() != 2 {return Err(DeserializeError::WrongDataLength);}
+                Self::Exception {function: function & 0x7F, code: bytes[1]}
+            }
+            1 | 2 => {
+                let coils = counted_data(bytes, 1)?;
+                let values = unpack_coils(coils, coils.len() * 8);
+                if function == 1 {Self::ReadCoils {values}} else {Self::ReadDiscreteInputs {values}}
+            }
+            3 | 4 => {
+                let data = counted_data(bytes, 1)?;
+                if data.len() % 2 != 0 {return Err(DeserializeError::WrongData);}
+                let values = registers(data);
+                if function == 3 {Self::ReadHoldingRegisters {values}} else {Self::ReadInputRegisters {values}}
+            }
+            5 | 6 | 15 | 16 => {
+                if bytes.len() != 5 {return Err(DeserializeError::WrongDataLength);}
+                let address = u16::from_be_bytes([bytes[1], bytes[2]]);
+                let value = u16::from_be_bytes([bytes[3], bytes[4]]);
+                match function {
+                    5 => match value {
+                        0xFF00 => Self::WriteSingleCoil {address, value: true},
+                        0x0000 => Self::WriteSingleCoil {address, value: false},
+                        _ => return Err(DeserializeError::WrongData)
+                    },
+                    6 => Self::WriteSingleRegister {address, value},
+                    15 => Self::WriteMultipleCoils {address, quantity: value},
+                    _ => Self::WriteMultipleRegisters {address, quantity: value}
+                }
+            }
+            _ => Self::Other {function, data: bytes[1..].to_vec()}
+        })
+    }
+}
+impl Serializable for ModbusResponse {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![self.get_function()];
+        match self {
+            Self::ReadCoils {values} | Self::ReadDiscreteInputs {values} => {
+                let coils = pack_coils(&values);
+                result.push(coils.len() as u8);
+                result.extend_from_slice(&coils);
+            }
+            Self::ReadHoldingRegisters {values} | Self::ReadInputRegisters {values} => {
+                result.push((values.len() * 2) as u8);
+                for value in values {
+                    result.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            Self::WriteSingleCoil {address, value} => {
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&(if value {0xFF00u16} else {0}).to_be_bytes());
+            }
+            Self::WriteSingleRegister {address, value: quantity} | Self::WriteMultipleCoils {address, quantity} | Self::WriteMultipleRegisters {address, quantity} => {
+                result.extend_from_slice(&address.to_be_bytes());
+                result.extend_from_slice(&quantity.to_be_bytes());
+            }
+            Self::Exception {code, ..} => result.push(code),
+            Self::Other {data, ..} => result.extend_from_slice(&data)
+        }
+        result
+    }
+}
+
+/// Modbus PDU, which is either request from client or response from server
+/// Function codes are the same in both directions, so direction should be known to parse it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModbusPdu {
+    Request(ModbusRequest),
+    Response(ModbusResponse)
+}
+impl ModbusPdu {
+    pub fn is_request(&self) -> bool {
+        matches!(self, Self::Request(_))
+    }
+    pub fn get_function(&self) -> u8 {
+        match self {
+            Self::Request(request) => request.get_function(),
+            Self::Response(response) => response.get_function()
+        }
+    }
+    /// Returns length of serialized PDU, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::Request(request) => request.wire_len(),
+            Self::Response(response) => response.wire_len()
+        }
+    }
+}
+impl Serializable for ModbusPdu {
+    fn serialize(self) -> Vec<u8> {
+        match self {
+            Self::Request(request) => request.serialize(),
+            Self::Response(response) => response.serialize()
+        }
+    }
+}
+
+/// Struct for Modbus TCP ADU: MBAP header and PDU
+/// Protocol identifier of MBAP header is always zero and length is calculated on serialization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModbusAdu {
+    /// Identifier, which server copies from request to response
+    pub transaction_id: u16,
+    /// Address of serial device behind gateway, usually 0xFF or 1 when server is addressed directly
+    pub unit_id: u8,
+    pub pdu: ModbusPdu
+}
+impl ModbusAdu {
+    pub fn request(transaction_id: u16, unit_id: u8, request: ModbusRequest) -> Self {
+        Self {transaction_id, unit_id, pdu: ModbusPdu::Request(request)}
+    }
+    pub fn response(transaction_id: u16, unit_id: u8, response: ModbusResponse) -> Self {
+        Self {transaction_id, unit_id, pdu: ModbusPdu::Response(response)}
+    }
+    /// Returns length of serialized ADU with MBAP header, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        7 + self.pdu.wire_len()
+    }
+    /// Constructs `ModbusAdu` from bytes, which should be a single ADU sent by client if `request` is `true` and by server otherwise
+    pub fn deserialize_with(bytes: &[u8], request: bool) -> Result<Self, DeserializeError> {
+        if bytes.len() < 8 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[2..4] != [0, 0] {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        if bytes.len() != 6 + length {return Err(DeserializeError::WrongDataLength);}
+        Ok(Self {
+            transaction_id: u16::from_be_bytes([bytes[0], bytes[1]]),
+            unit_id: bytes[6],
+            pdu: if request {ModbusPdu::Request(ModbusRequest::deserialize(&bytes[7..])?)} else {ModbusPdu::Response(ModbusResponse::deserialize(&bytes[7..])?)}
+        })
+    }
+    /// Parses the first ADU of reassembled TCP `stream`, sent by client if `request` is `true` and by server otherwise
+    /// Returns ADU and its length in bytes or `Err(DeserializeError::Incomplete)` if ADU isn't fully received yet
+    pub fn deserialize_next(stream: &[u8], request: bool) -> Result<(Self, usize), DeserializeError> {
+        require_stream_length(stream, 7)?;
+        let length = 6 + u16::from_be_bytes([stream[4], stream[5]]) as usize;
+        if length < 8 {return Err(DeserializeError::WrongData);}
+        require_stream_length(stream, length)?;
+        Ok((Self::deserialize_with(&stream[..length], request)?, length))
+    }
+    /// Splits reassembled TCP stream into ADUs, sent by client if `request` is `true` and by server otherwise
+    /// Returns parsed ADUs and count of consumed bytes, so incomplete ADU at the end of `stream` can be kept until more data arrives
+    pub fn deserialize_stream(stream: &[u8], request: bool) -> Result<(Vec<Self>, usize), DeserializeError> {
+        let mut adus = Vec::new();
+        let mut i = 0usize;
+        loop {
+            match Self::deserialize_next(&stream[i..], request) {
+                Ok((adu, length)) => {
+                    adus.push(adu);
+                    i += length;
+                }
+                Err(DeserializeError::Incomplete {..}) => break,
+                Err(error) => return Err(error)
+            }
+        }
+        Ok((adus, i))
+    }
+    /// Constructs ADUs from payload of `segment`, which is request if it is sent to port 502 and response if it is sent from it
+    /// Returns `Err(DeserializeError::WrongData)` if none of `segment` ports is 502 and `Err(DeserializeError::Incomplete)` if the last ADU continues in the next segment
+    pub fn from_tcp(segment: &TcpSegment) -> Result<Vec<Self>, DeserializeError> {
+        if segment.source != MODBUS_PORT && segment.destination != MODBUS_PORT {return Err(DeserializeError::WrongData);}
+        let (adus, length) = Self::deserialize_stream(&segment.payload, segment.destination == MODBUS_PORT)?;
+        if length < segment.payload.len() {Self::deserialize_next(&segment.payload[length..], segment.destination == MODBUS_PORT)?;}
+        Ok(adus)
+    }
+    /// Wraps ADU into TCP segment with `PSH` and `ACK` flags, requests are sent from `client_port` to port 502 and responses in opposite direction
+    /// Note that sequence numbers and `checksum` aren't set, use `recalculate_checksum()` after setting up IP addresses
+    pub fn into_tcp(self, client_port: u16) -> TcpSegment {
+        let mut segment = TcpSegment::new();
+        if self.pdu.is_request() {
+            segment.source = client_port;
+            segment.destination = MODBUS_PORT;
+        }
+        else {
+            segment.source = MODBUS_PORT;
+            segment.destination = client_port;
+        }
+        segment.flags.psh = true;
+        segment.flags.ack = true;
+        segment.payload = self.serialize();
+        segment
+    }
+}
+impl Serializable for ModbusAdu {
+    fn serialize(self) -> Vec<u8> {
+        let pdu = self.pdu.serialize();
+        let mut result = Vec::with_capacity(7 + pdu.len());
+        result.extend_from_slice(&self.transaction_id.to_be_bytes());
+        result.extend_from_slice(&[0, 0]);
+        result.extend_from_slice(&(1 + pdu.len() as u16).to_be_bytes());
+        result.push(self.unit_id);
+        result.extend_from_slice(&pdu);
+        result
+    }
+}
\ No newline at end of file
@@ -0,0 +1,417 @@
+use crate::util::{Serializable, Deserializable, DeserializeError, require_stream_length};
+
+/// TCP port of MQTT brokers
+pub const MQTT_PORT: u16 = 1883;
+/// TCP port of MQTT over TLS
+pub const MQTT_TLS_PORT: u16 = 8883;
+
+fn write_string(value: &str, result: &mut Vec<u8>) {
+    write_binary(value.as_bytes(), result);
+}
+
+fn write_binary(value: &[u8], result: &mut Vec<u8>) {
+    result.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    result.extend_from_slice(value);
+}
+
+/// Reads `2 bytes` length prefixed data at `*i` and moves `i` after it
+fn read_binary<'a>(bytes: &'a [u8], i: &mut usize) -> Result<&'a [u8], DeserializeError> {
+    if bytes.len() < *i + 2 {return Err(DeserializeError::WrongDataLength);}
+    let length = u16::from_be_bytes([bytes[*i], bytes[*i + 1]]) as usize;
+    if bytes.len() < *i + 2 + length {return Err(DeserializeError::WrongDataLength);}
+    *i += 2 + length;
+    Ok(&bytes[*i - length..*i])
+}
+
+fn read_string(bytes: &[u8], i: &mut usize) -> Result<String, DeserializeError> {
+    String::from_utf8(read_binary(bytes, i)?.to_vec()).map_err(|_| DeserializeError::WrongData)
+}
+
+fn read_u16(bytes: &[u8], i: &mut usize) -> Result<u16, DeserializeError> {
+    if bytes.len() < *i + 2 {return Err(DeserializeError::WrongDataLength);}
+    *i += 2;
+    Ok(u16::from_be_bytes([bytes[*i - 2], bytes[*i - 1]]))
+}
+
+/// Parses Remaining Length variable byte integer at the start of `bytes`
+/// Returns value and its length in bytes or `Err(DeserializeError::Incomplete)` if it continues after the end of `bytes`
+fn read_remaining_length(bytes: &[u8]) -> Result<(usize, usize), DeserializeError> {
+    let mut value = 0usize;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == 4 {return Err(DeserializeError::WrongData);}
+        value |= ((byte & 0x7F) as usize) << (7 * i);
+        if byte & 0x80 == 0 {return Ok((value, i + 1));}
+    }
+    if bytes.len() >= 4 {return Err(DeserializeError::WrongData);}
+    Err(DeserializeError::Incomplete {needed: 1})
+}
+
+fn write_remaining_length(mut value: usize, result: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            result.push(byte);
+            break;
+        }
+        result.push(byte | 0x80);
+    }
+}
+
+/// Returns length of Remaining Length field written by `write_remaining_length()`, which takes `7 bits` of value per byte
+fn remaining_length_len(value: usize) -> usize {
+    value.max(1).ilog2() as usize / 7 + 1
+}
+
+/// Will message of CONNECT packet, which broker publishes when client disconnects ungracefully
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttWill {
+    pub topic: String,
+    pub message: Vec<u8>,
+    pub qos: u8,
+    pub retain: bool
+}
+
+/// MQTT control packet of version 3.1 or 3.1.1, its variant is packet type
+/// MQTT 5 packets have properties after variable header and can't be parsed with this enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MqttPacket {
+    /// Type 1
+    Connect {
+        /// "MQTT" for version 3.1.1 and "MQIsdp" for version 3.1
+        protocol_name: String,
+        /// 4 for version 3.1.1 and 3 for version 3.1
+        protocol_level: u8,
+        clean_session: bool,
+        /// Keep alive interval in seconds
+        keep_alive: u16,
+        client_id: String,
+        will: Option<MqttWill>,
+        username: Option<String>,
+        password: Option<Vec<u8>>
+    },
+    /// Type 2
+    Connack {
+        session_present: bool,
+        /// 0 for accepted connection, i.e. 4 for bad user name or password, 5 for not authorized
+        return_code: u8
+    },
+    /// Type 3
+    Publish {
+        /// Set when message is redelivered
+        duplicate: bool,
+        qos: u8,
+        retain: bool,
+        topic: String,
+        /// Present only when `qos` is 1 or 2
+        packet_id: Option<u16>,
+        payload: Vec<u8>
+    },
+    /// Type 4, acknowledgement of PUBLISH with QoS 1
+    Puback {
+        packet_id: u16
+    },
+    /// Type 5, the first acknowledgement of PUBLISH with QoS 2
+    Pubrec {
+        packet_id: u16
+    },
+    /// Type 6
+    Pubrel {
+        packet_id: u16
+    },
+    /// Type 7
+    Pubcomp {
+        packet_id: u16
+    },
+    /// Type 8
+    Subscribe {
+        packet_id: u16,
+        /// Topic filters with requested QoS
+        topics: Vec<(String, u8)>
+    },
+    /// Type 9
+    Suback {
+        packet_id: u16,
+        /// Granted QoS for each topic filter of SUBSCRIBE or 0x80 for failure
+        return_codes: Vec<u8>
+    },
+    /// Type 10
+    Unsubscribe {
+        packet_id: u16,
+        topics: Vec<String>
+    },
+    /// Type 11
+    Unsuback {
+        packet_id: u16
+    },
+    /// Type 12
+    Pingreq,
+    /// Type 13
+    Pingresp,
+    /// Type 14
+    Disconnect,
+    /// Reserved types 0 and 15, or 15 for AUTH of MQTT 5
+    Other {
+        packet_type: u8,
+        /// Lower 4 bits of fixed header
+        flags: u8,
+        body: Vec<u8>
+    }
+}
+impl MqttPacket {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Connect {..} => 1,
+            Self::Connack {..} => 2,
+            Self::Publish {..} => 3,
+            Self::Puback {..} => 4,
+            Self::Pubrec {..} => 5,
+            Self::Pubrel {..} => 6,
+            Self::Pubcomp {..} => 7,
+            Self::Subscribe {..} => 8,
+            Self::Suback {..} => 9,
+            Self::Unsubscribe {..} => 10,
+            Self::Unsuback {..} => 11,
+            Self::Pingreq => 12,
+            Self::Pingresp => 13,
+            Self::Disconnect => 14,
+            Self::Other {packet_type, ..} => *packet_type & 0xF
+        }
+    }
+    /// Returns lower 4 bits of fixed header, which are fixed for all types except PUBLISH
+    fn flags(&self) -> u8 {
+        match self {
+            Self::Publish {duplicate, qos, retain, ..} => (*duplicate as u8) << 3 | (qos & 3) << 1 | *retain as u8,
+            Self::Pubrel {..} | Self::Subscribe {..} | Self::Unsubscribe {..} => 2,
+            Self::Other {flags, ..} => flags & 0xF,
+            _ => 0
+        }
+    }
+    /// Returns length of serialized packet including fixed header, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let string = |value: &str| 2 + value.len();
+        let body = match self {
+            Self::Connect {protocol_name, client_id, will, username, password, ..} => {
+                string(protocol_name) + 4 + string(client_id)
+                    + will.as_ref().map_or(0, |will| string(&will.topic) + 2 + will.message.len())
+                    + username.as_deref().map_or(0, string)
+                    + password.as_ref().map_or(0, |password| 2 + password.len())
+            }
+            Self::Connack {..} => 2,
+            Self::Publish {topic, packet_id, payload, ..} => string(topic) + packet_id.map_or(0, |_| 2) + payload.len(),
+            Self::Puback {..} | Self::Pubrec {..} | Self::Pubrel {..} | Self::Pubcomp {..} | Self::Unsuback {..} => 2,
+            Self::Subscribe {topics, ..} => 2 + topics.iter().map(|(topic, _)| string(topic) + 1).sum::<usize>(),
+            Self::Suback {return_codes, ..} => 2 + return_codes.len(),
+            Self::Unsubscribe {topics, ..} => 2 + topics.iter().map(|topic| string(topic)).sum::<usize>(),
+            Self::Pingreq | Self::Pingresp | Self::Disconnect => 0,
+            Self::Other {body, ..} => body.len()
+        };
+        1 + remaining_length_len(body) + body
+    }
+    /// Constructs PUBLISH packet with QoS 0
+    pub fn publish(topic: &str, payload: Vec<u8>) -> Self {
+        Self::Publish {duplicate: false, qos: 0, retain: false, topic: topic.to_string(), packet_id: None, payload}
+    }
+    /// Returns topic of PUBLISH packet
+    pub fn topic(&self) -> Option<&str> {
+        match self {
+            Self::Publish {topic, ..} => Some(topic),
+            _ => None
+        }
+    }
+    /// Returns application message of PUBLISH packet
+    pub fn payload(&self) -> Option<&[u8]> {
+        match self {
+            Self::Publish {payload, ..} => Some(payload),
+            _ => None
+        }
+    }
+    /// Returns packet identifier, which is present in acknowledged PUBLISH and all packets of SUBSCRIBE, UNSUBSCRIBE and QoS flows
+    pub fn packet_id(&self) -> Option<u16> {
+        match self {
+            Self::Publish {packet_id, ..} => *packet_id,
+            Self::Puback {packet_id} | Self::Pubrec {packet_id} | Self::Pubrel {packet_id} | Self::Pubcomp {packet_id} |
+            Self::Subscribe {packet_id, ..} | Self::Suback {packet_id, ..} | Self::Unsubscribe {packet_id, ..} | Self::Unsuback {packet_id} => Some(*packet_id),
+            _ => None
+        }
+    }
+    /// Parses the first packet of reassembled TCP `stream`
+    /// Returns packet and its length in bytes or `Err(DeserializeError::Incomplete)` if packet isn't fully received yet
+    pub fn deserialize_next(stream: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        require_stream_length(stream, 2)?;
+        let (remaining, size) = read_remaining_length(&stream[1..])?;
+        let length = 1 + size + remaining;
+        require_stream_length(stream, length)?;
+        Ok((Self::deserialize(&stream[..length])?, length))
+    }
+    /// Splits reassembled TCP stream into MQTT packets
+    /// Returns parsed packets and count of consumed bytes, so incomplete packet at the end of `stream` can be kept until more data arrives
+    pub fn deserialize_stream(stream: &[u8]) -> Result<(Vec<Self>, usize), DeserializeError> {
+        let mut packets = Vec::new();
+        let mut i = 0usize;
+        loop {
+            match Self::deserialize_next(&stream[i..]) {
+                Ok((packet, length)) => {
+                    packets.push(packet);
+                    i += length;
+                }
+                Err(DeserializeError::Incomplete {..}) => break,
+                Err(error) => return Err(error)
+            }
+        }
+        Ok((packets, i))
+    }
+}
+impl Serializable for MqttPacket {
+    fn serialize(self) -> Vec<u8> {
+        let header = (self.get_type() << 4) | self.flags();
+        let mut body = Vec::new();
+        match self {
+            Self::Connect {protocol_name, protocol_level, clean_session, keep_alive, client_id, will, username, password} => {
+                write_string(&protocol_name, &mut body);
+                body.push(protocol_level);
+                let mut flags = (clean_session as u8) << 1;
+                if let Some(will) = &will {flags |= 0x04 | (will.qos & 3) << 3 | (will.retain as u8) << 5;}
+                if password.is_some() {flags |= 0x40;}
+                if username.is_some() {flags |= 0x80;}
+                body.push(flags);
+                body.extend_from_slice(&keep_alive.to_be_bytes());
+                write_string(&client_id, &mut body);
+                if let Some(will) = will {
+                    write_string(&will.topic, &mut body);
+                    write_binary(&will.message, &mut body);
+                }
+                if let Some(username) = username {write_string(&username, &mut body);}
+                if let Some(password) = password {write_binary(&password, &mut body);}
+            }
+            Self::Connack {session_present, return_code} => {
+                body.push(session_present as u8);
+                body.push(return_code);
+            }
+            Self::Publish {topic, packet_id, payload, ..} => {
+                write_string(&topic, &mut body);
+                if let Some(packet_id) = packet_id {body.extend_from_slice(&packet_id.to_be_bytes());}
+                body.extend_from_slice(&payload);
+            }
+            Self::Puback {packet_id} | Self::Pubrec {packet_id} | Self::Pubrel {packet_id} | Self::Pubcomp {packet_id} | Self::Unsuback {packet_id} => {
+                body.extend_from_slice(&packet_id.to_be_bytes());
+            }
+            Self::Subscribe {packet_id, topics} => {
+                body.extend_from_slice(&packet_id.to_be_bytes());
+                for (topic, qos) in topics {
+                    write_string(&topic, &mut body);
+                    body.push(qos);
+                }
+            }
+            Self::Suback {packet_id, return_codes} => {
+                body.extend_from_slice(&packet_id.to_be_bytes());
+                body.extend_from_slice(&return_codes);
+            }
+            Self::Unsubscribe {packet_id, topics} => {
+                body.extend_from_slice(&packet_id.to_be_bytes());
+                for topic in topics {
+                    write_string(&topic, &mut body);
+                }
+            }
+            Self::Pingreq | Self::Pingresp | Self::Disconnect => {}
+            Self::Other {body: data, ..} => body = data
+        }
+        let mut result = Vec::with_capacity(5 + body.len());
+        result.push(header);
+        write_remaining_length(body.len(), &mut result);
+        result.extend_from_slice(&body);
+        result
+    }
+}
+impl Deserializable for MqttPacket {
+    /// Constructs `MqttPacket` from bytes of a single packet, use `deserialize_next()` or `deserialize_stream()` for TCP streams
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+        let (remaining, size) = read_remaining_length(&bytes[1..]).map_err(|_| DeserializeError::WrongDataLength)?;
+        if bytes.len() != 1 + size + remaining {return Err(DeserializeError::WrongDataLength);}
+        let packet_type = bytes[0] >> 4;
+        let flags = bytes[0] & 0xF;
+        let body = &bytes[1 + size..];
+        let mut i = 0usize;
+        let packet = match packet_type {
+            1 => {
+                let protocol_name = read_string(body, &mut i)?;
+                if body.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+                let protocol_level = body[i];
+                let connect_flags = body[i + 1];
+                let keep_alive = u16::from_be_bytes([body[i + 2], body[i + 3]]);
+                i += 4;
+                let client_id = read_string(body, &mut i)?;
+                let will = if connect_flags & 0x04 != 0 {
+                    Some(MqttWill {
+                        topic: read_string(body, &mut i)?,
+                        message: read_binary(body, &mut i)?.to_vec(),
+                        qos: (connect_flags >> 3) & 3,
+                        retain: connect_flags & 0x20 != 0
+                    })
+                }
+                else {None};
+                let username = if connect_flags & 0x80 != 0 {Some(read_string(body, &mut i)?)} else {None};
+                let password = if connect_flags & 0x40 != 0 {Some(read_binary(body, &mut i)?.to_vec())} else {None};
+                Self::Connect {protocol_name, protocol_level, clean_session: connect_flags & 0x02 != 0, keep_alive, client_id, will, username, password}
+            }
+            2 => {
+                if body.len() != 2 {return Err(DeserializeError::WrongDataLength);}
+                i = 2;
+                Self::Connack {session_present: body[0] & 1 != 0, return_code: body[1]}
+            }
+            3 => {
+                let qos = (flags >> 1) & 3;
+                if qos == 3 {return Err(DeserializeError::WrongData);}
+                let topic = read_string(body, &mut i)?;
+                let packet_id = if qos > 0 {Some(read_u16(body, &mut i)?)} else {None};
+                let payload = body[i..].to_vec();
+                i = body.len();
+                Self::Publish {duplicate: flags & 0x08 != 0, qos, retain: flags & 1 != 0, topic, packet_id, payload}
+            }
+            4..=7 | 11 => {
+                let packet_id = read_u16(body, &mut i)?;
+                match packet_type {
+                    4 => Self::Puback {packet_id},
+                    5 => Self::Pubrec {packet_id},
+                    6 => Self::Pubrel {packet_id},
+                    7 => Self::Pubcomp {packet_id},
+                    _ => Self::Unsuback {packet_id}
+                }
+            }
+            8 => {
+                let packet_id = read_u16(body, &mut i)?;
+                let mut topics = Vec::new();
+                while i < body.len() {
+                    let topic = read_string(body, &mut i)?;
+                    if body.len() <= i {return Err(DeserializeError::WrongDataLength);}
+                    topics.push((topic, body[i]));
+                    i += 1;
+                }
+                Self::Subscribe {packet_id, topics}
+            }
+            9 => {
+                let packet_id = read_u16(body, &mut i)?;
+                i = body.len();
+                Self::Suback {packet_id, return_codes: body[2..].to_vec()}
+            }
+            10 => {
+                let packet_id = read_u16(body, &mut i)?;
+                let mut topics = Vec::new();
+                while i < body.len() {
+                    topics.push(read_string(body, &mut i)?);
+                }
+                Self::Unsubscribe {packet_id, topics}
+            }
+            12 => Self::Pingreq,
+            13 => Self::Pingresp,
+            14 => Self::Disconnect,
+            _ => {
+                i = body.len();
+                Self::Other {packet_type, flags, body: body.to_vec()}
+            }
+        };
+        if i != body.len() {return Err(DeserializeError::WrongDataLength);}
+        Ok(packet)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,549 @@
+use core::net::Ipv4Addr;
+use std::collections::HashMap;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// Field specifier of flow template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemplateField {
+    /// Field type or Information Element ID, i.e. 8 for source IPv4 address, 1 for octet count
+    pub field_type: u16,
+    /// Field length in bytes, 65535 for variable-length IPFIX fields
+    pub length: u16,
+    /// Private Enterprise Number of enterprise-specific IPFIX Information Element, always `None` in NetFlow v9
+    pub enterprise_number: Option<u32>
+}
+impl TemplateField {
+    /// Constructs field specifier of IANA field type
+    pub fn new(field_type: u16, length: u16) -> Self {
+        Self {
+            field_type,
+            length,
+            enterprise_number: None
+        }
+    }
+    /// Returns `true` for variable-length IPFIX fields, which values are prefixed with their length
+    pub fn is_variable_length(&self) -> bool {
+        self.length == 65535
+    }
+}
+
+/// Template describing layout of data records
+/// Options templates have first `scope_field_count` fields as scope fields
+#[derive(Debug, Clone)]
+pub struct FlowTemplate {
+    /// Template ID, 256 or greater
+    pub template_id: u16,
+    /// Number of scope fields at the start of `fields`, 0 for ordinary templates
+    pub scope_field_count: u16,
+    pub fields: Vec<TemplateField>
+}
+impl FlowTemplate {
+    /// Returns minimal length of one data record in bytes, variable-length fields are counted as `1 byte`
+    pub fn record_length(&self) -> usize {
+        self.fields.iter().map(|field| if field.is_variable_length() {1} else {field.length as usize}).sum()
+    }
+    /// Splits data of flow set into records, ignoring trailing padding shorter than one record
+    pub fn decode_records(&self, data: &[u8]) -> Result<Vec<FlowRecord>, DeserializeError> {
+        let length = self.record_length();
+        if length == 0 {return Err(DeserializeError::WrongData);}
+        let mut records = Vec::new();
+        let mut i = 0usize;
+        while i + length <= data.len() {
+            let mut values = Vec::with_capacity(self.fields.len());
+            for field in &self.fields {
+                let mut field_length = field.length as usize;
+                if field.is_variable_length() {
+                    if data.len() < i + 1 {return Err(DeserializeError::WrongDataLength);}
+                    field_length = data[i] as usize;
+                    i += 1;
+                    if field_length == 255 {
+                        if data.len() < i + 2 {return Err(DeserializeError::WrongDataLength);}
+                        field_length = u16::from_be_bytes([data[i], data[i + 1]]) as usize;
+                        i += 2;
+                    }
+                }
+                if data.len() < i + field_length {return Err(DeserializeError::WrongDataLength);}
+                values.push((*field, data[i..i + field_length].to_vec()));
+                i += field_length;
+            }
+            records.push(FlowRecord {values});
+        }
+        Ok(records)
+    }
+    /// Encodes `records` into data of flow set, values of variable-length fields are prefixed with their length
+    /// Values are taken in template order, missing values are filled with zeroes and fixed-length values are truncated or zero-padded
+    pub fn encode_records(&self, records: &[FlowRecord]) -> Vec<u8> {
+        let mut result = Vec::new();
+        for record in records {
+            for (i, field) in self.fields.iter().enumerate() {
+                let value = record.values.get(i).map(|(_, value)| value.as_slice()).unwrap_or(&[]);
+                if field.is_variable_length() {
+                    if value.len() < 255 {
+                        result.push(value.len() as u8);
+                    } else {
+                        result.push(255);
+                        result.extend_from_slice(&(value.len().min(65535) as u16).to_be_bytes());
+                    }
+                    result.extend_from_slice(&value[..value.len().min(65535)]);
+                } else {
+                    let length = field.length as usize;
+                    let start = result.len();
+                    result.extend_from_slice(&value[..value.len().min(length)]);
+                    result.resize(start + length, 0);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Data record decoded with its template
+#[derive(Debug, Clone)]
+pub struct FlowRecord {
+    /// Values of record fields in template order
+    pub values: Vec<(TemplateField, Vec<u8>)>
+}
+impl FlowRecord {
+    /// Constructs record from `values` in order of `template` fields
+    pub fn new(template: &FlowTemplate, values: Vec<Vec<u8>>) -> Self {
+        Self {
+            values: template.fields.iter().copied().zip(values).collect()
+        }
+    }
+    /// Returns raw value of first field with `field_type`
+    pub fn get(&self, field_type: u16) -> Option<&[u8]> {
+        self.values.iter().find(|(field, _)| field.field_type == field_type).map(|(_, value)| value.as_slice())
+    }
+    /// Returns value of unsigned integer field with `field_type` of up to `8 bytes`
+    pub fn get_unsigned(&self, field_type: u16) -> Option<u64> {
+        let value = self.get(field_type)?;
+        if value.len() > 8 {return None;}
+        Some(value.iter().fold(0u64, |result, byte| result << 8 | *byte as u64))
+    }
+    /// Returns value of IPv4 address field with `field_type`
+    pub fn get_ipv4(&self, field_type: u16) -> Option<Ipv4Addr> {
+        Some(Ipv4Addr::from_octets(*self.get(field_type)?.as_array()?))
+    }
+}
+
+/// Flow set of NetFlow v9 packet or set of IPFIX message
+#[derive(Debug, Clone)]
+pub enum FlowSet {
+    /// Flow set ID 0 in NetFlow v9, set ID 2 in IPFIX
+    Templates(Vec<FlowTemplate>),
+    /// Flow set ID 1 in NetFlow v9, set ID 3 in IPFIX
+    OptionsTemplates(Vec<FlowTemplate>),
+    /// Flow set ID equal to template ID, data can be decoded with `FlowTemplate::decode_records()` or `TemplateCache`
+    Data {
+        template_id: u16,
+        data: Vec<u8>
+    }
+}
+impl FlowSet {
+    /// Reads all flow sets until end of `bytes`, in IPFIX format if `ipfix` is `true`
+    pub(crate) fn read_all(bytes: &[u8], ipfix: bool) -> Result<Vec<Self>, DeserializeError> {
+        let base = if ipfix {2} else {0};
+        let mut sets = Vec::new();
+        let mut i = 0usize;
+        while i + 4 <= bytes.len() {
+            let id = u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+            let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+            if length < 4 {return Err(DeserializeError::WrongData);}
+            if bytes.len() < i + length {return Err(DeserializeError::WrongDataLength);}
+            let body = &bytes[i + 4..i + length];
+            sets.push(match id {
+                256.. => Self::Data {template_id: id, data: body.to_vec()},
+                _ if id == base => Self::Templates(read_templates(body, false, ipfix)?),
+                _ if id == base + 1 => Self::OptionsTemplates(read_templates(body, true, ipfix)?),
+                _ => return Err(DeserializeError::WrongData)
+            });
+            i += length;
+        }
+        Ok(sets)
+    }
+    /// Returns length of flow set written by `write()`, including padding
+    pub(crate) fn wire_len(&self, ipfix: bool) -> usize {
+        let fields_len = |fields: &[TemplateField]| fields.iter().map(|field| 4 + field.enterprise_number.filter(|_| ipfix).map_or(0, |_| 4)).sum::<usize>();
+        let body = match self {
+            Self::Templates(templates) => templates.iter().map(|template| 4 + fields_len(&template.fields)).sum(),
+            Self::OptionsTemplates(templates) => templates.iter().map(|template| 6 + fields_len(&template.fields)).sum(),
+            Self::Data {data, ..} => data.len()
+        };
+        (4 + body).next_multiple_of(4)
+    }
+    /// Writes flow set padded to `4 bytes` boundary, in IPFIX format if `ipfix` is `true`
+    pub(crate) fn write(self, result: &mut Vec<u8>, ipfix: bool) {
+        let base = if ipfix {2} else {0};
+        let start = result.len();
+        result.extend_from_slice(&[0, 0, 0, 0]);
+        let id = match self {
+            Self::Templates(templates) => {
+                for template in templates {
+                    result.extend_from_slice(&template.template_id.to_be_bytes());
+                    result.extend_from_slice(&(template.fields.len() as u16).to_be_bytes());
+                    write_fields(&template.fields, result, ipfix);
+                }
+                base
+            }
+            Self::OptionsTemplates(templates) => {
+                for template in templates {
+                    let scope = (template.scope_field_count as usize).min(template.fields.len());
+                    result.extend_from_slice(&template.template_id.to_be_bytes());
+                    if ipfix {
+                        result.extend_from_slice(&(template.fields.len() as u16).to_be_bytes());
+                        result.extend_from_slice(&(scope as u16).to_be_bytes());
+                    } else {
+                        result.extend_from_slice(&(scope as u16 * 4).to_be_bytes());
+                        result.extend_from_slice(&((template.fields.len() - scope) as u16 * 4).to_be_bytes());
+                    }
+                    write_fields(&template.fields, result, ipfix);
+                }
+                base + 1
+            }
+            Self::Data {template_id, data} => {
+                result.extend_from_slice(&data);
+                template_id
+            }
+        };
+        result.resize(result.len().next_multiple_of(4), 0);
+        let length = (result.len() - start) as u16;
+        result[start..start + 2].copy_from_slice(&id.to_be_bytes());
+        result[start + 2..start + 4].copy_from_slice(&length.to_be_bytes());
+    }
+}
+
+/// Reads `count` field specifiers, which in IPFIX have enterprise bit followed by Private Enterprise Number
+/// Returns fields and their length in bytes
+fn read_fields(bytes: &[u8], count: usize, ipfix: bool) -> Result<(Vec<TemplateField>, usize), DeserializeError> {
+    let mut fields = Vec::with_capacity(count);
+    let mut i = 0usize;
+    for _ in 0..count {
+        if bytes.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+        let mut field = TemplateField::new(u16::from_be_bytes([bytes[i], bytes[i + 1]]), u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]));
+        i += 4;
+        if ipfix && field.field_type & 0x8000 != 0 {
+            if bytes.len() < i + 4 {return Err(DeserializeError::WrongDataLength);}
+            field.field_type &= 0x7FFF;
+            field.enterprise_number = Some(u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]));
+            i += 4;
+        }
+        fields.push(field);
+    }
+    Ok((fields, i))
+}
+
+fn write_fields(fields: &[TemplateField], result: &mut Vec<u8>, ipfix: bool) {
+    for field in fields {
+        let enterprise_number = field.enterprise_number.filter(|_| ipfix);
+        let field_type = field.field_type & 0x7FFF | (enterprise_number.is_some() as u16) << 15;
+        result.extend_from_slice(&field_type.to_be_bytes());
+        result.extend_from_slice(&field.length.to_be_bytes());
+        if let Some(enterprise_number) = enterprise_number {
+            result.extend_from_slice(&enterprise_number.to_be_bytes());
+        }
+    }
+}
+
+/// Reads templates of Template or Options Template flow set body, ignoring trailing padding
+/// Options Template header carries scope and option lengths in bytes in NetFlow v9, but field counts in IPFIX
+fn read_templates(body: &[u8], options: bool, ipfix: bool) -> Result<Vec<FlowTemplate>, DeserializeError> {
+    let mut templates = Vec::new();
+    let mut i = 0usize;
+    while i + 4 <= body.len() {
+        let template_id = u16::from_be_bytes([body[i], body[i + 1]]);
+        if template_id == 0 {break;}
+        let first = u16::from_be_bytes([body[i + 2], body[i + 3]]) as usize;
+        let (count, scope) = match options {
+            false => {
+                i += 4;
+                (first, 0)
+            }
+            true => {
+                if body.len() < i + 6 {return Err(DeserializeError::WrongDataLength);}
+                let second = u16::from_be_bytes([body[i + 4], body[i + 5]]) as usize;
+                i += 6;
+                match ipfix {
+                    false => (first / 4 + second / 4, first / 4),
+                    true => (first, second)
+                }
+            }
+        };
+        let (fields, length) = read_fields(&body[i..], count, ipfix)?;
+        i += length;
+        templates.push(FlowTemplate {template_id, scope_field_count: scope as u16, fields});
+    }
+    Ok(templates)
+}
+
+/// Cache of templates learned from export packets, keyed by observation domain or source ID and template ID
+#[derive(Debug, Clone, Default)]
+pub struct TemplateCache {
+    templates: HashMap<(u32, u16), FlowTemplate>
+}
+impl TemplateCache {
+    /// Constructs an empty `TemplateCache`
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Remembers templates of `set` exported by `domain`, replacing ones with the same ID
+    pub fn learn(&mut self, domain: u32, set: &FlowSet) {
+        if let FlowSet::Templates(templates) | FlowSet::OptionsTemplates(templates) = set {
+            for template in templates {
+                self.templates.insert((domain, template.template_id), template.clone());
+            }
+        }
+    }
+    pub fn get(&self, domain: u32, template_id: u16) -> Option<&FlowTemplate> {
+        self.templates.get(&(domain, template_id))
+    }
+    /// Decodes records of data `set` exported by `domain`
+    /// Returns `None` if `set` isn't data set or its template wasn't learned yet
+    pub fn decode(&self, domain: u32, set: &FlowSet) -> Option<Result<Vec<FlowRecord>, DeserializeError>> {
+        match set {
+            FlowSet::Data {template_id, data} => Some(self.get(domain, *template_id)?.decode_records(data)),
+            _ => None
+        }
+    }
+}
+
+/// NetFlow v5 fixed flow record
+/// All fields of this record **are in native order**
+#[derive(Debug, Clone)]
+pub struct NetflowV5Record {
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+    pub next_hop: Ipv4Addr,
+    /// SNMP index of input interface
+    pub input: u16,
+    /// SNMP index of output interface
+    pub output: u16,
+    pub packets: u32,
+    pub octets: u32,
+    /// System uptime in milliseconds at start of flow
+    pub first: u32,
+    /// System uptime in milliseconds at last packet of flow
+    pub last: u32,
+    pub source_port: u16,
+    pub destination_port: u16,
+    /// Cumulative OR of TCP flags
+    pub tcp_flags: u8,
+    /// IP protocol, i.e. 6 for TCP, 17 for UDP
+    pub protocol: u8,
+    /// IP Type of Service
+    pub tos: u8,
+    pub source_as: u16,
+    pub destination_as: u16,
+    pub source_mask: u8,
+    pub destination_mask: u8
+}
+impl Serializable for NetflowV5Record {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(48);
+        result.extend_from_slice(&self.source.octets());
+        result.extend_from_slice(&self.destination.octets());
+        result.extend_from_slice(&self.next_hop.octets());
+        result.extend_from_slice(&self.input.to_be_bytes());
+        result.extend_from_slice(&self.output.to_be_bytes());
+        result.extend_from_slice(&self.packets.to_be_bytes());
+        result.extend_from_slice(&self.octets.to_be_bytes());
+        result.extend_from_slice(&self.first.to_be_bytes());
+        result.extend_from_slice(&self.last.to_be_bytes());
+        result.extend_from_slice(&self.source_port.to_be_bytes());
+        result.extend_from_slice(&self.destination_port.to_be_bytes());
+        result.extend_from_slice(&[0, self.tcp_flags, self.protocol, self.tos]);
+        result.extend_from_slice(&self.source_as.to_be_bytes());
+        result.extend_from_slice(&self.destination_as.to_be_bytes());
+        result.extend_from_slice(&[self.source_mask, self.destination_mask, 0, 0]);
+        result
+    }
+}
+impl Deserializable for NetflowV5Record {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 48 {return Err(DeserializeError::WrongDataLength);}
+        let address = |i: usize| Ipv4Addr::new(bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]);
+        let short = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        let long = |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Ok(Self {
+            source: address(0),
+            destination: address(4),
+            next_hop: address(8),
+            input: short(12),
+            output: short(14),
+            packets: long(16),
+            octets: long(20),
+            first: long(24),
+            last: long(28),
+            source_port: short(32),
+            destination_port: short(34),
+            tcp_flags: bytes[37],
+            protocol: bytes[38],
+            tos: bytes[39],
+            source_as: short(40),
+            destination_as: short(42),
+            source_mask: bytes[44],
+            destination_mask: bytes[45]
+        })
+    }
+}
+
+/// Struct for NetFlow v5 export packet
+/// Note that record count is calculated automatically on serialization
+#[derive(Debug, Clone)]
+pub struct NetflowV5Packet {
+    /// System uptime in milliseconds
+    pub sys_uptime: u32,
+    pub unix_secs: u32,
+    pub unix_nsecs: u32,
+    /// Sequence counter of total flows seen
+    pub flow_sequence: u32,
+    pub engine_type: u8,
+    pub engine_id: u8,
+    /// Sampling mode in upper `2 bits` and interval in lower `14 bits`
+    pub sampling_interval: u16,
+    pub records: Vec<NetflowV5Record>
+}
+impl Serializable for NetflowV5Packet {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(24 + self.records.len() * 48);
+        result.extend_from_slice(&5u16.to_be_bytes());
+        result.extend_from_slice(&(self.records.len() as u16).to_be_bytes());
+        result.extend_from_slice(&self.sys_uptime.to_be_bytes());
+        result.extend_from_slice(&self.unix_secs.to_be_bytes());
+        result.extend_from_slice(&self.unix_nsecs.to_be_bytes());
+        result.extend_from_slice(&self.flow_sequence.to_be_bytes());
+        result.extend_from_slice(&[self.engine_type, self.engine_id]);
+        result.extend_from_slice(&self.sampling_interval.to_be_bytes());
+        for record in self.records {
+            result.append(&mut record.serialize());
+        }
+        result
+    }
+}
+impl Deserializable for NetflowV5Packet {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 24 {return Err(DeserializeError::WrongDataLength);}
+        if u16::from_be_bytes([bytes[0], bytes[1]]) != 5 {return Err(DeserializeError::WrongData);}
+        let count = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if bytes.len() < 24 + count * 48 {return Err(DeserializeError::WrongDataLength);}
+        let long = |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut records = Vec::with_capacity(count);
+        for record in bytes[24..24 + count * 48].chunks(48) {
+            records.push(NetflowV5Record::deserialize(record)?);
+        }
+        Ok(Self {
+            sys_uptime: long(4),
+            unix_secs: long(8),
+            unix_nsecs: long(12),
+            flow_sequence: long(16),
+            engine_type: bytes[20],
+            engine_id: bytes[21],
+            sampling_interval: u16::from_be_bytes([bytes[22], bytes[23]]),
+            records
+        })
+    }
+}
+
+/// Struct for NetFlow v9 export packet
+/// Data flow sets are kept raw, use `TemplateCache` to decode them with templates from previous packets
+#[derive(Debug, Clone)]
+pub struct NetflowV9Packet {
+    /// Total number of template and data records
+    /// Note that it isn't recalculated on serialization, since data records can't be counted without templates
+    pub count: u16,
+    /// System uptime in milliseconds
+    pub sys_uptime: u32,
+    pub unix_secs: u32,
+    /// Sequence counter of exported packets
+    pub sequence: u32,
+    /// Exporter observation domain, used as template cache key
+    pub source_id: u32,
+    pub flow_sets: Vec<FlowSet>
+}
+impl NetflowV5Packet {
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        24 + self.records.len() * 48
+    }
+}
+impl NetflowV9Packet {
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        20 + self.flow_sets.iter().map(|set| set.wire_len(false)).sum::<usize>()
+    }
+    /// Learns templates of this packet into `cache` and decodes all data flow sets it can
+    /// Data flow sets with unknown templates are skipped
+    pub fn decode_records(&self, cache: &mut TemplateCache) -> Result<Vec<FlowRecord>, DeserializeError> {
+        let mut records = Vec::new();
+        for set in &self.flow_sets {
+            cache.learn(self.source_id, set);
+            if let Some(decoded) = cache.decode(self.source_id, set) {
+                records.append(&mut decoded?);
+            }
+        }
+        Ok(records)
+    }
+}
+impl Serializable for NetflowV9Packet {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(1500);
+        result.extend_from_slice(&9u16.to_be_bytes());
+        result.extend_from_slice(&self.count.to_be_bytes());
+        result.extend_from_slice(&self.sys_uptime.to_be_bytes());
+        result.extend_from_slice(&self.unix_secs.to_be_bytes());
+        result.extend_from_slice(&self.sequence.to_be_bytes());
+        result.extend_from_slice(&self.source_id.to_be_bytes());
+        for set in self.flow_sets {
+            set.write(&mut result, false);
+        }
+        result
+    }
+}
+impl Deserializable for NetflowV9Packet {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        if u16::from_be_bytes([bytes[0], bytes[1]]) != 9 {return Err(DeserializeError::WrongData);}
+        let long = |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Ok(Self {
+            count: u16::from_be_bytes([bytes[2], bytes[3]]),
+            sys_uptime: long(4),
+            unix_secs: long(8),
+            sequence: long(12),
+            source_id: long(16),
+            flow_sets: FlowSet::read_all(&bytes[20..], false)?
+        })
+    }
+}
+
+/// NetFlow export packet, distinguished by version field when deserializing
+#[derive(Debug, Clone)]
+pub enum NetflowPacket {
+    V5(NetflowV5Packet),
+    V9(NetflowV9Packet)
+}
+impl NetflowPacket {
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Self::V5(packet) => packet.wire_len(),
+            Self::V9(packet) => packet.wire_len()
+        }
+    }
+}
+impl Serializable for NetflowPacket {
+    fn serialize(self) -> Vec<u8> {
+        match self {
+            Self::V5(packet) => packet.serialize(),
+            Self::V9(packet) => packet.serialize()
+        }
+    }
+}
+impl Deserializable for NetflowPacket {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {return Err(DeserializeError::WrongDataLength);}
+        match u16::from_be_bytes([bytes[0], bytes[1]]) {
+            5 => Ok(Self::V5(NetflowV5Packet::deserialize(bytes)?)),
+            9 => Ok(Self::V9(NetflowV9Packet::deserialize(bytes)?)),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
\ No newline at end of file
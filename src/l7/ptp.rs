@@ -0,0 +1,320 @@
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::time::Duration;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Serializable, Deserializable, DeserializeError, EtherPayload};
+
+/// UDP port of PTP event messages, which are timestamped: Sync, Delay_Req, Pdelay_Req and Pdelay_Resp
+pub const PTP_EVENT_PORT: u16 = 319;
+/// UDP port of PTP general messages, i.e. Follow_Up, Delay_Resp and Announce
+pub const PTP_GENERAL_PORT: u16 = 320;
+/// IPv4 multicast address of all PTP messages except peer delay mechanism ones
+pub const PTP_PRIMARY_MULTICAST_V4: Ipv4Addr = Ipv4Addr::new(224, 0, 1, 129);
+/// IPv6 link-local multicast address of all PTP messages except peer delay mechanism ones
+pub const PTP_PRIMARY_MULTICAST_V6: Ipv6Addr = Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 0, 0x181);
+/// Ethernet multicast address of all PTP messages over raw Ethernet except peer delay mechanism ones
+pub const PTP_PRIMARY_MULTICAST_MAC: [u8; 6] = [0x01, 0x1B, 0x19, 0x00, 0x00, 0x00];
+
+/// PTP 80-bit timestamp: 48-bit seconds and 32-bit nanoseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PtpTimestamp {
+    /// Seconds since PTP epoch, actually only 48 bits
+    pub seconds: u64,
+    /// Nanoseconds, less than `1 000 000 000`
+    pub nanoseconds: u32
+}
+impl PtpTimestamp {
+    /// Constructs timestamp from time since PTP epoch, seconds are truncated to 48 bits
+    pub fn from_duration(time: Duration) -> Self {
+        Self {
+            seconds: time.as_secs() & 0xFFFF_FFFF_FFFF,
+            nanoseconds: time.subsec_nanos()
+        }
+    }
+    pub fn to_duration(&self) -> Duration {
+        Duration::new(self.seconds, self.nanoseconds)
+    }
+    fn to_bytes(self) -> [u8; 10] {
+        let mut result = [0u8; 10];
+        result[0..6].copy_from_slice(&self.seconds.to_be_bytes()[2..8]);
+        result[6..10].copy_from_slice(&self.nanoseconds.to_be_bytes());
+        result
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            seconds: u64::from_be_bytes([0, 0, bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5]]),
+            nanoseconds: u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]])
+        }
+    }
+}
+
+/// Identity of PTP port: clock identity, usually EUI-64 derived from MAC address, and port number
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PortIdentity {
+    pub clock_identity: [u8; 8],
+    pub port_number: u16
+}
+impl PortIdentity {
+    fn to_bytes(self) -> [u8; 10] {
+        let mut result = [0u8; 10];
+        result[0..8].copy_from_slice(&self.clock_identity);
+        result[8..10].copy_from_slice(&self.port_number.to_be_bytes());
+        result
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            clock_identity: bytes[0..8].try_into().unwrap(),
+            port_number: u16::from_be_bytes([bytes[8], bytes[9]])
+        }
+    }
+}
+
+/// Body of Announce message, which describes grandmaster for Best Master Clock Algorithm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PtpAnnounce {
+    pub origin_timestamp: PtpTimestamp,
+    /// Offset between TAI and UTC in seconds
+    pub current_utc_offset: i16,
+    pub grandmaster_priority1: u8,
+    /// Clock class, i.e. 6 for clock synchronized to primary reference, 248 for default
+    pub grandmaster_clock_class: u8,
+    /// Clock accuracy, i.e. 0x21 for `100 ns`, 0xFE for unknown
+    pub grandmaster_clock_accuracy: u8,
+    pub grandmaster_offset_scaled_log_variance: u16,
+    pub grandmaster_priority2: u8,
+    pub grandmaster_identity: [u8; 8],
+    /// Number of boundary clocks between grandmaster and sender
+    pub steps_removed: u16,
+    /// Time source of grandmaster, i.e. 0x20 for GPS, 0xA0 for internal oscillator
+    pub time_source: u8
+}
+
+/// Body of PTP message, its variant is message type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PtpBody {
+    /// Type 0, event message
+    Sync {
+        /// Approximate transmission time, precise one follows in Follow_Up for two-step clocks
+        origin_timestamp: PtpTimestamp
+    },
+    /// Type 1, event message
+    DelayReq {
+        origin_timestamp: PtpTimestamp
+    },
+    /// Type 8
+    FollowUp {
+        precise_origin_timestamp: PtpTimestamp
+    },
+    /// Type 9
+    DelayResp {
+        /// Time Delay_Req was received by master
+        receive_timestamp: PtpTimestamp,
+        requesting_port: PortIdentity
+    },
+    /// Type 11
+    Announce(PtpAnnounce),
+    /// Other types, i.e. 2 and 3 for Pdelay_Req and Pdelay_Resp, 12 for Signaling, 13 for Management
+    Other {
+        message_type: u8,
+        body: Vec<u8>
+    }
+}
+impl PtpBody {
+    pub fn get_type(&self) -> u8 {
+        match self {
+            Self::Sync {..} => 0,
+            Self::DelayReq {..} => 1,
+            Self::FollowUp {..} => 8,
+            Self::DelayResp {..} => 9,
+            Self::Announce(_) => 11,
+            Self::Other {message_type, ..} => *message_type & 0xF
+        }
+    }
+    /// Returns deprecated controlField value of PTPv1, which PTPv2 still fills for compatibility
+    fn control(&self) -> u8 {
+        match self.get_type() {
+            0 => 0,
+            1 => 1,
+            8 => 2,
+            9 => 3,
+            13 => 4,
+            _ => 5
+        }
+    }
+}
+
+/// Struct for IEEE 1588 Precision Time Protocol version 2 message
+/// It is carried by UDP datagrams on ports 319 and 320 or by Ethernet frames with protocol 0x88F7
+/// You can construct it from scratch with `PtpMessage::new()` and consistently editing
+/// Or construct from existing message bytes with `PtpMessage::deserialize()` or from UDP datagram with `PtpMessage::from_udp()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PtpMessage {
+    /// Transport specific nibble, i.e. 1 for 802.1AS(gPTP)
+    pub transport_specific: u8,
+    /// PTP version, 2 for IEEE 1588-2008
+    pub version: u8,
+    /// PTP minor version, 1 for IEEE 1588-2019
+    pub minor_version: u8,
+    pub domain: u8,
+    /// Flags, i.e. 0x0200 for two-step clock, 0x0400 for unicast
+    pub flags: u16,
+    /// Correction in nanoseconds multiplied by `2^16`, accumulates residence time of transparent clocks
+    pub correction: i64,
+    pub source_port: PortIdentity,
+    pub sequence_id: u16,
+    /// Logarithm base 2 of mean message interval in seconds, 0x7F for unicast and Delay_Resp
+    pub log_message_interval: i8,
+    pub body: PtpBody,
+    /// TLVs following message body, i.e. Path Trace TLV of Announce
+    pub suffix: Vec<u8>
+}
+impl PtpMessage {
+    /// Constructs an empty PTPv2 Sync message of domain 0
+    pub fn new() -> Self {
+        Self {
+            transport_specific: 0,
+            version: 2,
+            minor_version: 0,
+            domain: 0,
+            flags: 0,
+            correction: 0,
+            source_port: PortIdentity::default(),
+            sequence_id: 0,
+            log_message_interval: 0,
+            body: PtpBody::Sync {origin_timestamp: PtpTimestamp::default()},
+            suffix: Vec::new()
+        }
+    }
+    /// Returns length of serialized message including suffix, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let body = match &self.body {
+            PtpBody::Sync {..} | PtpBody::DelayReq {..} | PtpBody::FollowUp {..} => 10,
+            PtpBody::DelayResp {..} => 20,
+            PtpBody::Announce(_) => 30,
+            PtpBody::Other {body, ..} => body.len()
+        };
+        34 + body + self.suffix.len()
+    }
+    /// Checks if message is event message, which is timestamped on transmission and reception and is sent to port 319
+    pub fn is_event(&self) -> bool {
+        self.body.get_type() < 8
+    }
+    /// Constructs `PtpMessage` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 319 or 320
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        let ports = [PTP_EVENT_PORT, PTP_GENERAL_PORT];
+        if !ports.contains(&datagram.source) && !ports.contains(&datagram.destination) {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps message into UDP datagram with both ports set to 319 for event messages or to 320 for general ones
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses, usually `PTP_PRIMARY_MULTICAST_V4`
+    pub fn into_udp(self) -> UdpDatagram {
+        let port = if self.is_event() {PTP_EVENT_PORT} else {PTP_GENERAL_PORT};
+        let mut datagram = UdpDatagram::new();
+        datagram.source = port;
+        datagram.destination = port;
+        datagram.payload = self.serialize();
+        datagram
+    }
+}
+impl Default for PtpMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for PtpMessage {
+    fn serialize(mut self) -> Vec<u8> {
+        let mut result = vec![0u8; 34];
+        result[0] = (self.transport_specific << 4) | self.body.get_type();
+        result[1] = (self.minor_version << 4) | (self.version & 0xF);
+        result[4] = self.domain;
+        result[6..8].copy_from_slice(&self.flags.to_be_bytes());
+        result[8..16].copy_from_slice(&self.correction.to_be_bytes());
+        result[20..30].copy_from_slice(&self.source_port.to_bytes());
+        result[30..32].copy_from_slice(&self.sequence_id.to_be_bytes());
+        result[32] = self.body.control();
+        result[33] = self.log_message_interval as u8;
+        match self.body {
+            PtpBody::Sync {origin_timestamp: timestamp} |
+            PtpBody::DelayReq {origin_timestamp: timestamp} |
+            PtpBody::FollowUp {precise_origin_timestamp: timestamp} => result.extend_from_slice(&timestamp.to_bytes()),
+            PtpBody::DelayResp {receive_timestamp, requesting_port} => {
+                result.extend_from_slice(&receive_timestamp.to_bytes());
+                result.extend_from_slice(&requesting_port.to_bytes());
+            }
+            PtpBody::Announce(announce) => {
+                result.extend_from_slice(&announce.origin_timestamp.to_bytes());
+                result.extend_from_slice(&announce.current_utc_offset.to_be_bytes());
+                result.push(0);
+                result.push(announce.grandmaster_priority1);
+                result.push(announce.grandmaster_clock_class);
+                result.push(announce.grandmaster_clock_accuracy);
+                result.extend_from_slice(&announce.grandmaster_offset_scaled_log_variance.to_be_bytes());
+                result.push(announce.grandmaster_priority2);
+                result.extend_from_slice(&announce.grandmaster_identity);
+                result.extend_from_slice(&announce.steps_removed.to_be_bytes());
+                result.push(announce.time_source);
+            }
+            PtpBody::Other {mut body, ..} => result.append(&mut body)
+        }
+        result.append(&mut self.suffix);
+        let length = result.len() as u16;
+        result[2..4].copy_from_slice(&length.to_be_bytes());
+        result
+    }
+}
+impl Deserializable for PtpMessage {
+    /// Constructs `PtpMessage` from bytes, padding of short Ethernet frames is stripped using message length
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 34 {return Err(DeserializeError::WrongDataLength);}
+        if bytes[1] & 0xF != 2 {return Err(DeserializeError::WrongData);}
+        let length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if length < 34 {return Err(DeserializeError::WrongData);}
+        if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+        let body = &bytes[34..length];
+        let body_length = match bytes[0] & 0xF {
+            0 | 1 | 8 => 10,
+            9 => 20,
+            11 => 30,
+            _ => body.len()
+        };
+        if body.len() < body_length {return Err(DeserializeError::WrongDataLength);}
+        let timestamp = || PtpTimestamp::from_bytes(body);
+        let parsed = match bytes[0] & 0xF {
+            0 => PtpBody::Sync {origin_timestamp: timestamp()},
+            1 => PtpBody::DelayReq {origin_timestamp: timestamp()},
+            8 => PtpBody::FollowUp {precise_origin_timestamp: timestamp()},
+            9 => PtpBody::DelayResp {receive_timestamp: timestamp(), requesting_port: PortIdentity::from_bytes(&body[10..20])},
+            11 => PtpBody::Announce(PtpAnnounce {
+                origin_timestamp: timestamp(),
+                current_utc_offset: i16::from_be_bytes([body[10], body[11]]),
+                grandmaster_priority1: body[13],
+                grandmaster_clock_class: body[14],
+                grandmaster_clock_accuracy: body[15],
+                grandmaster_offset_scaled_log_variance: u16::from_be_bytes([body[16], body[17]]),
+                grandmaster_priority2: body[18],
+                grandmaster_identity: body[19..27].try_into().unwrap(),
+                steps_removed: u16::from_be_bytes([body[27], body[28]]),
+                time_source: body[29]
+            }),
+            message_type => PtpBody::Other {message_type, body: body.to_vec()}
+        };
+        Ok(Self {
+            transport_specific: bytes[0] >> 4,
+            version: bytes[1] & 0xF,
+            minor_version: bytes[1] >> 4,
+            domain: bytes[4],
+            flags: u16::from_be_bytes([bytes[6], bytes[7]]),
+            correction: i64::from_be_bytes(bytes[8..16].try_into().unwrap()),
+            source_port: PortIdentity::from_bytes(&bytes[20..30]),
+            sequence_id: u16::from_be_bytes([bytes[30], bytes[31]]),
+            log_message_interval: bytes[33] as i8,
+            body: parsed,
+            suffix: body[body_length..].to_vec()
+        })
+    }
+}
+impl EtherPayload for PtpMessage {
+    fn ether_type(&self) -> u16 {
+        0x88F7
+    }
+}
\ No newline at end of file
@@ -0,0 +1,281 @@
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// QUIC version 1, RFC 9000
+pub const QUIC_V1: u32 = 0x0000_0001;
+/// QUIC version 2, RFC 9369
+pub const QUIC_V2: u32 = 0x6B33_43CF;
+
+/// Reads QUIC variable-length integer from the start of `bytes`
+/// Returns value and its length in bytes
+pub fn read_varint(bytes: &[u8]) -> Result<(u64, usize), DeserializeError> {
+    if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+    let length = 1usize << (bytes[0] >> 6);
+    if bytes.len() < length {return Err(DeserializeError::WrongDataLength);}
+    let mut value = (bytes[0] & 0x3F) as u64;
+    for byte in &bytes[1..length] {
+        value = value << 8 | *byte as u64;
+    }
+    Ok((value, length))
+}
+
+/// Writes QUIC variable-length integer in the shortest form
+/// Note that only `62 bits` of `value` can be encoded, upper bits are dropped
+pub fn write_varint(value: u64, result: &mut Vec<u8>) {
+    match value {
+        0..0x40 => result.push(value as u8),
+        0x40..0x4000 => result.extend_from_slice(&(value as u16 | 0x4000).to_be_bytes()),
+        0x4000..0x4000_0000 => result.extend_from_slice(&(value as u32 | 0x8000_0000).to_be_bytes()),
+        _ => result.extend_from_slice(&(value & 0x3FFF_FFFF_FFFF_FFFF | 0xC000_0000_0000_0000).to_be_bytes())
+    }
+}
+
+/// Returns length of variable-length integer written by `write_varint()`
+pub fn varint_len(value: u64) -> usize {
+    match value {
+        0..0x40 => 1,
+        0x40..0x4000 => 2,
+        0x4000..0x4000_0000 => 4,
+        _ => 8
+    }
+}
+
+/// Type of QUIC long header packet, which carries protected payload
+/// Retry packets are presented separately as `QuicPacket::Retry`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuicLongType {
+    Initial = 0,
+    ZeroRtt = 1,
+    Handshake = 2
+}
+
+/// QUIC packet, as seen by on-path observer
+/// Payloads are protected, so they are presented as is, starting with protected packet number
+/// You can construct it from existing packet bytes with `QuicPacket::deserialize()`
+/// Or all coalesced packets of UDP datagram with `QuicPacket::deserialize_datagram()`
+#[derive(Debug, Clone)]
+pub enum QuicPacket {
+    /// Long header packet with version 0, listing versions server supports
+    VersionNegotiation {
+        destination_cid: Vec<u8>,
+        source_cid: Vec<u8>,
+        supported_versions: Vec<u32>
+    },
+    /// Long header packet of type 3, carrying address validation token for client
+    Retry {
+        version: u32,
+        destination_cid: Vec<u8>,
+        source_cid: Vec<u8>,
+        /// Token client should echo in next Initial packet
+        token: Vec<u8>,
+        /// Retry Integrity Tag, last `16 bytes` of packet
+        integrity_tag: [u8; 16]
+    },
+    /// Initial, 0-RTT or Handshake packet
+    Long {
+        packet_type: QuicLongType,
+        /// Lower `4 bits` of first byte, protected by header protection
+        type_bits: u8,
+        version: u32,
+        destination_cid: Vec<u8>,
+        source_cid: Vec<u8>,
+        /// Token of Initial packets, always empty for other types
+        token: Vec<u8>,
+        /// Packet number and payload, its length is carried in Length field
+        payload: Vec<u8>
+    },
+    /// Short header packet, destination connection ID length isn't carried, so it's left inside `payload`
+    Short {
+        first_byte: u8,
+        payload: Vec<u8>
+    }
+}
+impl QuicPacket {
+    /// Constructs Version Negotiation packet in response to client packet with `destination_cid` and `source_cid`
+    /// Connection IDs are swapped, as RFC 9000 requires
+    pub fn version_negotiation(destination_cid: &[u8], source_cid: &[u8], supported_versions: Vec<u32>) -> Self {
+        Self::VersionNegotiation {
+            destination_cid: source_cid.to_vec(),
+            source_cid: destination_cid.to_vec(),
+            supported_versions
+        }
+    }
+    /// Returns length of serialized packet, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        let long_header = |destination_cid: &[u8], source_cid: &[u8]| 7 + destination_cid.len() + source_cid.len();
+        match self {
+            Self::VersionNegotiation {destination_cid, source_cid, supported_versions} => long_header(destination_cid, source_cid) + supported_versions.len() * 4,
+            Self::Retry {destination_cid, source_cid, token, ..} => long_header(destination_cid, source_cid) + token.len() + 16,
+            Self::Long {packet_type, destination_cid, source_cid, token, payload, ..} => {
+                let token_len = if *packet_type == QuicLongType::Initial {varint_len(token.len() as u64) + token.len()} else {0};
+                long_header(destination_cid, source_cid) + token_len + varint_len(payload.len() as u64) + payload.len()
+            }
+            Self::Short {payload, ..} => 1 + payload.len()
+        }
+    }
+    /// Returns version of long header packet, 0 for Version Negotiation
+    pub fn version(&self) -> Option<u32> {
+        match self {
+            Self::VersionNegotiation {..} => Some(0),
+            Self::Retry {version, ..} |
+            Self::Long {version, ..} => Some(*version),
+            Self::Short {..} => None
+        }
+    }
+    /// Returns destination connection ID of long header packet
+    pub fn destination_cid(&self) -> Option<&[u8]> {
+        match self {
+            Self::VersionNegotiation {destination_cid, ..} |
+            Self::Retry {destination_cid, ..} |
+            Self::Long {destination_cid, ..} => Some(destination_cid),
+            Self::Short {..} => None
+        }
+    }
+    /// Returns source connection ID of long header packet
+    pub fn source_cid(&self) -> Option<&[u8]> {
+        match self {
+            Self::VersionNegotiation {source_cid, ..} |
+            Self::Retry {source_cid, ..} |
+            Self::Long {source_cid, ..} => Some(source_cid),
+            Self::Short {..} => None
+        }
+    }
+    /// Returns versions listed by Version Negotiation packet
+    pub fn supported_versions(&self) -> Option<&[u32]> {
+        match self {
+            Self::VersionNegotiation {supported_versions, ..} => Some(supported_versions),
+            _ => None
+        }
+    }
+    /// Returns token of Retry packet or non-empty token of Initial packet
+    pub fn token(&self) -> Option<&[u8]> {
+        match self {
+            Self::Retry {token, ..} => Some(token),
+            Self::Long {token, ..} if !token.is_empty() => Some(token),
+            _ => None
+        }
+    }
+    /// Deserializes all coalesced packets of UDP datagram payload
+    /// Short header packet can only be the last one, since it has no length
+    pub fn deserialize_datagram(bytes: &[u8]) -> Result<Vec<Self>, DeserializeError> {
+        let mut packets = Vec::new();
+        let mut i = 0usize;
+        while i < bytes.len() {
+            let (packet, length) = Self::deserialize_with_length(&bytes[i..])?;
+            packets.push(packet);
+            i += length;
+        }
+        Ok(packets)
+    }
+    fn deserialize_with_length(bytes: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        let first_byte = bytes[0];
+        if first_byte & 0x80 == 0 {
+            return Ok((Self::Short {
+                first_byte,
+                payload: bytes[1..].to_vec()
+            }, bytes.len()));
+        }
+        if bytes.len() < 7 {return Err(DeserializeError::WrongDataLength);}
+        let version = u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+        let destination_length = bytes[5] as usize;
+        if bytes.len() < 7 + destination_length {return Err(DeserializeError::WrongDataLength);}
+        let destination_cid = bytes[6..6 + destination_length].to_vec();
+        let source_length = bytes[6 + destination_length] as usize;
+        let mut i = 7 + destination_length;
+        if bytes.len() < i + source_length {return Err(DeserializeError::WrongDataLength);}
+        let source_cid = bytes[i..i + source_length].to_vec();
+        i += source_length;
+        if version == 0 {
+            if !(bytes.len() - i).is_multiple_of(4) {return Err(DeserializeError::WrongDataLength);}
+            let supported_versions = bytes[i..].chunks(4).map(|version| u32::from_be_bytes([version[0], version[1], version[2], version[3]])).collect();
+            return Ok((Self::VersionNegotiation {destination_cid, source_cid, supported_versions}, bytes.len()));
+        }
+        let packet_type = match (first_byte >> 4) & 0b11 {
+            0 => QuicLongType::Initial,
+            1 => QuicLongType::ZeroRtt,
+            2 => QuicLongType::Handshake,
+            _ => {
+                if bytes.len() < i + 16 {return Err(DeserializeError::WrongDataLength);}
+                let tag = bytes.len() - 16;
+                return Ok((Self::Retry {
+                    version,
+                    destination_cid,
+                    source_cid,
+                    token: bytes[i..tag].to_vec(),
+                    integrity_tag: *bytes[tag..].as_array().unwrap()
+                }, bytes.len()));
+            }
+        };
+        let mut token = Vec::new();
+        if packet_type == QuicLongType::Initial {
+            let (length, size) = read_varint(&bytes[i..])?;
+            i += size;
+            if ((bytes.len() - i) as u64) < length {return Err(DeserializeError::WrongDataLength);}
+            token = bytes[i..i + length as usize].to_vec();
+            i += length as usize;
+        }
+        let (length, size) = read_varint(&bytes[i..])?;
+        i += size;
+        if ((bytes.len() - i) as u64) < length {return Err(DeserializeError::WrongDataLength);}
+        let end = i + length as usize;
+        Ok((Self::Long {
+            packet_type,
+            type_bits: first_byte & 0x0F,
+            version,
+            destination_cid,
+            source_cid,
+            token,
+            payload: bytes[i..end].to_vec()
+        }, end))
+    }
+}
+impl Serializable for QuicPacket {
+    fn serialize(self) -> Vec<u8> {
+        let long_header = |first_byte: u8, version: u32, destination_cid: &[u8], source_cid: &[u8]| {
+            let mut result = vec![first_byte];
+            result.extend_from_slice(&version.to_be_bytes());
+            result.push(destination_cid.len() as u8);
+            result.extend_from_slice(destination_cid);
+            result.push(source_cid.len() as u8);
+            result.extend_from_slice(source_cid);
+            result
+        };
+        match self {
+            Self::VersionNegotiation {destination_cid, source_cid, supported_versions} => {
+                let mut result = long_header(0xC0, 0, &destination_cid, &source_cid);
+                for version in supported_versions {
+                    result.extend_from_slice(&version.to_be_bytes());
+                }
+                result
+            }
+            Self::Retry {version, destination_cid, source_cid, token, integrity_tag} => {
+                let mut result = long_header(0xF0, version, &destination_cid, &source_cid);
+                result.extend_from_slice(&token);
+                result.extend_from_slice(&integrity_tag);
+                result
+            }
+            Self::Long {packet_type, type_bits, version, destination_cid, source_cid, token, payload} => {
+                let first_byte = 0xC0 | (packet_type as u8) << 4 | type_bits & 0x0F;
+                let mut result = long_header(first_byte, version, &destination_cid, &source_cid);
+                if packet_type == QuicLongType::Initial {
+                    write_varint(token.len() as u64, &mut result);
+                    result.extend_from_slice(&token);
+                }
+                write_varint(payload.len() as u64, &mut result);
+                result.extend_from_slice(&payload);
+                result
+            }
+            Self::Short {first_byte, payload} => {
+                let mut result = vec![first_byte];
+                result.extend_from_slice(&payload);
+                result
+            }
+        }
+    }
+}
+impl Deserializable for QuicPacket {
+    /// Deserializes the first packet of `bytes`, use `QuicPacket::deserialize_datagram()` to get coalesced ones too
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_with_length(bytes)?.0)
+    }
+}
\ No newline at end of file
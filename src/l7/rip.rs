@@ -0,0 +1,207 @@
+use core::net::Ipv4Addr;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Serializable, Deserializable, DeserializeError};
+
+/// RIP command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RipCommand {
+    /// Asks for all or part of routing table
+    Request = 1,
+    /// Carries all or part of routing table
+    Response = 2
+}
+impl Serializable for RipCommand {
+    fn serialize(self) -> Vec<u8> {
+        vec![self as u8]
+    }
+}
+impl Deserializable for RipCommand {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {return Err(DeserializeError::WrongDataLength);}
+        match bytes[0] {
+            1 => Ok(Self::Request),
+            2 => Ok(Self::Response),
+            _ => Err(DeserializeError::WrongData)
+        }
+    }
+}
+
+/// RIPv2 message entry, each is `20 bytes` long
+#[derive(Debug, Clone)]
+pub enum RipEntry {
+    Route {
+        /// Address Family Identifier, 2 for IP
+        /// Note that Request for whole routing table has single entry with `afi = 0` and `metric = 16`
+        afi: u16,
+        route_tag: u16,
+        address: Ipv4Addr,
+        subnet_mask: Ipv4Addr,
+        /// `0.0.0.0` means that packet source should be used as next hop
+        next_hop: Ipv4Addr,
+        /// Hop count from 1 to 15, 16 means infinity(unreachable)
+        metric: u32
+    },
+    /// Authentication entry, which has `afi = 0xFFFF` and may be only first entry in message
+    Authentication {
+        /// Authentication type, 2 for simple password
+        auth_type: u16,
+        /// Password padded with zeroes
+        data: [u8; 16]
+    }
+}
+impl RipEntry {
+    /// Constructs route entry with IP address family and zero route tag
+    pub fn route(address: Ipv4Addr, subnet_mask: Ipv4Addr, next_hop: Ipv4Addr, metric: u32) -> Self {
+        Self::Route {
+            afi: 2,
+            route_tag: 0,
+            address,
+            subnet_mask,
+            next_hop,
+            metric
+        }
+    }
+    /// Constructs simple password authentication entry
+    /// Note that password longer than `16 bytes` is truncated
+    pub fn password(password: &[u8]) -> Self {
+        let mut data = [0u8; 16];
+        let length = password.len().min(16);
+        data[..length].copy_from_slice(&password[..length]);
+        Self::Authentication {
+            auth_type: 2,
+            data
+        }
+    }
+}
+impl Serializable for RipEntry {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 20];
+        match self {
+            Self::Route {afi, route_tag, address, subnet_mask, next_hop, metric} => {
+                result[0..2].copy_from_slice(&afi.to_be_bytes());
+                result[2..4].copy_from_slice(&route_tag.to_be_bytes());
+                result[4..8].copy_from_slice(&address.octets());
+                result[8..12].copy_from_slice(&subnet_mask.octets());
+                result[12..16].copy_from_slice(&next_hop.octets());
+                result[16..20].copy_from_slice(&metric.to_be_bytes());
+            }
+            Self::Authentication {auth_type, data} => {
+                result[0..2].copy_from_slice(&[0xFF, 0xFF]);
+                result[2..4].copy_from_slice(&auth_type.to_be_bytes());
+                result[4..20].copy_from_slice(&data);
+            }
+        }
+        result
+    }
+}
+impl Deserializable for RipEntry {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 20 {return Err(DeserializeError::WrongDataLength);}
+        let afi = u16::from_be_bytes([bytes[0], bytes[1]]);
+        if afi == 0xFFFF {
+            return Ok(Self::Authentication {
+                auth_type: u16::from_be_bytes([bytes[2], bytes[3]]),
+                data: *bytes[4..20].as_array().unwrap()
+            });
+        }
+        Ok(Self::Route {
+            afi,
+            route_tag: u16::from_be_bytes([bytes[2], bytes[3]]),
+            address: Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]),
+            subnet_mask: Ipv4Addr::new(bytes[8], bytes[9], bytes[10], bytes[11]),
+            next_hop: Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]),
+            metric: u32::from_be_bytes([bytes[16], bytes[17], bytes[18], bytes[19]])
+        })
+    }
+}
+
+/// Struct for RIP Message, carried by UDP datagrams on port 520
+/// You can construct it from scratch with `RipMessage::new()` and consistently editing
+/// Or construct from existing message bytes with `RipMessage::deserialize()` or from UDP datagram with `RipMessage::from_udp()`
+#[derive(Debug, Clone)]
+pub struct RipMessage {
+    pub command: RipCommand,
+    /// RIP version, 2 for RIPv2
+    pub version: u8,
+    /// Up to 25 entries, including authentication entry
+    pub entries: Vec<RipEntry>
+}
+impl RipMessage {
+    /// Constructs an empty RIPv2 Response
+    pub fn new() -> Self {
+        Self {
+            command: RipCommand::Response,
+            version: 2,
+            entries: Vec::new()
+        }
+    }
+    /// Constructs RIPv2 Request for whole routing table
+    pub fn request_full_table() -> Self {
+        Self {
+            command: RipCommand::Request,
+            version: 2,
+            entries: vec![RipEntry::Route {
+                afi: 0,
+                route_tag: 0,
+                address: Ipv4Addr::UNSPECIFIED,
+                subnet_mask: Ipv4Addr::UNSPECIFIED,
+                next_hop: Ipv4Addr::UNSPECIFIED,
+                metric: 16
+            }]
+        }
+    }
+    /// Returns length of serialized message, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        4 + self.entries.len() * 20
+    }
+    /// Constructs `RipMessage` from payload of `datagram`
+    /// Returns `Err(DeserializeError::WrongData)` if none of `datagram` ports is 520
+    pub fn from_udp(datagram: &UdpDatagram) -> Result<Self, DeserializeError> {
+        if datagram.source != 520 && datagram.destination != 520 {return Err(DeserializeError::WrongData);}
+        Self::deserialize(&datagram.payload)
+    }
+    /// Wraps message into UDP datagram with both ports set to 520
+    /// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses
+    pub fn into_udp(self) -> UdpDatagram {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = 520;
+        datagram.destination = 520;
+        datagram.payload = self.serialize();
+        datagram
+    }
+    /// Returns authentication entry if message has one
+    pub fn authentication(&self) -> Option<&RipEntry> {
+        self.entries.first().filter(|entry| matches!(entry, RipEntry::Authentication {..}))
+    }
+}
+impl Default for RipMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for RipMessage {
+    fn serialize(self) -> Vec<u8> {
+        let mut result = vec![0u8; 4];
+        result[0] = self.command.serialize()[0];
+        result[1] = self.version;
+        for entry in self.entries {
+            result.append(&mut entry.serialize());
+        }
+        result
+    }
+}
+impl Deserializable for RipMessage {
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 4 {return Err(DeserializeError::WrongDataLength);}
+        if !(bytes.len() - 4).is_multiple_of(20) {return Err(DeserializeError::WrongDataLength);}
+        let mut entries = Vec::new();
+        for entry in bytes[4..].chunks(20) {
+            entries.push(RipEntry::deserialize(entry)?);
+        }
+        Ok(Self {
+            command: RipCommand::deserialize(&bytes[0..1])?,
+            version: bytes[1],
+            entries
+        })
+    }
+}
\ No newline at end of file
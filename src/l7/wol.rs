@@ -0,0 +1,52 @@
+use crate::l2::ethernet::EthernetFrame;
+use crate::l4::udp::UdpDatagram;
+
+/// Builds Wake-on-LAN magic packet for `mac`: `6 bytes` of 0xFF followed by 16 repetitions of `mac`
+pub fn magic_packet(mac: [u8; 6]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(102);
+    result.extend_from_slice(&[0xFF; 6]);
+    for _ in 0..16 {
+        result.extend_from_slice(&mac);
+    }
+    result
+}
+
+/// Builds Wake-on-LAN magic packet for `mac` with SecureOn `password` appended
+/// Note that `password` should be `4` or `6 bytes` long
+pub fn magic_packet_with_password(mac: [u8; 6], password: &[u8]) -> Vec<u8> {
+    let mut result = magic_packet(mac);
+    result.extend_from_slice(password);
+    result
+}
+
+/// Wraps magic packet for `target` into broadcast Ethernet frame with EtherType 0x0842 sent from `source`
+pub fn ethernet_frame(source: [u8; 6], target: [u8; 6]) -> EthernetFrame {
+    let mut frame = EthernetFrame::new();
+    frame.destination = [0xFF; 6];
+    frame.source = source;
+    frame.protocol = 0x0842;
+    frame.payload = magic_packet(target);
+    frame
+}
+
+/// Wraps magic packet for `target` into UDP datagram with both ports set to 9(discard)
+/// Note that `checksum` isn't calculated, use `recalculate_checksum()` after setting up IP addresses, usually broadcast ones
+pub fn udp_datagram(target: [u8; 6]) -> UdpDatagram {
+    let mut datagram = UdpDatagram::new();
+    datagram.source = 9;
+    datagram.destination = 9;
+    datagram.payload = magic_packet(target);
+    datagram
+}
+
+/// Searches `payload` for magic packet and returns MAC address it wakes up
+/// Returns `None` if there is no magic packet in `payload`
+pub fn find_magic_packet(payload: &[u8]) -> Option<[u8; 6]> {
+    if payload.len() < 102 {return None;}
+    (0..=payload.len() - 102).find_map(|start| {
+        let packet = &payload[start..start + 102];
+        if packet[..6] != [0xFF; 6] {return None;}
+        let mac = *packet[6..12].as_array()?;
+        if packet[6..].chunks(6).all(|chunk| chunk == mac) {Some(mac)} else {None}
+    })
+}
\ No newline at end of file
@@ -1,4 +1,30 @@
 pub mod l2;
 pub mod l3;
 pub mod l4;
-pub mod util;
\ No newline at end of file
+pub mod l7;
+pub mod util;
+pub mod stack;
+#[cfg(feature = "capture")]
+pub mod pcap;
+#[cfg(feature = "capture")]
+pub mod tshark;
+#[cfg(all(feature = "capture", target_os = "linux"))]
+pub mod af_packet;
+pub mod generator;
+pub mod mutator;
+pub mod template;
+pub mod fingerprint;
+pub mod stats;
+#[cfg(feature = "capture")]
+pub mod sanitize;
+#[cfg(feature = "capture")]
+pub mod dedup;
+pub mod compat;
+#[cfg(feature = "description")]
+pub mod description;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
\ No newline at end of file
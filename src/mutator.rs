@@ -0,0 +1,304 @@
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::l3::ipv6::NextHeader;
+use crate::util::{checksum, transport_checksum, SplitMix64};
+
+/// Targeted corruption of one field, which `PacketMutator` applied to a valid packet
+/// Offsets are from start of the packet, including Ethernet header if any
+/// Names of layers and fields are the same as in `ValidationIssue`, i.e. "ipv4" and "total_length"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    /// Packet is cut to `length` bytes inside `part` of `layer`, which is "header" or "options"
+    Truncate {
+        layer: &'static str,
+        part: &'static str,
+        length: usize
+    },
+    /// 16-bit length field, i.e. IPv4 total length, is set to `value`
+    LengthField {
+        layer: &'static str,
+        field: &'static str,
+        offset: usize,
+        original: u16,
+        value: u16
+    },
+    /// Header length in 32-bit words, i.e. IPv4 IHL or TCP data offset, is set to `value` below minimum or to maximum, which overflows into payload
+    HeaderLength {
+        layer: &'static str,
+        field: &'static str,
+        offset: usize,
+        original: u8,
+        value: u8
+    },
+    /// TCP flags are set to illegal combination, `flags` are named as in `ValidationIssue::IllegalFlags`, i.e. "SYN+FIN"
+    IllegalFlags {
+        layer: &'static str,
+        flags: &'static str,
+        offset: usize,
+        original: u8,
+        value: u8
+    },
+    /// Length byte of the first option is set to `value`, which is below minimum or runs past options end
+    OptionLength {
+        layer: &'static str,
+        offset: usize,
+        original: u8,
+        value: u8
+    },
+    /// Checksum field is set to wrong `value`
+    Checksum {
+        layer: &'static str,
+        offset: usize,
+        original: u16,
+        value: u16
+    }
+}
+
+/// Corrupted packet bytes with description of the corruption
+#[derive(Debug, Clone)]
+pub struct MutatedPacket {
+    pub bytes: Vec<u8>,
+    pub mutation: Mutation
+}
+
+/// Illegal TCP flag combinations with values of flags byte
+const ILLEGAL_TCP_FLAGS: [(&str, u8); 4] = [("SYN+FIN", 0x03), ("SYN+RST", 0x06), ("FIN+RST", 0x05), ("NONE", 0x00)];
+
+/// Offsets of headers found in packet
+struct Layout {
+    ip: usize,
+    /// IPv4 header length, `None` for IPv6
+    ipv4_header: Option<usize>,
+    /// Offset and length of IPv6 Hop-by-Hop and Destination Options headers
+    ipv6_options: Vec<(usize, usize)>,
+    /// Offset and protocol of transport header, `None` for fragments
+    transport: Option<(usize, u8)>,
+    /// End of IP packet, which can be less than packet length if Ethernet frame is padded
+    end: usize,
+    addresses: (IpAddr, IpAddr)
+}
+impl Layout {
+    fn parse(packet: &[u8], ethernet: bool) -> Option<Self> {
+        let ip = if ethernet {
+            if packet.len() < 14 || !matches!(u16::from_be_bytes([packet[12], packet[13]]), 0x0800 | 0x86DD) {return None;}
+            14
+        } else {0};
+        let bytes = packet.get(ip..)?;
+        match bytes.first()? >> 4 {
+            4 => {
+                let header = (bytes[0] & 15) as usize * 4;
+                if header < 20 || bytes.len() < header {return None;}
+                let total_length = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+                if total_length < header || total_length > bytes.len() {return None;}
+                let fragmented = bytes[6] & 0x20 != 0 || u16::from_be_bytes([bytes[6] & 31, bytes[7]]) != 0;
+                Some(Self {
+                    ip,
+                    ipv4_header: Some(header),
+                    ipv6_options: Vec::new(),
+                    transport: if fragmented {None} else {Some((ip + header, bytes[9]))},
+                    end: ip + total_length,
+                    addresses: (IpAddr::V4(Ipv4Addr::from_octets(*bytes[12..16].as_array()?)), IpAddr::V4(Ipv4Addr::from_octets(*bytes[16..20].as_array()?)))
+                })
+            }
+            6 => {
+                if bytes.len() < 40 {return None;}
+                let end = 40 + u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+                if end > bytes.len() {return None;}
+                let mut ipv6_options = Vec::new();
+                let mut next_header = NextHeader::from(bytes[6]);
+                let mut fragmented = false;
+                let mut i = 40usize;
+                while next_header.is_extension_header() {
+                    let header_len = next_header.extension_header_len(bytes.get(i..)?)?;
+                    if end < i + header_len {return None;}
+                    match next_header {
+                        NextHeader::HopByHopOptions | NextHeader::DestinationOptions => ipv6_options.push((ip + i, header_len)),
+                        NextHeader::Fragment => fragmented = true,
+                        _ => {}
+                    }
+                    next_header = NextHeader::from(bytes[i]);
+                    i += header_len;
+                }
+                Some(Self {
+                    ip,
+                    ipv4_header: None,
+                    ipv6_options,
+                    transport: if fragmented {None} else {Some((ip + i, next_header.into()))},
+                    end: ip + end,
+                    addresses: (IpAddr::V6(Ipv6Addr::from_octets(*bytes[8..24].as_array()?)), IpAddr::V6(Ipv6Addr::from_octets(*bytes[24..40].as_array()?)))
+                })
+            }
+            _ => None
+        }
+    }
+    /// Recalculates checksums, which `mutation` of other field has broken, so only the targeted field is wrong
+    fn fix_checksums(&self, bytes: &mut [u8], mutation: &Mutation) {
+        let layer = match mutation {
+            Mutation::LengthField {layer, ..} | Mutation::HeaderLength {layer, ..} | Mutation::IllegalFlags {layer, ..} | Mutation::OptionLength {layer, ..} => *layer,
+            Mutation::Truncate {..} | Mutation::Checksum {..} => return
+        };
+        match (layer, self.ipv4_header, self.transport) {
+            ("ipv4", Some(header), _) => {
+                let header = &mut bytes[self.ip..self.ip + header];
+                header[10..12].copy_from_slice(&[0, 0]);
+                let calculated = checksum(header);
+                header[10..12].copy_from_slice(&calculated.to_be_bytes());
+            }
+            ("tcp", _, Some((transport, protocol))) | ("udp", _, Some((transport, protocol))) => {
+                let offset = transport + if protocol == 6 {16} else {6};
+                let original = u16::from_be_bytes([bytes[offset], bytes[offset + 1]]);
+                if protocol == 17 && original == 0 {return;}
+                bytes[offset..offset + 2].copy_from_slice(&[0, 0]);
+                let mut calculated = transport_checksum(self.addresses.0, self.addresses.1, protocol, &[&bytes[transport..self.end]]).unwrap_or_default();
+                if protocol == 17 && calculated == 0 {calculated = 0xFFFF;}
+                bytes[offset..offset + 2].copy_from_slice(&calculated.to_be_bytes());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns offset of the first option with length byte in `options`, skipping one-byte `padding` options
+/// IPv4 and TCP options end at End of Option List, whose kind is the same as kind of IPv6 Pad1
+fn first_option(options: &[u8], padding: u8) -> Option<usize> {
+    let i = options.iter().position(|kind| *kind != padding)?;
+    (options[i] != 0 && i + 1 < options.len()).then_some(i)
+}
+
+/// Values for 16-bit length field, which are off by one or extreme
+fn length_values(original: u16) -> Vec<u16> {
+    let mut values = Vec::with_capacity(4);
+    for value in [original.wrapping_sub(1), original.wrapping_add(1), 0, 0xFFFF] {
+        if value != original && !values.contains(&value) {values.push(value);}
+    }
+    values
+}
+
+/// Mutator of valid packets for fuzzing of parsers, firewalls and IDS
+/// It corrupts one field at a time: truncates headers and options, changes length fields, overflows header lengths,
+/// sets illegal TCP flags, breaks option lengths and checksums
+/// Checksums covering the corrupted field are recalculated, so the targeted field is the only inconsistency, except for truncation
+/// Sequence of chosen mutations is determined by seed, so failing cases can be reproduced
+#[derive(Debug, Clone)]
+pub struct PacketMutator {
+    rng: SplitMix64,
+    /// Packets start with Ethernet header, otherwise with IP header
+    pub ethernet: bool
+}
+impl PacketMutator {
+    /// Constructs `PacketMutator` with `seed`, which mutates Ethernet frames
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64(seed),
+            ethernet: true
+        }
+    }
+    /// Returns all mutations applicable to `packet`, which is IPv4 or IPv6 packet optionally carrying TCP, UDP, ICMP or ICMPv6
+    /// Returns empty `Vec` if `packet` isn't IP packet or its headers are already inconsistent
+    pub fn mutations(&self, packet: &[u8]) -> Vec<MutatedPacket> {
+        let Some(layout) = Layout::parse(packet, self.ethernet) else {return Vec::new();};
+        let mut mutations = Vec::new();
+        let read_u16 = |offset: usize| u16::from_be_bytes([packet[offset], packet[offset + 1]]);
+        let ip = layout.ip;
+        match layout.ipv4_header {
+            Some(header) => {
+                mutations.push(Mutation::Truncate {layer: "ipv4", part: "header", length: ip + 10});
+                if header > 20 {
+                    mutations.push(Mutation::Truncate {layer: "ipv4", part: "options", length: ip + 20 + (header - 20) / 2});
+                    if let Some(option) = first_option(&packet[ip + 20..ip + header], 1) {
+                        let offset = ip + 20 + option + 1;
+                        for value in [1, (header - 20 - option + 1) as u8] {
+                            mutations.push(Mutation::OptionLength {layer: "ipv4", offset, original: packet[offset], value});
+                        }
+                    }
+                }
+                for value in length_values(read_u16(ip + 2)) {
+                    mutations.push(Mutation::LengthField {layer: "ipv4", field: "total_length", offset: ip + 2, original: read_u16(ip + 2), value});
+                }
+                let ihl = packet[ip] & 15;
+                for value in [4, 15] {
+                    if value != ihl {
+                        mutations.push(Mutation::HeaderLength {layer: "ipv4", field: "ihl", offset: ip, original: ihl, value});
+                    }
+                }
+                mutations.push(Mutation::Checksum {layer: "ipv4", offset: ip + 10, original: read_u16(ip + 10), value: read_u16(ip + 10).wrapping_add(1).max(1)});
+            }
+            None => {
+                mutations.push(Mutation::Truncate {layer: "ipv6", part: "header", length: ip + 20});
+                for value in length_values(read_u16(ip + 4)) {
+                    mutations.push(Mutation::LengthField {layer: "ipv6", field: "payload_length", offset: ip + 4, original: read_u16(ip + 4), value});
+                }
+                for (header, length) in &layout.ipv6_options {
+                    mutations.push(Mutation::Truncate {layer: "ipv6", part: "options", length: header + length / 2});
+                    if let Some(option) = first_option(&packet[header + 2..header + length], 0) {
+                        let offset = header + 2 + option + 1;
+                        let value = (length - 2 - option - 1) as u8;
+                        mutations.push(Mutation::OptionLength {layer: "ipv6", offset, original: packet[offset], value});
+                    }
+                }
+            }
+        }
+        if let Some((transport, protocol)) = layout.transport {
+            let length = layout.end - transport;
+            match protocol {
+                6 if length >= 20 => {
+                    let data_offset = (packet[transport + 12] >> 4) as usize * 4;
+                    mutations.push(Mutation::Truncate {layer: "tcp", part: "header", length: transport + 10});
+                    if data_offset > 20 && data_offset <= length {
+                        mutations.push(Mutation::Truncate {layer: "tcp", part: "options", length: transport + 20 + (data_offset - 20) / 2});
+                        if let Some(option) = first_option(&packet[transport + 20..transport + data_offset], 1) {
+                            let offset = transport + 20 + option + 1;
+                            for value in [1, (data_offset - 20 - option + 1) as u8] {
+                                mutations.push(Mutation::OptionLength {layer: "tcp", offset, original: packet[offset], value});
+                            }
+                        }
+                    }
+                    let words = packet[transport + 12] >> 4;
+                    for value in [4, 15] {
+                        if value != words {
+                            mutations.push(Mutation::HeaderLength {layer: "tcp", field: "data_offset", offset: transport + 12, original: words, value});
+                        }
+                    }
+                    for (flags, value) in ILLEGAL_TCP_FLAGS {
+                        if value != packet[transport + 13] {
+                            mutations.push(Mutation::IllegalFlags {layer: "tcp", flags, offset: transport + 13, original: packet[transport + 13], value});
+                        }
+                    }
+                    mutations.push(Mutation::Checksum {layer: "tcp", offset: transport + 16, original: read_u16(transport + 16), value: read_u16(transport + 16).wrapping_add(1).max(1)});
+                }
+                17 if length >= 8 => {
+                    mutations.push(Mutation::Truncate {layer: "udp", part: "header", length: transport + 4});
+                    for value in length_values(read_u16(transport + 4)) {
+                        mutations.push(Mutation::LengthField {layer: "udp", field: "length", offset: transport + 4, original: read_u16(transport + 4), value});
+                    }
+                    mutations.push(Mutation::Checksum {layer: "udp", offset: transport + 6, original: read_u16(transport + 6), value: read_u16(transport + 6).wrapping_add(1).max(1)});
+                }
+                1 | 58 if length >= 4 => {
+                    let layer = if protocol == 1 {"icmp"} else {"icmpv6"};
+                    mutations.push(Mutation::Truncate {layer, part: "header", length: transport + 2});
+                    mutations.push(Mutation::Checksum {layer, offset: transport + 2, original: read_u16(transport + 2), value: read_u16(transport + 2).wrapping_add(1).max(1)});
+                }
+                _ => {}
+            }
+        }
+        mutations.into_iter().map(|mutation| {
+            let mut bytes = packet.to_vec();
+            match mutation {
+                Mutation::Truncate {length, ..} => bytes.truncate(length),
+                Mutation::LengthField {offset, value, ..} | Mutation::Checksum {offset, value, ..} => bytes[offset..offset + 2].copy_from_slice(&value.to_be_bytes()),
+                Mutation::HeaderLength {layer: "ipv4", offset, value, ..} => bytes[offset] = (bytes[offset] & 0xF0) | value,
+                Mutation::HeaderLength {offset, value, ..} => bytes[offset] = (bytes[offset] & 0x0F) | value << 4,
+                Mutation::IllegalFlags {offset, value, ..} | Mutation::OptionLength {offset, value, ..} => bytes[offset] = value
+            }
+            layout.fix_checksums(&mut bytes, &mutation);
+            MutatedPacket {bytes, mutation}
+        }).collect()
+    }
+    /// Applies one random mutation from `mutations()` to `packet`
+    /// Returns `None` if no mutation is applicable
+    pub fn mutate(&mut self, packet: &[u8]) -> Option<MutatedPacket> {
+        let mut mutations = self.mutations(packet);
+        if mutations.is_empty() {return None;}
+        let index = self.rng.range(0, mutations.len() as u128 - 1) as usize;
+        Some(mutations.swap_remove(index))
+    }
+}
\ No newline at end of file
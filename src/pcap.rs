@@ -0,0 +1,276 @@
+use core::time::Duration;
+use std::io::{self, Read, Write};
+use crate::util::{Deserializable, DeserializeError, Serializable};
+
+/// Link type of Ethernet frames
+pub const LINKTYPE_ETHERNET: u32 = 1;
+/// Link type of raw IPv4 or IPv6 packets
+pub const LINKTYPE_RAW: u32 = 101;
+/// Link type of 802.11 frames
+pub const LINKTYPE_IEEE802_11: u32 = 105;
+/// Link type of Linux cooked capture (SLL) frames
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+/// Link type of 802.11 frames with Radiotap header
+pub const LINKTYPE_IEEE802_11_RADIOTAP: u32 = 127;
+/// Link type of raw IPv4 packets
+pub const LINKTYPE_IPV4: u32 = 228;
+/// Link type of raw IPv6 packets
+pub const LINKTYPE_IPV6: u32 = 229;
+/// Link type of Linux cooked capture v2 (SLL2) frames
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const MAGIC_MICROSECONDS: u32 = 0xA1B2C3D4;
+const MAGIC_NANOSECONDS: u32 = 0xA1B23C4D;
+
+/// Checks whether `bytes` start with pcap magic number in any byte order
+pub fn is_pcap(bytes: &[u8]) -> bool {
+    if bytes.len() < 4 {return false;}
+    let magic = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    [MAGIC_MICROSECONDS, MAGIC_NANOSECONDS].iter().any(|expected| magic == *expected || magic.swap_bytes() == *expected)
+}
+
+/// Record of pcap file, i.e. one captured packet
+#[derive(Debug, Clone)]
+pub struct PcapRecord {
+    /// Capture time since Unix epoch
+    pub timestamp: Duration,
+    /// Length of packet on the wire, which is greater than `data` length if packet was truncated to snaplen
+    pub original_length: u32,
+    pub data: Vec<u8>
+}
+
+impl PcapRecord {
+    /// Wraps record data into `PacketEnvelope` with unknown interface and direction
+    pub fn into_envelope(self) -> PacketEnvelope<Vec<u8>> {
+        PacketEnvelope {
+            timestamp: self.timestamp,
+            interface_index: None,
+            interface_name: None,
+            direction: Direction::Unknown,
+            original_length: self.original_length,
+            captured_length: self.data.len() as u32,
+            frame: self.data
+        }
+    }
+}
+
+/// Direction of captured packet relative to capturing host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+    #[default]
+    Unknown
+}
+impl Direction {
+    /// Converts Linux packet type of SLL header or `sockaddr_ll`, where 4 is sent by us and 0-3 are received
+    pub fn from_packet_type(packet_type: u16) -> Self {
+        match packet_type {
+            0..=3 => Self::Inbound,
+            4 => Self::Outbound,
+            _ => Self::Unknown
+        }
+    }
+}
+
+/// Captured frame together with its capture metadata
+/// `T` is either raw bytes or any parsed frame, i.e. `EthernetFrame` or `PacketStack`, see `map()` and `try_map()`
+/// Envelopes of raw bytes are produced by `PcapReader::envelopes()` and, on Linux, by `af_packet::AfPacketSocket`
+#[derive(Debug, Clone)]
+pub struct PacketEnvelope<T> {
+    /// Capture time since Unix epoch
+    pub timestamp: Duration,
+    /// Index of interface packet was captured on, if known
+    pub interface_index: Option<u32>,
+    /// Name of interface packet was captured on, if known
+    pub interface_name: Option<String>,
+    pub direction: Direction,
+    /// Length of packet on the wire
+    pub original_length: u32,
+    /// Length of captured bytes, which is less than `original_length` if packet was truncated to snaplen
+    pub captured_length: u32,
+    pub frame: T
+}
+impl<T> PacketEnvelope<T> {
+    /// Constructs envelope of whole, not truncated `frame` of `length bytes` captured at `timestamp`
+    pub fn new(timestamp: Duration, length: u32, frame: T) -> Self {
+        Self {
+            timestamp,
+            interface_index: None,
+            interface_name: None,
+            direction: Direction::Unknown,
+            original_length: length,
+            captured_length: length,
+            frame
+        }
+    }
+    /// Returns `true` if packet was truncated to snaplen on capture
+    pub fn is_truncated(&self) -> bool {
+        self.captured_length < self.original_length
+    }
+    /// Replaces frame with `f(frame)`, keeping metadata
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> PacketEnvelope<U> {
+        PacketEnvelope {
+            timestamp: self.timestamp,
+            interface_index: self.interface_index,
+            interface_name: self.interface_name,
+            direction: self.direction,
+            original_length: self.original_length,
+            captured_length: self.captured_length,
+            frame: f(self.frame)
+        }
+    }
+    /// The same as `map()`, but for fallible `f`, i.e. `Deserializable::deserialize`
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<PacketEnvelope<U>, E> {
+        let PacketEnvelope {timestamp, interface_index, interface_name, direction, original_length, captured_length, frame} = self;
+        Ok(PacketEnvelope {timestamp, interface_index, interface_name, direction, original_length, captured_length, frame: f(frame)?})
+    }
+    /// Returns envelope borrowing frame
+    pub fn as_ref(&self) -> PacketEnvelope<&T> {
+        PacketEnvelope {
+            timestamp: self.timestamp,
+            interface_index: self.interface_index,
+            interface_name: self.interface_name.clone(),
+            direction: self.direction,
+            original_length: self.original_length,
+            captured_length: self.captured_length,
+            frame: &self.frame
+        }
+    }
+}
+impl PacketEnvelope<Vec<u8>> {
+    /// Decodes captured bytes as `P`, keeping metadata
+    pub fn parse<P: Deserializable>(self) -> Result<PacketEnvelope<P>, DeserializeError> {
+        self.try_map(|data| P::deserialize(&data))
+    }
+}
+
+/// Reader of classic pcap files in any byte order with microsecond or nanosecond timestamps
+/// Records are read with `Iterator` implementation
+pub struct PcapReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    nanoseconds: bool,
+    /// Link type of all records, i.e. `LINKTYPE_ETHERNET`
+    pub link_type: u32,
+    /// Maximal length of captured packets
+    pub snaplen: u32
+}
+impl<R: Read> PcapReader<R> {
+    /// Reads pcap file header from `reader`
+    /// Returns `Err` with `InvalidData` kind if it is not a pcap file
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+        let magic = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let (big_endian, nanoseconds) = match (magic, magic.swap_bytes()) {
+            (MAGIC_MICROSECONDS, _) => (true, false),
+            (MAGIC_NANOSECONDS, _) => (true, true),
+            (_, MAGIC_MICROSECONDS) => (false, false),
+            (_, MAGIC_NANOSECONDS) => (false, true),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not a pcap file"))
+        };
+        let read_u32 = |bytes: &[u8]| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if big_endian {u32::from_be_bytes(bytes)} else {u32::from_le_bytes(bytes)}
+        };
+        Ok(Self {
+            snaplen: read_u32(&header[16..20]),
+            link_type: read_u32(&header[20..24]) & 0x0FFFFFFF,
+            reader,
+            big_endian,
+            nanoseconds
+        })
+    }
+    fn read_record(&mut self) -> io::Result<Option<PcapRecord>> {
+        let mut header = [0u8; 16];
+        match self.reader.read_exact(&mut header[..1]) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(error)
+        }
+        self.reader.read_exact(&mut header[1..])?;
+        let fields: Vec<u32> = header.chunks_exact(4).map(|field| {
+            let field = [field[0], field[1], field[2], field[3]];
+            if self.big_endian {u32::from_be_bytes(field)} else {u32::from_le_bytes(field)}
+        }).collect();
+        let fraction = if self.nanoseconds {fields[1]} else {fields[1].saturating_mul(1000)};
+        let mut data = vec![0u8; fields[2] as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(PcapRecord {
+            timestamp: Duration::new(fields[0] as u64, fraction),
+            original_length: fields[3],
+            data
+        }))
+    }
+    /// Consumes reader and returns iterator of records wrapped into `PacketEnvelope`
+    /// For Linux cooked capture link types direction and interface index are taken from pseudo-header
+    pub fn envelopes(self) -> impl Iterator<Item = io::Result<PacketEnvelope<Vec<u8>>>> {
+        let link_type = self.link_type;
+        self.map(move |record| record.map(|record| {
+            let mut envelope = record.into_envelope();
+            let data = &envelope.frame;
+            match link_type {
+                LINKTYPE_LINUX_SLL if data.len() >= 16 => {
+                    envelope.direction = Direction::from_packet_type(u16::from_be_bytes([data[0], data[1]]));
+                }
+                LINKTYPE_LINUX_SLL2 if data.len() >= 20 => {
+                    envelope.interface_index = Some(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+                    envelope.direction = Direction::from_packet_type(data[10] as u16);
+                }
+                _ => {}
+            }
+            envelope
+        }))
+    }
+}
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = io::Result<PcapRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Writer of classic little-endian pcap files with nanosecond timestamps
+pub struct PcapWriter<W: Write> {
+    writer: W
+}
+impl<W: Write> PcapWriter<W> {
+    /// Writes pcap file header with `link_type` and snaplen of `65535 bytes` to `writer`
+    pub fn new(mut writer: W, link_type: u32) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&MAGIC_NANOSECONDS.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&[0u8; 8]);
+        header.extend_from_slice(&65535u32.to_le_bytes());
+        header.extend_from_slice(&link_type.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self {writer})
+    }
+    pub fn write_record(&mut self, record: &PcapRecord) -> io::Result<()> {
+        self.write(record.timestamp, record.original_length, &record.data)
+    }
+    /// Serializes frame of `envelope` and writes it with capture timestamp and original length of envelope
+    pub fn write_envelope<T: Serializable>(&mut self, envelope: PacketEnvelope<T>) -> io::Result<()> {
+        let data = envelope.frame.serialize();
+        self.write(envelope.timestamp, envelope.original_length.max(data.len() as u32), &data)
+    }
+    /// Writes whole packet `data` captured at `timestamp`
+    pub fn write_packet(&mut self, timestamp: Duration, data: &[u8]) -> io::Result<()> {
+        self.write(timestamp, data.len() as u32, data)
+    }
+    fn write(&mut self, timestamp: Duration, original_length: u32, data: &[u8]) -> io::Result<()> {
+        let mut header = Vec::with_capacity(16);
+        header.extend_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+        header.extend_from_slice(&timestamp.subsec_nanos().to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        header.extend_from_slice(&original_length.to_le_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)
+    }
+    /// Returns underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
\ No newline at end of file
@@ -0,0 +1,567 @@
+//! Python bindings, build extension module with `maturin build --release`, which compiles crate as cdylib itself
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple, PyType};
+use crate::l2::ethernet::{self, EthernetFrame};
+use crate::l3::icmp::IcmpPacket;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::{TcpFlags, TcpSegment};
+use crate::l4::udp::UdpDatagram;
+use crate::stack::{Layer, PacketStack};
+use crate::util::{Deserializable, DeserializeError, Serializable};
+
+fn value_error(error: DeserializeError) -> PyErr {
+    PyValueError::new_err(match error {
+        DeserializeError::WrongDataLength => "wrong data length".to_string(),
+        DeserializeError::WrongData => "wrong data".to_string(),
+        DeserializeError::Incomplete {needed} => format!("incomplete data, {} more bytes needed", needed)
+    })
+}
+
+fn parse_mac(mac: &str) -> PyResult<[u8; 6]> {
+    ethernet::parse_mac(mac).ok_or_else(|| PyValueError::new_err(format!("invalid MAC address {}", mac)))
+}
+
+fn parse_address<A: core::str::FromStr>(address: &str) -> PyResult<A> {
+    address.parse().map_err(|_| PyValueError::new_err(format!("invalid IP address {}", address)))
+}
+
+fn parse_flags(flags: &str) -> PyResult<TcpFlags> {
+    TcpFlags::from_letters(flags).ok_or_else(|| PyValueError::new_err(format!("invalid TCP flags {}", flags)))
+}
+
+/// Methods shared by all layer classes: `payload` property, `from_bytes()`, `bytes()`, `/` operator and `repr()`
+macro_rules! layer_class {
+    ($class:ident, $inner:ty, {$($methods:tt)*}) => {
+        #[pymethods]
+        impl $class {
+            $($methods)*
+            #[getter]
+            fn payload<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+                PyBytes::new(py, &self.0.payload)
+            }
+            #[setter]
+            fn set_payload(&mut self, payload: Vec<u8>) {
+                self.0.payload = payload;
+            }
+            #[staticmethod]
+            fn from_bytes(data: &[u8]) -> PyResult<Self> {
+                <$inner>::deserialize(data).map(Self).map_err(value_error)
+            }
+            /// Serializes layer with its own payload as it is, checksums are calculated only when building `Packet`
+            fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+                PyBytes::new(py, &self.0.clone().serialize())
+            }
+            fn __truediv__(slf: &Bound<'_, Self>, other: &Bound<'_, PyAny>) -> PyResult<PyPacket> {
+                let mut packet = PyPacket {layers: vec![slf.clone().into_any().unbind()], payload: Vec::new()};
+                packet.push(other)?;
+                Ok(packet)
+            }
+            fn __repr__(&self) -> String {
+                format!("{:?}", self.0)
+            }
+        }
+    };
+}
+
+/// Python class `EthernetFrame`
+#[pyclass(name = "EthernetFrame", module = "packedit")]
+pub struct PyEthernetFrame(pub EthernetFrame);
+layer_class!(PyEthernetFrame, EthernetFrame, {
+    #[new]
+    #[pyo3(signature = (source = "00:00:00:00:00:00", destination = "ff:ff:ff:ff:ff:ff", protocol = 0, payload = Vec::new()))]
+    fn new(source: &str, destination: &str, protocol: u16, payload: Vec<u8>) -> PyResult<Self> {
+        Ok(Self(EthernetFrame {destination: parse_mac(destination)?, source: parse_mac(source)?, protocol, payload}))
+    }
+    #[getter]
+    fn source(&self) -> String {
+        ethernet::format_mac(self.0.source)
+    }
+    #[setter]
+    fn set_source(&mut self, source: &str) -> PyResult<()> {
+        self.0.source = parse_mac(source)?;
+        Ok(())
+    }
+    #[getter]
+    fn destination(&self) -> String {
+        ethernet::format_mac(self.0.destination)
+    }
+    #[setter]
+    fn set_destination(&mut self, destination: &str) -> PyResult<()> {
+        self.0.destination = parse_mac(destination)?;
+        Ok(())
+    }
+    #[getter]
+    fn protocol(&self) -> u16 {
+        self.0.protocol
+    }
+    #[setter]
+    fn set_protocol(&mut self, protocol: u16) {
+        self.0.protocol = protocol;
+    }
+});
+
+/// Python class `Ipv4Packet`
+#[pyclass(name = "Ipv4Packet", module = "packedit")]
+pub struct PyIpv4Packet(pub Ipv4Packet);
+layer_class!(PyIpv4Packet, Ipv4Packet, {
+    #[new]
+    #[pyo3(signature = (source = "0.0.0.0", destination = "0.0.0.0", protocol = 0, ttl = 64, payload = Vec::new()))]
+    fn new(source: &str, destination: &str, protocol: u8, ttl: u8, payload: Vec<u8>) -> PyResult<Self> {
+        let mut packet = Ipv4Packet::new();
+        packet.source = parse_address(source)?;
+        packet.destination = parse_address(destination)?;
+        packet.protocol = protocol.into();
+        packet.ttl = ttl;
+        packet.payload = payload;
+        Ok(Self(packet))
+    }
+    #[getter]
+    fn source(&self) -> String {
+        self.0.source.to_string()
+    }
+    #[setter]
+    fn set_source(&mut self, source: &str) -> PyResult<()> {
+        self.0.source = parse_address(source)?;
+        Ok(())
+    }
+    #[getter]
+    fn destination(&self) -> String {
+        self.0.destination.to_string()
+    }
+    #[setter]
+    fn set_destination(&mut self, destination: &str) -> PyResult<()> {
+        self.0.destination = parse_address(destination)?;
+        Ok(())
+    }
+    #[getter]
+    fn protocol(&self) -> u8 {
+        self.0.protocol.into()
+    }
+    #[setter]
+    fn set_protocol(&mut self, protocol: u8) {
+        self.0.protocol = protocol.into();
+    }
+    #[getter]
+    fn ttl(&self) -> u8 {
+        self.0.ttl
+    }
+    #[setter]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.0.ttl = ttl;
+    }
+    #[getter]
+    fn id(&self) -> u16 {
+        self.0.id
+    }
+    #[setter]
+    fn set_id(&mut self, id: u16) {
+        self.0.id = id;
+    }
+    #[getter]
+    fn dont_fragment(&self) -> bool {
+        self.0.dont_fragment
+    }
+    #[setter]
+    fn set_dont_fragment(&mut self, dont_fragment: bool) {
+        self.0.dont_fragment = dont_fragment;
+    }
+});
+
+/// Python class `Ipv6Packet`
+#[pyclass(name = "Ipv6Packet", module = "packedit")]
+pub struct PyIpv6Packet(pub Ipv6Packet);
+layer_class!(PyIpv6Packet, Ipv6Packet, {
+    #[new]
+    #[pyo3(signature = (source = "::", destination = "::", next_header = 59, hop_limit = 64, payload = Vec::new()))]
+    fn new(source: &str, destination: &str, next_header: u8, hop_limit: u8, payload: Vec<u8>) -> PyResult<Self> {
+        let mut packet = Ipv6Packet::new();
+        packet.source = parse_address(source)?;
+        packet.destination = parse_address(destination)?;
+        packet.next_header = next_header.into();
+        packet.hop_limit = hop_limit;
+        packet.payload = payload;
+        Ok(Self(packet))
+    }
+    #[getter]
+    fn source(&self) -> String {
+        self.0.source.to_string()
+    }
+    #[setter]
+    fn set_source(&mut self, source: &str) -> PyResult<()> {
+        self.0.source = parse_address(source)?;
+        Ok(())
+    }
+    #[getter]
+    fn destination(&self) -> String {
+        self.0.destination.to_string()
+    }
+    #[setter]
+    fn set_destination(&mut self, destination: &str) -> PyResult<()> {
+        self.0.destination = parse_address(destination)?;
+        Ok(())
+    }
+    #[getter]
+    fn next_header(&self) -> u8 {
+        self.0.next_header.into()
+    }
+    #[setter]
+    fn set_next_header(&mut self, next_header: u8) {
+        self.0.next_header = next_header.into();
+    }
+    #[getter]
+    fn hop_limit(&self) -> u8 {
+        self.0.hop_limit
+    }
+    #[setter]
+    fn set_hop_limit(&mut self, hop_limit: u8) {
+        self.0.hop_limit = hop_limit;
+    }
+    #[getter]
+    fn flow_label(&self) -> u32 {
+        self.0.flow_label
+    }
+    #[setter]
+    fn set_flow_label(&mut self, flow_label: u32) {
+        self.0.flow_label = flow_label;
+    }
+});
+
+/// Python class `TcpSegment`
+#[pyclass(name = "TcpSegment", module = "packedit")]
+pub struct PyTcpSegment(pub TcpSegment);
+layer_class!(PyTcpSegment, TcpSegment, {
+    #[new]
+    #[pyo3(signature = (source = 0, destination = 0, sequence_number = 0, acknowledgement_number = 0, flags = "S", window_size = 65535, payload = Vec::new()))]
+    fn new(source: u16, destination: u16, sequence_number: u32, acknowledgement_number: u32, flags: &str, window_size: u16, payload: Vec<u8>) -> PyResult<Self> {
+        let mut segment = TcpSegment::new();
+        segment.source = source;
+        segment.destination = destination;
+        segment.sequence_number = sequence_number;
+        segment.acknowledgement_number = acknowledgement_number;
+        segment.flags = parse_flags(flags)?;
+        segment.window_size = window_size;
+        segment.payload = payload;
+        Ok(Self(segment))
+    }
+    #[getter]
+    fn source(&self) -> u16 {
+        self.0.source
+    }
+    #[setter]
+    fn set_source(&mut self, source: u16) {
+        self.0.source = source;
+    }
+    #[getter]
+    fn destination(&self) -> u16 {
+        self.0.destination
+    }
+    #[setter]
+    fn set_destination(&mut self, destination: u16) {
+        self.0.destination = destination;
+    }
+    #[getter]
+    fn sequence_number(&self) -> u32 {
+        self.0.sequence_number
+    }
+    #[setter]
+    fn set_sequence_number(&mut self, sequence_number: u32) {
+        self.0.sequence_number = sequence_number;
+    }
+    #[getter]
+    fn acknowledgement_number(&self) -> u32 {
+        self.0.acknowledgement_number
+    }
+    #[setter]
+    fn set_acknowledgement_number(&mut self, acknowledgement_number: u32) {
+        self.0.acknowledgement_number = acknowledgement_number;
+    }
+    /// Flags in scapy notation, i.e. `"SA"` for SYN-ACK
+    #[getter]
+    fn flags(&self) -> String {
+        self.0.flags.letters()
+    }
+    #[setter]
+    fn set_flags(&mut self, flags: &str) -> PyResult<()> {
+        self.0.flags = parse_flags(flags)?;
+        Ok(())
+    }
+    #[getter]
+    fn window_size(&self) -> u16 {
+        self.0.window_size
+    }
+    #[setter]
+    fn set_window_size(&mut self, window_size: u16) {
+        self.0.window_size = window_size;
+    }
+    #[getter]
+    fn checksum(&self) -> u16 {
+        self.0.checksum
+    }
+});
+
+/// Python class `UdpDatagram`
+#[pyclass(name = "UdpDatagram", module = "packedit")]
+pub struct PyUdpDatagram(pub UdpDatagram);
+layer_class!(PyUdpDatagram, UdpDatagram, {
+    #[new]
+    #[pyo3(signature = (source = 0, destination = 0, payload = Vec::new()))]
+    fn new(source: u16, destination: u16, payload: Vec<u8>) -> Self {
+        let mut datagram = UdpDatagram::new();
+        datagram.source = source;
+        datagram.destination = destination;
+        datagram.payload = payload;
+        Self(datagram)
+    }
+    #[getter]
+    fn source(&self) -> u16 {
+        self.0.source
+    }
+    #[setter]
+    fn set_source(&mut self, source: u16) {
+        self.0.source = source;
+    }
+    #[getter]
+    fn destination(&self) -> u16 {
+        self.0.destination
+    }
+    #[setter]
+    fn set_destination(&mut self, destination: u16) {
+        self.0.destination = destination;
+    }
+    #[getter]
+    fn checksum(&self) -> Option<u16> {
+        self.0.checksum
+    }
+});
+
+/// Python class `IcmpPacket`
+#[pyclass(name = "IcmpPacket", module = "packedit")]
+pub struct PyIcmpPacket(pub IcmpPacket);
+layer_class!(PyIcmpPacket, IcmpPacket, {
+    /// `rest_of_header` defaults to identifier and sequence number of Echo messages
+    #[new]
+    #[pyo3(signature = (icmp_type = 8, code = 0, id = 0, seq = 0, payload = Vec::new()))]
+    fn new(icmp_type: u8, code: u8, id: u16, seq: u16, payload: Vec<u8>) -> Self {
+        let mut packet = IcmpPacket::new();
+        packet.icmp_type = icmp_type;
+        packet.code = code;
+        packet.rest_of_header[0..2].copy_from_slice(&id.to_be_bytes());
+        packet.rest_of_header[2..4].copy_from_slice(&seq.to_be_bytes());
+        packet.payload = payload;
+        Self(packet)
+    }
+    #[getter]
+    fn icmp_type(&self) -> u8 {
+        self.0.icmp_type
+    }
+    #[setter]
+    fn set_icmp_type(&mut self, icmp_type: u8) {
+        self.0.icmp_type = icmp_type;
+    }
+    #[getter]
+    fn code(&self) -> u8 {
+        self.0.code
+    }
+    #[setter]
+    fn set_code(&mut self, code: u8) {
+        self.0.code = code;
+    }
+    #[getter]
+    fn rest_of_header<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.rest_of_header)
+    }
+    #[setter]
+    fn set_rest_of_header(&mut self, rest_of_header: [u8; 4]) {
+        self.0.rest_of_header = rest_of_header;
+    }
+    #[getter]
+    fn checksum(&self) -> u16 {
+        self.0.checksum
+    }
+});
+
+/// Python class `Icmpv6Packet`
+#[pyclass(name = "Icmpv6Packet", module = "packedit")]
+pub struct PyIcmpv6Packet(pub Icmpv6Packet);
+layer_class!(PyIcmpv6Packet, Icmpv6Packet, {
+    /// `rest_of_header` defaults to identifier and sequence number of Echo messages
+    #[new]
+    #[pyo3(signature = (icmp_type = 128, code = 0, id = 0, seq = 0, payload = Vec::new()))]
+    fn new(icmp_type: u8, code: u8, id: u16, seq: u16, payload: Vec<u8>) -> Self {
+        let mut packet = Icmpv6Packet::new();
+        packet.icmp_type = icmp_type;
+        packet.code = code;
+        packet.rest_of_header[0..2].copy_from_slice(&id.to_be_bytes());
+        packet.rest_of_header[2..4].copy_from_slice(&seq.to_be_bytes());
+        packet.payload = payload;
+        Self(packet)
+    }
+    #[getter]
+    fn icmp_type(&self) -> u8 {
+        self.0.icmp_type
+    }
+    #[setter]
+    fn set_icmp_type(&mut self, icmp_type: u8) {
+        self.0.icmp_type = icmp_type;
+    }
+    #[getter]
+    fn code(&self) -> u8 {
+        self.0.code
+    }
+    #[setter]
+    fn set_code(&mut self, code: u8) {
+        self.0.code = code;
+    }
+    #[getter]
+    fn rest_of_header<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.rest_of_header)
+    }
+    #[setter]
+    fn set_rest_of_header(&mut self, rest_of_header: [u8; 4]) {
+        self.0.rest_of_header = rest_of_header;
+    }
+    #[getter]
+    fn checksum(&self) -> u16 {
+        self.0.checksum
+    }
+});
+
+/// Copies layer `object` into `Layer` of `PacketStack`
+fn extract_layer(object: &Bound<'_, PyAny>) -> PyResult<Layer> {
+    if let Ok(layer) = object.extract::<PyRef<PyEthernetFrame>>() {return Ok(Layer::Ethernet(layer.0.clone()));}
+    if let Ok(layer) = object.extract::<PyRef<PyIpv4Packet>>() {return Ok(Layer::Ipv4(layer.0.clone()));}
+    if let Ok(layer) = object.extract::<PyRef<PyIpv6Packet>>() {return Ok(Layer::Ipv6(layer.0.clone()));}
+    if let Ok(layer) = object.extract::<PyRef<PyTcpSegment>>() {return Ok(Layer::Tcp(layer.0.clone()));}
+    if let Ok(layer) = object.extract::<PyRef<PyUdpDatagram>>() {return Ok(Layer::Udp(layer.0.clone()));}
+    if let Ok(layer) = object.extract::<PyRef<PyIcmpPacket>>() {return Ok(Layer::Icmp(layer.0.clone()));}
+    if let Ok(layer) = object.extract::<PyRef<PyIcmpv6Packet>>() {return Ok(Layer::Icmpv6(layer.0.clone()));}
+    Err(PyTypeError::new_err(format!("{} is not a packedit layer", object.get_type().name()?)))
+}
+
+fn layer_object(py: Python<'_>, layer: Layer) -> PyResult<Py<PyAny>> {
+    Ok(match layer {
+        Layer::Ethernet(frame) => Py::new(py, PyEthernetFrame(frame))?.into_any(),
+        Layer::Ipv4(packet) => Py::new(py, PyIpv4Packet(packet))?.into_any(),
+        Layer::Ipv6(packet) => Py::new(py, PyIpv6Packet(packet))?.into_any(),
+        Layer::Tcp(segment) => Py::new(py, PyTcpSegment(segment))?.into_any(),
+        Layer::Udp(datagram) => Py::new(py, PyUdpDatagram(datagram))?.into_any(),
+        Layer::Icmp(packet) => Py::new(py, PyIcmpPacket(packet))?.into_any(),
+        Layer::Icmpv6(packet) => Py::new(py, PyIcmpv6Packet(packet))?.into_any()
+    })
+}
+
+/// Python class `Packet` - stack of layers, built with `/` operator like in scapy, e.g. `EthernetFrame() / Ipv4Packet(destination="10.0.0.1") / UdpDatagram(destination=53) / b"payload"`
+/// Layers are kept by reference, so editing layer object changes the packet
+#[pyclass(name = "Packet", module = "packedit")]
+pub struct PyPacket {
+    layers: Vec<Py<PyAny>>,
+    payload: Vec<u8>
+}
+impl PyPacket {
+    fn push(&mut self, other: &Bound<'_, PyAny>) -> PyResult<()> {
+        if let Ok(bytes) = other.cast::<PyBytes>() {
+            self.payload.extend_from_slice(bytes.as_bytes());
+            return Ok(());
+        }
+        if !self.payload.is_empty() {return Err(PyValueError::new_err("layer can't follow raw payload"));}
+        if let Ok(packet) = other.extract::<PyRef<PyPacket>>() {
+            self.layers.extend(packet.layers.iter().map(|layer| layer.clone_ref(other.py())));
+            self.payload.extend_from_slice(&packet.payload);
+            return Ok(());
+        }
+        extract_layer(other)?;
+        self.layers.push(other.clone().unbind());
+        Ok(())
+    }
+    fn from_stack(py: Python<'_>, stack: PacketStack) -> PyResult<Self> {
+        Ok(Self {
+            layers: stack.layers.into_iter().map(|layer| layer_object(py, layer)).collect::<PyResult<_>>()?,
+            payload: stack.payload
+        })
+    }
+}
+#[pymethods]
+impl PyPacket {
+    #[new]
+    #[pyo3(signature = (*layers))]
+    fn new(layers: &Bound<'_, PyTuple>) -> PyResult<Self> {
+        let mut packet = Self {layers: Vec::new(), payload: Vec::new()};
+        for layer in layers.iter() {
+            packet.push(&layer)?;
+        }
+        Ok(packet)
+    }
+    /// Parses Ethernet frame into layers, which are Ethernet, IP and TCP, UDP, ICMP or ICMPv6 if frame carries them
+    #[staticmethod]
+    fn from_bytes(py: Python<'_>, data: &[u8]) -> PyResult<Self> {
+        Self::from_stack(py, PacketStack::from_ethernet(data).map_err(value_error)?)
+    }
+    /// Parses IP packet into layers, the same as `from_bytes()`, but without Ethernet layer
+    #[staticmethod]
+    fn from_ip_bytes(py: Python<'_>, data: &[u8]) -> PyResult<Self> {
+        Self::from_stack(py, PacketStack::from_ip(data).map_err(value_error)?)
+    }
+    #[getter]
+    fn layers(&self, py: Python<'_>) -> Vec<Py<PyAny>> {
+        self.layers.iter().map(|layer| layer.clone_ref(py)).collect()
+    }
+    /// Raw payload after the last layer
+    #[getter]
+    fn payload<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.payload)
+    }
+    #[setter]
+    fn set_payload(&mut self, payload: Vec<u8>) {
+        self.payload = payload;
+    }
+    /// Serializes packet, protocol fields of each layer are set to match next layer and checksums are calculated
+    /// If raw payload is empty, own payload of the last layer is used
+    fn build<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let stack = PacketStack {
+            layers: self.layers.iter().map(|layer| extract_layer(layer.bind(py))).collect::<PyResult<_>>()?,
+            payload: self.payload.clone()
+        };
+        Ok(PyBytes::new(py, &stack.serialize()))
+    }
+    fn __bytes__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        self.build(py)
+    }
+    fn __truediv__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyPacket> {
+        let mut packet = Self {layers: self.layers.iter().map(|layer| layer.clone_ref(other.py())).collect(), payload: self.payload.clone()};
+        packet.push(other)?;
+        Ok(packet)
+    }
+    /// Returns the first layer of `layer_type`, e.g. `packet[TcpSegment]`
+    fn __getitem__(&self, py: Python<'_>, layer_type: &Bound<'_, PyType>) -> PyResult<Py<PyAny>> {
+        for layer in &self.layers {
+            if layer.bind(py).is_instance(layer_type)? {return Ok(layer.clone_ref(py));}
+        }
+        Err(PyKeyError::new_err(format!("no {} layer", layer_type.name()?)))
+    }
+    fn __len__(&self) -> usize {
+        self.layers.len()
+    }
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let mut parts = self.layers.iter().map(|layer| Ok(layer.bind(py).repr()?.to_string())).collect::<PyResult<Vec<_>>>()?;
+        if !self.payload.is_empty() {parts.push(format!("{:?}", PyBytes::new(py, &self.payload)));}
+        Ok(parts.join(" / "))
+    }
+}
+
+/// Python module `packedit`
+#[pymodule]
+#[pyo3(name = "packedit")]
+fn packedit_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyEthernetFrame>()?;
+    module.add_class::<PyIpv4Packet>()?;
+    module.add_class::<PyIpv6Packet>()?;
+    module.add_class::<PyTcpSegment>()?;
+    module.add_class::<PyUdpDatagram>()?;
+    module.add_class::<PyIcmpPacket>()?;
+    module.add_class::<PyIcmpv6Packet>()?;
+    module.add_class::<PyPacket>()?;
+    Ok(())
+}
\ No newline at end of file
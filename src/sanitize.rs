@@ -0,0 +1,165 @@
+use std::io::{self, Read, Write};
+use crate::l2::sll::{LinuxSll2Frame, LinuxSllFrame};
+#[cfg(feature = "arp")]
+use crate::l3::arp::ArpPacket;
+#[cfg(feature = "dhcp")]
+use crate::l7::dhcp::DhcpMessage;
+use crate::pcap::{self, PcapReader, PcapRecord, PcapWriter};
+use crate::stack::{Layer, PacketStack};
+use crate::util::{Serializable, Deserializable};
+
+/// Type numbers of IPv4 options which carry addresses, timestamps or security labels: Security(2), Loose Source Route(3), Timestamp(4), Extended Security(5), CIPSO(6), Record Route(7), Strict Source Route(9) and Traceroute(18)
+const IDENTIFYING_IPV4_OPTIONS: [u8; 8] = [2, 3, 4, 5, 6, 7, 9, 18];
+/// Kinds of TCP options which reveal host clock or carry authentication data: Timestamps(8), MD5 Signature(19) and TCP-AO(29)
+const IDENTIFYING_TCP_OPTIONS: [u8; 3] = [8, 19, 29];
+/// Codes of DHCP options which identify client: Host Name(12), Vendor Class Identifier(60), Client Identifier(61) and Client FQDN(81)
+#[cfg(feature = "dhcp")]
+const IDENTIFYING_DHCP_OPTIONS: [u8; 4] = [12, 60, 61, 81];
+/// OUI which replaces masked ones, it has locally administered bit set, so masked addresses don't collide with real vendors
+const MASKED_OUI: [u8; 3] = [0x02, 0x00, 0x00];
+
+/// What to do with payload after the innermost parsed layer, i.e. application data of TCP and UDP or body of unparsed protocols
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadAction {
+    Keep,
+    /// Overwrite payload with zeroes, keeping its length
+    Zero,
+    /// Zero payload and cut captured data after this count of payload bytes, like a smaller snaplen would do
+    /// Headers keep their lengths, so original length of record isn't changed
+    Truncate(usize)
+}
+
+/// Counts of packets processed by `Sanitizer::sanitize_pcap()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SanitizeReport {
+    /// Packets written to sanitized capture
+    pub packets: usize,
+    /// Packets which couldn't be parsed and were dropped, since their data can't be sanitized
+    pub dropped: usize
+}
+
+/// Configurable transform, which removes identifying data from packets to make capture shareable
+/// Packets are parsed into `PacketStack` and serialized back, so lengths and checksums stay consistent with sanitized content
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    pub payload: PayloadAction,
+    /// Replace OUI of unicast MAC addresses with 02:00:00, keeping the lower half, so hosts are still distinguishable
+    /// Addresses are masked in Ethernet, ARP and Linux cooked capture headers and in DHCP client hardware address
+    pub mask_oui: bool,
+    /// Remove identifying IPv4 options, TCP Timestamps and signature options and DHCP options naming client
+    pub strip_options: bool
+}
+impl Sanitizer {
+    /// Constructs `Sanitizer` which zeroes payloads, masks OUIs and strips options
+    pub fn new() -> Self {
+        Self {
+            payload: PayloadAction::Zero,
+            mask_oui: true,
+            strip_options: true
+        }
+    }
+    fn mask_mac(&self, mac: &mut [u8]) {
+        if !self.mask_oui || mac.len() < 3 || mac[0] & 1 != 0 {return;}
+        mac[..3].copy_from_slice(&MASKED_OUI);
+    }
+    /// Sanitizes headers of all layers and application payload of `stack` in place
+    /// Payload is only zeroed here, `PayloadAction::Truncate` is applied to serialized packet by `sanitize_packet()`
+    pub fn sanitize_stack(&self, stack: &mut PacketStack) {
+        #[cfg(feature = "dhcp")]
+        let mut dhcp = false;
+        for layer in &mut stack.layers {
+            match layer {
+                Layer::Ethernet(frame) => {
+                    self.mask_mac(&mut frame.source);
+                    self.mask_mac(&mut frame.destination);
+                }
+                Layer::Ipv4(packet) if self.strip_options => packet.options.retain(|option| !IDENTIFYING_IPV4_OPTIONS.contains(&option.type_number)),
+                Layer::Tcp(segment) if self.strip_options => segment.options.retain(|option| !IDENTIFYING_TCP_OPTIONS.contains(&option.kind)),
+                #[cfg(feature = "dhcp")]
+                Layer::Udp(datagram) => dhcp = [67, 68].contains(&datagram.source) || [67, 68].contains(&datagram.destination),
+                _ => {}
+            }
+        }
+        #[cfg(feature = "arp")]
+        if matches!(stack.layers.as_slice(), [Layer::Ethernet(frame)] if frame.protocol == 0x0806) && let Ok(mut packet) = ArpPacket::deserialize(&stack.payload) {
+            self.mask_mac(&mut packet.sender_mac);
+            self.mask_mac(&mut packet.target_mac);
+            stack.payload = packet.serialize();
+            return;
+        }
+        #[cfg(feature = "dhcp")]
+        if dhcp && self.payload == PayloadAction::Keep && let Ok(mut message) = DhcpMessage::deserialize(&stack.payload) {
+            if message.hardware_length == 6 {self.mask_mac(&mut message.chaddr[..6]);}
+            if self.strip_options {message.options.retain(|option| !IDENTIFYING_DHCP_OPTIONS.contains(&option.get_code()));}
+            stack.payload = message.serialize();
+        }
+        if self.payload != PayloadAction::Keep {stack.payload.fill(0);}
+    }
+    /// Sanitizes packet `data` captured with `link_type`
+    /// Returns `None` if link type isn't Ethernet, raw IP or Linux cooked capture, or packet can't be parsed
+    pub fn sanitize_packet(&self, data: &[u8], link_type: u32) -> Option<Vec<u8>> {
+        match link_type {
+            pcap::LINKTYPE_ETHERNET => self.sanitize_bytes(PacketStack::from_ethernet(data).ok()?),
+            pcap::LINKTYPE_RAW | pcap::LINKTYPE_IPV4 | pcap::LINKTYPE_IPV6 => self.sanitize_bytes(PacketStack::from_ip(data).ok()?),
+            pcap::LINKTYPE_LINUX_SLL => {
+                let mut frame = LinuxSllFrame::deserialize(data).ok()?;
+                self.mask_mac(&mut frame.address);
+                let header_length = data.len() - frame.payload.len();
+                frame.payload = self.sanitize_bytes(PacketStack::from_ip(&frame.payload).ok()?)?;
+                Some(self.cut(frame.serialize(), header_length))
+            }
+            pcap::LINKTYPE_LINUX_SLL2 => {
+                let mut frame = LinuxSll2Frame::deserialize(data).ok()?;
+                self.mask_mac(&mut frame.address);
+                let header_length = data.len() - frame.payload.len();
+                frame.payload = self.sanitize_bytes(PacketStack::from_ip(&frame.payload).ok()?)?;
+                Some(self.cut(frame.serialize(), header_length))
+            }
+            _ => None
+        }
+    }
+    /// Sanitizes and serializes `stack`, truncating payload if needed
+    fn sanitize_bytes(&self, mut stack: PacketStack) -> Option<Vec<u8>> {
+        self.sanitize_stack(&mut stack);
+        let payload_length = stack.payload.len();
+        let bytes = stack.serialize();
+        let header_length = bytes.len() - payload_length;
+        Some(self.cut(bytes, header_length))
+    }
+    /// Applies `PayloadAction::Truncate` to `bytes`, whose payload starts at `header_length`
+    fn cut(&self, mut bytes: Vec<u8>, header_length: usize) -> Vec<u8> {
+        if let PayloadAction::Truncate(length) = self.payload {bytes.truncate(header_length + length);}
+        bytes
+    }
+    /// Sanitizes pcap `record` captured with `link_type`, original length of packet is kept
+    pub fn sanitize_record(&self, record: &PcapRecord, link_type: u32) -> Option<PcapRecord> {
+        Some(PcapRecord {
+            timestamp: record.timestamp,
+            original_length: record.original_length,
+            data: self.sanitize_packet(&record.data, link_type)?
+        })
+    }
+    /// Reads pcap file from `reader` and writes sanitized capture with the same link type to `writer`
+    /// Packets which can't be sanitized are dropped and counted in report
+    pub fn sanitize_pcap<R: Read, W: Write>(&self, reader: R, writer: W) -> io::Result<SanitizeReport> {
+        let reader = PcapReader::new(reader)?;
+        let link_type = reader.link_type;
+        let mut writer = PcapWriter::new(writer, link_type)?;
+        let mut report = SanitizeReport::default();
+        for record in reader {
+            match self.sanitize_record(&record?, link_type) {
+                Some(record) => {
+                    writer.write_record(&record)?;
+                    report.packets += 1;
+                }
+                None => report.dropped += 1
+            }
+        }
+        Ok(report)
+    }
+}
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
\ No newline at end of file
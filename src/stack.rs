@@ -0,0 +1,412 @@
+use core::net::IpAddr;
+use core::ops::Div;
+use crate::l2::ethernet::EthernetFrame;
+use crate::l3::{IpPacket, IpProtocol};
+use crate::l3::icmp::IcmpPacket;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::TcpSegment;
+use crate::l4::udp::UdpDatagram;
+use crate::util::{Deserializable, DeserializableRef, DeserializeError, EtherPayload, IpPayload, Serializable};
+
+/// One layer of `PacketStack`
+#[derive(Debug, Clone)]
+pub enum Layer {
+    Ethernet(EthernetFrame),
+    Ipv4(Ipv4Packet),
+    Ipv6(Ipv6Packet),
+    Tcp(TcpSegment),
+    Udp(UdpDatagram),
+    Icmp(IcmpPacket),
+    Icmpv6(Icmpv6Packet)
+}
+impl Layer {
+    /// Returns EtherType of this layer, or `None` if it can't be carried by Ethernet frame directly
+    pub fn ether_type(&self) -> Option<u16> {
+        match self {
+            Self::Ipv4(packet) => Some(packet.ether_type()),
+            Self::Ipv6(packet) => Some(packet.ether_type()),
+            _ => None
+        }
+    }
+    /// Returns IP protocol of this layer, or `None` if it can't be carried by IP packet
+    pub fn ip_protocol(&self) -> Option<IpProtocol> {
+        match self {
+            Self::Ethernet(_) => None,
+            Self::Ipv4(packet) => Some(packet.ip_protocol()),
+            Self::Ipv6(packet) => Some(packet.ip_protocol()),
+            Self::Tcp(segment) => Some(segment.ip_protocol()),
+            Self::Udp(datagram) => Some(datagram.ip_protocol()),
+            Self::Icmp(packet) => Some(packet.ip_protocol()),
+            Self::Icmpv6(packet) => Some(packet.ip_protocol())
+        }
+    }
+    /// Returns source and destination addresses if this is IP layer
+    pub fn addresses(&self) -> Option<(IpAddr, IpAddr)> {
+        match self {
+            Self::Ipv4(packet) => Some((IpAddr::V4(packet.source), IpAddr::V4(packet.destination))),
+            Self::Ipv6(packet) => Some((IpAddr::V6(packet.source), IpAddr::V6(packet.destination))),
+            _ => None
+        }
+    }
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            Self::Ethernet(frame) => &frame.payload,
+            Self::Ipv4(packet) => &packet.payload,
+            Self::Ipv6(packet) => &packet.payload,
+            Self::Tcp(segment) => &segment.payload,
+            Self::Udp(datagram) => &datagram.payload,
+            Self::Icmp(packet) => &packet.payload,
+            Self::Icmpv6(packet) => &packet.payload
+        }
+    }
+    pub fn payload_mut(&mut self) -> &mut Vec<u8> {
+        match self {
+            Self::Ethernet(frame) => &mut frame.payload,
+            Self::Ipv4(packet) => &mut packet.payload,
+            Self::Ipv6(packet) => &mut packet.payload,
+            Self::Tcp(segment) => &mut segment.payload,
+            Self::Udp(datagram) => &mut datagram.payload,
+            Self::Icmp(packet) => &mut packet.payload,
+            Self::Icmpv6(packet) => &mut packet.payload
+        }
+    }
+    /// Returns length of serialized layer, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.wire_len_with(self.payload().len())
+    }
+    /// The same as `wire_len()`, but for layer with payload of `payload_len` bytes instead of its own
+    fn wire_len_with(&self, payload_len: usize) -> usize {
+        match self {
+            Self::Ethernet(_) => 14 + payload_len,
+            Self::Ipv4(packet) => packet.header_len() + payload_len,
+            Self::Ipv6(packet) => packet.wire_len_with(payload_len),
+            Self::Tcp(segment) => segment.header_len() + payload_len,
+            Self::Udp(_) | Self::Icmp(_) | Self::Icmpv6(_) => 8 + payload_len
+        }
+    }
+    /// Serializes layer with already serialized `payload`, which is a layer of `ether_type` or `ip_protocol`
+    /// Protocol fields and checksums are filled, TCP, UDP and ICMPv6 checksums use `addresses` of enclosing IP packet
+    fn build(mut self, ether_type: Option<u16>, ip_protocol: Option<IpProtocol>, payload: Option<Vec<u8>>, addresses: Option<(IpAddr, IpAddr)>) -> Vec<u8> {
+        if let Some(payload) = payload {*self.payload_mut() = payload;}
+        match self {
+            Self::Ethernet(mut frame) => {
+                if let Some(ether_type) = ether_type {frame.protocol = ether_type;}
+                frame.serialize()
+            }
+            Self::Ipv4(mut packet) => {
+                if let Some(protocol) = ip_protocol {packet.protocol = protocol;}
+                packet.recalculate_checksum();
+                packet.serialize()
+            }
+            Self::Ipv6(mut packet) => {
+                if let Some(protocol) = ip_protocol {
+                    match packet.extension_headers.last_mut() {
+                        Some(header) => header.set_next_header_type(protocol.into()),
+                        None => packet.next_header = protocol.into()
+                    }
+                }
+                packet.serialize()
+            }
+            Self::Tcp(mut segment) => {
+                if let Some((source, destination)) = addresses {let _ = segment.recalculate_checksum(source, destination);}
+                segment.serialize()
+            }
+            Self::Udp(mut datagram) => {
+                if let Some((source, destination)) = addresses {let _ = datagram.recalculate_checksum(source, destination);}
+                datagram.serialize()
+            }
+            Self::Icmp(mut packet) => {
+                packet.recalculate_checksum();
+                packet.serialize()
+            }
+            Self::Icmpv6(mut packet) => {
+                if let Some((IpAddr::V6(source), IpAddr::V6(destination))) = addresses {packet.recalculate_checksum(source, destination);}
+                packet.serialize()
+            }
+        }
+    }
+}
+impl From<EthernetFrame> for Layer {
+    fn from(frame: EthernetFrame) -> Self {
+        Self::Ethernet(frame)
+    }
+}
+impl From<Ipv4Packet> for Layer {
+    fn from(packet: Ipv4Packet) -> Self {
+        Self::Ipv4(packet)
+    }
+}
+impl From<Ipv6Packet> for Layer {
+    fn from(packet: Ipv6Packet) -> Self {
+        Self::Ipv6(packet)
+    }
+}
+impl From<IpPacket> for Layer {
+    fn from(packet: IpPacket) -> Self {
+        match packet {
+            IpPacket::V4(packet) => Self::Ipv4(packet),
+            IpPacket::V6(packet) => Self::Ipv6(packet)
+        }
+    }
+}
+impl From<TcpSegment> for Layer {
+    fn from(segment: TcpSegment) -> Self {
+        Self::Tcp(segment)
+    }
+}
+impl From<UdpDatagram> for Layer {
+    fn from(datagram: UdpDatagram) -> Self {
+        Self::Udp(datagram)
+    }
+}
+impl From<IcmpPacket> for Layer {
+    fn from(packet: IcmpPacket) -> Self {
+        Self::Icmp(packet)
+    }
+}
+impl From<Icmpv6Packet> for Layer {
+    fn from(packet: Icmpv6Packet) -> Self {
+        Self::Icmpv6(packet)
+    }
+}
+
+/// Stack of layers from the outermost one, followed by raw payload
+/// When serializing, protocol fields of each layer are set to match the next layer and checksums are calculated
+/// If raw `payload` is empty, own payload of the last layer is kept, otherwise payloads of all layers are replaced
+#[derive(Debug, Clone)]
+pub struct PacketStack {
+    pub layers: Vec<Layer>,
+    pub payload: Vec<u8>
+}
+impl PacketStack {
+    /// Constructs an empty `PacketStack`
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            payload: Vec::new()
+        }
+    }
+    /// Returns length of serialized stack, computed without serializing it
+    pub fn wire_len(&self) -> usize {
+        self.suffix_lengths().first().copied().unwrap_or(self.payload.len())
+    }
+    /// Returns length of serialized stack starting from each layer, i.e. lengths of each layer with everything it encloses
+    pub(crate) fn suffix_lengths(&self) -> Vec<usize> {
+        let mut lengths = vec![0usize; self.layers.len()];
+        let mut length = None;
+        for (i, layer) in self.layers.iter().enumerate().rev() {
+            let current = match length {
+                None if self.payload.is_empty() => layer.wire_len(),
+                None => layer.wire_len_with(self.payload.len()),
+                Some(length) => layer.wire_len_with(length)
+            };
+            lengths[i] = current;
+            length = Some(current);
+        }
+        lengths
+    }
+    /// Appends `layer` on top of the stack
+    pub fn push<L: Into<Layer>>(&mut self, layer: L) {
+        self.layers.push(layer.into());
+    }
+    /// Parses Ethernet frame into layers, which are Ethernet, IP and TCP, UDP, ICMP or ICMPv6 if frame carries them
+    /// The rest, i.e. payload of the innermost parsed layer, goes to raw `payload`
+    pub fn from_ethernet(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut frame = EthernetFrame::deserialize(bytes)?;
+        let payload = core::mem::take(&mut frame.payload);
+        let frame_protocol = frame.protocol;
+        let mut stack = Self::new();
+        stack.push(frame);
+        stack.payload = match frame_protocol {
+            0x0800 | 0x86DD => stack.parse_ip(&payload).unwrap_or(payload),
+            _ => payload
+        };
+        Ok(stack)
+    }
+    /// The same as `from_ethernet()`, but stack starts with IP layer
+    pub fn from_ip(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut stack = Self::new();
+        stack.payload = stack.parse_ip(bytes)?;
+        Ok(stack)
+    }
+    fn parse_ip(&mut self, bytes: &[u8]) -> Result<Vec<u8>, DeserializeError> {
+        let packet = IpPacket::deserialize(bytes)?;
+        let protocol = packet.protocol();
+        let mut packet = Layer::from(packet);
+        let mut payload = core::mem::take(packet.payload_mut());
+        self.push(packet);
+        let transport = match protocol {
+            IpProtocol::Tcp => TcpSegment::deserialize(&payload).ok().map(Layer::Tcp),
+            IpProtocol::Udp => UdpDatagram::deserialize(&payload).ok().map(Layer::Udp),
+            IpProtocol::Icmp => IcmpPacket::deserialize(&payload).ok().map(Layer::Icmp),
+            IpProtocol::Icmpv6 => Icmpv6Packet::deserialize(&payload).ok().map(Layer::Icmpv6),
+            _ => None
+        };
+        if let Some(mut transport) = transport {
+            payload = core::mem::take(transport.payload_mut());
+            self.push(transport);
+        }
+        Ok(payload)
+    }
+}
+impl Default for PacketStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Serializable for PacketStack {
+    fn serialize(self) -> Vec<u8> {
+        self.serialize_with(|_, bytes, _| bytes)
+    }
+}
+impl PacketStack {
+    /// Serializes stack, passing each layer to `emit` from the innermost one
+    /// `emit` gets index of layer, its normally serialized bytes and length of payload at their end, and returns bytes of layer to enclose
+    fn serialize_with(self, mut emit: impl FnMut(usize, Vec<u8>, usize) -> Vec<u8>) -> Vec<u8> {
+        let mut addresses = Vec::with_capacity(self.layers.len());
+        let mut enclosing = None;
+        for layer in &self.layers {
+            addresses.push(enclosing);
+            enclosing = layer.addresses().or(enclosing);
+        }
+        let inner = self.layers.iter().skip(1).map(|layer| (layer.ether_type(), layer.ip_protocol())).chain([(None, None)]).collect::<Vec<_>>();
+        let mut payload = if self.payload.is_empty() && !self.layers.is_empty() {None} else {Some(self.payload)};
+        for (i, ((layer, (ether_type, ip_protocol)), addresses)) in self.layers.into_iter().zip(inner).zip(addresses).enumerate().rev() {
+            let payload_len = payload.as_ref().map(|payload| payload.len()).unwrap_or(layer.payload().len());
+            payload = Some(emit(i, layer.build(ether_type, ip_protocol, payload, addresses), payload_len));
+        }
+        payload.unwrap_or_default()
+    }
+}
+
+/// Original bytes of one layer of `PreservedStack`
+#[derive(Debug, Clone)]
+struct OriginalLayer {
+    /// Raw header, including unknown options and nonstandard padding
+    header: Vec<u8>,
+    /// Raw bytes after payload, i.e. padding of short Ethernet frame
+    trailer: Vec<u8>,
+    payload_len: usize,
+    /// Header as `PacketStack` serializes it right after parsing, layer is considered unmodified while it's serialized the same
+    normalized: Vec<u8>
+}
+
+/// `PacketStack` which remembers original bytes of parsed packet, so it can be rewritten without normalizing traffic
+/// Unmodified layers are serialized exactly as they were captured, including unknown options, nonstandard padding, wrong checksums and trailing bytes
+/// Modified layers are serialized as usual, enclosing layers are kept as long as their serialized header doesn't change, i.e. length of payload stays the same
+/// Layers are matched by their index, so if layers are added or removed the whole stack is serialized as usual
+#[derive(Debug, Clone)]
+pub struct PreservedStack {
+    pub stack: PacketStack,
+    original: Vec<OriginalLayer>
+}
+impl PreservedStack {
+    /// Parses Ethernet frame, the same as `PacketStack::from_ethernet()`
+    pub fn from_ethernet(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::preserve(PacketStack::from_ethernet(bytes)?, bytes))
+    }
+    /// Parses IP packet, the same as `PacketStack::from_ip()`
+    pub fn from_ip(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::preserve(PacketStack::from_ip(bytes)?, bytes))
+    }
+    fn preserve(stack: PacketStack, bytes: &[u8]) -> Self {
+        let mut original = Vec::with_capacity(stack.layers.len());
+        let mut rest = bytes;
+        for layer in &stack.layers {
+            let payload = match layer {
+                Layer::Ethernet(_) => EthernetFrame::deserialize_ref(rest).map(|packet| packet.payload),
+                Layer::Ipv4(_) => Ipv4Packet::deserialize_ref(rest).map(|packet| packet.payload),
+                Layer::Ipv6(_) => Ipv6Packet::deserialize_ref(rest).map(|packet| packet.payload),
+                Layer::Tcp(_) => TcpSegment::deserialize_ref(rest).map(|packet| packet.payload),
+                Layer::Udp(_) => UdpDatagram::deserialize_ref(rest).map(|packet| packet.payload),
+                Layer::Icmp(_) => IcmpPacket::deserialize_ref(rest).map(|packet| packet.payload),
+                Layer::Icmpv6(_) => Icmpv6Packet::deserialize_ref(rest).map(|packet| packet.payload)
+            };
+            let Ok(payload) = payload else {return Self {stack, original: Vec::new()};};
+            let start = payload.as_ptr().addr() - rest.as_ptr().addr();
+            let end = start + payload.len();
+            original.push(OriginalLayer {header: rest[..start].to_vec(), trailer: rest[end..].to_vec(), payload_len: payload.len(), normalized: Vec::new()});
+            rest = payload;
+        }
+        stack.clone().serialize_with(|i, bytes, payload_len| {
+            let layer = &mut original[i];
+            layer.normalized = bytes[..bytes.len() - payload_len].to_vec();
+            [&layer.header[..], &bytes[bytes.len() - payload_len..], &layer.trailer].concat()
+        });
+        Self {stack, original}
+    }
+    /// Checks if any layer would be serialized differently from its original bytes
+    pub fn is_modified(&self) -> bool {
+        self.clone().rebuild().1
+    }
+    /// Serializes stack with original bytes of unmodified layers, also returns whether any layer is modified
+    fn rebuild(self) -> (Vec<u8>, bool) {
+        let Self {stack, original} = self;
+        if stack.layers.len() != original.len() {return (stack.serialize(), true);}
+        let mut modified = false;
+        let bytes = stack.serialize_with(|i, bytes, payload_len| {
+            let layer = &original[i];
+            let (header, payload) = bytes.split_at(bytes.len() - payload_len);
+            if header != layer.normalized {
+                modified = true;
+                return bytes;
+            }
+            let trailer = if payload_len == layer.payload_len {&layer.trailer[..]} else {&[]};
+            [&layer.header[..], payload, trailer].concat()
+        });
+        (bytes, modified)
+    }
+}
+impl Serializable for PreservedStack {
+    fn serialize(self) -> Vec<u8> {
+        self.rebuild().0
+    }
+}
+/// Scapy-style stacking, i.e. `EthernetFrame::new() / Ipv4Packet::new() / TcpSegment::new() / b"payload"`
+/// Layer on the right is pushed on top of the stack, fields wiring layers are filled when stack is serialized
+impl<L: Into<Layer>> Div<L> for PacketStack {
+    type Output = PacketStack;
+    fn div(mut self, layer: L) -> PacketStack {
+        self.push(layer);
+        self
+    }
+}
+/// Bytes on the right are appended to raw payload
+impl Div<&[u8]> for PacketStack {
+    type Output = PacketStack;
+    fn div(mut self, payload: &[u8]) -> PacketStack {
+        self.payload.extend_from_slice(payload);
+        self
+    }
+}
+impl<const N: usize> Div<&[u8; N]> for PacketStack {
+    type Output = PacketStack;
+    fn div(self, payload: &[u8; N]) -> PacketStack {
+        self / &payload[..]
+    }
+}
+impl Div<Vec<u8>> for PacketStack {
+    type Output = PacketStack;
+    fn div(self, payload: Vec<u8>) -> PacketStack {
+        self / &payload[..]
+    }
+}
+
+/// Implements `Div` for each of listed layer types, which starts `PacketStack` with the left layer
+macro_rules! impl_div_layer {
+    ($($layer:ty),* $(,)?) => {
+        $(
+            impl<R> Div<R> for $layer where PacketStack: Div<R, Output = PacketStack> {
+                type Output = PacketStack;
+                fn div(self, rhs: R) -> PacketStack {
+                    let mut stack = PacketStack::new();
+                    stack.push(self);
+                    stack / rhs
+                }
+            }
+        )*
+    };
+}
+impl_div_layer!(Layer, EthernetFrame, IpPacket, Ipv4Packet, Ipv6Packet, TcpSegment, UdpDatagram, IcmpPacket, Icmpv6Packet);
\ No newline at end of file
@@ -0,0 +1,161 @@
+use core::fmt;
+use std::collections::HashMap;
+use crate::l3::IpProtocol;
+use crate::l4::Port;
+use crate::stack::{Layer, PacketStack};
+
+/// Upper bounds of packet size histogram buckets, the same as in Wireshark Packet Lengths statistics, the last bucket has no bound
+pub const SIZE_BUCKETS: [usize; 9] = [20, 40, 80, 160, 320, 640, 1280, 2560, 5120];
+
+/// Number of packets and their total length in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counter {
+    pub packets: u64,
+    pub bytes: u64
+}
+impl Counter {
+    fn add(&mut self, length: usize) {
+        self.packets += 1;
+        self.bytes += length as u64;
+    }
+}
+
+/// Statistics sink, which accumulates packet counters by protocol and packet size histogram
+/// Feed it with parsed packets using `record()`, or with raw Ethernet frames using `record_ethernet()`
+/// Then take `snapshot()` for sorted counters or print it as a capture summary
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    total: Counter,
+    malformed: u64,
+    ether_types: HashMap<u16, Counter>,
+    ip_protocols: HashMap<u8, Counter>,
+    ports: HashMap<(u8, u16), Counter>,
+    sizes: [u64; SIZE_BUCKETS.len() + 1]
+}
+impl Stats {
+    /// Constructs an empty `Stats`
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Accounts parsed packet of `length` bytes, which is length of captured packet or `stack.wire_len()`
+    /// Ports are counted for TCP and UDP once for source and once for destination, unless they are equal
+    pub fn record(&mut self, stack: &PacketStack, length: usize) {
+        self.total.add(length);
+        self.sizes[SIZE_BUCKETS.iter().position(|bound| length < *bound).unwrap_or(SIZE_BUCKETS.len())] += 1;
+        for layer in &stack.layers {
+            match layer {
+                Layer::Ethernet(frame) => self.ether_types.entry(frame.protocol).or_default().add(length),
+                Layer::Ipv4(packet) => self.ip_protocols.entry(packet.protocol.into()).or_default().add(length),
+                Layer::Ipv6(packet) => {
+                    let protocol = packet.extension_headers.last().map(|header| header.get_next_header_type()).unwrap_or(packet.next_header);
+                    self.ip_protocols.entry(protocol.into()).or_default().add(length);
+                }
+                Layer::Tcp(segment) => self.add_ports(IpProtocol::Tcp, segment.source, segment.destination, length),
+                Layer::Udp(datagram) => self.add_ports(IpProtocol::Udp, datagram.source, datagram.destination, length),
+                Layer::Icmp(_) | Layer::Icmpv6(_) => {}
+            }
+        }
+    }
+    fn add_ports(&mut self, protocol: IpProtocol, source: u16, destination: u16, length: usize) {
+        self.ports.entry((protocol.into(), source)).or_default().add(length);
+        if destination != source {
+            self.ports.entry((protocol.into(), destination)).or_default().add(length);
+        }
+    }
+    /// Parses Ethernet frame `bytes` and accounts it with its captured length
+    /// Frames which can't be parsed are counted only in totals and as malformed
+    pub fn record_ethernet(&mut self, bytes: &[u8]) {
+        match PacketStack::from_ethernet(bytes) {
+            Ok(stack) => self.record(&stack, bytes.len()),
+            Err(_) => self.record_malformed(bytes.len())
+        }
+    }
+    /// The same as `record_ethernet()`, but packet starts with IP header
+    pub fn record_ip(&mut self, bytes: &[u8]) {
+        match PacketStack::from_ip(bytes) {
+            Ok(stack) => self.record(&stack, bytes.len()),
+            Err(_) => self.record_malformed(bytes.len())
+        }
+    }
+    /// Accounts packet of `length` bytes, which couldn't be parsed, it's counted only in totals and as malformed
+    pub fn record_malformed(&mut self, length: usize) {
+        self.record(&PacketStack::new(), length);
+        self.malformed += 1;
+    }
+    /// Returns total number of recorded packets and bytes
+    pub fn total(&self) -> Counter {
+        self.total
+    }
+    /// Forgets all recorded packets
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+    /// Returns counters sorted from the most frequent, ties are sorted by key
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            total: self.total,
+            malformed: self.malformed,
+            ether_types: sorted(&self.ether_types),
+            ip_protocols: sorted(&self.ip_protocols),
+            ports: sorted(&self.ports),
+            sizes: self.sizes.iter().enumerate().map(|(i, count)| {
+                let start = if i == 0 {0} else {SIZE_BUCKETS[i - 1]};
+                (start, SIZE_BUCKETS.get(i).copied(), *count)
+            }).collect()
+        }
+    }
+}
+
+fn sorted<K: Copy + Ord>(counters: &HashMap<K, Counter>) -> Vec<(K, Counter)> {
+    let mut counters: Vec<(K, Counter)> = counters.iter().map(|(key, counter)| (*key, *counter)).collect();
+    counters.sort_by(|(a_key, a), (b_key, b)| b.packets.cmp(&a.packets).then(a_key.cmp(b_key)));
+    counters
+}
+
+/// Counters of `Stats` at one moment, sorted from the most frequent
+/// Formats as plain text report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub total: Counter,
+    /// Number of packets, which couldn't be parsed
+    pub malformed: u64,
+    pub ether_types: Vec<(u16, Counter)>,
+    pub ip_protocols: Vec<(u8, Counter)>,
+    /// TCP and UDP ports with their IP protocol number
+    pub ports: Vec<((u8, u16), Counter)>,
+    /// Packet size histogram as `(start, end, packets)`, where `end` is exclusive and `None` for the last bucket
+    pub sizes: Vec<(usize, Option<usize>, u64)>
+}
+impl fmt::Display for StatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Packets: {}, bytes: {}, malformed: {}", self.total.packets, self.total.bytes, self.malformed)?;
+        if !self.ether_types.is_empty() {
+            writeln!(f, "EtherType:")?;
+            for (ether_type, counter) in &self.ether_types {
+                writeln!(f, "  0x{:04x} {:>10} packets {:>12} bytes", ether_type, counter.packets, counter.bytes)?;
+            }
+        }
+        if !self.ip_protocols.is_empty() {
+            writeln!(f, "IP protocol:")?;
+            for (protocol, counter) in &self.ip_protocols {
+                writeln!(f, "  {:<6} {:>10} packets {:>12} bytes", protocol, counter.packets, counter.bytes)?;
+            }
+        }
+        if !self.ports.is_empty() {
+            writeln!(f, "Port:")?;
+            for ((protocol, port), counter) in &self.ports {
+                let name = if IpProtocol::from(*protocol) == IpProtocol::Tcp {"tcp"} else {"udp"};
+                writeln!(f, "  {:<20} {:>10} packets {:>12} bytes", format!("{}/{:#}", name, Port(*port)), counter.packets, counter.bytes)?;
+            }
+        }
+        writeln!(f, "Packet size:")?;
+        for (start, end, packets) in &self.sizes {
+            let range = match end {
+                Some(end) => format!("{}-{}", start, end - 1),
+                None => format!("{}+", start)
+            };
+            writeln!(f, "  {:<10} {:>10} packets", range, packets)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use core::net::IpAddr;
+use std::collections::HashMap;
+use crate::stack::{Layer, PacketStack};
+use crate::util::{checksum, transport_checksum, update_checksum, Serializable};
+
+/// Field of `PacketTemplate`, which has to be given a value on each instantiation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Placeholder {
+    /// Source address of the innermost IP layer
+    SourceAddress,
+    /// Destination address of the innermost IP layer
+    DestinationAddress,
+    /// Source port of TCP or UDP layer
+    SourcePort,
+    /// Destination port of TCP or UDP layer
+    DestinationPort,
+    /// Sequence number of TCP layer
+    SequenceNumber,
+    /// Raw payload after the last layer
+    Payload
+}
+
+/// Values of template fields for one instance
+/// Values of placeholders are required, other values are optional overrides of template fields
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues<'a> {
+    pub source_address: Option<IpAddr>,
+    pub destination_address: Option<IpAddr>,
+    pub source_port: Option<u16>,
+    pub destination_port: Option<u16>,
+    pub sequence_number: Option<u32>,
+    pub payload: Option<&'a [u8]>
+}
+impl TemplateValues<'_> {
+    /// Returns `true` if value of `placeholder` field is given
+    pub fn contains(&self, placeholder: Placeholder) -> bool {
+        match placeholder {
+            Placeholder::SourceAddress => self.source_address.is_some(),
+            Placeholder::DestinationAddress => self.destination_address.is_some(),
+            Placeholder::SourcePort => self.source_port.is_some(),
+            Placeholder::DestinationPort => self.destination_port.is_some(),
+            Placeholder::SequenceNumber => self.sequence_number.is_some(),
+            Placeholder::Payload => self.payload.is_some()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateError {
+    /// Template has no layer with the field, i.e. ports without TCP or UDP layer
+    NoField(Placeholder),
+    /// Value of placeholder is not given
+    MissingValue(Placeholder),
+    /// Address has other IP version than IP layer of template
+    AddressVersion(Placeholder)
+}
+
+/// Kind and offset of one layer in serialized template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Ethernet,
+    Ipv4,
+    Ipv6,
+    Tcp,
+    Udp,
+    Icmp,
+    Icmpv6
+}
+impl Kind {
+    fn of(layer: &Layer) -> Self {
+        match layer {
+            Layer::Ethernet(_) => Self::Ethernet,
+            Layer::Ipv4(_) => Self::Ipv4,
+            Layer::Ipv6(_) => Self::Ipv6,
+            Layer::Tcp(_) => Self::Tcp,
+            Layer::Udp(_) => Self::Udp,
+            Layer::Icmp(_) => Self::Icmp,
+            Layer::Icmpv6(_) => Self::Icmpv6
+        }
+    }
+    /// Offset of checksum field from layer start
+    fn checksum_offset(self) -> Option<usize> {
+        match self {
+            Self::Ethernet | Self::Ipv6 => None,
+            Self::Ipv4 => Some(10),
+            Self::Tcp => Some(16),
+            Self::Udp => Some(6),
+            Self::Icmp | Self::Icmpv6 => Some(2)
+        }
+    }
+    /// Layer checksum covers pseudo-header with addresses of enclosing IP layer
+    fn uses_pseudo_header(self) -> bool {
+        matches!(self, Self::Tcp | Self::Udp | Self::Icmpv6)
+    }
+}
+
+/// Named packet, which is serialized once and instantiated many times by patching placeholders and overridden fields in place
+/// Checksums are updated incrementally, only payload override makes lengths and checksums of enclosing layers be recalculated
+#[derive(Debug, Clone)]
+pub struct PacketTemplate {
+    pub name: String,
+    placeholders: Vec<Placeholder>,
+    bytes: Vec<u8>,
+    /// Kind and offset of each layer
+    layers: Vec<(Kind, usize)>,
+    /// Offset of raw payload after the last layer
+    payload_offset: usize,
+    /// Index of the innermost IP layer
+    ip: Option<usize>,
+    /// Index of TCP or UDP layer after the innermost IP layer
+    transport: Option<usize>
+}
+impl PacketTemplate {
+    /// Constructs template `name` from `stack` with fields of `placeholders`, which have to be given a value on each instantiation
+    /// Fields of `stack` which are placeholders keep any value, it is never used
+    /// Returns `Err(TemplateError::NoField)` if `stack` has no layer with one of `placeholders` fields
+    pub fn new(name: &str, stack: PacketStack, placeholders: &[Placeholder]) -> Result<Self, TemplateError> {
+        let lengths = stack.suffix_lengths();
+        let payload_length = match stack.layers.last() {
+            Some(layer) if stack.payload.is_empty() => layer.payload().len(),
+            _ => stack.payload.len()
+        };
+        let layers: Vec<(Kind, usize)> = stack.layers.iter().zip(&lengths).map(|(layer, length)| (Kind::of(layer), lengths[0] - length)).collect();
+        let bytes = stack.serialize();
+        let ip = layers.iter().rposition(|(kind, _)| matches!(kind, Kind::Ipv4 | Kind::Ipv6));
+        let transport = ip.map(|ip| ip + 1).filter(|transport| matches!(layers.get(*transport), Some((Kind::Tcp | Kind::Udp, _))));
+        let template = Self {
+            name: name.to_string(),
+            placeholders: placeholders.to_vec(),
+            payload_offset: bytes.len() - payload_length,
+            bytes,
+            layers,
+            ip,
+            transport
+        };
+        for placeholder in placeholders {
+            if !template.has_field(*placeholder) {return Err(TemplateError::NoField(*placeholder));}
+        }
+        Ok(template)
+    }
+    pub fn placeholders(&self) -> &[Placeholder] {
+        &self.placeholders
+    }
+    /// Returns serialized template, where placeholders have values of the stack template was constructed from
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    fn has_field(&self, placeholder: Placeholder) -> bool {
+        match placeholder {
+            Placeholder::SourceAddress | Placeholder::DestinationAddress => self.ip.is_some(),
+            Placeholder::SourcePort | Placeholder::DestinationPort => self.transport.is_some(),
+            Placeholder::SequenceNumber => self.transport.is_some_and(|transport| self.layers[transport].0 == Kind::Tcp),
+            Placeholder::Payload => true
+        }
+    }
+    /// Returns offset of checksum field of layer `index`, or `None` if layer has no checksum or UDP checksum is not used
+    fn checksum_field(&self, bytes: &[u8], index: usize) -> Option<usize> {
+        let (kind, offset) = self.layers[index];
+        let field = offset + kind.checksum_offset()?;
+        if kind == Kind::Udp && bytes[field..field + 2] == [0, 0] {return None;}
+        Some(field)
+    }
+    /// Instantiates template with `values`
+    /// Returns `Err(TemplateError::MissingValue)` if value of any placeholder is not given
+    pub fn instantiate(&self, values: &TemplateValues) -> Result<Vec<u8>, TemplateError> {
+        let mut buffer = Vec::with_capacity(self.bytes.len());
+        self.instantiate_into(values, &mut buffer)?;
+        Ok(buffer)
+    }
+    /// The same as `instantiate()`, but writes packet into `buffer`, so it can be reused without allocations
+    pub fn instantiate_into(&self, values: &TemplateValues, buffer: &mut Vec<u8>) -> Result<(), TemplateError> {
+        for placeholder in &self.placeholders {
+            if !values.contains(*placeholder) {return Err(TemplateError::MissingValue(*placeholder));}
+        }
+        for placeholder in [Placeholder::SourceAddress, Placeholder::DestinationAddress, Placeholder::SourcePort, Placeholder::DestinationPort, Placeholder::SequenceNumber] {
+            if values.contains(placeholder) && !self.has_field(placeholder) {return Err(TemplateError::NoField(placeholder));}
+        }
+        buffer.clear();
+        match values.payload {
+            Some(payload) => {
+                buffer.extend_from_slice(&self.bytes[..self.payload_offset]);
+                buffer.extend_from_slice(payload);
+            }
+            None => buffer.extend_from_slice(&self.bytes)
+        }
+        if let Some(ip) = self.ip {
+            let (kind, offset) = self.layers[ip];
+            let mut checksums = Vec::with_capacity(2);
+            checksums.extend(self.checksum_field(buffer, ip));
+            if let Some(next) = self.layers.get(ip + 1) && next.0.uses_pseudo_header() {checksums.extend(self.checksum_field(buffer, ip + 1));}
+            let fields = [(Placeholder::SourceAddress, values.source_address, 0), (Placeholder::DestinationAddress, values.destination_address, 1)];
+            for (placeholder, address, position) in fields {
+                match (kind, address) {
+                    (Kind::Ipv4, Some(IpAddr::V4(address))) => patch(buffer, offset + 12 + position * 4, &address.octets(), &checksums),
+                    (Kind::Ipv6, Some(IpAddr::V6(address))) => patch(buffer, offset + 8 + position * 16, &address.octets(), &checksums),
+                    (_, Some(_)) => return Err(TemplateError::AddressVersion(placeholder)),
+                    (_, None) => {}
+                }
+            }
+        }
+        if let Some(transport) = self.transport {
+            let offset = self.layers[transport].1;
+            let checksums: Vec<usize> = self.checksum_field(buffer, transport).into_iter().collect();
+            if let Some(port) = values.source_port {patch(buffer, offset, &port.to_be_bytes(), &checksums);}
+            if let Some(port) = values.destination_port {patch(buffer, offset + 2, &port.to_be_bytes(), &checksums);}
+            if let Some(sequence_number) = values.sequence_number {patch(buffer, offset + 4, &sequence_number.to_be_bytes(), &checksums);}
+        }
+        if values.payload.is_some() {self.recalculate(buffer);}
+        Ok(())
+    }
+    /// Sets length fields of all layers to match changed payload and recalculates checksums from the innermost layer
+    fn recalculate(&self, bytes: &mut [u8]) {
+        let total = bytes.len();
+        for (kind, offset) in &self.layers {
+            match kind {
+                Kind::Ipv4 => bytes[offset + 2..offset + 4].copy_from_slice(&((total - offset) as u16).to_be_bytes()),
+                Kind::Ipv6 => bytes[offset + 4..offset + 6].copy_from_slice(&((total - offset - 40) as u16).to_be_bytes()),
+                Kind::Udp => bytes[offset + 4..offset + 6].copy_from_slice(&((total - offset) as u16).to_be_bytes()),
+                _ => {}
+            }
+        }
+        for index in (0..self.layers.len()).rev() {
+            let Some(field) = self.checksum_field(bytes, index) else {continue;};
+            let (kind, offset) = self.layers[index];
+            bytes[field..field + 2].copy_from_slice(&[0, 0]);
+            let calculated = match kind {
+                Kind::Ipv4 => checksum(&bytes[offset..offset + (bytes[offset] & 15) as usize * 4]),
+                Kind::Icmp => checksum(&bytes[offset..]),
+                _ => {
+                    let addresses = self.layers[..index].iter().rev().find_map(|(kind, ip)| match kind {
+                        Kind::Ipv4 => Some((IpAddr::from(*bytes[ip + 12..ip + 16].as_array::<4>().unwrap()), IpAddr::from(*bytes[ip + 16..ip + 20].as_array::<4>().unwrap()))),
+                        Kind::Ipv6 => Some((IpAddr::from(*bytes[ip + 8..ip + 24].as_array::<16>().unwrap()), IpAddr::from(*bytes[ip + 24..ip + 40].as_array::<16>().unwrap()))),
+                        _ => None
+                    });
+                    let protocol = match kind {Kind::Tcp => 6, Kind::Udp => 17, _ => 58};
+                    addresses.and_then(|(source, destination)| transport_checksum(source, destination, protocol, &[&bytes[offset..]])).unwrap_or_default()
+                }
+            };
+            let calculated = if kind == Kind::Udp && calculated == 0 {0xFFFF} else {calculated};
+            bytes[field..field + 2].copy_from_slice(&calculated.to_be_bytes());
+        }
+    }
+}
+
+/// Replaces bytes at `offset` with `value` and incrementally updates checksum fields at `checksums` offsets, which cover replaced bytes
+/// Replaced bytes have to be aligned to `2 bytes` boundary of checksummed data
+fn patch(bytes: &mut [u8], offset: usize, value: &[u8], checksums: &[usize]) {
+    for (i, word) in value.chunks_exact(2).enumerate() {
+        let old = u16::from_be_bytes([bytes[offset + i * 2], bytes[offset + i * 2 + 1]]);
+        let new = u16::from_be_bytes([word[0], word[1]]);
+        for field in checksums {
+            let updated = update_checksum(u16::from_be_bytes([bytes[*field], bytes[*field + 1]]), old, new);
+            bytes[*field..*field + 2].copy_from_slice(&updated.to_be_bytes());
+        }
+    }
+    bytes[offset..offset + value.len()].copy_from_slice(value);
+}
+
+/// Collection of templates by name
+#[derive(Debug, Clone)]
+pub struct TemplateRegistry {
+    templates: HashMap<String, PacketTemplate>
+}
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        Self {
+            templates: HashMap::new()
+        }
+    }
+    /// Adds `template` under its name, replacing and returning template with the same name
+    pub fn insert(&mut self, template: PacketTemplate) -> Option<PacketTemplate> {
+        self.templates.insert(template.name.clone(), template)
+    }
+    pub fn get(&self, name: &str) -> Option<&PacketTemplate> {
+        self.templates.get(name)
+    }
+    pub fn remove(&mut self, name: &str) -> Option<PacketTemplate> {
+        self.templates.remove(name)
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+}
+impl Default for TemplateRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
\ No newline at end of file
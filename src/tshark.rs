@@ -0,0 +1,251 @@
+use core::time::Duration;
+use std::io::{self, Write};
+use crate::l2::ethernet;
+use crate::stack::{Layer, PacketStack};
+use crate::util::Serializable;
+
+/// Output format of tshark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsharkFormat {
+    /// `-T json`: JSON array of packets, fields are named as Wireshark display filter fields, i.e. `ip.src`
+    Json,
+    /// `-T ek`: newline-delimited Elasticsearch bulk request, each packet is preceded by index action line and fields are prefixed with protocol, i.e. `ip_ip_src`
+    Ek
+}
+
+/// Fields of one protocol layer, named as Wireshark display filter fields, values are formatted as tshark does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsharkLayer {
+    /// Protocol name, i.e. `eth`, `ip`, `tcp` or `data` for payload
+    pub protocol: &'static str,
+    pub fields: Vec<(&'static str, String)>
+}
+
+/// Converts `stack` to tshark layers, starting with `frame` layer for packet captured at `timestamp`, which has `number` in capture
+/// Raw payload of `stack`, or payload of the last layer if raw payload is empty, becomes `data` layer
+/// Note that lengths are calculated from layers, but checksums are exported as they are in `stack`
+pub fn tshark_layers(stack: &PacketStack, timestamp: Duration, number: u64) -> Vec<TsharkLayer> {
+    let lengths = stack.suffix_lengths();
+    let length = stack.wire_len();
+    let payload = match stack.layers.last() {
+        Some(layer) if stack.payload.is_empty() => layer.payload(),
+        _ => &stack.payload
+    };
+    let mut layers = Vec::with_capacity(stack.layers.len() + 2);
+    let mut protocols = Vec::with_capacity(stack.layers.len() + 2);
+    for (layer, length) in stack.layers.iter().zip(lengths) {
+        let tshark_layer = layer_fields(layer, length);
+        protocols.push(tshark_layer.protocol);
+        if tshark_layer.protocol == "eth" {protocols.push("ethertype");}
+        layers.push(tshark_layer);
+    }
+    if !payload.is_empty() {
+        protocols.push("data");
+        layers.push(TsharkLayer {
+            protocol: "data",
+            fields: vec![
+                ("data.data", payload.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(":")),
+                ("data.len", payload.len().to_string())
+            ]
+        });
+    }
+    layers.insert(0, TsharkLayer {
+        protocol: "frame",
+        fields: vec![
+            ("frame.time_epoch", format!("{}.{:09}", timestamp.as_secs(), timestamp.subsec_nanos())),
+            ("frame.number", number.to_string()),
+            ("frame.len", length.to_string()),
+            ("frame.cap_len", length.to_string()),
+            ("frame.protocols", protocols.join(":"))
+        ]
+    });
+    layers
+}
+
+/// Fields of `layer`, which is `length` bytes long with all enclosed layers and payload
+fn layer_fields(layer: &Layer, length: usize) -> TsharkLayer {
+    let bool_field = |value: bool| (value as u8).to_string();
+    let (protocol, fields) = match layer {
+        Layer::Ethernet(frame) => ("eth", vec![
+            ("eth.dst", ethernet::format_mac(frame.destination)),
+            ("eth.src", ethernet::format_mac(frame.source)),
+            ("eth.type", format!("0x{:04x}", frame.protocol))
+        ]),
+        Layer::Ipv4(packet) => {
+            let dscp = packet.dscp.serialize()[0];
+            let ecn = packet.ecn.serialize()[0];
+            ("ip", vec![
+                ("ip.version", "4".to_string()),
+                ("ip.hdr_len", packet.header_len().to_string()),
+                ("ip.dsfield", format!("0x{:02x}", dscp << 2 | ecn)),
+                ("ip.dsfield.dscp", dscp.to_string()),
+                ("ip.dsfield.ecn", ecn.to_string()),
+                ("ip.len", length.to_string()),
+                ("ip.id", format!("0x{:04x}", packet.id)),
+                ("ip.flags.df", bool_field(packet.dont_fragment)),
+                ("ip.flags.mf", bool_field(packet.more_fragments)),
+                ("ip.frag_offset", packet.fragment_offset.to_string()),
+                ("ip.ttl", packet.ttl.to_string()),
+                ("ip.proto", packet.protocol.to_string()),
+                ("ip.checksum", format!("0x{:04x}", packet.checksum)),
+                ("ip.src", packet.source.to_string()),
+                ("ip.dst", packet.destination.to_string())
+            ])
+        }
+        Layer::Ipv6(packet) => {
+            let traffic_class = packet.dscp.serialize()[0] << 2 | packet.ecn.serialize()[0];
+            ("ipv6", vec![
+                ("ipv6.version", "6".to_string()),
+                ("ipv6.tclass", format!("0x{:02x}", traffic_class)),
+                ("ipv6.flow", format!("0x{:05x}", packet.flow_label)),
+                ("ipv6.plen", (length - 40).to_string()),
+                ("ipv6.nxt", packet.next_header.to_string()),
+                ("ipv6.hlim", packet.hop_limit.to_string()),
+                ("ipv6.src", packet.source.to_string()),
+                ("ipv6.dst", packet.destination.to_string())
+            ])
+        }
+        Layer::Tcp(segment) => ("tcp", vec![
+            ("tcp.srcport", segment.source.to_string()),
+            ("tcp.dstport", segment.destination.to_string()),
+            ("tcp.len", (length - segment.header_len()).to_string()),
+            ("tcp.seq_raw", segment.sequence_number.to_string()),
+            ("tcp.ack_raw", segment.acknowledgement_number.to_string()),
+            ("tcp.hdr_len", segment.header_len().to_string()),
+            ("tcp.flags", format!("0x{:04x}", segment.flags.to_u16())),
+            ("tcp.flags.cwr", bool_field(segment.flags.cwr)),
+            ("tcp.flags.ece", bool_field(segment.flags.ece)),
+            ("tcp.flags.urg", bool_field(segment.flags.urg)),
+            ("tcp.flags.ack", bool_field(segment.flags.ack)),
+            ("tcp.flags.push", bool_field(segment.flags.psh)),
+            ("tcp.flags.reset", bool_field(segment.flags.rst)),
+            ("tcp.flags.syn", bool_field(segment.flags.syn)),
+            ("tcp.flags.fin", bool_field(segment.flags.fin)),
+            ("tcp.window_size_value", segment.window_size.to_string()),
+            ("tcp.checksum", format!("0x{:04x}", segment.checksum)),
+            ("tcp.urgent_pointer", segment.urgent_pointer.to_string())
+        ]),
+        Layer::Udp(datagram) => ("udp", vec![
+            ("udp.srcport", datagram.source.to_string()),
+            ("udp.dstport", datagram.destination.to_string()),
+            ("udp.length", length.to_string()),
+            ("udp.checksum", format!("0x{:04x}", datagram.checksum.unwrap_or(0)))
+        ]),
+        Layer::Icmp(packet) => {
+            let mut fields = vec![
+                ("icmp.type", packet.icmp_type.to_string()),
+                ("icmp.code", packet.code.to_string()),
+                ("icmp.checksum", format!("0x{:04x}", packet.checksum))
+            ];
+            if matches!(packet.icmp_type, 0 | 8) {
+                fields.push(("icmp.ident", u16::from_be_bytes([packet.rest_of_header[0], packet.rest_of_header[1]]).to_string()));
+                fields.push(("icmp.seq", u16::from_be_bytes([packet.rest_of_header[2], packet.rest_of_header[3]]).to_string()));
+            }
+            ("icmp", fields)
+        }
+        Layer::Icmpv6(packet) => {
+            let mut fields = vec![
+                ("icmpv6.type", packet.icmp_type.to_string()),
+                ("icmpv6.code", packet.code.to_string()),
+                ("icmpv6.checksum", format!("0x{:04x}", packet.checksum))
+            ];
+            if matches!(packet.icmp_type, 128 | 129) {
+                fields.push(("icmpv6.echo.identifier", format!("0x{:04x}", u16::from_be_bytes([packet.rest_of_header[0], packet.rest_of_header[1]]))));
+                fields.push(("icmpv6.echo.sequence_number", u16::from_be_bytes([packet.rest_of_header[2], packet.rest_of_header[3]]).to_string()));
+            }
+            ("icmpv6", fields)
+        }
+    };
+    TsharkLayer {protocol, fields}
+}
+
+/// Returns Elasticsearch index name `packets-YYYY-MM-DD` for packet captured at `timestamp`, as tshark names it
+fn index_name(timestamp: Duration) -> String {
+    let days = (timestamp.as_secs() / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {month_index + 3} else {month_index - 9};
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("packets-{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Quotes `value` as JSON string
+fn quote(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            character if (character as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", character as u32)),
+            character => result.push(character)
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Writer of dissected packets in tshark JSON formats, so they can be ingested into tools built around Wireshark field names
+/// Note that all field values are written as strings, as `-T json` does
+pub struct TsharkWriter<W: Write> {
+    writer: W,
+    format: TsharkFormat,
+    count: u64
+}
+impl<W: Write> TsharkWriter<W> {
+    pub fn new(writer: W, format: TsharkFormat) -> Self {
+        Self {
+            writer,
+            format,
+            count: 0
+        }
+    }
+    /// Writes packet `stack` captured at `timestamp`, packets are numbered from 1 in order of writing
+    pub fn write_packet(&mut self, timestamp: Duration, stack: &PacketStack) -> io::Result<()> {
+        self.count += 1;
+        let layers = tshark_layers(stack, timestamp, self.count);
+        let index = index_name(timestamp);
+        match self.format {
+            TsharkFormat::Json => {
+                let mut text = String::from(if self.count == 1 {"[\n"} else {",\n"});
+                text.push_str(&format!("  {{\n    \"_index\": {},\n    \"_type\": \"doc\",\n    \"_score\": null,\n    \"_source\": {{\n      \"layers\": {{\n", quote(&index)));
+                for (i, layer) in layers.iter().enumerate() {
+                    text.push_str(&format!("        {}: {{\n", quote(layer.protocol)));
+                    for (j, (name, value)) in layer.fields.iter().enumerate() {
+                        let separator = if j + 1 < layer.fields.len() {","} else {""};
+                        text.push_str(&format!("          {}: {}{}\n", quote(name), quote(value), separator));
+                    }
+                    text.push_str(if i + 1 < layers.len() {"        },\n"} else {"        }\n"});
+                }
+                text.push_str("      }\n    }\n  }");
+                self.writer.write_all(text.as_bytes())
+            }
+            TsharkFormat::Ek => {
+                let mut text = format!("{{\"index\":{{\"_index\":{},\"_type\":\"doc\"}}}}\n", quote(&index));
+                text.push_str(&format!("{{\"timestamp\":\"{}\",\"layers\":{{", timestamp.as_millis()));
+                for (i, layer) in layers.iter().enumerate() {
+                    if i != 0 {text.push(',');}
+                    text.push_str(&format!("{}:{{", quote(layer.protocol)));
+                    for (j, (name, value)) in layer.fields.iter().enumerate() {
+                        if j != 0 {text.push(',');}
+                        text.push_str(&format!("{}:{}", quote(&format!("{}_{}", layer.protocol, name.replace('.', "_"))), quote(value)));
+                    }
+                    text.push('}');
+                }
+                text.push_str("}}\n");
+                self.writer.write_all(text.as_bytes())
+            }
+        }
+    }
+    /// Finishes output, i.e. closes JSON array, and returns underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.format == TsharkFormat::Json {
+            self.writer.write_all(if self.count == 0 {b"[\n]\n"} else {b"\n]\n"})?;
+        }
+        Ok(self.writer)
+    }
+}
\ No newline at end of file
@@ -1,27 +1,798 @@
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::ops::Deref;
+use crate::l3::IpProtocol;
+
 pub trait Serializable {
     fn serialize(self) -> Vec<u8>;
+    /// Converts packet to lowercase hex string without separators
+    fn to_hex_str(self) -> String where Self: Sized {
+        format_hex(&self.serialize())
+    }
 }
 
 pub trait Deserializable: Sized {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError>;
+    /// Constructs packet from hex string, which is parsed with `parse_hex()`, so `tcpdump -xx` output can be pasted as is
+    /// Returns `Err(DeserializeError::WrongData)` if string isn't valid hex
+    fn from_hex_str(hex: &str) -> Result<Self, DeserializeError> {
+        Self::deserialize(&parse_hex(hex).ok_or(DeserializeError::WrongData)?)
+    }
+}
+
+/// Parses hex string into bytes, whitespace, colons, dashes and `0x` prefixes are ignored
+/// Offset labels of at least 4 digits ending with colon, i.e. `0x0010:` in `tcpdump -xx` output, are skipped
+/// Returns `None` if string has other characters or odd number of digits
+pub fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    let mut digits = Vec::with_capacity(hex.len());
+    for token in hex.split_ascii_whitespace() {
+        if token.len() >= 5 && token.find(':') == Some(token.len() - 1) {continue;}
+        let token = token.strip_prefix("0x").or(token.strip_prefix("0X")).unwrap_or(token);
+        for digit in token.bytes().filter(|byte| *byte != b':' && *byte != b'-') {
+            digits.push((digit as char).to_digit(16)? as u8);
+        }
+    }
+    if !digits.len().is_multiple_of(2) {return None;}
+    Some(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+}
+
+/// Converts `bytes` to lowercase hex string without separators
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Packet parsed for read-only inspection, which `payload` borrows parsed bytes instead of copying them
+/// `header` is the packet with empty payload, its fields are accessible directly through `Deref`
+#[derive(Debug, Clone)]
+pub struct PacketRef<'a, P> {
+    pub header: P,
+    pub payload: &'a [u8]
+}
+impl<P: DeserializableRef> PacketRef<'_, P> {
+    /// Copies payload into owned packet, e.g. for modifying it
+    pub fn into_owned(self) -> P {
+        self.header.with_payload(self.payload.to_vec())
+    }
+}
+impl<P> Deref for PacketRef<'_, P> {
+    type Target = P;
+    fn deref(&self) -> &P {
+        &self.header
+    }
+}
+
+/// Trait for packets, which can be deserialized without copying payload
+pub trait DeserializableRef: Sized {
+    /// Parses header from `bytes`, borrowing payload from them
+    fn deserialize_ref(bytes: &[u8]) -> Result<PacketRef<'_, Self>, DeserializeError>;
+    /// Returns packet with `payload`, used by `PacketRef::into_owned()`
+    fn with_payload(self, payload: Vec<u8>) -> Self;
+}
+
+/// Packet which can be carried by IPv4 or IPv6 packet, knows its protocol number, i.e. `IpProtocol::Tcp` for TCP
+pub trait IpPayload {
+    fn ip_protocol(&self) -> IpProtocol;
+}
+
+/// Packet which can be carried by Ethernet frame or GRE packet, knows its EtherType, i.e. 0x0800 for IPv4
+pub trait EtherPayload {
+    fn ether_type(&self) -> u16;
 }
 
 pub enum DeserializeError {
     WrongDataLength,
-    WrongData
+    WrongData,
+    /// Message parsed from stream isn't fully received yet, at least `needed` more bytes are required
+    /// Callers can buffer received data and retry when more arrives
+    Incomplete {
+        needed: usize
+    }
+}
+
+/// Checked cursor over `bytes` for reading big-endian fields
+/// Read past the end returns `Err(DeserializeError::WrongDataLength)` and leaves cursor where it was, so parsers don't have to index bytes manually
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {bytes, position: 0}
+    }
+    /// Returns count of bytes read so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    /// Returns count of bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+    /// Returns bytes left to read without advancing cursor
+    pub fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+    /// Returns the next byte without advancing cursor
+    pub fn peek_u8(&self) -> Result<u8, DeserializeError> {
+        self.bytes.get(self.position).copied().ok_or(DeserializeError::WrongDataLength)
+    }
+    /// Reads the next `length` bytes
+    pub fn read_slice(&mut self, length: usize) -> Result<&'a [u8], DeserializeError> {
+        let end = self.position.checked_add(length).filter(|end| *end <= self.bytes.len()).ok_or(DeserializeError::WrongDataLength)?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+    /// Reads all bytes left
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let rest = self.rest();
+        self.position = self.bytes.len();
+        rest
+    }
+    pub fn skip(&mut self, length: usize) -> Result<(), DeserializeError> {
+        self.read_slice(length).map(|_| ())
+    }
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DeserializeError> {
+        Ok(*self.read_slice(N)?.as_array().unwrap())
+    }
+    pub fn read_u8(&mut self) -> Result<u8, DeserializeError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+    pub fn read_u16(&mut self) -> Result<u16, DeserializeError> {
+        Ok(u16::from_be_bytes(self.read_array()?))
+    }
+    pub fn read_u32(&mut self) -> Result<u32, DeserializeError> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+    pub fn read_u64(&mut self) -> Result<u64, DeserializeError> {
+        Ok(u64::from_be_bytes(self.read_array()?))
+    }
+    pub fn read_ipv4(&mut self) -> Result<Ipv4Addr, DeserializeError> {
+        Ok(Ipv4Addr::from_octets(self.read_array()?))
+    }
+    pub fn read_ipv6(&mut self) -> Result<Ipv6Addr, DeserializeError> {
+        Ok(Ipv6Addr::from_octets(self.read_array()?))
+    }
+    /// Reads slice prefixed with `1 byte` length
+    pub fn read_u8_prefixed(&mut self) -> Result<&'a [u8], DeserializeError> {
+        let start = self.position;
+        let length = self.read_u8()? as usize;
+        self.read_slice(length).inspect_err(|_| self.position = start)
+    }
+    /// Reads slice prefixed with `2 bytes` length
+    pub fn read_u16_prefixed(&mut self) -> Result<&'a [u8], DeserializeError> {
+        let start = self.position;
+        let length = self.read_u16()? as usize;
+        self.read_slice(length).inspect_err(|_| self.position = start)
+    }
+}
+
+/// Cursor appending big-endian fields to a buffer, counterpart of `ByteReader`
+#[derive(Debug, Clone, Default)]
+pub struct ByteWriter {
+    bytes: Vec<u8>
+}
+impl ByteWriter {
+    pub fn new() -> Self {
+        Self {bytes: Vec::new()}
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {bytes: Vec::with_capacity(capacity)}
+    }
+    /// Returns count of bytes written so far
+    pub fn position(&self) -> usize {
+        self.bytes.len()
+    }
+    pub fn write_slice(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+    /// Writes exactly `length` bytes of `bytes`, truncating them or padding with zeros
+    pub fn write_padded(&mut self, bytes: &[u8], length: usize) -> &mut Self {
+        let start = self.bytes.len();
+        self.bytes.extend_from_slice(&bytes[..bytes.len().min(length)]);
+        self.bytes.resize(start + length, 0);
+        self
+    }
+    pub fn write_u8(&mut self, value: u8) -> &mut Self {
+        self.bytes.push(value);
+        self
+    }
+    pub fn write_u16(&mut self, value: u16) -> &mut Self {
+        self.write_slice(&value.to_be_bytes())
+    }
+    pub fn write_u32(&mut self, value: u32) -> &mut Self {
+        self.write_slice(&value.to_be_bytes())
+    }
+    pub fn write_u64(&mut self, value: u64) -> &mut Self {
+        self.write_slice(&value.to_be_bytes())
+    }
+    /// Writes `bytes` prefixed with `1 byte` length
+    /// Returns `false` and writes nothing if `bytes` are longer than `255 bytes`
+    pub fn write_u8_prefixed(&mut self, bytes: &[u8]) -> bool {
+        let Ok(length) = u8::try_from(bytes.len()) else {return false;};
+        self.write_u8(length).write_slice(bytes);
+        true
+    }
+    /// Writes `bytes` prefixed with `2 bytes` length
+    /// Returns `false` and writes nothing if `bytes` are longer than `65535 bytes`
+    pub fn write_u16_prefixed(&mut self, bytes: &[u8]) -> bool {
+        let Ok(length) = u16::try_from(bytes.len()) else {return false;};
+        self.write_u16(length).write_slice(bytes);
+        true
+    }
+    /// Overwrites `2 bytes` at `offset` with `value`, i.e. length field known only after writing the rest
+    /// Returns `false` if `offset` is beyond written bytes
+    pub fn set_u16(&mut self, offset: usize, value: u16) -> bool {
+        match self.bytes.get_mut(offset..offset.saturating_add(2)) {
+            Some(field) => {
+                field.copy_from_slice(&value.to_be_bytes());
+                true
+            }
+            None => false
+        }
+    }
+    /// Returns written bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Checks that stream `bytes` hold at least `length` bytes of a message
+/// Returns `Err(DeserializeError::Incomplete)` with count of missing bytes otherwise
+#[cfg(any(feature = "bgp", feature = "dns", feature = "dnp3", feature = "modbus", feature = "mqtt"))]
+pub(crate) fn require_stream_length(bytes: &[u8], length: usize) -> Result<(), DeserializeError> {
+    if bytes.len() < length {return Err(DeserializeError::Incomplete {needed: length - bytes.len()});}
+    Ok(())
+}
+
+/// Error for when options don't fit into the `40 bytes` option space of TCP or IPv4 header
+#[derive(Debug, Clone, Copy)]
+pub struct OptionSpaceExceeded;
+
+/// Context of checksum calculation
+/// Checksums with pseudo-header need addresses of enclosing IP packet, others ignore context
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChecksumContext {
+    /// Source and destination addresses of enclosing IP packet
+    pub addresses: Option<(IpAddr, IpAddr)>
+}
+impl ChecksumContext {
+    /// Constructs context without addresses, which is enough for IPv4 header and ICMP checksums
+    pub fn new() -> Self {
+        Self {addresses: None}
+    }
+    /// Constructs context with `source` and `destination` addresses of enclosing IP packet
+    pub fn with_addresses(source: IpAddr, destination: IpAddr) -> Self {
+        Self {addresses: Some((source, destination))}
+    }
+}
+
+/// Layer with checksum field, so generic code can fix up or check checksum of any layer
+pub trait Checksummed {
+    /// Computes checksum of layer in `context` without changing layer
+    /// Returns `None` if `context` lacks addresses of right version for pseudo-header
+    fn compute_checksum(&self, context: &ChecksumContext) -> Option<u16>;
+    /// Returns checksum currently stored in layer, or `None` if layer carries no checksum
+    fn stored_checksum(&self) -> Option<u16>;
+    /// Stores `checksum` in layer
+    fn set_checksum(&mut self, checksum: u16);
+    /// Sets checksum field to zero, or to absent if protocol allows it
+    fn clear_checksum(&mut self);
+    /// Checks that stored checksum matches computed one, absent checksum is valid
+    /// Returns `false` if checksum can't be computed in `context`
+    fn verify_checksum(&self, context: &ChecksumContext) -> bool {
+        match self.stored_checksum() {
+            Some(stored) => self.compute_checksum(context) == Some(stored),
+            None => true
+        }
+    }
+    /// Computes checksum and stores it in layer
+    /// Returns `false` and leaves layer untouched if checksum can't be computed in `context`
+    fn fill_checksum(&mut self, context: &ChecksumContext) -> bool {
+        match self.compute_checksum(context) {
+            Some(checksum) => {
+                self.set_checksum(checksum);
+                true
+            }
+            None => false
+        }
+    }
 }
 
 /// **Sums up** all `16 bits` or `2 bytes` words(with adding `zero-byte` in end if `bytes.len() % 2 == 1`), **one's completing**, **inverting** and **returning** this sum
-pub fn checksum(mut bytes: Vec<u8>) -> u16 {
-    let mut sum = 0u32;
-    if bytes.len() % 2 == 1 {
-        bytes.push(0);
+pub fn checksum(bytes: &[u8]) -> u16 {
+    ChecksumAccumulator::new().update(bytes).finish()
+}
+
+/// Accumulates checksum over several parts as if they were concatenated, so header, pseudo-header and payload don't have to be copied into one buffer
+/// Words are summed `8 bytes` at a time with end-around carry and folded to `16 bits` in `finish()`, which gives the same result, as described in RFC 1071
+#[derive(Debug, Clone, Copy)]
+pub struct ChecksumAccumulator {
+    sum: u64,
+    /// Previous parts had odd length in total, so the next byte is the low byte of a word
+    odd: bool
+}
+impl ChecksumAccumulator {
+    pub fn new() -> Self {
+        Self {
+            sum: 0,
+            odd: false
+        }
     }
-    for word in bytes.chunks(2) {
-        sum += u16::from_be_bytes([word[0], word[1]]) as u32
+    /// Adds `bytes` right after all previously added parts
+    pub fn update(mut self, mut bytes: &[u8]) -> Self {
+        if self.odd && let Some((first, rest)) = bytes.split_first() {
+            self.add_word(*first as u64);
+            self.odd = false;
+            bytes = rest;
+        }
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add_word(u64::from_be_bytes(*chunk.as_array().unwrap()));
+        }
+        let remainder = chunks.remainder();
+        if !remainder.is_empty() {
+            let mut last = [0u8; 8];
+            last[..remainder.len()].copy_from_slice(remainder);
+            self.add_word(u64::from_be_bytes(last));
+            self.odd = remainder.len() % 2 == 1;
+        }
+        self
+    }
+    fn add_word(&mut self, word: u64) {
+        let (sum, carry) = self.sum.overflowing_add(word);
+        self.sum = sum + carry as u64;
+    }
+    /// Folds sum to `16 bits`, **one's completing** and **inverting** it
+    pub fn finish(self) -> u16 {
+        let mut sum = self.sum;
+        while sum > 0xFFFF {
+            sum = (sum >> 16) + (sum & 0xFFFF);
+        }
+        !sum as u16
+    }
+}
+impl Default for ChecksumAccumulator {
+    fn default() -> Self {
+        Self::new()
     }
+}
+/// Lets serializers writing into `io::Write` feed checksum directly
+impl std::io::Write for ChecksumAccumulator {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        *self = self.update(bytes);
+        Ok(bytes.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// **Incrementally updates** `checksum` after replacing one `16 bits` word `old` with `new` in checksummed data, as described in RFC 1624
+/// Note that replaced word has to be aligned to `2 bytes` boundary of checksummed data
+pub fn update_checksum(checksum: u16, old: u16, new: u16) -> u16 {
+    let mut sum = (!checksum) as u32 + (!old) as u32 + new as u32;
     while sum > 0xFFFF {
         sum = (sum >> 16) + (sum & 0xFFFF);
     }
     !sum as u16
-}
\ No newline at end of file
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {(crc >> 1) ^ 0xEDB88320} else {crc >> 1};
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Calculates **CRC-32**(IEEE 802.3) of `bytes`, which is used by Ethernet Frame Check Sequence
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/// SplitMix64 pseudo-random generator, which makes seeded generators and mutators reproducible
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(pub(crate) u64);
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D049BB133111EB);
+        value ^ (value >> 31)
+    }
+    /// Returns random number from `low..=high`, empty range gives `low`
+    pub(crate) fn range(&mut self, low: u128, high: u128) -> u128 {
+        if high <= low {return low;}
+        let value = (self.next_u64() as u128) << 64 | self.next_u64() as u128;
+        match (high - low).checked_add(1) {
+            Some(span) => low + value % span,
+            None => value
+        }
+    }
+}
+
+/// Problem found in raw packet bytes by `validate()` of packet types
+/// `layer` is short protocol name, i.e. "ipv4", "tcp"
+/// Link, network and transport layers are validated: Ethernet, PPPoE session, EAPOL, ARP, IPv4, IPv6, ICMP, ICMPv6, IGMP, GRE, OSPF, TCP and UDP, application protocols are not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// Data is too short to contain header or declared length
+    Truncated {
+        layer: &'static str,
+        needed: usize,
+        actual: usize
+    },
+    /// Length field doesn't match actual length of data
+    LengthMismatch {
+        layer: &'static str,
+        field: &'static str,
+        expected: usize,
+        actual: usize
+    },
+    /// Checksum field doesn't match calculated checksum
+    ChecksumMismatch {
+        layer: &'static str,
+        declared: u16,
+        calculated: u16
+    },
+    /// Flags combination that must not appear, i.e. "SYN+FIN"
+    IllegalFlags {
+        layer: &'static str,
+        flags: &'static str
+    },
+    /// Option at `offset` from header start has wrong length or runs past header end
+    MalformedOption {
+        layer: &'static str,
+        offset: usize
+    },
+    /// Nonzero byte at `offset` from header start after End of Option List
+    BadOptionPadding {
+        layer: &'static str,
+        offset: usize
+    },
+    /// Field has reserved or illegal value
+    InvalidField {
+        layer: &'static str,
+        field: &'static str,
+        value: u32
+    }
+}
+
+/// Layout of Type-Length-Value records, i.e. TCP, IPv4 or IPv6 options, DHCP options or LLDP TLVs
+/// Type and length fields are packed big-endian into `(type_bits + length_bits) / 8` header bytes
+#[derive(Debug, Clone, Copy)]
+pub struct TlvFormat {
+    pub type_bits: u8,
+    pub length_bits: u8,
+    /// Length field counts header too, as in TCP and IPv4 options, otherwise only value is counted
+    pub length_includes_header: bool,
+    /// Type of one-byte padding record without length and value, i.e. No-Operation of TCP
+    pub padding: Option<u8>,
+    /// Type of one-byte End of List record, which ends records
+    pub end: Option<u8>
+}
+impl TlvFormat {
+    /// TCP options, which are the same as IPv4 options
+    pub const TCP_OPTIONS: Self = Self {type_bits: 8, length_bits: 8, length_includes_header: true, padding: Some(1), end: Some(0)};
+    /// IPv4 options, which are the same as TCP options
+    pub const IPV4_OPTIONS: Self = Self::TCP_OPTIONS;
+    /// Options of IPv6 Hop-by-Hop and Destination Options headers, where Pad1 is padding and PadN is ordinary record of type 1
+    pub const IPV6_OPTIONS: Self = Self {type_bits: 8, length_bits: 8, length_includes_header: false, padding: Some(0), end: None};
+    /// DHCP options with Pad(0) and End(255)
+    pub const DHCP_OPTIONS: Self = Self {type_bits: 8, length_bits: 8, length_includes_header: false, padding: Some(0), end: Some(255)};
+    /// LLDP TLVs with `7 bits` type and `9 bits` length, where End of LLDPDU is an ordinary record of type 0
+    pub const LLDP: Self = Self {type_bits: 7, length_bits: 9, length_includes_header: false, padding: None, end: None};
+    /// Returns length of type and length fields
+    pub fn header_len(&self) -> usize {
+        (self.type_bits as usize + self.length_bits as usize) / 8
+    }
+    /// Returns maximal length of value, which length field can hold
+    pub fn max_value_len(&self) -> usize {
+        let max = (1usize << self.length_bits) - 1;
+        if self.length_includes_header {max.saturating_sub(self.header_len())} else {max}
+    }
+}
+
+/// One borrowed Type-Length-Value record
+#[derive(Debug, Clone, Copy)]
+pub struct Tlv<'a> {
+    pub kind: u32,
+    pub value: &'a [u8]
+}
+
+/// Iterator over Type-Length-Value records of `TlvFormat` without allocation
+/// Padding records are skipped and End of List record stops iteration
+/// Record running past end of bytes is yielded as `Err(DeserializeError::WrongDataLength)` and ends iteration
+#[derive(Debug, Clone)]
+pub struct TlvReader<'a> {
+    format: TlvFormat,
+    bytes: &'a [u8],
+    position: usize,
+    done: bool
+}
+impl<'a> TlvReader<'a> {
+    pub fn new(format: TlvFormat, bytes: &'a [u8]) -> Self {
+        Self {format, bytes, position: 0, done: false}
+    }
+    /// Returns offset of the next record, or of malformed record after error
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    /// Returns bytes after End of List record, which should be padding, or empty slice if there was no End of List record
+    pub fn remainder(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+}
+impl<'a> Iterator for TlvReader<'a> {
+    type Item = Result<Tlv<'a>, DeserializeError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {return None;}
+        while self.position < self.bytes.len() {
+            let first = self.bytes[self.position];
+            if self.format.end == Some(first) {
+                self.done = true;
+                self.position += 1;
+                return None;
+            }
+            if self.format.padding != Some(first) {break;}
+            self.position += 1;
+        }
+        if self.position >= self.bytes.len() {
+            self.done = true;
+            return None;
+        }
+        let header_len = self.format.header_len();
+        let i = self.position;
+        let Some(header) = self.bytes.get(i..i + header_len) else {
+            self.done = true;
+            return Some(Err(DeserializeError::WrongDataLength));
+        };
+        let header = header.iter().fold(0u32, |header, byte| (header << 8) | *byte as u32);
+        let kind = header >> self.format.length_bits;
+        let length = (header & ((1u32 << self.format.length_bits) - 1)) as usize;
+        let end = match self.format.length_includes_header {
+            true if length < header_len => None,
+            true => Some(i + length),
+            false => Some(i + header_len + length)
+        };
+        match end {
+            Some(end) if end <= self.bytes.len() => {
+                self.position = end;
+                Some(Ok(Tlv {kind, value: &self.bytes[i + header_len..end]}))
+            }
+            _ => {
+                self.done = true;
+                Some(Err(DeserializeError::WrongDataLength))
+            }
+        }
+    }
+}
+impl core::iter::FusedIterator for TlvReader<'_> {}
+
+/// Writer of Type-Length-Value records of `TlvFormat`
+#[derive(Debug, Clone)]
+pub struct TlvWriter {
+    format: TlvFormat,
+    bytes: Vec<u8>
+}
+impl TlvWriter {
+    pub fn new(format: TlvFormat) -> Self {
+        Self {format, bytes: Vec::new()}
+    }
+    /// Appends record of `kind` with `value`
+    /// Returns `false` and writes nothing if `kind` or `value` length don't fit their fields
+    pub fn write(&mut self, kind: u32, value: &[u8]) -> bool {
+        if value.len() > self.format.max_value_len() || (kind as u64) >> self.format.type_bits != 0 {return false;}
+        let header_len = self.format.header_len();
+        let length = if self.format.length_includes_header {header_len + value.len()} else {value.len()};
+        let header = ((kind as u64) << self.format.length_bits) | length as u64;
+        self.bytes.extend_from_slice(&header.to_be_bytes()[8 - header_len..]);
+        self.bytes.extend_from_slice(value);
+        true
+    }
+    /// Appends `count` padding records
+    /// Does nothing if format has no padding records
+    pub fn pad(&mut self, count: usize) {
+        if let Some(padding) = self.format.padding {
+            self.bytes.resize(self.bytes.len() + count, padding);
+        }
+    }
+    /// Appends End of List record
+    /// Does nothing if format has no End of List record
+    pub fn end(&mut self) {
+        if let Some(end) = self.format.end {
+            self.bytes.push(end);
+        }
+    }
+    /// Returns length of written records
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+    /// Returns written records
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Walks TCP or IPv4 option area, which starts at `offset` from header start, and reports malformed options and padding
+pub(crate) fn validate_options(layer: &'static str, options: &[u8], offset: usize, issues: &mut Vec<ValidationIssue>) {
+    let mut reader = TlvReader::new(TlvFormat::TCP_OPTIONS, options);
+    while let Some(option) = reader.next() {
+        if option.is_err() {
+            issues.push(ValidationIssue::MalformedOption {layer, offset: offset + reader.position()});
+            return;
+        }
+    }
+    if let Some(position) = reader.remainder().iter().position(|byte| *byte != 0) {
+        issues.push(ValidationIssue::BadOptionPadding {layer, offset: offset + reader.position() + position});
+    }
+}
+
+/// IPv4 or IPv6 pseudo-header, which checksums of TCP, UDP, UDP-Lite and ICMPv6 cover together with the segment
+/// It is never serialized into a buffer, but fed straight into `ChecksumAccumulator`
+#[derive(Debug, Clone, Copy)]
+pub struct PseudoHeader {
+    pub source: IpAddr,
+    pub destination: IpAddr,
+    /// Upper-layer protocol number, i.e. 6 for TCP, 17 for UDP, 58 for ICMPv6
+    pub protocol: u8,
+    /// Length of upper-layer segment, which is truncated to `16 bits` for IPv4
+    pub length: u32
+}
+impl PseudoHeader {
+    /// Constructs pseudo-header of segment with `length bytes` of `protocol`
+    /// Returns `None` when `source` and `destination` are not same version
+    pub fn new(source: IpAddr, destination: IpAddr, protocol: u8, length: u32) -> Option<Self> {
+        if source.is_ipv4() != destination.is_ipv4() {return None;}
+        Some(Self {source, destination, protocol, length})
+    }
+    /// Returns length of pseudo-header, `12 bytes` for IPv4 and `40 bytes` for IPv6
+    pub fn wire_len(&self) -> usize {
+        if self.source.is_ipv4() {12} else {40}
+    }
+    /// Adds pseudo-header to `accumulator` as if it was serialized
+    pub fn accumulate(&self, accumulator: ChecksumAccumulator) -> ChecksumAccumulator {
+        match (self.source, self.destination) {
+            (IpAddr::V4(source), IpAddr::V4(destination)) => accumulator
+                .update(&source.octets())
+                .update(&destination.octets())
+                .update(&[0, self.protocol])
+                .update(&(self.length as u16).to_be_bytes()),
+            (IpAddr::V6(source), IpAddr::V6(destination)) => accumulator
+                .update(&source.octets())
+                .update(&destination.octets())
+                .update(&self.length.to_be_bytes())
+                .update(&[0, 0, 0, self.protocol]),
+            _ => accumulator
+        }
+    }
+    /// Calculates checksum of pseudo-header followed by segment given as consecutive `parts`
+    /// Note that `length` isn't checked against length of `parts`
+    pub fn checksum<'a>(&self, parts: impl IntoIterator<Item = &'a [u8]>) -> u16 {
+        parts.into_iter().fold(self.accumulate(ChecksumAccumulator::new()), ChecksumAccumulator::update).finish()
+    }
+}
+
+/// Calculates checksum of TCP, UDP or ICMPv6 segment with IPv4 or IPv6 pseudo-header
+/// Segment is given as consecutive `parts`, e.g. header and payload, so they don't have to be copied into one buffer
+/// Returns `None` when `source` and `destination` are not same version
+pub(crate) fn transport_checksum(source: IpAddr, destination: IpAddr, protocol: u8, parts: &[&[u8]]) -> Option<u16> {
+    let length: usize = parts.iter().map(|part| part.len()).sum();
+    Some(PseudoHeader::new(source, destination, protocol, length as u32)?.checksum(parts.iter().copied()))
+}
+
+/// Implements `TryFrom<&[u8]>` delegating to `Deserializable::deserialize()` for each of listed types
+/// Blanket implementation for all `Deserializable` types would conflict with `TryFrom` implementation of `core`
+macro_rules! impl_try_from_bytes {
+    ($($(#[$meta:meta])* $packet:ty),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            impl TryFrom<&[u8]> for $packet {
+                type Error = DeserializeError;
+                fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                    <$packet as Deserializable>::deserialize(bytes)
+                }
+            }
+        )*
+    };
+}
+impl_try_from_bytes!(
+    crate::l2::ethernet::EthernetFrame,
+    #[cfg(feature = "ieee80211")]
+    crate::l2::ieee80211::Ieee80211Frame,
+    #[cfg(feature = "ppp")]
+    crate::l2::ppp::PppFrame,
+    #[cfg(feature = "ppp")]
+    crate::l2::ppp::PppControlPacket,
+    #[cfg(feature = "ppp")]
+    crate::l2::ppp::PapPacket,
+    #[cfg(feature = "ppp")]
+    crate::l2::ppp::ChapPacket,
+    #[cfg(feature = "ppp")]
+    crate::l2::ppp::PppoeSession,
+    #[cfg(feature = "radiotap")]
+    crate::l2::radiotap::RadiotapHeader,
+    crate::l2::sll::LinuxSllFrame,
+    crate::l2::sll::LinuxSll2Frame,
+    #[cfg(feature = "macsec")]
+    crate::l2::macsec::MacsecFrame,
+    #[cfg(feature = "eapol")]
+    crate::l2::eapol::EapolFrame,
+    #[cfg(feature = "eap")]
+    crate::l2::eap::EapPacket,
+    crate::l3::IpPacket,
+    crate::l3::ipv4::Ipv4Packet,
+    crate::l3::ipv6::Ipv6Packet,
+    #[cfg(feature = "arp")]
+    crate::l3::arp::ArpPacket,
+    #[cfg(feature = "advanced-arp")]
+    crate::l3::arp::ArpMessage,
+    crate::l3::icmp::IcmpPacket,
+    crate::l3::icmpv6::Icmpv6Packet,
+    #[cfg(feature = "igmp")]
+    crate::l3::igmp::IgmpPacket,
+    #[cfg(feature = "gre")]
+    crate::l3::gre::GrePacket,
+    crate::l4::tcp::TcpSegment,
+    crate::l4::udp::UdpDatagram,
+    #[cfg(feature = "ospf")]
+    crate::l4::ospf::OspfPacket,
+    #[cfg(feature = "bgp")]
+    crate::l7::bgp::BgpMessage,
+    #[cfg(feature = "rip")]
+    crate::l7::rip::RipMessage,
+    #[cfg(feature = "gtp")]
+    crate::l7::gtp::GtpuPacket,
+    #[cfg(feature = "l2tp")]
+    crate::l7::l2tp::L2tpPacket,
+    #[cfg(feature = "dns")]
+    crate::l7::dns::DnsMessage,
+    #[cfg(feature = "llmnr")]
+    crate::l7::llmnr::LlmnrMessage,
+    #[cfg(feature = "dhcp")]
+    crate::l7::dhcp::DhcpMessage,
+    #[cfg(feature = "quic")]
+    crate::l7::quic::QuicPacket,
+    #[cfg(feature = "dtls")]
+    crate::l7::dtls::DtlsRecord,
+    #[cfg(feature = "netflow")]
+    crate::l7::netflow::NetflowPacket,
+    #[cfg(feature = "netflow")]
+    crate::l7::netflow::NetflowV5Packet,
+    #[cfg(feature = "netflow")]
+    crate::l7::netflow::NetflowV9Packet,
+    #[cfg(feature = "ipfix")]
+    crate::l7::ipfix::IpfixMessage,
+    #[cfg(feature = "ptp")]
+    crate::l7::ptp::PtpMessage,
+    #[cfg(feature = "modbus")]
+    crate::l7::modbus::ModbusRequest,
+    #[cfg(feature = "modbus")]
+    crate::l7::modbus::ModbusResponse,
+    #[cfg(feature = "mqtt")]
+    crate::l7::mqtt::MqttPacket,
+    #[cfg(feature = "coap")]
+    crate::l7::coap::CoapMessage,
+    #[cfg(feature = "dnp3")]
+    crate::l7::dnp3::Dnp3Frame
+);
\ No newline at end of file
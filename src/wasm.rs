@@ -0,0 +1,242 @@
+//! JavaScript bindings, build with `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`
+//! and generate JS glue with `wasm-bindgen --target web target/wasm32-unknown-unknown/release/packedit.wasm --out-dir pkg`
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use crate::l2::ethernet::{self, EthernetFrame};
+use crate::l3::icmp::IcmpPacket;
+use crate::l3::icmpv6::Icmpv6Packet;
+use crate::l3::ipv4::Ipv4Packet;
+use crate::l3::ipv6::Ipv6Packet;
+use crate::l4::tcp::{TcpFlags, TcpSegment};
+use crate::l4::udp::UdpDatagram;
+use crate::stack::{Layer, PacketStack};
+use crate::util::{DeserializeError, Serializable};
+
+fn deserialize_error(error: DeserializeError) -> JsError {
+    JsError::new(&match error {
+        DeserializeError::WrongDataLength => "wrong data length".to_string(),
+        DeserializeError::WrongData => "wrong data".to_string(),
+        DeserializeError::Incomplete {needed} => format!("incomplete data, {} more bytes needed", needed)
+    })
+}
+
+fn set(object: &Object, key: &str, value: impl Into<JsValue>) {
+    let _ = Reflect::set(object, &JsValue::from_str(key), &value.into());
+}
+
+fn get(object: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(object, &JsValue::from_str(key)).ok().filter(|value| !value.is_undefined() && !value.is_null())
+}
+
+fn get_number<N: TryFrom<u64>>(object: &JsValue, key: &str) -> Result<Option<N>, JsError> {
+    match get(object, key) {
+        Some(value) => value.as_f64()
+            .filter(|number| number.fract() == 0.0 && *number >= 0.0)
+            .and_then(|number| N::try_from(number as u64).ok())
+            .map(Some)
+            .ok_or_else(|| JsError::new(&format!("field {} is not a valid number", key))),
+        None => Ok(None)
+    }
+}
+
+fn get_string(object: &JsValue, key: &str) -> Result<Option<String>, JsError> {
+    match get(object, key) {
+        Some(value) => value.as_string().map(Some).ok_or_else(|| JsError::new(&format!("field {} is not a string", key))),
+        None => Ok(None)
+    }
+}
+
+fn get_bool(object: &JsValue, key: &str) -> Option<bool> {
+    get(object, key).and_then(|value| value.as_bool())
+}
+
+/// Accepts `Uint8Array` or array of numbers
+fn get_bytes(object: &JsValue, key: &str) -> Option<Vec<u8>> {
+    get(object, key).map(|value| Uint8Array::new(&value).to_vec())
+}
+
+fn get_mac(object: &JsValue, key: &str) -> Result<Option<[u8; 6]>, JsError> {
+    match get_string(object, key)? {
+        Some(mac) => ethernet::parse_mac(&mac).map(Some).ok_or_else(|| JsError::new(&format!("invalid MAC address {}", mac))),
+        None => Ok(None)
+    }
+}
+
+fn get_address<A: core::str::FromStr>(object: &JsValue, key: &str) -> Result<Option<A>, JsError> {
+    match get_string(object, key)? {
+        Some(address) => address.parse().map(Some).map_err(|_| JsError::new(&format!("invalid IP address {}", address))),
+        None => Ok(None)
+    }
+}
+
+/// Converts `layer` to JS object with `layer` field naming its type and camelCase fields
+fn layer_object(layer: &Layer) -> Object {
+    let object = Object::new();
+    match layer {
+        Layer::Ethernet(frame) => {
+            set(&object, "layer", "ethernet");
+            set(&object, "source", ethernet::format_mac(frame.source));
+            set(&object, "destination", ethernet::format_mac(frame.destination));
+            set(&object, "protocol", frame.protocol);
+        }
+        Layer::Ipv4(packet) => {
+            set(&object, "layer", "ipv4");
+            set(&object, "source", packet.source.to_string());
+            set(&object, "destination", packet.destination.to_string());
+            set(&object, "protocol", u8::from(packet.protocol));
+            set(&object, "ttl", packet.ttl);
+            set(&object, "id", packet.id);
+            set(&object, "dontFragment", packet.dont_fragment);
+            set(&object, "moreFragments", packet.more_fragments);
+            set(&object, "fragmentOffset", packet.fragment_offset);
+            set(&object, "checksum", packet.checksum);
+        }
+        Layer::Ipv6(packet) => {
+            set(&object, "layer", "ipv6");
+            set(&object, "source", packet.source.to_string());
+            set(&object, "destination", packet.destination.to_string());
+            set(&object, "nextHeader", u8::from(packet.next_header));
+            set(&object, "hopLimit", packet.hop_limit);
+            set(&object, "flowLabel", packet.flow_label);
+        }
+        Layer::Tcp(segment) => {
+            set(&object, "layer", "tcp");
+            set(&object, "source", segment.source);
+            set(&object, "destination", segment.destination);
+            set(&object, "sequenceNumber", segment.sequence_number);
+            set(&object, "acknowledgementNumber", segment.acknowledgement_number);
+            set(&object, "flags", segment.flags.letters());
+            set(&object, "windowSize", segment.window_size);
+            set(&object, "checksum", segment.checksum);
+            set(&object, "urgentPointer", segment.urgent_pointer);
+        }
+        Layer::Udp(datagram) => {
+            set(&object, "layer", "udp");
+            set(&object, "source", datagram.source);
+            set(&object, "destination", datagram.destination);
+            set(&object, "checksum", datagram.checksum.map(JsValue::from).unwrap_or(JsValue::NULL));
+        }
+        Layer::Icmp(packet) => {
+            set(&object, "layer", "icmp");
+            set(&object, "type", packet.icmp_type);
+            set(&object, "code", packet.code);
+            set(&object, "checksum", packet.checksum);
+            set(&object, "restOfHeader", Uint8Array::from(&packet.rest_of_header[..]));
+        }
+        Layer::Icmpv6(packet) => {
+            set(&object, "layer", "icmpv6");
+            set(&object, "type", packet.icmp_type);
+            set(&object, "code", packet.code);
+            set(&object, "checksum", packet.checksum);
+            set(&object, "restOfHeader", Uint8Array::from(&packet.rest_of_header[..]));
+        }
+    }
+    object
+}
+
+/// Converts JS object of the same shape as `layer_object()` returns to `Layer`
+/// Missing fields keep defaults of `new()`, checksums and protocol fields are ignored since `build()` calculates them
+fn layer_from_object(object: &JsValue) -> Result<Layer, JsError> {
+    let layer = get_string(object, "layer")?.ok_or_else(|| JsError::new("layer type is missing"))?;
+    Ok(match layer.as_str() {
+        "ethernet" => {
+            let mut frame = EthernetFrame::new();
+            if let Some(source) = get_mac(object, "source")? {frame.source = source;}
+            if let Some(destination) = get_mac(object, "destination")? {frame.destination = destination;}
+            if let Some(protocol) = get_number(object, "protocol")? {frame.protocol = protocol;}
+            Layer::Ethernet(frame)
+        }
+        "ipv4" => {
+            let mut packet = Ipv4Packet::default();
+            if let Some(source) = get_address(object, "source")? {packet.source = source;}
+            if let Some(destination) = get_address(object, "destination")? {packet.destination = destination;}
+            if let Some(protocol) = get_number::<u8>(object, "protocol")? {packet.protocol = protocol.into();}
+            if let Some(ttl) = get_number(object, "ttl")? {packet.ttl = ttl;}
+            if let Some(id) = get_number(object, "id")? {packet.id = id;}
+            if let Some(dont_fragment) = get_bool(object, "dontFragment") {packet.dont_fragment = dont_fragment;}
+            if let Some(more_fragments) = get_bool(object, "moreFragments") {packet.more_fragments = more_fragments;}
+            if let Some(fragment_offset) = get_number(object, "fragmentOffset")? {packet.fragment_offset = fragment_offset;}
+            Layer::Ipv4(packet)
+        }
+        "ipv6" => {
+            let mut packet = Ipv6Packet::default();
+            if let Some(source) = get_address(object, "source")? {packet.source = source;}
+            if let Some(destination) = get_address(object, "destination")? {packet.destination = destination;}
+            if let Some(next_header) = get_number::<u8>(object, "nextHeader")? {packet.next_header = next_header.into();}
+            if let Some(hop_limit) = get_number(object, "hopLimit")? {packet.hop_limit = hop_limit;}
+            if let Some(flow_label) = get_number(object, "flowLabel")? {packet.flow_label = flow_label;}
+            Layer::Ipv6(packet)
+        }
+        "tcp" => {
+            let mut segment = TcpSegment::new();
+            if let Some(source) = get_number(object, "source")? {segment.source = source;}
+            if let Some(destination) = get_number(object, "destination")? {segment.destination = destination;}
+            if let Some(sequence_number) = get_number(object, "sequenceNumber")? {segment.sequence_number = sequence_number;}
+            if let Some(acknowledgement_number) = get_number(object, "acknowledgementNumber")? {segment.acknowledgement_number = acknowledgement_number;}
+            if let Some(flags) = get_string(object, "flags")? {
+                segment.flags = TcpFlags::from_letters(&flags).ok_or_else(|| JsError::new(&format!("invalid TCP flags {}", flags)))?;
+            }
+            if let Some(window_size) = get_number(object, "windowSize")? {segment.window_size = window_size;}
+            if let Some(urgent_pointer) = get_number(object, "urgentPointer")? {segment.urgent_pointer = urgent_pointer;}
+            Layer::Tcp(segment)
+        }
+        "udp" => {
+            let mut datagram = UdpDatagram::new();
+            if let Some(source) = get_number(object, "source")? {datagram.source = source;}
+            if let Some(destination) = get_number(object, "destination")? {datagram.destination = destination;}
+            Layer::Udp(datagram)
+        }
+        "icmp" => {
+            let mut packet = IcmpPacket::new();
+            if let Some(icmp_type) = get_number(object, "type")? {packet.icmp_type = icmp_type;}
+            if let Some(code) = get_number(object, "code")? {packet.code = code;}
+            if let Some(rest_of_header) = get_bytes(object, "restOfHeader") {
+                packet.rest_of_header = rest_of_header.try_into().map_err(|_| JsError::new("restOfHeader must be 4 bytes long"))?;
+            }
+            Layer::Icmp(packet)
+        }
+        "icmpv6" => {
+            let mut packet = Icmpv6Packet::new();
+            if let Some(icmp_type) = get_number(object, "type")? {packet.icmp_type = icmp_type;}
+            if let Some(code) = get_number(object, "code")? {packet.code = code;}
+            if let Some(rest_of_header) = get_bytes(object, "restOfHeader") {
+                packet.rest_of_header = rest_of_header.try_into().map_err(|_| JsError::new("restOfHeader must be 4 bytes long"))?;
+            }
+            Layer::Icmpv6(packet)
+        }
+        _ => return Err(JsError::new(&format!("unknown layer {}", layer)))
+    })
+}
+
+fn stack_object(stack: &PacketStack) -> Object {
+    let object = Object::new();
+    set(&object, "layers", stack.layers.iter().map(layer_object).collect::<Array>());
+    set(&object, "payload", Uint8Array::from(&stack.payload[..]));
+    object
+}
+
+/// Dissects Ethernet frame into `{layers: [...], payload: Uint8Array}` object
+/// Each layer is an object with `layer` field, i.e. `"ethernet"`, `"ipv4"`, `"ipv6"`, `"tcp"`, `"udp"`, `"icmp"` or `"icmpv6"`, and header fields in camelCase
+#[wasm_bindgen]
+pub fn dissect(data: &[u8]) -> Result<Object, JsError> {
+    PacketStack::from_ethernet(data).map(|stack| stack_object(&stack)).map_err(deserialize_error)
+}
+
+/// The same as `dissect()`, but for IP packet without Ethernet header
+#[wasm_bindgen(js_name = dissectIp)]
+pub fn dissect_ip(data: &[u8]) -> Result<Object, JsError> {
+    PacketStack::from_ip(data).map(|stack| stack_object(&stack)).map_err(deserialize_error)
+}
+
+/// Builds packet from object of the same shape as `dissect()` returns, fields missing in layers get default values
+/// Protocol fields and checksums are calculated
+#[wasm_bindgen]
+pub fn build(packet: &JsValue) -> Result<Vec<u8>, JsError> {
+    let layers = get(packet, "layers").ok_or_else(|| JsError::new("layers are missing"))?;
+    let mut stack = PacketStack::new();
+    for layer in Array::from(&layers).iter() {
+        stack.push(layer_from_object(&layer)?);
+    }
+    stack.payload = get_bytes(packet, "payload").unwrap_or_default();
+    Ok(stack.serialize())
+}
\ No newline at end of file